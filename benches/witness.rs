@@ -0,0 +1,87 @@
+//! Benchmarks of the CPU-bound steps of witness generation, run on the
+//! recorded demo block: `cargo bench --features bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use noir_mip::chain_spec::ChainSpec;
+use noir_mip::{demo, header, trie, ACCOUNT_PROOF_MAX_DEPTH, PROOF_BYTES_LEN};
+
+const BLOCK: &str = include_str!("../fixtures/demo_block.json");
+const PROOF: &str = include_str!("../fixtures/demo_proof.json");
+
+fn account_nodes() -> Vec<Vec<u8>> {
+    let proof: web3::types::Proof = serde_json::from_str(PROOF).unwrap();
+    proof.account_proof.into_iter().map(|node| node.0).collect()
+}
+
+fn header_encoding(c: &mut Criterion) {
+    let block: serde_json::Value = serde_json::from_str(BLOCK).unwrap();
+    let spec = ChainSpec::known(1);
+    c.bench_function("header/from_rpc", |b| {
+        b.iter(|| header::from_rpc(black_box(block.clone()), demo::BLOCK, &spec).unwrap())
+    });
+    let (header, _) = header::from_rpc(block, demo::BLOCK, &spec).unwrap();
+    c.bench_function("header/encode", |b| b.iter(|| black_box(&header).encode()));
+    c.bench_function("header/hash", |b| b.iter(|| black_box(&header).hash()));
+}
+
+fn padding(c: &mut Criterion) {
+    let nodes = account_nodes();
+    c.bench_function("proof/pad", |b| {
+        b.iter(|| {
+            noir_mip::pad_proof("account proof", black_box(&nodes), ACCOUNT_PROOF_MAX_DEPTH)
+                .unwrap()
+        })
+    });
+    let flat = noir_mip::pad_proof("account proof", &nodes, ACCOUNT_PROOF_MAX_DEPTH).unwrap();
+    c.bench_function("proof/unpad", |b| {
+        b.iter(|| trie::unpad_proof(black_box(&flat), PROOF_BYTES_LEN, nodes.len()))
+    });
+}
+
+fn verification(c: &mut Criterion) {
+    let block: serde_json::Value = serde_json::from_str(BLOCK).unwrap();
+    let (header, _) = header::from_rpc(block, demo::BLOCK, &ChainSpec::known(1)).unwrap();
+    let nodes = account_nodes();
+    let nibbles = trie::key_nibbles(demo::args().account.as_bytes());
+    c.bench_function("trie/verify_account_proof", |b| {
+        b.iter(|| {
+            trie::verify_proof(header.state_root.as_bytes(), &nibbles, black_box(&nodes)).unwrap()
+        })
+    });
+    c.bench_function("trie/proof_layouts", |b| {
+        b.iter(|| trie::proof_layouts(black_box(&nodes), &nibbles).unwrap())
+    });
+}
+
+fn serialization(c: &mut Criterion) {
+    let params = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(noir_mip::generate_with_source(
+            &demo::Recorded,
+            &demo::args(),
+            true,
+        ))
+        .unwrap();
+    c.bench_function("params/to_toml", |b| {
+        b.iter(|| black_box(&params).to_toml())
+    });
+    let toml = params.to_toml();
+    c.bench_function("params/from_toml", |b| {
+        b.iter(|| noir_mip::params::Params::from_toml(black_box(&toml)).unwrap())
+    });
+    c.bench_function("params/witness_hash", |b| {
+        b.iter(|| black_box(&params).witness_hash())
+    });
+}
+
+criterion_group!(
+    benches,
+    header_encoding,
+    padding,
+    verification,
+    serialization
+);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+//! Records the commit the crate is built from for `noir-mip info`. A build
+//! outside a git checkout, such as from a published crate, records none
+//! unless `NOIR_MIP_GIT_COMMIT` is set.
+
+use std::fs;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=NOIR_MIP_GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    if let Some(head) = fs::read_to_string(".git/HEAD")
+        .ok()
+        .and_then(|head| Some(head.strip_prefix("ref: ")?.trim().to_string()))
+    {
+        println!("cargo:rerun-if-changed=.git/{}", head);
+    }
+    if std::env::var_os("NOIR_MIP_GIT_COMMIT").is_some() {
+        return;
+    }
+    let commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=NOIR_MIP_GIT_COMMIT={}", commit.trim());
+    }
+}
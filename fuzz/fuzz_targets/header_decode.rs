@@ -0,0 +1,16 @@
+//! Arbitrary bytes as an RLP block header, as `decode-header` reads them.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use noir_mip::header::{self, BlockHeader, Layout};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = header::encoded_len(data);
+    for layout in [Layout::Ethereum, Layout::Coreth] {
+        let _ = header::field_spans(data, layout);
+        if let Ok(header) = BlockHeader::decode(data, layout) {
+            let _ = header.hash();
+        }
+    }
+});
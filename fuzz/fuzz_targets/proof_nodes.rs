@@ -0,0 +1,24 @@
+//! Arbitrary bytes as a key followed by a flattened proof, padded the way
+//! the parameters carry it, walked by the trie verifier.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use noir_mip::keccak::keccak256;
+use noir_mip::trie;
+use noir_mip::{ACCOUNT_PROOF_MAX_DEPTH, PROOF_BYTES_LEN};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 32 {
+        return;
+    }
+    let (key, flat) = data.split_at(32);
+    let nibbles = trie::nibbles(key);
+    let nodes = trie::unpad_proof(flat, PROOF_BYTES_LEN, ACCOUNT_PROOF_MAX_DEPTH);
+    let _ = trie::consumed_nibbles(&nodes);
+    let _ = trie::proof_layouts(&nodes, &nibbles);
+    // Rooting the walk at the first node lets it get past the root check.
+    if let Some(first) = nodes.first() {
+        let _ = trie::verify_proof(&keccak256(first), &nibbles, &nodes);
+    }
+});
@@ -0,0 +1,18 @@
+//! Arbitrary text as a parameter file, TOML or JSON, as `hash`,
+//! `explain-proof` and `verify-signature` read it, then checked natively.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use noir_mip::{demo, witness};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(params) = witness::parse(text) {
+        let _ = params.to_toml();
+        let _ = witness::to_json(&params);
+        let _ = demo::verify(&params);
+    }
+});
@@ -0,0 +1,164 @@
+//! A reader for the binary container `--format binary` writes, as
+//! `noir_mip::container` lays it out, borrowing every value from the input
+//! so the guest copies nothing it reads.
+
+use alloc::vec::Vec;
+
+use crate::Error;
+
+const MAGIC: &[u8; 4] = b"NMIP";
+/// The latest container version this reader knows.
+const VERSION: u16 = 1;
+/// The kind code of the metadata section; every other known kind holds
+/// fields.
+const META: u8 = 6;
+
+/// A value of a container entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value<'a> {
+    Bytes(&'a [u8]),
+    Int(u64),
+    Hex(&'a [u8]),
+    Str(&'a str),
+    Array(Vec<Value<'a>>),
+}
+
+/// The fields and metadata of a container, in the order they are stored.
+#[derive(Debug, Default)]
+pub struct Container<'a> {
+    pub fields: Vec<(&'a str, Value<'a>)>,
+    pub meta: Vec<(&'a str, Value<'a>)>,
+}
+
+impl<'a> Container<'a> {
+    pub fn field(&self, name: &str) -> Option<&Value<'a>> {
+        lookup(&self.fields, name)
+    }
+
+    pub fn bytes(&self, name: &str) -> Option<&'a [u8]> {
+        match self.field(name)? {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn int(&self, name: &str) -> Option<u64> {
+        match self.field(name)? {
+            Value::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn meta_str(&self, name: &str) -> Option<&'a str> {
+        match lookup(&self.meta, name)? {
+            Value::Str(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+fn lookup<'v, 'a>(entries: &'v [(&'a str, Value<'a>)], name: &str) -> Option<&'v Value<'a>> {
+    entries
+        .iter()
+        .find(|(entry, _)| *entry == name)
+        .map(|(_, value)| value)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.data.len() {
+            return Err(Error::Container("the container is truncated"));
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<usize, Error> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn data(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.u32()?;
+        self.take(len)
+    }
+
+    fn text(&mut self, len: usize) -> Result<&'a str, Error> {
+        core::str::from_utf8(self.take(len)?)
+            .map_err(|_| Error::Container("a container string is not UTF-8"))
+    }
+
+    fn value(&mut self) -> Result<Value<'a>, Error> {
+        Ok(match self.u8()? {
+            0 => Value::Bytes(self.data()?),
+            1 => {
+                let mut be_bytes = [0u8; 8];
+                be_bytes.copy_from_slice(self.take(8)?);
+                Value::Int(u64::from_be_bytes(be_bytes))
+            }
+            2 => Value::Hex(self.data()?),
+            3 => {
+                let len = self.u32()?;
+                Value::Str(self.text(len)?)
+            }
+            4 => {
+                let count = self.u32()?;
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.value()?);
+                }
+                Value::Array(values)
+            }
+            _ => return Err(Error::Container("unknown value tag in the container")),
+        })
+    }
+}
+
+/// Reads the container `data`. Sections of unknown kinds are skipped, as
+/// the generator's own reader does.
+pub fn read(data: &[u8]) -> Result<Container<'_>, Error> {
+    let mut reader = Reader { data };
+    if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(Error::Container("not a noir-mip container"));
+    }
+    if reader.u16()? > VERSION {
+        return Err(Error::Container(
+            "the container is newer than this guest reads",
+        ));
+    }
+    let mut container = Container::default();
+    while !reader.data.is_empty() {
+        let code = reader.u8()?;
+        let mut section = Reader {
+            data: reader.data()?,
+        };
+        let entries = match code {
+            META => &mut container.meta,
+            1..=5 => &mut container.fields,
+            _ => continue,
+        };
+        while !section.data.is_empty() {
+            // The entry's position in emission order, which lookups by
+            // name do not need.
+            section.u32()?;
+            let len = section.u16()? as usize;
+            let name = section.text(len)?;
+            entries.push((name, section.value()?));
+        }
+    }
+    Ok(container)
+}
@@ -0,0 +1,336 @@
+//! Verifies a `noir-mip` witness inside a zkVM guest, RISC Zero or SP1, as
+//! a proving route alternative to the Noir circuit. The guest reads the
+//! container `--format binary` writes, so one generator run feeds both
+//! proof systems the same padded fields, and checks the statement the
+//! circuit proves with the crate's `no_std` core: the block hash commits
+//! to the header, the header to the state root, the account proof leads
+//! from it to `account_value`, and the storage proof from the account's
+//! storage root to `storage_value`.
+//!
+//! ```ignore
+//! // RISC Zero
+//! let container: Vec<u8> = risc0_zkvm::guest::env::read();
+//! let statement = noir_mip_guest::verify(&container).expect("invalid witness");
+//! risc0_zkvm::guest::env::commit_slice(&statement.journal());
+//!
+//! // SP1
+//! let container = sp1_zkvm::io::read_vec();
+//! let statement = noir_mip_guest::verify(&container).expect("invalid witness");
+//! sp1_zkvm::io::commit_slice(&statement.journal());
+//! ```
+//!
+//! Witnesses of `gen_prove_params` are read as generated: with the default
+//! numeric encoding and value form, without `--rename`, and with proofs
+//! that fit the circuit whole rather than split by `--split-deep-proofs`.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod container;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use noir_mip::core::keccak::keccak256;
+use noir_mip::core::nodes::{encoded_len, key_nibbles, unpad_proof, EMPTY_TRIE_ROOT};
+use noir_mip::core::proof::walk_proof;
+use noir_mip::PROOF_BYTES_LEN;
+use rlp::Rlp;
+
+use container::Container;
+
+/// Why a witness does not verify.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input is not a container this guest reads.
+    Container(&'static str),
+    /// The witness lacks a field the statement needs.
+    Missing(&'static str),
+    /// The witness was generated with options the guest does not verify.
+    Unsupported(&'static str),
+    /// The hash chain of a proof breaks, as described.
+    Proof(String),
+    /// A field disagrees with what the proofs lead to.
+    Mismatch(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Container(message) => write!(f, "{}", message),
+            Error::Missing(name) => write!(f, "the witness has no {}", name),
+            Error::Unsupported(what) => write!(f, "unsupported witness: {}", what),
+            Error::Proof(message) => write!(f, "{}", message),
+            Error::Mismatch(name) => write!(f, "{} does not match the proofs", name),
+        }
+    }
+}
+
+/// What a verified witness proves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    /// The block hash, or `None` in state-root mode.
+    pub block_hash: Option<[u8; 32]>,
+    pub state_root: [u8; 32],
+    pub account: [u8; 20],
+    pub account_exists: bool,
+    /// The slot and its value, or `None` for an account proof.
+    pub storage: Option<([u8; 32], [u8; 32])>,
+}
+
+impl Statement {
+    /// The statement as the guest commits it: the block hash (zero in
+    /// state-root mode), the state root, the account, a byte that is 1 when
+    /// the account exists, and, for a storage proof, the slot and its
+    /// 32-byte big-endian value.
+    pub fn journal(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(149);
+        out.extend_from_slice(&self.block_hash.unwrap_or_default());
+        out.extend_from_slice(&self.state_root);
+        out.extend_from_slice(&self.account);
+        out.push(self.account_exists as u8);
+        if let Some((slot, value)) = &self.storage {
+            out.extend_from_slice(slot);
+            out.extend_from_slice(value);
+        }
+        out
+    }
+}
+
+fn fixed<const N: usize>(bytes: &[u8], name: &'static str) -> Result<[u8; N], Error> {
+    bytes.try_into().map_err(|_| Error::Mismatch(name))
+}
+
+/// Walks the proof `name`, of `depth` nodes, from `root` to the leaf at
+/// `key`, if any.
+fn walk(
+    container: &Container,
+    (name, depth): (&'static str, &'static str),
+    root: &[u8],
+    key: &[u8],
+) -> Result<Option<Vec<u8>>, Error> {
+    let nodes = proof(container, name, depth)?;
+    walk_proof(root, &key_nibbles(key), &nodes).map_err(|broken| {
+        Error::Proof(format!(
+            "the {} is broken: {}",
+            name,
+            broken.describe(nodes.len())
+        ))
+    })
+}
+
+/// The unpadded nodes of the proof `name`.
+fn proof(
+    container: &Container,
+    name: &'static str,
+    depth: &'static str,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let flat = container.bytes(name).ok_or(Error::Missing(name))?;
+    let depth = container.int(depth).ok_or(Error::Missing(depth))?;
+    Ok(unpad_proof(flat, PROOF_BYTES_LEN, depth as usize))
+}
+
+/// Verifies the witness in the container `data`, returning what it proves.
+pub fn verify(data: &[u8]) -> Result<Statement, Error> {
+    let witness = container::read(data)?;
+    if witness.field("account_proof_tail").is_some()
+        || witness.field("storage_proof_tail").is_some()
+    {
+        return Err(Error::Unsupported("proofs split by --split-deep-proofs"));
+    }
+    if witness
+        .meta_str("numeric_encoding")
+        .is_some_and(|encoding| encoding != "be-bytes")
+    {
+        return Err(Error::Unsupported("a numeric encoding other than be-bytes"));
+    }
+    if witness
+        .meta_str("value_form")
+        .is_some_and(|form| form != "padded")
+    {
+        return Err(Error::Unsupported("a value form other than padded"));
+    }
+
+    let (block_hash, state_root) = match witness.bytes("block_header_rlp") {
+        Some(padded) => {
+            let header = encoded_len(padded)
+                .ok()
+                .and_then(|len| padded.get(..len))
+                .ok_or(Error::Mismatch("block_header_rlp"))?;
+            let block_hash = witness
+                .bytes("block_hash")
+                .ok_or(Error::Missing("block_hash"))?;
+            if keccak256(header) != block_hash {
+                return Err(Error::Mismatch("block_hash"));
+            }
+            let state_root = Rlp::new(header)
+                .at(3)
+                .and_then(|item| item.data())
+                .map_err(|_| Error::Mismatch("block_header_rlp"))?;
+            (
+                Some(fixed(block_hash, "block_hash")?),
+                fixed(state_root, "block_header_rlp")?,
+            )
+        }
+        None => {
+            let state_root = witness
+                .bytes("state_root")
+                .ok_or(Error::Missing("state_root"))?;
+            (None, fixed(state_root, "state_root")?)
+        }
+    };
+
+    let account = witness
+        .bytes("account_key")
+        .ok_or(Error::Missing("account_key"))?;
+    let account_value = witness
+        .bytes("account_value")
+        .ok_or(Error::Missing("account_value"))?;
+    let account_exists = witness.int("account_exists") != Some(0);
+    match walk(
+        &witness,
+        ("account_proof", "account_proof_depth"),
+        &state_root,
+        account,
+    )? {
+        Some(leaf) if account_exists && leaf == account_value => {}
+        None if !account_exists => {}
+        _ => return Err(Error::Mismatch("account_value")),
+    }
+
+    let storage = match witness.bytes("storage_key") {
+        None => None,
+        Some(slot) => {
+            let storage_root = match account_exists {
+                true => Rlp::new(account_value)
+                    .at(2)
+                    .and_then(|item| item.data())
+                    .map_err(|_| Error::Mismatch("account_value"))?,
+                false => &EMPTY_TRIE_ROOT,
+            };
+            if witness
+                .bytes("storage_root")
+                .is_some_and(|field| field != storage_root)
+            {
+                return Err(Error::Mismatch("storage_root"));
+            }
+            let mut value = [0u8; 32];
+            if let Some(leaf) = walk(
+                &witness,
+                ("storage_proof", "storage_proof_depth"),
+                storage_root,
+                slot,
+            )? {
+                let bytes = Rlp::new(&leaf)
+                    .data()
+                    .ok()
+                    .filter(|bytes| bytes.len() <= 32)
+                    .ok_or(Error::Mismatch("storage_value"))?;
+                value[32 - bytes.len()..].copy_from_slice(bytes);
+            }
+            let expected = witness
+                .bytes("storage_value")
+                .ok_or(Error::Missing("storage_value"))?;
+            if value != expected {
+                return Err(Error::Mismatch("storage_value"));
+            }
+            Some((fixed(slot, "storage_key")?, value))
+        }
+    };
+
+    Ok(Statement {
+        block_hash,
+        state_root,
+        account: fixed(account, "account_key")?,
+        account_exists,
+        storage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The host's `--format binary` witness of the recorded demo block:
+    /// mainnet block 12965000, slot 0 of the USDT contract.
+    const RECORDED: &[u8] = include_bytes!("../../fixtures/golden/Prover.bin");
+
+    fn hex<const N: usize>(text: &str) -> [u8; N] {
+        let bytes: Vec<u8> = (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect();
+        let mut out = [0u8; N];
+        out[N - bytes.len()..].copy_from_slice(&bytes);
+        out
+    }
+
+    /// `RECORDED` with byte `at` of the bytes field `name` flipped.
+    fn tampered(name: &str, at: usize) -> Vec<u8> {
+        let mut entry = Vec::from((name.len() as u16).to_be_bytes());
+        entry.extend_from_slice(name.as_bytes());
+        entry.push(0);
+        let start = RECORDED
+            .windows(entry.len())
+            .position(|window| window == entry.as_slice())
+            .unwrap()
+            + entry.len();
+        let len = u32::from_be_bytes(RECORDED[start..start + 4].try_into().unwrap()) as usize;
+        assert!(at < len);
+        let mut data = RECORDED.to_vec();
+        data[start + 4 + at] ^= 1;
+        data
+    }
+
+    #[test]
+    fn the_recorded_witness_proves_its_statement() {
+        let statement = verify(RECORDED).unwrap();
+        assert_eq!(
+            statement,
+            Statement {
+                block_hash: Some(hex(
+                    "9b83c12c69edb74f6c8dd5d052765c1adf940e320bd1291696e6fa07829eee71"
+                )),
+                state_root: hex("41cf6e8e60fd087d2b00360dc29e5bfb21959bce1f4c242fd1ad7c4da968eb87"),
+                account: hex("dac17f958d2ee523a2206206994597c13d831ec7"),
+                account_exists: true,
+                storage: Some(([0; 32], hex("c6cde7c39eb2f0f0095f41570af89efc2c1ea828"))),
+            }
+        );
+        let journal = statement.journal();
+        assert_eq!(journal.len(), 149);
+        assert_eq!(journal[..32], statement.block_hash.unwrap());
+        assert_eq!(journal[84], 1);
+    }
+
+    #[test]
+    fn a_storage_value_the_proof_does_not_lead_to_is_refused() {
+        assert_eq!(
+            verify(&tampered("storage_value", 31)),
+            Err(Error::Mismatch("storage_value"))
+        );
+    }
+
+    #[test]
+    fn a_block_hash_the_header_does_not_hash_to_is_refused() {
+        assert_eq!(
+            verify(&tampered("block_hash", 31)),
+            Err(Error::Mismatch("block_hash"))
+        );
+    }
+
+    #[test]
+    fn a_tampered_account_or_proof_node_is_refused() {
+        assert_eq!(
+            verify(&tampered("account_value", 10)),
+            Err(Error::Mismatch("account_value"))
+        );
+        assert!(matches!(
+            verify(&tampered("storage_proof", 40)),
+            Err(Error::Proof(_))
+        ));
+    }
+}
@@ -0,0 +1,265 @@
+//! The `advise` command: samples the proofs a circuit would be fed over
+//! recent blocks and recommends its depth constants from what it saw.
+//! Besides the circuit's own account and slots, pseudo-random accounts and
+//! absent slots are proven, whose (exclusion) proofs run as deep as the
+//! trie around them, so the figures describe the tries rather than a single
+//! path.
+
+use std::fmt::Write;
+use std::fs;
+
+use web3::types::{H160, H256, U256};
+use web3::Web3;
+
+use crate::cli::AdviseArgs;
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::options::GenOptions;
+use crate::rpc::Client;
+use crate::source::EthDataSource;
+use crate::{
+    resolve_block, slots, ACCOUNT_PROOF_MAX_DEPTH, PROOF_BYTES_LEN, STORAGE_PROOF_MAX_DEPTH,
+};
+
+/// Keys asked for in one `eth_getProof`; providers cap the keys of a call.
+const KEYS_PER_CALL: usize = 50;
+
+/// Observations of one quantity.
+#[derive(Debug, Clone, Default)]
+pub struct Samples(Vec<usize>);
+
+impl Samples {
+    fn push(&mut self, value: usize) {
+        self.0.push(value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The nearest-rank `percent`th percentile.
+    pub fn percentile(&self, percent: usize) -> usize {
+        let mut sorted = self.0.clone();
+        sorted.sort_unstable();
+        let rank = (percent * sorted.len()).div_ceil(100).max(1);
+        sorted.get(rank - 1).copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> usize {
+        self.0.iter().copied().max().unwrap_or_default()
+    }
+}
+
+/// What [`advise`] saw.
+#[derive(Debug, Clone, Default)]
+pub struct Advice {
+    pub blocks: Vec<u64>,
+    pub account_depths: Samples,
+    pub storage_depths: Samples,
+    pub node_bytes: Samples,
+    /// Levels added to the deepest proof in the recommendation.
+    pub headroom: usize,
+}
+
+/// The `index`th pseudo-random key of `kind`, the same on every run.
+fn sample_key(kind: &str, index: usize) -> [u8; 32] {
+    keccak256(format!("noir-mip advise {} {}", kind, index).as_bytes())
+}
+
+/// The slots to sample: `--slot`s and the entries of the `--holder`s, or
+/// absent pseudo-random slots when neither is given.
+fn sampled_slots(args: &AdviseArgs) -> Result<Vec<H256>> {
+    let mut sampled = args.slot.clone();
+    if let Some(index) = &args.mapping {
+        let index = slots::parse_slot_index(index)?;
+        let mut holders = args.holders.clone();
+        if let Some(path) = &args.holders_file {
+            holders.extend(
+                fs::read_to_string(path)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        if holders.is_empty() {
+            return Err(Error::Config(
+                "--mapping samples the entries of --holder or --holders-file keys; pass some"
+                    .to_string(),
+            ));
+        }
+        for holder in holders {
+            sampled.push(slots::mapping_slot(
+                index,
+                &slots::parse_mapping_key(&holder)?,
+            ));
+        }
+    }
+    if sampled.is_empty() {
+        sampled = (0..args.random_slots)
+            .map(|i| H256(sample_key("slot", i)))
+            .collect();
+    }
+    Ok(sampled)
+}
+
+/// `count` blocks spread evenly over the `span` blocks before `last`,
+/// oldest first.
+fn sampled_blocks(last: u64, span: u64, count: usize) -> Vec<u64> {
+    let steps = (count as u64).saturating_sub(1).max(1);
+    let mut blocks: Vec<u64> = (0..count as u64)
+        .map(|i| last.saturating_sub(span * i / steps))
+        .collect();
+    blocks.reverse();
+    blocks.dedup();
+    blocks
+}
+
+/// Records the depth and node sizes of every proof of `account` and `keys`
+/// at `block`.
+async fn sample(
+    web3: &Web3<Client>,
+    advice: &mut Advice,
+    account: H160,
+    keys: &[H256],
+    block: u64,
+) -> Result<()> {
+    let chunks: Vec<&[H256]> = match keys.is_empty() {
+        true => vec![&[]],
+        false => keys.chunks(KEYS_PER_CALL).collect(),
+    };
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        let keys = chunk
+            .iter()
+            .map(|slot| U256::from_big_endian(slot.as_bytes()))
+            .collect();
+        let proof = web3.get_proof(account, keys, block).await?.ok_or_else(|| {
+            Error::Unsupported(format!(
+                "the provider returned no proof at block {}; sample recent blocks or use an \
+                 archive node",
+                block
+            ))
+        })?;
+        // Every call proves the account again; count it once.
+        if i == 0 {
+            advice.account_depths.push(proof.account_proof.len());
+            for node in &proof.account_proof {
+                advice.node_bytes.push(node.0.len());
+            }
+        }
+        for storage in &proof.storage_proof {
+            advice.storage_depths.push(storage.proof.len());
+            for node in &storage.proof {
+                advice.node_bytes.push(node.0.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Proves the account and slots of `args`, and the pseudo-random accounts,
+/// at every sampled block.
+pub async fn advise(args: &AdviseArgs) -> Result<Advice> {
+    let mut gen_args = GenOptions::new(&args.rpc_url, 0, args.account, None);
+    gen_args.block_ref = args.block;
+    resolve_block(&mut gen_args).await?;
+    let web3 = Web3::new(Client::new(&args.rpc_url)?);
+    let keys = sampled_slots(args)?;
+    let mut advice = Advice {
+        blocks: sampled_blocks(gen_args.block, args.span, args.blocks),
+        headroom: args.headroom,
+        ..Default::default()
+    };
+    for &block in &advice.blocks.clone() {
+        sample(&web3, &mut advice, args.account, &keys, block).await?;
+        for i in 0..args.random_accounts {
+            let account = H160::from_slice(&sample_key("account", i)[12..]);
+            sample(&web3, &mut advice, account, &[], block).await?;
+        }
+    }
+    Ok(advice)
+}
+
+impl Advice {
+    /// The percentiles of each quantity, the recommended constant and the
+    /// generator's current one.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:<22}{:>8}{:>6}{:>6}{:>6}{:>6}{:>11}{:>9}",
+            "", "samples", "p50", "p90", "p99", "max", "recommend", "current"
+        );
+        let rows = [
+            (
+                "account proof depth",
+                &self.account_depths,
+                self.account_depths.max() + self.headroom,
+                ACCOUNT_PROOF_MAX_DEPTH,
+            ),
+            (
+                "storage proof depth",
+                &self.storage_depths,
+                self.storage_depths.max() + self.headroom,
+                STORAGE_PROOF_MAX_DEPTH,
+            ),
+            // A branch node of 17 32-byte hashes, the largest node, fits.
+            (
+                "node bytes",
+                &self.node_bytes,
+                PROOF_BYTES_LEN,
+                PROOF_BYTES_LEN,
+            ),
+        ];
+        for (name, samples, recommend, current) in rows {
+            if samples.is_empty() {
+                continue;
+            }
+            let _ = writeln!(
+                out,
+                "{:<22}{:>8}{:>6}{:>6}{:>6}{:>6}{:>11}{:>9}",
+                name,
+                samples.len(),
+                samples.percentile(50),
+                samples.percentile(90),
+                samples.percentile(99),
+                samples.max(),
+                recommend,
+                current
+            );
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "global ACCOUNT_PROOF_MAX_DEPTH: Field = {};",
+            self.account_depths.max() + self.headroom
+        );
+        if !self.storage_depths.is_empty() {
+            let _ = writeln!(
+                out,
+                "global STORAGE_PROOF_MAX_DEPTH: Field = {};",
+                self.storage_depths.max() + self.headroom
+            );
+        }
+        for (name, samples, current) in [
+            ("account", &self.account_depths, ACCOUNT_PROOF_MAX_DEPTH),
+            ("storage", &self.storage_depths, STORAGE_PROOF_MAX_DEPTH),
+        ] {
+            if samples.max() > current {
+                let _ = writeln!(
+                    out,
+                    "Note: a {} proof of {} nodes exceeds this generator's {}; pass \
+                     --split-deep-proofs or rebuild with a larger constant",
+                    name,
+                    samples.max(),
+                    current
+                );
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,280 @@
+//! The `analyze-slot` command: reads a storage slot over a block range with
+//! `eth_getStorageAt`, without proofs, and prints when its value changed,
+//! how often, and which blocks a witness is best taken at. Between two
+//! samples `--stride` blocks apart a change is only known to lie somewhere
+//! in the gap, unless `--exact` bisects it down to its block.
+
+use std::fmt::Write;
+
+use futures::stream::{self, StreamExt, TryStreamExt};
+use web3::types::{BlockNumber, H160, H256, U256};
+use web3::Web3;
+
+use crate::cli::AnalyzeSlotArgs;
+use crate::error::{Error, Result};
+use crate::rpc::Client;
+
+/// Blocks read concurrently.
+const SAMPLE_CONCURRENCY: usize = 8;
+
+/// A change of the slot's value between two blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    /// The last block seen holding the previous value.
+    pub after: u64,
+    /// The first block seen holding the new value, `after + 1` once exact.
+    pub at: u64,
+    pub from: H256,
+    pub to: H256,
+}
+
+impl Change {
+    pub fn exact(&self) -> bool {
+        self.at == self.after + 1
+    }
+}
+
+/// What [`analyze_slot`] saw.
+#[derive(Debug, Clone)]
+pub struct History {
+    pub account: H160,
+    pub slot: H256,
+    pub lo: u64,
+    pub hi: u64,
+    pub stride: u64,
+    /// Blocks read, the samples and the bisection steps.
+    pub reads: usize,
+    /// The value at `lo`.
+    pub initial: H256,
+    pub changes: Vec<Change>,
+    pub deadline: Option<u64>,
+}
+
+async fn value_at(web3: &Web3<Client>, account: H160, slot: H256, block: u64) -> Result<H256> {
+    let key = U256::from_big_endian(slot.as_bytes());
+    Ok(web3
+        .eth()
+        .storage(account, key, Some(BlockNumber::Number(block.into())))
+        .await?)
+}
+
+/// Narrows `change` down to the block of each change it hides: the first
+/// block away from its previous value, then, if that is not yet the value
+/// after the gap, the first block away from that one, and so on. A value
+/// that changed and changed back inside the gap is not seen.
+async fn bisect(web3: &Web3<Client>, history: &mut History, change: Change) -> Result<Vec<Change>> {
+    let mut found = Vec::new();
+    let (mut after, mut from) = (change.after, change.from);
+    loop {
+        let (mut lo, mut hi, mut to) = (after, change.at, change.to);
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            let value = value_at(web3, history.account, history.slot, mid).await?;
+            history.reads += 1;
+            if value == from {
+                lo = mid;
+            } else {
+                (hi, to) = (mid, value);
+            }
+        }
+        found.push(Change {
+            after: lo,
+            at: hi,
+            from,
+            to,
+        });
+        if to == change.to {
+            return Ok(found);
+        }
+        (after, from) = (hi, to);
+    }
+}
+
+/// Reads the slot of `args` every `--stride` blocks of `--block-range`, and
+/// at its last block, and records each change between two samples.
+pub async fn analyze_slot(args: &AnalyzeSlotArgs, slot: H256) -> Result<History> {
+    let range = args.block_range;
+    if let Some(deadline) = args.deadline {
+        if !(range.lo..=range.hi).contains(&deadline) {
+            return Err(Error::Config(format!(
+                "--deadline {} is outside --block-range {}..{}",
+                deadline, range.lo, range.hi
+            )));
+        }
+    }
+    // The end of the range and the deadline are always read, so neither
+    // falls in the gap of a change.
+    let mut blocks = range.blocks(args.stride);
+    blocks.push(range.hi);
+    blocks.extend(args.deadline);
+    blocks.sort_unstable();
+    blocks.dedup();
+    log::info!(
+        "Reading {} block(s) from {} to {} every {}",
+        blocks.len(),
+        range.lo,
+        range.hi,
+        args.stride
+    );
+    let web3 = Web3::new(Client::new(&args.rpc_url)?);
+    let values: Vec<H256> = stream::iter(
+        blocks
+            .iter()
+            .map(|&block| value_at(&web3, args.account, slot, block)),
+    )
+    .buffered(SAMPLE_CONCURRENCY)
+    .try_collect()
+    .await?;
+
+    let mut history = History {
+        account: args.account,
+        slot,
+        lo: range.lo,
+        hi: range.hi,
+        stride: args.stride,
+        reads: blocks.len(),
+        initial: values[0],
+        changes: Vec::new(),
+        deadline: args.deadline,
+    };
+    let gaps: Vec<Change> = blocks
+        .windows(2)
+        .zip(values.windows(2))
+        .filter(|(_, values)| values[0] != values[1])
+        .map(|(blocks, values)| Change {
+            after: blocks[0],
+            at: blocks[1],
+            from: values[0],
+            to: values[1],
+        })
+        .collect();
+    for change in gaps {
+        match args.exact {
+            true => {
+                let found = bisect(&web3, &mut history, change).await?;
+                history.changes.extend(found);
+            }
+            false => history.changes.push(change),
+        }
+    }
+    Ok(history)
+}
+
+/// `value` as a number, in hex without leading zeros.
+fn number(value: &H256) -> String {
+    format!("{:#x}", U256::from_big_endian(value.as_bytes()))
+}
+
+impl History {
+    /// The value held from the start of the range up to each change and
+    /// after it, as (first block, last block, value). The bounds are the
+    /// samples, so a stretch may start earlier and end later.
+    pub fn stretches(&self) -> Vec<(u64, u64, H256)> {
+        let mut stretches = Vec::new();
+        let (mut start, mut value) = (self.lo, self.initial);
+        for change in &self.changes {
+            stretches.push((start, change.after, value));
+            (start, value) = (change.at, change.to);
+        }
+        stretches.push((start, self.hi, value));
+        stretches
+    }
+
+    /// The change history, how often the slot changes and the blocks to
+    /// take a witness at.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "Slot {:#x} of {:#x}, blocks {} to {} every {} ({} read)",
+            self.slot, self.account, self.lo, self.hi, self.stride, self.reads
+        );
+        let _ = writeln!(out, "value at {:<12} {}", self.lo, number(&self.initial));
+        for change in &self.changes {
+            let at = match change.exact() {
+                true => change.at.to_string(),
+                false => format!("{}..={}", change.after + 1, change.at),
+            };
+            let _ = writeln!(
+                out,
+                "changed at {:<10} {} -> {}",
+                at,
+                number(&change.from),
+                number(&change.to)
+            );
+        }
+        let _ = writeln!(out);
+
+        let span = self.hi - self.lo + 1;
+        let stretches = self.stretches();
+        match self.changes.len() {
+            0 => {
+                let _ = writeln!(out, "No change over {} blocks", span);
+            }
+            count => {
+                let _ = writeln!(
+                    out,
+                    "{} change(s) over {} blocks, one every {} blocks on average",
+                    count,
+                    span,
+                    span / count as u64
+                );
+            }
+        }
+        if !self.changes.iter().all(Change::exact) {
+            let _ = writeln!(
+                out,
+                "Changes are placed within the gap between samples; pass --exact to bisect them"
+            );
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "Candidate blocks:");
+        let _ = writeln!(
+            out,
+            "  {:<12} the end of the range, holding {}",
+            self.hi,
+            number(&stretches[stretches.len() - 1].2)
+        );
+        if let Some((start, end, value)) = stretches
+            .iter()
+            .copied()
+            .max_by_key(|(start, end, _)| end - start)
+            .filter(|_| stretches.len() > 1)
+        {
+            let _ = writeln!(
+                out,
+                "  {:<12} the longest stretch without a change, {} blocks holding {}; any block \
+                 from {} to {} proves it",
+                start,
+                end - start + 1,
+                number(&value),
+                start,
+                end
+            );
+        }
+        if let Some(deadline) = self.deadline {
+            let last = self
+                .changes
+                .iter()
+                .rev()
+                .find(|change| change.at <= deadline);
+            let (since, what, value) = match last {
+                Some(change) => (change.at, "the last change", change.to),
+                None => (self.lo, "no change", self.initial),
+            };
+            let _ = writeln!(
+                out,
+                "  {:<12} {} before --deadline {}, holding {}; any block from {} to {} \
+                 proves the value held at the deadline",
+                since,
+                what,
+                deadline,
+                number(&value),
+                since,
+                deadline
+            );
+        }
+        out
+    }
+}
@@ -0,0 +1,54 @@
+//! `--blind-slot`: keeps the raw storage slot, and with it the mapping key
+//! it was derived from, out of the written witness. `storage_key` is
+//! replaced with `storage_key_hash`, the keccak256 of the slot, which is the
+//! storage trie path the proof follows and all a circuit needs to walk it.
+//! The metadata records `slot_commitment`, the keccak256 of a 32-byte salt
+//! followed by the slot, so whoever holds the salt can later show which
+//! slot was proven without the witness revealing it.
+
+use ethereum_types::H256;
+
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::params::{Params, Value};
+
+/// Whether the field `name` holds a storage key, alone or prefixed in a
+/// batch.
+fn is_storage_key(name: &str) -> bool {
+    name == "storage_key" || name.ends_with("_storage_key")
+}
+
+/// A fresh random salt.
+pub fn random_salt() -> Result<H256> {
+    let mut salt = H256::zero();
+    openssl::rand::rand_bytes(salt.as_bytes_mut())
+        .map_err(|e| Error::Config(format!("cannot draw a salt: {}", e)))?;
+    Ok(salt)
+}
+
+/// keccak256 of `salt` followed by `slot`.
+pub fn commitment(slot: &H256, salt: &H256) -> [u8; 32] {
+    keccak256(&[salt.as_bytes(), slot.as_bytes()].concat())
+}
+
+/// Replaces every storage key of `params`, the encoding of `slot`, with the
+/// hash of `slot` in its place, and records the salted commitment.
+pub fn blind(params: &mut Params, slot: &H256, salt: &H256) {
+    let names: Vec<String> = params
+        .fields
+        .iter()
+        .map(|(name, _)| name.clone())
+        .filter(|name| is_storage_key(name))
+        .collect();
+    let hash = keccak256(slot.as_bytes());
+    for name in names {
+        let hashed = format!("{}_hash", name);
+        params.fields.rename(&name, &hashed);
+        params.fields.set(&hashed, hash.to_vec());
+    }
+    params.meta.push(
+        "slot_commitment",
+        Value::Hex(commitment(slot, salt).to_vec()),
+    );
+    params.record_witness_hash();
+}
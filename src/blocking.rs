@@ -0,0 +1,25 @@
+//! Blocking wrappers around the async API, for callers without an async
+//! runtime such as build scripts. Each call runs on its own current-thread
+//! runtime, so these must not be called from within one.
+
+use crate::error::Result;
+use crate::options::GenOptions;
+use crate::params::Params;
+use crate::witness::StorageProofWitness;
+
+fn block_on<T>(future: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(future)
+}
+
+/// Blocking version of [`crate::generate_params`].
+pub fn generate_params(args: &GenOptions, prove: bool) -> Result<Params> {
+    block_on(crate::generate_params(args, prove))
+}
+
+/// Blocking version of [`crate::generate_witness`].
+pub fn generate_witness(args: &GenOptions, prove: bool) -> Result<StorageProofWitness> {
+    block_on(crate::generate_witness(args, prove))
+}
@@ -0,0 +1,204 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use web3::types::{Proof, H160, H256, U256};
+
+use crate::compression::{self, Compressor};
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+
+static HITS: AtomicUsize = AtomicUsize::new(0);
+static MISSES: AtomicUsize = AtomicUsize::new(0);
+
+/// Lookups made since the statistics were last taken.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// Removes and returns the lookups counted so far.
+pub fn take_stats() -> CacheStats {
+    CacheStats {
+        hits: HITS.swap(0, Ordering::Relaxed),
+        misses: MISSES.swap(0, Ordering::Relaxed),
+    }
+}
+
+/// How much the `--proof-cache` directory may keep, the `[cache]` table.
+/// Runs that use the cache evict what exceeds it before they start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Entries unused for longer than this many days are evicted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_days: Option<u64>,
+    /// The least recently used entries are evicted until the cache holds
+    /// at most this many bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+}
+
+/// The entries of a cache directory, or those [`ProofCache::gc`] removed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// A file of the cache directory.
+struct Entry {
+    path: PathBuf,
+    bytes: u64,
+    used: SystemTime,
+}
+
+/// Removes the file at `path`, which a concurrent run may have removed
+/// first.
+fn remove(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// `eth_getProof` responses stored on disk by state root, account and
+/// slots. A proof only depends on the state it is taken against, so blocks
+/// that leave the state untouched share their proofs. Cached proofs are
+/// verified against the state root like fetched ones.
+pub struct ProofCache {
+    dir: PathBuf,
+}
+
+impl ProofCache {
+    pub fn new(dir: &Path) -> Self {
+        ProofCache {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn path(&self, state_root: H256, account: H160, keys: &[U256]) -> PathBuf {
+        let mut preimage = [state_root.as_bytes(), account.as_bytes()].concat();
+        for key in keys {
+            let mut bytes = [0; 32];
+            key.to_big_endian(&mut bytes);
+            preimage.extend_from_slice(&bytes);
+        }
+        self.dir
+            .join(format!("{}.json", hex::encode(keccak256(&preimage))))
+    }
+
+    pub fn get(&self, state_root: H256, account: H160, keys: &[U256]) -> Result<Option<Proof>> {
+        let path = self.path(state_root, account, keys);
+        let proof = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&compression::decompress(&data)?).map_err(|e| {
+                Error::Decode(format!("invalid cached proof {}: {}", path.display(), e))
+            })?,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                MISSES.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        HITS.fetch_add(1, Ordering::Relaxed);
+        // The modification time marks the last use, which eviction goes by.
+        let _ = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        Ok(Some(proof))
+    }
+
+    /// Stores `proof` compressed with `compressor`, writing it to a
+    /// temporary file first so concurrent runs never read a partial entry.
+    pub fn put(
+        &self,
+        state_root: H256,
+        account: H160,
+        keys: &[U256],
+        proof: &Proof,
+        compressor: Compressor,
+    ) -> Result<()> {
+        let path = self.path(state_root, account, keys);
+        let json = serde_json::to_vec(proof)
+            .map_err(|e| Error::Decode(format!("cannot serialize the proof: {}", e)))?;
+        fs::create_dir_all(&self.dir)?;
+        let partial = path.with_extension(format!("{}.partial", std::process::id()));
+        fs::write(&partial, compressor.compress(&json)?)?;
+        fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    /// The entries of the directory, least recently used first. Files that
+    /// are not entries, or left over from writes that never finished, are
+    /// left alone.
+    fn entries(&self) -> Result<Vec<Entry>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = Vec::new();
+        for file in dir {
+            let file = file?;
+            if !file.file_name().to_string_lossy().ends_with(".json") {
+                continue;
+            }
+            let metadata = file.metadata()?;
+            entries.push(Entry {
+                path: file.path(),
+                bytes: metadata.len(),
+                used: metadata.modified()?,
+            });
+        }
+        entries.sort_by_key(|entry| entry.used);
+        Ok(entries)
+    }
+
+    pub fn usage(&self) -> Result<Usage> {
+        let entries = self.entries()?;
+        Ok(Usage {
+            entries: entries.len(),
+            bytes: entries.iter().map(|entry| entry.bytes).sum(),
+        })
+    }
+
+    /// Removes every entry.
+    pub fn clear(&self) -> Result<Usage> {
+        self.gc(&CacheConfig {
+            max_bytes: Some(0),
+            ttl_days: None,
+        })
+    }
+
+    /// Removes the entries unused for longer than `policy.ttl_days`, then
+    /// the least recently used ones until the rest fit in
+    /// `policy.max_bytes`.
+    pub fn gc(&self, policy: &CacheConfig) -> Result<Usage> {
+        let ttl = policy
+            .ttl_days
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+        let now = SystemTime::now();
+        let entries = self.entries()?;
+        let mut kept: u64 = entries.iter().map(|entry| entry.bytes).sum();
+        let mut removed = Usage::default();
+        for entry in entries {
+            let expired = ttl.is_some_and(|ttl| {
+                now.duration_since(entry.used)
+                    .is_ok_and(|elapsed| elapsed > ttl)
+            });
+            if !expired && policy.max_bytes.is_none_or(|max| kept <= max) {
+                continue;
+            }
+            remove(&entry.path)?;
+            kept -= entry.bytes;
+            removed.entries += 1;
+            removed.bytes += entry.bytes;
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::forks::{self, Schedule};
+use crate::header::Layout;
+use crate::BLOCK_HEADER_RLP_BYTES;
+
+/// The chains [`ChainSpec::known`] has built-in expectations for, by id.
+pub const KNOWN_CHAINS: &[(u64, &str)] = &[
+    (1, "mainnet"),
+    (11_155_111, "sepolia"),
+    (17_000, "holesky"),
+    (560_048, "hoodi"),
+];
+
+/// How headers of a chain are expected to look.
+#[derive(Debug, Clone, Default)]
+pub struct ChainSpec {
+    /// Chain id the provider must report.
+    pub chain_id: Option<u64>,
+    /// Fork schedule headers are checked against; without one any set of
+    /// optional fields is accepted.
+    pub schedule: Option<Schedule>,
+    /// Length the extra data of every header must have.
+    pub extra_data_len: Option<usize>,
+    /// Encode the provider's `sealFields` in place of `mix_hash` and
+    /// `nonce`, as engines such as Aura require.
+    pub seal_fields: bool,
+    /// Length headers are padded to, for chains whose headers outgrow
+    /// [`BLOCK_HEADER_RLP_BYTES`].
+    pub max_header_bytes: Option<usize>,
+    /// Order and set of the header fields.
+    pub layout: Layout,
+}
+
+/// Layout of a `--chain-spec` file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecFile {
+    chain_id: Option<u64>,
+    #[serde(default)]
+    byzantium_block: u64,
+    london_block: Option<u64>,
+    shanghai_time: Option<u64>,
+    cancun_time: Option<u64>,
+    prague_time: Option<u64>,
+    extra_data_len: Option<usize>,
+    #[serde(default)]
+    seal_fields: bool,
+    max_header_bytes: Option<usize>,
+    #[serde(default)]
+    layout: Layout,
+}
+
+#[cfg(feature = "formats-toml")]
+fn parse(text: &str) -> std::result::Result<SpecFile, String> {
+    toml::from_str(text).map_err(|e| e.message().to_string())
+}
+
+#[cfg(not(feature = "formats-toml"))]
+fn parse(_: &str) -> std::result::Result<SpecFile, String> {
+    Err("this build reads no TOML; rebuild with --features formats-toml".to_string())
+}
+
+impl ChainSpec {
+    /// The built-in expectations for `chain_id`: the fork schedule of the
+    /// public Ethereum networks. Other chains are built in only once blocks
+    /// recorded from them under `fixtures/chains` check their headers.
+    pub fn known(chain_id: u64) -> Self {
+        ChainSpec {
+            chain_id: Some(chain_id),
+            schedule: forks::schedule(chain_id),
+            ..Default::default()
+        }
+    }
+
+    /// Reads a chain spec file. Forks it leaves out never activate, so a
+    /// chain that never adopted the London header omits `london_block`;
+    /// Byzantium left out counts from genesis.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = parse(&fs::read_to_string(path)?)
+            .map_err(|e| Error::Config(format!("invalid chain spec {}: {}", path.display(), e)))?;
+        Ok(ChainSpec {
+            chain_id: file.chain_id,
+            schedule: Some(Schedule {
+                byzantium_block: file.byzantium_block,
+                london_block: file.london_block,
+                shanghai_time: file.shanghai_time,
+                cancun_time: file.cancun_time,
+                prague_time: file.prague_time,
+            }),
+            extra_data_len: file.extra_data_len,
+            seal_fields: file.seal_fields,
+            max_header_bytes: file.max_header_bytes,
+            layout: file.layout,
+        })
+    }
+
+    /// Length the encoded header is zero-padded to in the witness.
+    pub fn header_rlp_bytes(&self) -> usize {
+        self.max_header_bytes.unwrap_or(BLOCK_HEADER_RLP_BYTES)
+    }
+
+    /// The header encoded as `rlp`, zero-padded to
+    /// [`header_rlp_bytes`](Self::header_rlp_bytes); headers longer than
+    /// that are rejected rather than cut short.
+    pub fn pad_header(&self, number: u64, mut rlp: Vec<u8>) -> Result<Vec<u8>> {
+        let max = self.header_rlp_bytes();
+        if rlp.len() > max {
+            return Err(Error::ProofTooDeep(format!(
+                "the header of block {} encodes to {} bytes, more than the {} the circuit takes; \
+                 set max_header_bytes in a --chain-spec and size the circuit's \
+                 BLOCK_HEADER_RLP_BYTES to match",
+                number,
+                rlp.len(),
+                max
+            )));
+        }
+        rlp.resize(max, 0);
+        Ok(rlp)
+    }
+}
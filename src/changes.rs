@@ -0,0 +1,143 @@
+use rlp::Rlp;
+use web3::types::U256;
+
+use crate::encoding;
+use crate::params::Params;
+use crate::units;
+
+/// Block number of the witness, read from its header or, for witnesses
+/// without one, from the `block_number` meta entry.
+fn block_number(params: &Params) -> Option<u64> {
+    if let Some(rlp) = params.fields.get_bytes("block_header_rlp") {
+        let rlp = encoding::header_padding(params).unpad(rlp)?;
+        return Rlp::new(rlp).val_at(8).ok();
+    }
+    params.meta.get_int("block_number")
+}
+
+fn storage_value(params: &Params) -> Option<U256> {
+    encoding::storage_value(params)
+}
+
+/// Nonce and balance of the encoded account in `account_value`.
+fn account(params: &Params) -> Option<(U256, U256)> {
+    let rlp = Rlp::new(params.fields.get_bytes("account_value")?);
+    Some((rlp.val_at(0).ok()?, rlp.val_at(1).ok()?))
+}
+
+/// `value`, followed by its reading under `--token-decimals` or `--as`.
+fn amount(params: &Params, value: U256) -> String {
+    match units::readable(params, value) {
+        Some(reading) => format!("{} ({})", value, reading),
+        None => value.to_string(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Describes, one line each, how `current` differs from `previous`, the
+/// witness an earlier run wrote to the same file: the block, the anchor,
+/// the account and storage values and the proof depths. Fields missing from
+/// either witness are not compared.
+pub fn summarize(previous: &Params, current: &Params) -> Vec<String> {
+    let mut changes = Vec::new();
+    let blocks = (block_number(previous), block_number(current));
+    match blocks {
+        (Some(before), Some(after)) if after > before => changes.push(format!(
+            "block advanced by {} ({} -> {})",
+            after - before,
+            before,
+            after
+        )),
+        (Some(before), Some(after)) if after < before => changes.push(format!(
+            "block moved back by {} ({} -> {})",
+            before - after,
+            before,
+            after
+        )),
+        _ => {}
+    }
+    for anchor in ["block_hash", "state_root"] {
+        if let (Some(before), Some(after)) = (
+            previous.fields.get_bytes(anchor),
+            current.fields.get_bytes(anchor),
+        ) {
+            if before == after {
+                continue;
+            }
+            match blocks {
+                (Some(before_block), Some(after_block)) if before_block == after_block => changes
+                    .push(format!(
+                        "{} of block {} changed from {} to {}",
+                        anchor,
+                        after_block,
+                        hex(before),
+                        hex(after)
+                    )),
+                (Some(_), Some(_)) => {}
+                _ => changes.push(format!(
+                    "{} changed from {} to {}",
+                    anchor,
+                    hex(before),
+                    hex(after)
+                )),
+            }
+        }
+    }
+    if let (Some((nonce_before, balance_before)), Some((nonce_after, balance_after))) =
+        (account(previous), account(current))
+    {
+        if nonce_before != nonce_after {
+            changes.push(format!(
+                "nonce changed from {} to {}",
+                nonce_before, nonce_after
+            ));
+        }
+        if balance_before != balance_after {
+            // The decimals apply to the balance when it is the proven value.
+            let balance = |balance: U256| match current.fields.get("storage_value") {
+                Some(_) => balance.to_string(),
+                None => amount(current, balance),
+            };
+            changes.push(format!(
+                "balance changed from {} to {}",
+                balance(balance_before),
+                balance(balance_after)
+            ));
+        }
+    }
+    if previous.fields.get("storage_key") == current.fields.get("storage_key") {
+        if let (Some(before), Some(after)) = (storage_value(previous), storage_value(current)) {
+            if before != after {
+                changes.push(format!(
+                    "value changed from {} to {}",
+                    amount(current, before),
+                    amount(current, after)
+                ));
+            }
+        }
+    } else {
+        changes.push("the proven slot changed".to_string());
+    }
+    // Both flags are only emitted when they differ from their default.
+    for (flag, default) in [("account_exists", 1), ("is_zero_value", 0)] {
+        let before = previous.fields.get_int(flag).unwrap_or(default);
+        let after = current.fields.get_int(flag).unwrap_or(default);
+        if before != after {
+            changes.push(format!("{} changed from {} to {}", flag, before, after));
+        }
+    }
+    for depth in ["account_proof_depth", "storage_proof_depth"] {
+        if let (Some(before), Some(after)) = (
+            previous.fields.get_int(depth),
+            current.fields.get_int(depth),
+        ) {
+            if before != after {
+                changes.push(format!("{} changed from {} to {}", depth, before, after));
+            }
+        }
+    }
+    changes
+}
@@ -0,0 +1,175 @@
+//! The checkpoint registry, `checkpoints.json`: block hashes an audit team
+//! trusts, each vetted by hand or verified with the light client, signed as
+//! a whole. `--only-checkpointed` anchors witnesses to these blocks only.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use web3::types::H256;
+
+use crate::error::{Error, Result};
+use crate::options::GenOptions;
+use crate::params::Params;
+use crate::signing;
+
+/// A trusted block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub chain_id: u64,
+    pub block: u64,
+    pub block_hash: H256,
+    /// How the hash was vetted: `manual` or `light-client`.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoints {
+    #[serde(default)]
+    pub checkpoints: Vec<Checkpoint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_scheme: Option<String>,
+    /// Raw ed25519 public key of the signer, hex.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+fn decode_hex(name: &str, value: &str) -> Result<Vec<u8>> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| Error::Signature(format!("invalid {} in the checkpoints: {}", name, e)))
+}
+
+impl Checkpoints {
+    /// Reads the registry at `path`; a missing file is an empty registry.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&text).map_err(|e| {
+            Error::Config(format!(
+                "{} is not a checkpoint file: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Decode(format!("cannot serialize the checkpoints: {}", e)))?;
+        fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Records `checkpoint`, replacing any earlier one of its block, and
+    /// drops the signature, which no longer covers the registry. Returns
+    /// whether the registry was signed.
+    pub fn add(&mut self, checkpoint: Checkpoint) -> bool {
+        self.checkpoints.retain(|existing| {
+            (existing.chain_id, existing.block) != (checkpoint.chain_id, checkpoint.block)
+        });
+        self.checkpoints.push(checkpoint);
+        self.checkpoints
+            .sort_by_key(|checkpoint| (checkpoint.chain_id, checkpoint.block));
+        let signed = self.signature.is_some();
+        self.signature_scheme = None;
+        self.signer = None;
+        self.signature = None;
+        signed
+    }
+
+    /// The signed serialization: the checkpoints as compact JSON.
+    fn message(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.checkpoints).unwrap_or_default()
+    }
+
+    /// Signs the checkpoints with the ed25519 key at `key_path`.
+    pub fn sign(&mut self, key_path: &Path) -> Result<()> {
+        let (signer, signature) = signing::sign_message(&self.message(), key_path)?;
+        self.signature_scheme = Some(signing::SIGNATURE_SCHEME.to_string());
+        self.signer = Some(format!("0x{}", hex::encode(signer)));
+        self.signature = Some(format!("0x{}", hex::encode(signature)));
+        Ok(())
+    }
+
+    /// Checks the signature and returns the signer's raw public key, which
+    /// must be `expected_signer` when one is given.
+    pub fn verify(&self, expected_signer: Option<&[u8]>) -> Result<Vec<u8>> {
+        let (Some(scheme), Some(signer), Some(signature)) =
+            (&self.signature_scheme, &self.signer, &self.signature)
+        else {
+            return Err(Error::Signature(
+                "the checkpoints are not signed; sign them with `checkpoint sign`".to_string(),
+            ));
+        };
+        if scheme != signing::SIGNATURE_SCHEME {
+            return Err(Error::Signature("unsupported signature scheme".to_string()));
+        }
+        let signer = decode_hex("signer", signer)?;
+        if let Some(expected) = expected_signer.filter(|expected| *expected != signer) {
+            return Err(Error::Signature(format!(
+                "the checkpoints are signed by 0x{}, expected 0x{}",
+                hex::encode(&signer),
+                hex::encode(expected)
+            )));
+        }
+        signing::verify_message(
+            &self.message(),
+            &signer,
+            &decode_hex("signature", signature)?,
+        )?;
+        Ok(signer)
+    }
+
+    pub fn find(&self, chain_id: u64, block: u64) -> Option<&Checkpoint> {
+        self.checkpoints
+            .iter()
+            .find(|checkpoint| (checkpoint.chain_id, checkpoint.block) == (chain_id, block))
+    }
+}
+
+/// Checks, for `--only-checkpointed`, that the block hash of `params` is the
+/// checkpointed hash of block `args.block` on chain `chain_id` in the signed
+/// registry `args.checkpoints`, and records the signer in the metadata.
+pub fn check(args: &GenOptions, chain_id: u64, params: &mut Params) -> Result<()> {
+    let expected = args
+        .checkpoint_signer
+        .as_deref()
+        .map(signing::read_public_key)
+        .transpose()?;
+    let checkpoints = Checkpoints::load(&args.checkpoints)?;
+    let signer = checkpoints.verify(expected.as_deref())?;
+    let checkpoint = checkpoints.find(chain_id, args.block).ok_or_else(|| {
+        Error::Verification(format!(
+            "block {} of chain {} is not in {}",
+            args.block,
+            chain_id,
+            args.checkpoints.display()
+        ))
+    })?;
+    let hash = params.fields.get_bytes("block_hash").ok_or_else(|| {
+        Error::Unsupported(
+            "--only-checkpointed checks the block hash, which state-root witnesses leave out"
+                .to_string(),
+        )
+    })?;
+    if hash != checkpoint.block_hash.as_bytes() {
+        return Err(Error::Verification(format!(
+            "block {} hashes to 0x{}, not the checkpointed {:?}",
+            args.block,
+            hex::encode(hash),
+            checkpoint.block_hash
+        )));
+    }
+    params.meta.set("checkpointed", checkpoint.source.clone());
+    params
+        .meta
+        .set("checkpoint_signer", format!("0x{}", hex::encode(signer)));
+    Ok(())
+}
@@ -0,0 +1,213 @@
+//! Proofs of large tries from providers that cap their responses. A
+//! multi-key `eth_getProof` whose response is refused as too large is split
+//! into smaller requests and the proofs reassembled, and a proof cut short is
+//! completed node by node with lookups by hash, where the provider serves
+//! them (`debug_dbGet`). Every completed node is checked against the hash its
+//! parent holds, so nothing fetched this way is trusted more than the proof.
+
+use web3::types::{Bytes, Proof, H160, H256, U256};
+
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::source::EthDataSource;
+use crate::trie;
+
+/// Nodes a proof is completed with at most, beyond which the trie is deeper
+/// than any key path.
+const MAX_COMPLETED_NODES: usize = 64;
+
+/// Whether `error` is a provider refusing a response for its size, rather
+/// than the request itself.
+pub(crate) fn is_size_cap(error: &Error) -> bool {
+    let Error::Rpc(e) = error else {
+        return false;
+    };
+    let message = match e {
+        web3::Error::Rpc(rpc) => rpc.message.to_lowercase(),
+        web3::Error::Transport(web3::error::TransportError::Code(code)) => return *code == 413,
+        e => e.to_string().to_lowercase(),
+    };
+    [
+        "too large",
+        "too big",
+        "size exceeded",
+        "size limit",
+        "exceeds the limit",
+        "response limit",
+    ]
+    .iter()
+    .any(|hint| message.contains(hint))
+}
+
+/// The `eth_getProof` response for `account` and `keys` at `block`. When the
+/// provider refuses it for its size, the keys are split in half and
+/// requested separately, down to one key per request, and the storage proofs
+/// joined under the account proof, which every part must agree on.
+pub async fn get_proof(
+    source: &dyn EthDataSource,
+    account: H160,
+    keys: Vec<U256>,
+    block: u64,
+) -> Result<Option<Proof>> {
+    let mut parts = vec![keys];
+    let mut proofs: Vec<Proof> = Vec::new();
+    while let Some(keys) = parts.pop() {
+        let proof = match source.get_proof(account, keys.clone(), block).await {
+            Ok(Some(proof)) => proof,
+            Ok(None) => return Ok(None),
+            Err(e) if is_size_cap(&e) && keys.len() > 1 => {
+                let (first, second) = keys.split_at(keys.len() / 2);
+                log::info!(
+                    "Note: the provider refused the proof of {} key(s) for its size; requesting \
+                     {} and {} separately",
+                    keys.len(),
+                    first.len(),
+                    second.len()
+                );
+                parts.push(second.to_vec());
+                parts.push(first.to_vec());
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        proofs.push(proof);
+    }
+
+    let mut proofs = proofs.into_iter();
+    let Some(mut joined) = proofs.next() else {
+        return Ok(None);
+    };
+    for proof in proofs {
+        let agrees = proof.balance == joined.balance
+            && proof.nonce == joined.nonce
+            && proof.code_hash == joined.code_hash
+            && proof.storage_hash == joined.storage_hash
+            && proof.account_proof == joined.account_proof;
+        if !agrees {
+            return Err(Error::Verification(format!(
+                "the split proofs of {:?} at block {} disagree on the account; the provider \
+                 served them from different states",
+                account, block
+            )));
+        }
+        joined.storage_proof.extend(proof.storage_proof);
+    }
+    Ok(Some(joined))
+}
+
+/// Completes `nodes`, a proof from `root` along `nibbles` that stops short
+/// or ends in a truncated node, with the missing nodes looked up by hash.
+/// Returns the number of nodes added, 0 when the proof was whole, empty or
+/// the source cannot look nodes up, leaving the proof to fail verification.
+/// A node that does not hash to its reference or does not decode cannot be
+/// completed and is an error.
+async fn complete_nodes(
+    source: &dyn EthDataSource,
+    what: &str,
+    root: &[u8],
+    nibbles: &[u8],
+    nodes: &mut Vec<Bytes>,
+) -> Result<usize> {
+    // An empty proof is the provider's answer, not a cut-off one.
+    if nodes.is_empty() {
+        return Ok(0);
+    }
+    let mut added = 0;
+    loop {
+        let flat: Vec<Vec<u8>> = nodes.iter().map(|node| node.0.clone()).collect();
+        let broken = match trie::walk_proof(root, nibbles, &flat) {
+            Ok(_) => return Ok(added),
+            Err(broken) => broken,
+        };
+        let hash = match broken.kind {
+            trie::Break::Missing { hash } => hash,
+            // The node is cut off: drop it and what follows, and look it up
+            // like a missing one.
+            trie::Break::Truncated { .. } => {
+                nodes.truncate(broken.node);
+                continue;
+            }
+            trie::Break::Mismatch | trie::Break::Invalid(_) => {
+                return Err(Error::Verification(format!(
+                    "the {} is broken: {}",
+                    what,
+                    broken.describe(flat.len())
+                )))
+            }
+        };
+        if added == MAX_COMPLETED_NODES {
+            return Ok(added);
+        }
+        let node = match source.get_node(H256(hash)).await {
+            Ok(Some(node)) => node,
+            Ok(None) => return Ok(added),
+            Err(Error::Unsupported(reason)) => {
+                log::warn!(
+                    "the {} stops short and cannot be completed: {}",
+                    what,
+                    reason
+                );
+                return Ok(added);
+            }
+            Err(e) => return Err(e),
+        };
+        if keccak256(&node.0) != hash {
+            return Err(Error::Verification(format!(
+                "the node the provider returned for {:#x} does not hash to it",
+                H256(hash)
+            )));
+        }
+        nodes.push(node);
+        added += 1;
+    }
+}
+
+/// Completes the account proof of `proof` from `state_root` and each of its
+/// storage proofs from the storage root it reports, wherever the provider
+/// cut one short, printing how many nodes each needed.
+pub async fn complete(
+    source: &dyn EthDataSource,
+    state_root: H256,
+    account: H160,
+    proof: &mut Proof,
+) -> Result<()> {
+    let what = format!("account proof of {:?}", account);
+    let added = complete_nodes(
+        source,
+        &what,
+        state_root.as_bytes(),
+        &trie::key_nibbles(account.as_bytes()),
+        &mut proof.account_proof,
+    )
+    .await?;
+    if added > 0 {
+        log::info!("Note: completed the {} with {} node(s)", what, added);
+    }
+    complete_storage(source, proof).await
+}
+
+/// Completes each storage proof of `proof` from the storage root it
+/// reports, for an account proof already checked.
+pub async fn complete_storage(source: &dyn EthDataSource, proof: &mut Proof) -> Result<()> {
+    let storage_root = match proof.storage_hash {
+        root if root.is_zero() => H256(trie::EMPTY_TRIE_ROOT),
+        root => root,
+    };
+    for storage in &mut proof.storage_proof {
+        let mut slot = H256::zero();
+        storage.key.to_big_endian(slot.as_bytes_mut());
+        let what = format!("storage proof of slot {:?}", slot);
+        let added = complete_nodes(
+            source,
+            &what,
+            storage_root.as_bytes(),
+            &trie::key_nibbles(slot.as_bytes()),
+            &mut storage.proof,
+        )
+        .await?;
+        if added > 0 {
+            log::info!("Note: completed the {} with {} node(s)", what, added);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,1396 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::builder::RangedU64ValueParser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+#[cfg(feature = "cli")]
+use clap_complete::Shell;
+use web3::types::{H160, H256, U256};
+
+use crate::compression::{Compression, Compressor};
+use crate::encoding::{ByteEncoding, HeaderPadding, NumericEncoding, ValueForm};
+use crate::encryption::Recipient;
+use crate::header::Layout;
+use crate::naming::Naming;
+use crate::options::{
+    parse_h160, parse_h256, BalanceAttestationOptions, BeaconProxyOptions, BlockRange, BlockRef,
+    BloomOptions, BundleOptions, ChainTarget, CreationOptions, DeploymentOptions, ExportSmtOptions,
+    GenOptions, MultiproofOptions, NonceOptions, ReceiptOptions, RootMode, ValueRange,
+};
+use crate::progress::ProgressFormat;
+use crate::proof_order::ProofOrder;
+use crate::pubkey::PublicKey;
+use crate::rpc::TransportOptions;
+pub use crate::witness::OutputFormat;
+
+#[derive(Parser)]
+#[command(
+    name = "noir-mip",
+    version,
+    about = "Generate Noir MPT inclusion proof parameters"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    /// Most JSON-RPC requests per second, across all concurrent workers
+    #[arg(long, global = true, env = "RPC_RATE_LIMIT", value_parser = parse_rate)]
+    pub rate_limit: Option<f64>,
+    /// Requests that may go out back to back under --rate-limit [default: the rate, rounded up]
+    #[arg(long, global = true, requires = "rate_limit")]
+    pub rate_burst: Option<u32>,
+    /// Fail a JSON-RPC or beacon API call that takes longer than this,
+    /// e.g. 30s, 500ms or 2m
+    #[arg(long, global = true, env = "RPC_TIMEOUT", value_name = "DURATION", value_parser = parse_timeout)]
+    pub rpc_timeout: Option<Duration>,
+    /// Fail any call still running this long after the run started
+    #[arg(long, global = true, env = "TOTAL_TIMEOUT", value_name = "DURATION", value_parser = parse_timeout)]
+    pub total_timeout: Option<Duration>,
+    /// Header sent with every JSON-RPC request, e.g. "Authorization: Bearer ..." (repeatable)
+    #[arg(long = "header", global = true, env = "RPC_HEADER", value_name = "NAME: VALUE", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+    /// Proxy for every connection, replacing HTTPS_PROXY, HTTP_PROXY and ALL_PROXY
+    #[arg(long, global = true, value_name = "URL")]
+    pub proxy: Option<String>,
+    /// PEM certificate to trust on top of the system's roots, for gateways
+    /// with a private CA (repeatable)
+    #[arg(long, global = true, value_name = "PEM")]
+    pub ca_cert: Vec<PathBuf>,
+    /// PEM certificate to present for mutual TLS
+    #[arg(long, global = true, value_name = "PEM", requires = "client_key")]
+    pub client_cert: Option<PathBuf>,
+    /// PKCS#8 PEM key of --client-cert
+    #[arg(long, global = true, value_name = "PEM", requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+    /// TOML file of the values `${NAME}` in provider URLs takes when the
+    /// environment lacks NAME [default: secrets.toml next to the config file]
+    #[arg(long, global = true, env = "NOIR_MIP_SECRETS")]
+    pub secrets: Option<PathBuf>,
+    /// Config file holding named targets [default: ~/.config/noir-mip/config.toml]
+    #[arg(long, global = true, env = "NOIR_MIP_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Compress the written parameter files, proof cache entries and
+    /// witness store objects; compressed files are detected when read
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        env = "NOIR_MIP_COMPRESSION",
+        default_value_t = Compression::None
+    )]
+    pub compression: Compression,
+    /// Compression level: 1-9 for gzip [default: 6], 1-19 for zstd [default: 3]
+    #[arg(long, global = true)]
+    pub compression_level: Option<u32>,
+    /// Progress events to write to stdout while generating, for
+    /// schedulers watching long runs
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        env = "NOIR_MIP_PROGRESS",
+        default_value_t = ProgressFormat::None
+    )]
+    pub progress: ProgressFormat,
+    /// How a failure is written to stderr
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        env = "NOIR_MIP_ERROR_FORMAT",
+        default_value_t = ErrorFormat::Text
+    )]
+    pub error_format: ErrorFormat,
+}
+
+impl Cli {
+    /// How connections are made, from the header, proxy and TLS options.
+    /// The compressor `--compression` and `--compression-level` select.
+    pub fn compressor(&self) -> crate::error::Result<Compressor> {
+        Compressor::new(self.compression, self.compression_level)
+    }
+
+    pub fn transport_options(&self) -> TransportOptions {
+        TransportOptions {
+            headers: self.headers.clone(),
+            proxy: self.proxy.clone(),
+            ca_certs: self.ca_cert.clone(),
+            client_cert: self.client_cert.clone().zip(self.client_key.clone()),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print the prover parameters (Prover.toml)
+    #[command(name = "gen_prove_params", alias = "gen-prove-params")]
+    GenProveParams(GenArgs),
+    /// Print the verifier parameters (Verifier.toml)
+    #[command(name = "gen_verify_params", alias = "gen-verify-params")]
+    GenVerifyParams(GenArgs),
+    /// Print one prover bundle covering the same account and slot on
+    /// several chains
+    #[command(name = "gen_bundle", alias = "gen-bundle")]
+    GenBundle(BundleOptions),
+    /// Print prover parameters proving several slots of one account with a
+    /// deduplicated storage multiproof
+    #[command(name = "gen_multiproof", alias = "gen-multiproof")]
+    GenMultiproof(MultiproofOptions),
+    /// Print the parameters proving the block an account first exists in:
+    /// its exclusion proof at the block before and its inclusion proof there
+    #[command(name = "gen_creation_params", alias = "gen-creation-params")]
+    GenCreationParams(CreationOptions),
+    /// Print the parameters attesting that an account held at least a
+    /// minimum balance at a block, a proof of funds
+    #[command(name = "gen_balance_attestation", alias = "gen-balance-attestation")]
+    GenBalanceAttestation(BalanceAttestationOptions),
+    /// Print the account proof of a deployer with its nonce and the address
+    /// of its next CREATE (and a CREATE2) deployment
+    #[command(name = "gen_deployment_params", alias = "gen-deployment-params")]
+    GenDeploymentParams(DeploymentOptions),
+    /// Print the parameters proving an EOA's nonce at a block, trimmed to
+    /// the fields a replay-protection circuit reads
+    #[command(name = "gen_nonce_params", alias = "gen-nonce-params")]
+    GenNonceParams(NonceOptions),
+    /// Print the chained proofs of an EIP-1967 beacon proxy: its beacon
+    /// slot, the beacon's implementation slot and the implementation account
+    #[command(name = "gen_beacon_proxy_params", alias = "gen-beacon-proxy-params")]
+    GenBeaconProxyParams(BeaconProxyOptions),
+    /// Print prover parameters proving one transaction's receipt against the
+    /// block header
+    #[command(name = "gen_receipt_params", alias = "gen-receipt-params")]
+    GenReceiptParams(ReceiptOptions),
+    /// Print the block header's logs bloom with the bits an event and an
+    /// address set, to check before proving a receipt
+    #[command(name = "gen_bloom_params", alias = "gen-bloom-params")]
+    GenBloomParams(BloomOptions),
+    /// List the occupied storage slots of a contract, optionally proving each
+    DumpSlots(DumpSlotsArgs),
+    /// List the holders an ERC-20 token's Transfer logs name over a block
+    /// range, optionally proving each one's balance
+    DumpHolders(DumpHoldersArgs),
+    /// Prove a batch of accounts and slots and write every node of their
+    /// proofs as one partial-trie bundle, for building proofs offline
+    ExportSmt(ExportSmtOptions),
+    /// Check what a provider serves: chain id, latency, eth_getProof, how
+    /// far back its state goes and batch requests
+    Probe(ProbeArgs),
+    /// Sample proof depths and node sizes over recent blocks and recommend
+    /// the circuit's depth constants
+    Advise(AdviseArgs),
+    /// Read a storage slot over a block range, without proofs, and print
+    /// when it changed and which blocks to take a witness at
+    AnalyzeSlot(AnalyzeSlotArgs),
+    /// Manage named targets (an account and slot) stored in the config file
+    #[command(subcommand)]
+    Target(TargetCommand),
+    /// List, print and prune the witnesses saved by --store
+    #[command(subcommand)]
+    Store(StoreCommand),
+    /// Inspect and prune a --proof-cache directory
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Record, sign and check the trusted block hashes of --only-checkpointed
+    #[command(subcommand)]
+    Checkpoint(CheckpointCommand),
+    /// Print the witness hash of parameter files, checking any recorded one
+    Hash(HashArgs),
+    /// Check the signature embedded in a generated parameter file
+    VerifySignature(VerifySignatureArgs),
+    /// Decrypt a file written with --encrypt-to
+    Decrypt(DecryptArgs),
+    /// Generate and check the parameters of a recorded mainnet block offline,
+    /// then run the circuit on them if nargo is installed
+    Demo(DemoArgs),
+    /// Compare the size constants of a Noir circuit with the generator's
+    CheckConstants(CheckConstantsArgs),
+    /// Decode an RLP block header and print its fields with their offsets
+    DecodeHeader(DecodeHeaderArgs),
+    /// Walk the proofs in a Prover.toml (or a hex flattened proof) node by node
+    ExplainProof(ExplainProofArgs),
+    /// Decode the public inputs of a bb proof back into the block, account,
+    /// slot and value it proves, checking them against the chain
+    InspectProof(InspectProofArgs),
+    /// Print the version, commit, features, chains, forks and constants of
+    /// this build
+    Info(InfoArgs),
+    /// Upgrade a parameter file written by an older version to the current layout
+    Migrate(MigrateArgs),
+    /// Drop or zero the keys, values and proofs of a parameter file, so a
+    /// failing witness can be shared without the state it proves
+    Redact(RedactArgs),
+    /// Regenerate a Prover.toml or Verifier.toml at another block, with the
+    /// account, slot and layout options it was written with
+    Refresh(RefreshArgs),
+    /// Explore interactively: pick a block, account and slot line by line
+    /// and emit parameter files, reusing the fetched data between them
+    Repl(ReplArgs),
+    /// Print the byte layout of the parameters: field order, types, padding
+    /// and the constants they follow from
+    Layout(LayoutArgs),
+    /// Print a shell completion script
+    #[cfg(feature = "cli")]
+    Completions(CompletionsArgs),
+    /// Print the man page, or write one page per subcommand to a directory
+    Man(ManArgs),
+    /// Browse the proofs node by node in the terminal
+    #[cfg(feature = "tui")]
+    Tui(TuiArgs),
+    /// Run the trie and RLP vectors of an ethereum/tests checkout
+    #[cfg(feature = "conformance")]
+    Conformance(ConformanceArgs),
+    /// Cross-check the header encoding and proof verifier against reference
+    /// implementations on real blocks
+    #[cfg(feature = "differential")]
+    Differential(DifferentialArgs),
+}
+
+/// How `layout` prints the contract.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LayoutFormat {
+    /// One aligned line per field
+    #[default]
+    Text,
+    /// A JSON object, for tooling that generates circuit signatures
+    Json,
+}
+
+/// How the error ending a failed run is written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// The error message
+    #[default]
+    Text,
+    /// One JSON object with the error's category, exit code and message
+    Json,
+}
+
+/// The arguments of `gen_prove_params` and `gen_verify_params`: the
+/// options of the run, then where its witness goes and what follows it.
+#[derive(Args, Clone, Default)]
+pub struct GenArgs {
+    #[command(flatten)]
+    pub options: GenOptions,
+    /// Target account address
+    #[arg(
+        long = "account",
+        env = "TARGET_ACCOUNT",
+        value_name = "ACCOUNT",
+        value_parser = parse_h160,
+        required_unless_present_any = ["target", "stdin", "pubkey"]
+    )]
+    pub account_arg: Option<H160>,
+    /// Prove the account and slot registered under this name with
+    /// `noir-mip target add`
+    #[arg(long)]
+    pub target: Option<String>,
+    /// Print the parameters to a terminal in full, rather than a table of
+    /// their sizes and digests
+    #[arg(long)]
+    pub print_full: bool,
+    /// Read further options from stdin, as a JSON object such as
+    /// {"block": 12965000, "slot": "0x0", "keccak-blocks": true}
+    #[arg(long)]
+    pub stdin: bool,
+    /// Fetch and check everything, then print the parameter shapes instead
+    /// of the parameters
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Write the parameters to this file instead of stdout
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+    /// With gen_prove_params, also write the verifier parameters to this
+    /// file, taken from the same fetch as the prover parameters
+    #[arg(long)]
+    pub verify_out: Option<PathBuf>,
+    /// Circuit template --emit-monomorphized-nr rewrites
+    #[arg(
+        long,
+        default_value = "src/main.nr",
+        requires = "emit_monomorphized_nr"
+    )]
+    pub nr_template: PathBuf,
+    /// Also write the header RLP and proof nodes without their padding, as
+    /// `*_raw` fields of `<out>.raw.json`, for debugging
+    #[arg(long, requires = "out")]
+    pub emit_raw: bool,
+    /// Encrypt the written files to this age X25519 recipient (`age1...`);
+    /// repeatable. Read them back with `noir-mip decrypt` or `age -d`
+    #[arg(long, value_name = "RECIPIENT", requires = "out", value_parser = parse_recipient)]
+    pub encrypt_to: Vec<Recipient>,
+    /// Format of the written parameters; repeat to write several from the
+    /// same witness, the first to --out and the others beside it under
+    /// their own extensions
+    #[arg(long, value_enum, default_value = "toml")]
+    pub format: Vec<OutputFormat>,
+    /// Group the fields into header, account and storage tables, for a
+    /// circuit taking them as struct inputs
+    #[arg(long)]
+    pub namespaced: bool,
+    /// Write a JSON report of the run (timings, provider statistics, proof
+    /// sizes) to this file
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+    /// POST the JSON report of the run to this URL when it completes or
+    /// fails
+    #[arg(long)]
+    pub notify_url: Option<String>,
+    /// Shell command to run once the parameters are written, such as
+    /// "nargo prove --dir {out_dir}"; {out}, {out_dir}, {block}, {account},
+    /// {slot} and {witness_hash} are filled in
+    #[arg(long, value_name = "COMMAND", requires = "out")]
+    pub post_hook: Option<String>,
+}
+
+impl GenArgs {
+    /// The formats to write, the first to `--out`; TOML alone unless
+    /// `--format` was given.
+    pub fn formats(&self) -> &[OutputFormat] {
+        match self.format.is_empty() {
+            true => &[OutputFormat::Toml],
+            false => &self.format,
+        }
+    }
+
+    /// Whether one of the formats to write is `format`.
+    pub fn writes(&self, format: OutputFormat) -> bool {
+        self.formats().contains(&format)
+    }
+}
+
+#[derive(Args)]
+pub struct DumpSlotsArgs {
+    /// Mainnet RPC endpoint; it must serve debug_storageRangeAt or
+    /// parity_listStorageKeys
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: String,
+    /// Block number
+    #[arg(long, env = "BLOCK_NUMBER")]
+    pub block: u64,
+    /// Contract whose storage is enumerated
+    #[arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160)]
+    pub account: H160,
+    /// Stop after this many slots
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Write the prover parameters of every slot to `<slot>.toml` in this
+    /// directory
+    #[arg(long)]
+    pub prove_dir: Option<PathBuf>,
+    /// How the files in the prove directory are named: after the slot, or
+    /// after a hash of what each witness was generated from
+    #[arg(long, value_enum, default_value_t = Naming::Key, requires = "prove_dir")]
+    pub naming: Naming,
+    /// Skip the slots an earlier, interrupted run already wrote to the prove
+    /// directory, as recorded in its journal
+    #[arg(long, requires = "prove_dir")]
+    pub resume: bool,
+    /// Slots whose proofs are fetched together, with one eth_getProof
+    /// carrying all of their keys; lower it for providers that cap the keys
+    /// of a call
+    #[arg(long, default_value_t = 50, requires = "prove_dir",
+          value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub batch_size: usize,
+    /// Also emit per-node RLP item counts, offsets, lengths and path indices
+    #[arg(long)]
+    pub rlp_hints: bool,
+    /// Split proofs deeper than the circuit allows into two chained segments
+    #[arg(long)]
+    pub split_deep_proofs: bool,
+    /// Layout of numeric outputs (storage key and value)
+    #[arg(long, value_enum, default_value_t = NumericEncoding::BeBytes)]
+    pub numeric_encoding: NumericEncoding,
+    /// Reuse proofs stored in this directory by state root, account and slot,
+    /// and store fetched ones there
+    #[arg(long)]
+    pub proof_cache: Option<PathBuf>,
+    /// How the proof cache entries and parameter files are compressed
+    #[arg(skip)]
+    pub compressor: Compressor,
+    /// Write a JSON report of the run to this file
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+    /// POST the JSON report of the run to this URL when it completes or
+    /// fails
+    #[arg(long)]
+    pub notify_url: Option<String>,
+    /// Shell command to run once each slot's parameters are written;
+    /// {out}, {out_dir}, {block}, {account}, {slot} and {witness_hash} are
+    /// filled in
+    #[arg(long, value_name = "COMMAND", requires = "prove_dir")]
+    pub post_hook: Option<String>,
+    /// Shell command to run once every slot is written; {out_dir},
+    /// {block}, {account} and {count} are filled in
+    #[arg(long, value_name = "COMMAND", requires = "prove_dir")]
+    pub post_batch_hook: Option<String>,
+}
+
+impl DumpSlotsArgs {
+    /// Generator arguments proving one of the enumerated slots.
+    pub fn slot_args(&self, slot: H256) -> GenOptions {
+        GenOptions {
+            rlp_hints: self.rlp_hints,
+            split_deep_proofs: self.split_deep_proofs,
+            numeric_encoding: self.numeric_encoding,
+            proof_cache: self.proof_cache.clone(),
+            compressor: self.compressor,
+            ..GenOptions::new(&self.rpc_url, self.block, self.account, Some(slot))
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct DumpHoldersArgs {
+    /// Mainnet RPC endpoint; it must serve eth_getLogs over --log-range
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: String,
+    /// Block the balances are proven at
+    #[arg(long, env = "BLOCK_NUMBER")]
+    pub block: u64,
+    /// ERC-20 token whose Transfer logs are scanned and whose balances are
+    /// proven
+    #[arg(long, value_parser = parse_h160)]
+    pub token: H160,
+    /// Blocks whose Transfer logs are scanned, as LO..HI (both included)
+    #[arg(long, value_name = "LO..HI", value_parser = parse_block_range)]
+    pub log_range: BlockRange,
+    /// Slot the token's balance mapping is declared at, decimal or hex
+    #[arg(long, value_name = "INDEX", value_parser = parse_u256)]
+    pub balance_slot: U256,
+    /// Blocks each eth_getLogs call spans at most; halved when the provider
+    /// refuses a call for its size
+    #[arg(long, default_value_t = 2000,
+          value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    pub log_chunk: u64,
+    /// Stop after this many holders
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Write the prover parameters of every holder's balance to
+    /// `<holder>.toml` in this directory
+    #[arg(long)]
+    pub prove_dir: Option<PathBuf>,
+    /// How the files in the prove directory are named: after the holder,
+    /// or after a hash of what each witness was generated from
+    #[arg(long, value_enum, default_value_t = Naming::Key, requires = "prove_dir")]
+    pub naming: Naming,
+    /// Skip the holders an earlier, interrupted run already wrote to the
+    /// prove directory, as recorded in its journal
+    #[arg(long, requires = "prove_dir")]
+    pub resume: bool,
+    /// Holders whose proofs are fetched together, with one eth_getProof
+    /// carrying all of their keys
+    #[arg(long, default_value_t = 50, requires = "prove_dir",
+          value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub batch_size: usize,
+    /// Also emit per-node RLP item counts, offsets, lengths and path indices
+    #[arg(long)]
+    pub rlp_hints: bool,
+    /// Split proofs deeper than the circuit allows into two chained segments
+    #[arg(long)]
+    pub split_deep_proofs: bool,
+    /// Layout of numeric outputs (storage key and value)
+    #[arg(long, value_enum, default_value_t = NumericEncoding::BeBytes)]
+    pub numeric_encoding: NumericEncoding,
+    /// Reuse proofs stored in this directory by state root, account and slot,
+    /// and store fetched ones there
+    #[arg(long)]
+    pub proof_cache: Option<PathBuf>,
+    /// How the proof cache entries and parameter files are compressed
+    #[arg(skip)]
+    pub compressor: Compressor,
+    /// Write a JSON report of the run to this file
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+    /// POST the JSON report of the run to this URL when it completes or
+    /// fails
+    #[arg(long)]
+    pub notify_url: Option<String>,
+    /// Shell command to run once each holder's parameters are written;
+    /// {out}, {out_dir}, {block}, {account}, {slot} and {witness_hash} are
+    /// filled in
+    #[arg(long, value_name = "COMMAND", requires = "prove_dir")]
+    pub post_hook: Option<String>,
+    /// Shell command to run once every holder is written; {out_dir},
+    /// {block}, {account} and {count} are filled in
+    #[arg(long, value_name = "COMMAND", requires = "prove_dir")]
+    pub post_batch_hook: Option<String>,
+}
+
+impl DumpHoldersArgs {
+    /// The `dump-slots` arguments proving the token's balance slots the
+    /// same way.
+    pub fn slots_args(&self) -> DumpSlotsArgs {
+        DumpSlotsArgs {
+            rpc_url: self.rpc_url.clone(),
+            block: self.block,
+            account: self.token,
+            limit: self.limit,
+            prove_dir: self.prove_dir.clone(),
+            naming: self.naming,
+            resume: self.resume,
+            batch_size: self.batch_size,
+            rlp_hints: self.rlp_hints,
+            split_deep_proofs: self.split_deep_proofs,
+            numeric_encoding: self.numeric_encoding,
+            proof_cache: self.proof_cache.clone(),
+            compressor: self.compressor,
+            report: self.report.clone(),
+            notify_url: self.notify_url.clone(),
+            post_hook: self.post_hook.clone(),
+            post_batch_hook: self.post_batch_hook.clone(),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ProbeArgs {
+    /// RPC endpoint to check
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: String,
+    /// Fail unless the provider can prove this block
+    #[arg(long)]
+    pub block: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct AdviseArgs {
+    /// Mainnet RPC endpoint; sampling older blocks needs an archive node
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: String,
+    /// Contract (or account) the circuit proves
+    #[arg(long, value_parser = parse_h160)]
+    pub account: H160,
+    /// Storage slot the circuit proves (repeatable)
+    #[arg(long, value_parser = parse_h256)]
+    pub slot: Vec<H256>,
+    /// Slot index of a Solidity mapping whose entries the circuit proves;
+    /// the entries of every --holder are sampled
+    #[arg(long, value_name = "INDEX")]
+    pub mapping: Option<String>,
+    /// Mapping key to sample, such as a holder's address (repeatable)
+    #[arg(long = "holder", value_name = "KEY", requires = "mapping")]
+    pub holders: Vec<String>,
+    /// File of mapping keys to sample, one per line
+    #[arg(long, value_name = "FILE", requires = "mapping")]
+    pub holders_file: Option<PathBuf>,
+    /// Latest block sampled: a number, latest, safe or finalized
+    #[arg(long, default_value = "latest", value_parser = parse_block_ref)]
+    pub block: BlockRef,
+    /// Blocks sampled
+    #[arg(long, default_value_t = 8,
+          value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub blocks: usize,
+    /// Blocks before --block the samples are spread over; full nodes keep
+    /// the state of the last 128
+    #[arg(long, default_value_t = 120)]
+    pub span: u64,
+    /// Other accounts proven at each block, so the account depth reflects
+    /// the state trie rather than one account's path
+    #[arg(long, default_value_t = 8)]
+    pub random_accounts: usize,
+    /// Absent slots proven at each block when no --slot or --mapping is
+    /// given, so the storage depth reflects the contract's trie
+    #[arg(long, default_value_t = 16)]
+    pub random_slots: usize,
+    /// Levels added to the deepest proof seen in the recommendation
+    #[arg(long, default_value_t = 1)]
+    pub headroom: usize,
+}
+
+#[derive(Args)]
+pub struct AnalyzeSlotArgs {
+    /// Mainnet RPC endpoint; blocks older than the last 128 need an archive
+    /// node
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: String,
+    /// Account whose storage is read
+    #[arg(long, value_parser = parse_h160)]
+    pub account: H160,
+    /// Storage slot to read
+    #[arg(long, value_parser = parse_h256, required_unless_present = "slot_mapping")]
+    pub slot: Option<H256>,
+    /// Slot of `KEY`'s entry in the Solidity mapping declared at slot `INDEX`
+    #[arg(long, num_args = 2, value_names = ["INDEX", "KEY"], conflicts_with = "slot")]
+    pub slot_mapping: Option<Vec<String>>,
+    /// Blocks to read, as LO..HI (both included)
+    #[arg(long, value_name = "LO..HI", value_parser = parse_block_range)]
+    pub block_range: BlockRange,
+    /// Blocks between two reads; a change is placed within the gap
+    #[arg(long, default_value_t = 1,
+          value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    pub stride: u64,
+    /// Bisect each change down to the block it happened at
+    #[arg(long)]
+    pub exact: bool,
+    /// Block by which the circuit's statement has to hold; the last change
+    /// before it is suggested
+    #[arg(long)]
+    pub deadline: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct ReplArgs {
+    /// Mainnet RPC endpoint
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: String,
+    /// Block to start at: a number, latest, safe or finalized
+    #[arg(long, env = "BLOCK_NUMBER", value_parser = parse_block_ref)]
+    pub block: Option<BlockRef>,
+    /// Account to start with
+    #[arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160)]
+    pub account: Option<H160>,
+    /// Storage slot to start with
+    #[arg(long, env = "STORAGE_SLOT", value_parser = parse_h256)]
+    pub slot: Option<H256>,
+}
+
+#[derive(Subcommand)]
+pub enum TargetCommand {
+    /// Register (or replace) a target
+    Add(TargetAddArgs),
+    /// Delete a target
+    Remove(TargetNameArgs),
+    /// Print every target
+    List,
+}
+
+#[derive(Args)]
+pub struct TargetAddArgs {
+    /// Name to pass to --target
+    pub name: String,
+    /// Account address
+    #[arg(long, value_parser = parse_h160)]
+    pub account: H160,
+    /// Storage slot
+    #[arg(long, value_parser = parse_h256, conflicts_with = "slot_mapping")]
+    pub slot: Option<H256>,
+    /// Slot of `KEY`'s entry in the Solidity mapping declared at slot `INDEX`
+    #[arg(long, num_args = 2, value_names = ["INDEX", "KEY"])]
+    pub slot_mapping: Option<Vec<String>>,
+    /// Provider to prove the target against, stored as given; `${NAME}` is
+    /// filled in from the environment or the secrets file on each run
+    /// (repeatable)
+    #[arg(long)]
+    pub rpc_url: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct TargetNameArgs {
+    pub name: String,
+}
+
+#[derive(Subcommand)]
+pub enum CheckpointCommand {
+    /// Record (or replace) the trusted hash of a block
+    Add(CheckpointAddArgs),
+    /// Sign the registry with an ed25519 key
+    Sign(CheckpointSignArgs),
+    /// Check the registry's signature
+    Verify(CheckpointVerifyArgs),
+    /// Print every checkpoint
+    List(CheckpointFileArgs),
+}
+
+#[derive(Args)]
+pub struct CheckpointFileArgs {
+    /// Checkpoint registry
+    #[arg(long, env = "NOIR_MIP_CHECKPOINTS", default_value = "checkpoints.json")]
+    pub file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CheckpointAddArgs {
+    #[command(flatten)]
+    pub file: CheckpointFileArgs,
+    /// Block number
+    #[arg(long)]
+    pub block: u64,
+    /// Block hash, vetted by hand; with --beacon-api it must match the
+    /// verified hash
+    #[arg(long, value_parser = parse_h256, required_unless_present = "beacon_api")]
+    pub hash: Option<H256>,
+    /// Chain id [default: the chain of --rpc-url]
+    #[arg(long, required_unless_present = "rpc_url")]
+    pub chain_id: Option<u64>,
+    /// RPC endpoint the light client walks the block hashes of
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: Option<String>,
+    /// Verify the hash with the light client against this beacon API
+    #[arg(long, requires_all = ["lc_checkpoint", "rpc_url"])]
+    pub beacon_api: Option<String>,
+    /// Trusted beacon block root the light client bootstraps from
+    #[arg(long, value_parser = parse_h256, requires = "beacon_api")]
+    pub lc_checkpoint: Option<H256>,
+    /// Maximum number of execution blocks between the block and the light
+    /// client's finalized block
+    #[arg(long, default_value_t = 8192)]
+    pub lc_max_ancestry: u64,
+    /// Free-form note kept with the checkpoint, such as who vetted it
+    #[arg(long, default_value = "")]
+    pub note: String,
+}
+
+#[derive(Args)]
+pub struct CheckpointSignArgs {
+    #[command(flatten)]
+    pub file: CheckpointFileArgs,
+    /// PEM ed25519 private key
+    #[arg(long)]
+    pub sign_key: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CheckpointVerifyArgs {
+    #[command(flatten)]
+    pub file: CheckpointFileArgs,
+    /// Ed25519 public key, hex or PEM file, that must have signed the
+    /// registry
+    #[arg(long)]
+    pub signer: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum StoreCommand {
+    /// Print the stored witnesses, one per line
+    Ls(StoreFilterArgs),
+    /// Print a stored witness, by its hash or by what it proves
+    Get(StoreGetArgs),
+    /// Delete expired refs and the witnesses no ref points to
+    Gc(StoreGcArgs),
+}
+
+#[derive(Args)]
+pub struct StoreFilterArgs {
+    /// Only witnesses of this chain id
+    #[arg(long)]
+    pub chain_id: Option<u64>,
+    /// Only witnesses at this block number
+    #[arg(long)]
+    pub block: Option<u64>,
+    /// Only witnesses of this account
+    #[arg(long, value_parser = parse_h160)]
+    pub account: Option<H160>,
+    /// Only witnesses of this storage slot
+    #[arg(long, value_parser = parse_h256)]
+    pub slot: Option<H256>,
+    /// Only verifier parameters
+    #[arg(long, conflicts_with = "prover")]
+    pub verifier: bool,
+    /// Only prover parameters
+    #[arg(long)]
+    pub prover: bool,
+}
+
+#[derive(Args)]
+pub struct StoreGetArgs {
+    /// Witness hash, or a unique prefix of it
+    #[arg(required_unless_present_any = ["chain_id", "block", "account", "slot"])]
+    pub hash: Option<String>,
+    /// Otherwise the most recently stored witness matching these
+    #[command(flatten)]
+    pub filter: StoreFilterArgs,
+    /// Write the witness to this file instead of stdout
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct StoreGcArgs {
+    /// Also delete refs stored more than this many days ago
+    #[arg(long, value_name = "DAYS")]
+    pub older_than: Option<u64>,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Print the number of cached proofs and their size
+    Stats(CacheDirArgs),
+    /// Delete every cached proof
+    Clear(CacheDirArgs),
+    /// Evict the proofs the [cache] policy of the config file leaves out
+    Gc(CacheGcArgs),
+}
+
+#[derive(Args)]
+pub struct CacheDirArgs {
+    /// The --proof-cache directory
+    pub dir: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CacheGcArgs {
+    #[command(flatten)]
+    pub dir: CacheDirArgs,
+    /// Evict proofs unused for longer than this many days, instead of the
+    /// configured ttl_days
+    #[arg(long, value_name = "DAYS")]
+    pub ttl_days: Option<u64>,
+    /// Evict the least recently used proofs until the cache holds at most
+    /// this many bytes, instead of the configured max_bytes
+    #[arg(long, value_name = "BYTES")]
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct VerifySignatureArgs {
+    /// Signed Prover.toml or Verifier.toml
+    pub file: PathBuf,
+    /// Expected signer, as a hex public key or a PEM public key file
+    #[arg(long)]
+    pub public_key: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DecryptArgs {
+    /// File written with --encrypt-to
+    pub file: PathBuf,
+    /// age identity file holding the AGE-SECRET-KEY-1 key of a recipient
+    #[arg(long, short)]
+    pub identity: PathBuf,
+    /// Write the decrypted file here instead of stdout
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct HashArgs {
+    /// Generated Prover.toml or Verifier.toml files
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct DemoArgs {
+    /// Directory to write the circuit and its parameters to
+    #[arg(long, default_value = "noir-mip-demo")]
+    pub out_dir: PathBuf,
+    /// Stop after writing the parameters, even if nargo is installed
+    #[arg(long)]
+    pub no_nargo: bool,
+}
+
+#[derive(Args)]
+pub struct CheckConstantsArgs {
+    /// Noir source declaring the circuit's `global` constants
+    #[arg(long, default_value = "src/main.nr")]
+    pub nr: PathBuf,
+}
+
+#[derive(Args)]
+pub struct InfoArgs {
+    /// Print one JSON object, for orchestration systems
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct DecodeHeaderArgs {
+    /// File holding the header RLP as hex; trailing zero padding is allowed
+    pub file: PathBuf,
+    /// Field layout of the header
+    #[arg(long, value_enum, default_value_t)]
+    pub layout: Layout,
+    /// Seal items the header holds in place of `mix_hash` and `nonce`, such
+    /// as 2 for the step and signature of AuRa chains like Gnosis
+    #[arg(long, value_name = "N")]
+    pub seal_fields: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct ExplainProofArgs {
+    /// Prover.toml, or a file holding one flattened padded proof as hex
+    pub file: PathBuf,
+    /// Account address or storage slot the hex proof is for (required for
+    /// hex input)
+    #[arg(long)]
+    pub key: Option<String>,
+    /// Root the first node of the hex proof must hash to
+    #[arg(long, value_parser = parse_h256)]
+    pub root: Option<H256>,
+    /// Bytes each node is padded to
+    #[arg(long, default_value_t = 532)]
+    pub node_len: usize,
+}
+
+#[derive(Args)]
+pub struct InspectProofArgs {
+    /// The public_inputs file bb writes next to the proof: 32-byte
+    /// big-endian field elements, or a JSON array of them as hex
+    #[arg(long, required_unless_present = "proof")]
+    pub public_inputs: Option<PathBuf>,
+    /// A proof from a bb that writes the public inputs at its start, read
+    /// in place of --public-inputs
+    #[arg(long, conflicts_with = "public_inputs")]
+    pub proof: Option<PathBuf>,
+    /// Bytes of account_value the circuit declares [default: what the
+    /// public inputs file leaves for it, or 70 for --proof]
+    #[arg(long)]
+    pub account_value_bytes: Option<usize>,
+    /// Root the proofs are anchored to
+    #[arg(long, value_enum, default_value_t = RootMode::BlockHash)]
+    pub root_mode: RootMode,
+    /// The circuit proves an account without a storage slot
+    #[arg(long)]
+    pub account_only: bool,
+    /// Layout of the storage key and value
+    #[arg(long, value_enum, default_value_t = NumericEncoding::BeBytes)]
+    pub numeric_encoding: NumericEncoding,
+    /// Form of the storage value
+    #[arg(long, value_enum, default_value_t = ValueForm::Padded)]
+    pub value_form: ValueForm,
+    /// Mainnet RPC endpoint to check the decoded inputs against; without
+    /// one they are only decoded
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: Option<String>,
+    /// Block the state root belongs to, for --root-mode state-root; a block
+    /// hash names its own block
+    #[arg(long)]
+    pub block: Option<u64>,
+}
+
+impl InspectProofArgs {
+    /// The layout of the circuit's parameters.
+    pub fn layout_args(&self) -> LayoutArgs {
+        LayoutArgs {
+            verifier: false,
+            root_mode: self.root_mode,
+            account_only: self.account_only,
+            numeric_encoding: self.numeric_encoding,
+            value_form: self.value_form,
+            byte_encoding: ByteEncoding::Decimal,
+            header_parts: false,
+            header_head_bytes: None,
+            header_tail_bytes: None,
+            header_padding: HeaderPadding::Post,
+            proof_order: ProofOrder::RootFirst,
+            chain_spec: None,
+            max_inputs: None,
+            max_public_inputs: None,
+            format: LayoutFormat::Text,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct RefreshArgs {
+    /// Prover.toml or Verifier.toml to regenerate, as TOML or JSON
+    pub file: PathBuf,
+    /// Mainnet RPC endpoint; repeat (or comma-separate) to cross-check providers
+    #[arg(long, env = "MAINNET_RPC", value_delimiter = ',', required = true)]
+    pub rpc_url: Vec<String>,
+    /// Block number, or `latest`, `safe` or `finalized`
+    #[arg(
+        long = "block",
+        env = "BLOCK_NUMBER",
+        value_name = "BLOCK",
+        value_parser = parse_block_ref,
+        default_value = "latest"
+    )]
+    pub block_ref: BlockRef,
+    /// Write the regenerated witness here instead of over FILE
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+    /// Chain spec of a chain this tool does not know, as the witness was
+    /// generated with
+    #[arg(long)]
+    pub chain_spec: Option<PathBuf>,
+    /// Key to sign the regenerated witness with; needed when FILE is signed
+    #[arg(long)]
+    pub sign_key: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct MigrateArgs {
+    /// Prover.toml or Verifier.toml to upgrade
+    pub file: PathBuf,
+    /// Rewrite the file instead of printing the upgraded parameters
+    #[arg(long)]
+    pub in_place: bool,
+}
+
+#[derive(Args)]
+pub struct RedactArgs {
+    /// Parameter file to redact: TOML, JSON or a binary container
+    pub file: PathBuf,
+    /// Zero the redacted fields in place, keeping their shapes and lengths,
+    /// rather than dropping them
+    #[arg(long)]
+    pub keep_structure: bool,
+    /// Keep this field although it is redacted by default (repeatable)
+    #[arg(long, value_name = "FIELD")]
+    pub keep: Vec<String>,
+    /// Also redact this field (repeatable)
+    #[arg(long, value_name = "FIELD")]
+    pub redact: Vec<String>,
+    /// Write the redacted parameters to this file instead of stdout
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+pub struct LayoutArgs {
+    /// Describe the verifier parameters instead of the prover ones
+    #[arg(long)]
+    pub verifier: bool,
+    /// Root the proofs are anchored to
+    #[arg(long, value_enum, default_value_t = RootMode::BlockHash)]
+    pub root_mode: RootMode,
+    /// Parameters of an account proof without a storage slot
+    #[arg(long)]
+    pub account_only: bool,
+    /// Layout of the storage key and value
+    #[arg(long, value_enum, default_value_t = NumericEncoding::BeBytes)]
+    pub numeric_encoding: NumericEncoding,
+    /// Form of the storage value
+    #[arg(long, value_enum, default_value_t = ValueForm::Padded)]
+    pub value_form: ValueForm,
+    /// How byte arrays are written
+    #[arg(long, value_enum, default_value_t = ByteEncoding::Decimal)]
+    pub byte_encoding: ByteEncoding,
+    /// Include the padded header parts before and after the state root
+    #[arg(long)]
+    pub header_parts: bool,
+    /// Length block_header_rlp_head is padded to [default: 91]
+    #[arg(long, requires = "header_parts")]
+    pub header_head_bytes: Option<usize>,
+    /// Length block_header_rlp_tail is padded to [default: the padded
+    /// header length less 123]
+    #[arg(long, requires = "header_parts")]
+    pub header_tail_bytes: Option<usize>,
+    /// Where block_header_rlp is zero-padded
+    #[arg(long, value_enum, default_value_t = HeaderPadding::Post)]
+    pub header_padding: HeaderPadding,
+    /// Order the nodes of the proofs are emitted in
+    #[arg(long, value_enum, default_value_t = ProofOrder::RootFirst)]
+    pub proof_order: ProofOrder,
+    /// Chain spec whose max_header_bytes sets the padded header length
+    #[arg(long)]
+    pub chain_spec: Option<PathBuf>,
+    /// Warn when the circuit inputs take more field elements than this
+    #[arg(long)]
+    pub max_inputs: Option<usize>,
+    /// Warn when the public inputs take more field elements than this, such
+    /// as the limit of an on-chain verifier
+    #[arg(long)]
+    pub max_public_inputs: Option<usize>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = LayoutFormat::Text)]
+    pub format: LayoutFormat,
+}
+
+#[cfg(feature = "conformance")]
+#[derive(Args)]
+pub struct ConformanceArgs {
+    /// Checkout of github.com/ethereum/tests, holding TrieTests/ and RLPTests/
+    pub tests_dir: PathBuf,
+}
+
+#[cfg(feature = "differential")]
+#[derive(Args)]
+pub struct DifferentialArgs {
+    /// RPC endpoint the blocks and proofs are fetched from
+    #[arg(long, env = "MAINNET_RPC")]
+    pub rpc_url: String,
+    /// First block of the corpus; by default the corpus ends at the latest
+    /// block
+    #[arg(long)]
+    pub from: Option<u64>,
+    /// Blocks in the corpus
+    #[arg(long, default_value_t = 10,
+          value_parser = RangedU64ValueParser::<u64>::new().range(1..))]
+    pub count: u64,
+    /// Also check the proofs of this account at every block
+    #[arg(long, value_parser = parse_h160)]
+    pub account: Option<H160>,
+    /// Storage slots of the account whose proofs are checked too
+    #[arg(long = "slot", requires = "account", value_parser = parse_u256)]
+    pub slots: Vec<U256>,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Args, Clone)]
+pub struct TuiArgs {
+    #[command(flatten)]
+    pub gen: GenArgs,
+    /// File the `e` key writes the prover parameters to
+    #[arg(long, default_value = "Prover.toml")]
+    pub export: PathBuf,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Args)]
+pub struct CompletionsArgs {
+    pub shell: Shell,
+}
+
+#[derive(Args)]
+pub struct ManArgs {
+    /// Directory to write noir-mip.1 and the subcommand pages to
+    #[arg(long)]
+    pub out_dir: Option<PathBuf>,
+}
+
+pub(crate) fn parse_chain_target(value: &str) -> Result<ChainTarget, String> {
+    let (chain_id, rest) = value
+        .split_once('=')
+        .ok_or("expected CHAIN_ID=RPC_URL@BLOCK")?;
+    let (rpc_url, block) = rest
+        .rsplit_once('@')
+        .ok_or("expected CHAIN_ID=RPC_URL@BLOCK")?;
+    Ok(ChainTarget {
+        chain_id: chain_id
+            .parse()
+            .map_err(|e| format!("invalid chain id: {}", e))?,
+        rpc_url: rpc_url.to_string(),
+        block: block.parse().map_err(|e| format!("invalid block: {}", e))?,
+    })
+}
+
+pub(crate) fn parse_override(value: &str) -> Result<(String, String), String> {
+    let (field, value) = value.split_once('=').ok_or("expected FIELD=VALUE")?;
+    Ok((field.trim().to_string(), value.trim().to_string()))
+}
+
+pub(crate) fn parse_rename(value: &str) -> Result<(String, String), String> {
+    let (old, new) = value.split_once('=').ok_or("expected OLD=NEW")?;
+    let (old, new) = (old.trim(), new.trim());
+    let mut chars = new.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !identifier {
+        return Err(format!("{} is not a valid Noir parameter name", new));
+    }
+    Ok((old.to_string(), new.to_string()))
+}
+
+pub(crate) fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        Ok(_) => Err("expected a positive number of requests per second".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub(crate) fn parse_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err("expected NAME: VALUE, e.g. \"Authorization: Bearer ...\"".to_string()),
+    }
+}
+
+/// A duration such as `30s`, `500ms`, `2m` or `1h`; plain numbers are
+/// seconds.
+pub(crate) fn parse_timeout(value: &str) -> Result<Duration, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let seconds = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => {
+            return Err(format!(
+                "expected a duration such as 30s, 500ms or 2m, got {}",
+                value
+            ))
+        }
+    };
+    match number.parse::<f64>() {
+        Ok(number) if number > 0.0 && (number * seconds).is_finite() => {
+            Ok(Duration::from_secs_f64(number * seconds))
+        }
+        _ => Err(format!(
+            "expected a positive duration such as 30s, 500ms or 2m, got {}",
+            value
+        )),
+    }
+}
+
+pub(crate) fn parse_block_ref(value: &str) -> Result<BlockRef, String> {
+    match value {
+        "latest" => Ok(BlockRef::Latest),
+        "safe" => Ok(BlockRef::Safe),
+        "finalized" => Ok(BlockRef::Finalized),
+        number => number.parse().map(BlockRef::Number).map_err(|_| {
+            format!(
+                "expected a block number, latest, safe or finalized, got {}",
+                number
+            )
+        }),
+    }
+}
+
+/// Days from 1970-01-01 to the given day of the proleptic Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+pub(crate) fn parse_timestamp(value: &str) -> Result<u64, String> {
+    if let Ok(seconds) = value.parse() {
+        return Ok(seconds);
+    }
+    let invalid = || {
+        format!(
+            "expected Unix seconds or an RFC 3339 time such as 2024-06-01T00:00:00Z, got {}",
+            value
+        )
+    };
+    let field = |text: &str, range: std::ops::RangeInclusive<i64>| {
+        text.parse::<i64>()
+            .ok()
+            .filter(|number| range.contains(number))
+            .ok_or_else(invalid)
+    };
+    let (date, time) = value.split_once(['T', 't', ' ']).ok_or_else(invalid)?;
+    let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let (time, offset) = time.split_at(time.rfind(['+', '-']).ok_or_else(invalid)?);
+            let (hours, minutes) = offset[1..].split_once(':').ok_or_else(invalid)?;
+            let seconds = field(hours, 0..=23)? * 3600 + field(minutes, 0..=59)? * 60;
+            (
+                time,
+                if offset.starts_with('-') {
+                    -seconds
+                } else {
+                    seconds
+                },
+            )
+        }
+    };
+    let date: Vec<&str> = date.split('-').collect();
+    let time: Vec<&str> = time.split(':').collect();
+    let ([year, month, day], [hour, minute, second]) = (&date[..], &time[..]) else {
+        return Err(invalid());
+    };
+    // Fractions of a second are dropped.
+    let second = second.split_once('.').map_or(*second, |(whole, _)| whole);
+    let days = days_from_civil(
+        field(year, 0..=9999)?,
+        field(month, 1..=12)?,
+        field(day, 1..=31)?,
+    );
+    let seconds = days * 86_400
+        + field(hour, 0..=23)? * 3600
+        + field(minute, 0..=59)? * 60
+        + field(second, 0..=60)?
+        - offset;
+    u64::try_from(seconds).map_err(|_| format!("{} is before 1970", value))
+}
+
+pub(crate) fn parse_u256(value: &str) -> Result<U256, String> {
+    match value.strip_prefix("0x") {
+        Some("") => Err("expected hex digits after 0x".to_string()),
+        Some(digits) if digits.len() <= 64 => {
+            U256::from_str_radix(digits, 16).map_err(|e| e.to_string())
+        }
+        Some(_) => Err(format!("{} is longer than 32 bytes", value)),
+        None => U256::from_dec_str(value).map_err(|e| e.to_string()),
+    }
+}
+
+/// An amount of ether: wei, or a decimal number followed by `wei`, `gwei`,
+/// `eth` or `ether`.
+pub(crate) fn parse_amount(value: &str) -> Result<U256, String> {
+    let text = value.trim().to_ascii_lowercase();
+    if text.starts_with("0x") {
+        return parse_u256(&text);
+    }
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let decimals = match unit.trim() {
+        "" | "wei" => 0,
+        "gwei" => 9,
+        "eth" | "ether" => 18,
+        unit => return Err(format!("unknown unit {}; use wei, gwei or eth", unit)),
+    };
+    let number = number.trim();
+    if decimals == 0 {
+        return parse_u256(number);
+    }
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if fraction.len() > decimals {
+        return Err(format!("{} has more than {} decimals", value, decimals));
+    }
+    let digits = format!(
+        "{}{}{}",
+        whole,
+        fraction,
+        "0".repeat(decimals - fraction.len())
+    );
+    if whole.is_empty() && fraction.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("{} is not a decimal amount", value));
+    }
+    U256::from_dec_str(&digits).map_err(|e| format!("{}: {:?}", value, e))
+}
+
+pub(crate) fn parse_value_range(value: &str) -> Result<ValueRange, String> {
+    let (lo, hi) = value.split_once("..").ok_or("expected LO..HI")?;
+    let hi = hi.strip_prefix('=').unwrap_or(hi);
+    let range = ValueRange {
+        lo: parse_u256(lo.trim())?,
+        hi: parse_u256(hi.trim())?,
+    };
+    if range.lo > range.hi {
+        return Err(format!("{} is an empty range", value));
+    }
+    Ok(range)
+}
+
+pub(crate) fn parse_block_range(value: &str) -> Result<BlockRange, String> {
+    let (lo, hi) = value.split_once("..").ok_or("expected LO..HI")?;
+    let hi = hi.strip_prefix('=').unwrap_or(hi);
+    let number = |text: &str| {
+        text.trim()
+            .parse::<u64>()
+            .map_err(|_| format!("{} is not a block number", text))
+    };
+    let range = BlockRange {
+        lo: number(lo)?,
+        hi: number(hi)?,
+    };
+    if range.lo > range.hi {
+        return Err(format!("{} is an empty range", value));
+    }
+    Ok(range)
+}
+
+pub(crate) fn parse_recipient(value: &str) -> Result<Recipient, String> {
+    Recipient::parse(value).map_err(|_| "expected an age1 X25519 recipient".to_string())
+}
+
+pub(crate) fn parse_pubkey(value: &str) -> Result<PublicKey, String> {
+    let bytes = hex::decode(value.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+    PublicKey::parse(&bytes)
+}
+
+/// An event topic: a 32-byte hex topic, or the keccak digest of an event
+/// signature.
+pub(crate) fn parse_event(value: &str) -> Result<H256, String> {
+    if value.starts_with("0x") {
+        return parse_h256(value);
+    }
+    match value.find('(') {
+        Some(open) if open > 0 && value.ends_with(')') && !value.contains(' ') => {
+            Ok(H256(crate::keccak::keccak256(value.as_bytes())))
+        }
+        _ => Err(
+            "expected an event signature such as Transfer(address,address,uint256), \
+             without spaces, or a 32-byte 0x topic"
+                .to_string(),
+        ),
+    }
+}
@@ -0,0 +1,302 @@
+//! Compression of the files the generator saves: `--out` files, proof
+//! cache entries and witness store objects. `--compression` picks the
+//! format for the whole run; reading detects it from the leading magic
+//! bytes, so compressed and plain files can be mixed. Gzip needs the
+//! `compression` feature; zstd needs the `zstd` feature, which links
+//! libzstd.
+
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::error::{Error, Result};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// The levels the format accepts, and the one used when none is given.
+    fn levels(&self) -> (u32, u32, u32) {
+        match self {
+            Compression::None => (0, 0, 0),
+            Compression::Gzip => (1, 9, 6),
+            Compression::Zstd => (1, 19, 3),
+        }
+    }
+
+    /// The format `data` is compressed with, from its magic bytes.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if data.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// A format with the level to compress at, as `--compression` and
+/// `--compression-level` give them. The default writes files as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Compressor {
+    pub compression: Compression,
+    pub level: u32,
+}
+
+impl Compressor {
+    /// Compresses with `compression` at `level`, or the format's default
+    /// level.
+    pub fn new(compression: Compression, level: Option<u32>) -> Result<Self> {
+        let (min, max, default) = compression.levels();
+        let level = level.unwrap_or(default);
+        if compression != Compression::None && !(min..=max).contains(&level) {
+            return Err(Error::Config(format!(
+                "--compression-level {} is outside the {} levels {}..={}",
+                level,
+                compression.name(),
+                min,
+                max
+            )));
+        }
+        #[cfg(not(feature = "compression"))]
+        if compression == Compression::Gzip {
+            return Err(gzip_unsupported());
+        }
+        #[cfg(not(feature = "zstd"))]
+        if compression == Compression::Zstd {
+            return Err(zstd_unsupported());
+        }
+        Ok(Compressor { compression, level })
+    }
+
+    /// `data` compressed with this format and level.
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self.compression {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Gzip => gzip(data, self.level),
+            Compression::Zstd => zstd_compress(data, self.level),
+        }
+    }
+
+    /// Writes `data` to `path`, compressed with this format and level.
+    pub fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        fs::write(path, self.compress(data)?)?;
+        Ok(())
+    }
+}
+
+/// The CRC-32 gzip checks the uncompressed data with.
+#[cfg(feature = "compression")]
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(feature = "compression")]
+fn gzip(data: &[u8], level: u32) -> Result<Vec<u8>> {
+    // Magic, deflate, no flags, no time, no extra flags, unknown OS.
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.extend(miniz_oxide::deflate::compress_to_vec(data, level as u8));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    Ok(out)
+}
+
+#[cfg(feature = "compression")]
+fn gunzip(data: &[u8]) -> Result<Vec<u8>> {
+    let invalid = |what: &str| Error::Decode(format!("invalid gzip data: {}", what));
+    if data.len() < 18 || data[2] != 8 {
+        return Err(invalid("not deflate"));
+    }
+    let flags = data[3];
+    let mut at = 10;
+    // FEXTRA, then the zero-terminated FNAME and FCOMMENT, then FHCRC.
+    if flags & 4 != 0 {
+        let len = data
+            .get(at..at + 2)
+            .map(|len| u16::from_le_bytes([len[0], len[1]]) as usize)
+            .ok_or_else(|| invalid("truncated header"))?;
+        at += 2 + len;
+    }
+    for flag in [8, 16] {
+        if flags & flag != 0 {
+            at += data
+                .get(at..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(|| invalid("truncated header"))?
+                + 1;
+        }
+    }
+    if flags & 2 != 0 {
+        at += 2;
+    }
+    let body = data
+        .get(at..data.len() - 8)
+        .ok_or_else(|| invalid("truncated header"))?;
+    let out = miniz_oxide::inflate::decompress_to_vec(body)
+        .map_err(|e| invalid(&format!("{:?}", e.status)))?;
+    let trailer = &data[data.len() - 8..];
+    if trailer[..4] != crc32(&out).to_le_bytes() {
+        return Err(invalid("CRC mismatch"));
+    }
+    Ok(out)
+}
+
+#[cfg(not(feature = "compression"))]
+fn gzip_unsupported() -> Error {
+    Error::Unsupported(
+        "gzip needs the compression feature; rebuild with it or use --compression none".to_string(),
+    )
+}
+
+#[cfg(not(feature = "compression"))]
+fn gzip(_: &[u8], _: u32) -> Result<Vec<u8>> {
+    Err(gzip_unsupported())
+}
+
+#[cfg(not(feature = "compression"))]
+fn gunzip(_: &[u8]) -> Result<Vec<u8>> {
+    Err(gzip_unsupported())
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(data: &[u8], level: u32) -> Result<Vec<u8>> {
+    Ok(zstd::bulk::compress(data, level as i32)?)
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(data: &[u8]) -> Result<Vec<u8>> {
+    zstd::stream::decode_all(data).map_err(|e| Error::Decode(format!("invalid zstd data: {}", e)))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_unsupported() -> Error {
+    Error::Unsupported(
+        "zstd needs the zstd feature; rebuild with it or use --compression gzip".to_string(),
+    )
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_compress(_: &[u8], _: u32) -> Result<Vec<u8>> {
+    Err(zstd_unsupported())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_: &[u8]) -> Result<Vec<u8>> {
+    Err(zstd_unsupported())
+}
+
+/// `data` uncompressed, whatever format it was compressed with.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    match Compression::detect(data) {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Gzip => gunzip(data),
+        Compression::Zstd => zstd_decompress(data),
+    }
+}
+
+/// Reads `path`, uncompressing it if it is compressed.
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    decompress(&fs::read(path)?)
+}
+
+/// Reads the text file `path`, uncompressing it if it is compressed.
+pub fn read_to_string(path: &Path) -> Result<String> {
+    String::from_utf8(read(path)?)
+        .map_err(|_| Error::Decode(format!("{} is not UTF-8 text", path.display())))
+}
+
+#[cfg(all(test, any(feature = "compression", feature = "zstd")))]
+mod tests {
+    use super::*;
+
+    /// `noir-mip\n` as `gzip -9` writes it, with the file name
+    /// `witness.toml` in the header.
+    #[cfg(feature = "compression")]
+    const GZIP_WITH_NAME: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x08, 0x45, 0x54, 0xcf, 0x6a, 0x02, 0x03, 0x77, 0x69, 0x74, 0x6e, 0x65,
+        0x73, 0x73, 0x2e, 0x74, 0x6f, 0x6d, 0x6c, 0x00, 0xcb, 0xcb, 0xcf, 0x2c, 0xd2, 0xcd, 0xcd,
+        0x2c, 0xe0, 0x02, 0x00, 0x5f, 0xfd, 0x56, 0x5b, 0x09, 0x00, 0x00, 0x00,
+    ];
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn crc32_matches_the_check_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"noir-mip\n"), 0x5b56_fd5f);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn gzip_writes_the_rfc_1952_framing() {
+        let data = b"noir-mip\n".repeat(100);
+        let out = Compressor::new(Compression::Gzip, Some(9))
+            .unwrap()
+            .compress(&data)
+            .unwrap();
+        assert_eq!(&out[..10], &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff]);
+        let trailer = &out[out.len() - 8..];
+        assert_eq!(trailer[..4], crc32(&data).to_le_bytes());
+        assert_eq!(trailer[4..], (data.len() as u32).to_le_bytes());
+        assert_eq!(Compression::detect(&out), Compression::Gzip);
+        assert_eq!(decompress(&out).unwrap(), data);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn gunzip_reads_what_gzip_writes() {
+        assert_eq!(decompress(GZIP_WITH_NAME).unwrap(), b"noir-mip\n");
+        let mut corrupt = GZIP_WITH_NAME.to_vec();
+        let crc = corrupt.len() - 8;
+        corrupt[crc] ^= 1;
+        assert!(decompress(&corrupt).is_err());
+        assert!(decompress(&GZIP_WITH_NAME[..17]).is_err());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn levels_outside_the_format_are_refused() {
+        assert!(Compressor::new(Compression::Gzip, Some(10)).is_err());
+        assert_eq!(Compressor::new(Compression::Gzip, None).unwrap().level, 6);
+        assert_eq!(Compressor::default().compress(b"plain").unwrap(), b"plain");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_reverses() {
+        let data = b"noir-mip\n".repeat(100);
+        let out = Compressor::new(Compression::Zstd, None)
+            .unwrap()
+            .compress(&data)
+            .unwrap();
+        assert_eq!(Compression::detect(&out), Compression::Zstd);
+        assert_eq!(decompress(&out).unwrap(), data);
+    }
+}
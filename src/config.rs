@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use web3::types::{H160, H256};
+
+use crate::cache::CacheConfig;
+use crate::cli::GenArgs;
+use crate::error::{Error, Result};
+
+/// A named account and slot, so they need not be spelled out in hex on
+/// every run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    pub account: H160,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot: Option<H256>,
+    /// Providers to prove the target against, as templates whose
+    /// `${NAME}`s are filled in by [`interpolate`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rpc_url: Vec<String>,
+}
+
+/// Settings kept between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub targets: BTreeMap<String, Target>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cache: CacheConfig,
+}
+
+fn is_default(cache: &CacheConfig) -> bool {
+    *cache == CacheConfig::default()
+}
+
+/// `$XDG_CONFIG_HOME/noir-mip/config.toml`, falling back to `~/.config`.
+pub fn default_path() -> Result<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".config"))
+            .ok_or_else(|| {
+                Error::Unsupported(
+                    "cannot locate the config file without HOME; pass --config".to_string(),
+                )
+            })?,
+    };
+    Ok(base.join("noir-mip").join("config.toml"))
+}
+
+/// `secrets.toml` next to the config file at `config`.
+pub fn default_secrets_path(config: &Path) -> PathBuf {
+    config.with_file_name("secrets.toml")
+}
+
+/// Values of a secrets file: a TOML table of strings, such as
+/// `ALCHEMY_KEY = "..."`; a missing file holds none.
+fn load_secrets(path: &Path) -> Result<BTreeMap<String, String>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if fs::metadata(path)?.permissions().mode() & 0o077 != 0 {
+            log::warn!(
+                "{} can be read by other users; chmod 600 it",
+                path.display()
+            );
+        }
+    }
+    toml::from_str(&text).map_err(|e| {
+        Error::Config(format!(
+            "invalid secrets file {}: {}",
+            path.display(),
+            e.message()
+        ))
+    })
+}
+
+/// `template` with every `${NAME}` replaced by the environment variable
+/// `NAME` or, when it is not set, by `NAME` in the secrets file at
+/// `secrets`, so that provider URLs can be committed without their keys.
+/// The file is only read when the environment lacks a name.
+pub fn interpolate(template: &str, secrets: &Path) -> Result<String> {
+    let mut out = String::new();
+    let mut rest = template;
+    let mut file = None;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find('}') else {
+            return Err(Error::Config(format!("unterminated ${{ in {}", template)));
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(Error::Config(format!(
+                "invalid variable name ${{{}}}; use letters, digits and underscores",
+                name
+            )));
+        }
+        let value = match env::var(name) {
+            Ok(value) => value,
+            Err(_) => {
+                if file.is_none() {
+                    file = Some(load_secrets(secrets)?);
+                }
+                file.as_ref()
+                    .and_then(|file| file.get(name))
+                    .cloned()
+                    .ok_or_else(|| {
+                        Error::Config(format!(
+                            "${{{}}} is set neither in the environment nor in {}",
+                            name,
+                            secrets.display()
+                        ))
+                    })?
+            }
+        };
+        out.push_str(&value);
+        rest = &rest[start + 3 + len..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+impl Config {
+    /// Reads the config at `path`; a missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(e.into()),
+        };
+        toml::from_str(&text).map_err(|e| {
+            Error::Config(format!(
+                "invalid config {}: {}",
+                path.display(),
+                e.message()
+            ))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string(self)
+            .map_err(|e| Error::Decode(format!("cannot serialize the config: {}", e)))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn target(&self, name: &str) -> Result<&Target> {
+        self.targets.get(name).ok_or_else(|| {
+            Error::Unsupported(format!(
+                "no target named {}; add it with `noir-mip target add`",
+                name
+            ))
+        })
+    }
+}
+
+/// Sets the account of `args` from `--account`, `--pubkey` or its
+/// `--target`. A target overrides `--account` and, when it names them,
+/// `--slot` and `--rpc-url`, so values left in `.env` do not get in its way.
+/// The `${NAME}`s of the provider URLs are then filled in from the
+/// environment or `secrets`.
+pub fn resolve_target(args: &mut GenArgs, config: &Config, secrets: &Path) -> Result<()> {
+    match &args.target {
+        Some(name) => {
+            let target = config.target(name)?;
+            args.options.account = target.account;
+            args.options.slot = target.slot.or(args.options.slot);
+            if !target.rpc_url.is_empty() {
+                args.options.rpc_url = target.rpc_url.clone();
+            }
+        }
+        None => {
+            args.options.account = args
+                .account_arg
+                .or(args.options.pubkey.map(|key| key.address()))
+                .unwrap_or(args.options.account)
+        }
+    }
+    if args.options.rpc_url.is_empty() && args.options.from_smt.is_none() {
+        return Err(Error::Config(
+            "no provider; pass --rpc-url or set MAINNET_RPC".to_string(),
+        ));
+    }
+    args.options.rpc_url = args
+        .options
+        .rpc_url
+        .iter()
+        .map(|url| interpolate(url, secrets))
+        .collect::<Result<_>>()?;
+    Ok(())
+}
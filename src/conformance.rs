@@ -0,0 +1,308 @@
+//! Runs the trie and RLP vectors of the ethereum/tests repository against
+//! the trie module and the RLP codec: the `conformance` command, built with
+//! the feature of the same name.
+
+use std::fs;
+use std::path::Path;
+
+use rlp::{Rlp, RlpStream};
+use serde_json::Value as Json;
+
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::trie::{self, NodeStore, EMPTY_TRIE_ROOT};
+
+/// Trie vector files, under `TrieTests/`. The `secureTrie` ones hash their
+/// keys, like the state and storage tries.
+const TRIE_FILES: &[&str] = &[
+    "trietest.json",
+    "trietest_secureTrie.json",
+    "trieanyorder.json",
+    "trieanyorder_secureTrie.json",
+    "hex_encoded_securetrie_test.json",
+];
+
+/// RLP vector files, under `RLPTests/`.
+const RLP_FILES: &[&str] = &["rlptest.json", "invalidRLPTest.json"];
+
+/// Outcome of one vector file.
+struct FileResult {
+    passed: usize,
+    failures: Vec<String>,
+}
+
+/// A key or value of a trie vector: hex when `0x` prefixed, UTF-8 text
+/// otherwise.
+fn trie_bytes(value: &Json) -> Result<Vec<u8>> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| Error::Decode(format!("{} is not a string", value)))?;
+    match text.strip_prefix("0x") {
+        Some(hex) => hex::decode(hex).map_err(|e| Error::Decode(format!("{}: {}", text, e))),
+        None => Ok(text.as_bytes().to_vec()),
+    }
+}
+
+fn hex_bytes(value: &Json) -> Result<Vec<u8>> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| Error::Decode(format!("{} is not a string", value)))?;
+    hex::decode(text.trim_start_matches("0x"))
+        .map_err(|e| Error::Decode(format!("{}: {}", text, e)))
+}
+
+/// Checks one trie vector. A list of pairs is applied in order with
+/// [`trie::update`], a `null` or empty value removing the key. A map is
+/// order independent: its root is computed with [`trie::build_proof`] and
+/// with updates, and every key is checked against its proof.
+fn check_trie(vector: &Json, secure: bool) -> Result<()> {
+    let expected = hex_bytes(&vector["root"])?;
+    let path = |key: &[u8]| match secure {
+        true => trie::key_nibbles(key),
+        false => trie::nibbles(key),
+    };
+    let decode_error = |e| Error::Decode(format!("{:?}", e));
+    let entries: Vec<(Vec<u8>, Option<Vec<u8>>)> = match &vector["in"] {
+        Json::Array(pairs) => pairs
+            .iter()
+            .map(|pair| {
+                let value = match &pair[1] {
+                    Json::Null => None,
+                    value => Some(trie_bytes(value)?).filter(|value| !value.is_empty()),
+                };
+                Ok((trie_bytes(&pair[0])?, value))
+            })
+            .collect::<Result<_>>()?,
+        Json::Object(map) => map
+            .iter()
+            .map(|(key, value)| {
+                Ok((
+                    trie_bytes(&Json::String(key.clone()))?,
+                    Some(trie_bytes(value)?),
+                ))
+            })
+            .collect::<Result<_>>()?,
+        other => return Err(Error::Decode(format!("unexpected trie input {}", other))),
+    };
+
+    let mut store = NodeStore::new();
+    let mut root = EMPTY_TRIE_ROOT;
+    for (key, value) in &entries {
+        root =
+            trie::update(root, &path(key), value.as_deref(), &mut store).map_err(decode_error)?;
+    }
+    if root.as_slice() != expected {
+        return Err(Error::Verification(format!(
+            "the root after updates is 0x{}, not 0x{}",
+            hex::encode(root),
+            hex::encode(&expected)
+        )));
+    }
+
+    if vector["in"].is_object() {
+        // build_proof walks nibble paths, so give it the hashed keys of a
+        // secure trie, split back into bytes.
+        let keyed: Vec<(Vec<u8>, Vec<u8>)> = entries
+            .iter()
+            .filter_map(|(key, value)| {
+                let key = match secure {
+                    true => keccak256(key).to_vec(),
+                    false => key.clone(),
+                };
+                Some((key, value.clone()?))
+            })
+            .collect();
+        for (key, value) in &keyed {
+            let (built, proof) = trie::build_proof(&keyed, key);
+            if built.as_slice() != expected {
+                return Err(Error::Verification(format!(
+                    "the built root is 0x{}, not 0x{}",
+                    hex::encode(built),
+                    hex::encode(&expected)
+                )));
+            }
+            let proven =
+                trie::verify_proof(&built, &trie::nibbles(key), &proof).map_err(decode_error)?;
+            if proven.as_ref() != Some(value) {
+                return Err(Error::Verification(format!(
+                    "the proof of key 0x{} does not lead to its value",
+                    hex::encode(key)
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Appends the input of an RLP vector: strings as their bytes, integers
+/// (and `#`-prefixed decimal big integers) as minimal big-endian bytes,
+/// lists recursively.
+fn append_rlp(stream: &mut RlpStream, value: &Json) -> Result<()> {
+    match value {
+        Json::String(text) => match text.strip_prefix('#') {
+            Some(digits) => {
+                let value = decimal_bytes(digits)
+                    .ok_or_else(|| Error::Decode(format!("{} is not a decimal integer", text)))?;
+                stream.append(&value.as_slice());
+            }
+            None => {
+                stream.append(&text.as_bytes());
+            }
+        },
+        Json::Number(number) => {
+            let value = number
+                .as_u64()
+                .ok_or_else(|| Error::Decode(format!("{} is not a u64", number)))?;
+            stream.append(&value);
+        }
+        Json::Array(items) => {
+            stream.begin_list(items.len());
+            for item in items {
+                append_rlp(stream, item)?;
+            }
+        }
+        other => return Err(Error::Decode(format!("unexpected RLP input {}", other))),
+    }
+    Ok(())
+}
+
+/// The minimal big-endian bytes of the decimal `digits`, which may exceed
+/// 256 bits, as the `bigint` vector does.
+fn decimal_bytes(digits: &str) -> Option<Vec<u8>> {
+    if digits.is_empty() {
+        return None;
+    }
+    let mut bytes: Vec<u8> = Vec::new();
+    for digit in digits.chars() {
+        let mut carry = digit.to_digit(10)?;
+        for byte in bytes.iter_mut().rev() {
+            let value = u32::from(*byte) * 10 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry > 0 {
+            bytes.insert(0, carry as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Decodes every item of `rlp`, failing on any malformed or non-canonical
+/// encoding.
+fn decode_all(rlp: &Rlp) -> std::result::Result<(), rlp::DecoderError> {
+    if rlp.is_list() {
+        for item in rlp.iter() {
+            decode_all(&item)?;
+        }
+        // Iteration stops at the first item that does not decode.
+        if rlp.iter().count() != rlp.item_count()? {
+            return Err(rlp::DecoderError::RlpIsTooShort);
+        }
+        Ok(())
+    } else {
+        rlp.as_val::<Vec<u8>>().map(|_| ())
+    }
+}
+
+/// Checks one RLP vector: a valid one must encode to `out` and decode back,
+/// an `INVALID` one must be rejected as a whole.
+fn check_rlp(vector: &Json) -> Result<()> {
+    let out = hex_bytes(&vector["out"])?;
+    let rlp = Rlp::new(&out);
+    let decoded = rlp.payload_info().and_then(|info| {
+        if info.header_len + info.value_len != out.len() {
+            return Err(rlp::DecoderError::RlpInconsistentLengthAndData);
+        }
+        decode_all(&rlp)
+    });
+    if vector["in"] == "INVALID" {
+        return match decoded {
+            Ok(()) => Err(Error::Verification(
+                "the invalid encoding decodes".to_string(),
+            )),
+            Err(_) => Ok(()),
+        };
+    }
+    let mut stream = RlpStream::new();
+    append_rlp(&mut stream, &vector["in"])?;
+    if stream.as_raw() != out.as_slice() {
+        return Err(Error::Verification(format!(
+            "encodes to 0x{}",
+            hex::encode(stream.as_raw())
+        )));
+    }
+    decoded.map_err(|e| Error::Verification(format!("does not decode: {:?}", e)))
+}
+
+fn run_file(path: &Path, check: impl Fn(&Json) -> Result<()>) -> Result<FileResult> {
+    let vectors: serde_json::Map<String, Json> =
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| Error::Decode(format!("{}: {}", path.display(), e)))?;
+    let mut result = FileResult {
+        passed: 0,
+        failures: Vec::new(),
+    };
+    for (name, vector) in &vectors {
+        match check(vector) {
+            Ok(()) => result.passed += 1,
+            Err(e) => result.failures.push(format!("{}: {}", name, e)),
+        }
+    }
+    Ok(result)
+}
+
+/// Runs the trie and RLP vectors of the ethereum/tests checkout at `dir`,
+/// printing a line per file and each failing vector. Missing files are
+/// skipped; fails if any vector fails or no file is found.
+pub fn run(dir: &Path) -> Result<()> {
+    let files = TRIE_FILES
+        .iter()
+        .map(|&file| (Path::new("TrieTests").join(file), Some(file)))
+        .chain(
+            RLP_FILES
+                .iter()
+                .map(|&file| (Path::new("RLPTests").join(file), None)),
+        );
+    let (mut ran, mut failed) = (0, 0);
+    for (file, trie_file) in files {
+        let path = dir.join(&file);
+        if !path.exists() {
+            log::info!("{}: not found, skipped", file.display());
+            continue;
+        }
+        let result = match trie_file {
+            Some(name) => {
+                let secure = name.to_lowercase().contains("secure");
+                run_file(&path, |vector| check_trie(vector, secure))?
+            }
+            None => run_file(&path, check_rlp)?,
+        };
+        ran += 1;
+        failed += result.failures.len();
+        log::info!(
+            "{}: {} passed, {} failed",
+            file.display(),
+            result.passed,
+            result.failures.len()
+        );
+        for failure in &result.failures {
+            log::info!("  {}", failure);
+        }
+    }
+    if ran == 0 {
+        return Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!(
+                "no vector files under {}; pass a checkout of ethereum/tests",
+                dir.display()
+            ),
+        )));
+    }
+    if failed > 0 {
+        return Err(Error::Verification(format!(
+            "{} conformance vector(s) failed",
+            failed
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use crate::{
+    ACCOUNT_PROOF_MAX_DEPTH, BLOCK_HEADER_KECCAK_MAX_BLOCKS, BLOCK_HEADER_RLP_BYTES,
+    PROOF_BYTES_LEN, STORAGE_PROOF_MAX_DEPTH,
+};
+
+/// One circuit constant the generator depends on: the names circuits use
+/// for it and the value the generator pads to.
+pub struct Constant {
+    pub names: &'static [&'static str],
+    pub value: u128,
+}
+
+/// Every constant the check knows about. The misspelt account proof name is
+/// the one the bundled circuit declares.
+pub const CONSTANTS: &[Constant] = &[
+    Constant {
+        names: &["BLOCK_HEADER_RLP_MAX_BYTES", "BLOCK_HEADER_RLP_BYTES"],
+        value: BLOCK_HEADER_RLP_BYTES as u128,
+    },
+    Constant {
+        names: &["BLOCK_HEADER_KECCAK_MAX_BLOCKS"],
+        value: BLOCK_HEADER_KECCAK_MAX_BLOCKS as u128,
+    },
+    Constant {
+        names: &["ACCOUNT_PROOF_MAX_DEPTH"],
+        value: ACCOUNT_PROOF_MAX_DEPTH as u128,
+    },
+    Constant {
+        names: &["STORAGE_PROOF_MAX_DEPTH"],
+        value: STORAGE_PROOF_MAX_DEPTH as u128,
+    },
+    Constant {
+        names: &["PROOF_BYTES_LEN", "MAX_NODE_LEN", "PROOF_NODE_MAX_BYTES"],
+        value: PROOF_BYTES_LEN as u128,
+    },
+    Constant {
+        names: &["ACCOUNT_PROOF_MAX_BYTES", "ACOOUNT_PROOF_MAX_BYTES"],
+        value: (ACCOUNT_PROOF_MAX_DEPTH * PROOF_BYTES_LEN) as u128,
+    },
+    Constant {
+        names: &["STORAGE_PROOF_MAX_BYTES"],
+        value: (STORAGE_PROOF_MAX_DEPTH * PROOF_BYTES_LEN) as u128,
+    },
+];
+
+/// A `global` of the circuit, with its value when it is an integer
+/// expression the parser understands.
+pub struct Global {
+    pub name: String,
+    pub value: Option<u128>,
+}
+
+/// Evaluates sums and products of integer literals and earlier globals,
+/// which covers how circuits size their arrays.
+fn evaluate(expression: &str, known: &HashMap<String, u128>) -> Option<u128> {
+    expression
+        .split('+')
+        .map(|term| {
+            term.split('*')
+                .map(|factor| {
+                    let factor = factor.trim().replace('_', "");
+                    match factor.strip_prefix("0x") {
+                        Some(hex) => u128::from_str_radix(hex, 16).ok(),
+                        None => factor.parse().ok().or_else(|| known.get(&factor).copied()),
+                    }
+                })
+                .try_fold(1u128, |product, factor| product.checked_mul(factor?))
+        })
+        .try_fold(0u128, |sum, term| sum.checked_add(term?))
+}
+
+/// The `global` declarations of a Noir source file, in declaration order.
+pub fn parse_globals(source: &str) -> Vec<Global> {
+    let mut known = HashMap::new();
+    let mut globals = Vec::new();
+    for line in source.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let Some(declaration) = line
+            .strip_prefix("pub ")
+            .unwrap_or(line)
+            .strip_prefix("global ")
+        else {
+            continue;
+        };
+        let Some((name, expression)) = declaration.split_once('=') else {
+            continue;
+        };
+        let name = name
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let value = evaluate(expression.trim().trim_end_matches(';'), &known);
+        if let Some(value) = value {
+            known.insert(name.clone(), value);
+        }
+        globals.push(Global { name, value });
+    }
+    globals
+}
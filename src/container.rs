@@ -0,0 +1,389 @@
+//! The binary container `--format binary` writes: a self-describing,
+//! length-prefixed layout that readers can parse without knowing every
+//! field, so fields and sections can be added without breaking them.
+//!
+//! All integers are big-endian. A container starts with the magic bytes
+//! `NMIP` and a `u16` version, followed by sections until the end of the
+//! data. Each section is a `u8` kind, a `u32` length and that many bytes of
+//! entries: [`SectionKind::Header`], [`SectionKind::Account`],
+//! [`SectionKind::Storage`] and [`SectionKind::Receipt`] hold the fields
+//! `namespace` groups into those tables, [`SectionKind::Fields`] every other
+//! field and [`SectionKind::Meta`] the metadata. A reader skips the kinds it
+//! does not know.
+//!
+//! An entry is its `u32` position in emission order, a `u16` length and the
+//! UTF-8 name, and the value: a `u8` tag followed by a `u32` length and the
+//! bytes for bytes (0), hex field strings (2) and strings (3), a `u64` for
+//! integers (1), or a `u32` count and that many values for arrays (4).
+
+use crate::error::{Error, Result};
+use crate::namespace;
+use crate::params::{Fields, Params, Value};
+
+/// The bytes every container starts with.
+pub const MAGIC: &[u8; 4] = b"NMIP";
+
+/// The version this build writes. A reader rejects later versions, whose
+/// existing sections may have changed; new sections and fields do not
+/// change the version.
+pub const VERSION: u16 = 1;
+
+/// The kind of a section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    Header,
+    Account,
+    Storage,
+    Receipt,
+    /// Fields of no table, such as keccak preimages and rollup extras.
+    Fields,
+    Meta,
+}
+
+impl SectionKind {
+    const ALL: [SectionKind; 6] = [
+        SectionKind::Header,
+        SectionKind::Account,
+        SectionKind::Storage,
+        SectionKind::Receipt,
+        SectionKind::Fields,
+        SectionKind::Meta,
+    ];
+
+    pub fn code(&self) -> u8 {
+        match self {
+            SectionKind::Header => 1,
+            SectionKind::Account => 2,
+            SectionKind::Storage => 3,
+            SectionKind::Receipt => 4,
+            SectionKind::Fields => 5,
+            SectionKind::Meta => 6,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.code() == code)
+    }
+
+    /// The section the field `name` is written to.
+    fn of_field(name: &str) -> Self {
+        match namespace::section_of(name) {
+            Some("header") => SectionKind::Header,
+            Some("account") => SectionKind::Account,
+            Some("storage") => SectionKind::Storage,
+            Some("receipt") => SectionKind::Receipt,
+            _ => SectionKind::Fields,
+        }
+    }
+}
+
+/// A section of a container, as [`sections`] reads it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section<'a> {
+    /// The kind code, which may be one this build does not know.
+    pub code: u8,
+    pub data: &'a [u8],
+}
+
+impl Section<'_> {
+    pub fn kind(&self) -> Option<SectionKind> {
+        SectionKind::from_code(self.code)
+    }
+}
+
+/// Whether `data` starts like a container.
+pub fn is_container(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn put_len(out: &mut Vec<u8>, len: usize) {
+    out.extend_from_slice(&(len as u32).to_be_bytes());
+}
+
+fn put_value(out: &mut Vec<u8>, value: &Value) {
+    let put_data = |out: &mut Vec<u8>, tag: u8, data: &[u8]| {
+        out.push(tag);
+        put_len(out, data.len());
+        out.extend_from_slice(data);
+    };
+    match value {
+        Value::Bytes(bytes) => put_data(out, 0, bytes),
+        Value::Int(value) => {
+            out.push(1);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        Value::Hex(bytes) => put_data(out, 2, bytes),
+        Value::Str(text) => put_data(out, 3, text.as_bytes()),
+        Value::Array(values) => {
+            out.push(4);
+            put_len(out, values.len());
+            for value in values {
+                put_value(out, value);
+            }
+        }
+    }
+}
+
+fn put_entry(out: &mut Vec<u8>, index: usize, name: &str, value: &Value) {
+    put_len(out, index);
+    out.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    out.extend_from_slice(name.as_bytes());
+    put_value(out, value);
+}
+
+/// `params` as a container.
+pub fn write(params: &Params) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&VERSION.to_be_bytes());
+    for kind in SectionKind::ALL {
+        let mut data = Vec::new();
+        let fields = match kind {
+            SectionKind::Meta => &params.meta,
+            _ => &params.fields,
+        };
+        for (index, (name, value)) in fields.iter().enumerate() {
+            if kind == SectionKind::Meta || SectionKind::of_field(name) == kind {
+                put_entry(&mut data, index, name, value);
+            }
+        }
+        if data.is_empty() {
+            continue;
+        }
+        out.push(kind.code());
+        put_len(&mut out, data.len());
+        out.extend_from_slice(&data);
+    }
+    out
+}
+
+/// Reads big-endian integers and length-prefixed data off a slice.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+fn truncated() -> Error {
+    Error::Decode("the container is truncated".to_string())
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(truncated());
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(
+            self.take(2)?.try_into().unwrap_or_default(),
+        ))
+    }
+
+    fn u32(&mut self) -> Result<usize> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap_or_default()) as usize)
+    }
+
+    fn text(&mut self, len: usize) -> Result<String> {
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| Error::Decode("a container string is not UTF-8".to_string()))
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        Ok(match self.u8()? {
+            0 => {
+                let len = self.u32()?;
+                Value::Bytes(self.take(len)?.to_vec())
+            }
+            1 => Value::Int(u64::from_be_bytes(
+                self.take(8)?.try_into().unwrap_or_default(),
+            )),
+            2 => {
+                let len = self.u32()?;
+                Value::Hex(self.take(len)?.to_vec())
+            }
+            3 => {
+                let len = self.u32()?;
+                Value::Str(self.text(len)?)
+            }
+            4 => {
+                let count = self.u32()?;
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.value()?);
+                }
+                Value::Array(values)
+            }
+            tag => {
+                return Err(Error::Decode(format!(
+                    "unknown value tag {} in the container",
+                    tag
+                )))
+            }
+        })
+    }
+}
+
+/// The version and the sections of the container `data`, in order.
+pub fn sections(data: &[u8]) -> Result<(u16, Vec<Section<'_>>)> {
+    let mut reader = Reader { data };
+    if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(Error::Decode("not a noir-mip container".to_string()));
+    }
+    let version = reader.u16()?;
+    if version > VERSION {
+        return Err(Error::Unsupported(format!(
+            "container version {} is newer than this build reads ({})",
+            version, VERSION
+        )));
+    }
+    let mut sections = Vec::new();
+    while !reader.data.is_empty() {
+        let code = reader.u8()?;
+        let len = reader.u32()?;
+        sections.push(Section {
+            code,
+            data: reader.take(len)?,
+        });
+    }
+    Ok((version, sections))
+}
+
+/// The entries of `section`, as (position, name, value).
+pub fn entries(section: &Section) -> Result<Vec<(usize, String, Value)>> {
+    let mut reader = Reader { data: section.data };
+    let mut entries = Vec::new();
+    while !reader.data.is_empty() {
+        let index = reader.u32()?;
+        let len = reader.u16()? as usize;
+        let name = reader.text(len)?;
+        entries.push((index, name, reader.value()?));
+    }
+    Ok(entries)
+}
+
+/// The parameters in the container `data`, with the fields in emission
+/// order. Sections of unknown kinds are skipped.
+pub fn read(data: &[u8]) -> Result<Params> {
+    let (_, sections) = sections(data)?;
+    let mut fields = Vec::new();
+    let mut meta = Vec::new();
+    for section in &sections {
+        match section.kind() {
+            Some(SectionKind::Meta) => meta.extend(entries(section)?),
+            Some(_) => fields.extend(entries(section)?),
+            None => {}
+        }
+    }
+    let collect = |mut entries: Vec<(usize, String, Value)>| {
+        entries.sort_by_key(|(index, _, _)| *index);
+        let mut collected = Fields::default();
+        for (_, name, value) in entries {
+            collected.push(name, value);
+        }
+        collected
+    };
+    Ok(Params {
+        fields: collect(fields),
+        meta: collect(meta),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parameters with a field in every section, out of section order, a
+    /// value of every type and metadata.
+    fn params() -> Params {
+        let mut params = Params::default();
+        params.push("storage_value", Value::Bytes(vec![0x2a; 32]));
+        params.push("block_hash", Value::Hex(vec![0xab; 32]));
+        params.push("account_proof_depth", Value::Int(u64::MAX));
+        params.push("receipt_index", Value::Int(3));
+        params.push(
+            "keccak_blocks",
+            Value::Array(vec![
+                Value::Array(vec![Value::Int(1), Value::Int(2)]),
+                Value::Array(Vec::new()),
+            ]),
+        );
+        params.push("block_header_rlp", Value::Bytes(Vec::new()));
+        params
+            .meta
+            .push("generator", Value::Str("noir-mip ✓".to_string()));
+        params.meta.push("chain_id", Value::Int(1));
+        params
+    }
+
+    #[test]
+    fn read_reverses_write() {
+        let data = write(&params());
+        assert!(is_container(&data));
+        assert_eq!(&data[..6], b"NMIP\x00\x01");
+        assert_eq!(read(&data).unwrap(), params());
+
+        let (version, sections) = sections(&data).unwrap();
+        assert_eq!(version, VERSION);
+        let kinds: Vec<_> = sections.iter().map(|s| s.kind().unwrap()).collect();
+        assert_eq!(kinds, SectionKind::ALL);
+        assert_eq!(read(&write(&Params::default())).unwrap(), Params::default());
+    }
+
+    #[test]
+    fn unknown_sections_are_skipped() {
+        let mut data = write(&params());
+        data.extend_from_slice(&[0x7f, 0, 0, 0, 3, 1, 2, 3]);
+        assert_eq!(read(&data).unwrap(), params());
+    }
+
+    #[test]
+    fn a_truncated_container_is_refused() {
+        let data = write(&params());
+        let (_, sections) = sections(&data).unwrap();
+        // Sections run to the end of the data, so a cut between two of them
+        // leaves a shorter container; a cut anywhere else is caught.
+        let mut boundaries = vec![MAGIC.len() + 2];
+        for section in &sections {
+            boundaries.push(boundaries.last().unwrap() + 5 + section.data.len());
+        }
+        for len in 0..data.len() {
+            match read(&data[..len]) {
+                Ok(_) => assert!(boundaries.contains(&len), "{} bytes read", len),
+                Err(Error::Decode(_)) => {}
+                Err(e) => panic!("{} bytes: {}", len, e),
+            }
+        }
+        assert!(
+            matches!(read(&data[..10]), Err(Error::Decode(message)) if message.contains("truncated"))
+        );
+    }
+
+    #[test]
+    fn other_data_is_refused() {
+        let mut data = write(&params());
+        data[3] = b'Q';
+        assert!(!is_container(&data));
+        assert!(
+            matches!(read(&data), Err(Error::Decode(message)) if message.contains("not a noir-mip container"))
+        );
+        assert!(read(b"").is_err());
+        assert!(read(b"[meta]\n").is_err());
+
+        let mut newer = write(&params());
+        newer[4..6].copy_from_slice(&(VERSION + 1).to_be_bytes());
+        assert!(matches!(read(&newer), Err(Error::Unsupported(_))));
+
+        // A value tag this build does not know, in the first entry.
+        let mut data = write(&params());
+        let (_, sections) = sections(&data).unwrap();
+        let name_len = u16::from_be_bytes([sections[0].data[4], sections[0].data[5]]) as usize;
+        data[6 + 5 + 6 + name_len] = 9;
+        assert!(matches!(read(&data), Err(Error::Decode(message)) if message.contains("tag 9")));
+    }
+}
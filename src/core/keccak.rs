@@ -0,0 +1,92 @@
+use alloc::vec::Vec;
+
+use tiny_keccak::{Hasher, Keccak};
+
+/// Rate of keccak-f[1600] with a 512-bit capacity, in bytes.
+pub const KECCAK_RATE_BYTES: usize = 136;
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut keccak = Keccak::v256();
+    let mut result = [0u8; 32];
+    keccak.update(data);
+    keccak.finalize(&mut result);
+    result
+}
+
+/// Number of rate blocks `data_len` bytes occupy once keccak padding is applied.
+pub fn rate_block_count(data_len: usize) -> usize {
+    data_len / KECCAK_RATE_BYTES + 1
+}
+
+/// Applies the keccak pad10*1 rule (domain byte 0x01) and zero-fills the
+/// result up to `max_blocks` rate blocks.
+pub fn pad_rate_blocks(data: &[u8], max_blocks: usize) -> (Vec<u8>, usize) {
+    let block_count = rate_block_count(data.len());
+    assert!(
+        block_count <= max_blocks,
+        "Pad_rate_blocks: {} blocks exceed the maximum of {}",
+        block_count,
+        max_blocks
+    );
+
+    let mut blocks = data.to_vec();
+    blocks.resize(block_count * KECCAK_RATE_BYTES, 0);
+    blocks[data.len()] |= 0x01;
+    blocks[block_count * KECCAK_RATE_BYTES - 1] |= 0x80;
+    blocks.resize(max_blocks * KECCAK_RATE_BYTES, 0);
+
+    (blocks, block_count)
+}
+
+/// Keccak-256 computed as data is written to it, for inputs too large to
+/// assemble first.
+pub struct Keccak256(Keccak);
+
+impl Default for Keccak256 {
+    fn default() -> Self {
+        Keccak256(Keccak::v256())
+    }
+}
+
+impl Keccak256 {
+    pub fn finalize(self) -> [u8; 32] {
+        let mut result = [0u8; 32];
+        self.0.finalize(&mut result);
+        result
+    }
+}
+
+impl core::fmt::Write for Keccak256 {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0.update(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn pad_rate_blocks_applies_pad10star1(
+            data in prop::collection::vec(any::<u8>(), 0..=4 * KECCAK_RATE_BYTES),
+            extra in 0..=2usize,
+        ) {
+            let max_blocks = rate_block_count(data.len()) + extra;
+            let (blocks, block_count) = pad_rate_blocks(&data, max_blocks);
+            let end = block_count * KECCAK_RATE_BYTES;
+            // The fewest blocks that leave room for at least one padding byte.
+            prop_assert!(data.len() < end && end - data.len() <= KECCAK_RATE_BYTES);
+            prop_assert_eq!(blocks.len(), max_blocks * KECCAK_RATE_BYTES);
+            prop_assert_eq!(&blocks[..data.len()], data.as_slice());
+            let mut padding = vec![0u8; end - data.len()];
+            padding[0] |= 0x01;
+            *padding.last_mut().unwrap() |= 0x80;
+            prop_assert_eq!(&blocks[data.len()..end], padding.as_slice());
+            prop_assert!(blocks[end..].iter().all(|&byte| byte == 0));
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! The encoding and verification primitives the rest of the crate builds
+//! on: keccak, the RLP of trie nodes and the proof walk. They need only
+//! `core` and `alloc`, so a build without the `std` feature is `no_std`
+//! and keeps this module alone, for zkVM guests and other constrained
+//! environments that cross-check a witness with the code that made it.
+
+pub mod keccak;
+pub mod nodes;
+pub mod proof;
@@ -0,0 +1,98 @@
+//! The RLP of trie nodes: nibble paths, their hex-prefix encoding, and
+//! the lengths of items padded to a fixed size.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use rlp::{DecoderError, Rlp};
+
+use super::keccak::keccak256;
+
+/// Items in a branch node: 16 children plus the value slot.
+pub const BRANCH_NODE_ITEMS: usize = 17;
+
+/// Root of a trie with no entries, `keccak256(rlp(""))`.
+pub const EMPTY_TRIE_ROOT: [u8; 32] = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+/// Code hash of accounts without code, `keccak256("")`.
+pub const EMPTY_CODE_HASH: [u8; 32] = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
+/// Splits `bytes` into nibbles, high nibble first.
+pub fn nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+/// Splits `key` into the nibble path used by the secure trie.
+pub fn key_nibbles(key: &[u8]) -> Vec<u8> {
+    nibbles(&keccak256(key))
+}
+
+/// Decodes the hex-prefix encoded path of an extension or leaf node into
+/// its nibbles and whether the node is a leaf.
+pub fn decode_compact_path(encoded: &[u8]) -> Result<(Vec<u8>, bool), DecoderError> {
+    let first = *encoded
+        .first()
+        .ok_or(DecoderError::Custom("empty compact path"))?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return Err(DecoderError::Custom("invalid compact path flag"));
+    }
+
+    let mut nibbles = Vec::with_capacity(encoded.len() * 2);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    for byte in &encoded[1..] {
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 0x0f);
+    }
+
+    Ok((nibbles, flag >= 2))
+}
+
+/// Splits a proof padded to `node_len` bytes per node back into its first
+/// `depth` nodes, dropping each node's padding.
+pub fn unpad_proof(flat: &[u8], node_len: usize, depth: usize) -> Vec<Vec<u8>> {
+    flat.chunks(node_len)
+        .take(depth)
+        .filter_map(|slot| {
+            let info = Rlp::new(slot).payload_info().ok()?;
+            slot.get(..info.header_len + info.value_len)
+                .map(<[u8]>::to_vec)
+        })
+        .collect()
+}
+
+/// Hex-prefix encodes `nibbles` as the path of a leaf or extension node,
+/// the inverse of [`decode_compact_path`].
+pub fn encode_compact_path(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = if is_leaf { 2 } else { 0 } + (nibbles.len() % 2) as u8;
+    let (first, rest) = match nibbles.len() % 2 {
+        1 => ((flag << 4) | nibbles[0], &nibbles[1..]),
+        _ => (flag << 4, nibbles),
+    };
+    let mut encoded = vec![first];
+    encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    encoded
+}
+
+/// Length of the RLP item at the start of `data`, such as a header list;
+/// anything after it, such as the zero padding circuits use, is not part
+/// of the item.
+pub fn encoded_len(data: &[u8]) -> Result<usize, DecoderError> {
+    let info = Rlp::new(data).payload_info()?;
+    let len = info.header_len + info.value_len;
+    if len > data.len() {
+        return Err(DecoderError::RlpIsTooShort);
+    }
+    Ok(len)
+}
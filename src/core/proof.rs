@@ -0,0 +1,166 @@
+//! The Merkle-Patricia proof walk every proof the crate emits or reads is
+//! checked with.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use rlp::{DecoderError, Rlp};
+
+use super::keccak::keccak256;
+use super::nodes::{decode_compact_path, BRANCH_NODE_ITEMS, EMPTY_TRIE_ROOT};
+
+/// How the hash chain of a proof breaks, at the node it names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Break {
+    /// The proof stops before the key is resolved, where the node hashing
+    /// to `hash` would follow.
+    Missing { hash: [u8; 32] },
+    /// The node is shorter than its RLP header declares.
+    Truncated { len: usize, declared: usize },
+    /// The node does not hash to the reference its parent holds.
+    Mismatch,
+    /// The node does not decode as a trie node.
+    Invalid(DecoderError),
+}
+
+/// A [`Break`] in a proof and the index of the node it is at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofBreak {
+    pub node: usize,
+    pub kind: Break,
+}
+
+impl ProofBreak {
+    /// The break as a report names it, e.g. "node 3 is truncated: 412 of
+    /// the 532 bytes its RLP header declares".
+    pub fn describe(&self, nodes: usize) -> String {
+        let parent = match self.node {
+            0 => "the root".to_string(),
+            node => format!("node {}", node - 1),
+        };
+        match &self.kind {
+            Break::Missing { .. } => format!(
+                "the proof stops after {} node(s), before the key is resolved; its last nodes \
+                 are missing",
+                nodes
+            ),
+            Break::Truncated { len, declared } => format!(
+                "node {} is truncated: {} of the {} bytes its RLP header declares",
+                self.node, len, declared
+            ),
+            Break::Mismatch => format!(
+                "node {} does not hash to the reference {} holds",
+                self.node, parent
+            ),
+            Break::Invalid(e) => format!("node {} is not a valid trie node: {:?}", self.node, e),
+        }
+    }
+}
+
+/// Walks `proof` from `root` along `nibbles`, checking every node against
+/// the reference held by its parent. Returns the leaf value when the key is
+/// in the trie and `None` when the proof shows it is absent.
+pub fn verify_proof(
+    root: &[u8],
+    nibbles: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, DecoderError> {
+    walk_proof(root, nibbles, proof).map_err(|broken| match broken.kind {
+        Break::Missing { .. } => DecoderError::Custom("proof ends before the key is resolved"),
+        Break::Truncated { .. } | Break::Mismatch => {
+            DecoderError::Custom("proof node does not match its parent reference")
+        }
+        Break::Invalid(e) => e,
+    })
+}
+
+/// [`verify_proof`], telling where the hash chain breaks when it does.
+pub fn walk_proof(
+    root: &[u8],
+    nibbles: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, ProofBreak> {
+    if root == EMPTY_TRIE_ROOT && proof.is_empty() {
+        return Ok(None);
+    }
+
+    // A 32 byte hash of the next proof node, or a shorter node embedded in
+    // its parent.
+    let mut reference = root.to_vec();
+    let mut position = 0;
+    let mut index = 0;
+    loop {
+        let node = if reference.len() == 32 {
+            let broken = |kind| ProofBreak { node: index, kind };
+            let node = proof.get(index).ok_or_else(|| {
+                let mut hash = [0; 32];
+                hash.copy_from_slice(&reference);
+                broken(Break::Missing { hash })
+            })?;
+            if keccak256(node) != reference.as_slice() {
+                let declared = rlp::PayloadInfo::from(node).map(|info| info.total());
+                return Err(broken(match declared {
+                    Ok(declared) if declared > node.len() => Break::Truncated {
+                        len: node.len(),
+                        declared,
+                    },
+                    _ => Break::Mismatch,
+                }));
+            }
+            index += 1;
+            node.clone()
+        } else {
+            reference
+        };
+        // The node last taken from the proof, which holds any embedded one.
+        let invalid = |e| ProofBreak {
+            node: index.saturating_sub(1),
+            kind: Break::Invalid(e),
+        };
+
+        let rlp = Rlp::new(&node);
+        let next = match rlp.item_count().map_err(invalid)? {
+            BRANCH_NODE_ITEMS => {
+                let Some(&nibble) = nibbles.get(position) else {
+                    let value = rlp.at(16).and_then(|value| value.data()).map_err(invalid)?;
+                    return Ok((!value.is_empty()).then(|| value.to_vec()));
+                };
+                position += 1;
+                rlp.at(nibble as usize).map_err(invalid)?
+            }
+            2 => {
+                let (path, is_leaf) = rlp
+                    .at(0)
+                    .and_then(|path| decode_compact_path(path.data()?))
+                    .map_err(invalid)?;
+                if !nibbles[position.min(nibbles.len())..].starts_with(&path) {
+                    return Ok(None);
+                }
+                position += path.len();
+                let item = rlp.at(1).map_err(invalid)?;
+                if is_leaf {
+                    if position != nibbles.len() {
+                        return Ok(None);
+                    }
+                    return Ok(Some(item.data().map_err(invalid)?.to_vec()));
+                }
+                item
+            }
+            _ => {
+                return Err(invalid(DecoderError::Custom(
+                    "unexpected trie node item count",
+                )))
+            }
+        };
+
+        if next.is_empty() {
+            return Ok(None);
+        }
+        reference = if next.is_list() {
+            next.as_raw().to_vec()
+        } else {
+            next.data().map_err(invalid)?.to_vec()
+        };
+    }
+}
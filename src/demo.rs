@@ -0,0 +1,195 @@
+//! The offline reference run behind the `demo` command: mainnet block
+//! 12965000, the London activation block, with the first storage slot of
+//! the USDT contract, served from recorded RPC responses.
+
+use futures::future::BoxFuture;
+use web3::types::{Bytes, Proof, TransactionReceipt, H160, H256, U256};
+
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::options::GenOptions;
+use crate::params::Params;
+use crate::source::EthDataSource;
+use crate::{
+    trie, ACCOUNT_PROOF_MAX_DEPTH, BLOCK_HEADER_RLP_BYTES, PROOF_BYTES_LEN, STORAGE_PROOF_MAX_DEPTH,
+};
+
+pub const BLOCK: u64 = 12_965_000;
+
+/// The circuit and manifest the demo writes next to its parameters.
+pub const CIRCUIT: &str = include_str!("main.nr");
+pub const NARGO_TOML: &str = include_str!("../Nargo.toml");
+
+/// The reference circuit, which checks the byte layout of the same
+/// parameters with the standard library alone, and its manifest.
+#[cfg(feature = "reference-circuit")]
+pub const REFERENCE_CIRCUIT: &str = include_str!("../fixtures/reference/src/main.nr");
+#[cfg(feature = "reference-circuit")]
+pub const REFERENCE_NARGO_TOML: &str = include_str!("../fixtures/reference/Nargo.toml");
+
+const RECORDED_BLOCK: &str = include_str!("../fixtures/demo_block.json");
+const RECORDED_PROOF: &str = include_str!("../fixtures/demo_proof.json");
+
+/// Serves the recorded block and proof, and nothing else.
+pub struct Recorded;
+
+fn not_recorded(what: &str) -> Error {
+    Error::Unsupported(format!("the demo has no recorded {}", what))
+}
+
+fn recorded<T: serde::de::DeserializeOwned>(json: &str) -> Result<T> {
+    serde_json::from_str(json)
+        .map_err(|e| Error::Decode(format!("invalid recorded response: {}", e)))
+}
+
+impl EthDataSource for Recorded {
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>> {
+        Box::pin(async { Ok(1) })
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>> {
+        Box::pin(async move {
+            if number != BLOCK {
+                return Err(not_recorded(&format!("block {}", number)));
+            }
+            recorded(RECORDED_BLOCK)
+        })
+    }
+
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>> {
+        Box::pin(async move {
+            if (account, keys.as_slice(), block) != (account_address(), &[U256::zero()][..], BLOCK)
+            {
+                return Err(not_recorded(&format!(
+                    "proof of {:?} {:?} at block {}",
+                    account, keys, block
+                )));
+            }
+            recorded(RECORDED_PROOF)
+        })
+    }
+
+    fn get_code(&self, _: H160, _: u64) -> BoxFuture<'_, Result<Bytes>> {
+        Box::pin(async { Err(not_recorded("code")) })
+    }
+
+    fn get_receipts(&self, _: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>> {
+        Box::pin(async { Err(not_recorded("receipts")) })
+    }
+}
+
+fn account_address() -> H160 {
+    "dac17f958d2ee523a2206206994597c13d831ec7"
+        .parse()
+        .expect("valid address")
+}
+
+/// Arguments for the recorded account and slot; everything else is left at
+/// the command line defaults.
+pub fn args() -> GenOptions {
+    GenOptions::new("recorded", BLOCK, account_address(), Some(H256::zero()))
+}
+
+fn field<'a>(params: &'a Params, name: &str) -> Result<&'a [u8]> {
+    params
+        .fields
+        .get_bytes(name)
+        .ok_or_else(|| Error::Decode(format!("the parameters have no {}", name)))
+}
+
+fn depth(params: &Params, name: &str) -> Result<usize> {
+    params
+        .fields
+        .get_int(name)
+        .map(|depth| depth as usize)
+        .ok_or_else(|| Error::Decode(format!("the parameters have no {}", name)))
+}
+
+/// The nodes of the flattened proof `name`, checked against the layout the
+/// circuit reads: exactly `max_depth` slots of [`PROOF_BYTES_LEN`] bytes,
+/// the first `depth` each holding one node followed by zeros and the rest
+/// all zero.
+fn padded_proof(params: &Params, name: &str, max_depth: usize) -> Result<Vec<Vec<u8>>> {
+    let failed = |check: &str| Error::Circuit(format!("the {} {} check fails", name, check));
+    let flat = field(params, name)?;
+    let depth = depth(params, &format!("{}_depth", name))?;
+    if flat.len() != max_depth * PROOF_BYTES_LEN || depth > max_depth {
+        return Err(failed("length"));
+    }
+    let nodes = trie::unpad_proof(flat, PROOF_BYTES_LEN, depth);
+    let padded = flat.chunks(PROOF_BYTES_LEN).enumerate().all(|(i, slot)| {
+        let used = nodes.get(i).map_or(0, Vec::len);
+        slot[used..].iter().all(|&byte| byte == 0)
+    });
+    if nodes.len() != depth || !padded {
+        return Err(failed("depth"));
+    }
+    Ok(nodes)
+}
+
+/// Makes the circuit's checks on prover parameters natively: the header
+/// hashes to the block hash, the account proof leads from its state root to
+/// the account value, and the storage proof from the account's storage root
+/// to the storage value. The byte layout is checked first: padded lengths,
+/// zero padding, depths that count the nodes, and a header split at its
+/// state root.
+pub fn verify(params: &Params) -> Result<()> {
+    let failed = |check: &str| Error::Circuit(format!("the {} check fails", check));
+    let decode_error = |e| Error::Decode(format!("Failed to decode proof node: {:?}", e));
+
+    let rlp = field(params, "block_header_rlp")?;
+    let head_len = depth(params, "block_header_rlp_head_len")?;
+    let tail_len = depth(params, "block_header_rlp_tail_len")?;
+    let header = rlp
+        .get(..head_len + 32 + tail_len)
+        .filter(|_| rlp.len() == BLOCK_HEADER_RLP_BYTES)
+        .ok_or_else(|| failed("header length"))?;
+    if rlp[header.len()..].iter().any(|&byte| byte != 0) {
+        return Err(failed("header padding"));
+    }
+    if keccak256(header) != field(params, "block_hash")? {
+        return Err(failed("block hash"));
+    }
+    // The split has to fall on the header's own state root field.
+    let state_root = &header[head_len..head_len + 32];
+    if rlp::Rlp::new(header).at(3)?.data()? != state_root {
+        return Err(failed("header split"));
+    }
+
+    let account_proof = padded_proof(params, "account_proof", ACCOUNT_PROOF_MAX_DEPTH)?;
+    let account = trie::verify_proof(
+        state_root,
+        &trie::key_nibbles(field(params, "account_key")?),
+        &account_proof,
+    )
+    .map_err(decode_error)?;
+    let account_value = field(params, "account_value")?;
+    if account.as_deref() != Some(account_value) {
+        return Err(failed("account proof"));
+    }
+    let storage_root = field(params, "storage_root")?;
+    if rlp::Rlp::new(account_value).at(2)?.data()? != storage_root {
+        return Err(failed("storage root"));
+    }
+
+    let storage_proof = padded_proof(params, "storage_proof", STORAGE_PROOF_MAX_DEPTH)?;
+    let storage = trie::verify_proof(
+        storage_root,
+        &trie::key_nibbles(field(params, "storage_key")?),
+        &storage_proof,
+    )
+    .map_err(decode_error)?;
+    let value = match storage {
+        Some(leaf) => U256::from_big_endian(rlp::Rlp::new(&leaf).data()?),
+        None => U256::zero(),
+    };
+    if value != U256::from_big_endian(field(params, "storage_value")?) {
+        return Err(failed("storage proof"));
+    }
+    Ok(())
+}
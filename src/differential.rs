@@ -0,0 +1,312 @@
+//! Cross-checks the header encoding and the proof verifier against
+//! independent reference implementations on real blocks: the
+//! `differential` command, built with the feature of the same name.
+//!
+//! The references are alloy's: `alloy-consensus` encodes the header from
+//! the block the provider returns and `alloy-trie` verifies the proofs.
+//! Neither shares code with [`crate::header`] or [`crate::trie`].
+
+use std::ops::RangeInclusive;
+
+use alloy_consensus::Header;
+use alloy_primitives::{Bytes, B256};
+use alloy_trie::Nibbles;
+use rlp::RlpStream;
+use serde::de::DeserializeOwned;
+use serde_json::Value as Json;
+use web3::types::{Proof, H160, H256, U256};
+
+use crate::chain_spec::ChainSpec;
+use crate::error::{Error, Result};
+use crate::header::{self, Layout};
+use crate::keccak::keccak256;
+use crate::source::EthDataSource;
+use crate::trie;
+
+fn field<T: DeserializeOwned>(block: &Json, name: &str) -> Result<T> {
+    serde_json::from_value(block[name].clone())
+        .map_err(|e| Error::Decode(format!("invalid {}: {}", name, e)))
+}
+
+/// The reference header encoding: alloy's [`Header`], read from the
+/// fields of the block.
+fn reference_header(block: &Json) -> Result<Vec<u8>> {
+    let header: Header = serde_json::from_value(block.clone())
+        .map_err(|e| Error::Decode(format!("the reference cannot read the header: {}", e)))?;
+    Ok(alloy_rlp::encode(&header))
+}
+
+/// Where two encodings of a header first differ, named by the field of
+/// the reference that holds that byte.
+fn first_divergence(ours: &[u8], reference: &[u8]) -> String {
+    let offset = ours
+        .iter()
+        .zip(reference)
+        .position(|(a, b)| a != b)
+        .unwrap_or(ours.len().min(reference.len()));
+    let field = header::field_spans(reference, Layout::Ethereum)
+        .ok()
+        .and_then(|spans| {
+            spans
+                .into_iter()
+                .take_while(|span| span.offset <= offset)
+                .last()
+        })
+        .map_or("the list header", |span| span.name);
+    format!(
+        "our {} byte encoding differs from the reference's {} bytes at byte {} (in {})",
+        ours.len(),
+        reference.len(),
+        offset,
+        field
+    )
+}
+
+/// The reference proof verifier, alloy-trie's: checks that `proof` shows
+/// `value` at `key` under `root`, or the key's absence for `None`.
+fn reference_verify(
+    root: &[u8],
+    key: &[u8],
+    proof: &[Vec<u8>],
+    value: Option<Vec<u8>>,
+) -> std::result::Result<(), String> {
+    let nodes: Vec<Bytes> = proof.iter().cloned().map(Bytes::from).collect();
+    alloy_trie::proof::verify_proof(
+        B256::from_slice(root),
+        Nibbles::unpack(keccak256(key)),
+        value,
+        &nodes,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Runs both verifiers on one proof and describes any disagreement,
+/// between them or with the value the provider reported.
+fn compare_proof(
+    what: &str,
+    root: &[u8],
+    key: &[u8],
+    proof: &[Vec<u8>],
+    expected: Option<Vec<u8>>,
+) -> Option<String> {
+    let show = |value: &Option<Vec<u8>>| match value {
+        Some(value) => format!("0x{}", hex::encode(value)),
+        None => "absent".to_string(),
+    };
+    match trie::verify_proof(root, &trie::key_nibbles(key), proof) {
+        Ok(ours) => match reference_verify(root, key, proof, ours.clone()) {
+            Err(e) => Some(format!(
+                "{}: we prove {}, the reference rejects it ({})",
+                what,
+                show(&ours),
+                e
+            )),
+            Ok(()) if ours != expected => Some(format!(
+                "{}: both prove {}, but the provider reported {}",
+                what,
+                show(&ours),
+                show(&expected)
+            )),
+            Ok(()) => None,
+        },
+        Err(e) => reference_verify(root, key, proof, expected.clone())
+            .is_ok()
+            .then(|| {
+                format!(
+                    "{}: we reject the proof ({:?}), the reference proves {}",
+                    what,
+                    e,
+                    show(&expected)
+                )
+            }),
+    }
+}
+
+fn proof_divergences(proof: &Proof, account: H160, state_root: &[u8]) -> Vec<String> {
+    let nodes = |proof: &[web3::types::Bytes]| -> Vec<Vec<u8>> {
+        proof.iter().map(|node| node.0.clone()).collect()
+    };
+    // Providers report zero hashes for what the trie commits to as empty.
+    let empty_or = |hash: H256, empty: [u8; 32]| match hash.is_zero() {
+        true => H256(empty),
+        false => hash,
+    };
+    let storage_root = empty_or(proof.storage_hash, trie::EMPTY_TRIE_ROOT);
+    let code_hash = empty_or(proof.code_hash, trie::EMPTY_CODE_HASH);
+    let mut account_value = RlpStream::new_list(4);
+    account_value
+        .append(&proof.nonce)
+        .append(&proof.balance)
+        .append(&storage_root)
+        .append(&code_hash);
+    let exists = !(proof.nonce.is_zero()
+        && proof.balance.is_zero()
+        && code_hash.0 == trie::EMPTY_CODE_HASH
+        && storage_root.0 == trie::EMPTY_TRIE_ROOT);
+    let mut divergences: Vec<String> = compare_proof(
+        &format!("account {:?}", account),
+        state_root,
+        account.as_bytes(),
+        &nodes(&proof.account_proof),
+        exists.then(|| account_value.out().to_vec()),
+    )
+    .into_iter()
+    .collect();
+    for slot in &proof.storage_proof {
+        let mut key = [0u8; 32];
+        slot.key.to_big_endian(&mut key);
+        divergences.extend(compare_proof(
+            &format!("slot {:?}", H256(key)),
+            storage_root.as_bytes(),
+            &key,
+            &nodes(&slot.proof),
+            (!slot.value.is_zero()).then(|| rlp::encode(&slot.value).to_vec()),
+        ));
+    }
+    divergences
+}
+
+/// Our header encoding of `block` against the reference one and the block
+/// hash, with a line for each divergence.
+fn header_divergences(block: &Json, number: u64, spec: &ChainSpec) -> Result<Vec<String>> {
+    let hash: H256 = field(block, "hash")?;
+    let reference = reference_header(block)?;
+    let mut divergences = Vec::new();
+    if keccak256(&reference) != hash.0 {
+        divergences.push(format!(
+            "the reference encoding does not hash to the block hash {:?}",
+            hash
+        ));
+    }
+    match header::from_rpc(block.clone(), number, spec) {
+        Ok((ours, _)) if ours.encode() != reference => {
+            divergences.push(first_divergence(&ours.encode(), &reference))
+        }
+        Ok(_) => {}
+        Err(e) => divergences.push(format!("our header encoding failed: {}", e)),
+    }
+    Ok(divergences)
+}
+
+/// Cross-checks every block of `blocks`: our header encoding against the
+/// reference one and the block hash, and, with `account`, both verifiers on
+/// its proof and those of `slots`. Prints a line per block and each
+/// divergence; fails if there is any.
+pub async fn run(
+    source: &dyn EthDataSource,
+    blocks: RangeInclusive<u64>,
+    account: Option<H160>,
+    slots: &[U256],
+) -> Result<()> {
+    let spec = ChainSpec::known(source.chain_id().await?);
+    if spec.layout != Layout::Ethereum || spec.seal_fields {
+        return Err(Error::Unsupported(
+            "the reference header encoder covers the Ethereum header layout only".to_string(),
+        ));
+    }
+    let mut diverged = 0;
+    for number in blocks {
+        let block = source.get_block(number).await?;
+        if block.is_null() {
+            return Err(Error::UnminedBlock(format!(
+                "block {} was not found",
+                number
+            )));
+        }
+        let mut divergences = header_divergences(&block, number, &spec)?;
+        if let Some(account) = account {
+            let state_root: H256 = field(&block, "stateRoot")?;
+            let proof = source
+                .get_proof(account, slots.to_vec(), number)
+                .await?
+                .ok_or_else(|| {
+                    Error::UnexpectedValue(format!("no proof returned for block {}", number))
+                })?;
+            divergences.extend(proof_divergences(&proof, account, state_root.as_bytes()));
+        }
+        match divergences.is_empty() {
+            true => log::info!("Block {}: header and proofs agree", number),
+            false => log::info!("Block {}: {} divergence(s)", number, divergences.len()),
+        }
+        for divergence in &divergences {
+            log::info!("  {}", divergence);
+        }
+        diverged += divergences.len();
+    }
+    if diverged > 0 {
+        return Err(Error::Verification(format!(
+            "{} divergence(s) from the reference implementations",
+            diverged
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo;
+
+    /// Recorded mainnet blocks: the genesis block, a proof-of-work block of
+    /// 2020, the London demo block and a Cancun block.
+    const CORPUS: &[&str] = &[
+        include_str!("../fixtures/genesis_block.json"),
+        include_str!("../fixtures/chains/1_11117104.json"),
+        include_str!("../fixtures/demo_block.json"),
+        include_str!("../fixtures/chains/1_19449567.json"),
+    ];
+
+    fn block(json: &str) -> (Json, u64) {
+        let block: Json = serde_json::from_str(json).unwrap();
+        let number = field::<U256>(&block, "number").unwrap().low_u64();
+        (block, number)
+    }
+
+    fn recorded_proof() -> Proof {
+        serde_json::from_str(include_str!("../fixtures/demo_proof.json")).unwrap()
+    }
+
+    #[test]
+    fn the_recorded_headers_agree_with_the_reference() {
+        let spec = ChainSpec::known(1);
+        for json in CORPUS {
+            let (block, number) = block(json);
+            assert_eq!(
+                header_divergences(&block, number, &spec).unwrap(),
+                Vec::<String>::new(),
+                "block {}",
+                number
+            );
+        }
+    }
+
+    #[test]
+    fn a_changed_header_field_no_longer_hashes_to_the_block_hash() {
+        let (mut block, number) = block(CORPUS[2]);
+        block["gasUsed"] = "0x1".into();
+        let divergences = header_divergences(&block, number, &ChainSpec::known(1)).unwrap();
+        assert!(divergences[0].contains("does not hash to the block hash"));
+    }
+
+    #[test]
+    fn the_recorded_proofs_agree_with_the_reference() {
+        let (block, _) = block(CORPUS[2]);
+        let state_root: H256 = field(&block, "stateRoot").unwrap();
+        let proof = recorded_proof();
+        assert_eq!(
+            proof_divergences(&proof, demo::args().account, state_root.as_bytes()),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn a_value_the_proof_does_not_show_diverges() {
+        let (block, _) = block(CORPUS[2]);
+        let state_root: H256 = field(&block, "stateRoot").unwrap();
+        let mut proof = recorded_proof();
+        proof.storage_proof[0].value += U256::one();
+        let divergences = proof_divergences(&proof, demo::args().account, state_root.as_bytes());
+        assert_eq!(divergences.len(), 1);
+        assert!(divergences[0].contains("the provider reported"));
+    }
+}
@@ -0,0 +1,368 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+use ethereum_types::U256;
+
+use crate::params::{Params, Value};
+
+/// How 256-bit numeric outputs (storage keys and values) are laid out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum NumericEncoding {
+    /// 32 big-endian bytes
+    #[default]
+    BeBytes,
+    /// 32 little-endian bytes
+    LeBytes,
+    /// Four 64-bit limbs, least significant first
+    Limbs64,
+    /// Two 128-bit limbs, least significant first
+    Limbs128,
+}
+
+impl NumericEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NumericEncoding::BeBytes => "be-bytes",
+            NumericEncoding::LeBytes => "le-bytes",
+            NumericEncoding::Limbs64 => "limbs64",
+            NumericEncoding::Limbs128 => "limbs128",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            NumericEncoding::BeBytes,
+            NumericEncoding::LeBytes,
+            NumericEncoding::Limbs64,
+            NumericEncoding::Limbs128,
+        ]
+        .into_iter()
+        .find(|encoding| encoding.name() == name)
+    }
+
+    pub fn encode(&self, value: U256) -> Value {
+        let mut bytes = [0u8; 32];
+        value.to_big_endian(&mut bytes);
+        match self {
+            NumericEncoding::BeBytes => Value::Bytes(bytes.to_vec()),
+            NumericEncoding::LeBytes => {
+                bytes.reverse();
+                Value::Bytes(bytes.to_vec())
+            }
+            NumericEncoding::Limbs64 => limbs(&bytes, 8),
+            NumericEncoding::Limbs128 => limbs(&bytes, 16),
+        }
+    }
+
+    /// Reverses [`NumericEncoding::encode`], also accepting limbs read back
+    /// from TOML as hex strings.
+    pub fn decode(&self, value: &Value) -> Option<U256> {
+        match (self, value) {
+            (NumericEncoding::BeBytes, Value::Bytes(bytes)) if bytes.len() == 32 => {
+                Some(U256::from_big_endian(bytes))
+            }
+            (NumericEncoding::LeBytes, Value::Bytes(bytes)) if bytes.len() == 32 => {
+                Some(U256::from_little_endian(bytes))
+            }
+            (NumericEncoding::Limbs64 | NumericEncoding::Limbs128, Value::Array(limbs)) => {
+                let mut be_bytes = Vec::with_capacity(32);
+                for limb in limbs.iter().rev() {
+                    match limb {
+                        Value::Hex(bytes) => be_bytes.extend_from_slice(bytes),
+                        Value::Str(text) => {
+                            be_bytes.extend(hex::decode(text.trim_start_matches("0x")).ok()?)
+                        }
+                        _ => return None,
+                    }
+                }
+                (be_bytes.len() == 32).then(|| U256::from_big_endian(&be_bytes))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Bytes the RLP form of a storage value is padded to: a one-byte prefix
+/// and up to 32 bytes of value.
+pub const STORAGE_VALUE_RLP_BYTES: usize = 33;
+
+/// How the storage value is laid out. The trimmed and RLP forms are of
+/// variable length, so they are zero-padded on the right and their length
+/// is emitted as `storage_value_len`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum ValueForm {
+    /// The value in the numeric encoding, 32 bytes for be-bytes
+    #[default]
+    Padded,
+    /// The big-endian bytes of the value without leading zeros, padded to 32
+    Trimmed,
+    /// The RLP of the storage trie leaf, as the proof holds it, padded to 33
+    Rlp,
+}
+
+impl ValueForm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueForm::Padded => "padded",
+            ValueForm::Trimmed => "trimmed",
+            ValueForm::Rlp => "rlp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ValueForm::Padded, ValueForm::Trimmed, ValueForm::Rlp]
+            .into_iter()
+            .find(|form| form.name() == name)
+    }
+
+    /// Checks that the form applies to `encoding`: the padded-out forms are
+    /// byte strings, which only the be-bytes encoding leaves unchanged.
+    pub fn check(&self, encoding: NumericEncoding) -> Result<(), String> {
+        match *self != ValueForm::Padded && encoding != NumericEncoding::BeBytes {
+            true => Err(format!(
+                "--value-form {} lays out bytes; it takes --numeric-encoding be-bytes",
+                self.name()
+            )),
+            false => Ok(()),
+        }
+    }
+
+    /// `value` in this form, with its length for the padded-out forms.
+    pub fn encode(
+        &self,
+        value: U256,
+        encoding: NumericEncoding,
+    ) -> Result<(Value, Option<usize>), String> {
+        self.check(encoding)?;
+        let (mut bytes, max) = match self {
+            ValueForm::Padded => return Ok((encoding.encode(value), None)),
+            ValueForm::Trimmed => {
+                let mut be_bytes = [0u8; 32];
+                value.to_big_endian(&mut be_bytes);
+                (be_bytes[32 - value.bits().div_ceil(8)..].to_vec(), 32)
+            }
+            // A zero value has no leaf; it would be stored as the empty
+            // string, 0x80.
+            ValueForm::Rlp => (rlp::encode(&value).to_vec(), STORAGE_VALUE_RLP_BYTES),
+        };
+        let len = bytes.len();
+        bytes.resize(max, 0);
+        Ok((Value::Bytes(bytes), Some(len)))
+    }
+
+    /// Reverses [`ValueForm::encode`], given the emitted length `len`.
+    pub fn decode(
+        &self,
+        value: &Value,
+        len: Option<u64>,
+        encoding: NumericEncoding,
+    ) -> Option<U256> {
+        let unpadded = || match value {
+            Value::Bytes(bytes) => bytes.get(..len? as usize),
+            _ => None,
+        };
+        match self {
+            ValueForm::Padded => encoding.decode(value),
+            ValueForm::Trimmed => unpadded()
+                .filter(|bytes| bytes.len() <= 32)
+                .map(U256::from_big_endian),
+            ValueForm::Rlp => rlp::Rlp::new(unpadded()?).as_val().ok(),
+        }
+    }
+}
+
+/// The value form `params` record, padded when they record none.
+pub fn value_form(params: &Params) -> ValueForm {
+    match params.meta.get("value_form") {
+        Some(Value::Str(name)) => ValueForm::from_name(name).unwrap_or_default(),
+        _ => ValueForm::default(),
+    }
+}
+
+/// The storage value of `params`, read in the numeric encoding and value
+/// form they record.
+pub fn storage_value(params: &Params) -> Option<U256> {
+    let encoding = match params.meta.get("numeric_encoding") {
+        Some(Value::Str(name)) => NumericEncoding::from_name(name).unwrap_or_default(),
+        _ => NumericEncoding::default(),
+    };
+    value_form(params).decode(
+        params.fields.get("storage_value")?,
+        params.fields.get_int("storage_value_len"),
+        encoding,
+    )
+}
+
+/// Where `block_header_rlp` is padded. The header's length varies from
+/// block to block; the post-padded layout has it start at byte 0, which
+/// suits a keccak that absorbs from the front, the pre-padded one has it end
+/// at the last byte, and the exact one is not padded at all, for circuits
+/// sized to one header. Each is emitted with the header's length as
+/// `block_header_rlp_len`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum HeaderPadding {
+    /// Zeros after the header, up to the circuit's length
+    #[default]
+    Post,
+    /// Zeros before the header, up to the circuit's length
+    Pre,
+    /// The header alone, at its own length
+    None,
+}
+
+impl HeaderPadding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HeaderPadding::Post => "post",
+            HeaderPadding::Pre => "pre",
+            HeaderPadding::None => "none",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [HeaderPadding::Post, HeaderPadding::Pre, HeaderPadding::None]
+            .into_iter()
+            .find(|padding| padding.name() == name)
+    }
+
+    /// The first `len` bytes of `padded`, a post-padded header, laid out
+    /// with this padding and kept at the length of `padded` unless exact.
+    pub fn pad(&self, padded: &[u8], len: usize) -> Vec<u8> {
+        match self {
+            HeaderPadding::Post => padded.to_vec(),
+            HeaderPadding::Pre => {
+                let mut out = vec![0; padded.len() - len];
+                out.extend_from_slice(&padded[..len]);
+                out
+            }
+            HeaderPadding::None => padded[..len].to_vec(),
+        }
+    }
+
+    /// The encoded header in `rlp`, laid out with this padding. A header
+    /// is an RLP list, whose prefix is never zero, so the pre-padding ends
+    /// at the first nonzero byte.
+    pub fn unpad<'a>(&self, rlp: &'a [u8]) -> Option<&'a [u8]> {
+        let start = match self {
+            HeaderPadding::Pre => rlp.iter().position(|&byte| byte != 0)?,
+            _ => 0,
+        };
+        let len = crate::core::nodes::encoded_len(&rlp[start..]).ok()?;
+        rlp.get(start..start + len)
+    }
+}
+
+/// The header padding `params` record, post when they record none.
+pub fn header_padding(params: &Params) -> HeaderPadding {
+    match params.meta.get("header_padding") {
+        Some(Value::Str(name)) => HeaderPadding::from_name(name).unwrap_or_default(),
+        _ => HeaderPadding::default(),
+    }
+}
+
+fn limbs(be_bytes: &[u8; 32], limb_bytes: usize) -> Value {
+    Value::Array(
+        be_bytes
+            .rchunks(limb_bytes)
+            .map(|limb| Value::Hex(limb.to_vec()))
+            .collect(),
+    )
+}
+
+/// How byte arrays are written out. Noir reads `[u8; N]` inputs only as
+/// decimal arrays; the string encodings are for tooling that reads the
+/// files, and are recorded in `[meta]` so they read back as the same bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum ByteEncoding {
+    /// Arrays of decimal bytes
+    #[default]
+    Decimal,
+    /// `0x`-prefixed hex strings
+    Hex,
+    /// Standard base64 strings
+    Base64,
+}
+
+impl ByteEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ByteEncoding::Decimal => "decimal",
+            ByteEncoding::Hex => "hex",
+            ByteEncoding::Base64 => "base64",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            ByteEncoding::Decimal,
+            ByteEncoding::Hex,
+            ByteEncoding::Base64,
+        ]
+        .into_iter()
+        .find(|encoding| encoding.name() == name)
+    }
+
+    /// The string form of `bytes`, or `None` for decimal arrays.
+    pub fn encode(&self, bytes: &[u8]) -> Option<String> {
+        match self {
+            ByteEncoding::Decimal => None,
+            ByteEncoding::Hex => Some(format!("0x{}", hex::encode(bytes))),
+            ByteEncoding::Base64 => Some(BASE64.encode(bytes)),
+        }
+    }
+
+    pub fn decode(&self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            ByteEncoding::Decimal => None,
+            ByteEncoding::Hex => hex::decode(text.strip_prefix("0x")?).ok(),
+            ByteEncoding::Base64 => BASE64.decode(text).ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// An RLP list, the shape of a header, zero-padded after its end.
+    fn padded_header() -> impl Strategy<Value = (Vec<u8>, usize)> {
+        (
+            prop::collection::vec(prop::collection::vec(any::<u8>(), 0..=80), 1..=21),
+            0..=64usize,
+        )
+            .prop_map(|(items, padding)| {
+                let mut stream = rlp::RlpStream::new_list(items.len());
+                for item in &items {
+                    stream.append(item);
+                }
+                let mut padded = stream.out().to_vec();
+                let len = padded.len();
+                padded.resize(len + padding, 0);
+                (padded, len)
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn unpad_reverses_pad((padded, len) in padded_header()) {
+            for padding in [HeaderPadding::Post, HeaderPadding::Pre, HeaderPadding::None] {
+                let laid_out = padding.pad(&padded, len);
+                let expected = if padding == HeaderPadding::None { len } else { padded.len() };
+                prop_assert_eq!(laid_out.len(), expected);
+                match padding {
+                    HeaderPadding::Pre => prop_assert!(laid_out.ends_with(&padded[..len])),
+                    _ => prop_assert!(laid_out.starts_with(&padded[..len])),
+                }
+                prop_assert_eq!(padding.unpad(&laid_out), Some(&padded[..len]));
+                prop_assert_eq!(HeaderPadding::from_name(padding.name()), Some(padding));
+            }
+        }
+    }
+}
@@ -0,0 +1,608 @@
+use std::fs;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use openssl::derive::Deriver;
+use openssl::hash::MessageDigest;
+use openssl::md::Md;
+use openssl::pkey::{Id, PKey};
+use openssl::pkey_ctx::PkeyCtx;
+use openssl::sign::Signer;
+use openssl::symm::{self, Cipher};
+
+use crate::error::{Error, Result};
+
+/// First line of the age v1 format these files are written in, so that
+/// `age -d` decrypts them as well.
+const VERSION_LINE: &str = "age-encryption.org/v1";
+
+const X25519_INFO: &[u8] = b"age-encryption.org/v1/X25519";
+
+const RECIPIENT_PREFIX: &str = "age";
+const IDENTITY_PREFIX: &str = "AGE-SECRET-KEY-";
+
+/// Plaintext bytes per payload chunk.
+const CHUNK_BYTES: usize = 64 * 1024;
+const TAG_BYTES: usize = 16;
+
+/// Columns of the base64 lines wrapping a stanza body.
+const BODY_COLUMNS: usize = 64;
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// An X25519 public key age encrypts to, given as `age1...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipient(pub [u8; 32]);
+
+fn openssl_error(e: openssl::error::ErrorStack) -> Error {
+    Error::Encryption(e.to_string())
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn bech32_hrp_values(hrp: &str) -> Vec<u8> {
+    let mut values: Vec<u8> = hrp.bytes().map(|byte| byte >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|byte| byte & 31));
+    values
+}
+
+/// Regroups `data` from `from` bit to `to` bit values, rejecting leftover
+/// bits that are not zero padding.
+fn convert_bits(data: &[u8], from: u32, to: u32) -> Option<Vec<u8>> {
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    let mut out = Vec::new();
+    for &value in data {
+        accumulator = ((accumulator << from) | value as u32) & ((1 << (from + to - 1)) - 1);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((accumulator >> bits) & ((1 << to) - 1)) as u8);
+        }
+    }
+    if bits >= from || (accumulator << (to - bits)) & ((1 << to) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+/// Decodes a bech32 string with the human-readable part `hrp`, in either
+/// case, into its data bytes.
+fn bech32_decode(hrp: &str, text: &str) -> Option<Vec<u8>> {
+    let lower = text.to_ascii_lowercase();
+    if lower != text && text.to_ascii_uppercase() != text {
+        return None;
+    }
+    let data = lower.strip_prefix(&format!("{}1", hrp.to_ascii_lowercase()))?;
+    let values: Vec<u8> = data
+        .bytes()
+        .map(|byte| {
+            BECH32_CHARSET
+                .iter()
+                .position(|&c| c == byte)
+                .map(|i| i as u8)
+        })
+        .collect::<Option<_>>()?;
+    if values.len() < 6 {
+        return None;
+    }
+    let mut checked = bech32_hrp_values(&hrp.to_ascii_lowercase());
+    checked.extend(&values);
+    if bech32_polymod(&checked) != 1 {
+        return None;
+    }
+    convert_bits(&values[..values.len() - 6], 5, 8)
+}
+
+impl Recipient {
+    pub fn parse(text: &str) -> Result<Self> {
+        bech32_decode(RECIPIENT_PREFIX, text.trim())
+            .and_then(|key| key.try_into().ok())
+            .map(Recipient)
+            .ok_or_else(|| Error::Encryption(format!("{} is not an age1 X25519 recipient", text)))
+    }
+}
+
+/// Reads the `AGE-SECRET-KEY-1...` lines of the age identity file at
+/// `path`, skipping comments and blank lines.
+pub fn read_identities(path: &Path) -> Result<Vec<[u8; 32]>> {
+    let text = fs::read_to_string(path)?;
+    let identities: Vec<[u8; 32]> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            bech32_decode(IDENTITY_PREFIX, line)
+                .and_then(|key| key.try_into().ok())
+                .ok_or_else(|| {
+                    Error::Encryption(format!(
+                        "{} holds a line that is not an AGE-SECRET-KEY-1 identity",
+                        path.display()
+                    ))
+                })
+        })
+        .collect::<Result<_>>()?;
+    if identities.is_empty() {
+        return Err(Error::Encryption(format!(
+            "{} holds no identities",
+            path.display()
+        )));
+    }
+    Ok(identities)
+}
+
+fn random<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    openssl::rand::rand_bytes(&mut bytes).map_err(openssl_error)?;
+    Ok(bytes)
+}
+
+fn hkdf(key: &[u8], salt: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>> {
+    let mut ctx = PkeyCtx::new_id(Id::HKDF).map_err(openssl_error)?;
+    ctx.derive_init().map_err(openssl_error)?;
+    ctx.set_hkdf_md(Md::sha256()).map_err(openssl_error)?;
+    ctx.set_hkdf_key(key).map_err(openssl_error)?;
+    if !salt.is_empty() {
+        ctx.set_hkdf_salt(salt).map_err(openssl_error)?;
+    }
+    ctx.add_hkdf_info(info).map_err(openssl_error)?;
+    let mut out = vec![0; len];
+    ctx.derive(Some(&mut out)).map_err(openssl_error)?;
+    Ok(out)
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let key = PKey::hmac(key).map_err(openssl_error)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(openssl_error)?;
+    signer.sign_oneshot_to_vec(data).map_err(openssl_error)
+}
+
+fn seal(key: &[u8], nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut tag = [0; TAG_BYTES];
+    let mut sealed = symm::encrypt_aead(
+        Cipher::chacha20_poly1305(),
+        key,
+        Some(nonce),
+        &[],
+        plaintext,
+        &mut tag,
+    )
+    .map_err(openssl_error)?;
+    sealed.extend_from_slice(&tag);
+    Ok(sealed)
+}
+
+fn open(key: &[u8], nonce: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    let (ciphertext, tag) = sealed.split_at(sealed.len().checked_sub(TAG_BYTES)?);
+    symm::decrypt_aead(
+        Cipher::chacha20_poly1305(),
+        key,
+        Some(nonce),
+        &[],
+        ciphertext,
+        tag,
+    )
+    .ok()
+}
+
+fn x25519(secret: &[u8], public: &[u8]) -> Result<Vec<u8>> {
+    let secret = PKey::private_key_from_raw_bytes(secret, Id::X25519).map_err(openssl_error)?;
+    let public = PKey::public_key_from_raw_bytes(public, Id::X25519).map_err(openssl_error)?;
+    let mut deriver = Deriver::new(&secret).map_err(openssl_error)?;
+    deriver.set_peer(&public).map_err(openssl_error)?;
+    let shared = deriver.derive_to_vec().map_err(openssl_error)?;
+    if shared.iter().all(|&byte| byte == 0) {
+        return Err(Error::Encryption(
+            "the X25519 share is a low order point".to_string(),
+        ));
+    }
+    Ok(shared)
+}
+
+fn x25519_public(secret: &[u8]) -> Result<Vec<u8>> {
+    PKey::private_key_from_raw_bytes(secret, Id::X25519)
+        .and_then(|key| key.raw_public_key())
+        .map_err(openssl_error)
+}
+
+/// Nonce of payload chunk `index`: its big-endian counter and whether it
+/// is the last chunk.
+fn chunk_nonce(index: usize, last: bool) -> [u8; 12] {
+    let mut nonce = [0; 12];
+    nonce[3..11].copy_from_slice(&(index as u64).to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+fn payload_key(file_key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    hkdf(file_key, nonce, b"payload", 32)
+}
+
+/// Encrypts `plaintext` to every recipient in the age v1 format.
+pub fn encrypt(plaintext: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>> {
+    let file_key: [u8; 16] = random()?;
+    let mut header = format!("{}\n", VERSION_LINE);
+    for recipient in recipients {
+        let ephemeral = PKey::generate_x25519().map_err(openssl_error)?;
+        let secret = ephemeral.raw_private_key().map_err(openssl_error)?;
+        let share = ephemeral.raw_public_key().map_err(openssl_error)?;
+        let shared = x25519(&secret, &recipient.0)?;
+        let wrap_key = hkdf(
+            &shared,
+            &[&share[..], &recipient.0].concat(),
+            X25519_INFO,
+            32,
+        )?;
+        let body = STANDARD_NO_PAD.encode(seal(&wrap_key, &[0; 12], &file_key)?);
+        header.push_str(&format!("-> X25519 {}\n", STANDARD_NO_PAD.encode(&share)));
+        // A body line shorter than the full width ends the stanza, so a body
+        // filling its last line is followed by an empty one.
+        for line in body.as_bytes().chunks(BODY_COLUMNS) {
+            header.push_str(std::str::from_utf8(line).unwrap_or_default());
+            header.push('\n');
+        }
+        if body.len() % BODY_COLUMNS == 0 {
+            header.push('\n');
+        }
+    }
+    header.push_str("---");
+    let mac = hmac(&hkdf(&file_key, &[], b"header", 32)?, header.as_bytes())?;
+    header.push_str(&format!(" {}\n", STANDARD_NO_PAD.encode(mac)));
+
+    let nonce: [u8; 16] = random()?;
+    let key = payload_key(&file_key, &nonce)?;
+    let mut out = header.into_bytes();
+    out.extend_from_slice(&nonce);
+    let chunks: Vec<&[u8]> = if plaintext.is_empty() {
+        vec![&[]]
+    } else {
+        plaintext.chunks(CHUNK_BYTES).collect()
+    };
+    for (i, chunk) in chunks.iter().enumerate() {
+        out.extend(seal(&key, &chunk_nonce(i, i + 1 == chunks.len()), chunk)?);
+    }
+    Ok(out)
+}
+
+fn format_error(message: &str) -> Error {
+    Error::Encryption(format!("not an age encrypted file: {}", message))
+}
+
+/// Splits the header lines after the version line into stanzas, each its
+/// arguments and decoded body. Every stanza is checked, including those of
+/// types this module does not read, as age rejects a malformed header.
+fn parse_stanzas<'a>(lines: &[&'a str]) -> Result<Vec<(Vec<&'a str>, Vec<u8>)>> {
+    let mut stanzas = Vec::new();
+    let mut lines = lines.iter();
+    while let Some(line) = lines.next() {
+        let arguments: Vec<&str> = line
+            .strip_prefix("-> ")
+            .ok_or_else(|| format_error("a stanza does not start with ->"))?
+            .split(' ')
+            .collect();
+        if arguments
+            .iter()
+            .any(|argument| argument.is_empty() || !argument.bytes().all(|b| b.is_ascii_graphic()))
+        {
+            return Err(format_error("a stanza has an invalid argument"));
+        }
+        let mut body = String::new();
+        loop {
+            let line = lines
+                .next()
+                .filter(|line| !line.starts_with("-> "))
+                .ok_or_else(|| format_error("a stanza body does not end with a short line"))?;
+            if line.len() > BODY_COLUMNS {
+                return Err(format_error("a stanza body line is too long"));
+            }
+            body.push_str(line);
+            if line.len() < BODY_COLUMNS {
+                break;
+            }
+        }
+        let body = STANDARD_NO_PAD
+            .decode(&body)
+            .map_err(|_| format_error("a stanza body is not canonical base64"))?;
+        stanzas.push((arguments, body));
+    }
+    Ok(stanzas)
+}
+
+/// Decrypts an age v1 file encrypted to one of the X25519 `identities`.
+pub fn decrypt(data: &[u8], identities: &[[u8; 32]]) -> Result<Vec<u8>> {
+    let mut lines = Vec::new();
+    let mut rest = data;
+    let mac_line = loop {
+        let end = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| format_error("the header does not end"))?;
+        let line = std::str::from_utf8(&rest[..end])
+            .map_err(|_| format_error("the header is not text"))?;
+        rest = &rest[end + 1..];
+        if let Some(mac) = line.strip_prefix("--- ") {
+            break mac;
+        }
+        lines.push(line);
+    };
+    if lines.first() != Some(&VERSION_LINE) {
+        return Err(format_error(
+            "it does not start with the age v1 version line",
+        ));
+    }
+    let header_len = data.len() - rest.len() - mac_line.len() - 2;
+
+    let mut file_key = None;
+    for (arguments, body) in parse_stanzas(&lines[1..])? {
+        if arguments[0] != "X25519" {
+            continue;
+        }
+        let share = match arguments[1..] {
+            [share] => STANDARD_NO_PAD
+                .decode(share)
+                .ok()
+                .filter(|share| share.len() == 32),
+            _ => None,
+        }
+        .ok_or_else(|| format_error("an X25519 stanza has an invalid share"))?;
+        for identity in identities {
+            let shared = x25519(identity, &share)?;
+            let salt = [&share[..], &x25519_public(identity)?].concat();
+            let wrap_key = hkdf(&shared, &salt, X25519_INFO, 32)?;
+            if let Some(key) = open(&wrap_key, &[0; 12], &body).filter(|key| key.len() == 16) {
+                file_key = Some(key);
+                break;
+            }
+        }
+        if file_key.is_some() {
+            break;
+        }
+    }
+    let file_key = file_key.ok_or_else(|| {
+        Error::Encryption("the file is not encrypted to any of the identities".to_string())
+    })?;
+
+    let mac = STANDARD_NO_PAD
+        .decode(mac_line)
+        .map_err(|_| format_error("the header MAC is not base64"))?;
+    let expected = hmac(&hkdf(&file_key, &[], b"header", 32)?, &data[..header_len])?;
+    if mac.len() != expected.len() || !openssl::memcmp::eq(&mac, &expected) {
+        return Err(Error::Encryption(
+            "the header MAC does not match; the file was modified".to_string(),
+        ));
+    }
+
+    if rest.len() < 16 + TAG_BYTES {
+        return Err(format_error("the payload is truncated"));
+    }
+    let (nonce, payload) = rest.split_at(16);
+    let key = payload_key(&file_key, nonce)?;
+    let chunks: Vec<&[u8]> = payload.chunks(CHUNK_BYTES + TAG_BYTES).collect();
+    let mut plaintext = Vec::with_capacity(payload.len());
+    for (i, chunk) in chunks.iter().enumerate() {
+        let last = i + 1 == chunks.len();
+        let opened = open(&key, &chunk_nonce(i, last), chunk).ok_or_else(|| {
+            Error::Encryption(format!(
+                "payload chunk {} does not decrypt; the file was modified or truncated",
+                i
+            ))
+        })?;
+        if last && opened.is_empty() && i > 0 {
+            return Err(format_error("the payload ends with an empty chunk"));
+        }
+        plaintext.extend(opened);
+    }
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The identity of the age testkit vectors.
+    const TESTKIT_IDENTITY: &str =
+        "AGE-SECRET-KEY-1EGTZVFFV20835NWYV6270LXYVK2VKNX2MMDKWYKLMGR48UAWX40Q2P2LM0";
+
+    fn keypair() -> ([u8; 32], Recipient) {
+        let secret: [u8; 32] = random().unwrap();
+        let public = x25519_public(&secret).unwrap();
+        (secret, Recipient(public.try_into().unwrap()))
+    }
+
+    fn identity(text: &str) -> [u8; 32] {
+        bech32_decode(IDENTITY_PREFIX, text)
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Byte offset of the payload nonce, just past the header MAC line.
+    fn payload_start(file: &[u8]) -> usize {
+        let mac = file.windows(4).position(|w| w == b"\n---").unwrap();
+        mac + file[mac + 1..].iter().position(|&b| b == b'\n').unwrap() + 2
+    }
+
+    #[test]
+    fn decrypt_reverses_encrypt() {
+        let (secret, recipient) = keypair();
+        let (other, other_recipient) = keypair();
+        for len in [
+            0,
+            1,
+            CHUNK_BYTES - 1,
+            CHUNK_BYTES,
+            CHUNK_BYTES + 1,
+            3 * CHUNK_BYTES,
+        ] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+            let file = encrypt(&plaintext, &[recipient.clone(), other_recipient.clone()]).unwrap();
+            assert!(file.starts_with(b"age-encryption.org/v1\n-> X25519 "));
+            assert_eq!(
+                decrypt(&file, &[secret]).unwrap(),
+                plaintext,
+                "{} bytes",
+                len
+            );
+            assert_eq!(
+                decrypt(&file, &[other]).unwrap(),
+                plaintext,
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    /// Runs the decryption vectors of the age testkit under
+    /// `fixtures/age-testkit`: each is a block of `key: value` lines, a blank
+    /// line and the file, zlib compressed if marked so.
+    #[test]
+    fn the_age_testkit_vectors_decrypt_as_expected() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/age-testkit");
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            let data = fs::read(&path).unwrap();
+            let split = data.windows(2).position(|w| w == b"\n\n").unwrap();
+            let fields: Vec<(&str, &str)> = std::str::from_utf8(&data[..split])
+                .unwrap()
+                .lines()
+                .map(|line| line.split_once(": ").unwrap())
+                .collect();
+            let field = |key: &str| fields.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+            let file = match field("compressed") {
+                None => data[split + 2..].to_vec(),
+                #[cfg(feature = "compression")]
+                Some(_) => {
+                    miniz_oxide::inflate::decompress_to_vec_zlib(&data[split + 2..]).unwrap()
+                }
+                #[cfg(not(feature = "compression"))]
+                Some(_) => continue,
+            };
+            let identities = [identity(field("identity").unwrap_or(TESTKIT_IDENTITY))];
+            match (field("expect").unwrap(), decrypt(&file, &identities)) {
+                ("success", Ok(plaintext)) => assert_eq!(
+                    hex::encode(openssl::sha::sha256(&plaintext)),
+                    field("payload").unwrap(),
+                    "{}",
+                    name
+                ),
+                ("success", Err(e)) => panic!("{}: {}", name, e),
+                (_, Ok(_)) => panic!("{} decrypted", name),
+                (_, Err(_)) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn a_modified_header_fails_its_mac() {
+        let (secret, recipient) = keypair();
+        let file = encrypt(b"noir-mip", &[recipient]).unwrap();
+        let mac = file.windows(4).position(|w| w == b"--- ").unwrap() + 4;
+
+        let mut tampered = file.clone();
+        tampered[mac] = if tampered[mac] == b'A' { b'B' } else { b'A' };
+        let err = decrypt(&tampered, &[secret]).unwrap_err();
+        assert!(err.to_string().contains("MAC does not match"), "{}", err);
+
+        let mut truncated = file[..mac + 10].to_vec();
+        truncated.extend_from_slice(&file[payload_start(&file) - 1..]);
+        assert!(decrypt(&truncated, &[secret]).is_err());
+
+        // A stanza added after encryption is covered by the MAC too.
+        let mut grease = b"age-encryption.org/v1\n-> grease\n\n".to_vec();
+        grease.extend_from_slice(&file[b"age-encryption.org/v1\n".len()..]);
+        let err = decrypt(&grease, &[secret]).unwrap_err();
+        assert!(err.to_string().contains("MAC does not match"), "{}", err);
+    }
+
+    #[test]
+    fn a_modified_or_truncated_final_chunk_is_refused() {
+        let (secret, recipient) = keypair();
+        let plaintext = vec![7u8; CHUNK_BYTES + 100];
+        let file = encrypt(&plaintext, &[recipient]).unwrap();
+        let last_chunk = payload_start(&file) + 16 + CHUNK_BYTES + TAG_BYTES;
+
+        let mut tampered = file.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let err = decrypt(&tampered, &[secret]).unwrap_err();
+        assert!(err.to_string().contains("payload chunk 1"), "{}", err);
+
+        for end in [file.len() - 1, last_chunk + 1, last_chunk] {
+            let err = decrypt(&file[..end], &[secret]).unwrap_err();
+            assert!(
+                err.to_string().contains("does not decrypt"),
+                "{}: {}",
+                end,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn another_identity_finds_no_stanza() {
+        let (_, recipient) = keypair();
+        let (other, _) = keypair();
+        let file = encrypt(b"noir-mip", &[recipient]).unwrap();
+        let err = decrypt(&file, &[other]).unwrap_err();
+        assert!(err.to_string().contains("not encrypted to any"), "{}", err);
+    }
+
+    #[test]
+    fn bech32_checksums_match_the_bip_173_vectors() {
+        for valid in [
+            "A12UEL5L",
+            "a12uel5l",
+            "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            "11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+            "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+            "?1ezyfcl",
+        ] {
+            let (hrp, _) = valid.rsplit_once('1').unwrap();
+            assert!(bech32_decode(hrp, valid).is_some(), "{}", valid);
+        }
+        for (hrp, invalid) in [
+            ("a", "a12UEL5L"),
+            ("a", "A1G7SGD8"),
+            ("a", "a12uel5m"),
+            ("x", "x1b4n0q5v"),
+            ("li", "li1dgmt3"),
+            ("de", "de1lg7wt\u{ff}"),
+            ("abc", "ABC1DEFGOH"),
+            // Bech32m, whose checksum constant differs.
+            ("a", "a1lqfn3a"),
+        ] {
+            assert!(bech32_decode(hrp, invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn age_keys_decode_to_the_x25519_pair() {
+        // The key pair of the age crate's own tests.
+        let secret =
+            identity("AGE-SECRET-KEY-1GQ9778VQXMMJVE8SK7J6VT8UJ4HDQAJUVSFCWCM02D8GEWQ72PVQ2Y5J33");
+        let recipient =
+            Recipient::parse("age1t7rxyev2z3rw82stdlrrepyc39nvn86l5078zqkf5uasdy86jp6svpy7pa")
+                .unwrap();
+        assert_eq!(x25519_public(&secret).unwrap(), recipient.0);
+        assert!(
+            Recipient::parse("age1t7rxyev2z3rw82stdlrrepyc39nvn86l5078zqkf5uasdy86jp6svpy7pb")
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    #[cfg(feature = "rpc")]
+    Rpc(web3::Error),
+    Decode(String),
+    /// An invalid config file or chain spec.
+    Config(String),
+    Io(std::io::Error),
+    Signature(String),
+    Encryption(String),
+    Upload(String),
+    /// The `--notify-url` webhook could not be reached.
+    Notify(String),
+    /// A `--post-hook` command could not run or failed.
+    Hook(String),
+    Quorum(String),
+    LightClient(String),
+    Unsupported(String),
+    UnminedBlock(String),
+    Reorg(String),
+    Chain(String),
+    Circuit(String),
+    /// The run was stopped by a signal before it completed.
+    Interrupted(String),
+    /// The provider returned no proof; carries the raw response.
+    EmptyProof(String),
+    /// The proven value failed an `--expect-*` check.
+    UnexpectedValue(String),
+    /// A proof, proof node or header does not fit the circuit.
+    ProofTooDeep(String),
+    /// A header, proof or file does not match what it is checked against.
+    Verification(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "rpc")]
+            Error::Rpc(e) => write!(f, "RPC error: {}", e),
+            Error::Decode(message) => write!(f, "Decode error: {}", message),
+            Error::Config(message) => write!(f, "Config error: {}", message),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Signature(message) => write!(f, "Signature error: {}", message),
+            Error::Encryption(message) => write!(f, "Encryption error: {}", message),
+            Error::Upload(message) => write!(f, "Upload error: {}", message),
+            Error::Notify(message) => write!(f, "Notification error: {}", message),
+            Error::Hook(message) => write!(f, "Hook error: {}", message),
+            Error::Quorum(message) => write!(f, "Quorum error: {}", message),
+            Error::LightClient(message) => write!(f, "Light client error: {}", message),
+            Error::Unsupported(message) => write!(f, "Unsupported: {}", message),
+            Error::UnminedBlock(message) => write!(
+                f,
+                "{}; pick a mined block, ideally one that is already finalized",
+                message
+            ),
+            Error::Reorg(message) => write!(f, "Reorg detected: {}", message),
+            Error::Chain(message) => write!(f, "Chain error: {}", message),
+            Error::Circuit(message) => write!(f, "Circuit mismatch: {}", message),
+            Error::Interrupted(message) => write!(f, "Interrupted: {}", message),
+            Error::EmptyProof(response) => write!(
+                f,
+                "eth_getProof returned an empty proof (pass --allow-empty to emit it anyway): {}",
+                response
+            ),
+            Error::UnexpectedValue(message) => write!(f, "Unexpected value: {}", message),
+            Error::ProofTooDeep(message) => write!(f, "Does not fit the circuit: {}", message),
+            Error::Verification(message) => write!(f, "Verification failed: {}", message),
+        }
+    }
+}
+
+impl Error {
+    /// Stable name of the error's category, as written by
+    /// `--error-format json`.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Error::Config(_) | Error::Unsupported(_) => "config",
+            #[cfg(feature = "rpc")]
+            Error::Rpc(_) => "rpc",
+            Error::EmptyProof(_) | Error::UnminedBlock(_) => "rpc",
+            Error::ProofTooDeep(_) => "proof-too-deep",
+            Error::Verification(_)
+            | Error::Signature(_)
+            | Error::Quorum(_)
+            | Error::LightClient(_)
+            | Error::Reorg(_)
+            | Error::Chain(_)
+            | Error::Circuit(_) => "verification-failed",
+            Error::UnexpectedValue(_) => "unexpected-value",
+            Error::Interrupted(_) => "interrupted",
+            Error::Decode(_)
+            | Error::Io(_)
+            | Error::Encryption(_)
+            | Error::Upload(_)
+            | Error::Notify(_)
+            | Error::Hook(_) => "error",
+        }
+    }
+
+    /// Exit status of the process for the error's category. Usage errors
+    /// exit with 2 before anything runs.
+    pub fn exit_code(&self) -> i32 {
+        match self.category() {
+            "unexpected-value" => 3,
+            "config" => 4,
+            "rpc" => 5,
+            "proof-too-deep" => 6,
+            "verification-failed" => 7,
+            "interrupted" => 130,
+            _ => 1,
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(feature = "rpc")]
+impl From<web3::Error> for Error {
+    fn from(e: web3::Error) -> Self {
+        Error::Rpc(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<rlp::DecoderError> for Error {
+    fn from(e: rlp::DecoderError) -> Self {
+        Error::Decode(e.to_string())
+    }
+}
@@ -0,0 +1,154 @@
+use rlp::Rlp;
+
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::trie::{self, BRANCH_NODE_ITEMS};
+
+fn nibble_string(nibbles: &[u8]) -> String {
+    nibbles
+        .iter()
+        .map(|nibble| format!("{:x}", nibble))
+        .collect()
+}
+
+/// Describes, one line per step, how a flattened proof, padded to `node_len` bytes per node, is
+/// traversed along `nibbles`. Without `depth` the proof ends at the first
+/// all-zero node slot. When `root` is given each node is checked against
+/// the reference its parent holds.
+pub fn explain(
+    name: &str,
+    flat: &[u8],
+    node_len: usize,
+    depth: Option<usize>,
+    nibbles: &[u8],
+    root: Option<&[u8]>,
+) -> Result<String> {
+    let slots: Vec<&[u8]> = flat.chunks(node_len).collect();
+    let depth = depth.unwrap_or_else(|| {
+        slots
+            .iter()
+            .take_while(|slot| slot.iter().any(|&byte| byte != 0))
+            .count()
+    });
+    if depth > slots.len() {
+        return Err(Error::Decode(format!(
+            "{} depth {} exceeds its {} node slots",
+            name,
+            depth,
+            slots.len()
+        )));
+    }
+    let mut lines = Vec::new();
+    lines.push(format!(
+        "{}: {} of {} node slots used",
+        name,
+        depth,
+        slots.len()
+    ));
+
+    let mut expected = root.map(|root| root.to_vec());
+    let mut position = 0;
+    for (i, slot) in slots.iter().take(depth).enumerate() {
+        let info = Rlp::new(slot).payload_info()?;
+        let len = info.header_len + info.value_len;
+        if len > slot.len() {
+            return Err(Error::Decode(format!(
+                "{} node {} is {} bytes, longer than its {} byte slot",
+                name,
+                i,
+                len,
+                slot.len()
+            )));
+        }
+        let node = &slot[..len];
+        let hash = keccak256(node);
+        let reference = match &expected {
+            Some(reference) if reference.as_slice() == hash => "matches its parent reference",
+            Some(_) => "DOES NOT MATCH its parent reference",
+            None => "unchecked",
+        };
+        lines.push(format!(
+            "  node {} at byte {}: {} bytes + {} padding, keccak 0x{} ({})",
+            i,
+            i * node_len,
+            len,
+            slot.len() - len,
+            hex::encode(hash),
+            reference
+        ));
+
+        let rlp = Rlp::new(node);
+        expected = match rlp.item_count()? {
+            BRANCH_NODE_ITEMS => {
+                let Some(&nibble) = nibbles.get(position) else {
+                    lines.push("    branch: key exhausted, value slot used".to_string());
+                    break;
+                };
+                let child = rlp.at(nibble as usize)?;
+                lines.push(format!(
+                    "    branch: nibble {:x} at key position {} selects child {}",
+                    nibble, position, nibble
+                ));
+                position += 1;
+                if child.is_list() {
+                    lines.push(
+                        "    child is an inline node; traversal continues inside it".to_string(),
+                    );
+                    None
+                } else if child.data()?.is_empty() {
+                    lines.push("    child is empty: the key is not in the trie".to_string());
+                    None
+                } else {
+                    lines.push(format!("    child 0x{}", hex::encode(child.data()?)));
+                    Some(child.data()?.to_vec())
+                }
+            }
+            2 => {
+                let (path, is_leaf) = trie::decode_compact_path(rlp.at(0)?.data()?)?;
+                let start = position.min(nibbles.len());
+                let end = (position + path.len()).min(nibbles.len());
+                let key_part = &nibbles[start..end];
+                lines.push(format!(
+                    "    {}: nibbles {} consume key positions {}..{}",
+                    if is_leaf { "leaf" } else { "extension" },
+                    nibble_string(&path),
+                    position,
+                    position + path.len()
+                ));
+                if key_part != path.as_slice() {
+                    lines.push(format!(
+                        "    path diverges from the key ({}): the key is not in the trie",
+                        nibble_string(key_part)
+                    ));
+                }
+                position += path.len();
+                let item = rlp.at(1)?;
+                if is_leaf {
+                    lines.push(format!("    value 0x{}", hex::encode(item.data()?)));
+                    None
+                } else if item.is_list() {
+                    lines.push(
+                        "    child is an inline node; traversal continues inside it".to_string(),
+                    );
+                    None
+                } else {
+                    lines.push(format!("    child 0x{}", hex::encode(item.data()?)));
+                    Some(item.data()?.to_vec())
+                }
+            }
+            count => {
+                lines.push(format!("    not a trie node: {} items", count));
+                None
+            }
+        };
+    }
+
+    if depth < slots.len() {
+        lines.push(format!(
+            "  padding starts at byte {} ({} unused node slots)",
+            depth * node_len,
+            slots.len() - depth
+        ));
+    }
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
@@ -0,0 +1,150 @@
+use crate::error::{Error, Result};
+use crate::header::{BlockHeader, BASE_FIELD_COUNT, FIELD_NAMES};
+
+/// Forks that changed the header layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Fork {
+    /// Berlin or any earlier fork
+    Berlin,
+    London,
+    Shanghai,
+    Cancun,
+    Prague,
+}
+
+impl Fork {
+    /// Every fork, in order.
+    pub const ALL: [Fork; 5] = [
+        Fork::Berlin,
+        Fork::London,
+        Fork::Shanghai,
+        Fork::Cancun,
+        Fork::Prague,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fork::Berlin => "berlin",
+            Fork::London => "london",
+            Fork::Shanghai => "shanghai",
+            Fork::Cancun => "cancun",
+            Fork::Prague => "prague",
+        }
+    }
+
+    /// Number of optional header fields, in [`FIELD_NAMES`] order, that a
+    /// header of this fork carries.
+    pub fn optional_fields(&self) -> usize {
+        match self {
+            Fork::Berlin => 0,
+            Fork::London => 1,
+            Fork::Shanghai => 2,
+            Fork::Cancun => 5,
+            Fork::Prague => 6,
+        }
+    }
+}
+
+/// When each fork activated on a chain. Byzantium and London are scheduled
+/// by block number, the later forks by timestamp; a fork left out never
+/// activates, except Byzantium, which chains launched since activated at
+/// genesis.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    /// First block whose receipts commit to a status rather than the
+    /// post-state root (EIP-658).
+    pub byzantium_block: u64,
+    pub london_block: Option<u64>,
+    pub shanghai_time: Option<u64>,
+    pub cancun_time: Option<u64>,
+    pub prague_time: Option<u64>,
+}
+
+impl Schedule {
+    pub fn active(&self, number: u64, timestamp: u64) -> Fork {
+        let reached = |time: Option<u64>| time.is_some_and(|time| timestamp >= time);
+        if reached(self.prague_time) {
+            Fork::Prague
+        } else if reached(self.cancun_time) {
+            Fork::Cancun
+        } else if reached(self.shanghai_time) {
+            Fork::Shanghai
+        } else if self.london_block.is_some_and(|block| number >= block) {
+            Fork::London
+        } else {
+            Fork::Berlin
+        }
+    }
+
+    /// Whether block `number` is past Byzantium.
+    pub fn byzantium(&self, number: u64) -> bool {
+        number >= self.byzantium_block
+    }
+}
+
+/// Fork schedule of the public networks this tool knows about.
+pub fn schedule(chain_id: u64) -> Option<Schedule> {
+    match chain_id {
+        // Mainnet
+        1 => Some(Schedule {
+            byzantium_block: 4_370_000,
+            london_block: Some(12_965_000),
+            shanghai_time: Some(1_681_338_455),
+            cancun_time: Some(1_710_338_135),
+            prague_time: Some(1_746_612_311),
+        }),
+        // Sepolia
+        11_155_111 => Some(Schedule {
+            byzantium_block: 0,
+            london_block: Some(0),
+            shanghai_time: Some(1_677_557_088),
+            cancun_time: Some(1_706_655_072),
+            prague_time: Some(1_741_159_776),
+        }),
+        // Holesky
+        17_000 => Some(Schedule {
+            byzantium_block: 0,
+            london_block: Some(0),
+            shanghai_time: Some(1_696_000_704),
+            cancun_time: Some(1_707_305_664),
+            prague_time: Some(1_740_434_112),
+        }),
+        // Hoodi
+        560_048 => Some(Schedule {
+            byzantium_block: 0,
+            london_block: Some(0),
+            shanghai_time: Some(0),
+            cancun_time: Some(0),
+            prague_time: Some(1_742_999_832),
+        }),
+        _ => None,
+    }
+}
+
+/// Checks that `header` carries exactly the optional fields its fork
+/// requires under `schedule`.
+pub fn validate(schedule: &Schedule, header: &BlockHeader) -> Result<()> {
+    let fork = schedule.active(header.number.as_u64(), header.timestamp.low_u64());
+    let present = header
+        .optional_present()
+        .iter()
+        .take_while(|&&present| present)
+        .count();
+    let expected = fork.optional_fields();
+    let problem = if present < expected {
+        format!("omitted {}", FIELD_NAMES[BASE_FIELD_COUNT + present])
+    } else if present > expected {
+        format!(
+            "returned {}, which a later fork added",
+            FIELD_NAMES[BASE_FIELD_COUNT + expected]
+        )
+    } else {
+        return Ok(());
+    };
+    Err(Error::Decode(format!(
+        "block {} is a {} block, but the provider {}",
+        header.number,
+        fork.name(),
+        problem
+    )))
+}
@@ -0,0 +1,2766 @@
+//! Generation of the witness from a provider: fetching the header and
+//! proofs, verifying them and laying them out as circuit parameters.
+
+use std::time::Duration;
+
+use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+use rayon::prelude::*;
+use rlp::RlpStream;
+use web3::types::{BlockId, BlockNumber, Bytes, H160, H256, U256, U64};
+
+use crate::cache::ProofCache;
+use crate::chain_spec::ChainSpec;
+use crate::encoding::{ByteEncoding, HeaderPadding, NumericEncoding, ValueForm};
+use crate::error::{Error, Result};
+use crate::forks::Fork;
+use crate::options::{
+    BalanceAttestationOptions, BeaconProxyOptions, BlockRange, BlockRef, BloomOptions,
+    BundleOptions, ChainPreset, CreationOptions, DeploymentOptions, GenOptions, MultiproofOptions,
+    NonceOptions, ReceiptOptions, RootMode,
+};
+use crate::params::{Fields, Params, Value};
+use crate::proof_order::ProofOrder;
+use crate::source::EthDataSource;
+use crate::trie::{NodeLayout, BRANCH_NODE_ITEMS};
+use crate::witness::StorageProofWitness;
+use crate::{
+    blind, chunked, encoding, header, keccak, light_client, migrate, monomorphize, multiproof,
+    pad_nodes, pad_proof, partial_trie, polygon, progress, proof_order, receipts, reorg, rollup,
+    rpc, simulate, slots, source, storage_layout, store, strict, trie, units, verifier_params,
+    witness, ACCOUNT_PROOF_MAX_DEPTH, BLOCK_HEADER_RLP_HEAD_BYTES, CREATE_PREIMAGE_MAX_BYTES,
+    DELEGATION_PREFIX, PROOF_BYTES_LEN, RECEIPT_KEY_MAX_BYTES, RECEIPT_PROOF_MAX_DEPTH,
+    STORAGE_PROOF_MAX_DEPTH,
+};
+#[cfg(feature = "signing")]
+use crate::{checkpoints, signing};
+
+/// Header part of the witness, absent in state-root mode.
+struct HeaderWitness {
+    hash: H256,
+    /// Encoded header, zero-padded to `BLOCK_HEADER_RLP_BYTES`.
+    rlp: Vec<u8>,
+    /// Encoded header up to the state root, and the rest after it.
+    head: Vec<u8>,
+    tail: Vec<u8>,
+    keccak_blocks: Vec<u8>,
+    keccak_block_count: usize,
+    layout: header::Layout,
+}
+
+/// Storage part of the witness, absent when no slot was requested.
+struct StorageWitness {
+    key: U256,
+    key_bytes: [u8; 32],
+    value: U256,
+    nodes: Vec<Vec<u8>>,
+    padded: Vec<u8>,
+    tail: Option<ProofTail>,
+}
+
+/// Lower part of a proof deeper than the circuit allows, verified as a
+/// second segment that starts at the node `link` hashes to.
+struct ProofTail {
+    padded: Vec<u8>,
+    depth: usize,
+    link: [u8; 32],
+    /// Key nibbles consumed above the link.
+    key_offset: usize,
+}
+
+/// Pads `nodes` like [`pad_proof`], first splitting off the nodes below
+/// `max_depth` into a [`ProofTail`] when `split` is set.
+fn split_proof(
+    name: &str,
+    nodes: &[Vec<u8>],
+    max_depth: usize,
+    split: bool,
+) -> Result<(Vec<u8>, Option<ProofTail>)> {
+    if nodes.len() <= max_depth {
+        return Ok((pad_proof(name, nodes, max_depth)?, None));
+    }
+    if !split {
+        return Err(Error::ProofTooDeep(format!(
+            "the {} has {} nodes, more than the circuit's {}; pass --split-deep-proofs to \
+             emit it as two chained segments",
+            name,
+            nodes.len(),
+            max_depth
+        )));
+    }
+    let (head, tail) = nodes.split_at(max_depth);
+    let tail = ProofTail {
+        padded: pad_proof(&format!("{} tail", name), tail, max_depth)?,
+        depth: tail.len(),
+        link: keccak::keccak256(&tail[0]),
+        key_offset: trie::consumed_nibbles(head)?,
+    };
+    Ok((pad_proof(name, head, max_depth)?, Some(tail)))
+}
+
+fn push_tail(params: &mut Params, name: &str, tail: &ProofTail) {
+    params.push(format!("{}_tail", name), tail.padded.clone());
+    params.push(format!("{}_tail_depth", name), tail.depth);
+    params.push(format!("{}_link", name), tail.link.as_slice());
+    params.push(format!("{}_link_key_offset", name), tail.key_offset);
+}
+
+fn push_layouts(params: &mut Params, name: &str, layouts: &[NodeLayout], max_depth: usize) {
+    let mut item_counts = vec![0; max_depth];
+    let mut item_offsets = vec![0; max_depth * BRANCH_NODE_ITEMS];
+    let mut item_lengths = vec![0; max_depth * BRANCH_NODE_ITEMS];
+    let mut path_indices = vec![0; max_depth];
+
+    for (i, layout) in layouts.iter().enumerate() {
+        item_counts[i] = layout.item_count;
+        path_indices[i] = layout.path_index;
+        let start = i * BRANCH_NODE_ITEMS;
+        item_offsets[start..start + layout.item_count].copy_from_slice(&layout.item_offsets);
+        item_lengths[start..start + layout.item_count].copy_from_slice(&layout.item_lengths);
+    }
+
+    params.push(format!("{}_item_counts", name), item_counts);
+    params.push(format!("{}_item_offsets", name), item_offsets);
+    params.push(format!("{}_item_lengths", name), item_lengths);
+    params.push(format!("{}_path_indices", name), path_indices);
+}
+
+/// Pushes the keccak digest of each of `nodes` as `<name>_node_hashes`,
+/// 32 bytes per node, zero-padded to `max_depth` nodes like the proof.
+fn push_node_hashes(params: &mut Params, name: &str, nodes: &[Vec<u8>], max_depth: usize) {
+    let mut hashes = vec![0; max_depth * 32];
+    for (i, node) in nodes.iter().take(max_depth).enumerate() {
+        hashes[i * 32..(i + 1) * 32].copy_from_slice(&keccak::keccak256(node));
+    }
+    params.push(format!("{}_node_hashes", name), hashes);
+}
+
+/// Pushes `preimages` in the order they are hashed, each zero-padded to
+/// `width` bytes, with their lengths and keccak digests; the arrays hold
+/// `max_entries` entries.
+fn push_keccak_preimages(
+    params: &mut Params,
+    preimages: &[&[u8]],
+    max_entries: usize,
+    width: usize,
+) {
+    let mut padded = vec![0; max_entries * width];
+    let mut lengths = vec![0; max_entries];
+    let mut digests = vec![0; max_entries * 32];
+    for (i, preimage) in preimages.iter().enumerate() {
+        padded[i * width..i * width + preimage.len()].copy_from_slice(preimage);
+        lengths[i] = preimage.len();
+        digests[i * 32..(i + 1) * 32].copy_from_slice(&keccak::keccak256(preimage));
+    }
+    params.push("keccak_preimages", padded);
+    params.push("keccak_preimage_lengths", lengths);
+    params.push("keccak_digests", digests);
+    params.push("keccak_preimage_count", preimages.len());
+}
+
+/// Pushes the offset and length of the `blob_gas_used` and
+/// `excess_blob_gas` payloads within the encoded header `rlp`, which may be
+/// padded. Headers before Cancun have neither field.
+fn push_blob_gas_offsets(
+    params: &mut Params,
+    rlp: &[u8],
+    layout: header::Layout,
+    number: u64,
+) -> Result<()> {
+    let spans = header::field_spans(&rlp[..header::encoded_len(rlp)?], layout)?;
+    for name in ["blob_gas_used", "excess_blob_gas"] {
+        let span = spans.iter().find(|span| span.name == name).ok_or_else(|| {
+            Error::Unsupported(format!(
+                "block {} predates Cancun and has no {} field",
+                number, name
+            ))
+        })?;
+        params.push(format!("{}_offset", name), span.offset);
+        params.push(format!("{}_len", name), span.value.len());
+    }
+    Ok(())
+}
+
+fn has_expectations(args: &GenOptions) -> bool {
+    args.expect_value.is_some()
+        || args.expect_min.is_some()
+        || args.expect_max.is_some()
+        || args.range.is_some()
+}
+
+/// Fails with [`Error::UnexpectedValue`] if the proven storage `value`
+/// breaks `--expect-value`, `--expect-min`, `--expect-max` or `--range`.
+pub(crate) fn check_expected(args: &GenOptions, value: U256) -> Result<()> {
+    let slot = args.slot.unwrap_or_default();
+    let failed = |expectation: String| {
+        Err(Error::UnexpectedValue(format!(
+            "slot {:?} of {:?} holds {} ({:#x}) at block {}, {}",
+            slot, args.account, value, value, args.block, expectation
+        )))
+    };
+    if let Some(expected) = args.expect_value.filter(|&expected| expected != value) {
+        return failed(format!("expected {:#x}", expected));
+    }
+    if let Some(min) = args.expect_min.filter(|&min| value < min) {
+        return failed(format!("below the minimum {}", min));
+    }
+    if let Some(max) = args.expect_max.filter(|&max| value > max) {
+        return failed(format!("above the maximum {}", max));
+    }
+    if let Some(range) = args
+        .range
+        .filter(|range| value < range.lo || value > range.hi)
+    {
+        return failed(format!("outside the range {}..{}", range.lo, range.hi));
+    }
+    Ok(())
+}
+
+/// `value` split into limbs of `bits` bits, least significant first, as
+/// many as `hi` needs, so a circuit can range check each limb and
+/// recombine them.
+fn range_limbs(value: U256, hi: U256, bits: u32) -> Vec<Value> {
+    let count = hi.bits().div_ceil(bits as usize).max(1);
+    let mask = (U256::one() << bits) - 1;
+    (0..count)
+        .map(|i| Value::Int(((value >> (i * bits as usize)) & mask).as_u64()))
+        .collect()
+}
+
+/// Decodes `text` as hex, left-padded with zeros to `len` bytes.
+fn padded_hex(text: &str, len: usize) -> Option<Vec<u8>> {
+    let digits = text.trim_start_matches("0x");
+    let mut data = hex::decode(format!("{}{}", "0".repeat(digits.len() % 2), digits)).ok()?;
+    if data.len() > len {
+        return None;
+    }
+    data.splice(0..0, std::iter::repeat_n(0, len - data.len()));
+    Some(data)
+}
+
+/// Replaces the fields named in `overrides` with the given values, keeping
+/// each field's type and length, and flags the result in `[meta]` so it is
+/// never mistaken for a genuine witness.
+fn apply_overrides(params: &mut Params, overrides: &[(String, String)]) -> Result<()> {
+    for (name, text) in overrides {
+        let invalid = |expected: &str| {
+            Error::Decode(format!(
+                "--override {}={}: expected {}",
+                name, text, expected
+            ))
+        };
+        let value = match params.fields.get(name) {
+            None => {
+                return Err(Error::Decode(format!(
+                    "--override {}: no such field in the generated parameters",
+                    name
+                )))
+            }
+            Some(Value::Int(_)) => Value::Int(
+                match text.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => text.parse(),
+                }
+                .map_err(|_| invalid("an integer"))?,
+            ),
+            Some(Value::Bytes(bytes)) => Value::Bytes(
+                padded_hex(text, bytes.len())
+                    .ok_or_else(|| invalid(&format!("hex of at most {} bytes", bytes.len())))?,
+            ),
+            Some(Value::Hex(bytes)) => Value::Hex(
+                padded_hex(text, bytes.len())
+                    .ok_or_else(|| invalid(&format!("hex of at most {} bytes", bytes.len())))?,
+            ),
+            Some(_) => return Err(invalid("a byte array or integer field")),
+        };
+        params.fields.set(name, value);
+        log::warn!("overrode {}; this witness is for negative tests", name);
+    }
+    if !overrides.is_empty() {
+        let names: Vec<Value> = overrides
+            .iter()
+            .map(|(name, _)| Value::from(name.as_str()))
+            .collect();
+        params.meta.push("overridden_fields", Value::Array(names));
+        params.record_witness_hash();
+    }
+    Ok(())
+}
+
+/// Renames fields to the names a circuit's parameters use, recording the
+/// mapping in `[meta]`. Every rename is checked against the generated names
+/// first, so a typo or a clash fails instead of emitting a file the circuit
+/// rejects.
+fn apply_renames(params: &mut Params, renames: &[(String, String)]) -> Result<()> {
+    for (old, new) in renames {
+        if params.fields.get(old).is_none() {
+            return Err(Error::Decode(format!(
+                "--rename {}: no such field in the generated parameters",
+                old
+            )));
+        }
+        let taken =
+            params.fields.get(new).is_some() && !renames.iter().any(|(renamed, _)| renamed == new);
+        let repeated = renames.iter().filter(|(_, other)| other == new).count() > 1;
+        if renames.iter().filter(|(other, _)| other == old).count() > 1 {
+            return Err(Error::Decode(format!(
+                "--rename {}: the field is renamed more than once",
+                old
+            )));
+        }
+        if taken || repeated {
+            return Err(Error::Decode(format!(
+                "--rename {}={}: another field is already named {}",
+                old, new, new
+            )));
+        }
+    }
+    // Renaming through placeholder names lets renames swap fields.
+    for (i, (old, _)) in renames.iter().enumerate() {
+        params.fields.rename(old, &format!("\0{}", i));
+    }
+    for (i, (_, new)) in renames.iter().enumerate() {
+        params.fields.rename(&format!("\0{}", i), new);
+    }
+    if !renames.is_empty() {
+        let mapping: Vec<Value> = renames
+            .iter()
+            .map(|(old, new)| Value::from(format!("{}={}", old, new)))
+            .collect();
+        params.meta.push("renamed_fields", Value::Array(mapping));
+        params.record_witness_hash();
+    }
+    Ok(())
+}
+
+/// Padded fields whose unpadded form [`raw_fields`] recovers.
+const RAW_PROOFS: &[&str] = &[
+    "account_proof",
+    "storage_proof",
+    "account_proof_tail",
+    "storage_proof_tail",
+];
+
+/// The header RLP and proof nodes of `params` without their padding, as
+/// `<field>_raw` entries of hex strings. Fields are looked up under the
+/// names `renames` gave them, and the raw entries follow those names.
+pub fn raw_fields(
+    params: &Params,
+    renames: &[(String, String)],
+) -> serde_json::Map<String, serde_json::Value> {
+    let emitted = |name: &str| {
+        renames
+            .iter()
+            .find(|(old, _)| old == name)
+            .map_or(name.to_string(), |(_, new)| new.clone())
+    };
+    let hex = |bytes: &[u8]| serde_json::Value::from(format!("0x{}", hex::encode(bytes)));
+    let mut raw = serde_json::Map::new();
+    let header = emitted("block_header_rlp");
+    if let Some(padded) = params.fields.get_bytes(&header) {
+        if let Some(rlp) = encoding::header_padding(params).unpad(padded) {
+            raw.insert(format!("{}_raw", header), hex(rlp));
+        }
+    }
+    for name in RAW_PROOFS {
+        let proof = emitted(name);
+        let depth = params.fields.get_int(&emitted(&format!("{}_depth", name)));
+        if let (Some(flat), Some(depth)) = (params.fields.get_bytes(&proof), depth) {
+            let nodes = trie::unpad_proof(flat, PROOF_BYTES_LEN, depth as usize);
+            raw.insert(
+                format!("{}_raw", proof),
+                nodes.iter().map(|node| hex(node)).collect(),
+            );
+        }
+    }
+    raw
+}
+
+/// Number and timestamp of the block `tag` names.
+async fn block_time(web3: &web3::Web3<rpc::Client>, tag: BlockNumber) -> Result<(u64, u64)> {
+    let block = web3.eth().block(BlockId::Number(tag)).await?;
+    match block
+        .as_ref()
+        .and_then(|block| block.number.zip(Some(block.timestamp)))
+    {
+        Some((number, timestamp)) => Ok((number.as_u64(), timestamp.low_u64())),
+        None => Err(Error::UnminedBlock(format!(
+            "the provider has no block {:?}",
+            tag
+        ))),
+    }
+}
+
+/// The last block mined at or before `timestamp`, found by binary search
+/// between the genesis and the latest block. Timestamps after the latest
+/// block's are rejected, since a block yet to come may still fall before
+/// them.
+pub async fn block_at_timestamp(web3: &web3::Web3<rpc::Client>, timestamp: u64) -> Result<u64> {
+    let (latest, latest_time) = block_time(web3, BlockNumber::Latest).await?;
+    if latest_time <= timestamp {
+        if latest_time < timestamp {
+            return Err(Error::UnminedBlock(format!(
+                "the latest block, {}, was mined at {}, before {}; later blocks may still be \
+                 mined before that time",
+                latest, latest_time, timestamp
+            )));
+        }
+        return Ok(latest);
+    }
+    let (_, genesis_time) = block_time(web3, BlockNumber::Number(U64::zero())).await?;
+    if genesis_time > timestamp {
+        return Err(Error::Chain(format!(
+            "the chain started at {}, after {}",
+            genesis_time, timestamp
+        )));
+    }
+    // The block at `low` is at or before the timestamp, the one at `high`
+    // after it.
+    let (mut low, mut high) = (0, latest);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if block_time(web3, BlockNumber::Number(U64::from(middle)))
+            .await?
+            .1
+            <= timestamp
+        {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(low)
+}
+
+/// Resolves a `--block` tag, or `--at-timestamp`, to the number the first
+/// provider reports for it, so every provider is then asked for the same
+/// block. With `--from-smt` the block is the bundle's, and with
+/// `--block-range` the first of the range.
+pub async fn resolve_block(args: &mut GenOptions) -> Result<()> {
+    if let Some(range) = args.block_range {
+        args.block = range.lo;
+        return Ok(());
+    }
+    if let Some(path) = &args.from_smt {
+        let bundle = partial_trie::Bundle::load(path)?;
+        args.block = match args.block_ref {
+            BlockRef::Latest => bundle.block,
+            BlockRef::Number(number) if number == bundle.block => number,
+            BlockRef::Number(number) => {
+                return Err(Error::Unsupported(format!(
+                    "{} holds block {}, not {}",
+                    path.display(),
+                    bundle.block,
+                    number
+                )))
+            }
+            BlockRef::Safe | BlockRef::Finalized => {
+                return Err(Error::Unsupported(
+                    "--from-smt has no provider to resolve a block tag; pass the block number"
+                        .to_string(),
+                ))
+            }
+        };
+        return Ok(());
+    }
+    if let Some(timestamp) = args.at_timestamp {
+        let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url[0])?);
+        args.block = block_at_timestamp(&web3, timestamp).await?;
+        log::info!(
+            "Using block {}, the last at or before {}",
+            args.block,
+            timestamp
+        );
+        return Ok(());
+    }
+    if let Some(root) = args.beacon_root {
+        let beacon_api = args
+            .beacon_node
+            .as_deref()
+            .or(args.beacon_api.as_deref())
+            .ok_or_else(|| {
+                Error::Config("--beacon-root needs a beacon API: pass --beacon-node".to_string())
+            })?;
+        let beacon = light_client::execution_block(beacon_api, root).await?;
+        args.block = beacon.block_number;
+        log::info!(
+            "Using block {}, the execution block of beacon slot {}",
+            args.block,
+            beacon.slot
+        );
+        args.beacon_block = Some(beacon);
+        return Ok(());
+    }
+    let (tag, name) = match args.block_ref {
+        BlockRef::Number(number) => {
+            args.block = number;
+            return Ok(());
+        }
+        BlockRef::Latest => (BlockNumber::Latest, "latest"),
+        BlockRef::Safe => (BlockNumber::Safe, "safe"),
+        BlockRef::Finalized => (BlockNumber::Finalized, "finalized"),
+    };
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url[0])?);
+    args.block = web3
+        .eth()
+        .block(BlockId::Number(tag))
+        .await?
+        .and_then(|block| block.number)
+        .ok_or_else(|| Error::UnminedBlock(format!("the provider has no {} block", name)))?
+        .as_u64();
+    log::info!("Using the {} block, {}", name, args.block);
+    Ok(())
+}
+
+/// The address `account` delegates its code to under EIP-7702, read from
+/// the delegation designator (`0xef0100` followed by the address) that is
+/// its code. The code is checked against `code_hash` from the verified
+/// account proof.
+async fn delegation(
+    source: &dyn EthDataSource,
+    account: H160,
+    block: u64,
+    code_hash: H256,
+) -> Result<Option<H160>> {
+    let code = source.get_code(account, block).await?;
+    if keccak::keccak256(&code.0) != code_hash.0 {
+        return Err(Error::Verification(format!(
+            "the code reported for {:?} does not match its code hash",
+            account
+        )));
+    }
+    Ok(code
+        .0
+        .strip_prefix(&DELEGATION_PREFIX)
+        .filter(|address| address.len() == 20)
+        .map(H160::from_slice))
+}
+
+/// Generates the parameters from the single provider at `rpc_url`, without
+/// the light client, reorg and signing steps.
+pub async fn generate_from(rpc_url: &str, args: &GenOptions, prove: bool) -> Result<Params> {
+    if let Some(rollup) = args.rollup {
+        return rollup::generate(rpc_url, args, rollup, prove).await;
+    }
+    let web3 = web3::Web3::new(rpc::Client::new(rpc_url)?);
+    // Name the provider whose response failed to verify, since with several
+    // configured the others may well be fine.
+    let named = |e| match e {
+        Error::Verification(message) => Error::Verification(format!(
+            "{} (returned by {})",
+            message,
+            rpc::redact(rpc_url)
+        )),
+        e => e,
+    };
+    let Some(path) = &args.state_override else {
+        return generate_with_source(&web3, args, prove)
+            .await
+            .map_err(named);
+    };
+    let simulated =
+        simulate::Simulated::new(&web3, path, args.block, args.chain_spec.as_deref()).await?;
+    let mut params = generate_with_source(&simulated, args, prove)
+        .await
+        .map_err(named)?;
+    params.meta.push(
+        "synthetic",
+        format!(
+            "state overrides from {} applied to block {}; not a canonical block",
+            path.display(),
+            args.block
+        ),
+    );
+    params.record_witness_hash();
+    Ok(params)
+}
+
+/// Generates the parameters from `source`, ignoring the providers in
+/// `args.rpc_url`; like [`generate_from`], without the light client, reorg
+/// and signing steps.
+pub async fn generate_with_source(
+    source: &dyn EthDataSource,
+    args: &GenOptions,
+    prove: bool,
+) -> Result<Params> {
+    let target_account = args.account;
+
+    let mut fork = None;
+    let mut max_header_bytes = None;
+    let (header_witness, state_root) = match (args.root_mode, args.state_root) {
+        (RootMode::StateRoot, Some(state_root)) => (None, state_root),
+        (RootMode::BlockHash, Some(_)) => {
+            return Err(Error::Unsupported(
+                "--state-root is only used with --root-mode state-root".to_string(),
+            ))
+        }
+        (root_mode, None) => {
+            let chain_id = source.chain_id().await?;
+            let spec = match &args.chain_spec {
+                Some(path) => ChainSpec::load(path)?,
+                None => ChainSpec::known(chain_id),
+            };
+            if spec.chain_id.is_some_and(|expected| expected != chain_id) {
+                return Err(Error::Chain(format!(
+                    "the provider serves chain {}, but the chain spec is for chain {}",
+                    chain_id,
+                    spec.chain_id.unwrap_or_default()
+                )));
+            }
+            let (header, block_hash) = header::fetch(source, args.block, &spec).await?;
+            fork = spec
+                .schedule
+                .as_ref()
+                .filter(|_| spec.layout == header::Layout::Ethereum)
+                .map(|schedule| {
+                    schedule.active(header.number.as_u64(), header.timestamp.low_u64())
+                });
+            let encoded = header.encode();
+            let state_root = header.state_root;
+
+            let (rlp_head_bytes, _, rlp_tail_bytes) =
+                header::split_rlp_by_state_root(&encoded, state_root.as_bytes(), header.layout())
+                    .expect("Failed to split RLP data");
+
+            let rlp_encoded_block = spec.pad_header(args.block, encoded.clone())?;
+            let (keccak_blocks, keccak_block_count) = keccak::pad_rate_blocks(
+                &encoded,
+                spec.header_rlp_bytes() / keccak::KECCAK_RATE_BYTES + 1,
+            );
+            max_header_bytes = spec.max_header_bytes;
+
+            let header_witness = (root_mode == RootMode::BlockHash).then_some(HeaderWitness {
+                hash: block_hash,
+                rlp: rlp_encoded_block,
+                head: rlp_head_bytes,
+                tail: rlp_tail_bytes,
+                keccak_blocks,
+                keccak_block_count,
+                layout: header.layout(),
+            });
+            (header_witness, state_root)
+        }
+    };
+
+    let storage_keys = args.slot.map(|slot| U256::from_big_endian(&slot.0));
+    let keys: Vec<U256> = storage_keys.into_iter().collect();
+    let cache = args.proof_cache.as_deref().map(ProofCache::new);
+    let cached = match &cache {
+        Some(cache) => cache.get(state_root, target_account, &keys)?,
+        None => None,
+    };
+    let step = serde_json::json!({
+        "block": args.block,
+        "account": target_account,
+        "slot": args.slot,
+    });
+    let (proof, fetched) = match cached {
+        Some(proof) => (Some(proof), false),
+        None => {
+            progress::event("fetch_started", step.clone());
+            let mut proof =
+                chunked::get_proof(source, target_account, keys.clone(), args.block).await?;
+            if let Some(proof) = &mut proof {
+                chunked::complete(source, state_root, target_account, proof).await?;
+            }
+            (proof, true)
+        }
+    };
+    if let (serde_json::Value::Object(mut details), Some(proof)) = (step.clone(), &proof) {
+        details.insert("cached".to_string(), (!fetched).into());
+        details.insert(
+            "account_nodes".to_string(),
+            proof.account_proof.len().into(),
+        );
+        details.insert(
+            "storage_nodes".to_string(),
+            proof
+                .storage_proof
+                .first()
+                .map_or(0, |storage| storage.proof.len())
+                .into(),
+        );
+        progress::event("proof_received", details.into());
+    }
+    // Stored once the witness is built, so a proof that fails to verify is
+    // never cached.
+    let to_cache = proof.clone().filter(|_| fetched && cache.is_some());
+
+    let (mut unwrapped, placeholder) = match proof {
+        Some(proof)
+            if !proof.account_proof.is_empty()
+                && (storage_keys.is_none() || !proof.storage_proof.is_empty()) =>
+        {
+            (proof, false)
+        }
+        proof if args.allow_empty => {
+            log::warn!("empty proof, emitting a zero-filled witness");
+            let mut proof = proof.unwrap_or_default();
+            if let (Some(slot_u256), true) = (storage_keys, proof.storage_proof.is_empty()) {
+                // web3 does not export the storage proof type, so build
+                // the empty entry from its serialized form.
+                let empty = serde_json::json!({
+                    "key": slot_u256,
+                    "value": U256::zero(),
+                    "proof": [],
+                });
+                proof
+                    .storage_proof
+                    .push(serde_json::from_value(empty).map_err(|e| Error::Decode(e.to_string()))?);
+            }
+            (proof, true)
+        }
+        proof => {
+            return Err(Error::EmptyProof(
+                serde_json::to_string(&proof).unwrap_or_default(),
+            ))
+        }
+    };
+
+    let account_nodes: Vec<Vec<u8>> = unwrapped
+        .account_proof
+        .iter()
+        .map(|node| node.0.clone())
+        .collect();
+    let (account_proof_flat_vec, account_tail) = split_proof(
+        "account proof",
+        &account_nodes,
+        ACCOUNT_PROOF_MAX_DEPTH,
+        args.split_deep_proofs,
+    )?;
+    let decode_error = |e| Error::Verification(format!("Failed to decode proof node: {:?}", e));
+    if let (Some(root), Some(first), false) = (args.state_root, account_nodes.first(), placeholder)
+    {
+        if keccak::keccak256(first) != root.0 {
+            return Err(Error::Verification(format!(
+                "the account proof of block {} is not rooted at --state-root {:?}; the root is \
+                 from another block or another chain",
+                args.block, root
+            )));
+        }
+    }
+
+    let account_leaf = if placeholder {
+        None
+    } else {
+        verify_chain(
+            "account proof",
+            state_root.as_bytes(),
+            &trie::key_nibbles(target_account.as_bytes()),
+            &account_nodes,
+        )?
+    };
+    let account_exists = placeholder || account_leaf.is_some();
+    if !account_exists {
+        // Providers disagree on what they report for missing accounts, so
+        // emit the fields of an empty account.
+        unwrapped.nonce = U256::zero();
+        unwrapped.balance = U256::zero();
+        unwrapped.storage_hash = H256(trie::EMPTY_TRIE_ROOT);
+        unwrapped.code_hash = H256(trie::EMPTY_CODE_HASH);
+        log::info!(
+            "Note: account {:?} does not exist at block {}; emitting its exclusion proof \
+             with account_exists = 0",
+            target_account,
+            args.block
+        );
+    } else if !placeholder && unwrapped.storage_hash.is_zero() {
+        // Some providers report a zero storage hash for accounts without
+        // storage; the trie commits to the empty root.
+        unwrapped.storage_hash = H256(trie::EMPTY_TRIE_ROOT);
+    }
+    let unwrapped = &unwrapped;
+
+    let mut account_value_rlp_stream = RlpStream::new();
+    account_value_rlp_stream
+        .begin_list(4)
+        .append(&unwrapped.nonce)
+        .append(&unwrapped.balance)
+        .append(&unwrapped.storage_hash)
+        .append(&unwrapped.code_hash);
+    if let Some(leaf) = &account_leaf {
+        if leaf.as_slice() != account_value_rlp_stream.as_raw() {
+            return Err(Error::Verification(format!(
+                "the account fields reported for {:?} do not match its proof",
+                target_account
+            )));
+        }
+    }
+
+    // Only accounts with code can delegate, and only from Prague on.
+    let may_delegate = account_leaf.is_some()
+        && unwrapped.code_hash != H256(trie::EMPTY_CODE_HASH)
+        && fork.is_none_or(|fork| fork >= Fork::Prague);
+    let delegate = match may_delegate {
+        true => delegation(source, target_account, args.block, unwrapped.code_hash).await?,
+        false => None,
+    };
+    if let Some(delegate) = delegate {
+        if args.follow_delegation {
+            log::info!(
+                "Note: {:?} delegates its code to {:?}; proving the delegate instead",
+                target_account,
+                delegate
+            );
+            let delegate_args = GenOptions {
+                account: delegate,
+                follow_delegation: false,
+                ..args.clone()
+            };
+            let mut params = Box::pin(generate_with_source(source, &delegate_args, prove)).await?;
+            params
+                .meta
+                .push("delegated_from", format!("{:?}", target_account));
+            params.record_witness_hash();
+            return Ok(params);
+        }
+        log::info!(
+            "Note: {:?} delegates its code to {:?} (EIP-7702); pass --follow-delegation to \
+             prove the delegate's storage instead",
+            target_account,
+            delegate
+        );
+    }
+
+    let storage = match unwrapped.storage_proof.first() {
+        Some(storage_proof) if storage_keys.is_some() => {
+            let nodes: Vec<Vec<u8>> = storage_proof
+                .proof
+                .iter()
+                .map(|node| node.0.clone())
+                .collect();
+            let key = U256::from(&storage_proof.key);
+            let mut key_bytes = [0u8; 32];
+            key.to_big_endian(&mut key_bytes);
+            let (padded, tail) = split_proof(
+                "storage proof",
+                &nodes,
+                STORAGE_PROOF_MAX_DEPTH,
+                args.split_deep_proofs,
+            )?;
+            Some(StorageWitness {
+                key,
+                value: U256::from(&storage_proof.value),
+                key_bytes,
+                nodes,
+                padded,
+                tail,
+            })
+        }
+        _ => None,
+    };
+
+    // A provider can answer with account and storage proofs taken from
+    // different states; the storage proof has to start at the storage root
+    // the proven account commits to.
+    if let (Some(storage), false) = (&storage, placeholder) {
+        let rooted = match storage.nodes.first() {
+            Some(first) => keccak::keccak256(first) == unwrapped.storage_hash.0,
+            None => unwrapped.storage_hash == H256(trie::EMPTY_TRIE_ROOT),
+        };
+        if !rooted {
+            return Err(Error::Verification(format!(
+                "the storage proof of slot {:?} is not rooted at the storage root {:?} of {:?}; \
+                 the provider returned inconsistent account and storage proofs",
+                H256(storage.key_bytes),
+                unwrapped.storage_hash,
+                target_account
+            )));
+        }
+    }
+
+    // Zero values are not stored in the trie, so the proof for such a slot
+    // shows that the key is absent rather than that it holds zero.
+    let is_zero_value = storage
+        .as_ref()
+        .is_some_and(|storage| storage.value.is_zero());
+    if let (Some(storage), false) = (&storage, placeholder) {
+        let leaf = verify_chain(
+            "storage proof",
+            unwrapped.storage_hash.as_bytes(),
+            &trie::key_nibbles(&storage.key_bytes),
+            &storage.nodes,
+        )?;
+        let proven = match leaf {
+            Some(leaf) => rlp::decode::<U256>(&leaf).map_err(decode_error)?,
+            None => U256::zero(),
+        };
+        if proven != storage.value {
+            return Err(Error::Verification(format!(
+                "the provider reports slot {:?} as {:#x} but its proof shows {:#x}",
+                H256(storage.key_bytes),
+                storage.value,
+                proven
+            )));
+        }
+        if is_zero_value {
+            log::info!(
+                "Note: slot {:?} of {:?} holds zero, which the trie represents by the slot's \
+                 absence; emitting its exclusion proof with is_zero_value = 1",
+                H256(storage.key_bytes),
+                target_account
+            );
+        }
+    }
+
+    if let Some(storage) = &storage {
+        check_expected(args, storage.value)?;
+    } else if has_expectations(args) {
+        return Err(Error::Unsupported(
+            "--expect-value, --expect-min, --expect-max and --range check a storage value; \
+             pass --slot"
+                .to_string(),
+        ));
+    }
+    if let (serde_json::Value::Object(mut details), false) = (step, placeholder) {
+        details.insert("account_exists".to_string(), account_exists.into());
+        if let Some(storage) = &storage {
+            details.insert("value".to_string(), serde_json::json!(storage.value));
+        }
+        progress::event("verified", details.into());
+    }
+
+    let layouts = if args.rlp_hints {
+        let account_layouts = trie::proof_layouts(
+            &account_nodes,
+            &trie::key_nibbles(target_account.as_bytes()),
+        )
+        .map_err(decode_error)?;
+        let storage_layouts = storage
+            .as_ref()
+            .map(|storage| {
+                trie::proof_layouts(&storage.nodes, &trie::key_nibbles(&storage.key_bytes))
+            })
+            .transpose()
+            .map_err(decode_error)?;
+        Some((account_layouts, storage_layouts))
+    } else {
+        None
+    };
+
+    let encoding = args.numeric_encoding;
+    let value = storage
+        .as_ref()
+        .map(|storage| args.value_form.encode(storage.value, encoding))
+        .transpose()
+        .map_err(Error::Unsupported)?;
+    let mut witness = StorageProofWitness {
+        header: witness::Header {
+            block_hash: header_witness
+                .as_ref()
+                .map(|header| header.hash.as_bytes().to_vec()),
+            state_root: header_witness
+                .is_none()
+                .then(|| state_root.as_bytes().to_vec()),
+            ..Default::default()
+        },
+        account: witness::Account {
+            key: target_account.as_bytes().to_vec(),
+            value: Some(account_value_rlp_stream.as_raw().to_vec()),
+            exists: account_exists,
+            ..Default::default()
+        },
+        storage: storage.as_ref().map(|storage| witness::Storage {
+            key: Some(encoding.encode(storage.key)),
+            value: value.as_ref().map(|(value, _)| value.clone()),
+            is_zero_value,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    if prove {
+        if let Some(header) = &header_witness {
+            let len = header.head.len() + 32 + header.tail.len();
+            witness.header.rlp = Some(args.header_padding.pad(&header.rlp, len));
+            witness.header.rlp_head_len = Some(header.head.len() as u64);
+            witness.header.rlp_tail_len = Some(header.tail.len() as u64);
+            if args.header_parts {
+                let head_max = args
+                    .header_head_bytes
+                    .unwrap_or(BLOCK_HEADER_RLP_HEAD_BYTES);
+                let tail_max = args
+                    .header_tail_bytes
+                    .unwrap_or(header.rlp.len() - 32 - BLOCK_HEADER_RLP_HEAD_BYTES);
+                for (name, part, max, option) in [
+                    ("head", &header.head, head_max, "--header-head-bytes"),
+                    ("tail", &header.tail, tail_max, "--header-tail-bytes"),
+                ] {
+                    if part.len() > max {
+                        return Err(Error::ProofTooDeep(format!(
+                            "the header of block {} has a {} of {} bytes, more than the {} of {}",
+                            args.block,
+                            name,
+                            part.len(),
+                            max,
+                            option
+                        )));
+                    }
+                }
+                let pad = |part: &[u8], max: usize| {
+                    let mut padded = part.to_vec();
+                    padded.resize(max, 0);
+                    padded
+                };
+                witness.header.rlp_head = Some(pad(&header.head, head_max));
+                witness.header.rlp_tail = Some(pad(&header.tail, tail_max));
+            }
+        }
+        witness.account.storage_root = Some(unwrapped.storage_hash.as_bytes().to_vec());
+        witness.account.proof = Some(account_proof_flat_vec);
+        witness.account.proof_depth = Some(account_nodes.len().min(ACCOUNT_PROOF_MAX_DEPTH) as u64);
+        if let (Some(typed), Some(storage)) = (&mut witness.storage, &storage) {
+            typed.proof = Some(storage.padded.clone());
+            typed.proof_depth = Some(storage.nodes.len().min(STORAGE_PROOF_MAX_DEPTH) as u64);
+        }
+    }
+    let mut params = witness.to_params();
+    if let Some((_, Some(len))) = value {
+        params.push("storage_value_len", len);
+    }
+    if let (Some(header), true) = (&header_witness, prove) {
+        params.push(
+            "block_header_rlp_len",
+            header.head.len() + 32 + header.tail.len(),
+        );
+    }
+    if args.account_fields {
+        push_account_fields(
+            &mut params,
+            account_value_rlp_stream.as_raw(),
+            encoding,
+            prove,
+        )?;
+    }
+    if args.empty_constants {
+        params.push("empty_trie_root", &trie::EMPTY_TRIE_ROOT[..]);
+        params.push("empty_code_hash", &trie::EMPTY_CODE_HASH[..]);
+        params.push(
+            "has_empty_storage",
+            (unwrapped.storage_hash.0 == trie::EMPTY_TRIE_ROOT) as usize,
+        );
+        params.push(
+            "has_empty_code",
+            (unwrapped.code_hash.0 == trie::EMPTY_CODE_HASH) as usize,
+        );
+    }
+    if let (Some(range), Some(storage)) = (args.range, &storage) {
+        params.push("range_lo", encoding.encode(range.lo));
+        params.push("range_hi", encoding.encode(range.hi));
+        if prove {
+            params.push(
+                "storage_value_limbs",
+                Value::Array(range_limbs(storage.value, range.hi, args.range_limb_bits)),
+            );
+        }
+    }
+    if prove {
+        if let (Some(header), true) = (&header_witness, args.keccak_blocks) {
+            params.push("block_header_keccak_blocks", header.keccak_blocks.clone());
+            params.push("block_header_keccak_block_count", header.keccak_block_count);
+        }
+        if let (Some(header), true) = (&header_witness, args.blob_gas_offsets) {
+            push_blob_gas_offsets(&mut params, &header.rlp, header.layout, args.block)?;
+        }
+        if args.keccak_preimages {
+            // The header, then the account key and nodes, then the storage
+            // key and nodes, as verification hashes them.
+            let header_rlp = header_witness
+                .as_ref()
+                .map(|header| [&header.head[..], state_root.as_bytes(), &header.tail[..]].concat());
+            let mut preimages: Vec<&[u8]> = header_rlp.as_deref().into_iter().collect();
+            preimages.push(target_account.as_bytes());
+            preimages.extend(account_nodes.iter().map(Vec::as_slice));
+            if let Some(storage) = &storage {
+                preimages.push(&storage.key_bytes);
+                preimages.extend(storage.nodes.iter().map(Vec::as_slice));
+            }
+            let segments = if args.split_deep_proofs { 2 } else { 1 };
+            let max_entries = 3 + segments * (ACCOUNT_PROOF_MAX_DEPTH + STORAGE_PROOF_MAX_DEPTH);
+            let width = header_witness.as_ref().map_or(PROOF_BYTES_LEN, |header| {
+                header.rlp.len().max(PROOF_BYTES_LEN)
+            });
+            push_keccak_preimages(&mut params, &preimages, max_entries, width);
+        }
+        if args.node_hashes {
+            push_node_hashes(
+                &mut params,
+                "account_proof",
+                &account_nodes,
+                ACCOUNT_PROOF_MAX_DEPTH,
+            );
+            if let Some(storage) = &storage {
+                push_node_hashes(
+                    &mut params,
+                    "storage_proof",
+                    &storage.nodes,
+                    STORAGE_PROOF_MAX_DEPTH,
+                );
+            }
+        }
+        if let Some((account_layouts, storage_layouts)) = &layouts {
+            push_layouts(
+                &mut params,
+                "account_proof",
+                &account_layouts[..account_layouts.len().min(ACCOUNT_PROOF_MAX_DEPTH)],
+                ACCOUNT_PROOF_MAX_DEPTH,
+            );
+            if let Some(storage_layouts) = storage_layouts {
+                push_layouts(
+                    &mut params,
+                    "storage_proof",
+                    &storage_layouts[..storage_layouts.len().min(STORAGE_PROOF_MAX_DEPTH)],
+                    STORAGE_PROOF_MAX_DEPTH,
+                );
+            }
+        }
+        if let Some(tail) = &account_tail {
+            push_tail(&mut params, "account_proof", tail);
+            if args.node_hashes {
+                push_node_hashes(
+                    &mut params,
+                    "account_proof_tail",
+                    &account_nodes[ACCOUNT_PROOF_MAX_DEPTH..],
+                    ACCOUNT_PROOF_MAX_DEPTH,
+                );
+            }
+            if let Some((account_layouts, _)) = &layouts {
+                push_layouts(
+                    &mut params,
+                    "account_proof_tail",
+                    &account_layouts[ACCOUNT_PROOF_MAX_DEPTH..],
+                    ACCOUNT_PROOF_MAX_DEPTH,
+                );
+            }
+        }
+        if let Some((storage, tail)) = storage
+            .as_ref()
+            .and_then(|storage| Some((storage, storage.tail.as_ref()?)))
+        {
+            push_tail(&mut params, "storage_proof", tail);
+            if args.node_hashes {
+                push_node_hashes(
+                    &mut params,
+                    "storage_proof_tail",
+                    &storage.nodes[STORAGE_PROOF_MAX_DEPTH..],
+                    STORAGE_PROOF_MAX_DEPTH,
+                );
+            }
+            if let Some((_, Some(storage_layouts))) = &layouts {
+                push_layouts(
+                    &mut params,
+                    "storage_proof_tail",
+                    &storage_layouts[STORAGE_PROOF_MAX_DEPTH..],
+                    STORAGE_PROOF_MAX_DEPTH,
+                );
+            }
+        }
+    }
+    params.meta.push(
+        "witness_format_version",
+        migrate::WITNESS_FORMAT_VERSION as usize,
+    );
+    params.meta.push("numeric_encoding", encoding.name());
+    if args.value_form != ValueForm::Padded {
+        params.meta.push("value_form", args.value_form.name());
+    }
+    if args.header_padding != HeaderPadding::Post {
+        params
+            .meta
+            .push("header_padding", args.header_padding.name());
+    }
+    if prove && args.proof_order == ProofOrder::LeafFirst {
+        proof_order::leaf_first(&mut params)?;
+    }
+    if let Some(fork) = fork {
+        params.meta.push("fork", fork.name());
+    }
+    if let (Some(len), Some(_)) = (max_header_bytes, &header_witness) {
+        params.meta.push("max_header_bytes", len);
+    }
+    if let Some(header) = header_witness
+        .as_ref()
+        .filter(|header| header.layout != header::Layout::Ethereum)
+    {
+        params.meta.push("header_layout", header.layout.name());
+    }
+    if header_witness.is_none() {
+        params.meta.push("root_mode", "state-root");
+    }
+    if let Some(delegate) = delegate {
+        params.meta.push("delegated_to", format!("{:?}", delegate));
+    }
+    if let (Some(_), true) = (args.range, prove) {
+        params
+            .meta
+            .push("range_limb_bits", args.range_limb_bits as usize);
+    }
+    params.record_witness_hash();
+
+    if let (Some(cache), Some(proof), false) = (&cache, &to_cache, placeholder) {
+        cache.put(state_root, target_account, &keys, proof, args.compressor)?;
+    }
+
+    Ok(params)
+}
+
+/// Rejects, for `--from-smt`, the options that need a provider or another
+/// service besides the bundle.
+fn check_offline(args: &GenOptions) -> Result<()> {
+    let online = [
+        (args.rollup.is_some(), "--rollup"),
+        (args.quorum > 1, "--quorum"),
+        (args.at_timestamp.is_some(), "--at-timestamp"),
+        (args.beacon_root.is_some(), "--beacon-root"),
+        (args.beacon_api.is_some(), "--beacon-api"),
+        (args.chain == Some(ChainPreset::Polygon), "--chain polygon"),
+        (
+            args.reorg_check || args.confirmations > 0 || args.wait_finalized,
+            "reorg checks",
+        ),
+        (args.only_checkpointed, "--only-checkpointed"),
+        (args.store, "--store"),
+        (args.proof_cache.is_some(), "--proof-cache"),
+        (args.state_override.is_some(), "--state-override"),
+    ];
+    match online.iter().find(|(requested, _)| *requested) {
+        Some((_, option)) => Err(Error::Unsupported(format!(
+            "{} needs a provider, and --from-smt answers from the bundle alone",
+            option
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Generates the parameters from every configured provider and keeps the
+/// result that at least `args.quorum` of them agree on byte-for-byte.
+pub async fn generate_with_quorum(args: &GenOptions, prove: bool) -> Result<Params> {
+    if let Some(path) = &args.from_smt {
+        check_offline(args)?;
+        let source = partial_trie::BundleSource::new(partial_trie::Bundle::load(path)?);
+        return generate_with_source(&source, args, prove).await;
+    }
+    if let Some(rollup) = args.rollup {
+        rollup::check_args(args, rollup)?;
+    }
+    if args.root_mode == RootMode::StateRoot {
+        let needs_header = [
+            (args.keccak_blocks, "--keccak-blocks"),
+            (args.blob_gas_offsets, "--blob-gas-offsets"),
+            (args.header_parts, "--header-parts"),
+            (args.beacon_api.is_some(), "--beacon-api"),
+            (args.chain == Some(ChainPreset::Polygon), "--chain polygon"),
+            (
+                args.reorg_check || args.confirmations > 0 || args.wait_finalized,
+                "reorg checks",
+            ),
+        ];
+        if let Some((_, option)) = needs_header.iter().find(|(requested, _)| *requested) {
+            return Err(Error::Unsupported(format!(
+                "{} needs the block header, which --root-mode state-root omits",
+                option
+            )));
+        }
+    }
+    if args.proof_cache.is_some() && args.rpc_url.len() > 1 {
+        return Err(Error::Unsupported(
+            "--proof-cache would let providers agree with cached proofs instead of each other; \
+             drop it when cross-checking providers"
+                .to_string(),
+        ));
+    }
+    if args.state_override.is_some() {
+        let canonical_only = [
+            (args.beacon_api.is_some(), "--beacon-api"),
+            (
+                args.reorg_check || args.confirmations > 0 || args.wait_finalized,
+                "reorg checks",
+            ),
+            (args.proof_cache.is_some(), "--proof-cache"),
+            (args.chain == Some(ChainPreset::Polygon), "--chain polygon"),
+        ];
+        if let Some((_, option)) = canonical_only.iter().find(|(requested, _)| *requested) {
+            return Err(Error::Unsupported(format!(
+                "{} only applies to canonical blocks, and a --state-override witness proves none",
+                option
+            )));
+        }
+        log::warn!(
+            "applying state overrides; the witness proves a hypothetical state, not a \
+             canonical block"
+        );
+    }
+    if args.quorum == 0 || args.quorum > args.rpc_url.len() {
+        return Err(Error::Quorum(format!(
+            "quorum of {} cannot be met with {} provider(s)",
+            args.quorum,
+            args.rpc_url.len()
+        )));
+    }
+    if let [rpc_url] = args.rpc_url.as_slice() {
+        return generate_from(rpc_url, args, prove).await;
+    }
+
+    let results = join_all(
+        args.rpc_url
+            .iter()
+            .map(|rpc_url| generate_from(rpc_url, args, prove)),
+    )
+    .await;
+
+    let mut groups: Vec<(Vec<u8>, Params, Vec<usize>)> = Vec::new();
+    let mut failures = Vec::new();
+    for (i, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(params) => {
+                let bytes = params.canonical_bytes(&[]);
+                match groups.iter_mut().find(|(other, _, _)| *other == bytes) {
+                    Some((_, _, providers)) => providers.push(i + 1),
+                    None => groups.push((bytes, params, vec![i + 1])),
+                }
+            }
+            Err(e) => failures.push(format!("provider #{}: {}", i + 1, e)),
+        }
+    }
+
+    groups.sort_by_key(|(_, _, providers)| std::cmp::Reverse(providers.len()));
+    for (_, _, providers) in groups.iter().skip(1) {
+        failures.push(format!(
+            "provider(s) {:?} returned diverging parameters",
+            providers
+        ));
+    }
+
+    match groups.into_iter().next() {
+        Some((_, mut params, providers)) if providers.len() >= args.quorum => {
+            for failure in &failures {
+                log::warn!("{}", failure);
+            }
+            params.meta.push("providers", args.rpc_url.len());
+            params.meta.push("providers_agreeing", providers.len());
+            Ok(params)
+        }
+        best => Err(Error::Quorum(format!(
+            "only {} of {} provider(s) agree, {} required: {}",
+            best.map_or(0, |(_, _, providers)| providers.len()),
+            args.rpc_url.len(),
+            args.quorum,
+            failures.join("; ")
+        ))),
+    }
+}
+
+/// Generates the prover (or verifier) parameters of every chain in `args`
+/// and merges them into one bundle. Each chain's fields are prefixed with
+/// `chain_<index>_`, and `chain_ids` lists the chains in that order.
+pub async fn generate_bundle(args: &BundleOptions) -> Result<Params> {
+    let results = join_all(args.chains.iter().map(|chain| async move {
+        let context = |e: Error| {
+            Error::Chain(format!(
+                "chain {} ({}): {}",
+                chain.chain_id, chain.rpc_url, e
+            ))
+        };
+        let web3 = web3::Web3::new(rpc::Client::new(&chain.rpc_url)?);
+        let chain_id = web3.eth().chain_id().await.map_err(|e| context(e.into()))?;
+        if chain_id != U256::from(chain.chain_id) {
+            return Err(Error::Chain(format!(
+                "{} serves chain {}, not {}",
+                chain.rpc_url, chain_id, chain.chain_id
+            )));
+        }
+        generate_from(&chain.rpc_url, &args.chain_args(chain), !args.verifier)
+            .await
+            .map_err(context)
+    }))
+    .await;
+
+    let mut bundle = Params::default();
+    bundle.push(
+        "chain_ids",
+        Value::Array(
+            args.chains
+                .iter()
+                .map(|chain| Value::Int(chain.chain_id))
+                .collect(),
+        ),
+    );
+    for (i, result) in results.into_iter().enumerate() {
+        let params = result?;
+        for (name, value) in params.fields.iter() {
+            bundle.push(format!("chain_{}_{}", i, name), value.clone());
+        }
+        if i == 0 {
+            bundle.meta = params.meta;
+        }
+    }
+    bundle.meta.push("chains", args.chains.len());
+    bundle.record_witness_hash();
+    Ok(bundle)
+}
+
+/// Whether `account` is in the state at `block`, going by the fields its
+/// `eth_getProof` reports: an absent account has no nonce, balance, code or
+/// storage, and providers report zero or empty hashes for it.
+async fn exists_at(source: &dyn EthDataSource, account: H160, block: u64) -> Result<bool> {
+    let proof = source
+        .get_proof(account, Vec::new(), block)
+        .await?
+        .ok_or_else(|| Error::UnexpectedValue(format!("no proof returned for block {}", block)))?;
+    let empty = |hash: H256, root: [u8; 32]| hash.is_zero() || hash.0 == root;
+    Ok(!(proof.nonce.is_zero()
+        && proof.balance.is_zero()
+        && empty(proof.code_hash, trie::EMPTY_CODE_HASH)
+        && empty(proof.storage_hash, trie::EMPTY_TRIE_ROOT)))
+}
+
+/// The first block in `from..=to` at which `account` exists, found by
+/// bisection. The account must be absent at `from` and present at `to`. An
+/// account that self-destructed and was created again within the range has
+/// several such boundaries, and any one of them may be returned.
+pub async fn find_creation_block(
+    source: &dyn EthDataSource,
+    account: H160,
+    from: u64,
+    to: u64,
+) -> Result<u64> {
+    if !exists_at(source, account, to).await? {
+        return Err(Error::UnexpectedValue(format!(
+            "{:?} does not exist at block {}",
+            account, to
+        )));
+    }
+    if exists_at(source, account, from).await? {
+        return Err(Error::UnexpectedValue(match from {
+            0 => format!("{:?} exists from genesis, so no block created it", account),
+            _ => format!(
+                "{:?} already exists at block {}; search from an earlier block",
+                account, from
+            ),
+        }));
+    }
+    let (mut absent, mut present) = (from, to);
+    while present - absent > 1 {
+        let middle = absent + (present - absent) / 2;
+        if exists_at(source, account, middle).await? {
+            present = middle;
+        } else {
+            absent = middle;
+        }
+    }
+    Ok(present)
+}
+
+/// Generates the witnesses of the block `args.account` is created in: the
+/// exclusion proof at the block before, with fields prefixed `before_`, and
+/// the inclusion proof at the block itself, prefixed `after_`. A circuit
+/// checking both, and that the second header's parent hash is the first
+/// block hash, proves when the account came to exist.
+pub async fn generate_creation(args: &CreationOptions) -> Result<Params> {
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url)?);
+    let to = match args.to_block {
+        Some(to) => to,
+        None => web3.eth().block_number().await?.as_u64(),
+    };
+    let block = find_creation_block(&web3, args.account, args.from_block, to).await?;
+    log::info!("{:?} first exists at block {}", args.account, block);
+
+    let mut pair = Params::default();
+    for (prefix, number) in [("before", block - 1), ("after", block)] {
+        let params = generate_with_source(&web3, &args.block_args(number), !args.verifier).await?;
+        let exists = params.fields.get_int("account_exists") != Some(0);
+        if exists != (prefix == "after") {
+            return Err(Error::Verification(format!(
+                "the proof of {:?} at block {} does not match the fields the provider reported for it",
+                args.account, number
+            )));
+        }
+        for (name, value) in params.fields.iter() {
+            pair.push(format!("{}_{}", prefix, name), value.clone());
+        }
+        pair.meta = params.meta;
+    }
+    pair.meta.push("creation_block", Value::Int(block));
+    pair.record_witness_hash();
+    Ok(pair)
+}
+
+/// Generates the account proof of `args.account` and checks that the
+/// balance it proves is at least `args.min_balance`, failing with
+/// [`Error::UnexpectedValue`] otherwise. `[meta]` records the threshold;
+/// with `args.public_threshold` it is also emitted as the public input
+/// `min_balance`, for the circuit to compare the proven balance against.
+pub async fn generate_balance_attestation(args: &BalanceAttestationOptions) -> Result<Params> {
+    let mut gen_args = args.gen_args();
+    resolve_block(&mut gen_args).await?;
+    let mut params = generate_checked(&gen_args, !args.verifier).await?;
+    let balance = match params.fields.get("account_value") {
+        Some(Value::Bytes(account)) => rlp::Rlp::new(account)
+            .val_at::<U256>(1)
+            .map_err(|e| Error::Decode(format!("invalid account_value: {:?}", e)))?,
+        _ => {
+            return Err(Error::Decode(
+                "generated parameters lack account_value".to_string(),
+            ))
+        }
+    };
+    if balance < args.min_balance {
+        return Err(Error::UnexpectedValue(format!(
+            "{:?} holds {} wei at block {}, below the minimum {}",
+            args.account, balance, gen_args.block, args.min_balance
+        )));
+    }
+    if args.public_threshold {
+        params.push(
+            "min_balance",
+            args.numeric_encoding.encode(args.min_balance),
+        );
+    }
+    params
+        .meta
+        .push("min_balance", args.min_balance.to_string());
+    params.record_witness_hash();
+    Ok(params)
+}
+
+/// The RLP list `[sender, nonce]` whose keccak digest ends in the address
+/// of the contract `sender` creates with its `nonce`th CREATE.
+fn create_preimage(sender: H160, nonce: U256) -> Vec<u8> {
+    let mut stream = RlpStream::new_list(2);
+    stream.append(&sender).append(&nonce);
+    stream.out().to_vec()
+}
+
+/// Generates the account proof of `args.account` with the nonce it proves
+/// and the address its next CREATE deploys to: `create_address`, the last
+/// 20 bytes of the keccak digest of `create_preimage`, the RLP list of the
+/// account and its nonce. With a salt and init code hash the CREATE2
+/// address, which does not depend on the nonce, is added as
+/// `create2_address`. `account_nonce_offset` and `account_nonce_len` locate
+/// the nonce in `account_value`, for circuits reading it from the proven
+/// account.
+pub async fn generate_deployment(args: &DeploymentOptions) -> Result<Params> {
+    let mut gen_args = args.gen_args();
+    resolve_block(&mut gen_args).await?;
+    let mut params = generate_checked(&gen_args, !args.verifier).await?;
+    let (nonce, nonce_offset, nonce_len) = account_nonce(&params)?;
+
+    let preimage = create_preimage(args.account, nonce);
+    let create_address = H160::from_slice(&keccak::keccak256(&preimage)[12..]);
+    let mut padded = preimage.clone();
+    padded.resize(CREATE_PREIMAGE_MAX_BYTES, 0);
+    params.push("account_nonce", args.numeric_encoding.encode(nonce));
+    params.push("account_nonce_offset", nonce_offset);
+    params.push("account_nonce_len", nonce_len);
+    params.push("create_preimage", padded);
+    params.push("create_preimage_len", preimage.len());
+    params.push("create_address", create_address.as_bytes());
+    log::info!(
+        "The next CREATE from {:?} (nonce {}) deploys to {:?}",
+        args.account,
+        nonce,
+        create_address
+    );
+    if let (Some(salt), Some(init_code_hash)) = (args.salt, args.init_code_hash) {
+        let mut preimage = vec![0xff];
+        preimage.extend_from_slice(args.account.as_bytes());
+        preimage.extend_from_slice(salt.as_bytes());
+        preimage.extend_from_slice(init_code_hash.as_bytes());
+        let create2_address = H160::from_slice(&keccak::keccak256(&preimage)[12..]);
+        params.push("create2_salt", salt.as_bytes());
+        params.push("create2_init_code_hash", init_code_hash.as_bytes());
+        params.push("create2_address", create2_address.as_bytes());
+        log::info!("CREATE2 with this salt deploys to {:?}", create2_address);
+    }
+    params.meta.push("deployer_nonce", nonce.to_string());
+    params.record_witness_hash();
+    Ok(params)
+}
+
+/// The nonce of the account proven in `params`, with the offset and
+/// length of its bytes in `account_value`.
+fn account_nonce(params: &Params) -> Result<(U256, usize, usize)> {
+    let account = params
+        .fields
+        .get_bytes("account_value")
+        .ok_or_else(|| Error::Decode("generated parameters lack account_value".to_string()))?;
+    account_item(account, 0)
+}
+
+/// The `index`th integer of the RLP account `account`, 0 for the nonce and
+/// 1 for the balance, with the offset and length of its bytes.
+fn account_item(account: &[u8], index: usize) -> Result<(U256, usize, usize)> {
+    let invalid = |e: rlp::DecoderError| Error::Decode(format!("invalid account_value: {:?}", e));
+    let rlp = rlp::Rlp::new(account);
+    let value: U256 = rlp.val_at(index).map_err(invalid)?;
+    let mut offset = rlp.payload_info().map_err(invalid)?.header_len;
+    for before in 0..index {
+        offset += rlp.at(before).map_err(invalid)?.as_raw().len();
+    }
+    let item = rlp
+        .at(index)
+        .and_then(|item| item.payload_info())
+        .map_err(invalid)?;
+    Ok((value, offset + item.header_len, item.value_len))
+}
+
+/// Pushes the nonce and balance of `account` as `account_nonce` and
+/// `account_balance` in `encoding`, each with the offset and length of its
+/// bytes in `account_value` for the prover.
+fn push_account_fields(
+    params: &mut Params,
+    account: &[u8],
+    encoding: NumericEncoding,
+    prove: bool,
+) -> Result<()> {
+    for (index, name) in [(0, "account_nonce"), (1, "account_balance")] {
+        let (value, offset, len) = account_item(account, index)?;
+        params.push(name, encoding.encode(value));
+        if prove {
+            params.push(format!("{}_offset", name), offset);
+            params.push(format!("{}_len", name), len);
+        }
+    }
+    Ok(())
+}
+
+/// Proves the nonce of an EOA at a block for replay-protection circuits,
+/// with only the fields such a circuit reads: `storage_root` is left out
+/// of the prover parameters, and the verifier ones take the nonce in place
+/// of the whole `account_value`.
+pub async fn generate_nonce(args: &NonceOptions) -> Result<Params> {
+    let mut gen_args = args.gen_args();
+    resolve_block(&mut gen_args).await?;
+    let mut params = generate_checked(&gen_args, !args.verifier).await?;
+    let (nonce, nonce_offset, nonce_len) = account_nonce(&params)?;
+    let code_hash = params
+        .fields
+        .get_bytes("account_value")
+        .and_then(|account| rlp::Rlp::new(account).val_at::<H256>(3).ok());
+    if code_hash.is_some_and(|hash| hash.0 != trie::EMPTY_CODE_HASH) {
+        log::warn!(
+            "{:?} has code, so its nonce counts the contracts it created rather than \
+             transactions it sent",
+            args.account
+        );
+    }
+    if let Some(expected) = args.expect_nonce.filter(|&expected| expected != nonce) {
+        return Err(Error::UnexpectedValue(format!(
+            "{:?} has nonce {} at block {}, not {}",
+            args.account, nonce, gen_args.block, expected
+        )));
+    }
+    match args.verifier {
+        true => {
+            params.fields.remove("account_value");
+        }
+        false => {
+            params.fields.remove("storage_root");
+        }
+    }
+    params.push("account_nonce", args.numeric_encoding.encode(nonce));
+    if !args.verifier {
+        params.push("account_nonce_offset", nonce_offset);
+        params.push("account_nonce_len", nonce_len);
+    }
+    params.meta.push("nonce", nonce.to_string());
+    params.record_witness_hash();
+    Ok(params)
+}
+
+/// The EIP-1967 slot of `label`: its keccak digest less one, so no
+/// preimage hashes to it.
+fn eip1967_slot(label: &str) -> H256 {
+    let digest = U256::from_big_endian(&keccak::keccak256(label.as_bytes())) - 1;
+    let mut slot = H256::zero();
+    digest.to_big_endian(slot.as_bytes_mut());
+    slot
+}
+
+/// The address held in the proven slot of `params`, failing unless the
+/// slot holds one.
+fn slot_address(params: &Params, what: &str) -> Result<H160> {
+    let value = encoding::storage_value(params)
+        .ok_or_else(|| Error::Decode("generated parameters lack storage_value".to_string()))?;
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    if value.is_zero() || word[..12].iter().any(|&b| b != 0) {
+        return Err(Error::UnexpectedValue(format!(
+            "the {} slot holds {:#x}, not an address",
+            what, value
+        )));
+    }
+    Ok(H160::from_slice(&word[12..]))
+}
+
+/// Fails unless the account proven in `params` has code.
+fn ensure_contract(params: &Params, account: H160, what: &str) -> Result<()> {
+    let code_hash = params
+        .fields
+        .get_bytes("account_value")
+        .and_then(|account| rlp::Rlp::new(account).val_at::<H256>(3).ok());
+    match code_hash {
+        Some(hash) if hash.0 != trie::EMPTY_CODE_HASH && !hash.is_zero() => Ok(()),
+        _ => Err(Error::UnexpectedValue(format!(
+            "the {} {:?} has no code",
+            what, account
+        ))),
+    }
+}
+
+/// Generates the chained proofs of the EIP-1967 beacon proxy `args.account`
+/// at one block: its beacon slot, with fields prefixed `proxy_`; the
+/// implementation slot of the beacon that slot holds, prefixed `beacon_`;
+/// and the account of the implementation that one holds, prefixed
+/// `implementation_`. Each proof is verified, and all three must be of the
+/// same block. A circuit checks that each proven address is the account
+/// key of the next proof and that the block hashes agree.
+pub async fn generate_beacon_proxy(args: &BeaconProxyOptions) -> Result<Params> {
+    let beacon_slot = eip1967_slot("eip1967.proxy.beacon");
+    let implementation_slot = args
+        .implementation_slot
+        .unwrap_or_else(|| H256::from_low_u64_be(1));
+    let mut proxy_args = args.gen_args(0, args.account, Some(beacon_slot));
+    proxy_args.block_ref = args.block_ref;
+    resolve_block(&mut proxy_args).await?;
+    let block = proxy_args.block;
+    let prove = !args.verifier;
+
+    let proxy = generate_checked(&proxy_args, prove).await?;
+    ensure_contract(&proxy, args.account, "proxy")?;
+    let beacon_address = slot_address(&proxy, "beacon")?;
+    let beacon_args = args.gen_args(block, beacon_address, Some(implementation_slot));
+    let beacon = generate_checked(&beacon_args, prove).await?;
+    ensure_contract(&beacon, beacon_address, "beacon")?;
+    let implementation_address = slot_address(&beacon, "implementation")?;
+    let implementation_args = args.gen_args(block, implementation_address, None);
+    let implementation = generate_checked(&implementation_args, prove).await?;
+    ensure_contract(&implementation, implementation_address, "implementation")?;
+    log::info!(
+        "{:?} uses the beacon {:?}, which points to {:?}",
+        args.account,
+        beacon_address,
+        implementation_address
+    );
+
+    let root = |params: &Params| {
+        let field = match params.fields.get("block_hash") {
+            Some(_) => "block_hash",
+            None => "state_root",
+        };
+        params.fields.get_bytes(field).map(<[u8]>::to_vec)
+    };
+    if root(&beacon) != root(&proxy) || root(&implementation) != root(&proxy) {
+        return Err(Error::Verification(format!(
+            "the proofs of the proxy chain are not all of the same block {}",
+            block
+        )));
+    }
+
+    let mut chain = Params::default();
+    for (prefix, params) in [
+        ("proxy", &proxy),
+        ("beacon", &beacon),
+        ("implementation", &implementation),
+    ] {
+        for (name, value) in params.fields.iter() {
+            chain.push(format!("{}_{}", prefix, name), value.clone());
+        }
+    }
+    chain.meta = proxy.meta;
+    chain
+        .meta
+        .push("proxy_beacon", format!("{:?}", beacon_address));
+    chain.meta.push(
+        "proxy_implementation",
+        format!("{:?}", implementation_address),
+    );
+    chain.record_witness_hash();
+    Ok(chain)
+}
+
+/// Walks a proof the provider returned, naming the damaged node when its
+/// hash chain breaks. Some gateways cut large `eth_getProof` responses short
+/// without an error, which shows up here as a truncated or missing node.
+fn verify_chain(
+    what: &str,
+    root: &[u8],
+    nibbles: &[u8],
+    nodes: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>> {
+    trie::walk_proof(root, nibbles, nodes).map_err(|broken| {
+        let hint = match broken.kind {
+            trie::Break::Invalid(_) => "",
+            _ => "; the provider may have truncated its response",
+        };
+        Error::Verification(format!(
+            "the {} is broken: {}{}",
+            what,
+            broken.describe(nodes.len()),
+            hint
+        ))
+    })
+}
+
+/// Checks the storage proof of `slot` against `storage_root` and the key and
+/// value the provider reported for it, returning its nodes and value.
+fn verify_storage_slot(
+    storage_root: H256,
+    slot: &H256,
+    key: U256,
+    value: U256,
+    proof: &[Bytes],
+) -> Result<(Vec<Vec<u8>>, U256)> {
+    let decode_error = |e| Error::Verification(format!("Failed to decode proof node: {:?}", e));
+    let nodes: Vec<Vec<u8>> = proof.iter().map(|node| node.0.clone()).collect();
+    if nodes.len() > STORAGE_PROOF_MAX_DEPTH {
+        return Err(Error::ProofTooDeep(format!(
+            "the storage proof of slot {:?} has {} nodes, more than the circuit's {}",
+            slot,
+            nodes.len(),
+            STORAGE_PROOF_MAX_DEPTH
+        )));
+    }
+    let leaf = verify_chain(
+        "storage proof",
+        storage_root.as_bytes(),
+        &trie::key_nibbles(slot.as_bytes()),
+        &nodes,
+    )?;
+    let proven = match leaf {
+        Some(leaf) => rlp::decode::<U256>(&leaf).map_err(decode_error)?,
+        None => U256::zero(),
+    };
+    if key != U256::from_big_endian(&slot.0) || proven != value {
+        return Err(Error::Verification(format!(
+            "the value reported for slot {:?} does not match its proof",
+            slot
+        )));
+    }
+    Ok((nodes, value))
+}
+
+/// Generates the account witness once and proves every slot in `args`
+/// against its storage root with one deduplicated node table. With
+/// `--slot-bytes` the slots are those of the string or bytes value there,
+/// and `slot_bytes_len` tells the circuit how many of their bytes it
+/// spans; with `--slot-struct` they are those of a struct in a mapping,
+/// placed member by member when a storage layout is given.
+pub async fn generate_multiproof(args: &MultiproofOptions) -> Result<Params> {
+    let mut params = generate_from(&args.rpc_url, &args.account_args(), true).await?;
+    let storage_root = params
+        .fields
+        .get_bytes("storage_root")
+        .map(H256::from_slice)
+        .unwrap_or_default();
+
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url)?);
+    let bytes_layout = match args.slot_bytes {
+        Some(base) => {
+            let word = web3
+                .eth()
+                .storage(
+                    args.account,
+                    U256::from_big_endian(base.as_bytes()),
+                    Some(BlockNumber::Number(args.block.into())),
+                )
+                .await?;
+            Some(slots::BytesLayout::decode(
+                base,
+                U256::from_big_endian(word.as_bytes()),
+            )?)
+        }
+        None => None,
+    };
+    let struct_slots = match &args.slot_struct {
+        Some(mapping) => {
+            let [index, key] = &mapping[..] else {
+                return Err(Error::Decode(
+                    "--slot-struct takes a mapping slot index and a key".to_string(),
+                ));
+            };
+            Some(storage_layout::StructSlots::resolve(
+                index,
+                key,
+                args.struct_slots,
+                args.storage_layout.as_deref(),
+            )?)
+        }
+        None => None,
+    };
+    let slots = match (&bytes_layout, &struct_slots) {
+        (Some(layout), _) => layout.slots(),
+        (None, Some(struct_slots)) => struct_slots.slots(),
+        (None, None) => args.slots.clone(),
+    };
+    let keys: Vec<U256> = slots
+        .iter()
+        .map(|slot| U256::from_big_endian(&slot.0))
+        .collect();
+    let mut proof = chunked::get_proof(&web3, args.account, keys.clone(), args.block)
+        .await?
+        .ok_or_else(|| Error::EmptyProof("null".to_string()))?;
+    chunked::complete_storage(&web3, &mut proof).await?;
+    let reported_root = match proof.storage_hash {
+        root if root.is_zero() => H256(trie::EMPTY_TRIE_ROOT),
+        root => root,
+    };
+    if reported_root != storage_root || proof.storage_proof.len() != keys.len() {
+        return Err(Error::Verification(format!(
+            "the storage proofs of {:?} do not match its account proof",
+            args.account
+        )));
+    }
+
+    let encoding = args.numeric_encoding;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.verify_threads.unwrap_or(0))
+        .build()
+        .map_err(|e| Error::Unsupported(format!("cannot start verification threads: {}", e)))?;
+    let verified = pool.install(|| {
+        slots
+            .par_iter()
+            .zip(&proof.storage_proof)
+            .map(|(slot, storage_proof)| {
+                verify_storage_slot(
+                    storage_root,
+                    slot,
+                    U256::from(&storage_proof.key),
+                    U256::from(&storage_proof.value),
+                    &storage_proof.proof,
+                )
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+    let (proofs, values): (Vec<_>, Vec<_>) = verified.into_iter().unzip();
+    if let Some(struct_slots) = struct_slots.as_ref() {
+        if values.iter().all(U256::is_zero) {
+            let warnings = slots::diagnose_mapping(
+                &web3,
+                args.account,
+                args.block,
+                struct_slots.mapping,
+                &struct_slots.key,
+            )
+            .await?;
+            for warning in warnings {
+                log::warn!("{}", warning);
+            }
+        }
+    }
+
+    let multiproof = multiproof::Multiproof::new(&proofs);
+    // The node table holds its own copy of every distinct node.
+    drop(proofs);
+    let max_nodes = args.max_nodes.unwrap_or(multiproof.nodes.len());
+    params.push(
+        "storage_keys",
+        Value::Array(keys.iter().map(|&key| encoding.encode(key)).collect()),
+    );
+    params.push(
+        "storage_values",
+        Value::Array(values.iter().map(|&value| encoding.encode(value)).collect()),
+    );
+    params.push(
+        "storage_multiproof",
+        pad_proof("storage multiproof", &multiproof.nodes, max_nodes)?,
+    );
+    params.push("storage_multiproof_node_count", multiproof.nodes.len());
+    params.push(
+        "storage_proof_paths",
+        multiproof.flat_paths(STORAGE_PROOF_MAX_DEPTH),
+    );
+    params.push(
+        "storage_proof_depths",
+        multiproof.paths.iter().map(Vec::len).collect::<Vec<_>>(),
+    );
+    if values.iter().any(U256::is_zero) {
+        params.push(
+            "is_zero_values",
+            values
+                .iter()
+                .map(|value| value.is_zero() as usize)
+                .collect::<Vec<_>>(),
+        );
+    }
+    if let Some(layout) = &bytes_layout {
+        // The length read beforehand must be the one proven.
+        if U256::from_big_endian(layout.base.as_bytes()) != keys[0]
+            || slots::BytesLayout::decode(layout.base, values[0])? != *layout
+        {
+            return Err(Error::Verification(format!(
+                "the provider reported another length for the value at slot {:?} than its \
+                 proof holds",
+                layout.base
+            )));
+        }
+        params.push("slot_bytes_len", layout.len);
+        params
+            .meta
+            .push("slot_bytes_slot", format!("{:?}", layout.base));
+        if let Some(data_slot) = layout.data_slot {
+            params
+                .meta
+                .push("slot_bytes_data_slot", format!("{:?}", data_slot));
+        }
+        params.meta.push(
+            "slot_bytes_value",
+            format!("0x{}", hex::encode(layout.value(&values))),
+        );
+    }
+    if let Some(struct_slots) = &struct_slots {
+        let members = &struct_slots.members;
+        if !members.is_empty() {
+            params.push(
+                "struct_member_slots",
+                members.iter().map(|member| member.slot).collect::<Vec<_>>(),
+            );
+            params.push(
+                "struct_member_offsets",
+                members
+                    .iter()
+                    .map(|member| member.offset)
+                    .collect::<Vec<_>>(),
+            );
+            params.push(
+                "struct_member_bytes",
+                members
+                    .iter()
+                    .map(|member| member.bytes)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        params
+            .meta
+            .push("struct_slot", format!("{:?}", struct_slots.base));
+        if !members.is_empty() {
+            params.meta.push(
+                "struct_members",
+                Value::Array(
+                    members
+                        .iter()
+                        .map(|member| Value::Str(format!("{} {}", member.type_label, member.label)))
+                        .collect(),
+                ),
+            );
+        }
+    }
+    params.meta.push("storage_slots", keys.len());
+    params.meta.push("storage_multiproof_nodes", max_nodes);
+    params.record_witness_hash();
+    Ok(params)
+}
+
+/// Generates the parameters proving the receipt of transaction
+/// `args.tx_index` of `args.block`: the header, which commits to the
+/// receipts root, and the receipt's proof in the receipt trie rebuilt from
+/// every receipt of the block. Typed transactions' receipts are stored with
+/// their type byte in front, and the type is recorded as `meta.tx_type`.
+///
+/// With `args.account`, the proofs of [`generate_params`] for the account
+/// (and slot) are added to the same witness, which then holds the header
+/// once with both the state root and the receipts root located in it.
+pub async fn generate_receipt(args: &ReceiptOptions) -> Result<Params> {
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url)?);
+    let source: &dyn EthDataSource = &web3;
+    let chain_id = source.chain_id().await?;
+    let spec = match &args.chain_spec {
+        Some(path) => ChainSpec::load(path)?,
+        None => ChainSpec::known(chain_id),
+    };
+    let (header, block_hash) = header::fetch(source, args.block, &spec).await?;
+    let state = match args.account {
+        Some(account) => Some(generate_params(&args.state_args(account), true).await?),
+        None => None,
+    };
+    let receipts = source.get_receipts(args.block).await?;
+    // Chains without a known schedule are taken to have launched past
+    // Byzantium.
+    let byzantium = spec
+        .schedule
+        .as_ref()
+        .is_none_or(|schedule| schedule.byzantium(args.block));
+    let receipts::ReceiptProof {
+        root,
+        receipt,
+        nodes,
+    } = receipts::prove(&receipts, args.tx_index, byzantium)?;
+    if root != header.receipts_root.0 {
+        return Err(Error::Verification(format!(
+            "the {} receipts of block {} build a trie with root {:?}, not the header's \
+             receipts root {:?}",
+            receipts.len(),
+            args.block,
+            H256(root),
+            header.receipts_root
+        )));
+    }
+    if receipt.len() > args.max_node_bytes {
+        return Err(Error::ProofTooDeep(format!(
+            "the receipt is {} bytes, more than the {} of --max-node-bytes",
+            receipt.len(),
+            args.max_node_bytes
+        )));
+    }
+
+    let rlp = header.encode();
+    let spans = header::field_spans(&rlp, spec.layout)?;
+    let offset_of = |name: &str| {
+        spans
+            .iter()
+            .find(|span| span.name == name)
+            .map_or(0, |span| span.offset)
+    };
+    let receipts_root_offset = offset_of("receipts_root");
+    let rlp = spec.pad_header(args.block, rlp)?;
+    let key = receipts::index_key(args.tx_index);
+    let mut padded_key = key.clone();
+    padded_key.resize(RECEIPT_KEY_MAX_BYTES, 0);
+    let mut padded_receipt = receipt.clone();
+    padded_receipt.resize(args.max_node_bytes, 0);
+    let tx_type = receipts::tx_type(&receipts[args.tx_index]);
+
+    let mut params = Params::default();
+    params.push("block_hash", block_hash.as_bytes());
+    params.push("block_header_rlp", rlp);
+    params.push("receipts_root_offset", receipts_root_offset);
+    params.push("receipts_root", header.receipts_root.as_bytes());
+    if args.blob_gas_offsets {
+        push_blob_gas_offsets(&mut params, &header.encode(), header.layout(), args.block)?;
+    }
+    if let Some(state) = &state {
+        if state.fields.get("block_header_rlp") != params.fields.get("block_header_rlp") {
+            return Err(Error::Verification(format!(
+                "the account proof and the receipt proof were read under different headers \
+                 of block {}; the provider may have reorged in between",
+                args.block
+            )));
+        }
+        params.push("state_root_offset", offset_of("state_root"));
+        params.push("state_root", header.state_root.as_bytes());
+        for (name, value) in state.fields.iter() {
+            if params.fields.get(name).is_none() {
+                params.push(name.clone(), value.clone());
+            }
+        }
+    }
+    params.push("receipt_key", padded_key);
+    params.push("receipt_key_len", key.len());
+    params.push("receipt", padded_receipt);
+    params.push("receipt_len", receipt.len());
+    params.push(
+        "receipt_proof",
+        pad_nodes(
+            "receipt proof",
+            &nodes,
+            RECEIPT_PROOF_MAX_DEPTH,
+            args.max_node_bytes,
+        )?,
+    );
+    params.push("receipt_proof_depth", nodes.len());
+    params.meta.push(
+        "witness_format_version",
+        migrate::WITNESS_FORMAT_VERSION as usize,
+    );
+    match (&spec.schedule, spec.layout) {
+        (Some(schedule), header::Layout::Ethereum) => {
+            let fork = schedule.active(header.number.as_u64(), header.timestamp.low_u64());
+            params.meta.push("fork", fork.name());
+        }
+        (_, header::Layout::Ethereum) => {}
+        (_, layout) => params.meta.push("header_layout", layout.name()),
+    }
+    if let Some(len) = spec.max_header_bytes {
+        params.meta.push("max_header_bytes", len);
+    }
+    params.meta.push("tx_index", args.tx_index);
+    params.meta.push("tx_type", tx_type as usize);
+    if let Some(state) = &state {
+        for (name, value) in state.meta.iter() {
+            if name != "witness_hash" && params.meta.get(name).is_none() {
+                params.meta.push(name.clone(), value.clone());
+            }
+        }
+    }
+    params.record_witness_hash();
+    Ok(params)
+}
+
+/// Pushes the three bits `item` sets in the logs bloom as `<name>_bits`,
+/// with `<name>_bits_set` telling which of them `bloom` has set; returns
+/// whether all are.
+fn push_bloom_bits(params: &mut Params, name: &str, bloom: &[u8; 256], item: &[u8]) -> bool {
+    let bits = receipts::bloom_bits(item);
+    let set = bits.map(|bit| receipts::bloom_bit_set(bloom, bit));
+    params.push(format!("{}_bits", name), bits.to_vec());
+    params.push(
+        format!("{}_bits_set", name),
+        set.map(|set| set as usize).to_vec(),
+    );
+    set.iter().all(|&set| set)
+}
+
+/// Builds the parameters of `gen_bloom_params`: the header of block
+/// `args.block` with its logs bloom, and the bloom bits of the event topic
+/// and address asked about. A bit that is not set proves no log of the
+/// block matches, so no receipt needs proving; all bits set only means one
+/// may.
+pub async fn generate_bloom(args: &BloomOptions) -> Result<Params> {
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url)?);
+    let source: &dyn EthDataSource = &web3;
+    let chain_id = source.chain_id().await?;
+    let spec = match &args.chain_spec {
+        Some(path) => ChainSpec::load(path)?,
+        None => ChainSpec::known(chain_id),
+    };
+    let (header, block_hash) = header::fetch(source, args.block, &spec).await?;
+    let rlp = header.encode();
+    let logs_bloom_offset = header::field_spans(&rlp, spec.layout)?
+        .iter()
+        .find(|span| span.name == "logs_bloom")
+        .map_or(0, |span| span.offset);
+    let bloom = header.logs_bloom.0;
+
+    let mut params = Params::default();
+    params.push("block_hash", block_hash.as_bytes());
+    params.push("block_header_rlp", spec.pad_header(args.block, rlp)?);
+    params.push("logs_bloom_offset", logs_bloom_offset);
+    params.push("logs_bloom", bloom.as_slice());
+    let mut may_contain = true;
+    if let Some(topic) = args.event {
+        params.push("bloom_event_topic", topic.as_bytes());
+        may_contain &= push_bloom_bits(&mut params, "bloom_event", &bloom, topic.as_bytes());
+    }
+    if let Some(address) = args.address {
+        params.push("bloom_address", address.as_bytes());
+        may_contain &= push_bloom_bits(&mut params, "bloom_address", &bloom, address.as_bytes());
+    }
+    params.push("bloom_may_contain", may_contain as usize);
+    params.meta.push(
+        "witness_format_version",
+        migrate::WITNESS_FORMAT_VERSION as usize,
+    );
+    params.meta.push("bloom_block", args.block as usize);
+    params.record_witness_hash();
+    Ok(params)
+}
+
+/// The hash of block `block` on the chain of `rpc_url`, verified by the
+/// light client bootstrapped from `checkpoint`: the parent hashes are walked
+/// back from its finalized execution block, at most `max_ancestry` blocks.
+/// Returns the finalized block too.
+pub async fn light_client_block_hash(
+    rpc_url: &str,
+    beacon_api: &str,
+    checkpoint: H256,
+    block: u64,
+    max_ancestry: u64,
+) -> Result<(H256, light_client::FinalizedExecution)> {
+    let finalized = light_client::verify_finalized(beacon_api, checkpoint).await?;
+    if block > finalized.block_number {
+        return Err(Error::LightClient(format!(
+            "block {} is newer than the finalized block {}",
+            block, finalized.block_number
+        )));
+    }
+    if finalized.block_number - block > max_ancestry {
+        return Err(Error::LightClient(format!(
+            "block {} is more than {} blocks behind the finalized block {}",
+            block, max_ancestry, finalized.block_number
+        )));
+    }
+
+    let web3 = web3::Web3::new(rpc::Client::new(rpc_url)?);
+    let mut expected_hash = finalized.block_hash;
+    for number in (block..=finalized.block_number).rev() {
+        let (header, _) = header::fetch(&web3, number, &ChainSpec::default()).await?;
+        let hash = header.hash();
+        if hash != expected_hash {
+            return Err(Error::LightClient(format!(
+                "block {} hash {:?} breaks the chain to the finalized block",
+                number, hash
+            )));
+        }
+        expected_hash = header.parent_hash;
+        if number == block {
+            return Ok((hash, finalized));
+        }
+    }
+    unreachable!("the walk ends at block {}", block)
+}
+
+/// Checks the generated block hash against the light client's finalized
+/// execution block by walking parent hashes back to the target block.
+async fn verify_with_light_client(
+    args: &GenOptions,
+    beacon_api: &str,
+    checkpoint: H256,
+    params: &mut Params,
+) -> Result<()> {
+    let (hash, finalized) = light_client_block_hash(
+        &args.rpc_url[0],
+        beacon_api,
+        checkpoint,
+        args.block,
+        args.lc_max_ancestry,
+    )
+    .await?;
+    if params.fields.get("block_hash") != Some(&Value::from(hash.as_bytes())) {
+        return Err(Error::LightClient(format!(
+            "generated block hash does not match the verified hash {:?}",
+            hash
+        )));
+    }
+
+    params.meta.push("light_client", "verified");
+    params
+        .meta
+        .push("light_client_finalized_slot", finalized.slot as usize);
+    params.meta.push(
+        "light_client_finalized_block",
+        finalized.block_number as usize,
+    );
+    Ok(())
+}
+
+/// Generates the prover (or, without `prove`, the verifier) parameters
+/// described by `args`: fetched from every provider, cross-checked, then
+/// optionally verified with the light client, checked for reorgs and
+/// signed.
+pub async fn generate_params(args: &GenOptions, prove: bool) -> Result<Params> {
+    let mut params = generate_unfinished(args, prove).await?;
+    if args.reproducibility_check {
+        check_reproducible(args, prove, &params).await?;
+        params.meta.push("reproducibility", "checked");
+    }
+    if args.emit_monomorphized_nr.is_some() {
+        monomorphize::trim(&mut params)?;
+    }
+    if args.blind_slot {
+        blind_slot(args, &mut params)?;
+    }
+    // Batches rename each witness before combining them.
+    let renames = match args.block_range.is_some() || args.uncles {
+        true => &[][..],
+        false => &args.renames[..],
+    };
+    finish(args, &mut params, renames)?;
+    Ok(params)
+}
+
+/// `--blind-slot`: replaces the slot of `params` with its hash and commits
+/// to it with `--slot-salt`, or a fresh salt printed for the caller to keep.
+fn blind_slot(args: &GenOptions, params: &mut Params) -> Result<()> {
+    let slot = args.slot.ok_or_else(|| {
+        Error::Config("--blind-slot hides the storage slot; pass --slot".to_string())
+    })?;
+    let salt = match args.slot_salt {
+        Some(salt) => salt,
+        None => {
+            let salt = blind::random_salt()?;
+            log::info!(
+                "Slot commitment salt {:?}; keep it to open slot_commitment",
+                salt
+            );
+            salt
+        }
+    };
+    blind::blind(params, &slot, &salt);
+    Ok(())
+}
+
+/// The parameters of [`generate_params`] before they are renamed and
+/// signed.
+async fn generate_unfinished(args: &GenOptions, prove: bool) -> Result<Params> {
+    if let Some(range) = args.block_range {
+        return generate_range(args, range, prove).await;
+    }
+    if args.uncles {
+        return generate_uncles(args, prove).await;
+    }
+    let mut params = generate_strict(args, prove).await?;
+    apply_overrides(&mut params, &args.overrides)?;
+    Ok(params)
+}
+
+/// `--reproducibility-check`: generates `params` a second time, past the
+/// proof cache and the witness store so every node is fetched again, and
+/// fails unless the two runs are byte-identical, fields and metadata.
+async fn check_reproducible(args: &GenOptions, prove: bool, params: &Params) -> Result<()> {
+    let args = GenOptions {
+        proof_cache: None,
+        store: false,
+        ..args.clone()
+    };
+    log::info!("Generating again to check that the run is reproducible");
+    let again = generate_unfinished(&args, prove).await?;
+    if again.canonical_bytes(&[]) == params.canonical_bytes(&[]) {
+        return Ok(());
+    }
+    let differs = |a: &Fields, b: &Fields, prefix: &str| {
+        a.iter()
+            .filter(|(name, value)| b.get(name) != Some(value))
+            .chain(b.iter().filter(|(name, _)| a.get(name).is_none()))
+            .map(|(name, _)| format!("{}{}", prefix, name))
+            .collect::<Vec<_>>()
+    };
+    let mut differing = differs(&params.fields, &again.fields, "");
+    differing.extend(differs(&params.meta, &again.meta, "meta."));
+    Err(Error::Verification(match differing.is_empty() {
+        true => {
+            "two runs with the same inputs emitted the same fields in a different order".to_string()
+        }
+        false => format!(
+            "two runs with the same inputs differ in {}; a provider may be serving \
+             inconsistent data",
+            differing.join(", ")
+        ),
+    }))
+}
+
+/// The witness of the canonical block of `args.block` and of every uncle
+/// of that height, combined into one batch like [`generate_bundle`]: the
+/// fields of the canonical witness, overridden and renamed, are prefixed
+/// with `canonical_` and those of the `i`th uncle with `uncle_{i}_`. The
+/// uncles are verified against their own state roots, read at their
+/// hashes, which providers rarely keep for long; one whose state is gone
+/// fails the batch.
+async fn generate_uncles(args: &GenOptions, prove: bool) -> Result<Params> {
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url[0])?);
+    let uncles = reorg::uncles_at(&web3, args.block).await?;
+    log::info!("Block {} has {} uncle(s)", args.block, uncles.len());
+    let finish_one = |mut params: Params| {
+        if args.strict {
+            strict::check(&params)?;
+        }
+        apply_overrides(&mut params, &args.overrides)?;
+        apply_renames(&mut params, &args.renames)?;
+        Ok::<_, Error>(params)
+    };
+    let canonical = generate_stored(args, prove).await.and_then(finish_one)?;
+    let mut batch = Params::default();
+    for (name, value) in canonical.fields.iter() {
+        batch.push(format!("canonical_{}", name), value.clone());
+    }
+    batch.meta = canonical.meta;
+    let mut hashes = Vec::new();
+    for (i, uncle) in uncles.into_iter().enumerate() {
+        let hash: H256 = serde_json::from_value(uncle["hash"].clone())
+            .map_err(|e| Error::Decode(format!("invalid uncle hash: {}", e)))?;
+        let source = source::AtHash::new(&web3, args.block, hash, uncle);
+        let params = generate_with_source(&source, args, prove)
+            .await
+            .and_then(finish_one)
+            .inspect_err(|_| log::error!("Failed at uncle {:?}", hash))?;
+        for (name, value) in params.fields.iter() {
+            batch.push(format!("uncle_{}_{}", i, name), value.clone());
+        }
+        hashes.push(Value::Str(format!("{:?}", hash)));
+    }
+    batch.meta.push("uncles", hashes.len());
+    batch.meta.push("uncle_hashes", Value::Array(hashes));
+    batch.record_witness_hash();
+    Ok(batch)
+}
+
+/// Number of blocks of a `--block-range` generated concurrently.
+const RANGE_CONCURRENCY: usize = 4;
+
+/// The witness of every block of `--block-range`, combined into one batch
+/// like [`generate_bundle`]: the fields of the `i`th block, overridden and
+/// renamed, are prefixed with `block_{i}_`, and the resolved block list is
+/// recorded in the metadata.
+async fn generate_range(args: &GenOptions, range: BlockRange, prove: bool) -> Result<Params> {
+    let blocks = range.blocks(args.stride);
+    log::info!(
+        "Generating {} witness(es), blocks {} to {} every {}",
+        blocks.len(),
+        range.lo,
+        range.hi,
+        args.stride
+    );
+    let results: Vec<Result<Params>> = stream::iter(blocks.iter().map(|&block| {
+        let mut args = args.clone();
+        args.block_range = None;
+        args.block_ref = BlockRef::Number(block);
+        args.block = block;
+        async move {
+            let mut params = generate_strict(&args, prove).await?;
+            apply_overrides(&mut params, &args.overrides)?;
+            apply_renames(&mut params, &args.renames)?;
+            Ok(params)
+        }
+    }))
+    .buffered(RANGE_CONCURRENCY)
+    .collect()
+    .await;
+
+    let mut batch = Params::default();
+    for (i, (result, block)) in results.into_iter().zip(&blocks).enumerate() {
+        let params =
+            result.inspect_err(|_| log::error!("Failed at block {} of the range", block))?;
+        for (name, value) in params.fields.iter() {
+            batch.push(format!("block_{}_{}", i, name), value.clone());
+        }
+        if i == 0 {
+            batch.meta = params.meta;
+        }
+    }
+    batch
+        .meta
+        .push("block_range", format!("{}..{}", range.lo, range.hi));
+    batch.meta.push("stride", args.stride as usize);
+    batch.meta.push(
+        "blocks",
+        blocks
+            .iter()
+            .map(|&block| block as usize)
+            .collect::<Vec<usize>>(),
+    );
+    batch.record_witness_hash();
+    Ok(batch)
+}
+
+/// [`generate_params`] as a typed [`StorageProofWitness`].
+pub async fn generate_witness(args: &GenOptions, prove: bool) -> Result<StorageProofWitness> {
+    StorageProofWitness::from_params(&generate_params(args, prove).await?)
+}
+
+/// Generates the prover and the verifier parameters described by `args`
+/// from a single fetch, so the two always describe the same block. The
+/// verifier parameters are taken from the prover parameters once the
+/// overrides are applied; renames of fields only the prover has are
+/// skipped for them.
+pub async fn generate_both(args: &GenOptions) -> Result<(Params, Params)> {
+    if args.uncles {
+        return Err(Error::Unsupported(
+            "--verify-out splits one witness; generate the verifier batch of --uncles with \
+             gen_verify_params"
+                .to_string(),
+        ));
+    }
+    if args.block_range.is_some() {
+        return Err(Error::Unsupported(
+            "--verify-out splits one witness; generate the verifier batch of a --block-range \
+             with gen_verify_params"
+                .to_string(),
+        ));
+    }
+    let mut prover = generate_unfinished(args, true).await?;
+    if args.reproducibility_check {
+        check_reproducible(args, true, &prover).await?;
+        prover.meta.push("reproducibility", "checked");
+    }
+    if args.blind_slot {
+        blind_slot(args, &mut prover)?;
+    }
+    let mut verifier = verifier_params(&prover);
+    let verifier_renames: Vec<(String, String)> = args
+        .renames
+        .iter()
+        .filter(|(old, _)| verifier.fields.get(old).is_some())
+        .cloned()
+        .collect();
+    finish(args, &mut prover, &args.renames)?;
+    finish(args, &mut verifier, &verifier_renames)?;
+    Ok((prover, verifier))
+}
+
+/// Records the reading of the proven value, renames the fields of
+/// `params`, records the byte encoding, TOML wrapping and public key and
+/// signs them.
+fn finish(args: &GenOptions, params: &mut Params, renames: &[(String, String)]) -> Result<()> {
+    units::record(args, params);
+    apply_renames(params, renames)?;
+
+    if args.byte_encoding != ByteEncoding::Decimal {
+        params.meta.push("byte_encoding", args.byte_encoding.name());
+    }
+    if let Some(wrap) = args.toml_wrap {
+        params.meta.push("toml_wrap", wrap);
+    }
+    if let Some(key) = args.pubkey {
+        params
+            .meta
+            .push("pubkey", format!("0x{}", hex::encode(key.as_bytes())));
+    }
+
+    if let Some(key_path) = &args.sign_key {
+        #[cfg(feature = "signing")]
+        signing::sign(params, key_path)?;
+        #[cfg(not(feature = "signing"))]
+        return Err(unsigned("--sign-key", key_path));
+    }
+    Ok(())
+}
+
+/// The `--only-checkpointed` check of `params` against the signed registry.
+fn check_checkpointed(args: &GenOptions, chain_id: u64, params: &mut Params) -> Result<()> {
+    #[cfg(feature = "signing")]
+    {
+        checkpoints::check(args, chain_id, params)
+    }
+    #[cfg(not(feature = "signing"))]
+    {
+        let _ = (chain_id, params);
+        Err(unsigned("--only-checkpointed", &args.checkpoints))
+    }
+}
+
+/// An option that signs or checks signatures, which this build was compiled
+/// without the `signing` feature to do.
+#[cfg(not(feature = "signing"))]
+fn unsigned(option: &str, path: &std::path::Path) -> Error {
+    Error::Unsupported(format!(
+        "{} {} needs the signing feature; rebuild with --features signing",
+        option,
+        path.display()
+    ))
+}
+
+/// [`generate_stored`] followed by the `--strict` check of the generated
+/// witness, before any override.
+async fn generate_strict(args: &GenOptions, prove: bool) -> Result<Params> {
+    let params = generate_stored(args, prove).await?;
+    if args.strict {
+        strict::check(&params)?;
+    }
+    Ok(params)
+}
+
+/// [`generate_checked`] through the witness store with `--store`: a
+/// witness stored for the same chain, block, account, slot and options is
+/// reused while the provider still has that block, and a generated one is
+/// saved. Synthetic witnesses and ones from a given state root are not
+/// tied to a block, so they are never stored.
+async fn generate_stored(args: &GenOptions, prove: bool) -> Result<Params> {
+    if !args.store
+        || args.state_override.is_some()
+        || args.state_root.is_some()
+        || args.from_smt.is_some()
+    {
+        return generate_checked(args, prove).await;
+    }
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url[0])?);
+    let chain_id = web3.eth().chain_id().await?.low_u64();
+    let block = web3
+        .eth()
+        .block(BlockId::Number(BlockNumber::Number(args.block.into())))
+        .await?;
+    let key = store::Key {
+        chain_id,
+        block: args.block,
+        account: args.account,
+        slot: args.slot,
+    };
+    let variant = store::variant(args, prove);
+    let store = store::Store::open()?;
+    if let (Some(mut params), Some(block)) = (store.get(&key, &variant)?, block) {
+        // A reorg since the witness was stored replaces it.
+        let canonical = match params.fields.get_bytes("block_hash") {
+            Some(hash) => block
+                .hash
+                .is_some_and(|block_hash| block_hash.as_bytes() == hash),
+            None => params.fields.get_bytes("state_root") == Some(block.state_root.as_bytes()),
+        };
+        if canonical {
+            if let Some(value) = params.fields.get("storage_value") {
+                let len = params.fields.get_int("storage_value_len");
+                let value = args
+                    .value_form
+                    .decode(value, len, args.numeric_encoding)
+                    .ok_or_else(|| {
+                        Error::Decode(format!(
+                            "the stored storage_value is not {} {}",
+                            args.value_form.name(),
+                            args.numeric_encoding.name()
+                        ))
+                    })?;
+                check_expected(args, value)?;
+            }
+            // The registry may have changed since the witness was stored.
+            if args.only_checkpointed {
+                check_checkpointed(args, chain_id, &mut params)?;
+            }
+            log::info!(
+                "Note: reusing the witness 0x{} from the store at {}",
+                hex::encode(params.witness_hash()),
+                store.dir().display()
+            );
+            return Ok(params);
+        }
+    }
+    let params = generate_checked(args, prove).await?;
+    store.put(&key, &variant, &params, args.compressor)?;
+    Ok(params)
+}
+
+/// [`generate_with_quorum`] followed by the light client, checkpoint and
+/// reorg checks `args` asks for.
+async fn generate_checked(args: &GenOptions, prove: bool) -> Result<Params> {
+    let mut params = generate_with_quorum(args, prove).await?;
+
+    if let (Some(beacon_api), Some(checkpoint)) = (&args.beacon_api, args.lc_checkpoint) {
+        verify_with_light_client(args, beacon_api, checkpoint, &mut params).await?;
+    }
+
+    if let (Some(ChainPreset::Polygon), Some(l1_rpc_url)) = (args.chain, &args.l1_rpc_url) {
+        polygon::link_checkpoint(&args.rpc_url[0], l1_rpc_url, args.block, prove, &mut params)
+            .await?;
+    }
+
+    if args.reorg_check || args.confirmations > 0 || args.wait_finalized {
+        let block_hash = match params.fields.get("block_hash") {
+            Some(Value::Bytes(bytes)) => H256::from_slice(bytes),
+            _ => {
+                return Err(Error::Reorg(
+                    "generated parameters lack block_hash".to_string(),
+                ))
+            }
+        };
+        let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url[0])?);
+        reorg::wait_for_block(
+            &web3,
+            args.block,
+            args.confirmations,
+            args.wait_finalized,
+            Duration::from_secs(args.poll_interval),
+        )
+        .await?;
+        reorg::ensure_canonical(&web3, args.block, block_hash).await?;
+        params.meta.push("reorg_check", "passed");
+        params
+            .meta
+            .push("confirmations", args.confirmations as usize);
+    }
+
+    if let Some(timestamp) = args.at_timestamp {
+        params.meta.push("at_timestamp", timestamp as usize);
+        params.meta.push("block_number", args.block as usize);
+    }
+
+    if args.only_checkpointed {
+        let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url[0])?);
+        let chain_id = web3.eth().chain_id().await?.low_u64();
+        check_checkpointed(args, chain_id, &mut params)?;
+    }
+
+    if let Some(beacon) = &args.beacon_block {
+        let matches = match params.fields.get_bytes("block_hash") {
+            Some(hash) => hash == beacon.block_hash.as_bytes(),
+            None => params.fields.get_bytes("state_root") == Some(beacon.state_root.as_bytes()),
+        };
+        if !matches {
+            return Err(Error::Verification(format!(
+                "block {} is not the execution block {:?} of beacon block {:?}",
+                args.block, beacon.block_hash, beacon.beacon_root
+            )));
+        }
+        params
+            .meta
+            .push("beacon_root", format!("{:?}", beacon.beacon_root));
+        params.meta.push("beacon_slot", beacon.slot as usize);
+        params.meta.push("block_number", args.block as usize);
+    }
+
+    Ok(params)
+}
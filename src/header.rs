@@ -0,0 +1,757 @@
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+use rlp::{DecoderError, Rlp, RlpStream};
+use serde::{Deserialize, Serialize};
+use web3::types::{Bytes, H160, H2048, H256, H64, U256, U64};
+
+use crate::chain_spec::ChainSpec;
+use crate::error::{Error, Result};
+use crate::forks;
+use crate::keccak::keccak256;
+use crate::source::EthDataSource;
+
+pub use crate::core::nodes::encoded_len;
+
+/// Number of fields in a header before any fork added optional ones.
+pub const BASE_FIELD_COUNT: usize = 15;
+
+/// Index of `mix_hash`, where the seal of engines that replace it and
+/// `nonce` starts.
+const SEAL_START: usize = 13;
+
+/// Seal items of an AuRa header: the step and the signature.
+pub const AURA_SEAL_FIELDS: usize = 2;
+
+/// Names of the header fields in encoding order.
+pub const FIELD_NAMES: [&str; BASE_FIELD_COUNT + 6] = [
+    "parent_hash",
+    "uncles_hash",
+    "author",
+    "state_root",
+    "transactions_root",
+    "receipts_root",
+    "logs_bloom",
+    "difficulty",
+    "number",
+    "gas_limit",
+    "gas_used",
+    "timestamp",
+    "extra_data",
+    "mix_hash",
+    "nonce",
+    "base_fee_per_gas",
+    "withdrawals_root",
+    "blob_gas_used",
+    "excess_blob_gas",
+    "parent_beacon_block_root",
+    "requests_hash",
+];
+
+/// Names of the header fields of the Avalanche C-Chain in encoding order.
+pub const CORETH_FIELD_NAMES: [&str; BASE_FIELD_COUNT + 9] = [
+    "parent_hash",
+    "uncles_hash",
+    "author",
+    "state_root",
+    "transactions_root",
+    "receipts_root",
+    "logs_bloom",
+    "difficulty",
+    "number",
+    "gas_limit",
+    "gas_used",
+    "timestamp",
+    "extra_data",
+    "mix_hash",
+    "nonce",
+    "ext_data_hash",
+    "base_fee_per_gas",
+    "ext_data_gas_used",
+    "block_gas_cost",
+    "blob_gas_used",
+    "excess_blob_gas",
+    "parent_beacon_block_root",
+    "timestamp_milliseconds",
+    "min_delay_excess",
+];
+
+/// Fields holding integers rather than hashes or byte strings.
+pub const SCALAR_FIELDS: &[&str] = &[
+    "difficulty",
+    "number",
+    "gas_limit",
+    "gas_used",
+    "timestamp",
+    "base_fee_per_gas",
+    "blob_gas_used",
+    "excess_blob_gas",
+    "ext_data_gas_used",
+    "block_gas_cost",
+    "timestamp_milliseconds",
+    "min_delay_excess",
+];
+
+/// Order and set of the fields in a header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Ethereum's, which most EVM chains share
+    #[default]
+    Ethereum,
+    /// The Avalanche C-Chain's: `ext_data_hash` follows `nonce`, the
+    /// Apricot fee fields follow the base fee and there is no withdrawals
+    /// root or requests hash
+    Coreth,
+}
+
+impl Layout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Ethereum => "ethereum",
+            Layout::Coreth => "coreth",
+        }
+    }
+
+    /// Names of the fields in encoding order.
+    pub fn field_names(&self) -> &'static [&'static str] {
+        match self {
+            Layout::Ethereum => &FIELD_NAMES,
+            Layout::Coreth => &CORETH_FIELD_NAMES,
+        }
+    }
+
+    /// Number of fields every header carries, before the optional ones.
+    pub fn required_fields(&self) -> usize {
+        match self {
+            Layout::Ethereum => BASE_FIELD_COUNT,
+            Layout::Coreth => BASE_FIELD_COUNT + 1,
+        }
+    }
+}
+
+/// A field located within an encoded header.
+pub struct FieldSpan {
+    pub name: &'static str,
+    /// Offset of the field's payload, past its RLP prefix.
+    pub offset: usize,
+    pub value: Vec<u8>,
+}
+
+/// Execution block header with every field up to Prague.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHeader {
+    pub parent_hash: H256,
+    #[serde(rename = "sha3Uncles")]
+    pub uncles_hash: H256,
+    #[serde(rename = "miner")]
+    pub author: H160,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+    pub logs_bloom: H2048,
+    pub difficulty: U256,
+    pub number: U64,
+    pub gas_limit: U256,
+    pub gas_used: U256,
+    pub timestamp: U256,
+    pub extra_data: Bytes,
+    /// Absent from the blocks of engines that seal with `seal_fields`.
+    #[serde(default)]
+    pub mix_hash: H256,
+    #[serde(default)]
+    pub nonce: H64,
+    /// London
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas: Option<U256>,
+    /// Shanghai
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals_root: Option<H256>,
+    /// Cancun
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_gas_used: Option<U64>,
+    /// Cancun
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excess_blob_gas: Option<U64>,
+    /// Cancun
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_beacon_block_root: Option<H256>,
+    /// Prague
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_hash: Option<H256>,
+    /// Avalanche C-Chain: hash of the block's atomic transactions, encoded
+    /// right after `nonce`. Its presence selects [`Layout::Coreth`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ext_data_hash: Option<H256>,
+    /// Avalanche Apricot Phase 4
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ext_data_gas_used: Option<U256>,
+    /// Avalanche Apricot Phase 4
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_gas_cost: Option<U256>,
+    /// Avalanche Granite
+    #[serde(
+        default,
+        alias = "timeMilliseconds",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timestamp_milliseconds: Option<U64>,
+    /// Avalanche Granite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_delay_excess: Option<U64>,
+    /// Seal of engines such as Aura, encoded in place of `mix_hash` and
+    /// `nonce`. Each item is already RLP encoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seal_fields: Option<Vec<Bytes>>,
+}
+
+impl BlockHeader {
+    pub fn layout(&self) -> Layout {
+        if self.ext_data_hash.is_some() {
+            Layout::Coreth
+        } else {
+            Layout::Ethereum
+        }
+    }
+
+    /// Drops the fields only the Avalanche C-Chain encodes.
+    fn clear_coreth(&mut self) {
+        self.ext_data_hash = None;
+        self.ext_data_gas_used = None;
+        self.block_gas_cost = None;
+        self.timestamp_milliseconds = None;
+        self.min_delay_excess = None;
+    }
+
+    /// The optional fields of the header's layout in encoding order, each
+    /// RLP encoded if present.
+    fn optional_fields(&self) -> Vec<Option<Vec<u8>>> {
+        let fields = match self.layout() {
+            Layout::Ethereum => vec![
+                self.base_fee_per_gas.map(|v| rlp::encode(&v)),
+                self.withdrawals_root.map(|v| rlp::encode(&v)),
+                self.blob_gas_used.map(|v| rlp::encode(&v)),
+                self.excess_blob_gas.map(|v| rlp::encode(&v)),
+                self.parent_beacon_block_root.map(|v| rlp::encode(&v)),
+                self.requests_hash.map(|v| rlp::encode(&v)),
+            ],
+            Layout::Coreth => vec![
+                self.base_fee_per_gas.map(|v| rlp::encode(&v)),
+                self.ext_data_gas_used.map(|v| rlp::encode(&v)),
+                self.block_gas_cost.map(|v| rlp::encode(&v)),
+                self.blob_gas_used.map(|v| rlp::encode(&v)),
+                self.excess_blob_gas.map(|v| rlp::encode(&v)),
+                self.parent_beacon_block_root.map(|v| rlp::encode(&v)),
+                self.timestamp_milliseconds.map(|v| rlp::encode(&v)),
+                self.min_delay_excess.map(|v| rlp::encode(&v)),
+            ],
+        };
+        fields
+            .into_iter()
+            .map(|field| field.map(|bytes| bytes.to_vec()))
+            .collect()
+    }
+
+    /// Whether each optional field is present, in fork order.
+    pub fn optional_present(&self) -> [bool; FIELD_NAMES.len() - BASE_FIELD_COUNT] {
+        [
+            self.base_fee_per_gas.is_some(),
+            self.withdrawals_root.is_some(),
+            self.blob_gas_used.is_some(),
+            self.excess_blob_gas.is_some(),
+            self.parent_beacon_block_root.is_some(),
+            self.requests_hash.is_some(),
+        ]
+    }
+
+    /// RLP encoding of the header. Optional fields are appended in fork
+    /// order for as long as they are present, whatever their value: a zero
+    /// base fee on a post-London chain is still encoded.
+    pub fn encode(&self) -> Vec<u8> {
+        let optional: Vec<Vec<u8>> = self
+            .optional_fields()
+            .into_iter()
+            .map_while(|field| field)
+            .collect();
+
+        let seal_len = self.seal_fields.as_ref().map_or(2, Vec::len);
+        let required = self.layout().required_fields() - BASE_FIELD_COUNT;
+        let mut rlp_stream = RlpStream::new();
+        rlp_stream
+            .begin_list(BASE_FIELD_COUNT - 2 + seal_len + required + optional.len())
+            .append(&self.parent_hash)
+            .append(&self.uncles_hash)
+            .append(&self.author)
+            .append(&self.state_root)
+            .append(&self.transactions_root)
+            .append(&self.receipts_root)
+            .append(&self.logs_bloom)
+            .append(&self.difficulty)
+            .append(&self.number)
+            .append(&self.gas_limit)
+            .append(&self.gas_used)
+            .append(&self.timestamp)
+            .append(&self.extra_data.0);
+        match &self.seal_fields {
+            Some(seal_fields) => {
+                for field in seal_fields {
+                    rlp_stream.append_raw(&field.0, 1);
+                }
+            }
+            None => {
+                rlp_stream.append(&self.mix_hash).append(&self.nonce);
+            }
+        }
+        if let Some(ext_data_hash) = &self.ext_data_hash {
+            rlp_stream.append(ext_data_hash);
+        }
+        for field in &optional {
+            rlp_stream.append_raw(field, 1);
+        }
+
+        rlp_stream.out().to_vec()
+    }
+
+    pub fn decode(data: &[u8], layout: Layout) -> std::result::Result<Self, DecoderError> {
+        Self::decode_sealed(data, layout, None)
+    }
+
+    /// Decodes a header whose engine seals it with `seal_len` items in
+    /// place of `mix_hash` and `nonce`, such as the step and signature of
+    /// AuRa ([`AURA_SEAL_FIELDS`]); the items are kept raw in
+    /// `seal_fields`, as [`encode`](Self::encode) writes them. Without a
+    /// seal this is [`decode`](Self::decode).
+    pub fn decode_sealed(
+        data: &[u8],
+        layout: Layout,
+        seal_len: Option<usize>,
+    ) -> std::result::Result<Self, DecoderError> {
+        let rlp = Rlp::new(data);
+        let item_count = rlp.item_count()?;
+        let names = layout.field_names();
+        let seal = seal_len.unwrap_or(2);
+        let shifted = |count: usize| (count + seal).checked_sub(2);
+        let (min, max) = match (shifted(layout.required_fields()), shifted(names.len())) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Err(DecoderError::RlpIncorrectListLen),
+        };
+        if !(min..=max).contains(&item_count) {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        if rlp.as_raw().len() != data.len() {
+            return Err(DecoderError::RlpInconsistentLengthAndData);
+        }
+        // Fields after the seal move by as many items as it differs from
+        // mix_hash and nonce.
+        let position = |name: &str| {
+            names
+                .iter()
+                .position(|field| *field == name)
+                .and_then(|i| {
+                    if i < SEAL_START + 2 {
+                        Some(i)
+                    } else {
+                        shifted(i)
+                    }
+                })
+                .filter(|&i| i < item_count)
+        };
+        let (mix_hash, nonce, seal_fields) = match seal_len {
+            None => (rlp.val_at(13)?, rlp.val_at(14)?, None),
+            Some(len) => {
+                let items = (SEAL_START..SEAL_START + len)
+                    .map(|i| Ok(Bytes(rlp.at(i)?.as_raw().to_vec())))
+                    .collect::<std::result::Result<_, DecoderError>>()?;
+                (H256::zero(), H64::zero(), Some(items))
+            }
+        };
+
+        Ok(BlockHeader {
+            parent_hash: rlp.val_at(0)?,
+            uncles_hash: rlp.val_at(1)?,
+            author: rlp.val_at(2)?,
+            state_root: rlp.val_at(3)?,
+            transactions_root: rlp.val_at(4)?,
+            receipts_root: rlp.val_at(5)?,
+            logs_bloom: rlp.val_at(6)?,
+            difficulty: rlp.val_at(7)?,
+            number: rlp.val_at(8)?,
+            gas_limit: rlp.val_at(9)?,
+            gas_used: rlp.val_at(10)?,
+            timestamp: rlp.val_at(11)?,
+            extra_data: Bytes(rlp.val_at(12)?),
+            mix_hash,
+            nonce,
+            base_fee_per_gas: optional_at(&rlp, position("base_fee_per_gas"))?,
+            withdrawals_root: optional_at(&rlp, position("withdrawals_root"))?,
+            blob_gas_used: optional_at(&rlp, position("blob_gas_used"))?,
+            excess_blob_gas: optional_at(&rlp, position("excess_blob_gas"))?,
+            parent_beacon_block_root: optional_at(&rlp, position("parent_beacon_block_root"))?,
+            requests_hash: optional_at(&rlp, position("requests_hash"))?,
+            ext_data_hash: optional_at(&rlp, position("ext_data_hash"))?,
+            ext_data_gas_used: optional_at(&rlp, position("ext_data_gas_used"))?,
+            block_gas_cost: optional_at(&rlp, position("block_gas_cost"))?,
+            timestamp_milliseconds: optional_at(&rlp, position("timestamp_milliseconds"))?,
+            min_delay_excess: optional_at(&rlp, position("min_delay_excess"))?,
+            seal_fields,
+        })
+    }
+
+    pub fn hash(&self) -> H256 {
+        keccak256(&self.encode()).into()
+    }
+}
+
+fn optional_at<T: rlp::Decodable>(
+    rlp: &Rlp,
+    index: Option<usize>,
+) -> std::result::Result<Option<T>, DecoderError> {
+    index.map(|i| rlp.val_at(i)).transpose()
+}
+
+/// Locates every field of a header encoded with `layout`.
+pub fn field_spans(
+    data: &[u8],
+    layout: Layout,
+) -> std::result::Result<Vec<FieldSpan>, DecoderError> {
+    field_spans_sealed(data, layout, None)
+}
+
+/// Locates every field of a header encoded with `layout` and sealed with
+/// `seal_len` items in place of `mix_hash` and `nonce`, each named `seal`.
+pub fn field_spans_sealed(
+    data: &[u8],
+    layout: Layout,
+    seal_len: Option<usize>,
+) -> std::result::Result<Vec<FieldSpan>, DecoderError> {
+    let rlp = Rlp::new(data);
+    let item_count = rlp.item_count()?;
+    let names = layout.field_names();
+    let seal = seal_len.unwrap_or(2);
+    let name = |i: usize| match seal_len {
+        Some(_) if (SEAL_START..SEAL_START + seal).contains(&i) => Some("seal"),
+        Some(_) if i >= SEAL_START + seal => names.get(i + 2 - seal).copied(),
+        _ => names.get(i).copied(),
+    };
+    (0..item_count)
+        .map(|i| {
+            let (item, offset) = rlp.at_with_offset(i)?;
+            Ok(FieldSpan {
+                name: name(i).ok_or(DecoderError::RlpIncorrectListLen)?,
+                offset: offset + item.payload_info()?.header_len,
+                value: item.data()?.to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// Seal fields of an AuRa block built from the `step` and `signature` that
+/// clients such as Nethermind report in place of `sealFields`.
+fn aura_seal(block: &serde_json::Value) -> Option<Vec<Bytes>> {
+    let step = match &block["step"] {
+        serde_json::Value::String(step) => match step.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+            None => step.parse().ok()?,
+        },
+        step => step.as_u64()?,
+    };
+    let signature: Bytes = serde_json::from_value(block["signature"].clone()).ok()?;
+    Some(vec![
+        Bytes(rlp::encode(&step).to_vec()),
+        Bytes(rlp::encode(&signature.0).to_vec()),
+    ])
+}
+
+/// Reads a block as returned by `eth_getBlockByNumber`, checks it against
+/// `spec` and checks that the re-encoded header hashes to the reported block
+/// hash. web3's `Block` type drops the fields added since Shanghai, so the
+/// raw response is used.
+pub fn from_rpc(
+    block: serde_json::Value,
+    number: u64,
+    spec: &ChainSpec,
+) -> Result<(BlockHeader, H256)> {
+    if block.is_null() {
+        return Err(Error::UnminedBlock(format!(
+            "block {} was not found",
+            number
+        )));
+    }
+    if block["hash"].is_null() || block["number"].is_null() {
+        return Err(Error::UnminedBlock(
+            "the block is pending and has no hash or number yet".to_string(),
+        ));
+    }
+    let hash: H256 = serde_json::from_value(block["hash"].clone())
+        .map_err(|e| Error::Decode(format!("invalid block hash: {}", e)))?;
+    let aura_seal = aura_seal(&block);
+    let mut header: BlockHeader = serde_json::from_value(block)
+        .map_err(|e| Error::Decode(format!("invalid block header: {}", e)))?;
+    match spec.layout {
+        Layout::Ethereum => header.clear_coreth(),
+        Layout::Coreth if header.ext_data_hash.is_none() => {
+            return Err(Error::Decode(format!(
+                "the chain spec uses the coreth header layout, but block {} has no extDataHash",
+                number
+            )));
+        }
+        Layout::Coreth => {}
+    }
+    if !spec.seal_fields {
+        header.seal_fields = None;
+    } else if header.seal_fields.is_none() {
+        header.seal_fields = Some(aura_seal.ok_or_else(|| {
+            Error::Decode(format!(
+                "the chain spec encodes seal fields, but block {} has neither sealFields nor an \
+                 AuRa step and signature",
+                number
+            ))
+        })?);
+    }
+    // A Clique genesis lists the initial signers in its extra data, so the
+    // genesis block is exempt from the chain's extra data length.
+    if let Some(len) = spec.extra_data_len.filter(|_| number != 0) {
+        if header.extra_data.0.len() != len {
+            return Err(Error::Decode(format!(
+                "block {} has {} bytes of extra data, the chain spec expects {}",
+                number,
+                header.extra_data.0.len(),
+                len
+            )));
+        }
+    }
+    let present: Vec<bool> = header
+        .optional_fields()
+        .iter()
+        .map(Option::is_some)
+        .collect();
+    if let Some(gap) = present.windows(2).position(|pair| !pair[0] && pair[1]) {
+        let names = &spec.layout.field_names()[spec.layout.required_fields()..];
+        return Err(Error::Decode(format!(
+            "block {} reports {} but not {}, which an earlier fork added; the provider dropped \
+             a header field",
+            number,
+            names[gap + 1],
+            names[gap]
+        )));
+    }
+    // Fork schedules describe Ethereum's header fields only.
+    if let (Some(schedule), Layout::Ethereum) = (&spec.schedule, spec.layout) {
+        forks::validate(schedule, &header)?;
+    }
+    if header.hash() != hash {
+        return Err(Error::Verification(format!(
+            "block {} header encodes to {:?}, not the reported hash {:?}",
+            number,
+            header.hash(),
+            hash
+        )));
+    }
+    Ok((header, hash))
+}
+
+/// Fetches the header of block `number`, checked against `spec`.
+pub async fn fetch(
+    source: &dyn EthDataSource,
+    number: u64,
+    spec: &ChainSpec,
+) -> Result<(BlockHeader, H256)> {
+    from_rpc(source.get_block(number).await?, number, spec)
+}
+
+/// Splits a header encoded with `layout` around its state root. The root
+/// is searched for in the bytes, but should they hold it more than once,
+/// as another 32-byte field or a run of the extra data might, the first
+/// match could be the wrong one: the offset is then read from the RLP
+/// structure instead, with a warning.
+pub fn split_rlp_by_state_root(
+    rlp_data: &[u8],
+    state_root: &[u8],
+    layout: Layout,
+) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let start = match find_subarrays(rlp_data, state_root)[..] {
+        [] => return None,
+        [start] => start,
+        ref starts => {
+            log::warn!(
+                "the state root occurs {} times in the header; locating it from the \
+                 RLP structure",
+                starts.len()
+            );
+            let span = field_spans(rlp_data, layout)
+                .ok()?
+                .into_iter()
+                .find(|field| field.name == "state_root")?;
+            if span.value != state_root {
+                return None;
+            }
+            span.offset
+        }
+    };
+    let rlp_head = rlp_data[..start].to_vec();
+    let state_root_bytes = rlp_data[start..start + 32].to_vec();
+    let rlp_tail = rlp_data[start + 32..].to_vec();
+
+    Some((rlp_head, state_root_bytes, rlp_tail))
+}
+
+/// Every offset `subarray` starts at in `array`.
+fn find_subarrays(array: &[u8], subarray: &[u8]) -> Vec<usize> {
+    array
+        .windows(subarray.len())
+        .enumerate()
+        .filter(|(_, window)| *window == subarray)
+        .map(|(start, _)| start)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn h256() -> impl Strategy<Value = H256> {
+        any::<[u8; 32]>().prop_map(H256)
+    }
+
+    /// Headers of every fork, with the optional fields present up to a
+    /// random one, and a state root that may repeat in other fields.
+    fn header() -> impl Strategy<Value = BlockHeader> {
+        (
+            (h256(), h256(), any::<[u8; 20]>(), h256(), h256(), h256()),
+            (
+                any::<u128>(),
+                any::<u64>(),
+                any::<u64>(),
+                any::<u64>(),
+                any::<u64>(),
+                prop::collection::vec(any::<u8>(), 0..=32),
+            ),
+            (h256(), any::<[u8; 8]>(), 0..=6usize, any::<u64>(), h256()),
+            (any::<bool>(), any::<bool>()),
+        )
+            .prop_map(
+                |(
+                    (
+                        parent_hash,
+                        uncles_hash,
+                        author,
+                        state_root,
+                        transactions_root,
+                        receipts_root,
+                    ),
+                    (difficulty, number, gas_limit, timestamp, base_fee, extra_data),
+                    (mix_hash, nonce, forks, blob_gas, root),
+                    (root_as_parent, root_in_extra),
+                )| {
+                    let mut extra_data = extra_data;
+                    if root_in_extra {
+                        extra_data = state_root.as_bytes().to_vec();
+                    }
+                    let present = |fork: usize| forks > fork;
+                    BlockHeader {
+                        parent_hash: if root_as_parent {
+                            state_root
+                        } else {
+                            parent_hash
+                        },
+                        uncles_hash,
+                        author: H160(author),
+                        state_root,
+                        transactions_root,
+                        receipts_root,
+                        logs_bloom: H2048::repeat_byte(timestamp as u8),
+                        difficulty: difficulty.into(),
+                        number: number.into(),
+                        gas_limit: gas_limit.into(),
+                        gas_used: (gas_limit / 2).into(),
+                        timestamp: timestamp.into(),
+                        extra_data: Bytes(extra_data),
+                        mix_hash,
+                        nonce: H64(nonce),
+                        base_fee_per_gas: present(0).then(|| base_fee.into()),
+                        withdrawals_root: present(1).then_some(root),
+                        blob_gas_used: present(2).then(|| blob_gas.into()),
+                        excess_blob_gas: present(3).then(|| (blob_gas / 3).into()),
+                        parent_beacon_block_root: present(4).then_some(root),
+                        requests_hash: present(5).then_some(root),
+                        ..Default::default()
+                    }
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn split_rebuilds_the_header_around_its_state_root(header in header()) {
+            let rlp = header.encode();
+            let (head, root, tail) =
+                split_rlp_by_state_root(&rlp, header.state_root.as_bytes(), Layout::Ethereum)
+                    .unwrap();
+            prop_assert_eq!(root.as_slice(), header.state_root.as_bytes());
+            prop_assert_eq!([head.as_slice(), &root, &tail].concat(), rlp.clone());
+            // The root is the state root field, a 32-byte string.
+            prop_assert_eq!(head.last(), Some(&0xa0));
+            let span = field_spans(&rlp, Layout::Ethereum).unwrap();
+            prop_assert_eq!(span[3].offset, head.len());
+        }
+
+        #[test]
+        fn decode_reverses_encode(header in header()) {
+            let rlp = header.encode();
+            prop_assert_eq!(encoded_len(&rlp).unwrap(), rlp.len());
+            prop_assert_eq!(BlockHeader::decode(&rlp, Layout::Ethereum).unwrap(), header);
+        }
+
+        #[test]
+        fn decode_sealed_reverses_encode_of_aura_headers(
+            header in header(),
+            step in any::<u64>(),
+            signature in any::<[u8; 32]>(),
+        ) {
+            let mut header = header;
+            header.mix_hash = H256::zero();
+            header.nonce = H64::zero();
+            let signature = [signature, signature].concat();
+            header.seal_fields = Some(vec![
+                Bytes(rlp::encode(&step).to_vec()),
+                Bytes(rlp::encode(&[&signature[..], &[27]].concat()).to_vec()),
+            ]);
+            let rlp = header.encode();
+            let decoded =
+                BlockHeader::decode_sealed(&rlp, Layout::Ethereum, Some(AURA_SEAL_FIELDS)).unwrap();
+            prop_assert_eq!(decoded.hash(), header.hash());
+            prop_assert_eq!(decoded, header);
+            let spans = field_spans_sealed(&rlp, Layout::Ethereum, Some(AURA_SEAL_FIELDS)).unwrap();
+            let names: Vec<_> = spans.iter().map(|span| span.name).collect();
+            prop_assert_eq!(&names[13..15], &["seal", "seal"]);
+            prop_assert!(names[15..].iter().zip(&FIELD_NAMES[15..]).all(|(a, b)| a == b));
+        }
+    }
+
+    /// Every block recorded under `fixtures/chains` as `<chain id>_<block
+    /// number>.json`, an `eth_getBlockByNumber` response, re-encodes in its
+    /// chain's layout to the hash the node reported, and fits the chain's
+    /// padded header length.
+    #[test]
+    fn recorded_blocks_hash_to_their_reported_hash() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/chains");
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+            let (chain_id, number) = name.split_once('_').unwrap();
+            let spec = ChainSpec::known(chain_id.parse().unwrap());
+            let block: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            let recorded: H256 = serde_json::from_value(block["hash"].clone()).unwrap();
+            let (header, _) = from_rpc(block, number.parse().unwrap(), &spec)
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(header.hash(), recorded, "{}", name);
+            if let Some(max) = spec.max_header_bytes {
+                assert!(header.encode().len() <= max, "{}", name);
+            }
+        }
+    }
+}
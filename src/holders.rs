@@ -0,0 +1,100 @@
+//! The holders `dump-holders` proves: every address an ERC-20 token's
+//! `Transfer` logs name as sender or recipient over a block range, read
+//! with `eth_getLogs` in chunks of blocks. A chunk the provider refuses for
+//! the number of its logs is halved until it is served, and later chunks
+//! keep the smaller span.
+
+use std::collections::BTreeSet;
+
+use web3::types::{BlockNumber, FilterBuilder, H160, H256};
+use web3::Web3;
+
+use crate::chunked;
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::options::BlockRange;
+use crate::rpc::Client;
+
+/// `keccak256("Transfer(address,address,uint256)")`, the topic of the
+/// event. ERC-721 tokens emit it too, with the token id as a third indexed
+/// topic, so only logs with exactly two indexed addresses are read.
+pub fn transfer_topic() -> H256 {
+    H256(keccak256(b"Transfer(address,address,uint256)"))
+}
+
+/// Whether `error` is the provider refusing a log query for how many logs
+/// or blocks it spans.
+fn is_range_cap(error: &Error) -> bool {
+    if chunked::is_size_cap(error) {
+        return true;
+    }
+    let message = error.to_string().to_lowercase();
+    ["block range", "more than", "query returned", "too many"]
+        .iter()
+        .any(|hint| message.contains(hint))
+}
+
+/// The address in the low 20 bytes of an indexed `address` topic.
+fn topic_address(topic: &H256) -> H160 {
+    H160::from_slice(&topic.as_bytes()[12..])
+}
+
+/// Every address `token`'s `Transfer` logs from `range.lo` to `range.hi`
+/// name, without the zero address mints and burns use, sorted. Queries
+/// span `chunk` blocks at most.
+pub async fn scan(
+    web3: &Web3<Client>,
+    token: H160,
+    range: BlockRange,
+    chunk: u64,
+) -> Result<Vec<H160>> {
+    let topic = transfer_topic();
+    let mut holders = BTreeSet::new();
+    let (mut from, mut span) = (range.lo, chunk.max(1));
+    let mut logs_read = 0;
+    while from <= range.hi {
+        let to = range.hi.min(from.saturating_add(span - 1));
+        let filter = FilterBuilder::default()
+            .address(vec![token])
+            .topics(Some(vec![topic]), None, None, None)
+            .from_block(BlockNumber::Number(from.into()))
+            .to_block(BlockNumber::Number(to.into()))
+            .build();
+        let logs =
+            match web3.eth().logs(filter).await.map_err(Error::from) {
+                Ok(logs) => logs,
+                Err(e) if is_range_cap(&e) && to > from => {
+                    span = (to - from).div_ceil(2);
+                    log::info!(
+                    "Note: the provider refused the logs of blocks {} to {}; reading {} block(s) \
+                     at a time",
+                    from, to, span
+                );
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+        for log in &logs {
+            if log.removed == Some(true) || log.topics.len() != 3 {
+                continue;
+            }
+            for topic in &log.topics[1..] {
+                let holder = topic_address(topic);
+                if !holder.is_zero() {
+                    holders.insert(holder);
+                }
+            }
+        }
+        logs_read += logs.len();
+        from = to + 1;
+    }
+    log::info!(
+        "{} Transfer log(s) of {:?} from block {} to {} name {} holder(s)",
+        logs_read,
+        token,
+        range.lo,
+        range.hi,
+        holders.len()
+    );
+    Ok(holders.into_iter().collect())
+}
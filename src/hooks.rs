@@ -0,0 +1,52 @@
+//! `--post-hook` and `--post-batch-hook`: a shell command run once a
+//! witness, or a whole `dump-slots` batch, has been written, such as
+//! `nargo prove --dir {out_dir}`. `{name}` placeholders are filled in with
+//! the run's values, quoted for the shell; other braces, like `${HOME}`,
+//! are left to the shell.
+
+use std::process::{Command, Stdio};
+
+use crate::error::{Error, Result};
+
+/// Whether `value` can be passed to the shell unquoted.
+fn plain(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_./:=@%+,".contains(&b))
+}
+
+fn quote(value: &str) -> String {
+    match plain(value) {
+        true => value.to_string(),
+        false => format!("'{}'", value.replace('\'', r"'\''")),
+    }
+}
+
+/// `template` with each `{name}` of `values` replaced by its quoted value.
+pub fn expand(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |command, (name, value)| {
+            command.replace(&format!("{{{}}}", name), &quote(value))
+        })
+}
+
+/// Runs `template`, expanded with `values`, with `sh -c`. Its output goes
+/// to stderr, so it cannot mix with parameters written to stdout, and a
+/// non-zero exit status fails the run.
+pub fn run(template: &str, values: &[(&str, String)]) -> Result<()> {
+    let command = expand(template, values);
+    log::info!("Running {}", command);
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::null())
+        .stdout(std::io::stderr())
+        .status()
+        .map_err(|e| Error::Hook(format!("cannot run `{}`: {}", command, e)))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(Error::Hook(format!("`{}` failed with {}", command, status))),
+    }
+}
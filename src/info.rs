@@ -0,0 +1,179 @@
+//! The `info` command: what this build of the generator supports, for
+//! orchestration systems to check compatibility before dispatching jobs.
+//! `--json` prints it as one object whose existing keys keep their meaning
+//! across releases; later releases only add keys.
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::chain_spec::{ChainSpec, KNOWN_CHAINS};
+use crate::constants::CONSTANTS;
+use crate::forks::Fork;
+use crate::migrate::WITNESS_FORMAT_VERSION;
+
+/// Cargo features this build was compiled with, of those that change what
+/// it can do.
+fn features() -> Vec<&'static str> {
+    [
+        (cfg!(feature = "std"), "std"),
+        (cfg!(feature = "rpc"), "rpc"),
+        (cfg!(feature = "cli"), "cli"),
+        (cfg!(feature = "tui"), "tui"),
+        (cfg!(feature = "formats-json"), "formats-json"),
+        (cfg!(feature = "formats-toml"), "formats-toml"),
+        (cfg!(feature = "compression"), "compression"),
+        (cfg!(feature = "zstd"), "zstd"),
+        (cfg!(feature = "encryption"), "encryption"),
+        (cfg!(feature = "signing"), "signing"),
+        (cfg!(feature = "bench"), "bench"),
+        (cfg!(feature = "blocking"), "blocking"),
+        (cfg!(feature = "conformance"), "conformance"),
+        (cfg!(feature = "differential"), "differential"),
+        (cfg!(feature = "linea"), "linea"),
+        (cfg!(feature = "object-store"), "object-store"),
+        (cfg!(feature = "reference-circuit"), "reference-circuit"),
+        (cfg!(feature = "scroll"), "scroll"),
+        (cfg!(feature = "zksync"), "zksync"),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, feature)| feature)
+    .collect()
+}
+
+/// A chain with built-in expectations.
+#[derive(Debug, Clone, Serialize)]
+pub struct Chain {
+    pub chain_id: u64,
+    pub name: &'static str,
+    /// The header field layout.
+    pub layout: &'static str,
+    /// Length headers are padded to.
+    pub header_bytes: usize,
+    /// The forks whose activation is scheduled, in order; empty for a chain
+    /// whose headers are not checked against a schedule.
+    pub forks: Vec<&'static str>,
+}
+
+/// A size constant circuits have to match.
+#[derive(Debug, Clone, Serialize)]
+pub struct Constant {
+    /// The names circuits declare it under, the first the generator's own.
+    pub names: &'static [&'static str],
+    pub value: u128,
+}
+
+/// What this build supports.
+#[derive(Debug, Clone, Serialize)]
+pub struct Info {
+    pub version: &'static str,
+    /// The commit built from, when it was built from a git checkout.
+    pub git_commit: Option<&'static str>,
+    pub features: Vec<&'static str>,
+    pub witness_format_version: u64,
+    pub chains: Vec<Chain>,
+    /// Every fork the header checks know, in order.
+    pub forks: Vec<&'static str>,
+    /// The `--rollup` backends compiled in.
+    pub rollups: Vec<&'static str>,
+    pub constants: Vec<Constant>,
+}
+
+impl Info {
+    /// Describes this build.
+    pub fn current() -> Self {
+        let chains = KNOWN_CHAINS
+            .iter()
+            .map(|&(chain_id, name)| {
+                let spec = ChainSpec::known(chain_id);
+                let forks = match &spec.schedule {
+                    Some(schedule) => Fork::ALL
+                        .iter()
+                        .filter(|fork| match fork {
+                            Fork::Berlin => false,
+                            Fork::London => schedule.london_block.is_some(),
+                            Fork::Shanghai => schedule.shanghai_time.is_some(),
+                            Fork::Cancun => schedule.cancun_time.is_some(),
+                            Fork::Prague => schedule.prague_time.is_some(),
+                        })
+                        .map(Fork::name)
+                        .collect(),
+                    None => Vec::new(),
+                };
+                Chain {
+                    chain_id,
+                    name,
+                    layout: spec.layout.name(),
+                    header_bytes: spec.header_rlp_bytes(),
+                    forks,
+                }
+            })
+            .collect();
+        let rollups = [
+            (cfg!(feature = "zksync"), "zksync"),
+            (cfg!(feature = "scroll"), "scroll"),
+            (cfg!(feature = "linea"), "linea"),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, rollup)| rollup)
+        .collect();
+        Info {
+            version: env!("CARGO_PKG_VERSION"),
+            git_commit: option_env!("NOIR_MIP_GIT_COMMIT"),
+            features: features(),
+            witness_format_version: WITNESS_FORMAT_VERSION,
+            chains,
+            forks: Fork::ALL.iter().map(Fork::name).collect(),
+            rollups,
+            constants: CONSTANTS
+                .iter()
+                .map(|constant| Constant {
+                    names: constant.names,
+                    value: constant.value,
+                })
+                .collect(),
+        }
+    }
+
+    /// The same, for a terminal.
+    pub fn render(&self) -> String {
+        let none = |list: &[&str]| match list.is_empty() {
+            true => "none".to_string(),
+            false => list.join(", "),
+        };
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "noir-mip {} ({})",
+            self.version,
+            self.git_commit.unwrap_or("unknown commit")
+        );
+        let _ = writeln!(out, "Features: {}", none(&self.features));
+        let _ = writeln!(
+            out,
+            "Witness format version: {}",
+            self.witness_format_version
+        );
+        let _ = writeln!(out, "Forks: {}", none(&self.forks));
+        let _ = writeln!(out, "Rollups: {}", none(&self.rollups));
+        let _ = writeln!(out, "Chains:");
+        for chain in &self.chains {
+            let _ = writeln!(
+                out,
+                "  {:<10} {:<12} {} layout, {}-byte headers, forks: {}",
+                chain.chain_id,
+                chain.name,
+                chain.layout,
+                chain.header_bytes,
+                none(&chain.forks)
+            );
+        }
+        let _ = writeln!(out, "Constants:");
+        for constant in &self.constants {
+            let _ = writeln!(out, "  {:<32} {}", constant.names[0], constant.value);
+        }
+        out
+    }
+}
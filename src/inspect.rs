@@ -0,0 +1,302 @@
+//! The `inspect-proof` command: reads the public inputs of a published bb
+//! proof back into the parameters they were generated as, using the layout
+//! the circuit declares them in, so an auditor can see which block,
+//! account, slot and value a proof is about and regenerate them from the
+//! chain to check that they are real.
+//!
+//! bb writes each public input as a 32-byte big-endian field element, in
+//! the order `main` declares them, which is the order of the prover
+//! parameters. A `[u8; N]` input takes one element per byte and a limb
+//! array one per limb.
+
+use std::fmt::Write;
+
+use rlp::Rlp;
+use web3::types::{BlockId, H160, H256, U256};
+
+use crate::cli::InspectProofArgs;
+use crate::encoding::{self, NumericEncoding, ValueForm};
+use crate::error::{Error, Result};
+use crate::layout::{self, FieldLayout};
+use crate::options::{GenOptions, RootMode};
+use crate::params::{Params, Value};
+use crate::{generate_params, rpc};
+
+/// Bytes of a field element as bb writes it.
+const ELEMENT_BYTES: usize = 32;
+
+/// The field elements of a public inputs file: concatenated 32-byte
+/// elements, or a JSON array of hex strings.
+pub fn read_elements(data: &[u8]) -> Result<Vec<U256>> {
+    if data.trim_ascii_start().starts_with(b"[") {
+        let elements: Vec<String> = serde_json::from_slice(data)
+            .map_err(|e| Error::Decode(format!("invalid public inputs JSON: {}", e)))?;
+        return elements
+            .iter()
+            .map(|element| {
+                U256::from_str_radix(element.trim_start_matches("0x"), 16)
+                    .map_err(|e| Error::Decode(format!("invalid field element {}: {}", element, e)))
+            })
+            .collect();
+    }
+    if !data.len().is_multiple_of(ELEMENT_BYTES) {
+        return Err(Error::Decode(format!(
+            "{} bytes of public inputs are not whole {}-byte field elements",
+            data.len(),
+            ELEMENT_BYTES
+        )));
+    }
+    Ok(data
+        .chunks(ELEMENT_BYTES)
+        .map(U256::from_big_endian)
+        .collect())
+}
+
+/// The public inputs the circuit `args` describe declares, in order. Fields
+/// emitted only in some cases are left out, as the circuit has to declare
+/// its inputs whatever the proof shows.
+pub fn public_fields(args: &InspectProofArgs) -> Result<Vec<FieldLayout>> {
+    Ok(layout::contract(&args.layout_args())?
+        .fields
+        .into_iter()
+        .filter(|field| field.public && field.only_when.is_none())
+        .collect())
+}
+
+/// Element type and count of a field of type `noir_type`.
+fn array_of(noir_type: &str) -> (&str, Option<usize>) {
+    let Some((element, len)) = noir_type
+        .strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+        .and_then(|inner| inner.split_once("; "))
+    else {
+        return (noir_type, None);
+    };
+    (element, len.parse().ok())
+}
+
+/// `element` as an integer of `bytes` bytes, big-endian.
+fn narrow(element: U256, bytes: usize, name: &str) -> Result<Vec<u8>> {
+    if element.bits() > bytes * 8 {
+        return Err(Error::Decode(format!(
+            "{} holds {:#x}, which does not fit in {} bits; check the layout flags",
+            name,
+            element,
+            bytes * 8
+        )));
+    }
+    let mut be_bytes = [0u8; ELEMENT_BYTES];
+    element.to_big_endian(&mut be_bytes);
+    Ok(be_bytes[ELEMENT_BYTES - bytes..].to_vec())
+}
+
+/// Decodes `elements` into the parameters of `fields`, as the generator
+/// emits them. The `account_value` length is `account_value_bytes`, or
+/// whatever the other fields leave.
+pub fn decode(
+    elements: &[U256],
+    fields: &[FieldLayout],
+    encoding: NumericEncoding,
+    value_form: ValueForm,
+    account_value_bytes: Option<usize>,
+) -> Result<Params> {
+    let fixed: usize = fields
+        .iter()
+        .filter(|field| field.name != "account_value")
+        .map(|field| field.elements)
+        .sum();
+    let account_value_bytes = match account_value_bytes {
+        Some(len) => len,
+        None => elements.len().checked_sub(fixed).ok_or_else(|| {
+            Error::Decode(format!(
+                "{} public inputs are fewer than the {} the layout declares besides \
+                 account_value",
+                elements.len(),
+                fixed
+            ))
+        })?,
+    };
+    if elements.len() < fixed + account_value_bytes {
+        return Err(Error::Decode(format!(
+            "{} public inputs are fewer than the {} the layout declares",
+            elements.len(),
+            fixed + account_value_bytes
+        )));
+    }
+
+    let mut params = Params::default();
+    let mut rest = elements;
+    for field in fields {
+        let (element, len) = array_of(&field.noir_type);
+        let count = match field.name {
+            "account_value" => account_value_bytes,
+            _ => len.unwrap_or(1),
+        };
+        let (taken, left) = rest.split_at(count);
+        rest = left;
+        let value = match (element, len) {
+            ("u8", _) => Value::Bytes(
+                taken
+                    .iter()
+                    .map(|&element| narrow(element, 1, field.name).map(|byte| byte[0]))
+                    .collect::<Result<_>>()?,
+            ),
+            ("u64" | "u128", Some(_)) => {
+                let bytes = if element == "u64" { 8 } else { 16 };
+                Value::Array(
+                    taken
+                        .iter()
+                        .map(|&element| narrow(element, bytes, field.name).map(Value::Hex))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            _ => Value::Int(
+                narrow(taken[0], 8, field.name)
+                    .map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap_or_default()))?,
+            ),
+        };
+        params.push(field.name, value);
+    }
+    if !rest.is_empty() {
+        log::warn!(
+            "{} public inputs follow the ones the layout declares; they are ignored",
+            rest.len()
+        );
+    }
+    params.meta.push("numeric_encoding", encoding.name());
+    if value_form != ValueForm::Padded {
+        params.meta.push("value_form", value_form.name());
+    }
+    Ok(params)
+}
+
+/// What the decoded `params` say: the anchor, the account and its state,
+/// and the slot and its value.
+pub fn describe(params: &Params, encoding: NumericEncoding) -> String {
+    let mut out = String::new();
+    let hex_of = |name: &str| params.fields.get_bytes(name).map(hex::encode);
+    if let Some(hash) = hex_of("block_hash") {
+        let _ = writeln!(out, "block hash    0x{}", hash);
+    }
+    if let Some(root) = hex_of("state_root") {
+        let _ = writeln!(out, "state root    0x{}", root);
+    }
+    if let Some(account) = hex_of("account_key") {
+        let _ = writeln!(out, "account       0x{}", account);
+    }
+    if let Some(value) = params.fields.get_bytes("account_value") {
+        let account = Rlp::new(value);
+        let nonce: Option<U256> = account.val_at(0).ok();
+        let balance: Option<U256> = account.val_at(1).ok();
+        match nonce.zip(balance) {
+            Some((nonce, balance)) => {
+                let _ = writeln!(out, "nonce         {}", nonce);
+                let _ = writeln!(out, "balance       {} wei", balance);
+            }
+            None => {
+                let _ = writeln!(
+                    out,
+                    "account value 0x{} (not an account RLP)",
+                    hex::encode(value)
+                );
+            }
+        }
+    }
+    if let Some(slot) = params
+        .fields
+        .get("storage_key")
+        .and_then(|key| encoding.decode(key))
+    {
+        let _ = writeln!(out, "slot          {:#066x}", slot);
+    }
+    match encoding::storage_value(params) {
+        Some(value) => {
+            let _ = writeln!(out, "value         {:#x} ({})", value, value);
+        }
+        None => {
+            if let Some(value) = hex_of("storage_value") {
+                let _ = writeln!(
+                    out,
+                    "value         0x{} (padded; its length is private)",
+                    value
+                );
+            }
+        }
+    }
+    out
+}
+
+/// Regenerates the public inputs `params` claim from `rpc_url` and checks
+/// that they agree, returning the block they were found at.
+pub async fn cross_check(args: &InspectProofArgs, rpc_url: &str, params: &Params) -> Result<u64> {
+    let missing = |name: &str| Error::Decode(format!("the public inputs have no {}", name));
+    let block = match (args.root_mode, params.fields.get_bytes("block_hash")) {
+        (RootMode::BlockHash, Some(hash)) => {
+            let hash = H256::from_slice(hash);
+            let web3 = web3::Web3::new(rpc::Client::new(rpc_url)?);
+            let block = web3.eth().block(BlockId::Hash(hash)).await?;
+            let number = block.and_then(|block| block.number).ok_or_else(|| {
+                Error::Verification(format!(
+                    "the provider has no block with hash {:#x}; the proof is not about this chain, \
+                     or the block was reorged out",
+                    hash
+                ))
+            })?;
+            if args.block.is_some_and(|block| block != number.as_u64()) {
+                log::warn!(
+                    "block {:#x} is block {}, not the --block given",
+                    hash,
+                    number
+                );
+            }
+            number.as_u64()
+        }
+        (RootMode::BlockHash, None) => return Err(missing("block_hash")),
+        (RootMode::StateRoot, _) => args.block.ok_or_else(|| {
+            Error::Config(
+                "a state root does not name its block; pass the --block it was taken at"
+                    .to_string(),
+            )
+        })?,
+    };
+    let account = H160::from_slice(
+        params
+            .fields
+            .get_bytes("account_key")
+            .ok_or_else(|| missing("account_key"))?,
+    );
+    let slot = match args.account_only {
+        true => None,
+        false => {
+            let key = params
+                .fields
+                .get("storage_key")
+                .and_then(|key| args.numeric_encoding.decode(key))
+                .ok_or_else(|| missing("storage_key"))?;
+            let mut slot = H256::zero();
+            key.to_big_endian(slot.as_bytes_mut());
+            Some(slot)
+        }
+    };
+    let gen = GenOptions {
+        root_mode: args.root_mode,
+        numeric_encoding: args.numeric_encoding,
+        value_form: args.value_form,
+        ..GenOptions::new(rpc_url, block, account, slot)
+    };
+    let chain = generate_params(&gen, false).await?;
+    let differing: Vec<&str> = params
+        .fields
+        .iter()
+        .filter(|(name, value)| chain.fields.get(name) != Some(value))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    match differing.is_empty() {
+        true => Ok(block),
+        false => Err(Error::Verification(format!(
+            "the chain at block {} disagrees with the proof's {}",
+            block,
+            differing.join(", ")
+        ))),
+    }
+}
@@ -0,0 +1,112 @@
+//! Input maps for frameworks other than Noir, built from the same
+//! parameters: `--format circom-json`, the `input.json` a Circom circuit's
+//! witness calculator reads, and `--format kv-json`, a flat map of the
+//! fields for Halo2 and other tooling to load.
+//!
+//! Circom inputs are signals, so every value is a field element written
+//! as a decimal string (snarkjs reads strings, as JSON numbers lose
+//! precision past 2^53), byte arrays are arrays of them, and field names
+//! are camelCased the way Circom templates name their signals. The
+//! witness calculator rejects inputs the circuit does not declare, so the
+//! metadata is left out.
+
+use ethereum_types::U256;
+use serde_json::{json, Map, Value as Json};
+
+use crate::error::{Error, Result};
+use crate::params::{Params, Value};
+
+/// `name` in camelCase: `block_header_rlp` reads `blockHeaderRlp` and
+/// `block_0_account_key` reads `block0AccountKey`.
+pub fn camel_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, word) in name.split('_').filter(|word| !word.is_empty()).enumerate() {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            match i {
+                0 => out.push(first),
+                _ => out.push(first.to_ascii_uppercase()),
+            }
+            out.extend(chars);
+        }
+    }
+    out
+}
+
+/// `value` as Circom signals, or `None` for a string, which no signal can
+/// hold.
+fn signals(value: &Value) -> Option<Json> {
+    Some(match value {
+        Value::Bytes(bytes) => bytes
+            .iter()
+            .map(|byte| byte.to_string().into())
+            .collect::<Vec<Json>>()
+            .into(),
+        Value::Int(value) => value.to_string().into(),
+        Value::Hex(bytes) if bytes.len() <= 32 => U256::from_big_endian(bytes).to_string().into(),
+        Value::Hex(_) | Value::Str(_) => return None,
+        Value::Array(values) => values
+            .iter()
+            .map(signals)
+            .collect::<Option<Vec<_>>>()?
+            .into(),
+    })
+}
+
+/// The fields of `params` as a Circom input map.
+pub fn to_circom_json(params: &Params) -> Result<String> {
+    let mut inputs = Map::new();
+    for (name, value) in params.fields.iter() {
+        let signal = camel_case(name);
+        let value = signals(value).ok_or_else(|| {
+            Error::Unsupported(format!(
+                "{} holds {}, which no Circom signal can take",
+                name,
+                value.shape()
+            ))
+        })?;
+        if inputs.insert(signal.clone(), value).is_some() {
+            return Err(Error::Unsupported(format!(
+                "two fields are both named {} in camelCase; rename one with --rename",
+                signal
+            )));
+        }
+    }
+    render(&Json::Object(inputs))
+}
+
+/// `value` as a plain JSON value: byte arrays and field elements as
+/// `0x`-prefixed hex, integers as numbers.
+fn plain(value: &Value) -> Json {
+    match value {
+        Value::Bytes(bytes) | Value::Hex(bytes) => format!("0x{}", hex::encode(bytes)).into(),
+        Value::Int(value) => json!(value),
+        Value::Str(text) => text.clone().into(),
+        Value::Array(values) => values.iter().map(plain).collect::<Vec<_>>().into(),
+    }
+}
+
+/// The fields of `params` as one flat map under their own names, sorted
+/// by name, with the metadata under `meta`.
+pub fn to_kv_json(params: &Params) -> Result<String> {
+    let mut map: Map<String, Json> = params
+        .fields
+        .iter()
+        .map(|(name, value)| (name.clone(), plain(value)))
+        .collect();
+    if !params.meta.is_empty() {
+        let meta = params
+            .meta
+            .iter()
+            .map(|(name, value)| (name.clone(), plain(value)))
+            .collect();
+        map.insert("meta".to_string(), Json::Object(meta));
+    }
+    render(&Json::Object(map))
+}
+
+fn render(json: &Json) -> Result<String> {
+    serde_json::to_string_pretty(json)
+        .map(|json| json + "\n")
+        .map_err(|e| Error::Decode(e.to_string()))
+}
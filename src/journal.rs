@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use ethereum_types::{H160, H256};
+
+use crate::error::{Error, Result};
+
+/// Completed entries of a batch run, one slot per line after a header
+/// naming the block and account, appended as each entry finishes.
+pub struct Journal {
+    file: File,
+    done: HashSet<H256>,
+}
+
+fn header(block: u64, account: H160) -> String {
+    format!("# block {} account {:?}", block, account)
+}
+
+impl Journal {
+    /// Opens the journal at `path` for a run over `account` at `block`. With
+    /// `resume` the entries of an earlier run of the same block and account
+    /// are kept; otherwise the journal starts empty.
+    pub fn open(path: &Path, block: u64, account: H160, resume: bool) -> Result<Self> {
+        let header = header(block, account);
+        let mut done = HashSet::new();
+        if resume && path.exists() {
+            let text = fs::read_to_string(path)?;
+            let mut lines = text.lines();
+            if lines.next() != Some(header.as_str()) {
+                return Err(Error::Decode(format!(
+                    "{} was written by a run over another block or account",
+                    path.display()
+                )));
+            }
+            for line in lines {
+                // A line cut short by a crash is ignored and its entry redone.
+                if let Ok(slot) = line.parse() {
+                    done.insert(slot);
+                }
+            }
+            let file = OpenOptions::new().append(true).open(path)?;
+            return Ok(Journal { file, done });
+        }
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", header)?;
+        Ok(Journal { file, done })
+    }
+
+    pub fn is_done(&self, slot: &H256) -> bool {
+        self.done.contains(slot)
+    }
+
+    /// Marks `slot` as completed.
+    pub fn record(&mut self, slot: H256) -> Result<()> {
+        writeln!(self.file, "{:?}", slot)?;
+        self.done.insert(slot);
+        Ok(())
+    }
+
+    /// Makes the recorded entries durable, before the process stops.
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,493 @@
+//! The byte layout contract of the parameters, as the `layout` command
+//! prints it: which fields are emitted in which order, their types, and how
+//! each is encoded and padded. [`crate::witness`] fixes the order; the
+//! lengths come from the same constants the generator pads to.
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::chain_spec::ChainSpec;
+use crate::cli::LayoutArgs;
+use crate::constants::CONSTANTS;
+use crate::encoding::{HeaderPadding, NumericEncoding, ValueForm, STORAGE_VALUE_RLP_BYTES};
+use crate::error::{Error, Result};
+use crate::keccak::KECCAK_RATE_BYTES;
+use crate::options::RootMode;
+use crate::proof_order::ProofOrder;
+use crate::witness::{PROVER_LAYOUT, VERIFIER_LAYOUT};
+use crate::{
+    ACCOUNT_PROOF_MAX_DEPTH, BLOCK_HEADER_RLP_HEAD_BYTES, PROOF_BYTES_LEN, STORAGE_PROOF_MAX_DEPTH,
+};
+
+/// Bytes of the account RLP when its nonce and balance are both below 128.
+const ACCOUNT_VALUE_MIN_BYTES: usize = 70;
+
+/// One emitted field.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldLayout {
+    pub name: &'static str,
+    /// The type the circuit declares the parameter with.
+    #[serde(rename = "type")]
+    pub noir_type: String,
+    /// Whether it is a public input, one of the verifier parameters.
+    pub public: bool,
+    /// Length in bytes of a byte array, padding included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<usize>,
+    /// Field elements the parameter takes as a circuit input.
+    pub elements: usize,
+    /// What the value holds, its byte order and its padding.
+    pub layout: String,
+    /// When the field is emitted, if not always.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_when: Option<&'static str>,
+}
+
+/// The circuit inputs of the configuration under one numeric encoding.
+#[derive(Debug, Clone, Serialize)]
+pub struct InputCount {
+    pub numeric_encoding: &'static str,
+    pub elements: usize,
+    pub public_elements: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConstantValue {
+    pub name: &'static str,
+    pub value: u128,
+}
+
+/// The layout of the prover or verifier parameters of one configuration.
+#[derive(Debug, Clone, Serialize)]
+pub struct Contract {
+    /// `prover` or `verifier`.
+    pub parameters: &'static str,
+    pub byte_encoding: &'static str,
+    pub numeric_encoding: &'static str,
+    /// The emitted fields, in the order they are written.
+    pub fields: Vec<FieldLayout>,
+    /// Field elements of the circuit inputs, and of the public ones, the
+    /// fields only emitted in some cases included.
+    pub elements: usize,
+    pub public_elements: usize,
+    /// The same counts under every numeric encoding the value form takes.
+    pub by_numeric_encoding: Vec<InputCount>,
+    /// The circuit constants the padded lengths follow from.
+    pub constants: Vec<ConstantValue>,
+}
+
+fn bytes_type(len: usize) -> String {
+    format!("[u8; {}]", len)
+}
+
+/// Type and layout of the storage key and value under `encoding`.
+fn numeric(encoding: NumericEncoding, what: &str) -> (String, Option<usize>, String) {
+    match encoding {
+        NumericEncoding::BeBytes => (
+            bytes_type(32),
+            Some(32),
+            format!("{}, 32 bytes big-endian", what),
+        ),
+        NumericEncoding::LeBytes => (
+            bytes_type(32),
+            Some(32),
+            format!("{}, 32 bytes little-endian", what),
+        ),
+        NumericEncoding::Limbs64 => (
+            "[u64; 4]".to_string(),
+            None,
+            format!("{}, four 64-bit limbs, least significant first", what),
+        ),
+        NumericEncoding::Limbs128 => (
+            "[u128; 2]".to_string(),
+            None,
+            format!("{}, two 128-bit limbs, least significant first", what),
+        ),
+    }
+}
+
+fn proof_layout(what: &str, max_depth: usize, order: ProofOrder) -> String {
+    let from = match order {
+        ProofOrder::RootFirst => "root",
+        ProofOrder::LeafFirst => "leaf",
+    };
+    format!(
+        "the {} proof nodes from the {}, each zero-padded to {} bytes, then zeros up to {} \
+         nodes",
+        what, from, PROOF_BYTES_LEN, max_depth
+    )
+}
+
+/// Field elements of a parameter of type `noir_type`: one per array
+/// element, or one for a scalar. `account_value` is counted at the 70
+/// bytes it takes while the nonce and balance are below 128, its shortest.
+fn elements(noir_type: &str) -> usize {
+    let array = noir_type
+        .strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+        .and_then(|inner| inner.split_once("; "));
+    match array {
+        Some((_, "N")) => ACCOUNT_VALUE_MIN_BYTES,
+        Some((_, len)) => len.parse().unwrap_or(1),
+        None => 1,
+    }
+}
+
+/// Padded lengths of the header and its parts.
+struct Lengths {
+    header: usize,
+    head: usize,
+    tail: usize,
+}
+
+/// The layout of field `name`, or `None` when `args` leaves it out.
+fn field(name: &'static str, args: &LayoutArgs, lengths: &Lengths) -> Option<FieldLayout> {
+    let header = args.root_mode == RootMode::BlockHash;
+    let (noir_type, bytes, layout, only_when) = match name {
+        "block_hash" if header => (
+            bytes_type(32),
+            Some(32),
+            "keccak256 of the header RLP".to_string(),
+            None,
+        ),
+        "state_root" if !header => (
+            bytes_type(32),
+            Some(32),
+            "the state root the account proof starts from".to_string(),
+            None,
+        ),
+        "account_key" => (
+            bytes_type(20),
+            Some(20),
+            "the account address, unhashed; its keccak256 is the trie path".to_string(),
+            None,
+        ),
+        "account_value" => (
+            "[u8; N]".to_string(),
+            None,
+            "RLP of [nonce, balance, storage_root, code_hash], unpadded: 70 bytes when the \
+                 nonce and balance are both below 128, longer otherwise"
+                .to_string(),
+            None,
+        ),
+        "storage_key" if !args.account_only => {
+            let (noir_type, bytes, layout) = numeric(args.numeric_encoding, "the slot");
+            (noir_type, bytes, layout, None)
+        }
+        "storage_value" if !args.account_only => match args.value_form {
+            ValueForm::Padded => {
+                let (noir_type, bytes, layout) =
+                    numeric(args.numeric_encoding, "the value held in the slot");
+                (noir_type, bytes, layout, None)
+            }
+            ValueForm::Trimmed => (
+                bytes_type(32),
+                Some(32),
+                "the value held in the slot, big-endian without leading zeros, zero-padded on \
+                 the right to 32 bytes; storage_value_len holds its length"
+                    .to_string(),
+                None,
+            ),
+            ValueForm::Rlp => (
+                bytes_type(STORAGE_VALUE_RLP_BYTES),
+                Some(STORAGE_VALUE_RLP_BYTES),
+                format!(
+                    "the RLP of the storage leaf value, zero-padded on the right to {} bytes; \
+                     storage_value_len holds its length",
+                    STORAGE_VALUE_RLP_BYTES
+                ),
+                None,
+            ),
+        },
+        "block_header_rlp" if header && !args.verifier => match args.header_padding {
+            HeaderPadding::Post => (
+                bytes_type(lengths.header),
+                Some(lengths.header),
+                format!(
+                    "the RLP encoded header, zero-padded on the right to {} bytes; \
+                     block_header_rlp_len holds its length",
+                    lengths.header
+                ),
+                None,
+            ),
+            HeaderPadding::Pre => (
+                bytes_type(lengths.header),
+                Some(lengths.header),
+                format!(
+                    "the RLP encoded header, zero-padded on the left to {} bytes; \
+                     block_header_rlp_len holds its length",
+                    lengths.header
+                ),
+                None,
+            ),
+            HeaderPadding::None => (
+                "[u8; N]".to_string(),
+                None,
+                format!(
+                    "the RLP encoded header, unpadded: at most {} bytes, as \
+                     block_header_rlp_len says",
+                    lengths.header
+                ),
+                None,
+            ),
+        },
+        "block_header_rlp_head_len" if header && !args.verifier => (
+            "u32".to_string(),
+            None,
+            "bytes of the header before the state root".to_string(),
+            None,
+        ),
+        "block_header_rlp_tail_len" if header && !args.verifier => (
+            "u32".to_string(),
+            None,
+            "bytes of the header after the state root".to_string(),
+            None,
+        ),
+        "block_header_rlp_head" if header && !args.verifier && args.header_parts => (
+            bytes_type(lengths.head),
+            Some(lengths.head),
+            format!(
+                "the header before the state root, zero-padded to {} bytes",
+                lengths.head
+            ),
+            None,
+        ),
+        "block_header_rlp_tail" if header && !args.verifier && args.header_parts => (
+            bytes_type(lengths.tail),
+            Some(lengths.tail),
+            format!(
+                "the header after the state root, zero-padded to {} bytes",
+                lengths.tail
+            ),
+            None,
+        ),
+        "storage_root" if !args.verifier => (
+            bytes_type(32),
+            Some(32),
+            "the account's storage root, where the storage proof starts".to_string(),
+            None,
+        ),
+        "account_proof" if !args.verifier => {
+            let len = ACCOUNT_PROOF_MAX_DEPTH * PROOF_BYTES_LEN;
+            (
+                bytes_type(len),
+                Some(len),
+                proof_layout("account", ACCOUNT_PROOF_MAX_DEPTH, args.proof_order),
+                None,
+            )
+        }
+        "storage_proof" if !args.verifier && !args.account_only => {
+            let len = STORAGE_PROOF_MAX_DEPTH * PROOF_BYTES_LEN;
+            (
+                bytes_type(len),
+                Some(len),
+                proof_layout("storage", STORAGE_PROOF_MAX_DEPTH, args.proof_order),
+                None,
+            )
+        }
+        "account_proof_depth" if !args.verifier => (
+            "Field".to_string(),
+            None,
+            format!(
+                "nodes in account_proof, at most {}",
+                ACCOUNT_PROOF_MAX_DEPTH
+            ),
+            None,
+        ),
+        "storage_proof_depth" if !args.verifier && !args.account_only => (
+            "Field".to_string(),
+            None,
+            format!(
+                "nodes in storage_proof, at most {}",
+                STORAGE_PROOF_MAX_DEPTH
+            ),
+            None,
+        ),
+        "account_exists" => (
+            "bool".to_string(),
+            None,
+            "0 when the account proof shows the account is absent".to_string(),
+            Some("the account does not exist"),
+        ),
+        "is_zero_value" if !args.account_only => (
+            "bool".to_string(),
+            None,
+            "1 when the storage proof shows the slot is absent, so holds zero".to_string(),
+            Some("the slot holds zero"),
+        ),
+        _ => return None,
+    };
+    Some(FieldLayout {
+        name,
+        elements: elements(&noir_type),
+        noir_type,
+        public: VERIFIER_LAYOUT.contains(&name),
+        bytes,
+        layout,
+        only_when,
+    })
+}
+
+/// The contract of the parameters `args` describes.
+pub fn contract(args: &LayoutArgs) -> Result<Contract> {
+    args.value_form
+        .check(args.numeric_encoding)
+        .map_err(Error::Unsupported)?;
+    let spec = match &args.chain_spec {
+        Some(path) => ChainSpec::load(path)?,
+        None => ChainSpec::default(),
+    };
+    let header_len = spec.header_rlp_bytes();
+    let head_len = args
+        .header_head_bytes
+        .unwrap_or(BLOCK_HEADER_RLP_HEAD_BYTES);
+    let tail_len = args
+        .header_tail_bytes
+        .unwrap_or(header_len - 32 - BLOCK_HEADER_RLP_HEAD_BYTES);
+    let lengths = Lengths {
+        header: header_len,
+        head: head_len,
+        tail: tail_len,
+    };
+    let order = if args.verifier {
+        VERIFIER_LAYOUT
+    } else {
+        PROVER_LAYOUT
+    };
+    let fields_with = |numeric_encoding| {
+        let args = LayoutArgs {
+            numeric_encoding,
+            ..args.clone()
+        };
+        order
+            .iter()
+            .filter_map(|&name| field(name, &args, &lengths))
+            .collect::<Vec<_>>()
+    };
+    let count = |fields: &[FieldLayout], public: bool| {
+        fields
+            .iter()
+            .filter(|field| field.public || !public)
+            .map(|field| field.elements)
+            .sum()
+    };
+    let fields = fields_with(args.numeric_encoding);
+    let by_numeric_encoding = NumericEncoding::value_variants()
+        .iter()
+        .filter(|&&encoding| args.value_form.check(encoding).is_ok())
+        .map(|&encoding| {
+            let fields = fields_with(encoding);
+            InputCount {
+                numeric_encoding: encoding.name(),
+                elements: count(&fields, false),
+                public_elements: count(&fields, true),
+            }
+        })
+        .collect();
+
+    let constants = CONSTANTS
+        .iter()
+        .map(|constant| {
+            let name = constant.names[0];
+            let value = match name {
+                "BLOCK_HEADER_RLP_MAX_BYTES" => header_len as u128,
+                "BLOCK_HEADER_KECCAK_MAX_BLOCKS" => (header_len / KECCAK_RATE_BYTES + 1) as u128,
+                _ => constant.value,
+            };
+            ConstantValue { name, value }
+        })
+        .collect();
+
+    Ok(Contract {
+        parameters: if args.verifier { "verifier" } else { "prover" },
+        byte_encoding: args.byte_encoding.name(),
+        numeric_encoding: args.numeric_encoding.name(),
+        elements: count(&fields, false),
+        public_elements: count(&fields, true),
+        by_numeric_encoding,
+        fields,
+        constants,
+    })
+}
+
+impl Contract {
+    /// The budgets of `args` the inputs exceed, one message each.
+    pub fn over_budget(&self, args: &LayoutArgs) -> Vec<String> {
+        let budgets = [
+            ("circuit inputs", self.elements, args.max_inputs),
+            (
+                "public inputs",
+                self.public_elements,
+                args.max_public_inputs,
+            ),
+        ];
+        budgets
+            .into_iter()
+            .filter_map(|(what, count, budget)| {
+                let budget = budget.filter(|&budget| count > budget)?;
+                Some(format!(
+                    "the {} take {} field elements, over the budget of {}",
+                    what, count, budget
+                ))
+            })
+            .collect()
+    }
+
+    /// The contract as aligned text, one field per line.
+    pub fn to_text(&self) -> String {
+        let arrays = match self.byte_encoding {
+            "hex" => "0x-prefixed hex strings",
+            "base64" => "base64 strings",
+            _ => "arrays of decimal bytes",
+        };
+        let title = match self.parameters {
+            "verifier" => "Verifier",
+            _ => "Prover",
+        };
+        let mut out = format!(
+            "{} parameters, in order; byte arrays are written as {}:\n",
+            title, arrays
+        );
+        let name_width = self.fields.iter().map(|f| f.name.len()).max().unwrap_or(0);
+        let type_width = self
+            .fields
+            .iter()
+            .map(|f| f.noir_type.len() + if f.public { 4 } else { 0 })
+            .max()
+            .unwrap_or(0);
+        for field in &self.fields {
+            let noir_type = match field.public {
+                true => format!("pub {}", field.noir_type),
+                false => field.noir_type.clone(),
+            };
+            out += &format!(
+                "  {:name_width$}  {:type_width$}  {}",
+                field.name, noir_type, field.layout
+            );
+            if let Some(condition) = field.only_when {
+                out += &format!(" (only emitted when {})", condition);
+            }
+            out.push('\n');
+        }
+        out += &format!(
+            "\ncircuit inputs: {} field elements, {} of them public\n",
+            self.elements, self.public_elements
+        );
+        if self.by_numeric_encoding.len() > 1 {
+            out += "by numeric encoding:\n";
+            for count in &self.by_numeric_encoding {
+                out += &format!(
+                    "  {:9}  {} field elements, {} public\n",
+                    count.numeric_encoding, count.elements, count.public_elements
+                );
+            }
+        }
+        out += "\nconstants:\n";
+        for constant in &self.constants {
+            out += &format!("  {} = {}\n", constant.name, constant.value);
+        }
+        out +=
+            "\nThe [meta] table after the fields describes the run and is not a circuit input.\n";
+        out
+    }
+}
@@ -0,0 +1,317 @@
+//! Generates the inputs of Noir circuits proving Ethereum state: block
+//! headers, account proofs and storage proofs, padded to the circuit's
+//! fixed sizes.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "cli")]
+pub mod advise;
+#[cfg(feature = "cli")]
+pub mod analyze;
+#[cfg(feature = "rpc")]
+pub mod blind;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "rpc")]
+pub mod cache;
+#[cfg(feature = "rpc")]
+pub mod chain_spec;
+#[cfg(feature = "rpc")]
+pub mod changes;
+#[cfg(all(feature = "rpc", feature = "signing"))]
+pub mod checkpoints;
+#[cfg(feature = "rpc")]
+pub mod chunked;
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "std")]
+pub mod compression;
+#[cfg(feature = "cli")]
+pub mod config;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+#[cfg(feature = "std")]
+pub mod constants;
+#[cfg(feature = "std")]
+pub mod container;
+pub mod core;
+#[cfg(feature = "rpc")]
+pub mod demo;
+#[cfg(feature = "differential")]
+pub mod differential;
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub mod explain;
+#[cfg(feature = "rpc")]
+pub mod forks;
+#[cfg(feature = "rpc")]
+mod generate;
+#[cfg(feature = "rpc")]
+pub mod header;
+#[cfg(feature = "rpc")]
+pub mod holders;
+#[cfg(feature = "std")]
+pub mod hooks;
+#[cfg(feature = "rpc")]
+pub mod info;
+#[cfg(feature = "cli")]
+pub mod inspect;
+#[cfg(feature = "formats-json")]
+pub mod interop;
+#[cfg(feature = "std")]
+pub mod journal;
+pub use self::core::keccak;
+#[cfg(feature = "cli")]
+pub mod layout;
+#[cfg(feature = "rpc")]
+pub mod light_client;
+#[cfg(feature = "linea")]
+pub mod linea;
+#[cfg(feature = "std")]
+pub mod migrate;
+#[cfg(feature = "std")]
+pub mod monomorphize;
+#[cfg(feature = "std")]
+pub mod multiproof;
+#[cfg(feature = "std")]
+pub mod namespace;
+#[cfg(feature = "std")]
+pub mod naming;
+#[cfg(feature = "object-store")]
+pub mod object_store;
+#[cfg(feature = "rpc")]
+pub mod options;
+#[cfg(feature = "std")]
+pub mod params;
+#[cfg(feature = "rpc")]
+pub mod partial_trie;
+#[cfg(feature = "rpc")]
+pub mod polygon;
+#[cfg(feature = "rpc")]
+pub mod probe;
+#[cfg(feature = "rpc")]
+pub mod progress;
+#[cfg(feature = "std")]
+pub mod proof_order;
+#[cfg(feature = "rpc")]
+pub mod pubkey;
+#[cfg(feature = "rpc")]
+pub mod receipts;
+#[cfg(feature = "std")]
+pub mod redact;
+#[cfg(feature = "cli")]
+pub mod refresh;
+#[cfg(feature = "rpc")]
+pub mod reorg;
+#[cfg(feature = "cli")]
+pub mod repl;
+#[cfg(feature = "rpc")]
+pub mod report;
+#[cfg(feature = "cli")]
+pub mod request;
+#[cfg(feature = "rpc")]
+pub mod rollup;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(feature = "scroll")]
+pub mod scroll;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "rpc")]
+pub mod simulate;
+#[cfg(feature = "rpc")]
+pub mod slots;
+#[cfg(feature = "rpc")]
+pub mod source;
+#[cfg(feature = "rpc")]
+pub mod statement;
+#[cfg(feature = "rpc")]
+pub mod storage_layout;
+#[cfg(feature = "rpc")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod strict;
+#[cfg(feature = "rpc")]
+pub mod summary;
+#[cfg(feature = "std")]
+pub mod trie;
+#[cfg(feature = "rpc")]
+pub mod units;
+#[cfg(feature = "std")]
+pub mod witness;
+#[cfg(feature = "zksync")]
+pub mod zksync;
+
+#[cfg(feature = "rpc")]
+pub use generate::*;
+
+#[cfg(feature = "std")]
+use error::{Error, Result};
+#[cfg(feature = "std")]
+use params::Params;
+
+pub const BLOCK_HEADER_RLP_BYTES: usize = 590;
+/// Bytes before the state root in every header from 256 to 65535 bytes
+/// long: the list prefix, parent hash, uncles hash, author and the state
+/// root's own prefix.
+pub const BLOCK_HEADER_RLP_HEAD_BYTES: usize = 91;
+pub const BLOCK_HEADER_KECCAK_MAX_BLOCKS: usize =
+    BLOCK_HEADER_RLP_BYTES / keccak::KECCAK_RATE_BYTES + 1;
+pub const PROOF_BYTES_LEN: usize = 532;
+pub const ACCOUNT_PROOF_MAX_DEPTH: usize = 10;
+pub const STORAGE_PROOF_MAX_DEPTH: usize = 9;
+/// Code of an EOA that delegates under EIP-7702 starts with this, followed
+/// by the delegate's address.
+pub const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+pub const RECEIPT_PROOF_MAX_DEPTH: usize = 6;
+/// Default padded length of receipt trie nodes; leaves hold a whole receipt
+/// with its bloom and logs, so they outgrow `PROOF_BYTES_LEN`.
+pub const RECEIPT_NODE_MAX_BYTES: usize = 2048;
+/// Longest key of the receipt trie, the RLP of a transaction index below
+/// 2^16.
+pub const RECEIPT_KEY_MAX_BYTES: usize = 3;
+/// Longest CREATE preimage, the RLP of an address and an EIP-2681 nonce
+/// below 2^64.
+pub const CREATE_PREIMAGE_MAX_BYTES: usize = 31;
+
+/// Pads every node to `PROOF_BYTES_LEN` bytes and the proof to `max_depth`
+/// nodes, flattened.
+#[cfg(feature = "std")]
+pub fn pad_proof(name: &str, nodes: &[Vec<u8>], max_depth: usize) -> Result<Vec<u8>> {
+    pad_nodes(name, nodes, max_depth, PROOF_BYTES_LEN)
+}
+
+/// Pads every node to `node_len` bytes and the proof to `max_depth` nodes,
+/// flattened.
+#[cfg(feature = "std")]
+fn pad_nodes(name: &str, nodes: &[Vec<u8>], max_depth: usize, node_len: usize) -> Result<Vec<u8>> {
+    if nodes.len() > max_depth {
+        return Err(Error::ProofTooDeep(format!(
+            "the {} has {} nodes, more than the circuit's {}",
+            name,
+            nodes.len(),
+            max_depth
+        )));
+    }
+    let mut flat = vec![0; max_depth * node_len];
+    for (i, node) in nodes.iter().enumerate() {
+        if node.len() > node_len {
+            return Err(Error::ProofTooDeep(format!(
+                "{} node {} is {} bytes, more than the circuit's {}",
+                name,
+                i,
+                node.len(),
+                node_len
+            )));
+        }
+        flat[i * node_len..i * node_len + node.len()].copy_from_slice(node);
+    }
+    Ok(flat)
+}
+
+/// Fields of the verifier parameters, in the order `gen_verify_params`
+/// emits them: the circuit's public inputs.
+pub const VERIFIER_FIELDS: &[&str] = &[
+    "account_key",
+    "account_value",
+    "block_hash",
+    "state_root",
+    "storage_key",
+    "storage_key_hash",
+    "storage_value",
+    "account_exists",
+    "account_nonce",
+    "account_balance",
+    "empty_trie_root",
+    "empty_code_hash",
+    "has_empty_storage",
+    "has_empty_code",
+    "is_zero_value",
+    "range_lo",
+    "range_hi",
+    "checkpoint_root",
+];
+
+/// The verifier parameters within the prover parameters `prover`, with its
+/// metadata.
+#[cfg(feature = "std")]
+pub fn verifier_params(prover: &Params) -> Params {
+    let mut params = Params {
+        meta: prover.meta.clone(),
+        ..Default::default()
+    };
+    for name in VERIFIER_FIELDS {
+        if let Some(value) = prover.fields.get(name) {
+            params.push(*name, value.clone());
+        }
+    }
+    params.record_witness_hash();
+    params
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// RLP lists of up to 15 short strings, the shape of trie nodes, within
+    /// `PROOF_BYTES_LEN` bytes.
+    fn node() -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(prop::collection::vec(any::<u8>(), 0..=33), 1..=15).prop_map(
+            |items| {
+                let mut stream = rlp::RlpStream::new_list(items.len());
+                for item in &items {
+                    stream.append(item);
+                }
+                stream.out().to_vec()
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn pad_proof_keeps_every_node_in_its_slot(
+            nodes in prop::collection::vec(node(), 0..=10),
+            extra in 0..=3usize,
+        ) {
+            let max_depth = nodes.len() + extra;
+            let flat = pad_proof("proof", &nodes, max_depth).unwrap();
+            prop_assert_eq!(flat.len(), max_depth * PROOF_BYTES_LEN);
+            prop_assert_eq!(crate::core::nodes::unpad_proof(&flat, PROOF_BYTES_LEN, nodes.len()), nodes.clone());
+            for (i, slot) in flat.chunks(PROOF_BYTES_LEN).enumerate() {
+                let used = nodes.get(i).map_or(0, Vec::len);
+                prop_assert!(slot[used..].iter().all(|&byte| byte == 0));
+            }
+        }
+
+        #[test]
+        fn pad_nodes_refuses_what_does_not_fit(
+            nodes in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..=64), 1..=8),
+            node_len in 1..=64usize,
+            max_depth in 0..=8usize,
+        ) {
+            let fits = nodes.len() <= max_depth && nodes.iter().all(|node| node.len() <= node_len);
+            match pad_nodes("proof", &nodes, max_depth, node_len) {
+                Ok(flat) => {
+                    prop_assert!(fits);
+                    prop_assert_eq!(flat.len(), max_depth * node_len);
+                    for (node, slot) in nodes.iter().zip(flat.chunks(node_len)) {
+                        prop_assert_eq!(&slot[..node.len()], node.as_slice());
+                    }
+                }
+                Err(e) => {
+                    prop_assert!(!fits);
+                    prop_assert!(matches!(e, Error::ProofTooDeep(_)));
+                }
+            }
+        }
+    }
+}
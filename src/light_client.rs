@@ -0,0 +1,1125 @@
+//! Verification of execution block hashes against the beacon chain light
+//! client protocol: a trusted checkpoint bootstraps the sync committee, which
+//! is advanced period by period and finally used to check the signature over
+//! the latest finality update.
+
+use blst::min_pk::{PublicKey, Signature};
+use blst::BLST_ERROR;
+use openssl::sha::sha256;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use web3::types::H256;
+
+use crate::error::{Error, Result};
+use crate::rpc;
+
+const SLOTS_PER_EPOCH: u64 = 32;
+const EPOCHS_PER_SYNC_COMMITTEE_PERIOD: u64 = 256;
+const SYNC_COMMITTEE_SIZE: usize = 512;
+const DOMAIN_SYNC_COMMITTEE: [u8; 4] = [7, 0, 0, 0];
+const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+const EXECUTION_PAYLOAD_GINDEX: u64 = 25;
+/// Maximum number of updates requested from the beacon API at once.
+const MAX_REQUEST_LIGHT_CLIENT_UPDATES: u64 = 128;
+
+/// Execution block the light client proved finalized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinalizedExecution {
+    pub slot: u64,
+    pub block_number: u64,
+    pub block_hash: H256,
+}
+
+/// Execution block carried by a beacon block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeaconExecution {
+    pub beacon_root: H256,
+    pub slot: u64,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub state_root: H256,
+}
+
+#[derive(Deserialize)]
+struct Data<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Versioned<T> {
+    version: String,
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Bootstrap {
+    header: LightClientHeader,
+    current_sync_committee: SyncCommittee,
+    current_sync_committee_branch: Vec<H256>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    attested_header: LightClientHeader,
+    next_sync_committee: SyncCommittee,
+    next_sync_committee_branch: Vec<H256>,
+    sync_aggregate: SyncAggregate,
+    signature_slot: Quantity,
+}
+
+#[derive(Deserialize)]
+struct FinalityUpdate {
+    attested_header: LightClientHeader,
+    finalized_header: LightClientHeader,
+    finality_branch: Vec<H256>,
+    sync_aggregate: SyncAggregate,
+    signature_slot: Quantity,
+}
+
+#[derive(Deserialize)]
+struct LightClientHeader {
+    beacon: BeaconBlockHeader,
+    execution: Option<ExecutionPayloadHeader>,
+    execution_branch: Option<Vec<H256>>,
+}
+
+#[derive(Deserialize)]
+struct BeaconBlockHeader {
+    slot: Quantity,
+    proposer_index: Quantity,
+    parent_root: H256,
+    state_root: H256,
+    body_root: H256,
+}
+
+#[derive(Deserialize)]
+struct ExecutionPayloadHeader {
+    parent_hash: H256,
+    fee_recipient: HexBytes,
+    state_root: H256,
+    receipts_root: H256,
+    logs_bloom: HexBytes,
+    prev_randao: H256,
+    block_number: Quantity,
+    gas_limit: Quantity,
+    gas_used: Quantity,
+    timestamp: Quantity,
+    extra_data: HexBytes,
+    base_fee_per_gas: String,
+    block_hash: H256,
+    transactions_root: H256,
+    withdrawals_root: H256,
+    blob_gas_used: Option<Quantity>,
+    excess_blob_gas: Option<Quantity>,
+}
+
+#[derive(Deserialize)]
+struct SignedBeaconBlock {
+    message: BeaconBlock,
+}
+
+#[derive(Deserialize)]
+struct BeaconBlock {
+    slot: Quantity,
+    body: BeaconBlockBody,
+}
+
+#[derive(Deserialize)]
+struct BeaconBlockBody {
+    execution_payload: Option<ExecutionPayloadSummary>,
+}
+
+#[derive(Deserialize)]
+struct ExecutionPayloadSummary {
+    block_number: Quantity,
+    block_hash: H256,
+    state_root: H256,
+}
+
+#[derive(Deserialize)]
+struct SyncCommittee {
+    pubkeys: Vec<HexBytes>,
+    aggregate_pubkey: HexBytes,
+}
+
+#[derive(Deserialize)]
+struct SyncAggregate {
+    sync_committee_bits: HexBytes,
+    sync_committee_signature: HexBytes,
+}
+
+#[derive(Deserialize)]
+struct Genesis {
+    genesis_validators_root: H256,
+}
+
+#[derive(Deserialize)]
+struct Fork {
+    current_version: HexBytes,
+    epoch: Quantity,
+}
+
+/// Decimal quantity as returned by the beacon API.
+#[derive(Clone, Copy)]
+struct Quantity(u64);
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value
+            .parse()
+            .map(Quantity)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+struct HexBytes(Vec<u8>);
+
+impl<'de> Deserialize<'de> for HexBytes {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        hex::decode(value.trim_start_matches("0x"))
+            .map(HexBytes)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Generalized indices that moved when Electra grew the beacon state.
+struct Gindices {
+    finalized_root: u64,
+    current_sync_committee: u64,
+    next_sync_committee: u64,
+}
+
+fn gindices(version: &str) -> Result<Gindices> {
+    match version {
+        "altair" | "bellatrix" | "capella" | "deneb" => Ok(Gindices {
+            finalized_root: 105,
+            current_sync_committee: 54,
+            next_sync_committee: 55,
+        }),
+        "electra" | "fulu" => Ok(Gindices {
+            finalized_root: 169,
+            current_sync_committee: 86,
+            next_sync_committee: 87,
+        }),
+        other => Err(Error::LightClient(format!("unsupported fork {}", other))),
+    }
+}
+
+fn sync_committee_period(slot: u64) -> u64 {
+    slot / SLOTS_PER_EPOCH / EPOCHS_PER_SYNC_COMMITTEE_PERIOD
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(left);
+    data[32..].copy_from_slice(right);
+    sha256(&data)
+}
+
+/// Merkleizes `chunks`, padded with zero chunks to the next power of two.
+fn merkleize(chunks: &[[u8; 32]]) -> [u8; 32] {
+    let mut layer = chunks.to_vec();
+    let width = layer.len().next_power_of_two().max(1);
+    layer.resize(width, [0u8; 32]);
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+    layer[0]
+}
+
+fn pack_bytes(bytes: &[u8]) -> Vec<[u8; 32]> {
+    bytes
+        .chunks(32)
+        .map(|chunk| {
+            let mut padded = [0u8; 32];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            padded
+        })
+        .collect()
+}
+
+fn uint_chunk(value: u64) -> [u8; 32] {
+    let mut chunk = [0u8; 32];
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    chunk
+}
+
+fn uint256_chunk(decimal: &str) -> Result<[u8; 32]> {
+    let value = web3::types::U256::from_dec_str(decimal)
+        .map_err(|e| Error::LightClient(format!("invalid base fee: {:?}", e)))?;
+    let mut chunk = [0u8; 32];
+    value.to_little_endian(&mut chunk);
+    Ok(chunk)
+}
+
+fn beacon_header_root(header: &BeaconBlockHeader) -> [u8; 32] {
+    merkleize(&[
+        uint_chunk(header.slot.0),
+        uint_chunk(header.proposer_index.0),
+        header.parent_root.0,
+        header.state_root.0,
+        header.body_root.0,
+    ])
+}
+
+fn execution_header_root(header: &ExecutionPayloadHeader) -> Result<[u8; 32]> {
+    // extra_data is a ByteList[32]: a single chunk with its length mixed in.
+    let extra_data = hash_pair(
+        &merkleize(&pack_bytes(&header.extra_data.0)),
+        &uint_chunk(header.extra_data.0.len() as u64),
+    );
+    let mut fields = vec![
+        header.parent_hash.0,
+        pack_bytes(&header.fee_recipient.0)[0],
+        header.state_root.0,
+        header.receipts_root.0,
+        merkleize(&pack_bytes(&header.logs_bloom.0)),
+        header.prev_randao.0,
+        uint_chunk(header.block_number.0),
+        uint_chunk(header.gas_limit.0),
+        uint_chunk(header.gas_used.0),
+        uint_chunk(header.timestamp.0),
+        extra_data,
+        uint256_chunk(&header.base_fee_per_gas)?,
+        header.block_hash.0,
+        header.transactions_root.0,
+        header.withdrawals_root.0,
+    ];
+    if let (Some(blob_gas_used), Some(excess_blob_gas)) =
+        (header.blob_gas_used, header.excess_blob_gas)
+    {
+        fields.push(uint_chunk(blob_gas_used.0));
+        fields.push(uint_chunk(excess_blob_gas.0));
+    }
+    Ok(merkleize(&fields))
+}
+
+fn pubkey_root(pubkey: &[u8]) -> [u8; 32] {
+    merkleize(&pack_bytes(pubkey))
+}
+
+fn sync_committee_root(committee: &SyncCommittee) -> [u8; 32] {
+    let pubkeys: Vec<[u8; 32]> = committee
+        .pubkeys
+        .iter()
+        .map(|pubkey| pubkey_root(&pubkey.0))
+        .collect();
+    hash_pair(
+        &merkleize(&pubkeys),
+        &pubkey_root(&committee.aggregate_pubkey.0),
+    )
+}
+
+fn is_valid_branch(leaf: [u8; 32], branch: &[H256], gindex: u64, root: &H256) -> bool {
+    let depth = 63 - gindex.leading_zeros() as usize;
+    if branch.len() != depth {
+        return false;
+    }
+    let index = gindex - (1 << depth);
+    let computed = branch.iter().enumerate().fold(leaf, |node, (i, sibling)| {
+        if (index >> i) & 1 == 1 {
+            hash_pair(&sibling.0, &node)
+        } else {
+            hash_pair(&node, &sibling.0)
+        }
+    });
+    computed == root.0
+}
+
+fn check_branch(
+    what: &str,
+    leaf: [u8; 32],
+    branch: &[H256],
+    gindex: u64,
+    root: &H256,
+) -> Result<()> {
+    if is_valid_branch(leaf, branch, gindex, root) {
+        Ok(())
+    } else {
+        Err(Error::LightClient(format!("invalid {} branch", what)))
+    }
+}
+
+fn check_execution_branch(header: &LightClientHeader) -> Result<()> {
+    match (&header.execution, &header.execution_branch) {
+        (Some(execution), Some(branch)) => check_branch(
+            "execution payload",
+            execution_header_root(execution)?,
+            branch,
+            EXECUTION_PAYLOAD_GINDEX,
+            &header.beacon.body_root,
+        ),
+        _ => Err(Error::LightClient(
+            "light client header carries no execution payload (pre-Capella)".to_string(),
+        )),
+    }
+}
+
+struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    genesis_validators_root: H256,
+    forks: Vec<Fork>,
+}
+
+impl Client {
+    async fn get<T: DeserializeOwned>(
+        http: &reqwest::Client,
+        base_url: &str,
+        path: &str,
+    ) -> Result<T> {
+        let url = format!("{}{}", base_url.trim_end_matches('/'), path);
+        let mut request = http.get(&url);
+        // The beacon API counts against --rpc-timeout and --total-timeout too.
+        if let Some((limit, _)) = rpc::time_limit() {
+            request = request.timeout(limit);
+        }
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::LightClient(format!("GET {}: {}", path, e)))?;
+        response
+            .json()
+            .await
+            .map_err(|e| Error::LightClient(format!("GET {}: {}", path, e)))
+    }
+
+    async fn new(base_url: &str) -> Result<Self> {
+        let http = rpc::http_client();
+        let genesis: Data<Genesis> = Self::get(&http, base_url, "/eth/v1/beacon/genesis").await?;
+        let forks: Data<Vec<Fork>> =
+            Self::get(&http, base_url, "/eth/v1/config/fork_schedule").await?;
+        Ok(Client {
+            http,
+            base_url: base_url.to_string(),
+            genesis_validators_root: genesis.data.genesis_validators_root,
+            forks: forks.data,
+        })
+    }
+
+    async fn fetch<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        Self::get(&self.http, &self.base_url, path).await
+    }
+
+    fn fork_version(&self, slot: u64) -> Result<[u8; 4]> {
+        let epoch = slot / SLOTS_PER_EPOCH;
+        self.forks
+            .iter()
+            .filter(|fork| fork.epoch.0 <= epoch)
+            .max_by_key(|fork| fork.epoch.0)
+            .and_then(|fork| fork.current_version.0.as_slice().try_into().ok())
+            .ok_or_else(|| Error::LightClient(format!("no fork scheduled at slot {}", slot)))
+    }
+
+    /// Checks that a supermajority of `committee` signed `header` at
+    /// `signature_slot`.
+    fn verify_sync_aggregate(
+        &self,
+        committee: &SyncCommittee,
+        aggregate: &SyncAggregate,
+        header: &BeaconBlockHeader,
+        signature_slot: u64,
+    ) -> Result<()> {
+        let bits = &aggregate.sync_committee_bits.0;
+        if bits.len() * 8 != SYNC_COMMITTEE_SIZE || committee.pubkeys.len() != SYNC_COMMITTEE_SIZE {
+            return Err(Error::LightClient("malformed sync aggregate".to_string()));
+        }
+        let participants: Vec<&HexBytes> = committee
+            .pubkeys
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| bits[i / 8] >> (i % 8) & 1 == 1)
+            .map(|(_, pubkey)| pubkey)
+            .collect();
+        if participants.len() * 3 < SYNC_COMMITTEE_SIZE * 2 {
+            return Err(Error::LightClient(format!(
+                "only {} of {} sync committee members signed",
+                participants.len(),
+                SYNC_COMMITTEE_SIZE
+            )));
+        }
+
+        let fork_version = self.fork_version(signature_slot.max(1) - 1)?;
+        let mut version_chunk = [0u8; 32];
+        version_chunk[..4].copy_from_slice(&fork_version);
+        let fork_data_root = hash_pair(&version_chunk, &self.genesis_validators_root.0);
+        let mut domain = [0u8; 32];
+        domain[..4].copy_from_slice(&DOMAIN_SYNC_COMMITTEE);
+        domain[4..].copy_from_slice(&fork_data_root[..28]);
+        let signing_root = hash_pair(&beacon_header_root(header), &domain);
+
+        let bls_error = |e: BLST_ERROR| Error::LightClient(format!("BLS error: {:?}", e));
+        let pubkeys = participants
+            .iter()
+            .map(|pubkey| PublicKey::from_bytes(&pubkey.0).map_err(bls_error))
+            .collect::<Result<Vec<_>>>()?;
+        let pubkeys: Vec<&PublicKey> = pubkeys.iter().collect();
+        let signature =
+            Signature::from_bytes(&aggregate.sync_committee_signature.0).map_err(bls_error)?;
+        match signature.fast_aggregate_verify(true, &signing_root, BLS_DST, &pubkeys) {
+            BLST_ERROR::BLST_SUCCESS => Ok(()),
+            e => Err(Error::LightClient(format!(
+                "sync committee signature is invalid: {:?}",
+                e
+            ))),
+        }
+    }
+}
+
+/// The execution block of the beacon block whose root is `root`, as the
+/// beacon API at `beacon_api` reports it. The response is not verified;
+/// `--lc-checkpoint` checks the resolved block against the beacon chain.
+pub async fn execution_block(beacon_api: &str, root: H256) -> Result<BeaconExecution> {
+    let block: Versioned<SignedBeaconBlock> = Client::get(
+        &rpc::http_client(),
+        beacon_api,
+        &format!("/eth/v2/beacon/blocks/{:?}", root),
+    )
+    .await?;
+    let message = block.data.message;
+    let payload = message.body.execution_payload.ok_or_else(|| {
+        Error::Unsupported(format!(
+            "beacon block {:?} ({}) carries no execution payload; it predates the merge",
+            root, block.version
+        ))
+    })?;
+    Ok(BeaconExecution {
+        beacon_root: root,
+        slot: message.slot.0,
+        block_number: payload.block_number.0,
+        block_hash: payload.block_hash,
+        state_root: payload.state_root,
+    })
+}
+
+/// Bootstraps from the trusted beacon block root `checkpoint` and returns
+/// the execution block of the latest finality update, after verifying every
+/// sync committee handover and signature on the way.
+pub async fn verify_finalized(beacon_api: &str, checkpoint: H256) -> Result<FinalizedExecution> {
+    let client = Client::new(beacon_api).await?;
+
+    let bootstrap: Versioned<Bootstrap> = client
+        .fetch(&format!(
+            "/eth/v1/beacon/light_client/bootstrap/{:?}",
+            checkpoint
+        ))
+        .await?;
+    let header = &bootstrap.data.header.beacon;
+    if beacon_header_root(header) != checkpoint.0 {
+        return Err(Error::LightClient(
+            "bootstrap header does not match the checkpoint".to_string(),
+        ));
+    }
+    check_branch(
+        "current sync committee",
+        sync_committee_root(&bootstrap.data.current_sync_committee),
+        &bootstrap.data.current_sync_committee_branch,
+        gindices(&bootstrap.version)?.current_sync_committee,
+        &header.state_root,
+    )?;
+
+    let finality: Versioned<FinalityUpdate> = client
+        .fetch("/eth/v1/beacon/light_client/finality_update")
+        .await?;
+    let signature_slot = finality.data.signature_slot.0;
+
+    let mut period = sync_committee_period(header.slot.0);
+    let mut committee = bootstrap.data.current_sync_committee;
+    let target_period = sync_committee_period(signature_slot);
+    while period < target_period {
+        let count = (target_period - period).min(MAX_REQUEST_LIGHT_CLIENT_UPDATES);
+        let updates: Vec<Versioned<Update>> = client
+            .fetch(&format!(
+                "/eth/v1/beacon/light_client/updates?start_period={}&count={}",
+                period, count
+            ))
+            .await?;
+        if updates.is_empty() {
+            return Err(Error::LightClient(format!(
+                "no light client update for period {}",
+                period
+            )));
+        }
+        for update in updates {
+            let attested = &update.data.attested_header.beacon;
+            if sync_committee_period(update.data.signature_slot.0) != period {
+                return Err(Error::LightClient(format!(
+                    "update for period {} is out of order",
+                    period
+                )));
+            }
+            client.verify_sync_aggregate(
+                &committee,
+                &update.data.sync_aggregate,
+                attested,
+                update.data.signature_slot.0,
+            )?;
+            check_branch(
+                "next sync committee",
+                sync_committee_root(&update.data.next_sync_committee),
+                &update.data.next_sync_committee_branch,
+                gindices(&update.version)?.next_sync_committee,
+                &attested.state_root,
+            )?;
+            committee = update.data.next_sync_committee;
+            period += 1;
+        }
+    }
+
+    let attested = &finality.data.attested_header.beacon;
+    client.verify_sync_aggregate(
+        &committee,
+        &finality.data.sync_aggregate,
+        attested,
+        signature_slot,
+    )?;
+    let finalized = &finality.data.finalized_header;
+    check_branch(
+        "finality",
+        beacon_header_root(&finalized.beacon),
+        &finality.data.finality_branch,
+        gindices(&finality.version)?.finalized_root,
+        &attested.state_root,
+    )?;
+    check_execution_branch(finalized)?;
+
+    let execution = finalized
+        .execution
+        .as_ref()
+        .expect("execution branch was checked");
+    Ok(FinalizedExecution {
+        slot: finalized.beacon.slot.0,
+        block_number: execution.block_number.0,
+        block_hash: execution.block_hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use blst::min_pk::{AggregatePublicKey, AggregateSignature, SecretKey};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    const ZERO_HASHES: [&str; 3] = [
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a92759fb4b",
+        "db56114e00fdd4c1f85c892bf35ac9a89289aaecb1ebd0a96cde606a748b5d71",
+    ];
+    const GENESIS_VALIDATORS_ROOT: [u8; 32] = [0x4b; 32];
+    const DENEB: [u8; 4] = [4, 0, 0, 0];
+    /// A slot in sync committee period 100.
+    const BOOTSTRAP_SLOT: u64 = 100 * 8192 + 10;
+
+    fn chunk(byte: u8) -> [u8; 32] {
+        [byte; 32]
+    }
+
+    fn hex32(bytes: &[u8; 32]) -> String {
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    fn from_json<T: DeserializeOwned>(value: Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// The sibling hashes proving `leaves[index]`, bottom up.
+    fn branch(leaves: &[[u8; 32]], index: usize) -> Vec<H256> {
+        let mut layer = leaves.to_vec();
+        layer.resize(layer.len().next_power_of_two(), [0u8; 32]);
+        let mut index = index;
+        let mut siblings = Vec::new();
+        while layer.len() > 1 {
+            siblings.push(H256(layer[index ^ 1]));
+            layer = layer
+                .chunks(2)
+                .map(|pair| hash_pair(&pair[0], &pair[1]))
+                .collect();
+            index /= 2;
+        }
+        siblings
+    }
+
+    /// A Deneb execution payload header, given as the beacon API returns it.
+    fn execution_json(blob_fields: bool) -> Value {
+        let mut logs_bloom = [0u8; 256];
+        logs_bloom[0] = 0x01;
+        logs_bloom[100] = 0x80;
+        logs_bloom[255] = 0xff;
+        let mut header = json!({
+            "parent_hash": hex32(&chunk(0x11)),
+            "fee_recipient": format!("0x{}", hex::encode([0x22; 20])),
+            "state_root": hex32(&chunk(0x33)),
+            "receipts_root": hex32(&chunk(0x44)),
+            "logs_bloom": format!("0x{}", hex::encode(logs_bloom)),
+            "prev_randao": hex32(&chunk(0x55)),
+            "block_number": "19000000",
+            "gas_limit": "30000000",
+            "gas_used": "12345678",
+            "timestamp": "1700000000",
+            "extra_data": format!("0x{}", hex::encode(b"noir-mip")),
+            "base_fee_per_gas": "123456789012345678901234567890",
+            "block_hash": hex32(&chunk(0x66)),
+            "transactions_root": hex32(&chunk(0x77)),
+            "withdrawals_root": hex32(&chunk(0x88)),
+        });
+        if blob_fields {
+            header["blob_gas_used"] = json!("131072");
+            header["excess_blob_gas"] = json!("262144");
+        }
+        header
+    }
+
+    fn beacon_json(slot: u64, state_root: [u8; 32], body_root: [u8; 32]) -> Value {
+        json!({
+            "slot": slot.to_string(),
+            "proposer_index": "4242",
+            "parent_root": hex32(&chunk(0xaa)),
+            "state_root": hex32(&state_root),
+            "body_root": hex32(&body_root),
+        })
+    }
+
+    fn beacon(slot: u64, state_root: [u8; 32], body_root: [u8; 32]) -> BeaconBlockHeader {
+        from_json(beacon_json(slot, state_root, body_root))
+    }
+
+    /// A sync committee of distinct, deterministic keys.
+    struct Committee {
+        keys: Vec<SecretKey>,
+    }
+
+    impl Committee {
+        fn new(seed: u8) -> Self {
+            let keys = (0..SYNC_COMMITTEE_SIZE)
+                .map(|i| {
+                    let mut ikm = [seed; 32];
+                    ikm[..2].copy_from_slice(&(i as u16).to_be_bytes());
+                    SecretKey::key_gen(&ikm, &[]).unwrap()
+                })
+                .collect();
+            Committee { keys }
+        }
+
+        fn json(&self) -> Value {
+            let pubkeys: Vec<PublicKey> = self.keys.iter().map(|key| key.sk_to_pk()).collect();
+            let refs: Vec<&PublicKey> = pubkeys.iter().collect();
+            let aggregate = AggregatePublicKey::aggregate(&refs, false)
+                .unwrap()
+                .to_public_key();
+            json!({
+                "pubkeys": pubkeys
+                    .iter()
+                    .map(|pubkey| format!("0x{}", hex::encode(pubkey.to_bytes())))
+                    .collect::<Vec<_>>(),
+                "aggregate_pubkey": format!("0x{}", hex::encode(aggregate.to_bytes())),
+            })
+        }
+
+        fn committee(&self) -> SyncCommittee {
+            from_json(self.json())
+        }
+
+        /// The aggregate with which the first `signers` members sign
+        /// `header` under the Deneb fork.
+        fn sign(&self, header: &BeaconBlockHeader, signers: usize) -> Value {
+            let mut version = [0u8; 32];
+            version[..4].copy_from_slice(&DENEB);
+            let fork_data_root = merkleize(&[version, GENESIS_VALIDATORS_ROOT]);
+            let mut domain = [0u8; 32];
+            domain[..4].copy_from_slice(&DOMAIN_SYNC_COMMITTEE);
+            domain[4..].copy_from_slice(&fork_data_root[..28]);
+            let signing_root = merkleize(&[beacon_header_root(header), domain]);
+
+            let signatures: Vec<Signature> = self.keys[..signers]
+                .iter()
+                .map(|key| key.sign(&signing_root, BLS_DST, &[]))
+                .collect();
+            let refs: Vec<&Signature> = signatures.iter().collect();
+            let signature = AggregateSignature::aggregate(&refs, false)
+                .unwrap()
+                .to_signature();
+            let mut bits = [0u8; SYNC_COMMITTEE_SIZE / 8];
+            for i in 0..signers {
+                bits[i / 8] |= 1 << (i % 8);
+            }
+            json!({
+                "sync_committee_bits": format!("0x{}", hex::encode(bits)),
+                "sync_committee_signature": format!("0x{}", hex::encode(signature.to_bytes())),
+            })
+        }
+    }
+
+    fn client(base_url: &str) -> Client {
+        Client {
+            http: reqwest::Client::new(),
+            base_url: base_url.to_string(),
+            genesis_validators_root: H256(GENESIS_VALIDATORS_ROOT),
+            forks: vec![Fork {
+                current_version: HexBytes(DENEB.to_vec()),
+                epoch: Quantity(0),
+            }],
+        }
+    }
+
+    #[test]
+    fn merkleize_pads_to_a_power_of_two() {
+        for (depth, zero) in ZERO_HASHES.iter().enumerate() {
+            let leaves = vec![[0u8; 32]; 1 << depth];
+            assert_eq!(hex::encode(merkleize(&leaves)), *zero);
+        }
+        assert_eq!(merkleize(&[]), [0u8; 32]);
+        assert_eq!(merkleize(&[chunk(1)]), chunk(1));
+        assert_eq!(
+            merkleize(&[chunk(1), chunk(2), chunk(3)]),
+            hash_pair(
+                &hash_pair(&chunk(1), &chunk(2)),
+                &hash_pair(&chunk(3), &[0u8; 32])
+            )
+        );
+    }
+
+    #[test]
+    fn gindices_follow_the_beacon_state_layout() {
+        // Before Electra the state has 28 fields (32 leaves), from Electra on
+        // 37 (64 leaves). finalized_checkpoint is field 20, with its root the
+        // second of two fields; the sync committees are fields 22 and 23.
+        for (version, leaves) in [("altair", 32), ("deneb", 32), ("electra", 64)] {
+            let gindices = gindices(version).unwrap();
+            assert_eq!(gindices.finalized_root, (leaves + 20) * 2 + 1);
+            assert_eq!(gindices.current_sync_committee, leaves + 22);
+            assert_eq!(gindices.next_sync_committee, leaves + 23);
+        }
+        // execution_payload is field 9 of the 12 or 13 in the block body.
+        assert_eq!(EXECUTION_PAYLOAD_GINDEX, 16 + 9);
+        assert!(gindices("phase0").is_err());
+    }
+
+    #[test]
+    fn header_roots_match_the_ssz_reference() {
+        // Roots computed by the `tree_hash` crate for the same values.
+        let deneb: ExecutionPayloadHeader = from_json(execution_json(true));
+        assert_eq!(
+            hex::encode(execution_header_root(&deneb).unwrap()),
+            "f87026e3163b92278ac6944cb89620a4529afa6f4c34b999a960a775e5007589"
+        );
+        let capella: ExecutionPayloadHeader = from_json(execution_json(false));
+        assert_eq!(
+            hex::encode(execution_header_root(&capella).unwrap()),
+            "62e682fce5773f36ed791b4fd19d68b514c2bf7d98a6a6d6e12a6c7bf9a6eea9"
+        );
+        let header = beacon(9_000_001, chunk(0xbb), chunk(0xcc));
+        assert_eq!(
+            hex::encode(beacon_header_root(&header)),
+            "f7145f8ca0b4ed82a3a83542e6f877a7806ce448f71f9d69d02399c3c27b773e"
+        );
+        let committee: SyncCommittee = from_json(json!({
+            "pubkeys": (0..SYNC_COMMITTEE_SIZE)
+                .map(|i| format!("0x{}", hex::encode([(i % 251) as u8; 48])))
+                .collect::<Vec<_>>(),
+            "aggregate_pubkey": format!("0x{}", hex::encode([0xab; 48])),
+        }));
+        assert_eq!(
+            hex::encode(sync_committee_root(&committee)),
+            "da8bb19818c2eea9ad858556693e4080580c1856ceaaae14eef988d76bea0db2"
+        );
+    }
+
+    #[test]
+    fn branches_are_checked_against_their_gindex() {
+        let leaves: Vec<[u8; 32]> = (0..32).map(chunk).collect();
+        let root = H256(merkleize(&leaves));
+        let proof = branch(&leaves, 22);
+        assert!(is_valid_branch(leaves[22], &proof, 32 + 22, &root));
+        assert!(!is_valid_branch(leaves[22], &proof, 32 + 23, &root));
+        assert!(!is_valid_branch(leaves[22], &proof[1..], 16 + 11, &root));
+        let mut wrong = proof.clone();
+        wrong[3].0[0] ^= 1;
+        assert!(!is_valid_branch(leaves[22], &wrong, 32 + 22, &root));
+    }
+
+    #[test]
+    fn a_supermajority_signature_is_accepted() {
+        let committee = Committee::new(1);
+        let header = beacon(BOOTSTRAP_SLOT, chunk(0xbb), chunk(0xcc));
+        let client = client("");
+        for signers in [SYNC_COMMITTEE_SIZE, 342] {
+            let aggregate = from_json(committee.sign(&header, signers));
+            client
+                .verify_sync_aggregate(
+                    &committee.committee(),
+                    &aggregate,
+                    &header,
+                    BOOTSTRAP_SLOT + 1,
+                )
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn a_flipped_participation_bit_fails_the_signature() {
+        let committee = Committee::new(1);
+        let header = beacon(BOOTSTRAP_SLOT, chunk(0xbb), chunk(0xcc));
+        for flip in [5, SYNC_COMMITTEE_SIZE - 1] {
+            let mut aggregate: SyncAggregate = from_json(committee.sign(&header, 500));
+            aggregate.sync_committee_bits.0[flip / 8] ^= 1 << (flip % 8);
+            let err = client("")
+                .verify_sync_aggregate(
+                    &committee.committee(),
+                    &aggregate,
+                    &header,
+                    BOOTSTRAP_SLOT + 1,
+                )
+                .unwrap_err();
+            assert!(err.to_string().contains("signature is invalid"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn a_signature_over_another_header_fails() {
+        let committee = Committee::new(1);
+        let header = beacon(BOOTSTRAP_SLOT, chunk(0xbb), chunk(0xcc));
+        let other = beacon(BOOTSTRAP_SLOT, chunk(0xbb), chunk(0xcd));
+        let aggregate = from_json(committee.sign(&other, 512));
+        let err = client("")
+            .verify_sync_aggregate(
+                &committee.committee(),
+                &aggregate,
+                &header,
+                BOOTSTRAP_SLOT + 1,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("signature is invalid"), "{}", err);
+    }
+
+    #[test]
+    fn less_than_two_thirds_of_the_committee_is_refused() {
+        let committee = Committee::new(1);
+        let header = beacon(BOOTSTRAP_SLOT, chunk(0xbb), chunk(0xcc));
+        let aggregate = from_json(committee.sign(&header, 341));
+        let err = client("")
+            .verify_sync_aggregate(
+                &committee.committee(),
+                &aggregate,
+                &header,
+                BOOTSTRAP_SLOT + 1,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("only 341 of 512"), "{}", err);
+    }
+
+    /// The responses of a beacon node whose chain runs from a bootstrap in
+    /// period 100, through one sync committee handover, to a finality update
+    /// in period 101.
+    struct Chain {
+        checkpoint: H256,
+        responses: HashMap<String, Value>,
+    }
+
+    impl Chain {
+        fn new() -> Self {
+            let version = "deneb";
+            let mut responses = HashMap::new();
+            responses.insert(
+                "/eth/v1/beacon/genesis".to_string(),
+                json!({ "data": { "genesis_validators_root": hex32(&GENESIS_VALIDATORS_ROOT) } }),
+            );
+            responses.insert(
+                "/eth/v1/config/fork_schedule".to_string(),
+                json!({ "data": [{
+                    "previous_version": "0x03000000",
+                    "current_version": format!("0x{}", hex::encode(DENEB)),
+                    "epoch": "0",
+                }] }),
+            );
+
+            let current = Committee::new(1);
+            let next = Committee::new(2);
+            let state = |field: usize, leaf: [u8; 32]| {
+                let mut fields: Vec<[u8; 32]> = (0..28).map(|i| chunk(i as u8)).collect();
+                fields[field] = leaf;
+                (merkleize(&fields), branch(&fields, field))
+            };
+
+            let (state_root, committee_branch) =
+                state(22, sync_committee_root(&current.committee()));
+            let bootstrap = beacon_json(BOOTSTRAP_SLOT, state_root, chunk(0xcc));
+            let checkpoint = H256(beacon_header_root(&from_json(bootstrap.clone())));
+            responses.insert(
+                format!("/eth/v1/beacon/light_client/bootstrap/{:?}", checkpoint),
+                json!({ "version": version, "data": {
+                    "header": { "beacon": bootstrap },
+                    "current_sync_committee": current.json(),
+                    "current_sync_committee_branch": committee_branch,
+                } }),
+            );
+
+            let (state_root, next_branch) = state(23, sync_committee_root(&next.committee()));
+            let attested = beacon_json(BOOTSTRAP_SLOT + 100, state_root, chunk(0xcc));
+            let aggregate = current.sign(&from_json(attested.clone()), 512);
+            responses.insert(
+                "/eth/v1/beacon/light_client/updates?start_period=100&count=1".to_string(),
+                json!([{ "version": version, "data": {
+                    "attested_header": { "beacon": attested },
+                    "next_sync_committee": next.json(),
+                    "next_sync_committee_branch": next_branch,
+                    "sync_aggregate": aggregate,
+                    "signature_slot": (BOOTSTRAP_SLOT + 101).to_string(),
+                } }]),
+            );
+
+            let execution = execution_json(true);
+            let mut body: Vec<[u8; 32]> = (0..12).map(|i| chunk(0xc0 + i as u8)).collect();
+            body[9] = execution_header_root(&from_json(execution.clone())).unwrap();
+            let slot = BOOTSTRAP_SLOT + 8192;
+            let finalized = beacon_json(slot, chunk(0xdd), merkleize(&body));
+            let checkpoint_root = [
+                uint_chunk(slot / 32),
+                beacon_header_root(&from_json(finalized.clone())),
+            ];
+            let (state_root, mut finality_branch) = state(20, merkleize(&checkpoint_root));
+            finality_branch.insert(0, H256(checkpoint_root[0]));
+            let attested = beacon_json(slot + 64, state_root, chunk(0xcc));
+            let aggregate = next.sign(&from_json(attested.clone()), 400);
+            responses.insert(
+                "/eth/v1/beacon/light_client/finality_update".to_string(),
+                json!({ "version": version, "data": {
+                    "attested_header": { "beacon": attested },
+                    "finalized_header": {
+                        "beacon": finalized,
+                        "execution": execution,
+                        "execution_branch": branch(&body, 9),
+                    },
+                    "finality_branch": finality_branch,
+                    "sync_aggregate": aggregate,
+                    "signature_slot": (slot + 65).to_string(),
+                } }),
+            );
+            Chain {
+                checkpoint,
+                responses,
+            }
+        }
+
+        fn finality(&mut self) -> &mut Value {
+            self.responses
+                .get_mut("/eth/v1/beacon/light_client/finality_update")
+                .unwrap()
+        }
+
+        /// Serves the responses over HTTP and runs the light client against
+        /// them.
+        fn verify(self) -> Result<FinalizedExecution> {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let responses = self.responses;
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut request = String::new();
+                    BufReader::new(&stream).read_line(&mut request).unwrap();
+                    let path = request.split(' ').nth(1).unwrap_or_default();
+                    let (status, body) = match responses.get(path) {
+                        Some(body) => ("200 OK", body.to_string()),
+                        None => ("404 Not Found", String::new()),
+                    };
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                }
+            });
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(verify_finalized(&url, self.checkpoint))
+        }
+    }
+
+    #[test]
+    fn the_finalized_execution_block_is_verified_from_a_checkpoint() {
+        let finalized = Chain::new().verify().unwrap();
+        assert_eq!(
+            finalized,
+            FinalizedExecution {
+                slot: BOOTSTRAP_SLOT + 8192,
+                block_number: 19_000_000,
+                block_hash: H256(chunk(0x66)),
+            }
+        );
+    }
+
+    #[test]
+    fn a_wrong_finality_branch_is_refused() {
+        let mut chain = Chain::new();
+        chain.finality()["data"]["finality_branch"][2] = json!(hex32(&chunk(0xee)));
+        let err = chain.verify().unwrap_err();
+        assert!(
+            err.to_string().contains("invalid finality branch"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn a_wrong_execution_branch_is_refused() {
+        let mut chain = Chain::new();
+        chain.finality()["data"]["finalized_header"]["execution"]["block_hash"] =
+            json!(hex32(&chunk(0x67)));
+        let err = chain.verify().unwrap_err();
+        assert!(
+            err.to_string().contains("invalid execution payload branch"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn a_finality_update_signed_by_the_old_committee_is_refused() {
+        let mut chain = Chain::new();
+        let attested = from_json(chain.finality()["data"]["attested_header"]["beacon"].clone());
+        chain.finality()["data"]["sync_aggregate"] = Committee::new(1).sign(&attested, 512);
+        let err = chain.verify().unwrap_err();
+        assert!(err.to_string().contains("signature is invalid"), "{}", err);
+    }
+
+    #[test]
+    fn a_checkpoint_the_bootstrap_does_not_hash_to_is_refused() {
+        let mut chain = Chain::new();
+        let path = format!(
+            "/eth/v1/beacon/light_client/bootstrap/{:?}",
+            chain.checkpoint
+        );
+        let bootstrap = chain.responses.remove(&path).unwrap();
+        chain.checkpoint = H256(chunk(0x01));
+        chain.responses.insert(
+            format!(
+                "/eth/v1/beacon/light_client/bootstrap/{:?}",
+                chain.checkpoint
+            ),
+            bootstrap,
+        );
+        let err = chain.verify().unwrap_err();
+        assert!(
+            err.to_string().contains("does not match the checkpoint"),
+            "{}",
+            err
+        );
+    }
+}
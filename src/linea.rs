@@ -0,0 +1,148 @@
+use serde::Deserialize;
+use web3::types::{Bytes, H256, U256, U64};
+use web3::Transport;
+
+use crate::error::{Error, Result};
+use crate::options::GenOptions;
+use crate::params::Params;
+use crate::{migrate, pad_nodes, rpc};
+
+/// Most nodes a Linea proof holds: the root, one sibling per level of the
+/// 40 level sparse Merkle tree and the leaf.
+pub const PROOF_MAX_NODES: usize = 42;
+
+/// Padded length of Linea proof nodes; leaves, the largest, hold the keys
+/// and values of their neighbours as well as their own.
+pub const NODE_MAX_BYTES: usize = 128;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Nodes {
+    value: Bytes,
+    proof_related_nodes: Vec<Bytes>,
+}
+
+/// Proof of one key. Keys missing from the state are proven by their two
+/// neighbouring leaves instead, which leaves `leaf_index` and `proof` out.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LeafProof {
+    leaf_index: Option<u64>,
+    proof: Option<Nodes>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Proof {
+    account_proof: LeafProof,
+    #[serde(default)]
+    storage_proofs: Vec<LeafProof>,
+    zk_end_state_root_hash: Option<H256>,
+}
+
+/// The leaf index and nodes of `proof`, rejecting exclusion proofs.
+fn inclusion<'a>(proof: &'a LeafProof, what: &str) -> Result<(u64, &'a Nodes)> {
+    match (proof.leaf_index, &proof.proof) {
+        (Some(index), Some(nodes)) => Ok((index, nodes)),
+        _ => Err(Error::Unsupported(format!(
+            "the provider proves the {} absent with its neighbouring leaves; Linea exclusion \
+             proofs are not supported",
+            what
+        ))),
+    }
+}
+
+fn node_bytes(nodes: &Nodes) -> Vec<Vec<u8>> {
+    nodes
+        .proof_related_nodes
+        .iter()
+        .map(|node| node.0.clone())
+        .collect()
+}
+
+/// Generates the parameters of `args` from the Linea provider at `rpc_url`:
+/// the account and storage proofs of `linea_getProof`, from Linea's sparse
+/// Merkle state rather than the MPT its block headers commit to.
+pub async fn generate(rpc_url: &str, args: &GenOptions, prove: bool) -> Result<Params> {
+    let web3 = web3::Web3::new(rpc::Client::new(rpc_url)?);
+    let slot = args
+        .slot
+        .ok_or_else(|| Error::Unsupported("--rollup linea needs --slot".to_string()))?;
+    let response = web3
+        .transport()
+        .execute(
+            "linea_getProof",
+            vec![
+                serde_json::json!(args.account),
+                serde_json::json!([slot]),
+                serde_json::json!(U64::from(args.block)),
+            ],
+        )
+        .await?;
+    let proof: Proof = serde_json::from_value(response)
+        .map_err(|e| Error::Decode(format!("invalid linea_getProof response: {}", e)))?;
+    let (account_index, account) = inclusion(&proof.account_proof, "account")?;
+    let storage = proof
+        .storage_proofs
+        .first()
+        .ok_or_else(|| Error::EmptyProof(format!("no storage proof of {:?}", slot)))?;
+    let (storage_index, storage) = inclusion(storage, "slot")?;
+    if storage.value.0.len() <= 32 {
+        crate::check_expected(args, U256::from_big_endian(&storage.value.0))?;
+    }
+    let account_nodes = node_bytes(account);
+    let storage_nodes = node_bytes(storage);
+    log::info!(
+        "Note: Linea proofs hash with MiMC, which this tool does not verify; the circuit must \
+         check the proofs against the state root"
+    );
+
+    let encoding = args.numeric_encoding;
+    let mut params = Params::default();
+    match proof.zk_end_state_root_hash {
+        Some(root) => params.push("state_root", root.as_bytes()),
+        None => log::info!(
+            "Note: the provider reports no state root; take it from the block's finalization \
+             on Ethereum"
+        ),
+    }
+    params.push("account_key", args.account.as_bytes());
+    params.push("account_value", account.value.0.clone());
+    params.push(
+        "storage_key",
+        encoding.encode(U256::from_big_endian(&slot.0)),
+    );
+    params.push("storage_value", storage.value.0.clone());
+    if prove {
+        params.push("account_leaf_index", account_index as usize);
+        params.push(
+            "account_proof",
+            pad_nodes(
+                "account proof",
+                &account_nodes,
+                PROOF_MAX_NODES,
+                NODE_MAX_BYTES,
+            )?,
+        );
+        params.push("account_proof_depth", account_nodes.len());
+        params.push("storage_leaf_index", storage_index as usize);
+        params.push(
+            "storage_proof",
+            pad_nodes(
+                "storage proof",
+                &storage_nodes,
+                PROOF_MAX_NODES,
+                NODE_MAX_BYTES,
+            )?,
+        );
+        params.push("storage_proof_depth", storage_nodes.len());
+    }
+    params.meta.push(
+        "witness_format_version",
+        migrate::WITNESS_FORMAT_VERSION as usize,
+    );
+    params.meta.push("numeric_encoding", encoding.name());
+    params.meta.push("rollup", "linea");
+    params.record_witness_hash();
+    Ok(params)
+}
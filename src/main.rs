@@ -1,247 +1,1856 @@
-use std::env;
+#[cfg(feature = "tui")]
+mod tui;
 
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
+
+use clap::{CommandFactory, Parser};
 use dotenv::dotenv;
-use rlp::{Rlp, RlpStream};
-use tiny_keccak::{Hasher, Keccak};
-use web3::transports::Http;
-use web3::types::{Block, BlockNumber, H160, H2048, H256, U256, U64};
-
-const BLOCK_HEADER_RLP_BYTES: usize = 590;
-const PROOF_BYTES_LEN: usize = 532;
-const ACCOUNT_PROOF_MAX_DEPTH: usize = 10;
-const STORAGE_PROOF_MAX_DEPTH: usize = 9;
-
-fn bloom_to_bytes(bloom_option: Option<H2048>) -> Vec<u8> {
-    match bloom_option {
-        Some(bloom) => bloom.as_bytes().to_vec(),
-        None => {
-            vec![]
+use futures::future;
+use futures::stream::{self, StreamExt};
+use web3::types::{H160, H256, U256};
+
+use noir_mip::cache::{CacheConfig, ProofCache};
+use noir_mip::checkpoints::{Checkpoint, Checkpoints};
+use noir_mip::cli::{
+    CacheCommand, CheckConstantsArgs, CheckpointCommand, Cli, Command, CompletionsArgs,
+    DecodeHeaderArgs, DecryptArgs, DemoArgs, DumpHoldersArgs, DumpSlotsArgs, ErrorFormat,
+    ExplainProofArgs, GenArgs, HashArgs, InfoArgs, InspectProofArgs, LayoutArgs, LayoutFormat,
+    ManArgs, MigrateArgs, OutputFormat, RedactArgs, ReplArgs, StoreCommand, TargetCommand,
+    VerifySignatureArgs,
+};
+use noir_mip::compression::Compressor;
+use noir_mip::config::{self, Config, Target};
+use noir_mip::encoding::{self, ByteEncoding, NumericEncoding};
+use noir_mip::error::{Error, Result};
+use noir_mip::header::{self, BlockHeader};
+use noir_mip::journal::Journal;
+use noir_mip::naming::{ArtifactKey, Naming};
+#[cfg(feature = "object-store")]
+use noir_mip::object_store;
+use noir_mip::params::{Params, Value};
+use noir_mip::report::Report;
+use noir_mip::{
+    advise, analyze, cache, changes, compression, constants, demo, encryption, explain,
+    generate_balance_attestation, generate_beacon_proxy, generate_bloom, generate_both,
+    generate_bundle, generate_creation, generate_deployment, generate_multiproof, generate_nonce,
+    generate_params, generate_receipt, generate_with_quorum, generate_with_source, holders, hooks,
+    info, inspect, keccak, layout, light_client_block_hash, migrate, monomorphize, namespace,
+    partial_trie, probe, progress, raw_fields, redact, refresh, repl, request, resolve_block, rpc,
+    signing, slots, source, statement, store, summary, trie, witness,
+};
+
+/// Writes `data` to `path`, compressed as `--compression` asks and
+/// encrypted to the `--encrypt-to` recipients if there are any.
+fn write_output(args: &GenArgs, path: &Path, data: &[u8]) -> Result<()> {
+    let mut data = args.options.compressor.compress(data)?;
+    if !args.encrypt_to.is_empty() {
+        data = encryption::encrypt(&data, &args.encrypt_to)?;
+    }
+    fs::write(path, &data)?;
+    progress_written(&path.display().to_string(), data.len());
+    Ok(())
+}
+
+/// Reports the file (or object) `path` of `bytes` bytes to `--progress`.
+fn progress_written(path: &str, bytes: usize) {
+    progress::event(
+        "written",
+        serde_json::json!({ "path": path, "bytes": bytes }),
+    );
+}
+
+fn raw_json(args: &GenArgs, params: &Params) -> Result<String> {
+    let raw = serde_json::Value::Object(raw_fields(params, &args.options.renames));
+    serde_json::to_string_pretty(&raw)
+        .map(|json| json + "\n")
+        .map_err(|e| Error::Decode(format!("cannot serialize the raw fields: {}", e)))
+}
+
+/// Whether `path` is an `s3://` or `gs://` object storage URL rather than a
+/// local file.
+fn is_object_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("s3://") || path.starts_with("gs://"))
+}
+
+/// Uploads the witness under the object storage prefix `url`, named by its
+/// witness hash: `<hash>/<stem>.toml` (or `.nr`, `.json`, `.bin`) holds the
+/// first of `outputs` and the others take their format's own extension,
+/// `<hash>/meta.json` holds the metadata and, with `raw`,
+/// `<hash>/<stem>.raw.json` the unpadded fields.
+#[cfg(feature = "object-store")]
+async fn upload_witness(
+    args: &GenArgs,
+    url: &Path,
+    params: &Params,
+    stem: &str,
+    outputs: &[(OutputFormat, Vec<u8>)],
+    raw: bool,
+    report: &mut Report,
+) -> Result<()> {
+    let location = object_store::Location::parse(&url.to_string_lossy())?;
+    let mut objects = Vec::new();
+    for (i, (format, text)) in outputs.iter().enumerate() {
+        let extension = match (i, format) {
+            (0, OutputFormat::Eip1186 | OutputFormat::CircomJson | OutputFormat::KvJson) => "json",
+            _ => format.extension(),
+        };
+        let content_type = match format {
+            OutputFormat::Binary => "application/octet-stream",
+            _ => "text/plain",
+        };
+        objects.push((
+            format!("{}.{}", stem, extension),
+            text.clone(),
+            content_type,
+        ));
+    }
+    if raw {
+        objects.push((
+            format!("{}.raw.json", stem),
+            raw_json(args, params)?.into_bytes(),
+            "application/json",
+        ));
+    }
+    objects.push((
+        "meta.json".to_string(),
+        params.meta_json().into_bytes(),
+        "application/json",
+    ));
+    let hash = hex::encode(params.witness_hash());
+    for (name, body, content_type) in objects {
+        let name = format!("{}/{}", hash, name);
+        let (body, content_type) = if args.encrypt_to.is_empty() {
+            (body, content_type)
+        } else {
+            (
+                encryption::encrypt(&body, &args.encrypt_to)?,
+                "application/octet-stream",
+            )
+        };
+        let bytes = body.len();
+        location.put(&name, body, content_type).await?;
+        let object = location.url(&name);
+        eprintln!("Uploaded {}", object);
+        progress_written(&object, bytes);
+        report.add_output(Path::new(&object));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "object-store"))]
+async fn upload_witness(
+    _: &GenArgs,
+    url: &Path,
+    _: &Params,
+    _: &str,
+    _: &[(OutputFormat, Vec<u8>)],
+    _: bool,
+    _: &mut Report,
+) -> Result<()> {
+    Err(Error::Unsupported(format!(
+        "this build cannot upload to {}; rebuild with --features object-store",
+        url.display()
+    )))
+}
+
+/// Prints how `params` differ from the witness an earlier run left at
+/// `path`, if it left one this version can read.
+fn print_changes(path: &Path, params: &Params) {
+    let Some(previous) = compression::read(path)
+        .ok()
+        .and_then(|data| witness::parse_bytes(&data).ok())
+    else {
+        return;
+    };
+    let changes = changes::summarize(&previous, params);
+    if changes.is_empty() {
+        eprintln!("No changes since the previous {}", path.display());
+        return;
+    }
+    eprintln!("Changes since the previous {}:", path.display());
+    for change in changes {
+        eprintln!("  {}", change);
+    }
+}
+
+/// Completes `report` with the run's `outcome`, writes it to `path` and
+/// posts it to `notify_url`, as requested. A failed notification fails a
+/// run that otherwise succeeded.
+async fn close_report(
+    mut report: Report,
+    started: Instant,
+    outcome: Result<()>,
+    path: Option<&Path>,
+    notify_url: Option<&str>,
+    proof_cache: bool,
+) -> Result<()> {
+    if path.is_none() && notify_url.is_none() {
+        return outcome;
+    }
+    if proof_cache {
+        report.set_proof_cache(cache::take_stats());
+    }
+    report.complete(started.elapsed(), &outcome, rpc::take_calls());
+    if let Some(path) = path {
+        report.write(path)?;
+    }
+    if let Some(url) = notify_url {
+        match (report.notify(url).await, &outcome) {
+            (Err(e), Ok(())) => return Err(e),
+            (Err(e), Err(_)) => eprintln!("Warning: {}", e),
+            (Ok(()), _) => {}
+        }
+    }
+    outcome
+}
+
+/// `params` in `format`.
+fn render(args: &GenArgs, format: OutputFormat, params: &Params) -> Result<Vec<u8>> {
+    let options = witness::RenderOptions {
+        block: args.options.block,
+        namespaced: args.namespaced,
+        numeric_encoding: args.options.numeric_encoding,
+    };
+    witness::render(params, format, &options)
+}
+
+/// `params` in each of the formats of `args`.
+fn render_all(args: &GenArgs, params: &Params) -> Result<Vec<(OutputFormat, Vec<u8>)>> {
+    args.formats()
+        .iter()
+        .map(|&format| Ok((format, render(args, format, params)?)))
+        .collect()
+}
+
+/// The files the formats of `args` are written to for `--out` (or
+/// `--verify-out`) `path`: the first to `path` itself, each other beside it
+/// under its format's extension.
+fn output_paths(args: &GenArgs, path: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![path.to_path_buf()];
+    for format in &args.formats()[1..] {
+        let sibling = path.with_extension(format.extension());
+        if paths.contains(&sibling) {
+            return Err(Error::Config(format!(
+                "two --format values would both be written to {}; give each format once, and \
+                 --out an extension no other format takes",
+                sibling.display()
+            )));
+        }
+        paths.push(sibling);
+    }
+    Ok(paths)
+}
+
+/// Generates the parameters and writes them to `args.out` (or stdout),
+/// reporting the run to `args.report` when one is requested.
+async fn emit(args: &GenArgs, prove: bool, command: &str) -> Result<()> {
+    let started = Instant::now();
+    // A blinded slot stays out of the report and the hook's variables.
+    let slot = args.options.slot.filter(|_| !args.options.blind_slot);
+    let mut report = Report::new(
+        command,
+        &args.options.rpc_url,
+        args.options.block,
+        args.options.account,
+        slot,
+    );
+    let outcome = async {
+        if args.writes(OutputFormat::NoirTest) && !prove {
+            return Err(Error::Unsupported(
+                "a Noir test calls main with every input; use gen_prove_params".to_string(),
+            ));
+        }
+        if args.writes(OutputFormat::NoirTest) && args.options.byte_encoding == ByteEncoding::Base64
+        {
+            return Err(Error::Unsupported(
+                "Noir has no base64 literals; use --byte-encoding decimal or hex".to_string(),
+            ));
+        }
+        if args.writes(OutputFormat::Binary) && args.namespaced {
+            return Err(Error::Unsupported(
+                "the binary container groups the fields into sections already; drop --namespaced"
+                    .to_string(),
+            ));
+        }
+        if (args.writes(OutputFormat::CircomJson) || args.writes(OutputFormat::KvJson))
+            && (args.namespaced || args.options.byte_encoding != ByteEncoding::Decimal)
+        {
+            return Err(Error::Unsupported(
+                "--format circom-json and kv-json write the fields flat in their own encoding; \
+                 they do not take --namespaced or --byte-encoding"
+                    .to_string(),
+            ));
+        }
+        if args.options.toml_wrap.is_some() && !args.writes(OutputFormat::Toml) {
+            return Err(Error::Unsupported(
+                "--toml-wrap lays out TOML arrays; it applies to the toml format".to_string(),
+            ));
+        }
+        if args.writes(OutputFormat::NoirTest) && args.namespaced {
+            return Err(Error::Unsupported(
+                "a Noir test passes the fields to main one by one; --namespaced applies to \
+                 the toml and json formats"
+                    .to_string(),
+            ));
+        }
+        if args.options.self_check && !prove {
+            return Err(Error::Unsupported(
+                "--self-check evaluates the proofs; use gen_prove_params".to_string(),
+            ));
+        }
+        if args.options.block_range.is_some()
+            && (args.options.self_check || args.writes(OutputFormat::Eip1186))
+        {
+            return Err(Error::Unsupported(
+                "a --block-range batch prefixes each block's fields; --self-check and --format \
+                 eip1186 take one witness"
+                    .to_string(),
+            ));
+        }
+        if args.options.uncles && (args.options.self_check || args.writes(OutputFormat::Eip1186)) {
+            return Err(Error::Unsupported(
+                "an --uncles batch prefixes each block's fields; --self-check and --format \
+                 eip1186 take one witness"
+                    .to_string(),
+            ));
+        }
+        if args.writes(OutputFormat::Eip1186) && !prove {
+            return Err(Error::Unsupported(
+                "an eth_getProof response carries the proofs; use gen_prove_params".to_string(),
+            ));
+        }
+        if args.writes(OutputFormat::Eip1186)
+            && (args.namespaced
+                || !args.options.renames.is_empty()
+                || args.verify_out.is_some()
+                || args.options.blind_slot)
+        {
+            return Err(Error::Unsupported(
+                "--format eip1186 writes the proof response as providers return it; it does \
+                 not take --namespaced, --rename, --verify-out or --blind-slot"
+                    .to_string(),
+            ));
+        }
+        if args.formats().len() > 1 && args.out.is_none() {
+            return Err(Error::Config(
+                "several --format values are written beside --out; pass --out to name the \
+                 files"
+                    .to_string(),
+            ));
+        }
+        for path in [&args.out, &args.verify_out].into_iter().flatten() {
+            if !is_object_url(path) {
+                output_paths(args, path)?;
+            }
+        }
+        if args.out.is_none() && progress::enabled() {
+            return Err(Error::Config(
+                "--progress jsonl writes its events to stdout; pass --out to write the \
+                 parameters to a file"
+                    .to_string(),
+            ));
+        }
+        if args.verify_out.is_some() && !prove {
+            return Err(Error::Unsupported(
+                "--verify-out adds the verifier parameters to gen_prove_params; use --out here"
+                    .to_string(),
+            ));
+        }
+        if args.options.emit_monomorphized_nr.is_some() && !prove {
+            return Err(Error::Unsupported(
+                "--emit-monomorphized-nr sizes the circuit to the proofs, which the verifier \
+                 parameters leave out; use gen_prove_params"
+                    .to_string(),
+            ));
+        }
+        let template = match &args.options.emit_monomorphized_nr {
+            Some(_) => Some(fs::read_to_string(&args.nr_template)?),
+            None => None,
+        };
+        let (params, verifier) = match &args.verify_out {
+            Some(_) => {
+                let (params, verifier) = generate_both(&args.options).await?;
+                (params, Some(verifier))
+            }
+            None => (generate_params(&args.options, prove).await?, None),
+        };
+        if args.options.self_check {
+            let steps = statement::evaluate(&params, &args.options.renames);
+            eprint!("{}", statement::render(&steps));
+            statement::check(&steps)?;
+            eprintln!("PASS");
+        }
+        report.add_witness(None, &params);
+        let outputs = render_all(args, &params)?;
+        let stem = if prove { "Prover" } else { "Verifier" };
+        match &args.out {
+            Some(path) if is_object_url(path) => {
+                upload_witness(
+                    args,
+                    path,
+                    &params,
+                    stem,
+                    &outputs,
+                    args.emit_raw,
+                    &mut report,
+                )
+                .await?
+            }
+            Some(path) => {
+                if args.formats()[0] != OutputFormat::NoirTest {
+                    print_changes(path, &params);
+                }
+                for ((_, text), path) in outputs.iter().zip(output_paths(args, path)?) {
+                    write_output(args, &path, text)?;
+                    report.add_output(&path);
+                }
+                if args.emit_raw {
+                    let raw_path = path.with_extension("raw.json");
+                    write_output(args, &raw_path, raw_json(args, &params)?.as_bytes())?;
+                    report.add_output(&raw_path);
+                }
+            }
+            None if args.print_full || !std::io::stdout().is_terminal() => {
+                std::io::stdout().write_all(&outputs[0].1)?
+            }
+            None => {
+                print!("{}", summary::render(&params));
+                eprintln!(
+                    "Pass --print-full to print the parameters, or --out to write them to a file"
+                );
+            }
+        }
+        if let (Some(path), Some(verifier)) = (&args.verify_out, &verifier) {
+            let outputs = render_all(args, verifier)?;
+            if is_object_url(path) {
+                upload_witness(
+                    args,
+                    path,
+                    verifier,
+                    "Verifier",
+                    &outputs,
+                    false,
+                    &mut report,
+                )
+                .await?;
+            } else {
+                for ((_, text), path) in outputs.iter().zip(output_paths(args, path)?) {
+                    write_output(args, &path, text)?;
+                    report.add_output(&path);
+                }
+            }
+        }
+        if let (Some(path), Some(template)) = (&args.options.emit_monomorphized_nr, &template) {
+            let depths = monomorphize::Depths::recorded(&params).ok_or_else(|| {
+                Error::Unsupported("the witness records no monomorphized depths".to_string())
+            })?;
+            fs::write(path, monomorphize::rewrite(template, &depths)?)?;
+            eprintln!(
+                "Wrote {} sized to an account proof depth of {}{}",
+                path.display(),
+                depths.account,
+                depths.storage.map_or_else(String::new, |depth| format!(
+                    " and a storage proof depth of {}",
+                    depth
+                ))
+            );
         }
+        if let (Some(hook), Some(out)) = (&args.post_hook, &args.out) {
+            let values = hook_values(out, args.options.block, args.options.account, slot, &params);
+            hooks::run(hook, &values)?;
+        }
+        Ok(())
     }
+    .await;
+    close_report(
+        report,
+        started,
+        outcome,
+        args.report.as_deref(),
+        args.notify_url.as_deref(),
+        args.options.proof_cache.is_some(),
+    )
+    .await
 }
 
-fn keccak256(data: &[u8]) -> [u8; 32] {
-    let mut keccak = Keccak::v256();
-    let mut result = [0u8; 32];
-    keccak.update(data);
-    keccak.finalize(&mut result);
-    result
+/// Fetches everything a real run would and prints the shape of each
+/// parameter instead of its data. The light client, reorg and signing steps
+/// are skipped.
+async fn dry_run(args: &GenArgs, prove: bool) -> Result<()> {
+    let params = generate_with_quorum(&args.options, prove).await?;
+    println!(
+        "Dry run: block {}, account {:?}, slot {}",
+        args.options.block,
+        args.options.account,
+        match (args.options.slot, args.options.blind_slot) {
+            (Some(_), true) => "blinded".to_string(),
+            (Some(slot), false) => format!("{:?}", slot),
+            (None, _) => "none".to_string(),
+        }
+    );
+    if let Some(range) = args.options.block_range {
+        println!(
+            "Block range: {} block(s) from {} to {} every {}; only the first is fetched",
+            range.blocks(args.options.stride).len(),
+            range.lo,
+            range.hi,
+            args.options.stride
+        );
+    }
+    println!(
+        "Providers: {} queried, eth_getProof served",
+        args.options.rpc_url.len()
+    );
+    println!("Parameters:");
+    for (name, value) in params.fields.iter() {
+        println!("  {:<36} {}", name, value.shape());
+    }
+    println!("Metadata:");
+    for (name, value) in params.meta.iter() {
+        println!("  {:<36} {}", name, value.shape());
+    }
+    let skipped: Vec<&str> = [
+        (
+            args.options.beacon_api.is_some(),
+            "light client verification",
+        ),
+        (
+            args.options.reorg_check
+                || args.options.confirmations > 0
+                || args.options.wait_finalized,
+            "reorg check",
+        ),
+        (args.options.sign_key.is_some(), "signing"),
+    ]
+    .iter()
+    .filter(|(requested, _)| *requested)
+    .map(|(_, step)| *step)
+    .collect();
+    if !skipped.is_empty() {
+        println!("Skipped: {}", skipped.join(", "));
+    }
+    Ok(())
 }
 
-fn split_rlp_by_state_root(
-    rlp_data: &[u8],
-    state_root: &[u8],
-) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
-    if let Some(start) = find_subarray(&rlp_data, &state_root) {
-        let rlp_head = rlp_data[..start].to_vec();
-        let state_root_bytes = rlp_data[start..start + 32].to_vec();
-        let rlp_tail = rlp_data[start + 32..].to_vec();
+/// Number of slot batches `dump-slots` fetches concurrently.
+const DUMP_SLOTS_CONCURRENCY: usize = 8;
 
-        Some((rlp_head, state_root_bytes, rlp_tail))
+/// Journal of the slots `dump-slots` has written, kept in the prove directory.
+const JOURNAL_FILE: &str = ".noir-mip-journal";
+
+/// Prints `params` as TOML, streaming it to stdout under `low_memory`
+/// rather than rendering the whole file first.
+fn print_toml(params: &Params, low_memory: bool) -> Result<()> {
+    if low_memory {
+        params.write_toml(std::io::stdout().lock())?;
     } else {
-        None
+        print!("{}", params.to_toml());
     }
+    Ok(())
 }
 
-fn find_subarray(array: &[u8], subarray: &[u8]) -> Option<usize> {
-    array
-        .windows(subarray.len())
-        .position(|window| window == subarray)
+async fn dump_slots(args: &DumpSlotsArgs) -> Result<()> {
+    let started = Instant::now();
+    let mut report = Report::new(
+        "dump-slots",
+        std::slice::from_ref(&args.rpc_url),
+        args.block,
+        args.account,
+        None,
+    );
+    let outcome = dump_slots_into(args, &mut report).await;
+    close_report(
+        report,
+        started,
+        outcome,
+        args.report.as_deref(),
+        args.notify_url.as_deref(),
+        args.proof_cache.is_some(),
+    )
+    .await
 }
 
-fn rlp_encode_block(block: &Block<H256>) -> Vec<u8> {
-    let mut rlp_stream = RlpStream::new();
+async fn dump_slots_into(args: &DumpSlotsArgs, report: &mut Report) -> Result<()> {
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url)?);
+    let slots = slots::enumerate(&web3, args.account, args.block, args.limit).await?;
+    eprintln!(
+        "{} occupied slot(s) in {:?} at block {}",
+        slots.len(),
+        args.account,
+        args.block
+    );
+    let Some(prove_dir) = &args.prove_dir else {
+        if progress::enabled() {
+            return Err(Error::Config(
+                "--progress jsonl writes its events to stdout; pass --prove-dir to write the \
+                 witnesses to files"
+                    .to_string(),
+            ));
+        }
+        for slot in &slots {
+            println!("{:?}", slot);
+        }
+        return Ok(());
+    };
+    let slots: Vec<(H256, String)> = slots
+        .into_iter()
+        .map(|slot| (slot, format!("{:?}", slot)))
+        .collect();
+    prove_slots(args, &web3, prove_dir, &slots, report).await
+}
 
-    let mut num_items = 15;
+/// Writes the witness of each of `slots` to `<name>.toml` in `prove_dir`,
+/// or to its hashed name under `--naming hash`, fetching them in batches and
+/// journaling them by slot for `--resume`. A file is written whole under a
+/// temporary name and then renamed, so a hashed name that exists is complete
+/// and is skipped on `--resume` too, even when the journal is lost.
+async fn prove_slots(
+    args: &DumpSlotsArgs,
+    web3: &web3::Web3<rpc::Client>,
+    prove_dir: &Path,
+    slots: &[(H256, String)],
+    report: &mut Report,
+) -> Result<()> {
+    fs::create_dir_all(prove_dir)?;
+    let hashed: Vec<(H256, String)>;
+    let slots = match args.naming {
+        Naming::Key => slots,
+        Naming::Hash => {
+            let chain_id = web3.eth().chain_id().await?.as_u64();
+            hashed = slots
+                .iter()
+                .map(|&(slot, _)| {
+                    let options = store::variant(&args.slot_args(slot), true);
+                    let key = ArtifactKey {
+                        chain_id,
+                        block: args.block,
+                        account: args.account,
+                        slot: Some(slot),
+                        format: OutputFormat::Toml,
+                        options: &options,
+                    };
+                    (slot, key.name())
+                })
+                .collect();
+            &hashed
+        }
+    };
+    let mut journal = Journal::open(
+        &prove_dir.join(JOURNAL_FILE),
+        args.block,
+        args.account,
+        args.resume,
+    )?;
+    let pending: Vec<(H256, &str)> = slots
+        .iter()
+        .filter(|(slot, name)| {
+            let written = args.naming == Naming::Hash
+                && args.resume
+                && prove_dir.join(format!("{}.toml", name)).exists();
+            !journal.is_done(slot) && !written
+        })
+        .map(|(slot, name)| (*slot, name.as_str()))
+        .collect();
+    if pending.len() < slots.len() {
+        eprintln!(
+            "Resuming: {} slot(s) already written",
+            slots.len() - pending.len()
+        );
+    }
+    let mut batches = stream::iter(pending.chunks(args.batch_size))
+        .map(|chunk| async move {
+            let keys = chunk
+                .iter()
+                .map(|(slot, _)| U256::from_big_endian(&slot.0))
+                .collect();
+            let batch = source::Batch::new(web3, args.account, keys, args.block);
+            let batch = &batch;
+            future::join_all(chunk.iter().map(|&(slot, name)| async move {
+                (
+                    slot,
+                    name,
+                    generate_with_source(batch, &args.slot_args(slot), true).await,
+                )
+            }))
+            .await
+        })
+        .buffered(DUMP_SLOTS_CONCURRENCY);
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+    let mut written = slots.len() - pending.len();
+    loop {
+        let next = tokio::select! {
+            next = batches.next() => next,
+            _ = &mut interrupted => {
+                journal.flush()?;
+                return Err(Error::Interrupted(format!(
+                    "{} of {} slot(s) written; rerun with --resume to continue",
+                    written,
+                    slots.len()
+                )));
+            }
+        };
+        let Some(batch) = next else {
+            break;
+        };
+        for (slot, name, params) in batch {
+            let params = params?;
+            let path = prove_dir.join(format!("{}.toml", name));
+            let data = args.compressor.compress(params.to_toml().as_bytes())?;
+            let partial = prove_dir.join(format!(".{}.toml.partial", name));
+            fs::write(&partial, &data)?;
+            fs::rename(&partial, &path)?;
+            journal.record(slot)?;
+            written += 1;
+            report.add_witness(Some(slot), &params);
+            report.add_output(&path);
+            match progress::enabled() {
+                true => progress_written(&path.display().to_string(), data.len()),
+                false => println!("{}", path.display()),
+            }
+            if let Some(hook) = &args.post_hook {
+                let values = hook_values(&path, args.block, args.account, Some(slot), &params);
+                hooks::run(hook, &values)?;
+            }
+        }
+    }
+    if let Some(hook) = &args.post_batch_hook {
+        let values = [
+            ("out_dir", prove_dir.display().to_string()),
+            ("block", args.block.to_string()),
+            ("account", format!("{:?}", args.account)),
+            ("count", written.to_string()),
+        ];
+        hooks::run(hook, &values)?;
+    }
+    Ok(())
+}
 
-    if let Some(_) = block.base_fee_per_gas {
-        num_items += 1;
+async fn dump_holders(args: &DumpHoldersArgs) -> Result<()> {
+    let started = Instant::now();
+    let mut report = Report::new(
+        "dump-holders",
+        std::slice::from_ref(&args.rpc_url),
+        args.block,
+        args.token,
+        None,
+    );
+    let outcome = dump_holders_into(args, &mut report).await;
+    close_report(
+        report,
+        started,
+        outcome,
+        args.report.as_deref(),
+        args.notify_url.as_deref(),
+        args.proof_cache.is_some(),
+    )
+    .await
+}
+
+async fn dump_holders_into(args: &DumpHoldersArgs, report: &mut Report) -> Result<()> {
+    if args.log_range.hi > args.block {
+        return Err(Error::Config(format!(
+            "--log-range ends at block {}, after block {} the balances are proven at; a holder \
+             touched later would be proven without that transfer",
+            args.log_range.hi, args.block
+        )));
     }
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url)?);
+    let mut holders = holders::scan(&web3, args.token, args.log_range, args.log_chunk).await?;
+    if let Some(limit) = args.limit {
+        holders.truncate(limit);
+    }
+    let slots: Vec<(H256, String)> = holders
+        .iter()
+        .map(|holder| {
+            let slot = slots::mapping_slot(args.balance_slot, holder.as_bytes());
+            (slot, format!("{:?}", holder))
+        })
+        .collect();
+    let Some(prove_dir) = &args.prove_dir else {
+        if progress::enabled() {
+            return Err(Error::Config(
+                "--progress jsonl writes its events to stdout; pass --prove-dir to write the \
+                 witnesses to files"
+                    .to_string(),
+            ));
+        }
+        for (slot, holder) in &slots {
+            println!("{} {:?}", holder, slot);
+        }
+        return Ok(());
+    };
+    prove_slots(&args.slots_args(), &web3, prove_dir, &slots, report).await
+}
 
-    rlp_stream
-        .begin_list(num_items)
-        .append(&block.parent_hash)
-        .append(&block.uncles_hash)
-        .append(&block.author)
-        .append(&block.state_root)
-        .append(&block.transactions_root)
-        .append(&block.receipts_root)
-        .append(&bloom_to_bytes(block.logs_bloom))
-        .append(&block.difficulty)
-        .append(&block.number.unwrap_or_default())
-        .append(&block.gas_limit)
-        .append(&block.gas_used)
-        .append(&block.timestamp)
-        .append(&block.extra_data.0)
-        .append(&block.mix_hash.unwrap_or_default())
-        .append(&block.nonce.unwrap_or_default());
+/// The placeholders of a `--post-hook` run for the witness `params`,
+/// written to `out`.
+fn hook_values(
+    out: &Path,
+    block: u64,
+    account: H160,
+    slot: Option<H256>,
+    params: &Params,
+) -> Vec<(&'static str, String)> {
+    let out_dir = match out.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.display().to_string(),
+        _ => ".".to_string(),
+    };
+    vec![
+        ("out", out.display().to_string()),
+        ("out_dir", out_dir),
+        ("block", block.to_string()),
+        ("account", format!("{:?}", account)),
+        (
+            "slot",
+            slot.map(|slot| format!("{:?}", slot)).unwrap_or_default(),
+        ),
+        (
+            "witness_hash",
+            format!("0x{}", hex::encode(params.witness_hash())),
+        ),
+    ]
+}
 
-    if let Some(base_fee_per_gas) = block.base_fee_per_gas {
-        if base_fee_per_gas != U256::zero() {
-            rlp_stream.append(&base_fee_per_gas);
+fn verify_signature(args: &VerifySignatureArgs) -> Result<()> {
+    let params = witness::parse_bytes(&compression::read(&args.file)?)?;
+    let expected = args
+        .public_key
+        .as_deref()
+        .map(signing::read_public_key)
+        .transpose()?;
+    let signer = signing::verify(&params, expected.as_deref())?;
+    println!("Valid signature by 0x{}", hex::encode(signer));
+    Ok(())
+}
+
+fn decrypt_file(args: &DecryptArgs) -> Result<()> {
+    let identities = encryption::read_identities(&args.identity)?;
+    let plaintext = encryption::decrypt(&fs::read(&args.file)?, &identities)?;
+    match &args.out {
+        Some(path) => fs::write(path, plaintext)?,
+        None => std::io::stdout().write_all(&plaintext)?,
+    }
+    Ok(())
+}
+
+/// Prints each file's witness hash like `sha256sum`, failing when it
+/// disagrees with the `meta.witness_hash` recorded at generation.
+fn hash_files(args: &HashArgs) -> Result<()> {
+    for file in &args.files {
+        let params = witness::parse_bytes(&compression::read(file)?)?;
+        let hash = params.witness_hash();
+        let recorded = match params.meta.get("witness_hash") {
+            Some(Value::Str(value)) => Some(
+                hex::decode(value.trim_start_matches("0x"))
+                    .map_err(|e| Error::Decode(format!("invalid meta.witness_hash: {}", e)))?,
+            ),
+            Some(Value::Hex(bytes)) => Some(bytes.clone()),
+            _ => None,
+        };
+        if recorded.is_some_and(|recorded| recorded != hash) {
+            return Err(Error::Verification(format!(
+                "{} does not match its recorded witness_hash; it changed after generation",
+                file.display()
+            )));
         }
+        println!("0x{}  {}", hex::encode(hash), file.display());
     }
+    Ok(())
+}
 
-    let rlp_data = rlp_stream.as_raw();
-    let hash = keccak256(&rlp_data);
-    assert_eq!(
-        block.hash.unwrap(),
-        hash.into(),
-        "Rlp_encode_block: Block hash mismatch!"
+async fn run_demo(args: &DemoArgs) -> Result<()> {
+    let gen = demo::args();
+    let prover = generate_with_source(&demo::Recorded, &gen, true).await?;
+    let verifier = generate_with_source(&demo::Recorded, &gen, false).await?;
+    demo::verify(&prover)?;
+    eprintln!(
+        "Generated the parameters of block {} from recorded responses and checked them",
+        demo::BLOCK
     );
 
-    rlp_stream.out().to_vec()
+    let out_dir = &args.out_dir;
+    fs::create_dir_all(out_dir.join("src"))?;
+    fs::write(out_dir.join("Nargo.toml"), demo::NARGO_TOML)?;
+    fs::write(out_dir.join("src").join("main.nr"), demo::CIRCUIT)?;
+    fs::write(out_dir.join("Prover.toml"), prover.to_toml())?;
+    fs::write(out_dir.join("Verifier.toml"), verifier.to_toml())?;
+    eprintln!(
+        "Wrote the circuit and its parameters to {}",
+        out_dir.display()
+    );
+    #[cfg(feature = "reference-circuit")]
+    let reference_dir = {
+        let dir = out_dir.join("reference");
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(dir.join("Nargo.toml"), demo::REFERENCE_NARGO_TOML)?;
+        fs::write(dir.join("src").join("main.nr"), demo::REFERENCE_CIRCUIT)?;
+        fs::write(dir.join("Prover.toml"), prover.to_toml())?;
+        eprintln!(
+            "Wrote the reference circuit and its parameters to {}",
+            dir.display()
+        );
+        dir
+    };
+    if args.no_nargo {
+        return Ok(());
+    }
+
+    // The reference circuit goes first: it needs no dependency to fetch,
+    // and a layout it rejects is the generator's fault, not the circuit's.
+    #[cfg(feature = "reference-circuit")]
+    if !nargo_execute(&reference_dir, "the reference circuit")? {
+        return Ok(());
+    }
+    nargo_execute(out_dir, "the circuit")?;
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> web3::Result<()> {
-    dotenv().ok();
-    let args: Vec<String> = env::args().collect();
-    if args.len() == 1 {
-        panic!("No arguments passed.");
-    }
-
-    let provider_url = env::var("MAINNET_RPC").unwrap();
-    let http = Http::new(&provider_url)?;
-    let web3 = web3::Web3::new(http);
-    let block_number = env::var("BLOCK_NUMBER").unwrap();
-    let block_number = BlockNumber::Number(U64::from_str_radix(&block_number, 10).unwrap());
-    let block = web3
-        .eth()
-        .block(web3::types::BlockId::Number(block_number))
-        .await?;
-
-    if let Some(block) = block {
-        let target_account: H160 =
-            H160::from_slice(&hex::decode(env::var("TARGET_ACCOUNT").unwrap()).unwrap());
-        let slot: H256 = H256::from_slice(&hex::decode(env::var("STORAGE_SLOT").unwrap()).unwrap());
-        let slot_u256 = U256::from_big_endian(&slot.0);
-
-        let mut rlp_encoded_block = rlp_encode_block(&block);
-
-        let rlp = Rlp::new(&rlp_encoded_block);
-
-        let state_root = match rlp.at(3) {
-            Ok(item) => item
-                .data()
-                .map_err(|e| web3::Error::Decoder(format!("Failed to decode: {:?}", e)))?
-                .to_vec(),
-            Err(_) => {
-                return Err(web3::Error::Decoder(
-                    "Failed to decode RLP at index 3".to_string(),
-                ))
+/// Runs `nargo execute` in `dir`, returning false when nargo is not
+/// installed.
+fn nargo_execute(dir: &Path, what: &str) -> Result<bool> {
+    match process::Command::new("nargo")
+        .arg("execute")
+        .current_dir(dir)
+        .status()
+    {
+        Ok(status) if status.success() => eprintln!("nargo executed {}", what),
+        Ok(status) => {
+            return Err(Error::Circuit(format!(
+                "nargo execute failed on {} in {} ({})",
+                what,
+                dir.display(),
+                status
+            )))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            eprintln!(
+                "nargo is not installed; run `nargo execute` in {} once it is",
+                dir.display()
+            );
+            return Ok(false);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "differential")]
+async fn run_differential(args: &noir_mip::cli::DifferentialArgs) -> Result<()> {
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url)?);
+    let from = match args.from {
+        Some(from) => from,
+        None => (web3.eth().block_number().await?.as_u64() + 1).saturating_sub(args.count),
+    };
+    noir_mip::differential::run(
+        &web3,
+        from..=from + args.count - 1,
+        args.account,
+        &args.slots,
+    )
+    .await
+}
+
+fn print_layout(args: &LayoutArgs) -> Result<()> {
+    let contract = layout::contract(args)?;
+    for warning in contract.over_budget(args) {
+        eprintln!("Warning: {}", warning);
+    }
+    match args.format {
+        LayoutFormat::Text => print!("{}", contract.to_text()),
+        LayoutFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&contract).map_err(|e| Error::Decode(e.to_string()))?
+        ),
+    }
+    Ok(())
+}
+
+fn print_info(args: &InfoArgs) -> Result<()> {
+    let info = info::Info::current();
+    match args.json {
+        true => println!(
+            "{}",
+            serde_json::to_string_pretty(&info).map_err(|e| Error::Decode(e.to_string()))?
+        ),
+        false => print!("{}", info.render()),
+    }
+    Ok(())
+}
+
+fn check_constants(args: &CheckConstantsArgs) -> Result<()> {
+    let globals = constants::parse_globals(&fs::read_to_string(&args.nr)?);
+    let mut checked = 0;
+    let mut differing = 0;
+    for constant in constants::CONSTANTS {
+        let Some(global) = globals
+            .iter()
+            .find(|global| constant.names.contains(&global.name.as_str()))
+        else {
+            continue;
+        };
+        checked += 1;
+        match global.value {
+            Some(value) if value == constant.value => {
+                println!("  {} = {}", global.name, value);
+                continue;
             }
+            Some(value) => println!("- {} = {} (circuit)", global.name, value),
+            None => println!("- {} = ? (circuit, not an integer expression)", global.name),
+        }
+        println!("+ {} = {} (generator)", global.name, constant.value);
+        differing += 1;
+    }
+    if checked == 0 {
+        return Err(Error::Circuit(format!(
+            "{} declares none of the generator's size constants",
+            args.nr.display()
+        )));
+    }
+    if differing > 0 {
+        return Err(Error::Circuit(format!(
+            "{} of {} constant(s) in {} differ from the generator; the circuit will reject \
+             its parameters",
+            differing,
+            checked,
+            args.nr.display()
+        )));
+    }
+    Ok(())
+}
+
+fn decode_header(args: &DecodeHeaderArgs) -> Result<()> {
+    let text = fs::read_to_string(&args.file)?;
+    let text: String = text.split_whitespace().collect();
+    let data = hex::decode(text.trim_start_matches("0x"))
+        .map_err(|e| Error::Decode(format!("{} is not hex: {}", args.file.display(), e)))?;
+
+    let len = header::encoded_len(&data)?;
+    let (rlp, padding) = data.split_at(len);
+    println!("Header: {} bytes", len);
+    if !padding.is_empty() {
+        let nonzero = padding.iter().filter(|&&byte| byte != 0).count();
+        println!(
+            "Padding: {} bytes from offset {} ({} non-zero)",
+            padding.len(),
+            len,
+            nonzero
+        );
+    }
+
+    println!("{:>6} {:>5}  {:<26} value", "offset", "len", "field");
+    for field in header::field_spans_sealed(rlp, args.layout, args.seal_fields)? {
+        let value = if header::SCALAR_FIELDS.contains(&field.name) && field.value.len() <= 32 {
+            U256::from_big_endian(&field.value).to_string()
+        } else {
+            format!("0x{}", hex::encode(&field.value))
         };
+        println!(
+            "{:>6} {:>5}  {:<26} {}",
+            field.offset,
+            field.value.len(),
+            field.name,
+            value
+        );
+    }
+
+    let hash = H256::from(keccak::keccak256(rlp));
+    println!("Hash: {:?}", hash);
+    match BlockHeader::decode_sealed(rlp, args.layout, args.seal_fields) {
+        Ok(header) if header.hash() == hash => {}
+        Ok(_) => println!("Warning: the encoding is not canonical; re-encoding changes the hash"),
+        Err(e) => println!("Warning: not a valid header: {}", e),
+    }
+    Ok(())
+}
 
-        let (rlp_head_bytes, _, rlp_tail_bytes) =
-            split_rlp_by_state_root(&rlp_encoded_block, state_root.as_slice())
-                .expect("Failed to split RLP data");
+/// Prints what the public inputs of a proof claim and, given an endpoint,
+/// checks them against the chain.
+async fn inspect_proof(args: &InspectProofArgs) -> Result<()> {
+    let fields = inspect::public_fields(args)?;
+    let (elements, account_value_bytes) = match (&args.public_inputs, &args.proof) {
+        (Some(path), _) => (
+            inspect::read_elements(&fs::read(path)?)?,
+            args.account_value_bytes,
+        ),
+        (None, Some(path)) => (
+            inspect::read_elements(&fs::read(path)?)?,
+            Some(args.account_value_bytes.unwrap_or(70)),
+        ),
+        (None, None) => unreachable!("clap requires --public-inputs or --proof"),
+    };
+    let params = inspect::decode(
+        &elements,
+        &fields,
+        args.numeric_encoding,
+        args.value_form,
+        account_value_bytes,
+    )?;
+    print!("{}", inspect::describe(&params, args.numeric_encoding));
+    match &args.rpc_url {
+        Some(rpc_url) => {
+            let block = inspect::cross_check(args, rpc_url, &params).await?;
+            println!("Matches the chain at block {}", block);
+        }
+        None => eprintln!("Note: pass --rpc-url to check the inputs against the chain"),
+    }
+    Ok(())
+}
 
-        let hash = keccak256(&rlp_encoded_block);
-        assert_eq!(
-            block.hash.unwrap(),
-            hash.into(),
-            "Verification: Block hash mismatch!"
+fn explain_proof(args: &ExplainProofArgs) -> Result<()> {
+    let text = compression::read_to_string(&args.file)?;
+    let params = match witness::parse(&text) {
+        Ok(params) => params,
+        Err(_) => {
+            let key = args.key.as_deref().ok_or_else(|| {
+                Error::Decode("--key is required to explain a hex encoded proof".to_string())
+            })?;
+            let key = hex::decode(key.trim_start_matches("0x"))
+                .map_err(|e| Error::Decode(format!("invalid --key: {}", e)))?;
+            let text: String = text.split_whitespace().collect();
+            let proof = hex::decode(text.trim_start_matches("0x")).map_err(|e| {
+                Error::Decode(format!(
+                    "{} is neither TOML nor hex: {}",
+                    args.file.display(),
+                    e
+                ))
+            })?;
+            print!(
+                "{}",
+                explain::explain(
+                    "proof",
+                    &proof,
+                    args.node_len,
+                    None,
+                    &trie::key_nibbles(&key),
+                    args.root.as_ref().map(|root| root.as_bytes()),
+                )?
+            );
+            return Ok(());
+        }
+    };
+
+    let missing = |name: &str| Error::Decode(format!("{} has no {}", args.file.display(), name));
+    let state_root = match params.fields.get_bytes("state_root") {
+        // Generated with --root-mode state-root, without a header.
+        Some(state_root) => state_root,
+        None => {
+            let header = params
+                .fields
+                .get_bytes("block_header_rlp")
+                .ok_or_else(|| missing("block_header_rlp"))?;
+            let head_len = params
+                .fields
+                .get_int("block_header_rlp_head_len")
+                .ok_or_else(|| missing("block_header_rlp_head_len"))?
+                as usize;
+            encoding::header_padding(&params)
+                .unpad(header)
+                .and_then(|header| header.get(head_len..head_len + 32))
+                .ok_or_else(|| {
+                    Error::Decode("block_header_rlp is shorter than its head".to_string())
+                })?
+        }
+    };
+    let account_key = params
+        .fields
+        .get_bytes("account_key")
+        .ok_or_else(|| missing("account_key"))?;
+    let account = explain::explain(
+        "account_proof",
+        params
+            .fields
+            .get_bytes("account_proof")
+            .ok_or_else(|| missing("account_proof"))?,
+        args.node_len,
+        params
+            .fields
+            .get_int("account_proof_depth")
+            .map(|depth| depth as usize),
+        &trie::key_nibbles(account_key),
+        Some(state_root),
+    )?;
+    print!("{}", account);
+
+    if params.fields.get("storage_proof").is_none() {
+        // Account-only parameters, generated without a slot.
+        return Ok(());
+    }
+    let encoding = match params.meta.get("numeric_encoding") {
+        Some(Value::Str(name)) => <NumericEncoding as clap::ValueEnum>::from_str(name, false)
+            .map_err(|e| Error::Decode(format!("unknown numeric_encoding: {}", e)))?,
+        _ => NumericEncoding::default(),
+    };
+    let storage_key = params
+        .fields
+        .get("storage_key")
+        .and_then(|value| encoding.decode(value))
+        .ok_or_else(|| missing("storage_key"))?;
+    let mut storage_key_bytes = [0u8; 32];
+    storage_key.to_big_endian(&mut storage_key_bytes);
+    let storage = explain::explain(
+        "storage_proof",
+        params
+            .fields
+            .get_bytes("storage_proof")
+            .ok_or_else(|| missing("storage_proof"))?,
+        args.node_len,
+        params
+            .fields
+            .get_int("storage_proof_depth")
+            .map(|depth| depth as usize),
+        &trie::key_nibbles(&storage_key_bytes),
+        params.fields.get_bytes("storage_root"),
+    )?;
+    print!("{}", storage);
+    Ok(())
+}
+
+fn migrate_file(args: &MigrateArgs, compressor: Compressor) -> Result<()> {
+    let mut params = Params::from_toml(&compression::read_to_string(&args.file)?)?;
+    let from = migrate::migrate(&mut params)?;
+    if from == migrate::WITNESS_FORMAT_VERSION {
+        eprintln!(
+            "{} is already at witness format version {}",
+            args.file.display(),
+            from
+        );
+    } else {
+        eprintln!(
+            "Migrated {} from witness format version {} to {}",
+            args.file.display(),
+            from,
+            migrate::WITNESS_FORMAT_VERSION
         );
+    }
+    if args.in_place {
+        compressor.write(&args.file, params.to_toml().as_bytes())?;
+    } else {
+        print!("{}", params.to_toml());
+    }
+    Ok(())
+}
 
-        while rlp_encoded_block.len() < BLOCK_HEADER_RLP_BYTES {
-            rlp_encoded_block.push(0);
+fn redact_file(args: &RedactArgs, compressor: Compressor) -> Result<()> {
+    let params = witness::parse_bytes(&compression::read(&args.file)?)?;
+    let options = redact::Options {
+        keep_structure: args.keep_structure,
+        keep: args.keep.clone(),
+        redact: args.redact.clone(),
+    };
+    let (redacted, names) = redact::redact(&params, &options);
+    eprintln!(
+        "{} {} field(s) of {}: {}",
+        if args.keep_structure {
+            "Zeroed"
+        } else {
+            "Dropped"
+        },
+        names.len(),
+        args.file.display(),
+        names.join(", ")
+    );
+    if params.meta.get("signature").is_some() {
+        eprintln!("Warning: the signature covered the original fields and was removed");
+    }
+    match &args.out {
+        Some(path) => compressor.write(path, redacted.to_toml().as_bytes())?,
+        None => print!("{}", redacted.to_toml()),
+    }
+    Ok(())
+}
+
+fn print_completions(args: &CompletionsArgs) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(args.shell, &mut command, name, &mut std::io::stdout());
+}
+
+fn print_man(args: &ManArgs) -> Result<()> {
+    let command = Cli::command();
+    match &args.out_dir {
+        Some(out_dir) => {
+            fs::create_dir_all(out_dir)?;
+            clap_mangen::generate_to(command, out_dir)?;
         }
+        None => clap_mangen::Man::new(command).render(&mut std::io::stdout())?,
+    }
+    Ok(())
+}
 
-        let proof = web3
-            .eth()
-            .proof(target_account, vec![slot_u256], Some(block_number.into()))
-            .await?;
+fn config_path(config: Option<&Path>) -> Result<PathBuf> {
+    match config {
+        Some(path) => Ok(path.to_path_buf()),
+        None => config::default_path(),
+    }
+}
 
-        let unwrapped = &proof.unwrap_or_default();
+/// `--secrets`, falling back to `secrets.toml` next to the config file.
+fn secrets_path(config: Option<&Path>, secrets: Option<&Path>) -> PathBuf {
+    match secrets {
+        Some(path) => path.to_path_buf(),
+        None => config_path(config).map_or_else(
+            |_| PathBuf::from("secrets.toml"),
+            |path| config::default_secrets_path(&path),
+        ),
+    }
+}
 
-        let mut account_value_rlp_stream = RlpStream::new();
-        account_value_rlp_stream
-            .begin_list(4)
-            .append(&unwrapped.nonce)
-            .append(&unwrapped.balance)
-            .append(&unwrapped.storage_hash)
-            .append(&unwrapped.code_hash);
+/// Resolves `--target`, the `${NAME}`s of provider URLs and `--block`
+/// tags, which need the config file, the secrets and the provider, before
+/// anything is generated.
+async fn resolve(args: &mut GenArgs, config: Option<&Path>, secrets: Option<&Path>) -> Result<()> {
+    let loaded = match &args.target {
+        Some(_) => Config::load(&config_path(config)?)?,
+        None => Config::default(),
+    };
+    config::resolve_target(args, &loaded, &secrets_path(config, secrets))?;
+    if let Some(dir) = &args.options.proof_cache {
+        evict_proof_cache(dir, config)?;
+    }
+    resolve_block(&mut args.options).await
+}
 
-        let mut account_proof: Vec<Vec<u8>> = Vec::new();
+fn parse_mapping_slot(mapping: &[String]) -> Result<H256> {
+    let [index, key] = mapping else {
+        return Err(Error::Decode(
+            "--slot-mapping takes a slot index and a key".to_string(),
+        ));
+    };
+    let index = slots::parse_slot_index(index)?;
+    let key = slots::parse_mapping_key(key)?;
+    for warning in slots::check_mapping(index, &key) {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(slots::mapping_slot(index, &key))
+}
 
-        for proof in &unwrapped.account_proof {
-            let mut raw = proof.0.clone();
-            while raw.len() < PROOF_BYTES_LEN {
-                raw.push(0);
+fn manage_targets(command: &TargetCommand, config: Option<&Path>) -> Result<()> {
+    let path = config_path(config)?;
+    let mut config = Config::load(&path)?;
+    match command {
+        TargetCommand::Add(args) => {
+            let slot = match &args.slot_mapping {
+                Some(mapping) => Some(parse_mapping_slot(mapping)?),
+                None => args.slot,
+            };
+            config.targets.insert(
+                args.name.clone(),
+                Target {
+                    account: args.account,
+                    slot,
+                    rpc_url: args.rpc_url.clone(),
+                },
+            );
+            config.save(&path)?;
+            eprintln!("Saved target {} to {}", args.name, path.display());
+        }
+        TargetCommand::Remove(args) => {
+            config.target(&args.name)?;
+            config.targets.remove(&args.name);
+            config.save(&path)?;
+        }
+        TargetCommand::List => {
+            for (name, target) in &config.targets {
+                let mut line = format!("{}  {:?}", name, target.account);
+                if let Some(slot) = target.slot {
+                    line += &format!("  {:?}", slot);
+                }
+                // Templates, so no key is printed unless one was stored.
+                if !target.rpc_url.is_empty() {
+                    line += &format!("  via {}", target.rpc_url.join(","));
+                }
+                println!("{}", line);
             }
-            account_proof.push(raw);
         }
+    }
+    Ok(())
+}
 
-        while account_proof.len() < ACCOUNT_PROOF_MAX_DEPTH {
-            account_proof.push(vec![0; PROOF_BYTES_LEN]);
+/// Reads REPL lines from stdin until `quit` or the end of input. Typed at
+/// a terminal, a failed line is reported and the session goes on; piped
+/// in, the first failure ends the run with its exit code.
+async fn run_repl(args: &ReplArgs, config: Option<&Path>, secrets: Option<&Path>) -> Result<()> {
+    let rpc_url = config::interpolate(&args.rpc_url, &secrets_path(config, secrets))?;
+    let mut session = repl::Repl::new(&rpc_url);
+    session.account = args.account;
+    session.slot = args.slot;
+    if let Some(block) = args.block {
+        session.set_block(block).await?;
+    }
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    if interactive {
+        eprintln!("noir-mip repl; type `help` for the commands");
+    }
+    let mut line = String::new();
+    loop {
+        if interactive {
+            eprint!("noir-mip> ");
+            std::io::stderr().flush()?;
         }
-
-        let mut account_proof_flat_vec = Vec::new();
-        for inner_vec in account_proof {
-            for item in inner_vec {
-                account_proof_flat_vec.push(item);
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let result = match session.execute(&line).await {
+            Ok(repl::Action::Continue) => Ok(()),
+            Ok(repl::Action::Quit) => return Ok(()),
+            Ok(repl::Action::Emit { prove, out }) => {
+                async {
+                    let mut gen_args = session.gen_args(prove, out)?;
+                    resolve(&mut gen_args, config, secrets).await?;
+                    emit(&gen_args, prove, "repl").await?;
+                    if let Some(path) = &gen_args.out {
+                        eprintln!("Wrote {}", path.display());
+                    }
+                    Ok(())
+                }
+                .await
             }
+            Err(e) => Err(e),
+        };
+        match result {
+            Err(e) if interactive => eprintln!("{}", e),
+            result => result?,
         }
+    }
+}
 
-        let mut storage_proof: Vec<Vec<u8>> = Vec::new();
-
-        for proof in &unwrapped.storage_proof[0].proof {
-            let mut raw = proof.0.clone();
-            while raw.len() < PROOF_BYTES_LEN {
-                raw.push(0);
+async fn manage_checkpoints(command: &CheckpointCommand) -> Result<()> {
+    match command {
+        CheckpointCommand::Add(args) => {
+            let path = &args.file.file;
+            let chain_id = match (args.chain_id, &args.rpc_url) {
+                (Some(chain_id), _) => chain_id,
+                (None, Some(rpc_url)) => web3::Web3::new(rpc::Client::new(rpc_url)?)
+                    .eth()
+                    .chain_id()
+                    .await?
+                    .low_u64(),
+                (None, None) => unreachable!("clap requires --chain-id or --rpc-url"),
+            };
+            let (block_hash, source) = match (&args.beacon_api, args.lc_checkpoint, &args.rpc_url) {
+                (Some(beacon_api), Some(checkpoint), Some(rpc_url)) => {
+                    let (hash, _) = light_client_block_hash(
+                        rpc_url,
+                        beacon_api,
+                        checkpoint,
+                        args.block,
+                        args.lc_max_ancestry,
+                    )
+                    .await?;
+                    if let Some(vetted) = args.hash.filter(|&vetted| vetted != hash) {
+                        return Err(Error::Verification(format!(
+                            "the light client verified {:?} for block {}, not {:?}",
+                            hash, args.block, vetted
+                        )));
+                    }
+                    (hash, "light-client")
+                }
+                _ => (
+                    args.hash
+                        .expect("clap requires --hash without --beacon-api"),
+                    "manual",
+                ),
+            };
+            let mut checkpoints = Checkpoints::load(path)?;
+            let was_signed = checkpoints.add(Checkpoint {
+                chain_id,
+                block: args.block,
+                block_hash,
+                source: source.to_string(),
+                note: args.note.clone(),
+            });
+            checkpoints.save(path)?;
+            eprintln!(
+                "Recorded block {} of chain {} as {:?} ({})",
+                args.block, chain_id, block_hash, source
+            );
+            if was_signed {
+                eprintln!(
+                    "Warning: the signature of {} no longer covers it; sign it again",
+                    path.display()
+                );
             }
-            storage_proof.push(raw);
         }
-
-        while storage_proof.len() < STORAGE_PROOF_MAX_DEPTH {
-            storage_proof.push(vec![0; PROOF_BYTES_LEN]);
+        CheckpointCommand::Sign(args) => {
+            let mut checkpoints = Checkpoints::load(&args.file.file)?;
+            checkpoints.sign(&args.sign_key)?;
+            checkpoints.save(&args.file.file)?;
+            eprintln!(
+                "Signed {} checkpoint(s) as {}",
+                checkpoints.checkpoints.len(),
+                checkpoints.signer.unwrap_or_default()
+            );
+        }
+        CheckpointCommand::Verify(args) => {
+            let expected = args
+                .signer
+                .as_deref()
+                .map(signing::read_public_key)
+                .transpose()?;
+            let checkpoints = Checkpoints::load(&args.file.file)?;
+            let signer = checkpoints.verify(expected.as_deref())?;
+            println!(
+                "OK: {} checkpoint(s) signed by 0x{}",
+                checkpoints.checkpoints.len(),
+                hex::encode(signer)
+            );
+        }
+        CheckpointCommand::List(args) => {
+            for checkpoint in Checkpoints::load(&args.file)?.checkpoints {
+                let mut line = format!(
+                    "{}  {}  {:?}  {}",
+                    checkpoint.chain_id, checkpoint.block, checkpoint.block_hash, checkpoint.source
+                );
+                if !checkpoint.note.is_empty() {
+                    line += &format!("  {}", checkpoint.note);
+                }
+                println!("{}", line);
+            }
         }
+    }
+    Ok(())
+}
 
-        let mut storage_proof_flat_vec = Vec::new();
-        for inner_vec in storage_proof {
-            for item in inner_vec {
-                storage_proof_flat_vec.push(item);
+fn manage_store(command: &StoreCommand, compressor: Compressor) -> Result<()> {
+    let store = store::Store::open()?;
+    match command {
+        StoreCommand::Ls(filter) => {
+            for entry in store
+                .entries()?
+                .iter()
+                .filter(|entry| entry.matches(filter))
+            {
+                let slot = entry
+                    .key
+                    .slot
+                    .map_or_else(|| "-".to_string(), |slot| format!("{:?}", slot));
+                println!(
+                    "{}  {}  {:?}  {}  {}  0x{}",
+                    entry.key.chain_id,
+                    entry.key.block,
+                    entry.key.account,
+                    slot,
+                    entry.variant,
+                    entry.hash
+                );
+            }
+        }
+        StoreCommand::Get(args) => {
+            let text = match &args.hash {
+                Some(hash) => store.object(hash)?,
+                None => {
+                    let entry = store
+                        .entries()?
+                        .into_iter()
+                        .filter(|entry| entry.matches(&args.filter))
+                        .max_by_key(|entry| entry.modified)
+                        .ok_or_else(|| {
+                            Error::Io(std::io::Error::new(
+                                std::io::ErrorKind::NotFound,
+                                format!("no witness in {} matches", store.dir().display()),
+                            ))
+                        })?;
+                    store.object(&entry.hash)?
+                }
+            };
+            match &args.out {
+                Some(path) => compressor.write(path, text.as_bytes())?,
+                None => print!("{}", text),
             }
         }
+        StoreCommand::Gc(args) => {
+            let older_than = args
+                .older_than
+                .map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60));
+            let stats = store.gc(older_than)?;
+            eprintln!(
+                "Removed {} refs and {} witnesses ({} bytes) from {}",
+                stats.refs,
+                stats.objects,
+                stats.bytes,
+                store.dir().display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The `[cache]` policy of the config file, if there is one.
+fn cache_policy(config: Option<&Path>) -> Result<CacheConfig> {
+    match config_path(config) {
+        Ok(path) => Ok(Config::load(&path)?.cache),
+        Err(_) if config.is_none() => Ok(CacheConfig::default()),
+        Err(e) => Err(e),
+    }
+}
 
-        let storage_key = U256::from(&unwrapped.storage_proof[0].key);
-        let storage_value = U256::from(&unwrapped.storage_proof[0].value);
-        let mut storage_key_bytes = [0u8; 32];
-        let mut storage_value_bytes = [0u8; 32];
-        storage_key.to_big_endian(&mut storage_key_bytes);
-        storage_value.to_big_endian(&mut storage_value_bytes);
+/// Evicts from the proof cache at `dir` what the configured policy leaves
+/// out.
+fn evict_proof_cache(dir: &Path, config: Option<&Path>) -> Result<()> {
+    let removed = ProofCache::new(dir).gc(&cache_policy(config)?)?;
+    if removed.entries > 0 {
+        eprintln!(
+            "Evicted {} cached proofs ({} bytes) from {}",
+            removed.entries,
+            removed.bytes,
+            dir.display()
+        );
+    }
+    Ok(())
+}
 
-        if &args[1] == "gen_prove_params" {
-            // Output
-            println!("block_hash = {:?}", block.hash.unwrap().as_bytes());
-            println!("account_key = {:?}", target_account.as_bytes());
-            println!("account_value = {:?}", account_value_rlp_stream.as_raw());
-            println!("storage_key = {:?}", storage_key_bytes);
-            println!("storage_value = {:?}", storage_value_bytes);
-            println!("block_header_rlp = {:?}", rlp_encoded_block);
-            println!("block_header_rlp_head_len = {:?}", rlp_head_bytes.len());
-            println!("block_header_rlp_tail_len = {:?}", rlp_tail_bytes.len());
-            println!("storage_root = {:?}", &unwrapped.storage_hash.as_bytes());
-            println!("account_proof = {:?}", account_proof_flat_vec);
-            println!("storage_proof = {:?}", storage_proof_flat_vec);
-            println!("account_proof_depth = {:?}", &unwrapped.account_proof.len());
+fn manage_cache(command: &CacheCommand, config: Option<&Path>) -> Result<()> {
+    match command {
+        CacheCommand::Stats(args) => {
+            let usage = ProofCache::new(&args.dir).usage()?;
             println!(
-                "storage_proof_depth = {:?}",
-                &unwrapped.storage_proof[0].proof.len()
+                "{} cached proofs, {} bytes in {}",
+                usage.entries,
+                usage.bytes,
+                args.dir.display()
+            );
+        }
+        CacheCommand::Clear(args) => {
+            let removed = ProofCache::new(&args.dir).clear()?;
+            eprintln!(
+                "Removed {} cached proofs ({} bytes) from {}",
+                removed.entries,
+                removed.bytes,
+                args.dir.display()
+            );
+        }
+        CacheCommand::Gc(args) => {
+            let configured = cache_policy(config)?;
+            let policy = CacheConfig {
+                ttl_days: args.ttl_days.or(configured.ttl_days),
+                max_bytes: args.max_bytes.or(configured.max_bytes),
+            };
+            let removed = ProofCache::new(&args.dir.dir).gc(&policy)?;
+            eprintln!(
+                "Removed {} cached proofs ({} bytes) from {}",
+                removed.entries,
+                removed.bytes,
+                args.dir.dir.display()
             );
-        } else if &args[1] == "gen_verify_params" {
-            println!("account_key = {:?}", target_account.as_bytes());
-            println!("account_value = {:?}", account_value_rlp_stream.as_raw());
-            println!("block_hash = {:?}", block.hash.unwrap().as_bytes());
-            println!("storage_key = {:?}", storage_key_bytes);
-            println!("storage_value = {:?}", storage_value_bytes);
-        } else {
-            panic!("Invalid command!");
         }
-    } else {
-        eprintln!("Block not found!");
     }
+    Ok(())
+}
 
+async fn run(cli: Cli) -> Result<()> {
+    if let Some(rate) = cli.rate_limit {
+        rpc::set_rate_limit(rate, cli.rate_burst.unwrap_or(rate.ceil() as u32));
+    }
+    rpc::set_timeouts(cli.rpc_timeout, cli.total_timeout);
+    rpc::set_transport(&cli.transport_options())?;
+    let compressor = cli.compressor()?;
+    progress::set_progress(cli.progress);
+    let config = cli.config.as_deref();
+    let secrets = cli.secrets.as_deref();
+    match cli.command {
+        Command::GenProveParams(mut args) => {
+            if args.stdin {
+                args = request::from_stdin()?;
+            }
+            args.options.compressor = compressor;
+            resolve(&mut args, config, secrets).await?;
+            match args.dry_run {
+                true => dry_run(&args, true).await?,
+                false => emit(&args, true, "gen_prove_params").await?,
+            }
+        }
+        Command::GenVerifyParams(mut args) => {
+            if args.stdin {
+                args = request::from_stdin()?;
+            }
+            args.options.compressor = compressor;
+            resolve(&mut args, config, secrets).await?;
+            match args.dry_run {
+                true => dry_run(&args, false).await?,
+                false => emit(&args, false, "gen_verify_params").await?,
+            }
+        }
+        Command::GenBundle(args) => {
+            print_toml(&generate_bundle(&args).await?, args.low_memory)?;
+        }
+        Command::GenMultiproof(args) => {
+            let params = generate_multiproof(&args).await?;
+            match args.namespaced {
+                true => print!("{}", namespace::split(&params)?.to_toml()),
+                false => print_toml(&params, args.low_memory)?,
+            }
+        }
+        Command::GenCreationParams(args) => print!("{}", generate_creation(&args).await?.to_toml()),
+        Command::GenBalanceAttestation(args) => {
+            print!("{}", generate_balance_attestation(&args).await?.to_toml())
+        }
+        Command::GenDeploymentParams(args) => {
+            print!("{}", generate_deployment(&args).await?.to_toml())
+        }
+        Command::GenNonceParams(args) => print!("{}", generate_nonce(&args).await?.to_toml()),
+        Command::GenBeaconProxyParams(args) => {
+            print!("{}", generate_beacon_proxy(&args).await?.to_toml())
+        }
+        Command::GenReceiptParams(args) => {
+            let params = generate_receipt(&args).await?;
+            match args.namespaced {
+                true => print!("{}", namespace::split(&params)?.to_toml()),
+                false => print!("{}", params.to_toml()),
+            }
+        }
+        Command::GenBloomParams(args) => print!("{}", generate_bloom(&args).await?.to_toml()),
+        Command::DumpSlots(mut args) => {
+            args.compressor = compressor;
+            if let Some(dir) = &args.proof_cache {
+                evict_proof_cache(dir, config)?;
+            }
+            dump_slots(&args).await?
+        }
+        Command::DumpHolders(mut args) => {
+            args.compressor = compressor;
+            if let Some(dir) = &args.proof_cache {
+                evict_proof_cache(dir, config)?;
+            }
+            dump_holders(&args).await?
+        }
+        Command::ExportSmt(args) => {
+            let bundle = partial_trie::export(&args).await?;
+            let json = serde_json::to_string_pretty(&bundle)
+                .map_err(|e| Error::Decode(format!("cannot serialize the bundle: {}", e)))?;
+            match &args.out {
+                Some(path) => compressor.write(path, (json + "\n").as_bytes())?,
+                None => println!("{}", json),
+            }
+        }
+        Command::Probe(args) => {
+            let probe = probe::probe(&args.rpc_url).await?;
+            print!("{}", probe.render());
+            if let Some(block) = args.block {
+                probe.serves(block)?;
+                println!("{:<22}served", format!("Block {}:", block));
+            }
+        }
+        Command::Advise(args) => print!("{}", advise::advise(&args).await?.render()),
+        Command::AnalyzeSlot(args) => {
+            let slot = match &args.slot_mapping {
+                Some(mapping) => parse_mapping_slot(mapping)?,
+                None => args.slot.unwrap_or_default(),
+            };
+            print!("{}", analyze::analyze_slot(&args, slot).await?.render());
+        }
+        Command::Target(command) => manage_targets(&command, config)?,
+        Command::Store(command) => manage_store(&command, compressor)?,
+        Command::Cache(command) => manage_cache(&command, config)?,
+        Command::Checkpoint(command) => manage_checkpoints(&command).await?,
+        Command::Repl(args) => run_repl(&args, config, secrets).await?,
+        Command::Hash(args) => hash_files(&args)?,
+        Command::VerifySignature(args) => verify_signature(&args)?,
+        Command::Decrypt(args) => decrypt_file(&args)?,
+        Command::Demo(args) => run_demo(&args).await?,
+        Command::CheckConstants(args) => check_constants(&args)?,
+        Command::DecodeHeader(args) => decode_header(&args)?,
+        Command::ExplainProof(args) => explain_proof(&args)?,
+        Command::InspectProof(args) => inspect_proof(&args).await?,
+        Command::Info(args) => print_info(&args)?,
+        Command::Migrate(args) => migrate_file(&args, compressor)?,
+        Command::Redact(args) => redact_file(&args, compressor)?,
+        Command::Refresh(args) => {
+            let data = compression::read(&args.file)?;
+            let (mut gen_args, prove) =
+                refresh::gen_args(&witness::parse_bytes(&data)?, &data, &args)?;
+            gen_args.options.compressor = compressor;
+            resolve_block(&mut gen_args.options).await?;
+            emit(&gen_args, prove, "refresh").await?;
+        }
+        Command::Layout(args) => print_layout(&args)?,
+        Command::Completions(args) => print_completions(&args),
+        Command::Man(args) => print_man(&args)?,
+        #[cfg(feature = "tui")]
+        Command::Tui(mut args) => {
+            args.gen.options.compressor = compressor;
+            resolve(&mut args.gen, config, secrets).await?;
+            tui::run(args).await?
+        }
+        #[cfg(feature = "conformance")]
+        Command::Conformance(args) => noir_mip::conformance::run(&args.tests_dir)?,
+        #[cfg(feature = "differential")]
+        Command::Differential(args) => run_differential(&args).await?,
+    }
     Ok(())
 }
+
+/// Prints the library's log records to stderr: warnings with a `Warning:`
+/// prefix, progress and notes as they are. Records from dependencies are
+/// dropped.
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target().starts_with("noir_mip") && metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            log::Level::Warn => eprintln!("Warning: {}", record.args()),
+            _ => eprintln!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if e.use_stderr() && json_errors_requested() => {
+            print_json_error("usage", 2, e.render().to_string().trim());
+            process::exit(2);
+        }
+        Err(e) => e.exit(),
+    };
+    let error_format = cli.error_format;
+    if let Err(e) = run(cli).await {
+        match error_format {
+            ErrorFormat::Text => eprintln!("{}", e),
+            ErrorFormat::Json => print_json_error(e.category(), e.exit_code(), &e.to_string()),
+        }
+        process::exit(e.exit_code());
+    }
+}
+
+/// Whether JSON errors were asked for on a command line that failed to
+/// parse, so that usage errors are reported like any other.
+fn json_errors_requested() -> bool {
+    let args: Vec<String> = std::env::args().collect();
+    args.windows(2)
+        .any(|pair| pair[0] == "--error-format" && pair[1] == "json")
+        || args.iter().any(|arg| arg == "--error-format=json")
+        || std::env::var("NOIR_MIP_ERROR_FORMAT").is_ok_and(|format| format == "json")
+}
+
+fn print_json_error(category: &str, exit_code: i32, message: &str) {
+    eprintln!(
+        "{}",
+        serde_json::json!({
+            "category": category,
+            "exit_code": exit_code,
+            "message": message,
+        })
+    );
+}
@@ -0,0 +1,149 @@
+use crate::encoding::{self, HeaderPadding};
+use crate::error::{Error, Result};
+use crate::params::{Params, Value};
+use crate::{
+    ACCOUNT_PROOF_MAX_DEPTH, BLOCK_HEADER_RLP_BYTES, PROOF_BYTES_LEN, STORAGE_PROOF_MAX_DEPTH,
+};
+
+/// Version of the parameter layout this build emits, recorded in
+/// `[meta]`. Files without it predate versioning and count as version 0.
+/// Version 2 always emits the header's length as `block_header_rlp_len`.
+pub const WITNESS_FORMAT_VERSION: u64 = 2;
+
+/// Fields renamed since version 0, as (old name, new name).
+const RENAMED_FIELDS: &[(&str, &str)] = &[];
+
+pub fn version(params: &Params) -> Result<u64> {
+    match params.meta.get("witness_format_version") {
+        None => Ok(0),
+        Some(Value::Int(version)) => Ok(*version),
+        Some(_) => Err(Error::Decode(
+            "meta.witness_format_version is not an integer".to_string(),
+        )),
+    }
+}
+
+/// Zero-pads (or trims trailing zeros from) `name` to `len` bytes.
+fn repad(params: &mut Params, name: &str, len: usize) -> Result<()> {
+    let Some(bytes) = params.fields.get_bytes(name) else {
+        return Ok(());
+    };
+    if bytes.len() > len && bytes[len..].iter().any(|&byte| byte != 0) {
+        return Err(Error::Unsupported(format!(
+            "{} holds {} bytes of data, more than the current {}; regenerate the file",
+            name,
+            bytes.len(),
+            len
+        )));
+    }
+    let mut bytes = bytes.to_vec();
+    bytes.resize(len, 0);
+    params.fields.set(name, bytes);
+    Ok(())
+}
+
+fn repad_proof(params: &mut Params, name: &str, max_depth: usize) -> Result<()> {
+    if let Some(bytes) = params.fields.get_bytes(name) {
+        if bytes.len() % PROOF_BYTES_LEN != 0 {
+            return Err(Error::Unsupported(format!(
+                "{} is not made of {} byte nodes; regenerate the file",
+                name, PROOF_BYTES_LEN
+            )));
+        }
+    }
+    repad(params, name, max_depth * PROOF_BYTES_LEN)
+}
+
+/// Upgrades `params` to [`WITNESS_FORMAT_VERSION`], returning the version it
+/// had.
+pub fn migrate(params: &mut Params) -> Result<u64> {
+    let from = version(params)?;
+    if from > WITNESS_FORMAT_VERSION {
+        return Err(Error::Unsupported(format!(
+            "witness format version {} is newer than this build supports ({})",
+            from, WITNESS_FORMAT_VERSION
+        )));
+    }
+    if from == WITNESS_FORMAT_VERSION {
+        return Ok(from);
+    }
+    if params.meta.get("signature").is_some() {
+        return Err(Error::Unsupported(
+            "the file is signed and migrating it would invalidate the signature; regenerate \
+             and re-sign it instead"
+                .to_string(),
+        ));
+    }
+    for required in ["account_key", "account_value", "block_hash"] {
+        if params.fields.get(required).is_none() {
+            return Err(Error::Unsupported(format!(
+                "{} is missing, so this is not a parameter file this tool produced",
+                required
+            )));
+        }
+    }
+
+    for (old, new) in RENAMED_FIELDS {
+        if let Some(value) = params.fields.get(old).cloned() {
+            params.fields.set(new, value);
+        }
+    }
+    let renamed: Vec<&str> = RENAMED_FIELDS.iter().map(|(old, _)| *old).collect();
+    let mut fields = Params::default().fields;
+    for (name, value) in params.fields.iter() {
+        if !renamed.contains(&name.as_str()) {
+            fields.push(name.clone(), value.clone());
+        }
+    }
+    params.fields = fields;
+
+    // Headers padded elsewhere than after them keep their layout.
+    if encoding::header_padding(params) == HeaderPadding::Post {
+        repad(params, "block_header_rlp", BLOCK_HEADER_RLP_BYTES)?;
+    }
+    repad_proof(params, "account_proof", ACCOUNT_PROOF_MAX_DEPTH)?;
+    repad_proof(params, "storage_proof", STORAGE_PROOF_MAX_DEPTH)?;
+
+    // Files before version 2 only carry the header's length with
+    // --header-padding.
+    if params.fields.get("block_header_rlp_len").is_none() {
+        let padding = encoding::header_padding(params);
+        if let Some(header) = params.fields.get_bytes("block_header_rlp") {
+            let len = padding.unpad(header).map(<[u8]>::len).ok_or_else(|| {
+                Error::Decode("block_header_rlp holds no RLP encoded header".to_string())
+            })?;
+            params.fields.push("block_header_rlp_len", len);
+        }
+    }
+
+    // Version 0 files predate --numeric-encoding and always used big-endian
+    // bytes.
+    if params.meta.get("numeric_encoding").is_none() {
+        params.meta.set("numeric_encoding", "be-bytes");
+    }
+    params
+        .meta
+        .set("witness_format_version", WITNESS_FORMAT_VERSION as usize);
+    params.record_witness_hash();
+
+    Ok(from)
+}
+
+#[cfg(all(test, feature = "formats-toml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_files_gain_the_header_length() {
+        let current = Params::from_toml(include_str!("../fixtures/golden/Prover.toml")).unwrap();
+        let mut old = current.clone();
+        old.fields.remove("block_header_rlp_len");
+        old.meta.set("witness_format_version", 1usize);
+        assert_eq!(migrate(&mut old).unwrap(), 1);
+        assert_eq!(
+            old.fields.get_int("block_header_rlp_len"),
+            current.fields.get_int("block_header_rlp_len")
+        );
+        assert_eq!(old.witness_hash(), current.witness_hash());
+    }
+}
@@ -0,0 +1,175 @@
+//! `--emit-monomorphized-nr`: sizes a circuit to one witness. The proofs
+//! are padded to the depths this witness needs rather than the generator's
+//! maximums, and the circuit template's depth `global`s, and the byte
+//! lengths that follow from them, are rewritten to match. The circuit proves
+//! only witnesses this deep or shallower, but proves them faster, for when
+//! proving time matters more than reusing one circuit.
+
+use crate::constants;
+use crate::error::{Error, Result};
+use crate::params::Params;
+use crate::PROOF_BYTES_LEN;
+
+/// Fields whose length follows the padded depth of a proof besides the
+/// proofs themselves, which a trimmed witness would have to shorten too.
+const DEPTH_SIZED: &[&str] = &[
+    "account_proof_tail",
+    "storage_proof_tail",
+    "account_proof_node_hashes",
+    "storage_proof_node_hashes",
+    "account_proof_item_counts",
+    "storage_proof_item_counts",
+    "keccak_preimages",
+];
+
+/// The depths a witness is padded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Depths {
+    pub account: usize,
+    /// `None` for a witness without a storage proof.
+    pub storage: Option<usize>,
+}
+
+impl Depths {
+    /// The depths [`trim`] recorded in the metadata of `params`.
+    pub fn recorded(params: &Params) -> Option<Self> {
+        Some(Depths {
+            account: params.meta.get_int("account_proof_max_depth")? as usize,
+            storage: params
+                .meta
+                .get_int("storage_proof_max_depth")
+                .map(|depth| depth as usize),
+        })
+    }
+
+    /// The `global`s a circuit sized to these depths declares, under each
+    /// name circuits use for them. The misspelt account proof name is the
+    /// one the bundled circuit declares.
+    fn globals(&self) -> Vec<(&'static str, usize)> {
+        let mut globals = vec![
+            ("ACCOUNT_PROOF_MAX_DEPTH", self.account),
+            ("ACCOUNT_PROOF_MAX_BYTES", self.account * PROOF_BYTES_LEN),
+            ("ACOOUNT_PROOF_MAX_BYTES", self.account * PROOF_BYTES_LEN),
+        ];
+        if let Some(storage) = self.storage {
+            globals.push(("STORAGE_PROOF_MAX_DEPTH", storage));
+            globals.push(("STORAGE_PROOF_MAX_BYTES", storage * PROOF_BYTES_LEN));
+        }
+        globals
+    }
+}
+
+/// Pads the proofs of `params` to their own depths, at least one node each
+/// so no array of the circuit is empty, and records the depths in the
+/// metadata.
+pub fn trim(params: &mut Params) -> Result<Depths> {
+    if let Some(name) = DEPTH_SIZED
+        .iter()
+        .find(|name| params.fields.get(name).is_some())
+    {
+        return Err(Error::Unsupported(format!(
+            "--emit-monomorphized-nr pads the proofs alone; the witness also has {}, which \
+             follows their depth",
+            name
+        )));
+    }
+    let mut trim_proof = |name: &str| -> Result<Option<usize>> {
+        let Some(depth) = params.fields.get_int(&format!("{}_depth", name)) else {
+            return Ok(None);
+        };
+        let depth = (depth as usize).max(1);
+        let Some(bytes) = params.fields.get_bytes(name) else {
+            return Ok(None);
+        };
+        let mut bytes = bytes.to_vec();
+        bytes.resize(depth * PROOF_BYTES_LEN, 0);
+        params.fields.set(name, bytes);
+        Ok(Some(depth))
+    };
+    let account = trim_proof("account_proof")?.ok_or_else(|| {
+        Error::Unsupported(
+            "--emit-monomorphized-nr sizes a circuit to one witness with an account proof"
+                .to_string(),
+        )
+    })?;
+    let depths = Depths {
+        account,
+        storage: trim_proof("storage_proof")?,
+    };
+    params.meta.push("account_proof_max_depth", depths.account);
+    if let Some(storage) = depths.storage {
+        params.meta.push("storage_proof_max_depth", storage);
+    }
+    params.record_witness_hash();
+    Ok(depths)
+}
+
+/// Whether `expression` is a bare integer literal rather than one computed
+/// from other globals, which follows them by itself.
+fn is_literal(expression: &str) -> bool {
+    let expression = expression.trim().replace('_', "");
+    match expression.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !expression.is_empty() && expression.chars().all(|c| c.is_ascii_digit()),
+    }
+}
+
+/// `source` with the depth `global`s of a circuit, and the literal byte
+/// lengths sized by them, set to `depths`. A rewritten line keeps its
+/// declaration and loses any trailing comment, which described the old
+/// value.
+pub fn rewrite(source: &str, depths: &Depths) -> Result<String> {
+    let globals = depths.globals();
+    let mut out = String::new();
+    for line in source.split_inclusive('\n') {
+        let code = line.split("//").next().unwrap_or_default();
+        let declaration = code.trim_start();
+        let rewritten = declaration
+            .strip_prefix("pub ")
+            .unwrap_or(declaration)
+            .strip_prefix("global ")
+            .and_then(|rest| rest.split_once('='))
+            .and_then(|(name, expression)| {
+                let name = name.split(':').next().unwrap_or_default().trim();
+                let (_, value) = globals.iter().find(|(global, _)| *global == name)?;
+                let expression = expression.trim().trim_end_matches(';');
+                is_literal(expression).then_some(value)
+            });
+        match rewritten {
+            Some(value) => {
+                let (head, _) = code.split_once('=').unwrap_or((code, ""));
+                let newline = if line.ends_with('\n') { "\n" } else { "" };
+                out.push_str(&format!("{}= {};{}", head, value, newline));
+            }
+            None => out.push_str(line),
+        }
+    }
+
+    let declared = constants::parse_globals(&out);
+    for (name, value) in &globals {
+        let Some(global) = declared.iter().find(|global| global.name == *name) else {
+            continue;
+        };
+        if global.value != Some(*value as u128) {
+            return Err(Error::Unsupported(format!(
+                "the template computes {} from other globals, and it does not come to the {} \
+                 the witness is padded to; declare it as a literal",
+                name, value
+            )));
+        }
+    }
+    let required = match depths.storage {
+        Some(_) => &["ACCOUNT_PROOF_MAX_DEPTH", "STORAGE_PROOF_MAX_DEPTH"][..],
+        None => &["ACCOUNT_PROOF_MAX_DEPTH"][..],
+    };
+    for required in required {
+        if declared.iter().all(|global| global.name != *required) {
+            return Err(Error::Unsupported(format!(
+                "the template declares no global {}, so the circuit cannot be sized to the \
+                 witness",
+                required
+            )));
+        }
+    }
+    Ok(out)
+}
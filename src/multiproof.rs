@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+/// Storage proofs of several slots under one storage root, with every node
+/// the proofs share stored once.
+pub struct Multiproof {
+    /// Distinct nodes in the order they are first reached; the root is
+    /// node 0.
+    pub nodes: Vec<Vec<u8>>,
+    /// Indices into `nodes` along each slot's path, root first.
+    pub paths: Vec<Vec<usize>>,
+}
+
+impl Multiproof {
+    /// Deduplicates the nodes of `proofs`, one proof per slot.
+    pub fn new(proofs: &[Vec<Vec<u8>>]) -> Self {
+        let mut nodes: Vec<Vec<u8>> = Vec::new();
+        let mut index: HashMap<&[u8], usize> = HashMap::new();
+        let paths = proofs
+            .iter()
+            .map(|proof| {
+                proof
+                    .iter()
+                    .map(|node| {
+                        *index.entry(node).or_insert_with(|| {
+                            nodes.push(node.clone());
+                            nodes.len() - 1
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        Multiproof { nodes, paths }
+    }
+
+    /// Node indices of every path, each padded with zeros to `max_depth`.
+    pub fn flat_paths(&self, max_depth: usize) -> Vec<usize> {
+        let mut flat = vec![0; self.paths.len() * max_depth];
+        for (i, path) in self.paths.iter().enumerate() {
+            flat[i * max_depth..i * max_depth + path.len()].copy_from_slice(path);
+        }
+        flat
+    }
+}
@@ -0,0 +1,326 @@
+//! Namespaced outputs for circuits composed of several proofs: the fields
+//! grouped into one table per proof part, `header`, `account`, `storage`
+//! and `receipt`, which Noir reads as struct inputs. Fields keep their names
+//! within a table. The slots of a multiproof become an array of `storage`
+//! tables, one per slot, with the names of a single storage proof.
+//!
+//! The grouping only changes how the fields are written: the witness hash
+//! and signature stay those of the flat fields, and [`flatten`] reads the
+//! tables back into them.
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use crate::encoding::ByteEncoding;
+use crate::error::{Error, Result};
+use crate::params::{Fields, Params, Value};
+
+/// The tables, in the order they are written.
+pub const SECTIONS: &[&str] = &["header", "account", "storage", "receipt"];
+
+/// Per-slot fields of a multiproof, with the name each has in its slot's
+/// table.
+const SLOT_FIELDS: &[(&str, &str)] = &[
+    ("storage_keys", "storage_key"),
+    ("storage_values", "storage_value"),
+    ("storage_proof_paths", "storage_proof_path"),
+    ("storage_proof_depths", "storage_proof_depth"),
+    ("is_zero_values", "is_zero_value"),
+];
+
+/// The table field `name` belongs in, or `None` to keep it at the top
+/// level.
+pub fn section_of(name: &str) -> Option<&'static str> {
+    if matches!(
+        name,
+        "block_hash"
+            | "state_root"
+            | "state_root_offset"
+            | "receipts_root"
+            | "receipts_root_offset"
+    ) || ["block_header_", "blob_gas_used_", "excess_blob_gas_"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+    {
+        Some("header")
+    } else if name.starts_with("account_") || name == "storage_root" {
+        Some("account")
+    } else if name.starts_with("storage_multiproof") {
+        None
+    } else if name.starts_with("storage_")
+        || name.starts_with("range_")
+        || name == "is_zero_value"
+        || name == "is_zero_values"
+    {
+        Some("storage")
+    } else if name.starts_with("receipt") {
+        Some("receipt")
+    } else {
+        None
+    }
+}
+
+/// A table, or the array of tables of a multiproof's slots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Section {
+    Table(Fields),
+    Array(Vec<Fields>),
+}
+
+/// The parameters split into top-level fields and tables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Namespaced {
+    pub fields: Fields,
+    pub sections: Vec<(&'static str, Section)>,
+    pub meta: Fields,
+    byte_encoding: ByteEncoding,
+    toml_wrap: Option<usize>,
+}
+
+/// The entries of an array. Read back from TOML, an array of small
+/// integers is a byte array.
+fn entries(value: &Value) -> Option<Vec<Value>> {
+    match value {
+        Value::Array(values) => Some(values.clone()),
+        Value::Bytes(bytes) => Some(bytes.iter().map(|&b| Value::Int(b as u64)).collect()),
+        _ => None,
+    }
+}
+
+/// Splits the per-slot arrays of a multiproof into one table per slot. The
+/// paths are flattened, so each slot takes an equal share of them.
+fn slot_tables(params: &Params) -> Result<Vec<Fields>> {
+    let mut slots: Vec<Fields> = Vec::new();
+    for &(plural, singular) in SLOT_FIELDS {
+        let Some(value) = params.fields.get(plural) else {
+            continue;
+        };
+        let values =
+            entries(value).ok_or_else(|| Error::Decode(format!("{} is not an array", plural)))?;
+        if plural == "storage_keys" {
+            slots = vec![Fields::default(); values.len()];
+        }
+        let paths = plural == "storage_proof_paths";
+        let per_slot = match (paths, slots.len()) {
+            (true, 0) => 1,
+            (true, count) => values.len() / count,
+            (false, _) => 1,
+        };
+        if values.len() != per_slot * slots.len() {
+            return Err(Error::Decode(format!(
+                "{} has {} entries for {} slots",
+                plural,
+                values.len(),
+                slots.len()
+            )));
+        }
+        for (slot, chunk) in slots.iter_mut().zip(values.chunks(per_slot.max(1))) {
+            let value = match paths {
+                true => Value::Array(chunk.to_vec()),
+                false => chunk[0].clone(),
+            };
+            slot.push(singular, value);
+        }
+    }
+    Ok(slots)
+}
+
+/// Groups the fields of `params` into their tables.
+pub fn split(params: &Params) -> Result<Namespaced> {
+    let multiproof = params.fields.get("storage_keys").is_some();
+    let mut namespaced = Namespaced {
+        meta: params.meta.clone(),
+        byte_encoding: params.byte_encoding()?,
+        toml_wrap: params.toml_wrap(),
+        ..Default::default()
+    };
+    let mut tables: Vec<(&'static str, Fields)> = Vec::new();
+    for (name, value) in params.fields.iter() {
+        // The per-slot fields of a multiproof go to the slot tables.
+        let section = match section_of(name) {
+            Some("storage") if multiproof => {
+                if SLOT_FIELDS.iter().any(|&(plural, _)| plural == name) {
+                    continue;
+                }
+                None
+            }
+            section => section,
+        };
+        let Some(section) = section else {
+            namespaced.fields.push(name.clone(), value.clone());
+            continue;
+        };
+        match tables.iter_mut().find(|(table, _)| *table == section) {
+            Some((_, fields)) => fields.push(name.clone(), value.clone()),
+            None => {
+                let mut fields = Fields::default();
+                fields.push(name.clone(), value.clone());
+                tables.push((section, fields));
+            }
+        }
+    }
+    for &section in SECTIONS {
+        if multiproof && section == "storage" {
+            namespaced
+                .sections
+                .push((section, Section::Array(slot_tables(params)?)));
+        } else if let Some((_, fields)) = tables.iter().find(|(table, _)| *table == section) {
+            namespaced
+                .sections
+                .push((section, Section::Table(fields.clone())));
+        }
+    }
+    Ok(namespaced)
+}
+
+/// Pushes the fields of `content`, the table `section` of a namespaced
+/// file, onto `params`. An array of `storage` tables is turned back into
+/// the per-slot arrays of a multiproof.
+pub fn flatten(params: &mut Params, section: &str, content: Section) -> Result<()> {
+    if !SECTIONS.contains(&section) {
+        return Err(Error::Decode(format!("unknown table [{}]", section)));
+    }
+    let tables = match content {
+        Section::Table(fields) => {
+            for (name, value) in fields.iter() {
+                params.push(name.clone(), value.clone());
+            }
+            return Ok(());
+        }
+        Section::Array(_) if section != "storage" => {
+            return Err(Error::Decode(format!(
+                "[{}] cannot be an array of tables",
+                section
+            )))
+        }
+        Section::Array(tables) => tables,
+    };
+    for &(plural, singular) in SLOT_FIELDS {
+        let values: Vec<&Value> = tables
+            .iter()
+            .filter_map(|slot| slot.get(singular))
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+        if values.len() != tables.len() {
+            return Err(Error::Decode(format!(
+                "{} is missing from some [[storage]] tables",
+                singular
+            )));
+        }
+        let values = match plural {
+            "storage_proof_paths" => values
+                .into_iter()
+                .flat_map(|value| entries(value).unwrap_or_else(|| vec![value.clone()]))
+                .collect(),
+            _ => values.into_iter().cloned().collect(),
+        };
+        params.push(plural, Value::Array(values));
+    }
+    Ok(())
+}
+
+/// A value written plainly, as Noir input maps take it, rather than tagged
+/// with its kind.
+struct Plain<'a>(&'a Value, ByteEncoding);
+
+impl Serialize for Plain<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let Plain(value, bytes) = *self;
+        match value {
+            Value::Bytes(data) if bytes != ByteEncoding::Decimal => {
+                serializer.serialize_str(&bytes.encode(data).unwrap_or_default())
+            }
+            Value::Bytes(data) => serializer.collect_seq(data),
+            Value::Int(value) => serializer.serialize_u64(*value),
+            Value::Hex(data) => serializer.serialize_str(&format!("0x{}", hex::encode(data))),
+            Value::Str(text) => serializer.serialize_str(text),
+            Value::Array(values) => {
+                serializer.collect_seq(values.iter().map(|value| Plain(value, bytes)))
+            }
+        }
+    }
+}
+
+/// Fields as an object of plain values, in their order.
+struct PlainFields<'a>(&'a Fields, ByteEncoding);
+
+impl Serialize for PlainFields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.0
+                .iter()
+                .map(|(name, value)| (name, Plain(value, self.1))),
+        )
+    }
+}
+
+impl Serialize for Namespaced {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let bytes = self.byte_encoding;
+        let mut map = serializer.serialize_map(None)?;
+        for (name, value) in self.fields.iter() {
+            map.serialize_entry(name, &Plain(value, bytes))?;
+        }
+        for (section, content) in &self.sections {
+            match content {
+                Section::Table(fields) => {
+                    map.serialize_entry(section, &PlainFields(fields, bytes))?
+                }
+                Section::Array(tables) => {
+                    let tables: Vec<PlainFields> = tables
+                        .iter()
+                        .map(|fields| PlainFields(fields, bytes))
+                        .collect();
+                    map.serialize_entry(section, &tables)?
+                }
+            }
+        }
+        if !self.meta.is_empty() {
+            map.serialize_entry("meta", &PlainFields(&self.meta, ByteEncoding::Decimal))?;
+        }
+        map.end()
+    }
+}
+
+impl Namespaced {
+    /// The parameters as TOML: the top-level fields, then one table per
+    /// section and the `[meta]` table.
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        let _ = self
+            .fields
+            .write_toml(&mut out, self.byte_encoding, self.toml_wrap);
+        for (section, content) in &self.sections {
+            match content {
+                Section::Table(fields) => {
+                    out += &format!("\n[{}]\n", section);
+                    let _ = fields.write_toml(&mut out, self.byte_encoding, self.toml_wrap);
+                }
+                Section::Array(tables) => {
+                    for fields in tables {
+                        out += &format!("\n[[{}]]\n", section);
+                        let _ = fields.write_toml(&mut out, self.byte_encoding, self.toml_wrap);
+                    }
+                }
+            }
+        }
+        if !self.meta.is_empty() {
+            out += "\n[meta]\n";
+            let _ = self.meta.write_toml(&mut out, ByteEncoding::Decimal, None);
+        }
+        out
+    }
+
+    /// The parameters as one JSON object nesting the sections, with plain
+    /// values as Noir input maps take them: byte arrays as arrays of
+    /// numbers (or strings in a non-decimal byte encoding), field elements
+    /// as hex strings.
+    #[cfg(feature = "formats-json")]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map(|json| json + "\n")
+            .map_err(|e| Error::Decode(e.to_string()))
+    }
+}
@@ -0,0 +1,101 @@
+//! Names of the files a batch run writes. By default a witness is named
+//! after what it proves, such as its slot; with `--naming hash` it is named
+//! after what it proves and how: the chain, block, account, slot, output
+//! format, a digest of the generation options and the witness format
+//! version. The same witness always lands at the same path, a rerun
+//! overwrites rather than duplicates it, a run with other options writes
+//! other files, and a consumer can compute where a witness will be without
+//! reading a report.
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+use ethereum_types::{H160, H256};
+
+use crate::keccak::keccak256;
+use crate::migrate::WITNESS_FORMAT_VERSION;
+use crate::witness::OutputFormat;
+
+/// How batch artifacts are named.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum Naming {
+    /// After the key the witness proves: its slot, or its holder
+    #[default]
+    Key,
+    /// After the keccak256 of the chain, block, account, slot, output
+    /// format, generation options and witness format version
+    Hash,
+}
+
+/// What a batch artifact was generated from, which its hashed name pins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtifactKey<'a> {
+    pub chain_id: u64,
+    pub block: u64,
+    pub account: H160,
+    pub slot: Option<H256>,
+    /// The format the artifact is written in.
+    pub format: OutputFormat,
+    /// What the generation options select, as [`crate::store::variant`]
+    /// gives it: the kind of parameters and a digest of the options.
+    pub options: &'a str,
+}
+
+impl ArtifactKey<'_> {
+    /// The text the name hashes, one field after another, separated by
+    /// colons: `noir-mip-artifact:<chain id>:<block>:<account>:<slot>:
+    /// <format>:<options>:<version>`, with the numbers in decimal, the
+    /// account and slot as lowercase `0x` hex, the slot empty for an account
+    /// alone, and the format as its file extension.
+    pub fn preimage(&self) -> String {
+        format!(
+            "noir-mip-artifact:{}:{}:{:?}:{}:{}:{}:{}",
+            self.chain_id,
+            self.block,
+            self.account,
+            self.slot
+                .map(|slot| format!("{:?}", slot))
+                .unwrap_or_default(),
+            self.format.extension(),
+            self.options,
+            WITNESS_FORMAT_VERSION
+        )
+    }
+
+    /// The file stem: the keccak256 of the preimage, as 64 hex digits.
+    pub fn name(&self) -> String {
+        hex::encode(keccak256(self.preimage().as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_preimage_names_every_input() {
+        let key = ArtifactKey {
+            chain_id: 1,
+            block: 12965000,
+            account: H160::repeat_byte(0xab),
+            slot: Some(H256::zero()),
+            format: OutputFormat::Toml,
+            options: "prover-0011223344556677",
+        };
+        assert_eq!(
+            key.preimage(),
+            format!(
+                "noir-mip-artifact:1:12965000:0x{}:0x{}:toml:prover-0011223344556677:{}",
+                "ab".repeat(20),
+                "00".repeat(32),
+                WITNESS_FORMAT_VERSION
+            )
+        );
+        let other = ArtifactKey {
+            options: "prover-8899aabbccddeeff",
+            ..key
+        };
+        assert_ne!(key.name(), other.name());
+        assert_eq!(key.name().len(), 64);
+    }
+}
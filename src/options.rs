@@ -0,0 +1,883 @@
+//! The options of a generation run as the library takes them: what to
+//! prove, where from, and how the witness is laid out. They need no
+//! command-line parser; the `cli` feature derives one, and
+//! `gen_prove_params` flattens them into its arguments beside its own,
+//! such as where the files go.
+
+use std::path::PathBuf;
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+use web3::types::{H160, H256, U256};
+
+use crate::compression::Compressor;
+use crate::encoding::{ByteEncoding, HeaderPadding, NumericEncoding, ValueForm};
+use crate::proof_order::ProofOrder;
+use crate::pubkey::PublicKey;
+use crate::units::Unit;
+
+/// A `--block` value: a number or a tag the provider resolves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlockRef {
+    Number(u64),
+    #[default]
+    Latest,
+    Safe,
+    Finalized,
+}
+
+/// What the emitted proofs are anchored to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum RootMode {
+    /// The block hash, with the header as a witness
+    #[default]
+    BlockHash,
+    /// The state root, taken as a trusted public input; no header is emitted
+    StateRoot,
+}
+
+/// Chains whose witnesses need more than the chain's own state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum ChainPreset {
+    /// Polygon PoS, linked to the Ethereum checkpoint covering the block
+    Polygon,
+}
+
+/// Rollups whose state is proved through their own proof APIs, each built
+/// in with the cargo feature of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum Rollup {
+    /// zkSync Era, from `zks_getProof` against the state root of the block's
+    /// L1 batch
+    Zksync,
+    /// Scroll, from eth_getProof once the chain moved to the MPT
+    Scroll,
+    /// Linea, from `linea_getProof` against the sparse Merkle state
+    Linea,
+}
+
+impl Rollup {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rollup::Zksync => "zksync",
+            Rollup::Scroll => "scroll",
+            Rollup::Linea => "linea",
+        }
+    }
+}
+
+/// The options of one generation run.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct GenOptions {
+    /// Mainnet RPC endpoint; repeat (or comma-separate) to cross-check providers
+    #[cfg_attr(feature = "cli", arg(
+        long,
+        env = "MAINNET_RPC",
+        value_delimiter = ',',
+        required_unless_present_any = ["target", "stdin", "from_smt"]
+    ))]
+    pub rpc_url: Vec<String>,
+    /// Number of providers that must return identical parameters
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 1))]
+    pub quorum: usize,
+    /// Block number, or `latest`, `safe` or `finalized` for that block of the
+    /// first provider
+    #[cfg_attr(feature = "cli", arg(
+        long = "block",
+        env = "BLOCK_NUMBER",
+        value_name = "BLOCK",
+        value_parser = crate::cli::parse_block_ref,
+        required_unless_present_any = ["at_timestamp", "beacon_root", "block_range", "stdin", "from_smt"],
+        default_value = "latest",
+        hide_default_value = true
+    ))]
+    pub block_ref: BlockRef,
+    /// Block number `block_ref` resolves to
+    #[cfg_attr(feature = "cli", arg(skip))]
+    pub block: u64,
+    /// Use the last block at or before this time instead of --block: Unix
+    /// seconds, or an RFC 3339 time such as `2024-06-01T00:00:00Z`
+    #[cfg_attr(feature = "cli", arg(long, value_parser = crate::cli::parse_timestamp))]
+    pub at_timestamp: Option<u64>,
+    /// Use the execution block of the beacon block with this root instead
+    /// of --block
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256, conflicts_with = "at_timestamp"))]
+    pub beacon_root: Option<H256>,
+    /// Generate a batch of one witness per block of this inclusive range
+    /// instead of --block, every --stride blocks from LO
+    #[cfg_attr(feature = "cli", arg(
+        long,
+        value_name = "LO..HI",
+        value_parser = crate::cli::parse_block_range,
+        conflicts_with_all = ["block_ref", "at_timestamp", "beacon_root"]
+    ))]
+    pub block_range: Option<BlockRange>,
+    /// Blocks between the witnesses of --block-range, such as 7200 for
+    /// about one a day on mainnet
+    #[cfg_attr(feature = "cli", arg(
+        long,
+        default_value_t = 1,
+        requires = "block_range",
+        value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..)
+    ))]
+    pub stride: u64,
+    /// Also prove the account against every uncle of the block's height,
+    /// as a batch labelling the canonical witness and each uncle's, for
+    /// circuits studying fork choice
+    #[cfg_attr(feature = "cli", arg(long, conflicts_with_all = ["block_range", "from_smt", "state_root"]))]
+    pub uncles: bool,
+    /// Beacon API resolving --beacon-root [default: --beacon-api]
+    #[cfg_attr(feature = "cli", arg(long, env = "BEACON_NODE_URL"))]
+    pub beacon_node: Option<String>,
+    /// The execution block `beacon_root` resolves to
+    #[cfg_attr(feature = "cli", arg(skip))]
+    pub beacon_block: Option<crate::light_client::BeaconExecution>,
+    /// Prove the account of this secp256k1 public key, uncompressed
+    /// (`0x04...`) or compressed, in place of --account; the key is
+    /// recorded in [meta]
+    #[cfg_attr(feature = "cli", arg(long, value_parser = crate::cli::parse_pubkey, conflicts_with_all = ["account_arg", "target"]))]
+    pub pubkey: Option<PublicKey>,
+    /// Account `account_arg`, `pubkey` or the target resolves to
+    #[cfg_attr(feature = "cli", arg(skip))]
+    pub account: H160,
+    /// Target storage slot; omit it to prove only the account (EOAs,
+    /// precompiles)
+    #[cfg_attr(feature = "cli", arg(long, env = "STORAGE_SLOT", value_parser = parse_h256))]
+    pub slot: Option<H256>,
+    /// Abort unless the proven storage value equals this, given as hex
+    /// (`0x...`) or decimal
+    #[cfg_attr(feature = "cli", arg(long, value_parser = crate::cli::parse_u256))]
+    pub expect_value: Option<U256>,
+    /// Abort unless the proven storage value, read as an unsigned integer,
+    /// is at least this
+    #[cfg_attr(feature = "cli", arg(long, value_parser = crate::cli::parse_u256))]
+    pub expect_min: Option<U256>,
+    /// Abort unless the proven storage value, read as an unsigned integer,
+    /// is at most this
+    #[cfg_attr(feature = "cli", arg(long, value_parser = crate::cli::parse_u256))]
+    pub expect_max: Option<U256>,
+    /// Decimals of the token whose raw amount the proven value holds, such
+    /// as 6 for USDC; the value is recorded in [meta] as it reads to people
+    #[cfg_attr(feature = "cli", arg(long, value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(0..=77)))]
+    pub token_decimals: Option<u32>,
+    /// Read the proven value as an amount of ether in this unit instead
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long = "as",
+            value_name = "UNIT",
+            value_enum,
+            conflicts_with = "token_decimals"
+        )
+    )]
+    pub as_unit: Option<Unit>,
+    /// Abort unless the proven storage value lies in LO..HI, both inclusive,
+    /// and emit the bounds as the public inputs range_lo and range_hi with
+    /// the value split into range check limbs
+    #[cfg_attr(feature = "cli", arg(long, value_name = "LO..HI", value_parser = crate::cli::parse_value_range, conflicts_with = "rollup"))]
+    pub range: Option<ValueRange>,
+    /// Bits per limb of storage_value_limbs
+    #[cfg_attr(feature = "cli", arg(
+        long,
+        default_value_t = 16,
+        requires = "range",
+        value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..=64)
+    ))]
+    pub range_limb_bits: u32,
+    /// Anchor the proofs to the block hash or only to the state root;
+    /// `--state-root` selects the state root
+    #[cfg_attr(feature = "cli", arg(
+        long,
+        value_enum,
+        default_value_t = RootMode::BlockHash,
+        default_value_if("state_root", clap::builder::ArgPredicate::IsPresent, "state-root")
+    ))]
+    pub root_mode: RootMode,
+    /// Trusted state root to prove against; the header is then not fetched
+    /// at all and the proofs are checked against this root instead
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256))]
+    pub state_root: Option<H256>,
+    /// TOML file describing the header rules of a chain this tool does not
+    /// know, such as a private PoA network
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub chain_spec: Option<PathBuf>,
+    /// Also emit the header split into padded keccak rate blocks
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub keccak_blocks: bool,
+    /// Also emit where blob_gas_used and excess_blob_gas sit in the header
+    /// RLP, for blocks from Cancun on
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub blob_gas_offsets: bool,
+    /// Also emit the header bytes before and after the state root, each
+    /// zero-padded, as block_header_rlp_head and block_header_rlp_tail
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub header_parts: bool,
+    /// Length block_header_rlp_head is padded to [default: 91]
+    #[cfg_attr(feature = "cli", arg(long, requires = "header_parts"))]
+    pub header_head_bytes: Option<usize>,
+    /// Length block_header_rlp_tail is padded to [default: the padded
+    /// header length less 123, the head and state root]
+    #[cfg_attr(feature = "cli", arg(long, requires = "header_parts"))]
+    pub header_tail_bytes: Option<usize>,
+    /// Where block_header_rlp is zero-padded: after the header, before it,
+    /// or not at all; its length is emitted as block_header_rlp_len
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = HeaderPadding::Post))]
+    pub header_padding: HeaderPadding,
+    /// When the account delegates its code under EIP-7702, prove the
+    /// delegate's account and storage instead
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub follow_delegation: bool,
+    /// Chain preset; `polygon` also proves the block is in an Ethereum
+    /// checkpoint, read through --l1-rpc-url
+    #[cfg_attr(
+        feature = "cli",
+        arg(long = "chain", value_enum, requires = "l1_rpc_url")
+    )]
+    pub chain: Option<ChainPreset>,
+    /// Ethereum RPC endpoint the Polygon checkpoints are read from
+    #[cfg_attr(feature = "cli", arg(long, env = "L1_RPC_URL", requires = "chain"))]
+    pub l1_rpc_url: Option<String>,
+    /// Prove the state of this rollup through its own proof API
+    #[cfg_attr(feature = "cli", arg(long, value_enum, conflicts_with_all = ["chain", "state_override"]))]
+    pub rollup: Option<Rollup>,
+    /// Apply the state overrides in this JSON file, in the format `eth_call`
+    /// takes, and prove the resulting hypothetical state. The witness is
+    /// synthetic: its state root and block hash belong to no real block
+    #[cfg_attr(feature = "cli", arg(long, conflicts_with = "state_root"))]
+    pub state_override: Option<PathBuf>,
+    /// Also emit per-node RLP item counts, offsets, lengths and path indices
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub rlp_hints: bool,
+    /// Refuse to emit a witness with a proof node its padding could be
+    /// mistaken for, unless --rlp-hints or --keccak-preimages emits the
+    /// node lengths
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub strict: bool,
+    /// Also emit every keccak preimage hashed while verifying the witness
+    /// (the header, the proof keys and each proof node) with its digest
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub keccak_preimages: bool,
+    /// Also emit the keccak digest of each proof node, zero-padded to the
+    /// circuit's depth like the nodes
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub node_hashes: bool,
+    /// Also emit the account's nonce and balance as account_nonce and
+    /// account_balance in the --numeric-encoding, with their offsets and
+    /// lengths in account_value
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub account_fields: bool,
+    /// Also emit the empty trie root and empty code hash as
+    /// empty_trie_root and empty_code_hash, and whether the account's
+    /// storage root and code hash are those
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub empty_constants: bool,
+    /// Evaluate the circuit's statement on the emitted parameters and
+    /// print each step, failing if the circuit would reject them
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub self_check: bool,
+    /// Generate the parameters twice, fetching everything again, and fail
+    /// unless the two runs are byte-identical
+    #[cfg_attr(feature = "cli", arg(long, conflicts_with = "dry_run"))]
+    pub reproducibility_check: bool,
+    /// Answer the proof requests from a partial-trie bundle written by
+    /// `export-smt` instead of a provider; --block defaults to its block
+    #[cfg_attr(feature = "cli", arg(long, value_name = "BUNDLE"))]
+    pub from_smt: Option<PathBuf>,
+    /// Split proofs deeper than the circuit allows into two chained segments
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub split_deep_proofs: bool,
+    /// Layout of numeric outputs (storage key and value)
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = NumericEncoding::BeBytes))]
+    pub numeric_encoding: NumericEncoding,
+    /// Form of the storage value: left-padded to 32 bytes, trimmed of its
+    /// leading zeros, or the RLP of the trie leaf
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = ValueForm::Padded))]
+    pub value_form: ValueForm,
+    /// Order the nodes of both proofs are emitted in; leaf-first suits
+    /// circuits that verify from the leaf up to the root
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = ProofOrder::RootFirst))]
+    pub proof_order: ProofOrder,
+    /// Beacon API used to verify the block against the light client protocol
+    #[cfg_attr(feature = "cli", arg(long, requires = "lc_checkpoint"))]
+    pub beacon_api: Option<String>,
+    /// Trusted beacon block root the light client bootstraps from
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256, requires = "beacon_api"))]
+    pub lc_checkpoint: Option<H256>,
+    /// Maximum number of execution blocks between the target and the
+    /// light client's finalized block
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 8192))]
+    pub lc_max_ancestry: u64,
+    /// Reuse proofs stored in this directory by state root, account and slot,
+    /// and store fetched ones there
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub proof_cache: Option<PathBuf>,
+    /// Reuse the witness saved in the witness store ($NOIR_MIP_STORE, or
+    /// ~/.noir-mip/store) for the same chain, block, account, slot and
+    /// options, and save generated ones there
+    #[cfg_attr(feature = "cli", arg(long, conflicts_with_all = ["state_override", "state_root"]))]
+    pub store: bool,
+    /// How written files, proof cache entries and store objects are
+    /// compressed, as the global --compression options give it
+    #[cfg_attr(feature = "cli", arg(skip))]
+    pub compressor: Compressor,
+    /// Emit a zero-filled witness when the provider returns an empty proof
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub allow_empty: bool,
+    /// Re-fetch the block hash after generation and abort if it changed
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub reorg_check: bool,
+    /// Wait until the block has this many confirmations (implies --reorg-check)
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0))]
+    pub confirmations: u64,
+    /// Wait until the block is finalized (implies --reorg-check)
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub wait_finalized: bool,
+    /// Seconds between polls while waiting for confirmations
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 12))]
+    pub poll_interval: u64,
+    /// Replace an emitted field after generation, to build a negative test
+    /// witness; repeatable. Byte arrays take hex, left-padded to their length
+    #[cfg_attr(feature = "cli", arg(long = "override", value_name = "FIELD=VALUE", value_parser = crate::cli::parse_override))]
+    pub overrides: Vec<(String, String)>,
+    /// Emit a field under another name, to match the parameter names of a
+    /// circuit; repeatable
+    #[cfg_attr(feature = "cli", arg(long = "rename", value_name = "OLD=NEW", value_parser = crate::cli::parse_rename))]
+    pub renames: Vec<(String, String)>,
+    /// Sign the parameters and metadata with this PEM ed25519 private key
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub sign_key: Option<PathBuf>,
+    /// Fail unless the block is in the signed checkpoint registry with the
+    /// hash the witness is anchored to
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub only_checkpointed: bool,
+    /// Checkpoint registry of --only-checkpointed
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, env = "NOIR_MIP_CHECKPOINTS", default_value = "checkpoints.json")
+    )]
+    pub checkpoints: PathBuf,
+    /// Ed25519 public key, hex or PEM file, that must have signed the
+    /// checkpoint registry
+    #[cfg_attr(feature = "cli", arg(long, requires = "only_checkpointed"))]
+    pub checkpoint_signer: Option<String>,
+    /// Also write the circuit template sized to this witness: its proof
+    /// depth globals set to the depths the witness needs, with the proofs
+    /// padded to them
+    #[cfg_attr(feature = "cli", arg(long, value_name = "PATH", conflicts_with_all = ["block_range", "uncles", "verify_out"]))]
+    pub emit_monomorphized_nr: Option<PathBuf>,
+    /// Keep the raw slot out of the written files: emit its hash, the
+    /// storage trie path, as storage_key_hash, and a salted commitment to it
+    /// in [meta]
+    #[cfg_attr(feature = "cli", arg(long, conflicts_with_all = ["keccak_preimages", "self_check", "store", "proof_cache"]))]
+    pub blind_slot: bool,
+    /// 32-byte hex salt of the --blind-slot commitment [default: random,
+    /// printed to stderr]
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256, requires = "blind_slot"))]
+    pub slot_salt: Option<H256>,
+    /// How byte arrays are written; nargo only reads decimal arrays
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = ByteEncoding::Decimal))]
+    pub byte_encoding: ByteEncoding,
+    /// Spread TOML arrays over lines of this many entries, for editors and
+    /// diffs of committed fixtures
+    #[cfg_attr(feature = "cli", arg(long, value_name = "N",
+          value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub toml_wrap: Option<usize>,
+}
+
+impl GenOptions {
+    /// Options proving `account` (and `slot`) at `block` from the single
+    /// provider at `rpc_url`, with the command line defaults for the rest.
+    pub fn new(rpc_url: &str, block: u64, account: H160, slot: Option<H256>) -> Self {
+        GenOptions {
+            rpc_url: vec![rpc_url.to_string()],
+            quorum: 1,
+            block_ref: BlockRef::Number(block),
+            block,
+            account,
+            slot,
+            range_limb_bits: 16,
+            stride: 1,
+            lc_max_ancestry: 8192,
+            poll_interval: 12,
+            ..Default::default()
+        }
+    }
+}
+
+/// Inclusive bounds given to `--range` as `LO..HI`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueRange {
+    pub lo: U256,
+    pub hi: U256,
+}
+
+/// Inclusive block numbers given to `--block-range` as `LO..HI`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRange {
+    pub lo: u64,
+    pub hi: u64,
+}
+
+impl BlockRange {
+    /// The blocks from `lo` to at most `hi`, `stride` apart.
+    pub fn blocks(&self, stride: u64) -> Vec<u64> {
+        (self.lo..=self.hi)
+            .step_by(stride.max(1) as usize)
+            .collect()
+    }
+}
+
+/// A chain to include in a bundle, given as `CHAIN_ID=RPC_URL@BLOCK`.
+#[derive(Debug, Clone)]
+pub struct ChainTarget {
+    pub chain_id: u64,
+    pub rpc_url: String,
+    pub block: u64,
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct BundleOptions {
+    /// Chain to include, as CHAIN_ID=RPC_URL@BLOCK; repeat for each chain
+    #[cfg_attr(feature = "cli", arg(long = "chain", value_parser = crate::cli::parse_chain_target, required = true))]
+    pub chains: Vec<ChainTarget>,
+    /// Target account address
+    #[cfg_attr(feature = "cli", arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160))]
+    pub account: H160,
+    /// Target storage slot; omit it to prove only the account
+    #[cfg_attr(feature = "cli", arg(long, env = "STORAGE_SLOT", value_parser = parse_h256))]
+    pub slot: Option<H256>,
+    /// Emit only the public inputs of each chain (the verifier bundle)
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub verifier: bool,
+    /// Also emit the header split into padded keccak rate blocks
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub keccak_blocks: bool,
+    /// Also emit per-node RLP item counts, offsets, lengths and path indices
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub rlp_hints: bool,
+    /// Split proofs deeper than the circuit allows into two chained segments
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub split_deep_proofs: bool,
+    /// Layout of numeric outputs (storage key and value)
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = NumericEncoding::BeBytes))]
+    pub numeric_encoding: NumericEncoding,
+    /// Emit a zero-filled witness when a provider returns an empty proof
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub allow_empty: bool,
+    /// Write the output as it is rendered instead of assembling it first,
+    /// bounding peak memory for very large witnesses
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub low_memory: bool,
+}
+
+impl BundleOptions {
+    /// Generator arguments for one chain of the bundle.
+    pub fn chain_args(&self, chain: &ChainTarget) -> GenOptions {
+        GenOptions {
+            keccak_blocks: self.keccak_blocks,
+            rlp_hints: self.rlp_hints,
+            split_deep_proofs: self.split_deep_proofs,
+            numeric_encoding: self.numeric_encoding,
+            allow_empty: self.allow_empty,
+            ..GenOptions::new(&chain.rpc_url, chain.block, self.account, self.slot)
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct CreationOptions {
+    /// RPC endpoint; searching old blocks needs an archive node
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Account whose creation block is proven
+    #[cfg_attr(feature = "cli", arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160))]
+    pub account: H160,
+    /// Storage slot to prove in both witnesses; omit it to prove only the
+    /// account
+    #[cfg_attr(feature = "cli", arg(long, env = "STORAGE_SLOT", value_parser = parse_h256))]
+    pub slot: Option<H256>,
+    /// Lowest block searched; the account must not exist there
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = 0))]
+    pub from_block: u64,
+    /// Highest block searched; the account must exist there [default: the
+    /// latest block]
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub to_block: Option<u64>,
+    /// Emit only the public inputs of both witnesses
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub verifier: bool,
+    /// Also emit per-node RLP item counts, offsets, lengths and path indices
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub rlp_hints: bool,
+    /// Layout of numeric outputs (storage key and value)
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = NumericEncoding::BeBytes))]
+    pub numeric_encoding: NumericEncoding,
+}
+
+impl CreationOptions {
+    /// Generator arguments for the witness at `block`.
+    pub fn block_args(&self, block: u64) -> GenOptions {
+        GenOptions {
+            rlp_hints: self.rlp_hints,
+            numeric_encoding: self.numeric_encoding,
+            ..GenOptions::new(&self.rpc_url, block, self.account, self.slot)
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct BalanceAttestationOptions {
+    /// Mainnet RPC endpoint
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Block number, or `latest`, `safe` or `finalized`
+    #[cfg_attr(feature = "cli", arg(
+        long = "block",
+        env = "BLOCK_NUMBER",
+        value_name = "BLOCK",
+        value_parser = crate::cli::parse_block_ref,
+        default_value = "latest"
+    ))]
+    pub block_ref: BlockRef,
+    /// Account whose balance is attested
+    #[cfg_attr(feature = "cli", arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160))]
+    pub account: H160,
+    /// Least balance the account must hold: wei, or an amount with a unit
+    /// such as `1eth`, `0.5ether` or `30gwei`
+    #[cfg_attr(feature = "cli", arg(long, value_parser = crate::cli::parse_amount))]
+    pub min_balance: U256,
+    /// Also emit the threshold as the public input min_balance, so the
+    /// circuit commits to the bound it checked
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub public_threshold: bool,
+    /// Emit only the public inputs
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub verifier: bool,
+    /// Layout of min_balance with --public-threshold
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = NumericEncoding::BeBytes))]
+    pub numeric_encoding: NumericEncoding,
+}
+
+impl BalanceAttestationOptions {
+    /// Generator arguments proving the account alone at `block_ref`.
+    pub fn gen_args(&self) -> GenOptions {
+        GenOptions {
+            block_ref: self.block_ref,
+            numeric_encoding: self.numeric_encoding,
+            ..GenOptions::new(&self.rpc_url, 0, self.account, None)
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct MultiproofOptions {
+    /// Mainnet RPC endpoint
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Block number
+    #[cfg_attr(feature = "cli", arg(long, env = "BLOCK_NUMBER"))]
+    pub block: u64,
+    /// Target account address
+    #[cfg_attr(feature = "cli", arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160))]
+    pub account: H160,
+    /// Storage slot to prove; repeat for each slot
+    #[cfg_attr(feature = "cli", arg(
+        long = "slot",
+        value_parser = parse_h256,
+        required_unless_present_any = ["slot_bytes", "slot_struct"]
+    ))]
+    pub slots: Vec<H256>,
+    /// Prove the Solidity string or bytes declared at this slot: the slot
+    /// holding its length and, for values over 31 bytes, every slot of its
+    /// data
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256, conflicts_with_all = ["slots", "slot_struct"]))]
+    pub slot_bytes: Option<H256>,
+    /// Prove the struct stored under `KEY` in the mapping declared at slot
+    /// `INDEX` (or, with --storage-layout, the mapping named `INDEX`): its
+    /// consecutive slots from the first
+    #[cfg_attr(feature = "cli", arg(
+        long,
+        num_args = 2,
+        value_names = ["INDEX", "KEY"],
+        conflicts_with = "slots"
+    ))]
+    pub slot_struct: Option<Vec<String>>,
+    /// Slots the struct takes [default: from --storage-layout]
+    #[cfg_attr(feature = "cli", arg(long, requires = "slot_struct"))]
+    pub struct_slots: Option<usize>,
+    /// solc storage layout JSON (or a Foundry or Hardhat artifact holding
+    /// one) naming the struct's members and where they sit
+    #[cfg_attr(feature = "cli", arg(long, requires = "slot_struct"))]
+    pub storage_layout: Option<PathBuf>,
+    /// Pad the shared node table to this many nodes; defaults to the number
+    /// of distinct nodes
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub max_nodes: Option<usize>,
+    /// Split an account proof deeper than the circuit allows into two
+    /// chained segments
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub split_deep_proofs: bool,
+    /// Layout of numeric outputs (storage keys and values)
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = NumericEncoding::BeBytes))]
+    pub numeric_encoding: NumericEncoding,
+    /// Threads verifying the storage proofs [default: one per CPU]
+    #[cfg_attr(feature = "cli", arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)))]
+    pub verify_threads: Option<usize>,
+    /// Write the output as it is rendered instead of assembling it first,
+    /// bounding peak memory for very large witnesses
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub low_memory: bool,
+    /// Group the fields into header and account tables and one storage
+    /// table per slot, for a circuit taking them as struct inputs
+    #[cfg_attr(feature = "cli", arg(long, conflicts_with = "low_memory"))]
+    pub namespaced: bool,
+}
+
+impl MultiproofOptions {
+    /// Generator arguments for the account part of the witness.
+    pub fn account_args(&self) -> GenOptions {
+        GenOptions {
+            split_deep_proofs: self.split_deep_proofs,
+            numeric_encoding: self.numeric_encoding,
+            ..GenOptions::new(&self.rpc_url, self.block, self.account, None)
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct DeploymentOptions {
+    /// Mainnet RPC endpoint
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Block number, or `latest`, `safe` or `finalized`
+    #[cfg_attr(feature = "cli", arg(
+        long = "block",
+        env = "BLOCK_NUMBER",
+        value_name = "BLOCK",
+        value_parser = crate::cli::parse_block_ref,
+        default_value = "latest"
+    ))]
+    pub block_ref: BlockRef,
+    /// Deploying account or contract
+    #[cfg_attr(feature = "cli", arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160))]
+    pub account: H160,
+    /// Also derive the CREATE2 address for this salt
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256, requires = "init_code_hash"))]
+    pub salt: Option<H256>,
+    /// Keccak digest of the init code deployed with --salt
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256, requires = "salt"))]
+    pub init_code_hash: Option<H256>,
+    /// Emit only the public inputs
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub verifier: bool,
+    /// Layout of the nonce
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = NumericEncoding::BeBytes))]
+    pub numeric_encoding: NumericEncoding,
+}
+
+impl DeploymentOptions {
+    /// Generator arguments proving the deployer alone at `block_ref`.
+    pub fn gen_args(&self) -> GenOptions {
+        GenOptions {
+            block_ref: self.block_ref,
+            numeric_encoding: self.numeric_encoding,
+            ..GenOptions::new(&self.rpc_url, 0, self.account, None)
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct NonceOptions {
+    /// Mainnet RPC endpoint
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Block number, or `latest`, `safe` or `finalized`
+    #[cfg_attr(feature = "cli", arg(
+        long = "block",
+        env = "BLOCK_NUMBER",
+        value_name = "BLOCK",
+        value_parser = crate::cli::parse_block_ref,
+        default_value = "latest"
+    ))]
+    pub block_ref: BlockRef,
+    /// The EOA whose nonce is proven
+    #[cfg_attr(feature = "cli", arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160))]
+    pub account: H160,
+    /// Abort unless the account has this nonce, given as hex (`0x...`) or
+    /// decimal
+    #[cfg_attr(feature = "cli", arg(long, value_parser = crate::cli::parse_u256))]
+    pub expect_nonce: Option<U256>,
+    /// Emit only the public inputs: the block hash (or state root), the
+    /// account and its nonce
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub verifier: bool,
+    /// Layout of the nonce
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = NumericEncoding::BeBytes))]
+    pub numeric_encoding: NumericEncoding,
+}
+
+impl NonceOptions {
+    /// Generator arguments proving the account alone at `block_ref`.
+    pub fn gen_args(&self) -> GenOptions {
+        GenOptions {
+            block_ref: self.block_ref,
+            numeric_encoding: self.numeric_encoding,
+            ..GenOptions::new(&self.rpc_url, 0, self.account, None)
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct BeaconProxyOptions {
+    /// Mainnet RPC endpoint
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Block number, or `latest`, `safe` or `finalized`
+    #[cfg_attr(feature = "cli", arg(
+        long = "block",
+        env = "BLOCK_NUMBER",
+        value_name = "BLOCK",
+        value_parser = crate::cli::parse_block_ref,
+        default_value = "latest"
+    ))]
+    pub block_ref: BlockRef,
+    /// The beacon proxy
+    #[cfg_attr(feature = "cli", arg(long, env = "TARGET_ACCOUNT", value_parser = parse_h160))]
+    pub account: H160,
+    /// Slot the beacon keeps its implementation in [default: 1, as in
+    /// OpenZeppelin's UpgradeableBeacon]
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256))]
+    pub implementation_slot: Option<H256>,
+    /// Emit only the public inputs of the three proofs
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub verifier: bool,
+    /// Also emit per-node RLP item counts, offsets, lengths and path indices
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub rlp_hints: bool,
+    /// Layout of numeric outputs (storage keys and values)
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = NumericEncoding::BeBytes))]
+    pub numeric_encoding: NumericEncoding,
+}
+
+impl BeaconProxyOptions {
+    /// Generator arguments proving `slot` of `account`, or the account
+    /// alone, at `block`.
+    pub fn gen_args(&self, block: u64, account: H160, slot: Option<H256>) -> GenOptions {
+        GenOptions {
+            rlp_hints: self.rlp_hints,
+            numeric_encoding: self.numeric_encoding,
+            ..GenOptions::new(&self.rpc_url, block, account, slot)
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct ReceiptOptions {
+    /// Mainnet RPC endpoint; it must serve eth_getBlockReceipts
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Block number
+    #[cfg_attr(feature = "cli", arg(long, env = "BLOCK_NUMBER"))]
+    pub block: u64,
+    /// Index of the transaction within the block
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub tx_index: usize,
+    /// Pad every receipt trie node, and the receipt itself, to this many
+    /// bytes
+    #[cfg_attr(feature = "cli", arg(long, default_value_t = crate::RECEIPT_NODE_MAX_BYTES))]
+    pub max_node_bytes: usize,
+    /// Also emit where blob_gas_used and excess_blob_gas sit in the header
+    /// RLP, for blocks from Cancun on
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub blob_gas_offsets: bool,
+    /// TOML file describing the header rules of a chain this tool does not
+    /// know, such as a private PoA network
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub chain_spec: Option<PathBuf>,
+    /// Also prove this account under the same header, for a statement
+    /// about its state and the receipt in one block
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h160))]
+    pub account: Option<H160>,
+    /// With --account, also prove this storage slot of it
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256, requires = "account"))]
+    pub slot: Option<H256>,
+    /// Group the fields into header, account, storage and receipt tables,
+    /// for a circuit taking them as struct inputs
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub namespaced: bool,
+}
+
+impl ReceiptOptions {
+    /// Arguments proving `account` (and `--slot`) at the receipt's block.
+    pub fn state_args(&self, account: H160) -> GenOptions {
+        GenOptions {
+            chain_spec: self.chain_spec.clone(),
+            blob_gas_offsets: self.blob_gas_offsets,
+            ..GenOptions::new(&self.rpc_url, self.block, account, self.slot)
+        }
+    }
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct BloomOptions {
+    /// Mainnet RPC endpoint
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Block number
+    #[cfg_attr(feature = "cli", arg(long, env = "BLOCK_NUMBER"))]
+    pub block: u64,
+    /// Event whose topic is looked up: its signature, such as
+    /// `Transfer(address,address,uint256)`, or the 32-byte topic itself
+    #[cfg_attr(feature = "cli", arg(long, value_parser = crate::cli::parse_event, required_unless_present = "address"))]
+    pub event: Option<H256>,
+    /// Contract whose logs are looked up
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h160))]
+    pub address: Option<H160>,
+    /// TOML file describing the header rules of a chain this tool does not
+    /// know, such as a private PoA network
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub chain_spec: Option<PathBuf>,
+}
+
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct ExportSmtOptions {
+    /// Mainnet RPC endpoint
+    #[cfg_attr(feature = "cli", arg(long, env = "MAINNET_RPC"))]
+    pub rpc_url: String,
+    /// Block number
+    #[cfg_attr(feature = "cli", arg(long, env = "BLOCK_NUMBER"))]
+    pub block: u64,
+    /// Account to prove; repeat for each account
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h160, required_unless_present = "batch"))]
+    pub account: Vec<H160>,
+    /// Storage slot to prove under every --account; repeat for each slot
+    #[cfg_attr(feature = "cli", arg(long, value_parser = parse_h256))]
+    pub slot: Vec<H256>,
+    /// File of further accounts, one per line with the slots to prove
+    /// under it: `ACCOUNT [SLOT...]`
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub batch: Option<PathBuf>,
+    /// Write the bundle to this file instead of stdout
+    #[cfg_attr(feature = "cli", arg(long, short))]
+    pub out: Option<PathBuf>,
+}
+
+fn parse_hex(value: &str, len: usize) -> Result<Vec<u8>, String> {
+    let bytes = hex::decode(value.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+    if bytes.len() != len {
+        return Err(format!("expected {} bytes, got {}", len, bytes.len()));
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn parse_h160(value: &str) -> Result<H160, String> {
+    parse_hex(value, 20).map(|bytes| H160::from_slice(&bytes))
+}
+
+pub(crate) fn parse_h256(value: &str) -> Result<H256, String> {
+    parse_hex(value, 32).map(|bytes| H256::from_slice(&bytes))
+}
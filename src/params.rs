@@ -0,0 +1,662 @@
+use std::fmt::{self, Write};
+use std::io;
+
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::encoding::ByteEncoding;
+use crate::error::{Error, Result};
+use crate::keccak::Keccak256;
+#[cfg(feature = "formats-toml")]
+use crate::namespace::{self, Section};
+
+/// Byte strings as `0x`-prefixed hex in serialized witnesses.
+pub(crate) mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        hex::decode(text.trim_start_matches("0x")).map_err(serde::de::Error::custom)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            bytes: &Option<Vec<u8>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match bytes {
+                Some(bytes) => super::serialize(bytes, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Vec<u8>>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|text| hex::decode(text.trim_start_matches("0x")))
+                .transpose()
+                .map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+/// A single emitted parameter value. It serializes tagged with its kind,
+/// as `{"bytes": "0x..."}` or `{"int": 3}`, so it reads back as the same
+/// variant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Value {
+    Bytes(#[serde(with = "hex_bytes")] Vec<u8>),
+    Int(u64),
+    /// Field element given as a `0x`-prefixed big-endian hex string.
+    Hex(#[serde(with = "hex_bytes")] Vec<u8>),
+    Str(String),
+    Array(Vec<Value>),
+}
+
+impl From<&[u8]> for Value {
+    fn from(bytes: &[u8]) -> Self {
+        Value::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        Value::Bytes(bytes)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Int(value as u64)
+    }
+}
+
+impl From<Vec<usize>> for Value {
+    fn from(values: Vec<usize>) -> Self {
+        Value::Array(values.into_iter().map(Value::from).collect())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl Value {
+    #[cfg(feature = "formats-toml")]
+    fn from_toml(value: toml::Value) -> Result<Self> {
+        match value {
+            toml::Value::Integer(value) => u64::try_from(value)
+                .map(Value::Int)
+                .map_err(|_| Error::Decode(format!("negative integer {}", value))),
+            toml::Value::String(value) => Ok(Value::Str(value)),
+            toml::Value::Array(values) => {
+                let values = values
+                    .into_iter()
+                    .map(Value::from_toml)
+                    .collect::<Result<Vec<_>>>()?;
+                let bytes: Option<Vec<u8>> = values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Int(value) => u8::try_from(*value).ok(),
+                        _ => None,
+                    })
+                    .collect();
+                match bytes {
+                    Some(bytes) if !bytes.is_empty() => Ok(Value::Bytes(bytes)),
+                    _ => Ok(Value::Array(values)),
+                }
+            }
+            other => Err(Error::Decode(format!(
+                "unsupported TOML value type {}",
+                other.type_str()
+            ))),
+        }
+    }
+
+    /// Short description of the value's size, or the value itself for
+    /// scalars.
+    pub fn shape(&self) -> String {
+        match self {
+            Value::Bytes(bytes) => format!("{} bytes", bytes.len()),
+            Value::Int(value) => value.to_string(),
+            Value::Hex(bytes) => format!("{} byte field", bytes.len()),
+            Value::Str(value) => format!("{:?}", value),
+            Value::Array(values) => format!("{} values", values.len()),
+        }
+    }
+
+    /// Writes the value as compact JSON. Byte arrays and integer arrays
+    /// serialize identically, so a value read back from TOML produces the
+    /// same bytes as the one that was emitted.
+    fn write_canonical(&self, out: &mut impl Write) -> fmt::Result {
+        match self {
+            Value::Bytes(bytes) => {
+                out.write_char('[')?;
+                for (i, byte) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    write!(out, "{}", byte)?;
+                }
+                out.write_char(']')
+            }
+            Value::Int(value) => write!(out, "{}", value),
+            Value::Hex(bytes) => write_json_string(out, &format!("0x{}", hex::encode(bytes))),
+            Value::Str(value) => write_json_string(out, value),
+            Value::Array(values) => {
+                out.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    value.write_canonical(out)?;
+                }
+                out.write_char(']')
+            }
+        }
+    }
+
+    fn write_toml(&self, out: &mut impl Write) -> fmt::Result {
+        match self {
+            Value::Bytes(bytes) => write!(out, "{:?}", bytes),
+            Value::Int(value) => write!(out, "{}", value),
+            Value::Hex(bytes) => write!(out, "\"0x{}\"", hex::encode(bytes)),
+            Value::Str(value) => write!(out, "{:?}", value),
+            Value::Array(values) => {
+                out.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.write_str(", ")?;
+                    }
+                    value.write_toml(out)?;
+                }
+                out.write_char(']')
+            }
+        }
+    }
+
+    /// Writes an array as a multi-line TOML array of `wrap` entries per
+    /// line, or the value as [`Value::write_toml`] does when it is not an
+    /// array longer than that.
+    fn write_toml_wrapped(&self, out: &mut impl Write, wrap: usize) -> fmt::Result {
+        let len = match self {
+            Value::Bytes(bytes) => bytes.len(),
+            Value::Array(values) => values.len(),
+            _ => 0,
+        };
+        if len <= wrap {
+            return self.write_toml(out);
+        }
+        out.write_char('[')?;
+        for i in 0..len {
+            out.write_str(if i % wrap == 0 { "\n    " } else { " " })?;
+            match self {
+                Value::Bytes(bytes) => write!(out, "{}", bytes[i])?,
+                Value::Array(values) => values[i].write_toml(out)?,
+                _ => {}
+            }
+            out.write_char(',')?;
+        }
+        out.write_str("\n]")
+    }
+
+    /// Writes the value as a Noir literal: byte and integer arrays as array
+    /// literals, field elements as hex field literals. With `hex` the bytes
+    /// of byte arrays are hex literals too.
+    fn write_noir(&self, out: &mut impl Write, hex: bool) -> fmt::Result {
+        match self {
+            Value::Bytes(bytes) if hex => {
+                out.write_char('[')?;
+                for (i, byte) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        out.write_str(", ")?;
+                    }
+                    write!(out, "0x{:02x}", byte)?;
+                }
+                out.write_char(']')
+            }
+            Value::Hex(bytes) => write!(out, "0x{}", hex::encode(bytes)),
+            Value::Array(values) => {
+                out.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.write_str(", ")?;
+                    }
+                    value.write_noir(out, hex)?;
+                }
+                out.write_char(']')
+            }
+            value => value.write_toml(out),
+        }
+    }
+}
+
+/// Ordered collection of named values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fields(Vec<(String, Value)>);
+
+impl Fields {
+    pub fn push(&mut self, name: impl Into<String>, value: impl Into<Value>) {
+        self.0.push((name.into(), value.into()));
+    }
+
+    /// Replaces the value of `name`, appending it if it is not present.
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) {
+        let value = value.into();
+        match self.0.iter_mut().find(|(field, _)| field == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.0.push((name.to_string(), value)),
+        }
+    }
+
+    /// Renames the field `from` to `to`, returning whether it was present.
+    pub fn rename(&mut self, from: &str, to: &str) -> bool {
+        match self.0.iter_mut().find(|(field, _)| field == from) {
+            Some((field, _)) => {
+                *field = to.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the field `name`, returning its value if it was present.
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        let index = self.0.iter().position(|(field, _)| field == name)?;
+        Some(self.0.remove(index).1)
+    }
+
+    #[cfg(feature = "formats-toml")]
+    fn from_toml_table(table: toml::Table) -> Result<Self> {
+        let mut fields = Fields::default();
+        for (name, value) in table {
+            fields.push(name, Value::from_toml(value)?);
+        }
+        Ok(fields)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Value)> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_bytes(&self, name: &str) -> Option<&[u8]> {
+        match self.get(name)? {
+            Value::Bytes(bytes) | Value::Hex(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<u64> {
+        match self.get(name)? {
+            Value::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Canonical JSON of each field not named in `exclude`.
+    fn canonical_entries(&self, exclude: &[&str]) -> Vec<(String, String)> {
+        self.0
+            .iter()
+            .filter(|(name, _)| !exclude.contains(&name.as_str()))
+            .map(|(name, value)| {
+                let mut out = String::new();
+                let _ = value.write_canonical(&mut out);
+                (name.clone(), out)
+            })
+            .collect()
+    }
+
+    /// Writes the fields as a canonical JSON object, sorted by name, without
+    /// first rendering each value on its own.
+    fn write_canonical(&self, out: &mut impl Write) -> fmt::Result {
+        let mut entries: Vec<&(String, Value)> = self.0.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        out.write_char('{')?;
+        for (i, (name, value)) in entries.into_iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write_json_string(out, name)?;
+            out.write_char(':')?;
+            value.write_canonical(out)?;
+        }
+        out.write_char('}')
+    }
+
+    /// Writes one `name = value` entry per field, with top-level byte arrays
+    /// in `bytes` and, with `wrap`, top-level arrays longer than `wrap`
+    /// entries spread over lines of that many.
+    pub(crate) fn write_toml(
+        &self,
+        out: &mut impl Write,
+        bytes: ByteEncoding,
+        wrap: Option<usize>,
+    ) -> fmt::Result {
+        for (name, value) in &self.0 {
+            write!(out, "{} = ", name)?;
+            match (value, wrap) {
+                (Value::Bytes(data), _) if bytes != ByteEncoding::Decimal => {
+                    write_json_string(out, &bytes.encode(data).unwrap_or_default())?
+                }
+                (value, Some(wrap)) => value.write_toml_wrapped(out, wrap)?,
+                (value, None) => value.write_toml(out)?,
+            }
+            out.write_char('\n')?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes as an object whose entries keep their order.
+impl Serialize for Fields {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Fields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = Fields;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object of named values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Fields, A::Error> {
+                let mut fields = Fields::default();
+                while let Some((name, value)) = map.next_entry::<String, Value>()? {
+                    fields.push(name, value);
+                }
+                Ok(fields)
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+/// Circuit parameters plus the metadata describing how they were produced.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    pub fields: Fields,
+    pub meta: Fields,
+}
+
+impl Params {
+    pub fn push(&mut self, name: impl Into<String>, value: impl Into<Value>) {
+        self.fields.push(name, value);
+    }
+
+    /// Parses a file previously produced by [`Params::to_toml`].
+    #[cfg(feature = "formats-toml")]
+    pub fn from_toml(input: &str) -> Result<Self> {
+        let table: toml::Table = input
+            .parse()
+            .map_err(|e: toml::de::Error| Error::Decode(e.message().to_string()))?;
+
+        let mut params = Params::default();
+        for (name, value) in table {
+            match (name.as_str(), value) {
+                ("meta", toml::Value::Table(meta)) => {
+                    for (name, value) in meta {
+                        params.meta.push(name, Value::from_toml(value)?);
+                    }
+                }
+                // The tables of a namespaced file.
+                (_, toml::Value::Table(table)) => {
+                    let section = Section::Table(Fields::from_toml_table(table)?);
+                    namespace::flatten(&mut params, &name, section)?;
+                }
+                (_, toml::Value::Array(values))
+                    if !values.is_empty() && values.iter().all(toml::Value::is_table) =>
+                {
+                    let tables = values
+                        .into_iter()
+                        .filter_map(|value| match value {
+                            toml::Value::Table(table) => Some(Fields::from_toml_table(table)),
+                            _ => None,
+                        })
+                        .collect::<Result<_>>()?;
+                    namespace::flatten(&mut params, &name, Section::Array(tables))?;
+                }
+                (_, value) => params.push(name, Value::from_toml(value)?),
+            }
+        }
+        let bytes = params.byte_encoding()?;
+        if bytes != ByteEncoding::Decimal {
+            for (name, value) in &mut params.fields.0 {
+                if let Value::Str(text) = value {
+                    *value = Value::Bytes(bytes.decode(text).ok_or_else(|| {
+                        Error::Decode(format!("{} is not {} encoded", name, bytes.name()))
+                    })?);
+                }
+            }
+        }
+        Ok(params)
+    }
+
+    /// How the byte arrays of the fields are written, from
+    /// `meta.byte_encoding`; decimal arrays when it is absent.
+    pub fn byte_encoding(&self) -> Result<ByteEncoding> {
+        match self.meta.get("byte_encoding") {
+            None => Ok(ByteEncoding::Decimal),
+            Some(Value::Str(name)) => ByteEncoding::from_name(name)
+                .ok_or_else(|| Error::Decode(format!("unknown meta.byte_encoding {}", name))),
+            Some(_) => Err(Error::Decode(
+                "meta.byte_encoding is not a string".to_string(),
+            )),
+        }
+    }
+
+    /// Entries per line of the arrays of the TOML form, from
+    /// `meta.toml_wrap`; one line per array when it is absent.
+    pub fn toml_wrap(&self) -> Option<usize> {
+        self.meta
+            .get_int("toml_wrap")
+            .map(|wrap| wrap as usize)
+            .filter(|&wrap| wrap > 0)
+    }
+
+    /// Canonical serialization of the fields and metadata: compact JSON with
+    /// sorted keys and the metadata nested under `meta`. Metadata entries
+    /// named in `exclude_meta` are left out.
+    pub fn canonical_bytes(&self, exclude_meta: &[&str]) -> Vec<u8> {
+        let mut meta = String::new();
+        write_canonical_object(&mut meta, self.meta.canonical_entries(exclude_meta));
+
+        let mut entries = self.fields.canonical_entries(&["meta"]);
+        entries.push(("meta".to_string(), meta));
+
+        let mut out = String::new();
+        write_canonical_object(&mut out, entries);
+        out.into_bytes()
+    }
+
+    /// The metadata alone as a canonical JSON object.
+    pub fn meta_json(&self) -> String {
+        let mut out = String::new();
+        write_canonical_object(&mut out, self.meta.canonical_entries(&[]));
+        out
+    }
+
+    /// Keccak-256 of the canonical serialization of the fields alone, so
+    /// metadata such as provider counts or signatures does not change it.
+    /// The serialization is hashed as it is written rather than built first.
+    pub fn witness_hash(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::default();
+        let _ = self.fields.write_canonical(&mut hasher);
+        hasher.finalize()
+    }
+
+    /// Records [`Params::witness_hash`] as `meta.witness_hash`, replacing an
+    /// earlier one.
+    pub fn record_witness_hash(&mut self) {
+        let hash = self.witness_hash();
+        self.meta.set("witness_hash", Value::Hex(hash.to_vec()));
+    }
+
+    /// Renders the parameters as a Noir input TOML file, with the metadata
+    /// in a trailing `[meta]` table that nargo ignores. Byte arrays follow
+    /// `meta.byte_encoding`.
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        let _ = self.render_toml(&mut out);
+        out
+    }
+
+    /// Writes [`Params::to_toml`] to `out` as it is rendered, through a
+    /// buffer of bounded size, so arrays far larger than memory allows
+    /// twice over never exist as text all at once.
+    pub fn write_toml(&self, out: impl io::Write) -> io::Result<()> {
+        write_buffered(out, |out| self.render_toml(out))
+    }
+
+    fn render_toml(&self, out: &mut impl Write) -> fmt::Result {
+        self.fields.write_toml(
+            out,
+            self.byte_encoding().unwrap_or_default(),
+            self.toml_wrap(),
+        )?;
+        if self.meta.iter().next().is_some() {
+            out.write_str("\n[meta]\n")?;
+            self.meta.write_toml(out, ByteEncoding::Decimal, None)?;
+        }
+        Ok(())
+    }
+
+    /// A Noir `#[test]` function named `name` that binds every field as a
+    /// literal and passes them to `main` in emission order, which is the
+    /// order of the circuit's parameters. The circuit's own assertions then
+    /// check the public inputs against the proofs. Metadata is kept as
+    /// comments. Byte arrays are hex literals under a hex
+    /// `meta.byte_encoding`.
+    pub fn to_noir_test(&self, name: &str) -> String {
+        let mut out = String::new();
+        let _ = self.render_noir_test(&mut out, name);
+        out
+    }
+
+    /// Writes [`Params::to_noir_test`] to `out` as it is rendered, like
+    /// [`Params::write_toml`].
+    pub fn write_noir_test(&self, out: impl io::Write, name: &str) -> io::Result<()> {
+        write_buffered(out, |out| self.render_noir_test(out, name))
+    }
+
+    fn render_noir_test(&self, out: &mut impl Write, name: &str) -> fmt::Result {
+        let hex = self.byte_encoding().ok() == Some(ByteEncoding::Hex);
+        for (field, value) in self.meta.iter() {
+            write!(out, "// {} = ", field)?;
+            value.write_toml(out)?;
+            out.write_char('\n')?;
+        }
+        writeln!(out, "#[test]\nfn {}() {{", name)?;
+        for (field, value) in self.fields.iter() {
+            write!(out, "    let {} = ", field)?;
+            value.write_noir(out, hex)?;
+            out.write_str(";\n")?;
+        }
+        let arguments: Vec<&str> = self
+            .fields
+            .iter()
+            .map(|(field, _)| field.as_str())
+            .collect();
+        writeln!(out, "    main({});\n}}", arguments.join(", "))
+    }
+}
+
+/// Adapts an [`io::Write`] to the [`fmt::Write`] the renderers take, keeping
+/// the I/O error that `fmt::Error` cannot carry.
+struct IoWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+fn write_buffered<W: io::Write>(
+    out: W,
+    render: impl FnOnce(&mut IoWriter<io::BufWriter<W>>) -> fmt::Result,
+) -> io::Result<()> {
+    let mut writer = IoWriter {
+        inner: io::BufWriter::new(out),
+        error: None,
+    };
+    match render(&mut writer) {
+        Ok(()) => io::Write::flush(&mut writer.inner),
+        Err(_) => Err(writer
+            .error
+            .unwrap_or_else(|| io::Error::other("cannot render the parameters"))),
+    }
+}
+
+fn write_json_string(out: &mut impl Write, value: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+fn write_canonical_object(out: &mut String, mut entries: Vec<(String, String)>) {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    out.push('{');
+    for (i, (name, value)) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write_json_string(out, name);
+        out.push(':');
+        out.push_str(value);
+    }
+    out.push('}');
+}
@@ -0,0 +1,560 @@
+//! Partial-trie bundles, written by `export-smt`: every node of the verified
+//! account and storage proofs of a batch, keyed by nothing but their
+//! keccak256, with the header and state root they hang from. A bundle is a
+//! snapshot of the state along the proven paths, from which other tools can
+//! build further proofs of those accounts and slots offline, and that
+//! `--from-smt` serves as the data source of a run without any network.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use futures::future::BoxFuture;
+use rlp::Rlp;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use web3::types::{Bytes, Proof, TransactionReceipt, H160, H256, U256};
+
+use crate::error::{Error, Result};
+use crate::header::{BlockHeader, Layout, AURA_SEAL_FIELDS};
+use crate::keccak::keccak256;
+use crate::options::{self, ExportSmtOptions, GenOptions};
+use crate::params::Params;
+use crate::source::EthDataSource;
+use crate::trie::{NodeStore, EMPTY_CODE_HASH, EMPTY_TRIE_ROOT};
+use crate::{compression, encoding, generate_from, proof_order, rpc, trie, PROOF_BYTES_LEN};
+
+pub const BUNDLE_FORMAT: &str = "noir-mip-partial-trie";
+pub const BUNDLE_VERSION: u64 = 1;
+
+/// An account of the bundle and the slots proven under it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleAccount {
+    pub address: H160,
+    pub storage_root: H256,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slots: Vec<H256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    pub version: u64,
+    pub chain_id: u64,
+    pub block: u64,
+    pub block_hash: H256,
+    pub block_header_rlp: Bytes,
+    pub state_root: H256,
+    pub accounts: Vec<BundleAccount>,
+    /// The distinct nodes of the state and storage tries, ordered by hash.
+    pub nodes: Vec<Bytes>,
+}
+
+impl Bundle {
+    /// Reads the bundle at `path`, checking its header against its block
+    /// hash and state root.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bundle: Bundle = serde_json::from_slice(&compression::read(path)?).map_err(|e| {
+            Error::Config(format!(
+                "{} is not a partial-trie bundle: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        if bundle.format != BUNDLE_FORMAT || bundle.version != BUNDLE_VERSION {
+            return Err(Error::Unsupported(format!(
+                "{} is a {} bundle of version {}; this version reads {} version {}",
+                path.display(),
+                bundle.format,
+                bundle.version,
+                BUNDLE_FORMAT,
+                BUNDLE_VERSION
+            )));
+        }
+        let header = &bundle.block_header_rlp.0;
+        let state_root = Rlp::new(header).at(3).and_then(|item| item.data());
+        if H256(keccak256(header)) != bundle.block_hash
+            || state_root.ok() != Some(bundle.state_root.as_bytes())
+        {
+            return Err(Error::Verification(format!(
+                "the header in {} does not hash to its block hash {:?} or lacks its state root",
+                path.display(),
+                bundle.block_hash
+            )));
+        }
+        Ok(bundle)
+    }
+}
+
+/// The accounts of `args` with their slots: `--account`s with every
+/// `--slot`, then the lines of `--batch`, `ACCOUNT [SLOT...]`.
+fn targets(args: &ExportSmtOptions) -> Result<Vec<(H160, Vec<H256>)>> {
+    let mut targets: Vec<(H160, Vec<H256>)> = args
+        .account
+        .iter()
+        .map(|account| (*account, args.slot.clone()))
+        .collect();
+    if let Some(path) = &args.batch {
+        for line in fs::read_to_string(path)?.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let account = words.next().unwrap_or_default();
+            let parsed = options::parse_h160(account).and_then(|account| {
+                Ok((
+                    account,
+                    words
+                        .map(options::parse_h256)
+                        .collect::<std::result::Result<_, _>>()?,
+                ))
+            });
+            targets.push(parsed.map_err(|e| {
+                Error::Config(format!(
+                    "invalid line `{}` in {}: {}",
+                    line,
+                    path.display(),
+                    e
+                ))
+            })?);
+        }
+    }
+    Ok(targets)
+}
+
+/// The unpadded nodes of the proof `name` of `params` and of its tail.
+fn proof_nodes(params: &Params, name: &str) -> Vec<Vec<u8>> {
+    let mut nodes = Vec::new();
+    for part in [name.to_string(), format!("{}_tail", name)] {
+        if let (Some(flat), Some(depth)) = (
+            params.fields.get_bytes(&part),
+            params.fields.get_int(&format!("{}_depth", part)),
+        ) {
+            nodes.extend(trie::unpad_proof(flat, PROOF_BYTES_LEN, depth as usize));
+        }
+    }
+    proof_order::recorded(params).root_first(nodes)
+}
+
+fn field(params: &Params, name: &str) -> Result<H256> {
+    params
+        .fields
+        .get_bytes(name)
+        .filter(|bytes| bytes.len() == 32)
+        .map(H256::from_slice)
+        .ok_or_else(|| Error::Decode(format!("the generated parameters lack {}", name)))
+}
+
+/// Proves every account and slot of `args` at its block, each checked
+/// against the header like a `gen_prove_params` run, and gathers their
+/// nodes into one bundle.
+pub async fn export(args: &ExportSmtOptions) -> Result<Bundle> {
+    let web3 = web3::Web3::new(rpc::Client::new(&args.rpc_url)?);
+    let chain_id = web3.eth().chain_id().await?.as_u64();
+    let mut nodes = BTreeMap::new();
+    let mut accounts: Vec<BundleAccount> = Vec::new();
+    let mut anchor = None;
+    for (address, slots) in targets(args)? {
+        // An account-only proof when no slot is asked for.
+        let keys: Vec<Option<H256>> = match slots.is_empty() {
+            true => vec![None],
+            false => slots.iter().copied().map(Some).collect(),
+        };
+        for slot in keys {
+            let mut gen_args = GenOptions::new(&args.rpc_url, args.block, address, slot);
+            gen_args.split_deep_proofs = true;
+            let params = generate_from(&args.rpc_url, &gen_args, true).await?;
+            for name in ["account_proof", "storage_proof"] {
+                for node in proof_nodes(&params, name) {
+                    nodes.insert(keccak256(&node), node);
+                }
+            }
+            let storage_root = field(&params, "storage_root")?;
+            match accounts
+                .iter_mut()
+                .find(|account| account.address == address)
+            {
+                Some(account) => account.slots.extend(slot),
+                None => accounts.push(BundleAccount {
+                    address,
+                    storage_root,
+                    slots: slot.into_iter().collect(),
+                }),
+            }
+            if anchor.is_none() {
+                let padded = params.fields.get_bytes("block_header_rlp").ok_or_else(|| {
+                    Error::Decode("the generated parameters lack a header".to_string())
+                })?;
+                let rlp = encoding::header_padding(&params)
+                    .unpad(padded)
+                    .ok_or_else(|| Error::Decode("invalid block_header_rlp".to_string()))?;
+                anchor = Some((field(&params, "block_hash")?, rlp.to_vec()));
+            }
+        }
+    }
+    let Some((block_hash, header_rlp)) = anchor else {
+        return Err(Error::Config(
+            "nothing to export; pass --account or --batch".to_string(),
+        ));
+    };
+    let state_root = rlp::Rlp::new(&header_rlp)
+        .at(3)
+        .and_then(|item| item.data().map(H256::from_slice))
+        .map_err(|e| Error::Decode(format!("the header has no state root: {:?}", e)))?;
+    Ok(Bundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        chain_id,
+        block: args.block,
+        block_hash,
+        block_header_rlp: Bytes(header_rlp),
+        state_root,
+        accounts,
+        nodes: nodes.into_values().map(Bytes).collect(),
+    })
+}
+
+/// Serves the block and the proofs of a bundle, rebuilt from its nodes, for
+/// `--from-smt`. A key is covered when the bundle holds every node on its
+/// path, so keys near the exported ones may be provable too, including
+/// absent ones. Nothing else is served.
+pub struct BundleSource {
+    bundle: Bundle,
+    nodes: NodeStore,
+}
+
+impl BundleSource {
+    pub fn new(bundle: Bundle) -> Self {
+        let nodes = bundle
+            .nodes
+            .iter()
+            .map(|node| (keccak256(&node.0), node.0.clone()))
+            .collect();
+        BundleSource { bundle, nodes }
+    }
+
+    fn check_block(&self, block: u64) -> Result<()> {
+        match block == self.bundle.block {
+            true => Ok(()),
+            false => Err(Error::Unsupported(format!(
+                "the bundle holds block {}, not {}",
+                self.bundle.block, block
+            ))),
+        }
+    }
+
+    /// The proof of `key` under `root` and the value it leads to.
+    fn prove(&self, root: H256, key: &[u8], what: &str) -> Result<(Vec<Bytes>, Option<Vec<u8>>)> {
+        let nibbles = trie::key_nibbles(key);
+        let proof = trie::collect_proof(root.0, &nibbles, &self.nodes).map_err(|_| {
+            Error::Unsupported(format!("the bundle does not cover the proof of {}", what))
+        })?;
+        let leaf = trie::verify_proof(root.as_bytes(), &nibbles, &proof).map_err(|e| {
+            Error::Verification(format!(
+                "the bundle's proof of {} is invalid: {:?}",
+                what, e
+            ))
+        })?;
+        Ok((proof.into_iter().map(Bytes).collect(), leaf))
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::Unsupported(format!("a partial-trie bundle holds no {}", what))
+}
+
+impl EthDataSource for BundleSource {
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>> {
+        Box::pin(async move { Ok(self.bundle.chain_id) })
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>> {
+        Box::pin(async move {
+            self.check_block(number)?;
+            let rlp = &self.bundle.block_header_rlp.0;
+            let header = BlockHeader::decode(rlp, Layout::Ethereum)
+                .or_else(|_| BlockHeader::decode(rlp, Layout::Coreth))
+                .or_else(|_| {
+                    BlockHeader::decode_sealed(rlp, Layout::Ethereum, Some(AURA_SEAL_FIELDS))
+                })
+                .map_err(|e| Error::Decode(format!("invalid bundle header: {:?}", e)))?;
+            let mut block = serde_json::to_value(header)
+                .map_err(|e| Error::Decode(format!("cannot serialize the header: {}", e)))?;
+            block["hash"] = json!(self.bundle.block_hash);
+            Ok(block)
+        })
+    }
+
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>> {
+        Box::pin(async move {
+            self.check_block(block)?;
+            let what = format!("{:?}", account);
+            let (account_proof, leaf) =
+                self.prove(self.bundle.state_root, account.as_bytes(), &what)?;
+            let invalid = |e: rlp::DecoderError| {
+                Error::Decode(format!(
+                    "invalid account {:?} in the bundle: {:?}",
+                    account, e
+                ))
+            };
+            let (nonce, balance, storage_hash, code_hash) = match leaf {
+                Some(leaf) => {
+                    let account = Rlp::new(&leaf);
+                    (
+                        account.val_at(0).map_err(invalid)?,
+                        account.val_at(1).map_err(invalid)?,
+                        H256::from_slice(
+                            account
+                                .at(2)
+                                .and_then(|item| item.data())
+                                .map_err(invalid)?,
+                        ),
+                        H256::from_slice(
+                            account
+                                .at(3)
+                                .and_then(|item| item.data())
+                                .map_err(invalid)?,
+                        ),
+                    )
+                }
+                None => (
+                    U256::zero(),
+                    U256::zero(),
+                    H256(EMPTY_TRIE_ROOT),
+                    H256(EMPTY_CODE_HASH),
+                ),
+            };
+            let mut storage_proof = Vec::new();
+            for key in keys {
+                let mut key_bytes = [0u8; 32];
+                key.to_big_endian(&mut key_bytes);
+                let what = format!("slot {:#x} of {:?}", key, account);
+                let (proof, leaf) = self.prove(storage_hash, &key_bytes, &what)?;
+                let value = match leaf {
+                    Some(leaf) => Rlp::new(&leaf).as_val().map_err(invalid)?,
+                    None => U256::zero(),
+                };
+                storage_proof.push(json!({ "key": key, "value": value, "proof": proof }));
+            }
+            // web3 does not export the type of the storage entries, so the
+            // proof is built as the JSON a node answers with.
+            let proof = json!({
+                "balance": balance,
+                "codeHash": code_hash,
+                "nonce": nonce,
+                "storageHash": storage_hash,
+                "accountProof": account_proof,
+                "storageProof": storage_proof,
+            });
+            serde_json::from_value(proof)
+                .map(Some)
+                .map_err(|e| Error::Decode(format!("cannot build the proof: {}", e)))
+        })
+    }
+
+    fn get_code(&self, _: H160, _: u64) -> BoxFuture<'_, Result<Bytes>> {
+        Box::pin(async { Err(unsupported("code")) })
+    }
+
+    fn get_receipts(&self, _: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>> {
+        Box::pin(async { Err(unsupported("receipts")) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::RlpStream;
+    use std::collections::BTreeSet;
+
+    /// The entries of a trie, as `trie::build_proof` takes them.
+    type Entries = Vec<(Vec<u8>, Vec<u8>)>;
+
+    /// A state of a few accounts with a few slots each, as fully built
+    /// tries: every key is in `state` or `storage`, under its keccak256.
+    struct World {
+        state: Entries,
+        storage: BTreeMap<H160, Entries>,
+    }
+
+    fn address(i: u64) -> H160 {
+        H160::from_low_u64_be(i)
+    }
+
+    fn slot(i: u64) -> H256 {
+        H256::from_low_u64_be(i)
+    }
+
+    impl World {
+        fn new() -> Self {
+            let mut state = Vec::new();
+            let mut storage = BTreeMap::new();
+            for i in 1..=6 {
+                let slots: Vec<_> = (0..5)
+                    .map(|j| {
+                        let value = U256::from(i * 100 + j + 1);
+                        (
+                            keccak256(slot(j).as_bytes()).to_vec(),
+                            rlp::encode(&value).to_vec(),
+                        )
+                    })
+                    .collect();
+                let (storage_root, _) = trie::build_proof(&slots, &[]);
+                let mut leaf = RlpStream::new_list(4);
+                leaf.append(&U256::from(i))
+                    .append(&U256::from(i * 1000))
+                    .append(&H256(storage_root))
+                    .append(&H256(EMPTY_CODE_HASH));
+                state.push((
+                    keccak256(address(i).as_bytes()).to_vec(),
+                    leaf.out().to_vec(),
+                ));
+                storage.insert(address(i), slots);
+            }
+            World { state, storage }
+        }
+
+        fn state_proof(&self, account: H160) -> ([u8; 32], Vec<Vec<u8>>) {
+            trie::build_proof(&self.state, &keccak256(account.as_bytes()))
+        }
+
+        fn storage_proof(&self, account: H160, key: H256) -> ([u8; 32], Vec<Vec<u8>>) {
+            trie::build_proof(&self.storage[&account], &keccak256(key.as_bytes()))
+        }
+
+        /// A bundle of the proofs of `account` and `slots` alone.
+        fn bundle(&self, account: H160, slots: &[H256]) -> Bundle {
+            let (state_root, proof) = self.state_proof(account);
+            let mut nodes: BTreeMap<[u8; 32], Vec<u8>> = proof
+                .into_iter()
+                .map(|node| (keccak256(&node), node))
+                .collect();
+            let (storage_root, _) = self.storage_proof(account, slot(0));
+            for &key in slots {
+                for node in self.storage_proof(account, key).1 {
+                    nodes.insert(keccak256(&node), node);
+                }
+            }
+            Bundle {
+                format: BUNDLE_FORMAT.to_string(),
+                version: BUNDLE_VERSION,
+                chain_id: 1,
+                block: 7,
+                block_hash: H256::zero(),
+                block_header_rlp: Bytes::default(),
+                state_root: H256(state_root),
+                accounts: vec![BundleAccount {
+                    address: account,
+                    storage_root: H256(storage_root),
+                    slots: slots.to_vec(),
+                }],
+                nodes: nodes.into_values().map(Bytes).collect(),
+            }
+        }
+    }
+
+    fn get_proof(source: &BundleSource, account: H160, slots: &[H256]) -> Result<Proof> {
+        let keys = slots
+            .iter()
+            .map(|key| U256::from_big_endian(key.as_bytes()))
+            .collect();
+        futures::executor::block_on(source.get_proof(account, keys, 7)).map(Option::unwrap)
+    }
+
+    fn nodes(proof: &[Bytes]) -> Vec<Vec<u8>> {
+        proof.iter().map(|node| node.0.clone()).collect()
+    }
+
+    /// Whether every node of `proof` is in `bundle`.
+    fn covered(bundle: &Bundle, proof: &[Vec<u8>]) -> bool {
+        let held: BTreeSet<&[u8]> = bundle.nodes.iter().map(|node| node.0.as_slice()).collect();
+        proof.iter().all(|node| held.contains(node.as_slice()))
+    }
+
+    #[test]
+    fn exported_keys_are_proven_as_the_full_trie_proves_them() {
+        let world = World::new();
+        let bundle = world.bundle(address(2), &[slot(0), slot(3)]);
+        let source = BundleSource::new(bundle);
+        let proof = get_proof(&source, address(2), &[slot(3), slot(0)]).unwrap();
+        assert_eq!(nodes(&proof.account_proof), world.state_proof(address(2)).1);
+        assert_eq!(proof.nonce, U256::from(2));
+        assert_eq!(proof.balance, U256::from(2000));
+        assert_eq!(
+            proof.storage_hash,
+            H256(world.storage_proof(address(2), slot(0)).0)
+        );
+        assert_eq!(proof.code_hash, H256(EMPTY_CODE_HASH));
+        for (entry, (key, value)) in proof.storage_proof.iter().zip([(3, 204), (0, 201)]) {
+            assert_eq!(entry.value, U256::from(value));
+            assert_eq!(
+                nodes(&entry.proof),
+                world.storage_proof(address(2), slot(key)).1
+            );
+        }
+    }
+
+    #[test]
+    fn absent_keys_on_exported_paths_are_proven_absent() {
+        let world = World::new();
+        let bundle = world.bundle(address(2), &[slot(0), slot(3)]);
+        // Keys that are in neither trie, whose paths end in nodes the
+        // bundle holds.
+        let account = (100..)
+            .map(address)
+            .find(|&account| covered(&bundle, &world.state_proof(account).1))
+            .unwrap();
+        let key = (100..)
+            .map(slot)
+            .find(|&key| covered(&bundle, &world.storage_proof(address(2), key).1))
+            .unwrap();
+        let source = BundleSource::new(bundle);
+
+        let proof = get_proof(&source, account, &[]).unwrap();
+        assert_eq!(nodes(&proof.account_proof), world.state_proof(account).1);
+        assert_eq!(proof.balance, U256::zero());
+        assert_eq!(proof.storage_hash, H256(EMPTY_TRIE_ROOT));
+
+        let proof = get_proof(&source, address(2), &[key]).unwrap();
+        assert_eq!(proof.storage_proof[0].value, U256::zero());
+        assert_eq!(
+            nodes(&proof.storage_proof[0].proof),
+            world.storage_proof(address(2), key).1
+        );
+    }
+
+    #[test]
+    fn keys_under_subtrees_the_bundle_lacks_are_refused() {
+        let world = World::new();
+        let bundle = world.bundle(address(2), &[slot(0)]);
+        // Keys of the full tries whose leaves the bundle only holds as a
+        // hash in their parent.
+        let account = (1..=6)
+            .map(address)
+            .find(|&account| !covered(&bundle, &world.state_proof(account).1))
+            .unwrap();
+        let key = (1..5)
+            .map(slot)
+            .find(|&key| !covered(&bundle, &world.storage_proof(address(2), key).1))
+            .unwrap();
+        let source = BundleSource::new(bundle);
+
+        for (account, slots) in [(account, vec![]), (address(2), vec![key])] {
+            match get_proof(&source, account, &slots) {
+                Err(Error::Unsupported(message)) => {
+                    assert!(message.contains("does not cover"), "{}", message)
+                }
+                other => panic!("{:?}", other.map(|proof| proof.balance)),
+            }
+        }
+        assert!(matches!(
+            futures::executor::block_on(source.get_proof(address(2), vec![], 8)),
+            Err(Error::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+use futures::stream::{self, StreamExt, TryStreamExt};
+use web3::types::{BlockId, BlockNumber, Bytes, CallRequest, H160, H256, U256, U64};
+
+use crate::chain_spec::ChainSpec;
+use crate::error::{Error, Result};
+use crate::header::{self, BlockHeader};
+use crate::keccak::keccak256;
+use crate::params::{Params, Value};
+use crate::rpc;
+use crate::source::EthDataSource;
+
+pub const CHAIN_ID: u64 = 137;
+
+/// The `RootChainProxy` contract on Ethereum that Polygon PoS checkpoints
+/// are submitted to.
+pub const ROOT_CHAIN: H160 = H160([
+    0x86, 0xe4, 0xdc, 0x95, 0xc7, 0xfb, 0xdb, 0xf5, 0x2e, 0x33, 0xd5, 0x63, 0xbb, 0xdb, 0x00, 0x82,
+    0x38, 0x94, 0xc2, 0x87,
+]);
+
+/// Deepest checkpoint Merkle proof the circuit takes, enough for
+/// checkpoints of 65536 blocks.
+pub const CHECKPOINT_PROOF_MAX_DEPTH: usize = 16;
+
+/// Checkpoint ids advance by this much, leaving room for deposits between
+/// them.
+const CHECKPOINT_ID_STEP: u64 = 10_000;
+
+/// `currentHeaderBlock()`
+const CURRENT_HEADER_BLOCK: [u8; 4] = [0xec, 0x7e, 0x48, 0x55];
+/// `headerBlocks(uint256)`
+const HEADER_BLOCKS: [u8; 4] = [0x41, 0x53, 0x9d, 0x4a];
+
+/// Headers fetched at once while rebuilding a checkpoint.
+const HEADER_CONCURRENCY: usize = 16;
+
+/// A range of Polygon blocks committed to Ethereum by its Merkle root.
+pub struct Checkpoint {
+    pub id: u64,
+    pub root: H256,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Leaf of `header` in a checkpoint tree: the hash of its number,
+/// timestamp, transactions root and receipts root, each 32 bytes.
+pub fn leaf(header: &BlockHeader) -> [u8; 32] {
+    let mut data = [0u8; 128];
+    U256::from(header.number.as_u64()).to_big_endian(&mut data[..32]);
+    header.timestamp.to_big_endian(&mut data[32..64]);
+    data[64..96].copy_from_slice(header.transactions_root.as_bytes());
+    data[96..].copy_from_slice(header.receipts_root.as_bytes());
+    keccak256(&data)
+}
+
+/// Root of the checkpoint tree over `leaves` and the proof of the leaf at
+/// `index`, siblings from the bottom up. The leaves are padded with zero
+/// hashes to a power of two, as the checkpoint submitters do.
+pub fn merkle_proof(leaves: &[[u8; 32]], mut index: usize) -> ([u8; 32], Vec<[u8; 32]>) {
+    let mut layer = leaves.to_vec();
+    layer.resize(leaves.len().next_power_of_two(), [0; 32]);
+    let mut proof = Vec::new();
+    while layer.len() > 1 {
+        proof.push(layer[index ^ 1]);
+        layer = layer
+            .chunks(2)
+            .map(|pair| keccak256(&[pair[0], pair[1]].concat()))
+            .collect();
+        index /= 2;
+    }
+    (layer[0], proof)
+}
+
+async fn call(l1: &web3::Web3<rpc::Client>, data: Vec<u8>, l1_block: u64) -> Result<Vec<u8>> {
+    let request = CallRequest {
+        to: Some(ROOT_CHAIN),
+        data: Some(Bytes(data)),
+        ..Default::default()
+    };
+    let block = BlockId::Number(BlockNumber::Number(U64::from(l1_block)));
+    Ok(l1.eth().call(request, Some(block)).await?.0)
+}
+
+fn word(output: &[u8], index: usize) -> Result<&[u8]> {
+    output.get(index * 32..(index + 1) * 32).ok_or_else(|| {
+        Error::Decode(format!(
+            "the root chain contract returned {} bytes, too short for a checkpoint",
+            output.len()
+        ))
+    })
+}
+
+async fn checkpoint(l1: &web3::Web3<rpc::Client>, id: u64, l1_block: u64) -> Result<Checkpoint> {
+    let mut data = HEADER_BLOCKS.to_vec();
+    data.extend_from_slice(&[0; 24]);
+    data.extend_from_slice(&id.to_be_bytes());
+    let output = call(l1, data, l1_block).await?;
+    Ok(Checkpoint {
+        id,
+        root: H256::from_slice(word(&output, 0)?),
+        start: U256::from_big_endian(word(&output, 1)?).low_u64(),
+        end: U256::from_big_endian(word(&output, 2)?).low_u64(),
+    })
+}
+
+/// Finds the checkpoint covering Polygon block `number` among those
+/// submitted by Ethereum block `l1_block`, by binary search over the
+/// checkpoint ids.
+pub async fn find_checkpoint(
+    l1: &web3::Web3<rpc::Client>,
+    number: u64,
+    l1_block: u64,
+) -> Result<Checkpoint> {
+    let current = call(l1, CURRENT_HEADER_BLOCK.to_vec(), l1_block).await?;
+    let count = U256::from_big_endian(word(&current, 0)?).low_u64() / CHECKPOINT_ID_STEP;
+    let latest = checkpoint(l1, count * CHECKPOINT_ID_STEP, l1_block).await?;
+    if count == 0 || latest.end < number {
+        return Err(Error::Chain(format!(
+            "Polygon block {} is not checkpointed on Ethereum yet; the checkpoints at Ethereum \
+             block {} end at block {}",
+            number, l1_block, latest.end
+        )));
+    }
+    let (mut low, mut high) = (1, count);
+    while low < high {
+        let middle = (low + high) / 2;
+        if checkpoint(l1, middle * CHECKPOINT_ID_STEP, l1_block)
+            .await?
+            .end
+            < number
+        {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    let found = checkpoint(l1, low * CHECKPOINT_ID_STEP, l1_block).await?;
+    if found.start > number {
+        return Err(Error::Chain(format!(
+            "no checkpoint covers Polygon block {}; checkpoint {} starts at block {}",
+            number, found.id, found.start
+        )));
+    }
+    Ok(found)
+}
+
+/// Proves that `block` of the Polygon provider at `rpc_url` is in
+/// the latest Ethereum checkpoint covering it, and adds the linkage to
+/// `params`: the checkpoint root and, with `prove`, the block's leaf index
+/// and its Merkle proof. The block's hash must match the `block_hash`
+/// already generated.
+pub async fn link_checkpoint(
+    rpc_url: &str,
+    l1_rpc_url: &str,
+    block: u64,
+    prove: bool,
+    params: &mut Params,
+) -> Result<()> {
+    let source = web3::Web3::new(rpc::Client::new(rpc_url)?);
+    let chain_id = source.chain_id().await?;
+    if chain_id != CHAIN_ID {
+        return Err(Error::Chain(format!(
+            "--chain polygon expects chain {}, but the provider serves chain {}",
+            CHAIN_ID, chain_id
+        )));
+    }
+    let l1 = web3::Web3::new(rpc::Client::new(l1_rpc_url)?);
+    let l1_block = l1.eth().block_number().await?.as_u64();
+    let checkpoint = find_checkpoint(&l1, block, l1_block).await?;
+
+    let spec = ChainSpec::known(CHAIN_ID);
+    let headers: Vec<(BlockHeader, H256)> = stream::iter(checkpoint.start..=checkpoint.end)
+        .map(|number| header::fetch(&source, number, &spec))
+        .buffered(HEADER_CONCURRENCY)
+        .try_collect()
+        .await?;
+    let index = (block - checkpoint.start) as usize;
+    if params.fields.get("block_hash") != Some(&Value::from(headers[index].1.as_bytes())) {
+        return Err(Error::Chain(format!(
+            "the checkpointed block {} has hash {:?}, not the generated block hash",
+            block, headers[index].1
+        )));
+    }
+    let leaves: Vec<[u8; 32]> = headers.iter().map(|(header, _)| leaf(header)).collect();
+    let (root, proof) = merkle_proof(&leaves, index);
+    if root != checkpoint.root.0 {
+        return Err(Error::Chain(format!(
+            "blocks {} to {} build the checkpoint root {:?}, not the {:?} checkpoint {} holds",
+            checkpoint.start,
+            checkpoint.end,
+            H256(root),
+            checkpoint.root,
+            checkpoint.id
+        )));
+    }
+    if proof.len() > CHECKPOINT_PROOF_MAX_DEPTH {
+        return Err(Error::Unsupported(format!(
+            "checkpoint {} covers {} blocks, deeper than the circuit's {} levels",
+            checkpoint.id,
+            leaves.len(),
+            CHECKPOINT_PROOF_MAX_DEPTH
+        )));
+    }
+
+    let mut flat = proof.concat();
+    flat.resize(CHECKPOINT_PROOF_MAX_DEPTH * 32, 0);
+    params.push("checkpoint_root", checkpoint.root.as_bytes());
+    if prove {
+        params.push("checkpoint_leaf_index", index);
+        params.push("checkpoint_proof", flat);
+        params.push("checkpoint_proof_depth", proof.len());
+    }
+    params.meta.push("checkpoint_id", checkpoint.id as usize);
+    params
+        .meta
+        .push("checkpoint_start", checkpoint.start as usize);
+    params.meta.push("checkpoint_end", checkpoint.end as usize);
+    params.meta.push("root_chain", format!("{:?}", ROOT_CHAIN));
+    params.meta.push("l1_block", l1_block as usize);
+    params.record_witness_hash();
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use web3::{BatchTransport, Transport, Web3};
+
+use crate::error::{Error, Result};
+use crate::rpc::{self, Client};
+use crate::source::EthDataSource;
+
+/// `eth_blockNumber` calls timed for the latency line.
+const LATENCY_SAMPLES: usize = 5;
+
+/// Whether the provider serves `eth_getProof` at `block`, with the error
+/// it returned when it does not.
+async fn proof_at(web3: &Web3<Client>, block: u64) -> std::result::Result<(), Error> {
+    web3.get_proof(Default::default(), Vec::new(), block)
+        .await?
+        .map(|_| ())
+        .ok_or_else(|| Error::UnexpectedValue(format!("no proof returned for block {}", block)))
+}
+
+/// The oldest block whose state the provider still proves, found by
+/// bisection below `latest`, which it is known to prove.
+/// Nodes prune state from the oldest block onwards, so availability is
+/// monotonic in the block number.
+async fn oldest_state(web3: &Web3<Client>, latest: u64) -> u64 {
+    if proof_at(web3, 0).await.is_ok() {
+        return 0;
+    }
+    let (mut missing, mut served) = (0, latest);
+    while served - missing > 1 {
+        let middle = missing + (served - missing) / 2;
+        if proof_at(web3, middle).await.is_ok() {
+            served = middle;
+        } else {
+            missing = middle;
+        }
+    }
+    served
+}
+
+/// Median latency of `eth_blockNumber`.
+async fn latency(web3: &Web3<Client>) -> Result<Duration> {
+    let mut samples = Vec::with_capacity(LATENCY_SAMPLES);
+    for _ in 0..LATENCY_SAMPLES {
+        let started = Instant::now();
+        web3.eth().block_number().await?;
+        samples.push(started.elapsed());
+    }
+    samples.sort();
+    Ok(samples[LATENCY_SAMPLES / 2])
+}
+
+/// Whether a JSON-RPC batch of two calls comes back with both answered.
+async fn batch_support(rpc_url: &str) -> Result<()> {
+    let http = rpc::http_transport(rpc_url)?;
+    let calls = ["eth_chainId", "eth_blockNumber"].map(|method| http.prepare(method, Vec::new()));
+    for result in http.send_batch(calls).await? {
+        result?;
+    }
+    Ok(())
+}
+
+/// The provider's own message for RPC errors, the error otherwise.
+fn reason(e: Error) -> String {
+    match e {
+        Error::Rpc(web3::Error::Rpc(rpc)) => rpc.message,
+        e => e.to_string(),
+    }
+}
+
+fn yes_or(outcome: std::result::Result<(), String>) -> String {
+    match outcome {
+        Ok(()) => "yes".to_string(),
+        Err(e) => format!("no ({})", e),
+    }
+}
+
+/// What a provider can serve, as [`probe`] found it.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    /// The endpoint, with any credentials redacted.
+    pub provider: String,
+    pub chain_id: u64,
+    pub latest: u64,
+    /// Median latency of `eth_blockNumber`.
+    pub latency: Duration,
+    /// Whether `eth_getProof` works, with the provider's reason if not.
+    pub proofs: std::result::Result<(), String>,
+    /// The oldest block with state, when `eth_getProof` works.
+    pub oldest: Option<u64>,
+    pub batches: std::result::Result<(), String>,
+    pub block_receipts: std::result::Result<(), String>,
+}
+
+impl Probe {
+    /// One line per capability.
+    pub fn render(&self) -> String {
+        let oldest = match self.oldest {
+            Some(0) => "block 0 (archive node)".to_string(),
+            Some(oldest) => format!(
+                "block {} ({} blocks back; older blocks need an archive node)",
+                oldest,
+                self.latest - oldest
+            ),
+            None => "unknown".to_string(),
+        };
+        [
+            format!("Provider:             {}", self.provider),
+            format!("Chain id:             {}", self.chain_id),
+            format!("Latest block:         {}", self.latest),
+            format!(
+                "Latency:              {:.1?} (median of {} eth_blockNumber calls)",
+                self.latency, LATENCY_SAMPLES
+            ),
+            format!("eth_getProof:         {}", yes_or(self.proofs.clone())),
+            format!("Oldest state:         {}", oldest),
+            format!("Batch requests:       {}", yes_or(self.batches.clone())),
+            format!(
+                "eth_getBlockReceipts: {}",
+                yes_or(self.block_receipts.clone())
+            ),
+        ]
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+    }
+
+    /// Fails unless the provider can prove `block`.
+    pub fn serves(&self, block: u64) -> Result<()> {
+        match self.oldest {
+            _ if block > self.latest => Err(Error::UnminedBlock(format!(
+                "block {} is past the provider's latest block, {}",
+                block, self.latest
+            ))),
+            Some(oldest) if block >= oldest => Ok(()),
+            Some(oldest) => Err(Error::Unsupported(format!(
+                "the provider has no state for block {}; its oldest is block {}, so use an \
+                 archive node",
+                block, oldest
+            ))),
+            None => Err(Error::Unsupported(format!(
+                "the provider cannot prove block {} without eth_getProof",
+                block
+            ))),
+        }
+    }
+}
+
+/// Checks what the provider at `rpc_url` can serve: the chain id, the
+/// latency, `eth_getProof`, the oldest block with state, JSON-RPC batches
+/// and `eth_getBlockReceipts`.
+pub async fn probe(rpc_url: &str) -> Result<Probe> {
+    let web3 = Web3::new(Client::new(rpc_url)?);
+    let chain_id = EthDataSource::chain_id(&web3).await?;
+    let latest = web3.eth().block_number().await?.as_u64();
+    let latency = latency(&web3).await?;
+    let proofs = proof_at(&web3, latest).await;
+    let oldest = match &proofs {
+        Ok(()) => Some(oldest_state(&web3, latest).await),
+        Err(_) => None,
+    };
+    Ok(Probe {
+        provider: rpc::redact(rpc_url),
+        chain_id,
+        latest,
+        latency,
+        proofs: proofs.map_err(reason),
+        oldest,
+        batches: batch_support(rpc_url).await.map_err(reason),
+        block_receipts: web3.get_receipts(latest).await.map(|_| ()).map_err(reason),
+    })
+}
@@ -0,0 +1,55 @@
+//! Machine-readable progress for schedulers driving long batch runs.
+//! `--progress jsonl` writes one JSON object per line to stdout as each
+//! step starts or ends: `fetch_started` before a proof is requested,
+//! `proof_received` when it arrives (or comes from the proof cache),
+//! `verified` once it checks out against its root, and `written` for every
+//! file saved. Each event carries `event`, a `ts_ms` Unix timestamp in
+//! milliseconds and the step's details.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum ProgressFormat {
+    /// No progress events
+    #[default]
+    None,
+    /// One JSON event per line on stdout
+    Jsonl,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Writes progress events in `format` from now on.
+pub fn set_progress(format: ProgressFormat) {
+    ENABLED.store(format == ProgressFormat::Jsonl, Ordering::Relaxed);
+}
+
+/// Whether progress events go to stdout, which then carries nothing else.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Writes the event `name` with the fields of the JSON object `details`.
+pub fn event(name: &str, details: Value) {
+    if !enabled() {
+        return;
+    }
+    let ts_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    let mut line = json!({ "event": name, "ts_ms": ts_ms });
+    if let (Some(line), Value::Object(details)) = (line.as_object_mut(), details) {
+        line.extend(details);
+    }
+    // One write per event, so concurrent workers never interleave a line.
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
@@ -0,0 +1,128 @@
+//! `--proof-order leaf-first`: emits both proofs from the leaf up to the
+//! root rather than from the root down, for circuits that verify upwards
+//! and would otherwise index the proof backwards. The nodes still fill the
+//! first `depth` slots with the zero padding after them, and the per-node
+//! hints and hashes follow them. The order is recorded in the metadata as
+//! `proof_order`, so readers of the witness can walk it from the root.
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::error::{Error, Result};
+use crate::params::{Params, Value};
+use crate::PROOF_BYTES_LEN;
+
+/// Fields laid out node by node like a proof, by their suffix to its name.
+const PER_NODE: &[&str] = &[
+    "",
+    "_node_hashes",
+    "_item_counts",
+    "_item_offsets",
+    "_item_lengths",
+    "_path_indices",
+];
+
+/// The order the nodes of a proof are emitted in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum ProofOrder {
+    /// From the root node to the leaf, as `eth_getProof` returns them
+    #[default]
+    RootFirst,
+    /// From the leaf node to the root
+    LeafFirst,
+}
+
+impl ProofOrder {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProofOrder::RootFirst => "root-first",
+            ProofOrder::LeafFirst => "leaf-first",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ProofOrder::RootFirst, ProofOrder::LeafFirst]
+            .into_iter()
+            .find(|order| order.name() == name)
+    }
+
+    /// `nodes`, unpadded from a proof emitted in this order, from the root.
+    pub fn root_first(&self, mut nodes: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        if *self == ProofOrder::LeafFirst {
+            nodes.reverse();
+        }
+        nodes
+    }
+}
+
+/// The proof order `params` record, root-first when they record none.
+pub fn recorded(params: &Params) -> ProofOrder {
+    match params.meta.get("proof_order") {
+        Some(Value::Str(name)) => ProofOrder::from_name(name).unwrap_or_default(),
+        _ => ProofOrder::default(),
+    }
+}
+
+/// `value`, laid out in `slots` equal chunks, with its first `depth`
+/// chunks reversed.
+fn reverse_nodes(value: &mut Value, slots: usize, depth: usize) {
+    match value {
+        Value::Bytes(items) | Value::Hex(items) => reverse_chunks(items, slots, depth),
+        Value::Array(items) => reverse_chunks(items, slots, depth),
+        _ => {}
+    }
+}
+
+fn reverse_chunks<T>(items: &mut [T], slots: usize, depth: usize) {
+    let stride = items.len() / slots;
+    if stride == 0 {
+        return;
+    }
+    // Reversing each node and then the whole span reverses the nodes and
+    // leaves the items of each in order.
+    let used = &mut items[..depth.min(slots) * stride];
+    used.chunks_mut(stride).for_each(<[T]>::reverse);
+    used.reverse();
+}
+
+/// Reverses every proof of `params` and the fields laid out node by node
+/// with it. A proof split by `--split-deep-proofs` is refused: its upper
+/// segment links to the lower one, which reversing the two apart breaks.
+pub fn leaf_first(params: &mut Params) -> Result<()> {
+    let proofs: Vec<(String, usize, usize)> = params
+        .fields
+        .iter()
+        .filter(|(name, _)| name.ends_with("_proof"))
+        .filter_map(|(name, value)| {
+            let Value::Bytes(flat) = value else {
+                return None;
+            };
+            let depth = params.fields.get_int(&format!("{}_depth", name))?;
+            Some((name.clone(), flat.len() / PROOF_BYTES_LEN, depth as usize))
+        })
+        .collect();
+    for (name, slots, depth) in proofs {
+        if params.fields.get(&format!("{}_tail", name)).is_some() {
+            return Err(Error::Unsupported(format!(
+                "--proof-order leaf-first reverses whole proofs; {} is split into segments by \
+                 --split-deep-proofs",
+                name
+            )));
+        }
+        if slots == 0 {
+            continue;
+        }
+        for suffix in PER_NODE {
+            let field = format!("{}{}", name, suffix);
+            if let Some(mut value) = params.fields.get(&field).cloned() {
+                reverse_nodes(&mut value, slots, depth);
+                params.fields.set(&field, value);
+            }
+        }
+    }
+    params
+        .meta
+        .push("proof_order", ProofOrder::LeafFirst.name());
+    Ok(())
+}
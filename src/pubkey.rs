@@ -0,0 +1,53 @@
+//! `--pubkey`: proves the account a secp256k1 public key controls, so a
+//! circuit linking a key to its on-chain state gets the key and the
+//! account inputs from one run. The address is the last 20 bytes of the
+//! keccak256 of the uncompressed key without its `0x04` prefix.
+
+use ethereum_types::H160;
+use openssl::bn::BigNumContext;
+use openssl::ec::{EcGroup, EcPoint, PointConversionForm};
+use openssl::nid::Nid;
+
+use crate::keccak::keccak256;
+
+/// An uncompressed secp256k1 public key, checked to be on the curve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey([u8; 65]);
+
+impl PublicKey {
+    /// Reads a key given uncompressed (`0x04` and 64 bytes), as its 64
+    /// bytes of coordinates alone, or compressed (`0x02` or `0x03` and 32
+    /// bytes).
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let bytes = match bytes.len() {
+            64 => [&[4u8][..], bytes].concat(),
+            33 | 65 => bytes.to_vec(),
+            len => {
+                return Err(format!(
+                    "expected a 33 or 65 byte public key, got {} bytes",
+                    len
+                ))
+            }
+        };
+        fn invalid<E>(_: E) -> String {
+            "not a point on the secp256k1 curve".to_string()
+        }
+        let group = EcGroup::from_curve_name(Nid::SECP256K1).map_err(invalid)?;
+        let mut ctx = BigNumContext::new().map_err(invalid)?;
+        let point = EcPoint::from_bytes(&group, &bytes, &mut ctx).map_err(invalid)?;
+        let uncompressed = point
+            .to_bytes(&group, PointConversionForm::UNCOMPRESSED, &mut ctx)
+            .map_err(invalid)?;
+        uncompressed.try_into().map(PublicKey).map_err(invalid)
+    }
+
+    /// The uncompressed key, `0x04` first.
+    pub fn as_bytes(&self) -> &[u8; 65] {
+        &self.0
+    }
+
+    /// The address of the account the key controls.
+    pub fn address(&self) -> H160 {
+        H160::from_slice(&keccak256(&self.0[1..])[12..])
+    }
+}
@@ -0,0 +1,192 @@
+use rlp::RlpStream;
+use web3::types::TransactionReceipt;
+
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::trie;
+
+/// Highest EIP-2718 transaction type whose receipt encoding is known:
+/// access list (1), dynamic fee (2), blob (3) and set code (4) transactions
+/// all share the legacy receipt payload.
+pub const MAX_TX_TYPE: u64 = 4;
+
+/// EIP-2718 type of the transaction `receipt` belongs to; 0 for legacy
+/// transactions and providers that leave the type out.
+pub fn tx_type(receipt: &TransactionReceipt) -> u64 {
+    receipt
+        .transaction_type
+        .map_or(0, |tx_type| tx_type.as_u64())
+}
+
+/// Encodes `receipt` as the receipt trie stores it: the RLP list of status
+/// (or the post-state root before Byzantium), cumulative gas used, logs bloom
+/// and logs, prefixed with the transaction type for typed transactions.
+/// `byzantium` tells whether its block is past Byzantium; providers report
+/// a status, a root or both whatever the fork, so the field is chosen by it.
+pub fn encode(receipt: &TransactionReceipt, byzantium: bool) -> Result<Vec<u8>> {
+    let tx_type = tx_type(receipt);
+    if tx_type > MAX_TX_TYPE {
+        return Err(Error::Unsupported(format!(
+            "the receipt of transaction {:?} is of type {:#04x}, whose encoding is unknown",
+            receipt.transaction_hash, tx_type
+        )));
+    }
+    let mut stream = RlpStream::new_list(4);
+    // Receipts from before Byzantium commit to the post-state root; a
+    // provider that also reports a status for them derived it.
+    match (byzantium, receipt.status, receipt.root) {
+        (true, Some(status), _) => stream.append(&status.as_u64()),
+        (false, _, Some(root)) => stream.append(&root),
+        (true, None, _) => {
+            return Err(Error::Decode(format!(
+                "the receipt of transaction {:?} has no status, though its block is past \
+                 Byzantium",
+                receipt.transaction_hash
+            )))
+        }
+        (false, _, None) => {
+            return Err(Error::Decode(format!(
+                "the receipt of transaction {:?} has no post-state root, though its block is \
+                 before Byzantium; set byzantium_block in a --chain-spec if the chain \
+                 activated it elsewhere",
+                receipt.transaction_hash
+            )))
+        }
+    };
+    stream
+        .append(&receipt.cumulative_gas_used)
+        .append(&receipt.logs_bloom)
+        .begin_list(receipt.logs.len());
+    for log in &receipt.logs {
+        stream
+            .begin_list(3)
+            .append(&log.address)
+            .append_list(&log.topics)
+            .append(&log.data.0);
+    }
+    let mut encoded = Vec::new();
+    if tx_type > 0 {
+        encoded.push(tx_type as u8);
+    }
+    encoded.extend_from_slice(&stream.out());
+    Ok(encoded)
+}
+
+/// The three bits of a 2048-bit logs bloom that `item`, a log's address or
+/// one of its topics, sets: the low 11 bits of the first three big-endian
+/// byte pairs of its keccak digest.
+pub fn bloom_bits(item: &[u8]) -> [usize; 3] {
+    let hash = keccak256(item);
+    [0, 1, 2].map(|i| (usize::from(hash[2 * i]) << 8 | usize::from(hash[2 * i + 1])) & 2047)
+}
+
+/// Whether `bit` is set in `bloom`, whose last byte holds the bits 0 to 7.
+pub fn bloom_bit_set(bloom: &[u8; 256], bit: usize) -> bool {
+    bloom[255 - bit / 8] & (1 << (bit % 8)) != 0
+}
+
+/// Key of the transaction at `index` in the transaction and receipt tries.
+pub fn index_key(index: usize) -> Vec<u8> {
+    rlp::encode(&(index as u64)).to_vec()
+}
+
+/// A receipt with its proof in the receipt trie of its block.
+pub struct ReceiptProof {
+    pub root: [u8; 32],
+    /// The receipt as the trie stores it.
+    pub receipt: Vec<u8>,
+    /// Trie nodes from the root to the receipt's leaf.
+    pub nodes: Vec<Vec<u8>>,
+}
+
+/// The receipt trie entries of a block from its `receipts`, in block order.
+fn entries(receipts: &[TransactionReceipt], byzantium: bool) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    receipts
+        .iter()
+        .enumerate()
+        .map(|(i, receipt)| Ok((index_key(i), encode(receipt, byzantium)?)))
+        .collect()
+}
+
+/// The root of the receipt trie of a block from its `receipts`, in block
+/// order, as its header's `receiptsRoot` commits to it.
+pub fn root(receipts: &[TransactionReceipt], byzantium: bool) -> Result<[u8; 32]> {
+    Ok(trie::build_proof(&entries(receipts, byzantium)?, &index_key(0)).0)
+}
+
+/// Builds the receipt trie of a block from its `receipts`, in block order,
+/// and proves the receipt at `index`.
+pub fn prove(
+    receipts: &[TransactionReceipt],
+    index: usize,
+    byzantium: bool,
+) -> Result<ReceiptProof> {
+    let entries = entries(receipts, byzantium)?;
+    let receipt = entries
+        .get(index)
+        .map(|(_, value)| value.clone())
+        .ok_or_else(|| {
+            Error::Decode(format!(
+                "the block has {} transaction(s), none at index {}",
+                receipts.len(),
+                index
+            ))
+        })?;
+    let (root, nodes) = trie::build_proof(&entries, &index_key(index));
+    Ok(ReceiptProof {
+        root,
+        receipt,
+        nodes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_spec::ChainSpec;
+    use crate::{forks, header};
+
+    const GENESIS: &str = include_str!("../fixtures/genesis_block.json");
+    /// The first receipt of mainnet block 1,000,000, before Byzantium, as a
+    /// provider reports it.
+    const PRE_BYZANTIUM: &str = include_str!("../fixtures/receipt_1000000.json");
+
+    fn pre_byzantium() -> TransactionReceipt {
+        serde_json::from_str(PRE_BYZANTIUM).unwrap()
+    }
+
+    #[test]
+    fn the_genesis_block_commits_to_no_receipts() {
+        let spec = ChainSpec::known(1);
+        let (header, _) =
+            header::from_rpc(serde_json::from_str(GENESIS).unwrap(), 0, &spec).unwrap();
+        let byzantium = spec.schedule.unwrap().byzantium(0);
+        assert!(!byzantium);
+        assert_eq!(root(&[], byzantium).unwrap(), header.receipts_root.0);
+    }
+
+    #[test]
+    fn receipts_before_byzantium_commit_to_the_state_root() {
+        let mut receipt = pre_byzantium();
+        let schedule = forks::schedule(1).unwrap();
+        let byzantium = schedule.byzantium(receipt.block_number.unwrap().as_u64());
+        assert!(!byzantium);
+        let root = receipt.root.unwrap();
+        let encoded = encode(&receipt, byzantium).unwrap();
+        assert_eq!(encoded[3..].first(), Some(&0xa0));
+        assert_eq!(&encoded[4..36], root.as_bytes());
+
+        // A status the provider derived does not replace the root.
+        receipt.status = Some(1.into());
+        assert_eq!(encode(&receipt, byzantium).unwrap(), encoded);
+        // Past Byzantium the status is encoded, root or not.
+        let status = encode(&receipt, true).unwrap();
+        assert_eq!(status[3], 0x01);
+        assert!(status.len() < encoded.len());
+
+        receipt.status = None;
+        assert!(encode(&receipt, true).is_err());
+        receipt.root = None;
+        assert!(encode(&receipt, false).is_err());
+    }
+}
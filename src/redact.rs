@@ -0,0 +1,111 @@
+//! The `redact` command: strips the values of a witness that say whose
+//! state it proves, so a failing witness can be shared for debugging. The
+//! keys, the account and storage values and the proofs, whose nodes hold
+//! them, are dropped, or with `--keep-structure` zeroed in place, keeping
+//! every array's shape and length. Lengths, depths and offsets are kept, as
+//! are the header and the other fields, which are about the block rather
+//! than the account.
+
+use crate::params::{Fields, Params, Value};
+
+/// Fields redacted by default, alone or prefixed in a batch.
+pub const SENSITIVE: &[&str] = &[
+    "account_key",
+    "account_value",
+    "account_nonce",
+    "account_balance",
+    "storage_key",
+    "storage_key_hash",
+    "storage_value",
+    "storage_root",
+    "account_proof",
+    "storage_proof",
+    "account_proof_tail",
+    "storage_proof_tail",
+    "account_proof_node_hashes",
+    "storage_proof_node_hashes",
+    "keccak_preimages",
+];
+
+/// Metadata entries that read the proven value or name the account.
+const SENSITIVE_META: &[&str] = &["value_readable", "pubkey", "delegated_to"];
+
+/// The signature covers the original fields, which no longer match.
+const SIGNATURE_META: &[&str] = &["signature_scheme", "signer", "signature"];
+
+/// Whether the field `name` is `field`, alone or prefixed in a batch.
+fn names(name: &str, field: &str) -> bool {
+    name == field
+        || name
+            .strip_suffix(field)
+            .is_some_and(|prefix| prefix.ends_with('_'))
+}
+
+/// `value` with the same shape and every byte and integer zeroed.
+fn zeroed(value: &Value) -> Value {
+    match value {
+        Value::Bytes(bytes) => Value::Bytes(vec![0; bytes.len()]),
+        Value::Hex(bytes) => Value::Hex(vec![0; bytes.len()]),
+        Value::Int(_) => Value::Int(0),
+        Value::Str(text) => Value::Str("0".repeat(text.len())),
+        Value::Array(values) => Value::Array(values.iter().map(zeroed).collect()),
+    }
+}
+
+/// How [`redact`] treats the sensitive fields.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Zero them in place rather than drop them.
+    pub keep_structure: bool,
+    /// Fields to keep although they are sensitive.
+    pub keep: Vec<String>,
+    /// Further fields to redact.
+    pub redact: Vec<String>,
+}
+
+/// `params` with the sensitive fields dropped or zeroed, returning the
+/// names of those redacted. The metadata records them as `redacted` and
+/// the original witness hash as `redacted_from`, so the maintainer can tell
+/// which witness it was without seeing it.
+pub fn redact(params: &Params, options: &Options) -> (Params, Vec<String>) {
+    let sensitive = |name: &str| {
+        SENSITIVE
+            .iter()
+            .copied()
+            .chain(options.redact.iter().map(String::as_str))
+            .any(|field| names(name, field))
+            && !options.keep.iter().any(|field| names(name, field))
+    };
+    let mut fields = Fields::default();
+    let mut redacted = Vec::new();
+    for (name, value) in params.fields.iter() {
+        if !sensitive(name) {
+            fields.push(name.clone(), value.clone());
+            continue;
+        }
+        redacted.push(name.clone());
+        if options.keep_structure {
+            fields.push(name.clone(), zeroed(value));
+        }
+    }
+
+    let mut meta = Fields::default();
+    for (name, value) in params.meta.iter() {
+        let name = name.as_str();
+        if SENSITIVE_META.contains(&name) || SIGNATURE_META.contains(&name) {
+            continue;
+        }
+        if name == "witness_hash" {
+            meta.push("redacted_from", value.clone());
+            continue;
+        }
+        meta.push(name, value.clone());
+    }
+    meta.push(
+        "redacted",
+        Value::Array(redacted.iter().cloned().map(Value::Str).collect()),
+    );
+    let mut params = Params { fields, meta };
+    params.record_witness_hash();
+    (params, redacted)
+}
@@ -0,0 +1,222 @@
+//! Regenerates an existing witness file at another block: the `refresh`
+//! command. The account, slot and layout options are read back from the
+//! file's fields and metadata, so a re-attestation job only names the file
+//! and the block.
+
+use clap::ValueEnum;
+use web3::types::{H160, H256};
+
+use crate::cli::{GenArgs, OutputFormat, RefreshArgs};
+use crate::encoding::{self, NumericEncoding};
+use crate::error::{Error, Result};
+use crate::options::{GenOptions, RootMode, ValueRange};
+use crate::params::{Params, Value};
+use crate::pubkey::PublicKey;
+use crate::units::Unit;
+use crate::{container, namespace, proof_order};
+
+/// Metadata of witnesses `refresh` cannot regenerate, with the command
+/// that produced them.
+const OTHER_COMMANDS: &[(&str, &str)] = &[
+    ("chains", "gen_bundle"),
+    ("storage_slots", "gen_multiproof"),
+    ("creation_block", "gen_creation_params"),
+    ("tx_index", "gen_receipt_params"),
+    ("bloom_block", "gen_bloom_params"),
+    ("deployer_nonce", "gen_deployment_params"),
+    ("nonce", "gen_nonce_params"),
+    ("proxy_beacon", "gen_beacon_proxy_params"),
+    ("rollup", "gen_prove_params --rollup"),
+    ("checkpoint_id", "gen_prove_params --chain polygon"),
+    ("synthetic", "gen_prove_params --state-override"),
+    ("overridden_fields", "gen_prove_params --override"),
+    ("block_range", "gen_prove_params --block-range"),
+    ("uncle_hashes", "gen_prove_params --uncles"),
+    ("slot_commitment", "gen_prove_params --blind-slot"),
+    ("redacted", "redact"),
+    (
+        "account_proof_max_depth",
+        "gen_prove_params --emit-monomorphized-nr",
+    ),
+];
+
+fn numeric_encoding(params: &Params) -> Result<NumericEncoding> {
+    match params.meta.get("numeric_encoding") {
+        None => Ok(NumericEncoding::default()),
+        Some(Value::Str(name)) => <NumericEncoding as clap::ValueEnum>::from_str(name, false)
+            .map_err(|_| Error::Decode(format!("unknown meta.numeric_encoding {}", name))),
+        Some(_) => Err(Error::Decode(
+            "meta.numeric_encoding is not a string".to_string(),
+        )),
+    }
+}
+
+/// The `--rename` mapping recorded in `meta.renamed_fields`, undone on
+/// `params` so the fields have the generator's names again.
+fn undo_renames(params: &mut Params) -> Result<Vec<(String, String)>> {
+    let Some(Value::Array(mapping)) = params.meta.get("renamed_fields").cloned() else {
+        return Ok(Vec::new());
+    };
+    let renames = mapping
+        .iter()
+        .map(|entry| match entry {
+            Value::Str(text) => text
+                .split_once('=')
+                .map(|(old, new)| (old.to_string(), new.to_string())),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| Error::Decode("invalid meta.renamed_fields".to_string()))?;
+    // Through placeholders, as the renames may have swapped fields.
+    for (i, (_, new)) in renames.iter().enumerate() {
+        params.fields.rename(new, &format!("\0{}", i));
+    }
+    for (i, (old, _)) in renames.iter().enumerate() {
+        params.fields.rename(&format!("\0{}", i), old);
+    }
+    Ok(renames)
+}
+
+fn padded_len(params: &Params, name: &str) -> Option<usize> {
+    params.fields.get_bytes(name).map(<[u8]>::len)
+}
+
+/// The generator arguments reproducing the witness `params`, read from
+/// `data`, at the block `args` asks for, and whether it holds the prover
+/// parameters.
+pub fn gen_args(params: &Params, data: &[u8], args: &RefreshArgs) -> Result<(GenArgs, bool)> {
+    if let Some((_, command)) = OTHER_COMMANDS
+        .iter()
+        .find(|(name, _)| params.meta.get(name).is_some())
+    {
+        return Err(Error::Unsupported(format!(
+            "{} was written by {}; refresh regenerates gen_prove_params and gen_verify_params \
+             witnesses only",
+            args.file.display(),
+            command
+        )));
+    }
+    if params.meta.get("signer").is_some() && args.sign_key.is_none() {
+        return Err(Error::Unsupported(format!(
+            "{} is signed; pass --sign-key to sign the refreshed witness",
+            args.file.display()
+        )));
+    }
+    let mut params = params.clone();
+    let renames = undo_renames(&mut params)?;
+    let fields = &params.fields;
+    let has = |name: &str| fields.get(name).is_some();
+
+    let account = match params.meta.get("delegated_from") {
+        Some(Value::Str(text)) => text
+            .parse::<H160>()
+            .map_err(|_| Error::Decode(format!("invalid meta.delegated_from {}", text)))?,
+        _ => fields
+            .get_bytes("account_key")
+            .filter(|key| key.len() == 20)
+            .map(H160::from_slice)
+            .ok_or_else(|| Error::Decode("the witness has no 20 byte account_key".to_string()))?,
+    };
+    let encoding = numeric_encoding(&params)?;
+    let decode = |name: &str| {
+        fields
+            .get(name)
+            .map(|value| {
+                encoding
+                    .decode(value)
+                    .ok_or_else(|| Error::Decode(format!("{} is not {}", name, encoding.name())))
+            })
+            .transpose()
+    };
+    let slot = decode("storage_key")?.map(|key| {
+        let mut bytes = [0u8; 32];
+        key.to_big_endian(&mut bytes);
+        H256(bytes)
+    });
+    let range = match (decode("range_lo")?, decode("range_hi")?) {
+        (Some(lo), Some(hi)) => Some(ValueRange { lo, hi }),
+        _ => None,
+    };
+    let header_parts = has("block_header_rlp_head");
+    let prove = has("account_proof");
+    let text = std::str::from_utf8(data).unwrap_or_default();
+    let format = if container::is_container(data) {
+        OutputFormat::Binary
+    } else if text.trim_start().starts_with('{') {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Toml
+    };
+    let namespaced = format == OutputFormat::Toml
+        && text.parse::<toml::Table>().is_ok_and(|table| {
+            namespace::SECTIONS
+                .iter()
+                .any(|section| table.get(*section).is_some())
+        });
+
+    let options = GenOptions {
+        rpc_url: args.rpc_url.clone(),
+        block_ref: args.block_ref,
+        slot,
+        range,
+        range_limb_bits: params.meta.get_int("range_limb_bits").unwrap_or(16) as u32,
+        root_mode: match params.meta.get("root_mode") {
+            Some(Value::Str(mode)) if mode == "state-root" => RootMode::StateRoot,
+            _ => RootMode::BlockHash,
+        },
+        chain_spec: args.chain_spec.clone(),
+        keccak_blocks: has("block_header_keccak_blocks"),
+        blob_gas_offsets: has("blob_gas_used_offset"),
+        header_parts,
+        header_head_bytes: padded_len(&params, "block_header_rlp_head"),
+        header_tail_bytes: padded_len(&params, "block_header_rlp_tail"),
+        header_padding: encoding::header_padding(&params),
+        follow_delegation: params.meta.get("delegated_from").is_some(),
+        rlp_hints: has("account_proof_item_counts"),
+        keccak_preimages: has("keccak_preimages"),
+        node_hashes: has("account_proof_node_hashes"),
+        account_fields: has("account_balance"),
+        empty_constants: has("empty_trie_root"),
+        split_deep_proofs: has("account_proof_tail") || has("storage_proof_tail"),
+        numeric_encoding: encoding,
+        value_form: encoding::value_form(&params),
+        proof_order: proof_order::recorded(&params),
+        as_unit: match params.meta.get("value_unit") {
+            Some(Value::Str(unit)) => Some(
+                Unit::from_str(unit, false)
+                    .map_err(|_| Error::Decode(format!("unknown meta.value_unit {}", unit)))?,
+            ),
+            _ => None,
+        },
+        token_decimals: params
+            .meta
+            .get_int("value_decimals")
+            .filter(|_| params.meta.get("value_unit").is_none())
+            .map(|decimals| decimals as u32),
+        reorg_check: params.meta.get("reorg_check").is_some(),
+        confirmations: params.meta.get_int("confirmations").unwrap_or(0),
+        renames,
+        sign_key: args.sign_key.clone(),
+        byte_encoding: params.byte_encoding()?,
+        toml_wrap: params.toml_wrap(),
+        pubkey: match params.meta.get("pubkey") {
+            Some(Value::Str(text)) => Some(
+                hex::decode(text.trim_start_matches("0x"))
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| PublicKey::parse(&bytes))
+                    .map_err(|e| Error::Decode(format!("invalid meta.pubkey: {}", e)))?,
+            ),
+            _ => None,
+        },
+        ..GenOptions::new(&args.rpc_url[0], 0, account, None)
+    };
+    let gen_args = GenArgs {
+        options,
+        account_arg: Some(account),
+        out: Some(args.out.clone().unwrap_or_else(|| args.file.clone())),
+        format: vec![format],
+        namespaced,
+        ..Default::default()
+    };
+    Ok((gen_args, prove))
+}
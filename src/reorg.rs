@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use web3::types::{BlockId, BlockNumber, H256, U64};
+use web3::{Transport, Web3};
+
+use crate::error::{Error, Result};
+use crate::rpc::Client;
+
+/// Waits until `block` is buried under `confirmations` blocks and, when
+/// `finalized` is set, until the chain has finalized it.
+pub async fn wait_for_block(
+    web3: &Web3<Client>,
+    block: u64,
+    confirmations: u64,
+    finalized: bool,
+    poll_interval: Duration,
+) -> Result<()> {
+    loop {
+        let head = web3.eth().block_number().await?.as_u64();
+        let confirmed = head >= block + confirmations;
+        let finalized_ok = !finalized
+            || web3
+                .eth()
+                .block(BlockId::Number(BlockNumber::Finalized))
+                .await?
+                .and_then(|finalized| finalized.number)
+                .is_some_and(|number| number.as_u64() >= block);
+        if confirmed && finalized_ok {
+            return Ok(());
+        }
+        log::info!(
+            "Waiting for block {} (head {}, {} confirmation(s){})",
+            block,
+            head,
+            confirmations,
+            if finalized { ", finalized" } else { "" }
+        );
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Re-fetches `block` by number and fails if its hash is no longer
+/// `expected_hash`.
+pub async fn ensure_canonical(web3: &Web3<Client>, block: u64, expected_hash: H256) -> Result<()> {
+    let current = web3
+        .eth()
+        .block(BlockId::Number(BlockNumber::Number(block.into())))
+        .await?
+        .and_then(|block| block.hash);
+    match current {
+        Some(hash) if hash == expected_hash => Ok(()),
+        Some(hash) => Err(Error::Reorg(format!(
+            "block {} was reorged: generated against {:?}, canonical is now {:?}",
+            block, expected_hash, hash
+        ))),
+        None => Err(Error::Reorg(format!(
+            "block {} is no longer part of the chain",
+            block
+        ))),
+    }
+}
+
+/// Later blocks an uncle of block `block` can be included in: uncles may
+/// be up to six generations older than the block referencing them.
+const UNCLE_INCLUSION_BLOCKS: u64 = 7;
+
+/// The uncles of the height `block` reported by the blocks that can
+/// include them, as `eth_getUncleByBlockNumberAndIndex` returns them.
+pub async fn uncles_at(web3: &Web3<Client>, block: u64) -> Result<Vec<serde_json::Value>> {
+    let execute = |method: &'static str, params: Vec<serde_json::Value>| async move {
+        Ok::<_, Error>(web3.transport().execute(method, params).await?)
+    };
+    let mut uncles: Vec<serde_json::Value> = Vec::new();
+    for nephew in block + 1..=block + UNCLE_INCLUSION_BLOCKS {
+        let count = execute(
+            "eth_getUncleCountByBlockNumber",
+            vec![serde_json::json!(U64::from(nephew))],
+        )
+        .await?;
+        // A block the chain has not reached yet counts as null.
+        let Ok(count) = serde_json::from_value::<U64>(count) else {
+            break;
+        };
+        for index in 0..count.as_u64() {
+            let uncle = execute(
+                "eth_getUncleByBlockNumberAndIndex",
+                vec![
+                    serde_json::json!(U64::from(nephew)),
+                    serde_json::json!(U64::from(index)),
+                ],
+            )
+            .await?;
+            let number = serde_json::from_value::<U64>(uncle["number"].clone()).ok();
+            let known = uncles.iter().any(|known| known["hash"] == uncle["hash"]);
+            if number == Some(U64::from(block)) && !known {
+                uncles.push(uncle);
+            }
+        }
+    }
+    Ok(uncles)
+}
@@ -0,0 +1,297 @@
+//! The `repl` command: an interactive session that builds up a target line
+//! by line (`block`, `account`, `slot`, generator options set with `set`)
+//! and emits parameter files on request. The session keeps its provider
+//! connection and every response pinned to a block (see
+//! [`rpc::start_session`]), so emitting again after changing an option
+//! does not fetch the block and proofs a second time.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use web3::types::{BlockNumber, H160, H256, U256};
+
+use crate::cli::{self, Cli, Command, GenArgs};
+use crate::error::{Error, Result};
+use crate::options::{self, BlockRef, GenOptions};
+use crate::{resolve_block, rpc, slots};
+
+pub const HELP: &str = "\
+commands:
+  block <number|latest|safe|finalized>   prove this block; tags are resolved once
+  account <address>                      prove this account
+  slot <slot>                            prove this storage slot
+  slot mapping <index> <key>             prove key's entry in the mapping at slot index
+  slot none                              prove the account only
+  set <option> [value]                   pass a gen_prove_params option, e.g. set keccak-blocks
+  unset <option>                         drop an option set earlier
+  show                                   print the target and options
+  value                                  print the slot's value, or the account's balance
+  emit [prover|verifier] <file>          write the parameters to file
+  help                                   print this help
+  quit                                   leave the session
+";
+
+/// Options the session's own commands set.
+const MANAGED: &[&str] = &[
+    "rpc-url",
+    "block",
+    "account",
+    "slot",
+    "out",
+    "at-timestamp",
+    "beacon-root",
+];
+
+/// What the caller does after a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Continue,
+    /// Generate the prover (or verifier) parameters into `out`.
+    Emit {
+        prove: bool,
+        out: PathBuf,
+    },
+    Quit,
+}
+
+pub struct Repl {
+    pub rpc_url: String,
+    pub block: Option<u64>,
+    pub account: Option<H160>,
+    pub slot: Option<H256>,
+    /// Options set with `set`, in the order they were set.
+    pub options: Vec<(String, Option<String>)>,
+}
+
+fn usage(message: &str) -> Error {
+    Error::Config(message.to_string())
+}
+
+impl Repl {
+    /// A session against `rpc_url`, which from now on keeps its connections
+    /// and block-bound responses.
+    pub fn new(rpc_url: &str) -> Self {
+        rpc::start_session();
+        Repl {
+            rpc_url: rpc_url.to_string(),
+            block: None,
+            account: None,
+            slot: None,
+            options: Vec::new(),
+        }
+    }
+
+    /// Fixes the block to prove, resolving a tag against the provider.
+    pub async fn set_block(&mut self, block: BlockRef) -> Result<u64> {
+        let mut args = GenOptions::new(&self.rpc_url, 0, H160::zero(), None);
+        args.block_ref = block;
+        resolve_block(&mut args).await?;
+        self.block = Some(args.block);
+        Ok(args.block)
+    }
+
+    /// The command line of the options set so far.
+    fn option_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (name, value) in &self.options {
+            args.push(format!("--{}", name));
+            args.extend(value.clone());
+        }
+        args
+    }
+
+    /// The generator arguments of the session, writing to `out`.
+    pub fn gen_args(&self, prove: bool, out: PathBuf) -> Result<GenArgs> {
+        let block = self
+            .block
+            .ok_or_else(|| usage("no block yet; pick one with `block <number>`"))?;
+        let account = self
+            .account
+            .ok_or_else(|| usage("no account yet; pick one with `account <address>`"))?;
+        self.parse(prove, block, account, out)
+    }
+
+    fn parse(&self, prove: bool, block: u64, account: H160, out: PathBuf) -> Result<GenArgs> {
+        let command = if prove {
+            "gen_prove_params"
+        } else {
+            "gen_verify_params"
+        };
+        let mut argv: Vec<String> = [
+            "noir-mip",
+            command,
+            "--rpc-url",
+            &self.rpc_url,
+            "--block",
+            &block.to_string(),
+            "--account",
+            &format!("{:?}", account),
+        ]
+        .map(str::to_string)
+        .to_vec();
+        argv.extend(self.option_args());
+        let cli = Cli::try_parse_from(argv).map_err(|e| {
+            Error::Config(
+                e.render()
+                    .to_string()
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim_start_matches("error: ")
+                    .to_string(),
+            )
+        })?;
+        let mut args = match cli.command {
+            Command::GenProveParams(args) | Command::GenVerifyParams(args) => args,
+            _ => unreachable!("parsed as a generator command"),
+        };
+        // Not from the environment: the session decides.
+        args.options.slot = self.slot;
+        args.out = Some(out);
+        Ok(args)
+    }
+
+    /// The slot's value at the session's block, or the account's balance
+    /// when no slot is set.
+    async fn value(&self) -> Result<String> {
+        let (Some(block), Some(account)) = (self.block, self.account) else {
+            return Err(usage("pick a block and an account first"));
+        };
+        let web3 = web3::Web3::new(rpc::Client::new(&self.rpc_url)?);
+        let at = Some(BlockNumber::Number(block.into()));
+        Ok(match self.slot {
+            Some(slot) => {
+                let value = web3
+                    .eth()
+                    .storage(account, U256::from_big_endian(slot.as_bytes()), at)
+                    .await?;
+                let value = U256::from_big_endian(value.as_bytes());
+                format!("{} ({:#x})", value, value)
+            }
+            None => {
+                let balance = web3.eth().balance(account, at).await?;
+                let code = web3.eth().code(account, at).await?;
+                let kind = if code.0.is_empty() {
+                    "account"
+                } else {
+                    "contract"
+                };
+                format!("{} wei ({})", balance, kind)
+            }
+        })
+    }
+
+    fn show(&self) -> String {
+        let mut out = format!("rpc     {}\n", rpc::redact(&self.rpc_url));
+        out += &match self.block {
+            Some(block) => format!("block   {}\n", block),
+            None => "block   -\n".to_string(),
+        };
+        out += &match self.account {
+            Some(account) => format!("account {:?}\n", account),
+            None => "account -\n".to_string(),
+        };
+        out += &match self.slot {
+            Some(slot) => format!("slot    {:?}\n", slot),
+            None => "slot    - (account only)\n".to_string(),
+        };
+        for (name, value) in &self.options {
+            match value {
+                Some(value) => out += &format!("set     {} {}\n", name, value),
+                None => out += &format!("set     {}\n", name),
+            }
+        }
+        out
+    }
+
+    /// Runs one line of input, printing what it reports, and tells the
+    /// caller what to do next.
+    pub async fn execute(&mut self, line: &str) -> Result<Action> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, rest)) = words.split_first() else {
+            return Ok(Action::Continue);
+        };
+        match (command, rest) {
+            (command, _) if command.starts_with('#') => {}
+            ("help", _) => print!("{}", HELP),
+            ("quit" | "exit", []) => return Ok(Action::Quit),
+            ("block", [block]) => {
+                let block = cli::parse_block_ref(block).map_err(Error::Config)?;
+                let number = self.set_block(block).await?;
+                println!("block {}", number);
+            }
+            ("account", [account]) => {
+                self.account = Some(options::parse_h160(account).map_err(Error::Config)?);
+            }
+            ("slot", ["none"]) => self.slot = None,
+            ("slot", ["mapping", index, key]) => {
+                let index = slots::parse_slot_index(index)?;
+                let key = slots::parse_mapping_key(key)?;
+                let slot = slots::mapping_slot(index, &key);
+                println!("slot {:?}", slot);
+                self.slot = Some(slot);
+                let warnings = match (self.block, self.account) {
+                    (Some(block), Some(account)) => {
+                        let web3 = web3::Web3::new(rpc::Client::new(&self.rpc_url)?);
+                        slots::diagnose_mapping(&web3, account, block, index, &key).await?
+                    }
+                    _ => slots::check_mapping(index, &key),
+                };
+                for warning in warnings {
+                    log::warn!("{}", warning);
+                }
+            }
+            ("slot", [slot]) => self.slot = Some(options::parse_h256(slot).map_err(Error::Config)?),
+            ("set", [name, value @ ..]) if value.len() <= 1 => {
+                let name = name.trim_start_matches("--").to_string();
+                if MANAGED.contains(&name.as_str()) {
+                    return Err(usage(&format!(
+                        "{} is set by the session's own commands; see `help`",
+                        name
+                    )));
+                }
+                let previous = self.options.clone();
+                // Options other than repeatable ones replace an earlier value.
+                if !matches!(name.as_str(), "override" | "rename" | "encrypt-to") {
+                    self.options.retain(|(option, _)| *option != name);
+                }
+                self.options
+                    .push((name, value.first().map(|value| value.to_string())));
+                let check = self.parse(true, 0, H160::zero(), PathBuf::from("-"));
+                if let Err(e) = check {
+                    self.options = previous;
+                    return Err(e);
+                }
+            }
+            ("unset", [name]) => {
+                let name = name.trim_start_matches("--");
+                let count = self.options.len();
+                self.options.retain(|(option, _)| option != name);
+                if self.options.len() == count {
+                    return Err(usage(&format!("{} is not set", name)));
+                }
+            }
+            ("show", []) => print!("{}", self.show()),
+            ("value", []) => println!("{}", self.value().await?),
+            ("emit", [out]) => {
+                return Ok(Action::Emit {
+                    prove: true,
+                    out: PathBuf::from(out),
+                })
+            }
+            ("emit", [kind @ ("prover" | "verifier"), out]) => {
+                return Ok(Action::Emit {
+                    prove: *kind == "prover",
+                    out: PathBuf::from(out),
+                })
+            }
+            _ => {
+                return Err(usage(&format!(
+                    "unknown command `{}`; type `help` for the commands",
+                    line.trim()
+                )))
+            }
+        }
+        Ok(Action::Continue)
+    }
+}
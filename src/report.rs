@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use web3::types::{H160, H256};
+
+use crate::cache::CacheStats;
+use crate::error::{Error, Result};
+use crate::params::Params;
+use crate::proof_order;
+use crate::rpc::{self, CallRecord};
+use crate::trie;
+use crate::PROOF_BYTES_LEN;
+
+/// Notifications tried before a failure is returned.
+const NOTIFY_ATTEMPTS: u32 = 3;
+
+/// Wait before the first notification retry, doubled after each one.
+const NOTIFY_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Proofs whose depth and node sizes are reported, when present.
+const PROOFS: &[&str] = &[
+    "account_proof",
+    "storage_proof",
+    "account_proof_tail",
+    "storage_proof_tail",
+];
+
+#[derive(Serialize, Default)]
+struct MethodStats {
+    calls: usize,
+    failures: usize,
+    total_ms: u64,
+    max_ms: u64,
+}
+
+#[derive(Serialize)]
+struct ProviderStats {
+    provider: String,
+    calls: usize,
+    failures: usize,
+    total_ms: u64,
+    methods: BTreeMap<String, MethodStats>,
+}
+
+#[derive(Serialize)]
+struct ProofStats {
+    name: String,
+    depth: usize,
+    node_sizes: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct WitnessStats {
+    /// Slot the witness proves, for runs covering several slots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slot: Option<H256>,
+    witness_hash: String,
+    proofs: Vec<ProofStats>,
+}
+
+/// Summary of one run, written as JSON for monitoring.
+#[derive(Serialize)]
+pub struct Report {
+    command: String,
+    started_at: u64,
+    duration_ms: u64,
+    block: u64,
+    account: H160,
+    slot: Option<H256>,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    providers: Vec<ProviderStats>,
+    /// Proof cache lookups, when a cache is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof_cache: Option<CacheStats>,
+    witnesses: Vec<WitnessStats>,
+    outputs: Vec<PathBuf>,
+    /// Provider URLs as configured, redacted wherever they show up in
+    /// errors.
+    #[serde(skip)]
+    rpc_urls: Vec<String>,
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+fn provider_stats(calls: Vec<CallRecord>) -> Vec<ProviderStats> {
+    let mut providers: Vec<ProviderStats> = Vec::new();
+    for call in calls {
+        let index = match providers
+            .iter()
+            .position(|stats| stats.provider == call.provider)
+        {
+            Some(index) => index,
+            None => {
+                providers.push(ProviderStats {
+                    provider: call.provider.clone(),
+                    calls: 0,
+                    failures: 0,
+                    total_ms: 0,
+                    methods: BTreeMap::new(),
+                });
+                providers.len() - 1
+            }
+        };
+        let stats = &mut providers[index];
+        let latency = millis(call.latency);
+        let method = stats.methods.entry(call.method).or_default();
+        method.calls += 1;
+        method.total_ms += latency;
+        method.max_ms = method.max_ms.max(latency);
+        stats.calls += 1;
+        stats.total_ms += latency;
+        if !call.ok {
+            method.failures += 1;
+            stats.failures += 1;
+        }
+    }
+    providers
+}
+
+impl Report {
+    /// Starts a report for `command` against `block`, `account` and `slot`,
+    /// fetched from `rpc_urls`.
+    pub fn new(
+        command: &str,
+        rpc_urls: &[String],
+        block: u64,
+        account: H160,
+        slot: Option<H256>,
+    ) -> Self {
+        Report {
+            command: command.to_string(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            duration_ms: 0,
+            block,
+            account,
+            slot,
+            status: "ok",
+            error: None,
+            providers: Vec::new(),
+            proof_cache: None,
+            witnesses: Vec::new(),
+            outputs: Vec::new(),
+            rpc_urls: rpc_urls.to_vec(),
+        }
+    }
+
+    /// Records the proofs of one generated witness.
+    pub fn add_witness(&mut self, slot: Option<H256>, params: &Params) {
+        let proofs = PROOFS
+            .iter()
+            .filter_map(|name| {
+                let flat = params.fields.get_bytes(name)?;
+                let depth = params.fields.get_int(&format!("{}_depth", name))? as usize;
+                let nodes = proof_order::recorded(params).root_first(trie::unpad_proof(
+                    flat,
+                    PROOF_BYTES_LEN,
+                    depth,
+                ));
+                Some(ProofStats {
+                    name: name.to_string(),
+                    depth,
+                    node_sizes: nodes.iter().map(Vec::len).collect(),
+                })
+            })
+            .collect();
+        self.witnesses.push(WitnessStats {
+            slot,
+            witness_hash: format!("0x{}", hex::encode(params.witness_hash())),
+            proofs,
+        });
+    }
+
+    pub fn set_proof_cache(&mut self, stats: CacheStats) {
+        self.proof_cache = Some(stats);
+    }
+
+    pub fn add_output(&mut self, path: &Path) {
+        self.outputs.push(path.to_path_buf());
+    }
+
+    /// Completes the report with the run's outcome and the RPC calls made
+    /// since it started.
+    pub fn complete<T>(&mut self, elapsed: Duration, outcome: &Result<T>, calls: Vec<CallRecord>) {
+        self.duration_ms = millis(elapsed);
+        if let Err(e) = outcome {
+            self.status = "failed";
+            let mut error = e.to_string();
+            for url in &self.rpc_urls {
+                error = error.replace(url.trim_end_matches('/'), &rpc::redact(url));
+            }
+            self.error = Some(error);
+        }
+        self.providers = provider_stats(calls);
+    }
+
+    fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Decode(format!("cannot serialize the report: {}", e)))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()? + "\n")?;
+        Ok(())
+    }
+
+    /// POSTs the report as JSON to `url`, retrying network errors and
+    /// server errors.
+    pub async fn notify(&self, url: &str) -> Result<()> {
+        let body = self.to_json()?;
+        let http = reqwest::Client::builder()
+            .timeout(NOTIFY_TIMEOUT)
+            .build()
+            .map_err(|e| Error::Notify(e.to_string()))?;
+        let target = rpc::redact(url);
+        let mut delay = NOTIFY_RETRY_DELAY;
+        for attempt in 1..=NOTIFY_ATTEMPTS {
+            let outcome = http
+                .post(url)
+                .header("content-type", "application/json")
+                .body(body.clone())
+                .send()
+                .await;
+            let error = match outcome {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) if !response.status().is_server_error() => {
+                    return Err(Error::Notify(format!(
+                        "POST {}: {}",
+                        target,
+                        response.status()
+                    )))
+                }
+                Ok(response) => format!("POST {}: {}", target, response.status()),
+                Err(e) => format!("POST {}: {}", target, e.without_url()),
+            };
+            if attempt == NOTIFY_ATTEMPTS {
+                return Err(Error::Notify(format!(
+                    "{} (after {} attempts)",
+                    error, NOTIFY_ATTEMPTS
+                )));
+            }
+            log::warn!("{}; retrying in {:?}", error, delay);
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        unreachable!("the last attempt returns")
+    }
+}
@@ -0,0 +1,93 @@
+//! `--stdin`: the options of a `gen_prove_params` or `gen_verify_params`
+//! run read as a JSON object from stdin, so a pipeline can build the
+//! request with `jq` instead of a command line. Each key names an option,
+//! with or without the leading `--` and in kebab or snake case: `true`
+//! passes a flag, `false` and `null` leave the option out, strings and
+//! numbers are its value and arrays repeat it. The options are parsed
+//! with the command-line ones, and checked the same way.
+
+use std::io::Read;
+
+use clap::Parser;
+use serde_json::{Map, Value};
+
+use crate::cli::{Cli, Command, GenArgs};
+use crate::error::{Error, Result};
+
+fn invalid(message: String) -> Error {
+    Error::Config(format!("invalid stdin request: {}", message))
+}
+
+/// A scalar of the request as an option value.
+fn scalar(name: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(value) => Ok(value.to_string()),
+        _ => Err(invalid(format!(
+            "--{} takes strings and numbers, not {}",
+            name, value
+        ))),
+    }
+}
+
+/// The command-line arguments of the request `object`.
+pub fn options(object: &Map<String, Value>) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    for (key, value) in object {
+        let name = key.trim_start_matches("--").replace('_', "-");
+        if name == "stdin" {
+            return Err(invalid("a request cannot ask for --stdin".to_string()));
+        }
+        let flag = format!("--{}", name);
+        match value {
+            Value::Null | Value::Bool(false) => {}
+            Value::Bool(true) => args.push(flag),
+            Value::Array(values) => {
+                for value in values {
+                    args.push(flag.clone());
+                    args.push(scalar(&name, value)?);
+                }
+            }
+            value => {
+                args.push(flag);
+                args.push(scalar(&name, value)?);
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// The generator arguments of the command line `argv`, less its
+/// `--stdin`, with the options of the JSON request `json` added.
+pub fn gen_args(argv: impl IntoIterator<Item = String>, json: &str) -> Result<GenArgs> {
+    let request: Value =
+        serde_json::from_str(json).map_err(|e| invalid(format!("not JSON: {}", e)))?;
+    let Value::Object(object) = request else {
+        return Err(invalid("expected a JSON object of options".to_string()));
+    };
+    let mut argv: Vec<String> = argv.into_iter().filter(|arg| arg != "--stdin").collect();
+    argv.extend(options(&object)?);
+    let cli = Cli::try_parse_from(argv).map_err(|e| {
+        // The message, without the usage that follows it.
+        let message = e.render().to_string();
+        let message: Vec<&str> = message
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .map(str::trim)
+            .collect();
+        invalid(message.join(" ").trim_start_matches("error: ").to_string())
+    })?;
+    match cli.command {
+        Command::GenProveParams(args) | Command::GenVerifyParams(args) => Ok(args),
+        _ => Err(invalid(
+            "--stdin applies to gen_prove_params and gen_verify_params".to_string(),
+        )),
+    }
+}
+
+/// [`gen_args`] of this process's command line and the request on stdin.
+pub fn from_stdin() -> Result<GenArgs> {
+    let mut json = String::new();
+    std::io::stdin().read_to_string(&mut json)?;
+    gen_args(std::env::args(), &json)
+}
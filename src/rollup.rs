@@ -0,0 +1,95 @@
+use crate::encoding::{HeaderPadding, ValueForm};
+use crate::error::{Error, Result};
+use crate::options::{GenOptions, Rollup, RootMode};
+use crate::params::Params;
+use crate::proof_order::ProofOrder;
+
+/// Rejects options that read a block header or an MPT proof, which the
+/// native proofs of zkSync and Linea have neither of. Scroll proofs are MPT
+/// proofs and take every option.
+pub fn check_args(args: &GenOptions, rollup: Rollup) -> Result<()> {
+    if rollup == Rollup::Scroll {
+        return Ok(());
+    }
+    let mpt_only = [
+        (args.root_mode == RootMode::StateRoot, "--root-mode"),
+        (args.state_root.is_some(), "--state-root"),
+        (args.keccak_blocks, "--keccak-blocks"),
+        (args.blob_gas_offsets, "--blob-gas-offsets"),
+        (args.header_parts, "--header-parts"),
+        (
+            args.header_padding != HeaderPadding::Post,
+            "--header-padding",
+        ),
+        (args.follow_delegation, "--follow-delegation"),
+        (args.rlp_hints, "--rlp-hints"),
+        (args.keccak_preimages, "--keccak-preimages"),
+        (args.node_hashes, "--node-hashes"),
+        (args.account_fields, "--account-fields"),
+        (args.empty_constants, "--empty-constants"),
+        (args.self_check, "--self-check"),
+        (args.split_deep_proofs, "--split-deep-proofs"),
+        (args.value_form != ValueForm::Padded, "--value-form"),
+        (args.proof_order != ProofOrder::RootFirst, "--proof-order"),
+        (args.allow_empty, "--allow-empty"),
+        (args.beacon_api.is_some(), "--beacon-api"),
+        (
+            args.reorg_check || args.confirmations > 0 || args.wait_finalized,
+            "reorg checks",
+        ),
+        (args.proof_cache.is_some(), "--proof-cache"),
+        (args.uncles, "--uncles"),
+        (args.blind_slot, "--blind-slot"),
+        (
+            args.emit_monomorphized_nr.is_some(),
+            "--emit-monomorphized-nr",
+        ),
+    ];
+    if let Some((_, option)) = mpt_only.iter().find(|(requested, _)| *requested) {
+        return Err(Error::Unsupported(format!(
+            "{} does not apply to --rollup {}, whose proofs are not MPT proofs under a block \
+             header",
+            option,
+            rollup.name()
+        )));
+    }
+    if args.slot.is_none() {
+        return Err(Error::Unsupported(format!(
+            "--rollup {} proves storage slots only; its state has no account proofs to emit \
+             alone, so pass --slot",
+            rollup.name()
+        )));
+    }
+    Ok(())
+}
+
+/// Generates the parameters of `args` with the backend of `rollup`, from
+/// the provider at `rpc_url`.
+#[cfg_attr(
+    not(any(feature = "zksync", feature = "scroll", feature = "linea")),
+    allow(unused_variables)
+)]
+pub async fn generate(
+    rpc_url: &str,
+    args: &GenOptions,
+    rollup: Rollup,
+    prove: bool,
+) -> Result<Params> {
+    match rollup {
+        #[cfg(feature = "zksync")]
+        Rollup::Zksync => crate::zksync::generate(rpc_url, args, prove).await,
+        #[cfg(feature = "scroll")]
+        Rollup::Scroll => {
+            let web3 = web3::Web3::new(crate::rpc::Client::new(rpc_url)?);
+            crate::generate_with_source(&crate::scroll::Source::new(&web3), args, prove).await
+        }
+        #[cfg(feature = "linea")]
+        Rollup::Linea => crate::linea::generate(rpc_url, args, prove).await,
+        #[allow(unreachable_patterns)]
+        rollup => Err(Error::Unsupported(format!(
+            "this build has no {} backend; rebuild with --features {}",
+            rollup.name(),
+            rollup.name()
+        ))),
+    }
+}
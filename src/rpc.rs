@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use jsonrpc_core::{Call, Value};
+use web3::error::TransportError;
+use web3::transports::Http;
+use web3::{RequestId, Transport};
+
+use crate::error::{Error, Result};
+
+/// One JSON-RPC call made during the run.
+#[derive(Debug, Clone)]
+pub struct CallRecord {
+    /// Provider the call went to, without credentials or path.
+    pub provider: String,
+    pub method: String,
+    pub latency: Duration,
+    pub ok: bool,
+}
+
+/// Every call made since the calls were last taken.
+static CALLS: Mutex<Vec<CallRecord>> = Mutex::new(Vec::new());
+
+/// Removes and returns the calls recorded so far.
+pub fn take_calls() -> Vec<CallRecord> {
+    std::mem::take(&mut *CALLS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Scheme and host of `url`. Provider URLs often carry an API key in their
+/// path or user info, which must not end up in reports.
+pub fn redact(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    if scheme.is_empty() {
+        host.to_string()
+    } else {
+        format!("{}://{}", scheme, host)
+    }
+}
+
+/// Token bucket shared by every provider connection, so concurrent workers
+/// together stay under a provider's request rate.
+struct RateLimiter {
+    /// Tokens added per second.
+    rate: f64,
+    /// Most tokens the bucket holds, which bounds how many calls can go out
+    /// back to back after a quiet spell.
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+static RATE_LIMITER: Mutex<Option<RateLimiter>> = Mutex::new(None);
+
+/// Limits JSON-RPC calls to `rate` per second across the whole process,
+/// allowing bursts of up to `burst` calls.
+pub fn set_rate_limit(rate: f64, burst: u32) {
+    let burst = f64::from(burst.max(1));
+    *RATE_LIMITER.lock().unwrap_or_else(|e| e.into_inner()) = Some(RateLimiter {
+        rate,
+        burst,
+        tokens: burst,
+        updated: Instant::now(),
+    });
+}
+
+/// Takes a token from the bucket and returns how long to wait before the
+/// call may go out. Tokens are reserved even when the bucket is empty, so
+/// waiting callers go out in the order they arrived.
+fn reserve_call() -> Duration {
+    let mut limiter = RATE_LIMITER.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(limiter) = limiter.as_mut() else {
+        return Duration::ZERO;
+    };
+    let now = Instant::now();
+    let refill = now.duration_since(limiter.updated).as_secs_f64() * limiter.rate;
+    limiter.tokens = (limiter.tokens + refill).min(limiter.burst) - 1.0;
+    limiter.updated = now;
+    if limiter.tokens >= 0.0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64(-limiter.tokens / limiter.rate)
+    }
+}
+
+/// How long calls may take: `--rpc-timeout` for each call, and
+/// `--total-timeout` for all of them from the start of the run.
+struct Timeouts {
+    call: Option<Duration>,
+    total: Option<(Duration, Instant)>,
+}
+
+static TIMEOUTS: Mutex<Timeouts> = Mutex::new(Timeouts {
+    call: None,
+    total: None,
+});
+
+/// Fails every call taking longer than `call`, and every call still
+/// running `total` after now.
+pub fn set_timeouts(call: Option<Duration>, total: Option<Duration>) {
+    *TIMEOUTS.lock().unwrap_or_else(|e| e.into_inner()) = Timeouts {
+        call,
+        total: total.map(|total| (total, Instant::now() + total)),
+    };
+}
+
+/// The longest the next call may take, and what to blame when it runs
+/// out; `None` without timeouts.
+pub fn time_limit() -> Option<(Duration, String)> {
+    let timeouts = TIMEOUTS.lock().unwrap_or_else(|e| e.into_inner());
+    let call = timeouts
+        .call
+        .map(|call| (call, format!("timed out after {:?} (--rpc-timeout)", call)));
+    let total = timeouts.total.map(|(total, deadline)| {
+        (
+            deadline.saturating_duration_since(Instant::now()),
+            format!("timed out: the run exceeded --total-timeout {:?}", total),
+        )
+    });
+    match (call, total) {
+        (Some(call), Some(total)) => Some(if total.0 < call.0 { total } else { call }),
+        (call, total) => call.or(total),
+    }
+}
+
+/// How connections to providers are made. Proxies set in `HTTPS_PROXY`,
+/// `HTTP_PROXY` and `ALL_PROXY` (minus `NO_PROXY`) are used unless `proxy`
+/// replaces them.
+#[derive(Debug, Clone, Default)]
+pub struct TransportOptions {
+    /// Sent with every JSON-RPC request, e.g. an `Authorization` header.
+    pub headers: Vec<(String, String)>,
+    pub proxy: Option<String>,
+    /// PEM certificates trusted on top of the system's roots.
+    pub ca_certs: Vec<PathBuf>,
+    /// PEM certificate and PKCS#8 key presented to the server.
+    pub client_cert: Option<(PathBuf, PathBuf)>,
+}
+
+/// The HTTP clients built from the transport options: one for JSON-RPC
+/// calls, and one without the headers for other services.
+static HTTP_CLIENTS: Mutex<Option<(reqwest::Client, reqwest::Client)>> = Mutex::new(None);
+
+fn read_pem(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| Error::Config(format!("cannot read {}: {}", path.display(), e)))
+}
+
+fn client_builder(options: &TransportOptions) -> Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder().user_agent("web3.rs");
+    if let Some(proxy) = &options.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| Error::Config(format!("invalid --proxy {}: {}", proxy, e)))?;
+        builder = builder.proxy(proxy);
+    }
+    for path in &options.ca_certs {
+        let certificate = reqwest::Certificate::from_pem(&read_pem(path)?).map_err(|e| {
+            Error::Config(format!(
+                "{} is not a PEM certificate: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    if let Some((cert, key)) = &options.client_cert {
+        let identity = reqwest::Identity::from_pkcs8_pem(&read_pem(cert)?, &read_pem(key)?)
+            .map_err(|e| {
+                Error::Config(format!(
+                    "{} and {} are not a PEM certificate and PKCS#8 key: {}",
+                    cert.display(),
+                    key.display(),
+                    e
+                ))
+            })?;
+        builder = builder.identity(identity);
+    }
+    Ok(builder)
+}
+
+/// Makes every connection from now on with `options`.
+pub fn set_transport(options: &TransportOptions) -> Result<()> {
+    let build_error =
+        |e: reqwest::Error| Error::Config(format!("cannot build the HTTP client: {}", e));
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (name, value) in &options.headers {
+        let invalid = || Error::Config(format!("invalid --header {}: {}", name, value));
+        let name =
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+        let mut value = reqwest::header::HeaderValue::from_str(value).map_err(|_| invalid())?;
+        // Kept out of debug output, as it usually holds a token.
+        value.set_sensitive(true);
+        headers.append(name, value);
+    }
+    let rpc = client_builder(options)?
+        .default_headers(headers)
+        .build()
+        .map_err(build_error)?;
+    let other = client_builder(options)?.build().map_err(build_error)?;
+    *HTTP_CLIENTS.lock().unwrap_or_else(|e| e.into_inner()) = Some((rpc, other));
+    Ok(())
+}
+
+/// The HTTP client for services other than the JSON-RPC providers, such
+/// as a beacon API: through the same proxy and TLS options, without the
+/// `--header`s.
+pub fn http_client() -> reqwest::Client {
+    match HTTP_CLIENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+    {
+        Some((_, other)) => other.clone(),
+        None => reqwest::Client::new(),
+    }
+}
+
+/// A JSON-RPC transport to `url`, made with the transport options.
+pub fn http_transport(url: &str) -> web3::Result<Http> {
+    let client = HTTP_CLIENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|(rpc, _)| rpc.clone());
+    match client {
+        Some(client) => Ok(Http::with_client(
+            client,
+            url.parse().map_err(|e| {
+                web3::Error::Transport(TransportError::Message(format!(
+                    "invalid URL {}: {}",
+                    redact(url),
+                    e
+                )))
+            })?,
+        )),
+        None => Http::new(url),
+    }
+}
+
+/// Connections and responses kept across the commands of a long-running
+/// session such as the REPL.
+#[derive(Default)]
+struct Session {
+    transports: HashMap<String, Http>,
+    responses: HashMap<String, Value>,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// Keeps the connections and the responses bound to a block for the rest
+/// of the process: clients of the same URL share one connection pool, and
+/// calls pinned to a block number or hash are answered from memory when
+/// repeated.
+pub fn start_session() {
+    *SESSION.lock().unwrap_or_else(|e| e.into_inner()) = Some(Session::default());
+}
+
+/// Position of the parameter naming the block in calls whose result only
+/// depends on it.
+fn block_param(method: &str) -> Option<usize> {
+    match method {
+        "eth_getBlockByNumber" | "eth_getBlockReceipts" => Some(0),
+        "eth_getCode" | "eth_getBalance" | "eth_getTransactionCount" => Some(1),
+        "eth_getProof" | "eth_getStorageAt" => Some(2),
+        _ => None,
+    }
+}
+
+/// The session key of `call` to `url`, if its result cannot change.
+fn session_key(url: &str, call: &Call) -> Option<String> {
+    let Call::MethodCall(call) = call else {
+        return None;
+    };
+    let pinned = match call.method.as_str() {
+        "eth_chainId" | "net_version" | "eth_getBlockByHash" => true,
+        method => block_param(method).is_some_and(|index| {
+            let params = serde_json::to_value(&call.params).unwrap_or_default();
+            // A number or a hash, not a tag such as `latest`.
+            params[index]
+                .as_str()
+                .is_some_and(|block| block.starts_with("0x"))
+        }),
+    };
+    pinned.then(|| {
+        format!(
+            "{} {} {}",
+            url,
+            call.method,
+            serde_json::to_string(&call.params).unwrap_or_default()
+        )
+    })
+}
+
+/// HTTP transport that records the method, latency and outcome of every
+/// call for the run report, and holds calls back while a rate limit is set.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: Http,
+    url: String,
+    provider: String,
+}
+
+impl Client {
+    pub fn new(url: &str) -> web3::Result<Self> {
+        let mut session = SESSION.lock().unwrap_or_else(|e| e.into_inner());
+        let http = match session.as_mut() {
+            Some(session) => match session.transports.get(url) {
+                Some(http) => http.clone(),
+                None => {
+                    let http = http_transport(url)?;
+                    session.transports.insert(url.to_string(), http.clone());
+                    http
+                }
+            },
+            None => http_transport(url)?,
+        };
+        Ok(Client {
+            http,
+            url: url.to_string(),
+            provider: redact(url),
+        })
+    }
+}
+
+impl Transport for Client {
+    type Out = BoxFuture<'static, web3::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, Call) {
+        self.http.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: Call) -> Self::Out {
+        let method = match &request {
+            Call::MethodCall(call) => call.method.clone(),
+            Call::Notification(notification) => notification.method.clone(),
+            Call::Invalid { .. } => String::new(),
+        };
+        let key = match SESSION.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            Some(session) => match session_key(&self.url, &request) {
+                Some(key) => match session.responses.get(&key) {
+                    Some(response) => {
+                        return Box::pin(futures::future::ready(Ok(response.clone())))
+                    }
+                    None => Some(key),
+                },
+                None => None,
+            },
+            None => None,
+        };
+        let provider = self.provider.clone();
+        let http = self.http.clone();
+        let wait = reserve_call();
+        Box::pin(async move {
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            let started = Instant::now();
+            let result = match time_limit() {
+                Some((limit, reason)) => tokio::time::timeout(limit, http.send(id, request))
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(web3::Error::Transport(TransportError::Message(format!(
+                            "{} to {} {}",
+                            method, provider, reason
+                        ))))
+                    }),
+                None => http.send(id, request).await,
+            };
+            CALLS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(CallRecord {
+                    provider,
+                    method,
+                    latency: started.elapsed(),
+                    ok: result.is_ok(),
+                });
+            if let (Some(key), Ok(response)) = (key, &result) {
+                // A missing block or proof may turn up later.
+                if !response.is_null() {
+                    if let Some(session) =
+                        SESSION.lock().unwrap_or_else(|e| e.into_inner()).as_mut()
+                    {
+                        session.responses.insert(key, response.clone());
+                    }
+                }
+            }
+            result
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use futures::future::BoxFuture;
+use web3::types::{Bytes, Proof, TransactionReceipt, H160, U256};
+
+use crate::error::{Error, Result};
+use crate::source::EthDataSource;
+
+/// Chain ids of Scroll mainnet and Scroll Sepolia.
+pub const CHAIN_IDS: [u64; 2] = [534_352, 534_351];
+
+/// Last node of every proof from Scroll's zktrie, the Poseidon-hashed
+/// sparse Merkle trie Scroll used before it moved its state to the MPT in
+/// the Euclid upgrade.
+const ZKTRIE_MAGIC: &[u8] = b"THIS IS SOME MAGIC BYTES FOR SMT m1rRXgP2xpDI";
+
+/// A Scroll provider. Proofs of blocks since Euclid are MPT proofs and pass
+/// through; zktrie proofs of earlier blocks are rejected, since their
+/// Poseidon hashing is not something this tool verifies.
+pub struct Source<'a> {
+    inner: &'a dyn EthDataSource,
+}
+
+impl<'a> Source<'a> {
+    pub fn new(inner: &'a dyn EthDataSource) -> Self {
+        Source { inner }
+    }
+}
+
+fn is_zktrie(proof: &[Bytes]) -> bool {
+    proof.iter().any(|node| node.0 == ZKTRIE_MAGIC)
+}
+
+impl EthDataSource for Source<'_> {
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>> {
+        Box::pin(async move {
+            let chain_id = self.inner.chain_id().await?;
+            if !CHAIN_IDS.contains(&chain_id) {
+                return Err(Error::Chain(format!(
+                    "--rollup scroll expects Scroll mainnet ({}) or Sepolia ({}), but the \
+                     provider serves chain {}",
+                    CHAIN_IDS[0], CHAIN_IDS[1], chain_id
+                )));
+            }
+            Ok(chain_id)
+        })
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>> {
+        self.inner.get_block(number)
+    }
+
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>> {
+        Box::pin(async move {
+            let proof = self.inner.get_proof(account, keys, block).await?;
+            if let Some(proof) = &proof {
+                if is_zktrie(&proof.account_proof)
+                    || proof
+                        .storage_proof
+                        .iter()
+                        .any(|storage| is_zktrie(&storage.proof))
+                {
+                    return Err(Error::Unsupported(format!(
+                        "Scroll block {} predates the move to the MPT; its zktrie proofs hash \
+                         with Poseidon, which this tool does not verify",
+                        block
+                    )));
+                }
+            }
+            Ok(proof)
+        })
+    }
+
+    fn get_code(&self, account: H160, block: u64) -> BoxFuture<'_, Result<Bytes>> {
+        self.inner.get_code(account, block)
+    }
+
+    fn get_receipts(&self, number: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>> {
+        self.inner.get_receipts(number)
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::Path;
+
+use openssl::pkey::{Id, PKey, Private};
+use openssl::sign::{Signer, Verifier};
+
+use crate::error::{Error, Result};
+use crate::params::{Params, Value};
+
+pub const SIGNATURE_SCHEME: &str = "ed25519";
+
+/// Metadata entries excluded from the signed serialization.
+const UNSIGNED_META: &[&str] = &["signature"];
+
+fn openssl_error(e: openssl::error::ErrorStack) -> Error {
+    Error::Signature(e.to_string())
+}
+
+fn meta_hex(params: &Params, name: &str) -> Result<Vec<u8>> {
+    match params.meta.get(name) {
+        Some(Value::Str(value)) => hex::decode(value.trim_start_matches("0x"))
+            .map_err(|e| Error::Signature(format!("invalid meta.{}: {}", name, e))),
+        Some(Value::Hex(bytes)) => Ok(bytes.clone()),
+        _ => Err(Error::Signature(format!("missing meta.{}", name))),
+    }
+}
+
+fn load_key(key_path: &Path) -> Result<PKey<Private>> {
+    let pem = fs::read(key_path)?;
+    let key = PKey::private_key_from_pem(&pem).map_err(openssl_error)?;
+    if key.id() != Id::ED25519 {
+        return Err(Error::Signature(format!(
+            "{} is not an ed25519 private key",
+            key_path.display()
+        )));
+    }
+    Ok(key)
+}
+
+fn sign_with(key: &PKey<Private>, message: &[u8]) -> Result<Vec<u8>> {
+    let mut signer = Signer::new_without_digest(key).map_err(openssl_error)?;
+    signer.sign_oneshot_to_vec(message).map_err(openssl_error)
+}
+
+/// Signs `message` with the PEM encoded ed25519 private key at `key_path`,
+/// returning the signer's raw public key and the signature.
+pub fn sign_message(message: &[u8], key_path: &Path) -> Result<(Vec<u8>, Vec<u8>)> {
+    let key = load_key(key_path)?;
+    let public_key = key.raw_public_key().map_err(openssl_error)?;
+    Ok((public_key, sign_with(&key, message)?))
+}
+
+/// Checks that `signature` is the ed25519 signature of `message` by the
+/// raw public key `signer`.
+pub fn verify_message(message: &[u8], signer: &[u8], signature: &[u8]) -> Result<()> {
+    let key = PKey::public_key_from_raw_bytes(signer, Id::ED25519).map_err(openssl_error)?;
+    let mut verifier = Verifier::new_without_digest(&key).map_err(openssl_error)?;
+    if !verifier
+        .verify_oneshot(signature, message)
+        .map_err(openssl_error)?
+    {
+        return Err(Error::Signature("signature does not match".to_string()));
+    }
+    Ok(())
+}
+
+/// Signs the canonical serialization of `params` with the PEM encoded
+/// ed25519 private key at `key_path`, recording the signer's public key and
+/// the signature in the metadata.
+pub fn sign(params: &mut Params, key_path: &Path) -> Result<()> {
+    let key = load_key(key_path)?;
+    let public_key = key.raw_public_key().map_err(openssl_error)?;
+
+    params.meta.push("signature_scheme", SIGNATURE_SCHEME);
+    params.meta.push("signer", Value::Hex(public_key));
+
+    let message = params.canonical_bytes(UNSIGNED_META);
+    params
+        .meta
+        .push("signature", Value::Hex(sign_with(&key, &message)?));
+
+    Ok(())
+}
+
+/// Checks the signature embedded in `params` and returns the signer's raw
+/// public key. When `expected_signer` is given the signer must match it.
+pub fn verify(params: &Params, expected_signer: Option<&[u8]>) -> Result<Vec<u8>> {
+    match params.meta.get("signature_scheme") {
+        Some(Value::Str(scheme)) if scheme == SIGNATURE_SCHEME => {}
+        Some(_) => return Err(Error::Signature("unsupported signature scheme".to_string())),
+        None => return Err(Error::Signature("parameters are not signed".to_string())),
+    }
+    let signer = meta_hex(params, "signer")?;
+    let signature = meta_hex(params, "signature")?;
+
+    if let Some(expected) = expected_signer {
+        if expected != signer.as_slice() {
+            return Err(Error::Signature(format!(
+                "signed by 0x{}, expected 0x{}",
+                hex::encode(&signer),
+                hex::encode(expected)
+            )));
+        }
+    }
+
+    verify_message(&params.canonical_bytes(UNSIGNED_META), &signer, &signature)?;
+    Ok(signer)
+}
+
+/// Reads an ed25519 public key given either as hex or as a PEM file path.
+pub fn read_public_key(value: &str) -> Result<Vec<u8>> {
+    if let Ok(bytes) = hex::decode(value.trim_start_matches("0x")) {
+        return Ok(bytes);
+    }
+    let pem = fs::read(value)?;
+    let key = PKey::public_key_from_pem(&pem).map_err(openssl_error)?;
+    key.raw_public_key().map_err(openssl_error)
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use futures::future::BoxFuture;
+use rlp::{DecoderError, Rlp, RlpStream};
+use serde::Deserialize;
+use web3::types::{Bytes, Proof, TransactionReceipt, H160, H256, U256};
+
+use crate::chain_spec::ChainSpec;
+use crate::error::{Error, Result};
+use crate::header;
+use crate::keccak::keccak256;
+use crate::source::EthDataSource;
+use crate::trie::{self, NodeStore};
+
+/// Changes to one account, in the state override format of `eth_call`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AccountOverride {
+    balance: Option<U256>,
+    nonce: Option<U256>,
+    code: Option<Bytes>,
+    /// Replaces the whole storage, which proofs of single slots cannot
+    /// rebuild; only read to reject it.
+    state: Option<serde_json::Value>,
+    /// Slots to set, keeping the rest of the storage.
+    #[serde(default)]
+    state_diff: BTreeMap<H256, H256>,
+}
+
+/// The fields of an account leaf.
+struct Account {
+    nonce: U256,
+    balance: U256,
+    storage_root: H256,
+    code_hash: H256,
+}
+
+impl Account {
+    /// Decodes an account leaf; a missing leaf is an empty account.
+    fn decode(leaf: Option<&[u8]>) -> std::result::Result<Self, DecoderError> {
+        let Some(leaf) = leaf else {
+            return Ok(Account {
+                nonce: U256::zero(),
+                balance: U256::zero(),
+                storage_root: H256(trie::EMPTY_TRIE_ROOT),
+                code_hash: H256(trie::EMPTY_CODE_HASH),
+            });
+        };
+        let rlp = Rlp::new(leaf);
+        Ok(Account {
+            nonce: rlp.val_at(0)?,
+            balance: rlp.val_at(1)?,
+            storage_root: rlp.val_at(2)?,
+            code_hash: rlp.val_at(3)?,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(4);
+        stream
+            .append(&self.nonce)
+            .append(&self.balance)
+            .append(&self.storage_root)
+            .append(&self.code_hash);
+        stream.out().to_vec()
+    }
+}
+
+fn trie_error(e: DecoderError) -> Error {
+    let reason = match e {
+        DecoderError::Custom(reason) => reason.to_string(),
+        e => format!("{:?}", e),
+    };
+    Error::Decode(format!("cannot apply the state overrides: {}", reason))
+}
+
+fn proof_nodes(proof: &[Bytes]) -> Vec<Vec<u8>> {
+    proof.iter().map(|node| node.0.clone()).collect()
+}
+
+/// Proof of `account` in the state trie under `root`, with the account it
+/// proves.
+fn read_account(
+    nodes: &NodeStore,
+    root: [u8; 32],
+    account: H160,
+) -> std::result::Result<(Vec<Vec<u8>>, Account), DecoderError> {
+    let key = trie::key_nibbles(account.as_bytes());
+    let proof = trie::collect_proof(root, &key, nodes)?;
+    let leaf = trie::verify_proof(&root, &key, &proof)?;
+    Ok((proof, Account::decode(leaf.as_deref())?))
+}
+
+/// A data source serving one block as it would be with state overrides
+/// applied. The overridden accounts are rewritten in a state trie rebuilt
+/// from their proofs, and the block is served with the resulting state root
+/// and the hash of the header carrying it, so every proof checks out
+/// against a block that was never mined.
+pub struct Simulated<'a> {
+    inner: &'a dyn EthDataSource,
+    block: u64,
+    /// The block as `inner` returns it, with the new state root and hash.
+    block_json: serde_json::Value,
+    state_root: [u8; 32],
+    codes: BTreeMap<H160, Bytes>,
+    /// Nodes of the original and the rewritten tries, for the state trie and
+    /// every storage trie alike. Proofs fetched later add the nodes along
+    /// their paths.
+    nodes: Mutex<NodeStore>,
+}
+
+impl<'a> Simulated<'a> {
+    /// Applies the overrides in the JSON file at `path` to `block` of
+    /// `inner`, whose header is checked against the spec at `chain_spec` or
+    /// the known rules of its chain.
+    pub async fn new(
+        inner: &'a dyn EthDataSource,
+        path: &Path,
+        block: u64,
+        chain_spec: Option<&Path>,
+    ) -> Result<Self> {
+        let overrides: BTreeMap<H160, AccountOverride> =
+            serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| {
+                Error::Decode(format!("invalid state overrides {}: {}", path.display(), e))
+            })?;
+        if overrides.is_empty() {
+            return Err(Error::Decode(format!(
+                "{} overrides no account",
+                path.display()
+            )));
+        }
+        let spec = match chain_spec {
+            Some(path) => ChainSpec::load(path)?,
+            None => ChainSpec::known(inner.chain_id().await?),
+        };
+        let mut block_json = inner.get_block(block).await?;
+        let (mut header, _) = header::from_rpc(block_json.clone(), block, &spec)?;
+
+        let mut nodes = NodeStore::new();
+        let mut state_root = header.state_root.0;
+        let mut codes = BTreeMap::new();
+        for (address, changes) in overrides {
+            if changes.state.is_some() {
+                return Err(Error::Unsupported(format!(
+                    "the override of {:?} replaces its whole storage with `state`, which cannot \
+                     be rebuilt from proofs; list the slots to change under `stateDiff`",
+                    address
+                )));
+            }
+            let keys = changes
+                .state_diff
+                .keys()
+                .map(|slot| U256::from_big_endian(&slot.0))
+                .collect();
+            let proof = inner
+                .get_proof(address, keys, block)
+                .await?
+                .unwrap_or_default();
+            trie::store_proof(&mut nodes, &proof_nodes(&proof.account_proof));
+            for storage in &proof.storage_proof {
+                trie::store_proof(&mut nodes, &proof_nodes(&storage.proof));
+            }
+
+            let (_, mut account) = read_account(&nodes, state_root, address).map_err(trie_error)?;
+            for (slot, value) in &changes.state_diff {
+                let value = U256::from_big_endian(&value.0);
+                // Zero slots are removed from the trie.
+                let leaf = (!value.is_zero()).then(|| rlp::encode(&value).to_vec());
+                account.storage_root = H256(
+                    trie::update(
+                        account.storage_root.0,
+                        &trie::key_nibbles(&slot.0),
+                        leaf.as_deref(),
+                        &mut nodes,
+                    )
+                    .map_err(trie_error)?,
+                );
+            }
+            if let Some(balance) = changes.balance {
+                account.balance = balance;
+            }
+            if let Some(nonce) = changes.nonce {
+                account.nonce = nonce;
+            }
+            if let Some(code) = changes.code {
+                account.code_hash = H256(keccak256(&code.0));
+                codes.insert(address, code);
+            }
+            state_root = trie::update(
+                state_root,
+                &trie::key_nibbles(address.as_bytes()),
+                Some(&account.encode()),
+                &mut nodes,
+            )
+            .map_err(trie_error)?;
+        }
+
+        header.state_root = H256(state_root);
+        block_json["stateRoot"] = serde_json::json!(header.state_root);
+        block_json["hash"] = serde_json::json!(header.hash());
+        Ok(Simulated {
+            inner,
+            block,
+            block_json,
+            state_root,
+            codes,
+            nodes: Mutex::new(nodes),
+        })
+    }
+
+    /// Rebuilds `fetched`, a proof at the original state, against the
+    /// rewritten tries.
+    fn rewrite_proof(&self, fetched: &Proof, account: H160, keys: &[U256]) -> Result<Proof> {
+        let mut nodes = self.nodes.lock().unwrap_or_else(|e| e.into_inner());
+        trie::store_proof(&mut nodes, &proof_nodes(&fetched.account_proof));
+        for storage in &fetched.storage_proof {
+            trie::store_proof(&mut nodes, &proof_nodes(&storage.proof));
+        }
+
+        let (account_proof, state) =
+            read_account(&nodes, self.state_root, account).map_err(trie_error)?;
+        let storage_proof = keys
+            .iter()
+            .map(|key| {
+                let mut slot = [0u8; 32];
+                key.to_big_endian(&mut slot);
+                let slot_key = trie::key_nibbles(&slot);
+                let proof = trie::collect_proof(state.storage_root.0, &slot_key, &nodes)?;
+                let value =
+                    match trie::verify_proof(state.storage_root.as_bytes(), &slot_key, &proof)? {
+                        Some(leaf) => U256::from_big_endian(Rlp::new(&leaf).data()?),
+                        None => U256::zero(),
+                    };
+                // web3 does not export the storage proof type, so build
+                // each entry from its serialized form.
+                Ok(serde_json::json!({
+                    "key": key,
+                    "value": value,
+                    "proof": proof.into_iter().map(Bytes).collect::<Vec<_>>(),
+                }))
+            })
+            .collect::<std::result::Result<Vec<_>, DecoderError>>()
+            .map_err(trie_error)?;
+        let storage_proof = serde_json::from_value(serde_json::Value::Array(storage_proof))
+            .map_err(|e| Error::Decode(e.to_string()))?;
+        Ok(Proof {
+            balance: state.balance,
+            code_hash: state.code_hash,
+            nonce: state.nonce,
+            storage_hash: state.storage_root,
+            account_proof: account_proof.into_iter().map(Bytes).collect(),
+            storage_proof,
+        })
+    }
+}
+
+impl EthDataSource for Simulated<'_> {
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>> {
+        self.inner.chain_id()
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>> {
+        if number != self.block {
+            return self.inner.get_block(number);
+        }
+        Box::pin(async move { Ok(self.block_json.clone()) })
+    }
+
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>> {
+        if block != self.block {
+            return self.inner.get_proof(account, keys, block);
+        }
+        Box::pin(async move {
+            let fetched = self
+                .inner
+                .get_proof(account, keys.clone(), block)
+                .await?
+                .unwrap_or_default();
+            self.rewrite_proof(&fetched, account, &keys).map(Some)
+        })
+    }
+
+    fn get_code(&self, account: H160, block: u64) -> BoxFuture<'_, Result<Bytes>> {
+        match self.codes.get(&account) {
+            Some(code) if block == self.block => {
+                let code = code.clone();
+                Box::pin(async move { Ok(code) })
+            }
+            _ => self.inner.get_code(account, block),
+        }
+    }
+
+    fn get_receipts(&self, number: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>> {
+        self.inner.get_receipts(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo;
+
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    /// Applies `overrides` to the recorded demo block.
+    fn simulate(name: &str, overrides: serde_json::Value) -> Result<Simulated<'static>> {
+        let path = std::env::temp_dir().join(format!(
+            "noir-mip-overrides-{}-{}.json",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, overrides.to_string()).unwrap();
+        let simulated = block_on(Simulated::new(&demo::Recorded, &path, demo::BLOCK, None));
+        fs::remove_file(&path).unwrap();
+        simulated
+    }
+
+    /// Sets the balance of the demo account to 100 and its slot 0 to
+    /// `value`.
+    fn slot_zero(value: u64) -> serde_json::Value {
+        serde_json::json!({
+            format!("{:?}", demo::args().account): {
+                "balance": "0x64",
+                "stateDiff": { format!("{:?}", H256::zero()): H256::from_low_u64_be(value) },
+            }
+        })
+    }
+
+    #[test]
+    fn the_overridden_block_proves_the_new_values() {
+        let simulated = simulate("set", slot_zero(0x2a)).unwrap();
+        let block = block_on(simulated.get_block(demo::BLOCK)).unwrap();
+        let recorded = block_on(demo::Recorded.get_block(demo::BLOCK)).unwrap();
+        assert_ne!(block["stateRoot"], recorded["stateRoot"]);
+        assert_ne!(block["hash"], recorded["hash"]);
+
+        let params =
+            block_on(crate::generate_with_source(&simulated, &demo::args(), true)).unwrap();
+        demo::verify(&params).unwrap();
+        let hash: H256 = serde_json::from_value(block["hash"].clone()).unwrap();
+        assert_eq!(params.fields.get_bytes("block_hash"), Some(hash.as_bytes()));
+        let value = params.fields.get_bytes("storage_value").unwrap();
+        assert_eq!(U256::from_big_endian(value), U256::from(0x2a));
+
+        let proof =
+            block_on(simulated.get_proof(demo::args().account, vec![U256::zero()], demo::BLOCK))
+                .unwrap()
+                .unwrap();
+        assert_eq!(proof.balance, U256::from(0x64));
+        assert_eq!(proof.storage_proof[0].value, U256::from(0x2a));
+    }
+
+    #[test]
+    fn a_zero_value_removes_the_slot() {
+        let simulated = simulate("clear", slot_zero(0)).unwrap();
+        let proof =
+            block_on(simulated.get_proof(demo::args().account, vec![U256::zero()], demo::BLOCK))
+                .unwrap()
+                .unwrap();
+        let nodes = proof_nodes(&proof.storage_proof[0].proof);
+        let leaf = trie::verify_proof(
+            proof.storage_hash.as_bytes(),
+            &trie::key_nibbles(H256::zero().as_bytes()),
+            &nodes,
+        )
+        .unwrap();
+        assert_eq!(leaf, None);
+        assert_eq!(proof.storage_proof[0].value, U256::zero());
+    }
+
+    #[test]
+    fn overrides_proofs_cannot_rebuild_are_refused() {
+        let account = format!("{:?}", demo::args().account);
+        match simulate("state", serde_json::json!({ &account: { "state": {} } })) {
+            Err(Error::Unsupported(message)) => assert!(message.contains("stateDiff")),
+            _ => panic!("a whole storage override was applied"),
+        }
+        assert!(matches!(
+            simulate("empty", serde_json::json!({})),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            simulate(
+                "unknown",
+                serde_json::json!({ &account: { "storage": {} } })
+            ),
+            Err(Error::Decode(_))
+        ));
+    }
+}
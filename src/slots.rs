@@ -0,0 +1,356 @@
+use web3::types::{BlockNumber, H160, H256, U256, U64};
+use web3::{Transport, Web3};
+
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::rpc::Client;
+
+/// Slot holding `key`'s entry in a Solidity mapping declared at slot
+/// `index`: `keccak256(key . index)`, both left-padded to 32 bytes.
+pub fn mapping_slot(index: U256, key: &[u8]) -> H256 {
+    let mut preimage = [0u8; 64];
+    preimage[32 - key.len()..32].copy_from_slice(key);
+    index.to_big_endian(&mut preimage[32..]);
+    H256(keccak256(&preimage))
+}
+
+/// A slot index given in decimal or as `0x`-prefixed hex.
+pub fn parse_slot_index(text: &str) -> Result<U256> {
+    match text.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(text).ok(),
+    }
+    .ok_or_else(|| Error::Decode(format!("invalid mapping slot index {}", text)))
+}
+
+/// A mapping key given as hex of at most 32 bytes, which
+/// [`mapping_slot`] left-pads.
+pub fn parse_mapping_key(text: &str) -> Result<Vec<u8>> {
+    hex::decode(text.trim_start_matches("0x"))
+        .ok()
+        .filter(|key| key.len() <= 32)
+        .ok_or_else(|| {
+            Error::Decode(format!(
+                "mapping key {} is not hex of at most 32 bytes",
+                text
+            ))
+        })
+}
+
+/// Warnings about the arguments of a mapping slot derived from `index` and
+/// `key` that look mixed up. Keccak-derived slots never land among the low
+/// slots variables are declared at, so a small key beside an index too
+/// large to be a declaration's suggests the two are swapped, unless the
+/// mapping is nested in another, whose entry's slot is the index.
+pub fn check_mapping(index: U256, key: &[u8]) -> Vec<String> {
+    let key_value = U256::from_big_endian(key);
+    let mut warnings = Vec::new();
+    if index > U256::from(u64::MAX) && key_value <= U256::from(u16::MAX) {
+        warnings.push(format!(
+            "the mapping index {:#x} is too large for a declared variable and the key {} looks \
+             like a slot index; are INDEX and KEY swapped? (a nested mapping's index is the \
+             slot of its outer entry)",
+            index, key_value
+        ));
+    }
+    warnings
+}
+
+/// Slots that common mistakes in the `index` and `key` of a mapping slot
+/// would have meant, each with the mistake.
+fn mapping_alternatives(index: U256, key: &[u8]) -> Vec<(H256, String)> {
+    let mut alternatives = Vec::new();
+    let mut index_bytes = [0u8; 32];
+    index.to_big_endian(&mut index_bytes);
+    if key.len() < 32 {
+        let mut preimage = [0u8; 64];
+        preimage[..key.len()].copy_from_slice(key);
+        preimage[32..].copy_from_slice(&index_bytes);
+        alternatives.push((
+            H256(keccak256(&preimage)),
+            "the key is a bytesN, which Solidity pads on the right, not the left".to_string(),
+        ));
+        let mut unpadded = key.to_vec();
+        unpadded.extend_from_slice(&index_bytes);
+        alternatives.push((
+            H256(keccak256(&unpadded)),
+            "the key is a string or bytes, which Solidity hashes without padding".to_string(),
+        ));
+    }
+    alternatives.push((
+        mapping_slot(U256::from_big_endian(key), &index_bytes),
+        "INDEX and KEY are swapped".to_string(),
+    ));
+    for base in [index.checked_sub(1.into()), index.checked_add(1.into())]
+        .into_iter()
+        .flatten()
+    {
+        alternatives.push((
+            mapping_slot(base, key),
+            format!("the mapping is declared at slot {}, not {}", base, index),
+        ));
+    }
+    alternatives
+}
+
+/// Warnings for a mapping slot derived from `index` and `key` that holds
+/// zero in `account` at `block`: [`check_mapping`]'s, and for each common
+/// mistake whose slot `eth_getStorageAt` reports a value in, that mistake
+/// as the likely cause. A slot holding a value gets none.
+pub async fn diagnose_mapping<T: Transport>(
+    web3: &Web3<T>,
+    account: H160,
+    block: u64,
+    index: U256,
+    key: &[u8],
+) -> Result<Vec<String>> {
+    let at = Some(BlockNumber::Number(block.into()));
+    let read = |slot: H256| async move {
+        let word = web3
+            .eth()
+            .storage(account, U256::from_big_endian(slot.as_bytes()), at)
+            .await?;
+        Ok::<_, Error>(U256::from_big_endian(word.as_bytes()))
+    };
+    let slot = mapping_slot(index, key);
+    if !read(slot).await?.is_zero() {
+        return Ok(Vec::new());
+    }
+    let mut warnings = check_mapping(index, key);
+    for (alternative, cause) in mapping_alternatives(index, key) {
+        let value = read(alternative).await?;
+        if !value.is_zero() {
+            warnings.push(format!(
+                "slot {:?} holds zero, but {:?} holds {:#x}; perhaps {}",
+                slot, alternative, value, cause
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Longest `string` or `bytes` value [`BytesLayout::decode`] accepts, in
+/// slots.
+const MAX_BYTES_SLOTS: usize = 1 << 15;
+
+/// Where a Solidity `string` or `bytes` declared at slot `base` keeps its
+/// value. Up to 31 bytes sit in the base slot itself, left-aligned, with
+/// twice the length in the lowest byte. Longer values hold `2 * len + 1`
+/// there and fill the slots from `keccak256(base)` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BytesLayout {
+    pub base: H256,
+    pub len: usize,
+    /// First slot of the data, for values over 31 bytes.
+    pub data_slot: Option<H256>,
+}
+
+impl BytesLayout {
+    /// The layout given by `word`, the value held in slot `base`.
+    pub fn decode(base: H256, word: U256) -> Result<Self> {
+        let invalid = |why: &str| {
+            Err(Error::Decode(format!(
+                "slot {:?} holds {:#x}, which is not the length of a Solidity string or bytes: \
+                 {}",
+                base, word, why
+            )))
+        };
+        if !word.bit(0) {
+            let len = (word.low_u64() & 0xff) as usize / 2;
+            if len > 31 {
+                return invalid("a short value has at most 31 bytes");
+            }
+            if (word >> 8) & ((U256::one() << (8 * (31 - len))) - 1) != U256::zero() {
+                return invalid("the bytes after a short value are not zero");
+            }
+            return Ok(BytesLayout {
+                base,
+                len,
+                data_slot: None,
+            });
+        }
+        let len = word >> 1;
+        if len < U256::from(32) {
+            return invalid("values under 32 bytes are stored in the slot itself");
+        }
+        if len > U256::from(MAX_BYTES_SLOTS * 32) {
+            return Err(Error::Unsupported(format!(
+                "the {} byte value at slot {:?} spans more than {} slots",
+                len, base, MAX_BYTES_SLOTS
+            )));
+        }
+        Ok(BytesLayout {
+            base,
+            len: len.as_usize(),
+            data_slot: Some(H256(keccak256(base.as_bytes()))),
+        })
+    }
+
+    /// The slots to prove: the base slot, then the data slots in order.
+    pub fn slots(&self) -> Vec<H256> {
+        let mut slots = vec![self.base];
+        if let Some(data_slot) = self.data_slot {
+            let first = U256::from_big_endian(data_slot.as_bytes());
+            for i in 0..self.len.div_ceil(32) {
+                let mut slot = [0u8; 32];
+                first
+                    .overflowing_add(U256::from(i))
+                    .0
+                    .to_big_endian(&mut slot);
+                slots.push(H256(slot));
+            }
+        }
+        slots
+    }
+
+    /// The value, from `words`, the values held in [`BytesLayout::slots`].
+    pub fn value(&self, words: &[U256]) -> Vec<u8> {
+        let words = match self.data_slot {
+            Some(_) => &words[1..],
+            None => &words[..1],
+        };
+        let mut value: Vec<u8> = words
+            .iter()
+            .flat_map(|word| {
+                let mut bytes = [0u8; 32];
+                word.to_big_endian(&mut bytes);
+                bytes
+            })
+            .collect();
+        value.truncate(self.len);
+        value
+    }
+}
+
+/// Keys requested per call while paging through a contract's storage.
+const PAGE_SIZE: usize = 1024;
+
+fn is_method_missing(e: &web3::Error) -> bool {
+    matches!(e, web3::Error::Rpc(rpc) if rpc.code.code() == -32601)
+}
+
+fn decode<T: serde::de::DeserializeOwned>(method: &str, value: serde_json::Value) -> Result<T> {
+    serde_json::from_value(value)
+        .map_err(|e| Error::Decode(format!("invalid {} response: {}", method, e)))
+}
+
+/// Lists the occupied storage slots of `account` after block `block`, at
+/// most `limit` of them. Uses geth's `debug_storageRangeAt` and falls back
+/// to `parity_listStorageKeys`.
+pub async fn enumerate(
+    web3: &Web3<Client>,
+    account: H160,
+    block: u64,
+    limit: Option<usize>,
+) -> Result<Vec<H256>> {
+    match storage_range_at(web3, account, block, limit).await {
+        Err(Error::Rpc(e)) if is_method_missing(&e) => {}
+        result => return result,
+    }
+    match list_storage_keys(web3, account, block, limit).await {
+        Err(Error::Rpc(e)) if is_method_missing(&e) => Err(Error::Unsupported(
+            "the provider serves neither debug_storageRangeAt nor parity_listStorageKeys, so the \
+             storage of the account cannot be enumerated"
+                .to_string(),
+        )),
+        result => result,
+    }
+}
+
+/// Pages through `debug_storageRangeAt`. It reports the state before a
+/// transaction, so the state after `block` is read at the first
+/// transaction of the next block.
+async fn storage_range_at(
+    web3: &Web3<Client>,
+    account: H160,
+    block: u64,
+    limit: Option<usize>,
+) -> Result<Vec<H256>> {
+    let next = web3
+        .transport()
+        .execute(
+            "eth_getBlockByNumber",
+            vec![
+                serde_json::json!(U64::from(block + 1)),
+                serde_json::json!(false),
+            ],
+        )
+        .await?;
+    if next.is_null() {
+        return Err(Error::UnminedBlock(format!(
+            "enumerating storage after block {} reads it from block {}, which was not found",
+            block,
+            block + 1
+        )));
+    }
+    let next_hash: H256 = decode("eth_getBlockByNumber", next["hash"].clone())?;
+
+    let mut slots: Vec<H256> = Vec::new();
+    let mut start = H256::zero();
+    loop {
+        let page = limit.map_or(PAGE_SIZE, |limit| PAGE_SIZE.min(limit - slots.len()));
+        let response = web3
+            .transport()
+            .execute(
+                "debug_storageRangeAt",
+                vec![
+                    serde_json::json!(next_hash),
+                    serde_json::json!(0),
+                    serde_json::json!(account),
+                    serde_json::json!(start),
+                    serde_json::json!(page),
+                ],
+            )
+            .await?;
+        let storage: serde_json::Map<String, serde_json::Value> =
+            decode("debug_storageRangeAt", response["storage"].clone())?;
+        for (hashed, entry) in storage {
+            if entry["key"].is_null() {
+                return Err(Error::Unsupported(format!(
+                    "the provider has no preimage for hashed slot {}; enable preimage recording \
+                     on the node to enumerate slots",
+                    hashed
+                )));
+            }
+            slots.push(decode("debug_storageRangeAt", entry["key"].clone())?);
+        }
+
+        if response["nextKey"].is_null() || limit.is_some_and(|limit| slots.len() >= limit) {
+            return Ok(slots);
+        }
+        start = decode("debug_storageRangeAt", response["nextKey"].clone())?;
+    }
+}
+
+/// Pages through `parity_listStorageKeys`, which OpenEthereum only serves
+/// with fat DB enabled.
+async fn list_storage_keys(
+    web3: &Web3<Client>,
+    account: H160,
+    block: u64,
+    limit: Option<usize>,
+) -> Result<Vec<H256>> {
+    let mut slots: Vec<H256> = Vec::new();
+    loop {
+        let page = limit.map_or(PAGE_SIZE, |limit| PAGE_SIZE.min(limit - slots.len()));
+        let response = web3
+            .transport()
+            .execute(
+                "parity_listStorageKeys",
+                vec![
+                    serde_json::json!(account),
+                    serde_json::json!(page),
+                    serde_json::json!(slots.last()),
+                    serde_json::json!(U64::from(block)),
+                ],
+            )
+            .await?;
+        let keys: Option<Vec<H256>> = decode("parity_listStorageKeys", response)?;
+        let keys = keys.unwrap_or_default();
+        let done = keys.len() < page;
+        slots.extend(keys);
+        if done || limit.is_some_and(|limit| slots.len() >= limit) {
+            return Ok(slots);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use futures::future::BoxFuture;
+use futures::lock::Mutex;
+use web3::types::{BlockNumber, Bytes, Proof, TransactionReceipt, H160, H256, U256, U64};
+use web3::{Transport, Web3};
+
+use crate::error::{Error, Result};
+
+/// Where blocks and proofs come from. The generators only reach the chain
+/// through this trait, so library users can pass a mock or their own client
+/// (an authenticated gateway, say) to [`crate::generate_with_source`].
+pub trait EthDataSource: Send + Sync {
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>>;
+
+    /// Block `number` as the provider returns it from
+    /// `eth_getBlockByNumber`, without transactions; `None` if it has no
+    /// such block.
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>>;
+
+    /// The `eth_getProof` response for `account` and `keys` at `block`.
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>>;
+
+    fn get_code(&self, account: H160, block: u64) -> BoxFuture<'_, Result<Bytes>>;
+
+    /// Receipts of every transaction in block `number`, in block order.
+    fn get_receipts(&self, number: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>>;
+
+    /// The trie node hashing to `hash`, for completing a proof a provider
+    /// cut short; `None` if the node is not known. Sources that cannot look
+    /// nodes up by hash need not implement it.
+    fn get_node(&self, hash: H256) -> BoxFuture<'_, Result<Option<Bytes>>> {
+        Box::pin(async move {
+            Err(Error::Unsupported(format!(
+                "this source cannot look up trie node {:?} by hash",
+                hash
+            )))
+        })
+    }
+}
+
+/// Explains providers that refuse `eth_getProof` instead of surfacing a
+/// bare RPC error.
+fn proof_rpc_error(e: web3::Error) -> Error {
+    if let web3::Error::Rpc(rpc) = &e {
+        let message = rpc.message.to_lowercase();
+        let unsupported = rpc.code.code() == -32601
+            || [
+                "not supported",
+                "not available",
+                "does not exist",
+                "disabled",
+            ]
+            .iter()
+            .any(|hint| message.contains(hint));
+        if unsupported {
+            return Error::Unsupported(format!(
+                "the provider does not serve eth_getProof ({}). Proofs cannot be rebuilt from \
+                 debug_accountRange or trace APIs without the full state trie; use a node \
+                 that exposes eth_getProof for this block",
+                rpc.message
+            ));
+        }
+        if message.contains("missing trie node") {
+            return Error::Unsupported(format!(
+                "the provider no longer has the state for this block ({}); use an archive node",
+                rpc.message
+            ));
+        }
+    }
+    Error::Rpc(e)
+}
+
+/// Any web3 transport: the recording HTTP [`crate::rpc::Client`] the command
+/// line uses, web3's WebSocket and IPC transports, or a custom one.
+impl<T> EthDataSource for Web3<T>
+where
+    T: Transport + Send + Sync,
+    T::Out: Send,
+{
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>> {
+        Box::pin(async move { Ok(self.eth().chain_id().await?.as_u64()) })
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>> {
+        Box::pin(async move {
+            Ok(self
+                .transport()
+                .execute(
+                    "eth_getBlockByNumber",
+                    vec![
+                        serde_json::json!(U64::from(number)),
+                        serde_json::json!(false),
+                    ],
+                )
+                .await?)
+        })
+    }
+
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>> {
+        Box::pin(async move {
+            self.eth()
+                .proof(account, keys, Some(BlockNumber::Number(U64::from(block))))
+                .await
+                .map_err(proof_rpc_error)
+        })
+    }
+
+    fn get_code(&self, account: H160, block: u64) -> BoxFuture<'_, Result<Bytes>> {
+        Box::pin(async move {
+            Ok(self
+                .eth()
+                .code(account, Some(BlockNumber::Number(U64::from(block))))
+                .await?)
+        })
+    }
+
+    fn get_receipts(&self, number: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>> {
+        Box::pin(async move {
+            let receipts = self
+                .transport()
+                .execute(
+                    "eth_getBlockReceipts",
+                    vec![serde_json::json!(U64::from(number))],
+                )
+                .await?;
+            serde_json::from_value(receipts)
+                .map_err(|e| Error::Decode(format!("invalid block receipts: {}", e)))
+        })
+    }
+
+    /// Reads the node with `debug_dbGet`, which nodes keeping the state
+    /// trie by hash, such as Geth's hash-based scheme, serve.
+    fn get_node(&self, hash: H256) -> BoxFuture<'_, Result<Option<Bytes>>> {
+        Box::pin(async move {
+            let node = self
+                .transport()
+                .execute("debug_dbGet", vec![serde_json::json!(hash)])
+                .await
+                .map_err(|e| match e {
+                    web3::Error::Rpc(rpc) if rpc.code.code() == -32601 => Error::Unsupported(
+                        format!("the provider does not serve debug_dbGet ({})", rpc.message),
+                    ),
+                    e => Error::Rpc(e),
+                })?;
+            serde_json::from_value(node)
+                .map_err(|e| Error::Decode(format!("invalid debug_dbGet response: {}", e)))
+        })
+    }
+}
+
+/// Serves every slot of one account at one block from a single
+/// `eth_getProof` carrying all of their keys, fetched on first use, with
+/// the chain id and the block also read once. Anything else goes to
+/// `inner`. `dump-slots` generates each slot's witness from it, so a batch
+/// of slots costs three calls instead of three per slot.
+pub struct Batch<'a> {
+    inner: &'a dyn EthDataSource,
+    account: H160,
+    keys: Vec<U256>,
+    block: u64,
+    fetched: Mutex<Fetched>,
+}
+
+#[derive(Default)]
+struct Fetched {
+    chain_id: Option<u64>,
+    block: Option<serde_json::Value>,
+    proof: Option<Option<Proof>>,
+}
+
+impl<'a> Batch<'a> {
+    pub fn new(inner: &'a dyn EthDataSource, account: H160, keys: Vec<U256>, block: u64) -> Self {
+        Batch {
+            inner,
+            account,
+            keys,
+            block,
+            fetched: Mutex::new(Fetched::default()),
+        }
+    }
+}
+
+impl EthDataSource for Batch<'_> {
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>> {
+        Box::pin(async move {
+            let mut fetched = self.fetched.lock().await;
+            if let Some(chain_id) = fetched.chain_id {
+                return Ok(chain_id);
+            }
+            let chain_id = self.inner.chain_id().await?;
+            fetched.chain_id = Some(chain_id);
+            Ok(chain_id)
+        })
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>> {
+        Box::pin(async move {
+            if number != self.block {
+                return self.inner.get_block(number).await;
+            }
+            let mut fetched = self.fetched.lock().await;
+            if let Some(block) = &fetched.block {
+                return Ok(block.clone());
+            }
+            let block = self.inner.get_block(number).await?;
+            fetched.block = Some(block.clone());
+            Ok(block)
+        })
+    }
+
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>> {
+        Box::pin(async move {
+            let batched = account == self.account
+                && block == self.block
+                && keys.iter().all(|key| self.keys.contains(key));
+            if !batched {
+                return self.inner.get_proof(account, keys, block).await;
+            }
+            let mut fetched = self.fetched.lock().await;
+            if fetched.proof.is_none() {
+                let proof = self
+                    .inner
+                    .get_proof(account, self.keys.clone(), block)
+                    .await?;
+                fetched.proof = Some(proof);
+            }
+            let Some(Some(proof)) = &fetched.proof else {
+                return Ok(None);
+            };
+            let storage_proof = keys
+                .iter()
+                .map(|key| {
+                    proof
+                        .storage_proof
+                        .iter()
+                        .find(|entry| entry.key == *key)
+                        .cloned()
+                        .ok_or_else(|| {
+                            Error::Decode(format!(
+                                "the batched eth_getProof response has no proof of key {:#x}",
+                                key
+                            ))
+                        })
+                })
+                .collect::<Result<_>>()?;
+            Ok(Some(Proof {
+                storage_proof,
+                ..proof.clone()
+            }))
+        })
+    }
+
+    fn get_code(&self, account: H160, block: u64) -> BoxFuture<'_, Result<Bytes>> {
+        self.inner.get_code(account, block)
+    }
+
+    fn get_receipts(&self, number: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>> {
+        self.inner.get_receipts(number)
+    }
+
+    fn get_node(&self, hash: H256) -> BoxFuture<'_, Result<Option<Bytes>>> {
+        self.inner.get_node(hash)
+    }
+}
+
+/// Serves the block numbered `number` as `block`, a block off the
+/// canonical chain such as an uncle, by reading its proofs and code at its
+/// hash (EIP-1898) rather than at the number, which names the canonical
+/// block. Providers keep the state of such blocks only briefly, if at all.
+pub struct AtHash<'a, T: Transport> {
+    web3: &'a Web3<T>,
+    number: u64,
+    hash: H256,
+    block: serde_json::Value,
+}
+
+impl<'a, T: Transport> AtHash<'a, T> {
+    pub fn new(web3: &'a Web3<T>, number: u64, hash: H256, block: serde_json::Value) -> Self {
+        AtHash {
+            web3,
+            number,
+            hash,
+            block,
+        }
+    }
+
+    fn check(&self, number: u64) -> Result<()> {
+        match number == self.number {
+            true => Ok(()),
+            false => Err(Error::Unsupported(format!(
+                "only block {} is served at {:?}",
+                self.number, self.hash
+            ))),
+        }
+    }
+
+    /// `method` called with `params` followed by this block's hash.
+    async fn at_hash<R: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        mut params: Vec<serde_json::Value>,
+    ) -> Result<R> {
+        params.push(serde_json::json!({ "blockHash": self.hash }));
+        let value = self
+            .web3
+            .transport()
+            .execute(method, params)
+            .await
+            .map_err(proof_rpc_error)?;
+        serde_json::from_value(value)
+            .map_err(|e| Error::Decode(format!("invalid {} response: {}", method, e)))
+    }
+}
+
+impl<T> EthDataSource for AtHash<'_, T>
+where
+    T: Transport + Send + Sync,
+    T::Out: Send,
+{
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>> {
+        self.web3.chain_id()
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>> {
+        Box::pin(async move {
+            self.check(number)?;
+            Ok(self.block.clone())
+        })
+    }
+
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>> {
+        Box::pin(async move {
+            self.check(block)?;
+            self.at_hash(
+                "eth_getProof",
+                vec![serde_json::json!(account), serde_json::json!(keys)],
+            )
+            .await
+        })
+    }
+
+    fn get_code(&self, account: H160, block: u64) -> BoxFuture<'_, Result<Bytes>> {
+        Box::pin(async move {
+            self.check(block)?;
+            self.at_hash("eth_getCode", vec![serde_json::json!(account)])
+                .await
+        })
+    }
+
+    fn get_receipts(&self, _number: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>> {
+        Box::pin(async move {
+            Err(Error::Unsupported(format!(
+                "receipts are not read at block hash {:?}",
+                self.hash
+            )))
+        })
+    }
+
+    fn get_node(&self, hash: H256) -> BoxFuture<'_, Result<Option<Bytes>>> {
+        self.web3.get_node(hash)
+    }
+}
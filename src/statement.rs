@@ -0,0 +1,281 @@
+//! `--self-check`: evaluates the statement a circuit proves from the prover
+//! parameters alone, before any prover runs. Each link of the chain, block
+//! hash to header to state root to account to storage root to value, is
+//! checked on the emitted (padded) fields the way the circuit reads them,
+//! and reported with the values it produced, so a witness the circuit would
+//! reject shows which link breaks.
+
+use rlp::Rlp;
+use web3::types::U256;
+
+use crate::encoding::{self, NumericEncoding};
+use crate::error::{Error, Result};
+use crate::params::{Params, Value};
+use crate::{proof_order, trie, PROOF_BYTES_LEN};
+
+/// One link of the statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub name: &'static str,
+    pub passed: bool,
+    /// What the step computed, or why it failed.
+    pub detail: String,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// The fields of `params`, looked up under the names `renames` gave them.
+struct Fields<'a> {
+    params: &'a Params,
+    renames: &'a [(String, String)],
+}
+
+impl Fields<'_> {
+    fn name(&self, name: &str) -> String {
+        self.renames
+            .iter()
+            .find(|(old, _)| old == name)
+            .map_or(name.to_string(), |(_, new)| new.clone())
+    }
+
+    fn get(&self, name: &str) -> Option<&Value> {
+        self.params.fields.get(&self.name(name))
+    }
+
+    fn bytes(&self, name: &str) -> Option<&[u8]> {
+        self.params.fields.get_bytes(&self.name(name))
+    }
+
+    fn int(&self, name: &str) -> Option<u64> {
+        self.params.fields.get_int(&self.name(name))
+    }
+
+    /// The unpadded nodes of the proof `name` and of its tail, if split.
+    fn proof(&self, name: &str) -> Option<Vec<Vec<u8>>> {
+        let mut nodes = Vec::new();
+        for part in [name.to_string(), format!("{}_tail", name)] {
+            if let Some(flat) = self.bytes(&part) {
+                let depth = self.int(&format!("{}_depth", part))?;
+                nodes.extend(trie::unpad_proof(flat, PROOF_BYTES_LEN, depth as usize));
+            }
+        }
+        let nodes = proof_order::recorded(self.params).root_first(nodes);
+        (!nodes.is_empty()).then_some(nodes)
+    }
+}
+
+fn numeric_encoding(params: &Params) -> NumericEncoding {
+    match params.meta.get("numeric_encoding") {
+        Some(Value::Str(name)) => NumericEncoding::from_name(name).unwrap_or_default(),
+        _ => NumericEncoding::default(),
+    }
+}
+
+/// Evaluates the statement of `params`, prover parameters whose fields
+/// `renames` may have renamed. Steps after a failed one are not evaluated,
+/// since they would start from a value the circuit never reaches.
+pub fn evaluate(params: &Params, renames: &[(String, String)]) -> Vec<Step> {
+    let fields = Fields { params, renames };
+    let mut steps = Vec::new();
+    let mut step = |name, outcome: std::result::Result<String, String>| {
+        let passed = outcome.is_ok();
+        steps.push(Step {
+            name,
+            passed,
+            detail: outcome.unwrap_or_else(|e| e),
+        });
+        passed
+    };
+
+    let state_root = match (fields.bytes("block_header_rlp"), fields.bytes("state_root")) {
+        (Some(padded), _) => {
+            let header = encoding::header_padding(params).unpad(padded);
+            let Some(header) = header else {
+                step(
+                    "block hash",
+                    Err("block_header_rlp is not an RLP list".to_string()),
+                );
+                return steps;
+            };
+            let hash = crate::keccak::keccak256(header);
+            let expected = fields.bytes("block_hash").unwrap_or_default();
+            let outcome = match hash == expected {
+                true => Ok(format!(
+                    "keccak of the {}-byte header = {}",
+                    header.len(),
+                    hex(&hash)
+                )),
+                false => Err(format!(
+                    "keccak of the {}-byte header = {}, but block_hash = {}",
+                    header.len(),
+                    hex(&hash),
+                    hex(expected)
+                )),
+            };
+            if !step("block hash", outcome) {
+                return steps;
+            }
+            let from_rlp = Rlp::new(header).at(3).and_then(|item| item.data());
+            let at_split = fields
+                .int("block_header_rlp_head_len")
+                .and_then(|head_len| header.get(head_len as usize..head_len as usize + 32));
+            let root = from_rlp.as_ref().ok().map(|root| root.to_vec());
+            let outcome = match (from_rlp, at_split) {
+                (Ok(root), Some(split)) if root != split => Err(format!(
+                    "the header's state root is {}, but the head length points at {}",
+                    hex(root),
+                    hex(split)
+                )),
+                (Ok(root), _) => Ok(hex(root)),
+                (Err(e), _) => Err(format!("the header has no state root: {:?}", e)),
+            };
+            if !step("state root", outcome) {
+                return steps;
+            }
+            root.unwrap_or_default()
+        }
+        (None, Some(root)) => {
+            step("state root", Ok(format!("{}, a public input", hex(root))));
+            root.to_vec()
+        }
+        (None, None) => {
+            step(
+                "state root",
+                Err("the parameters have neither a header nor a state root".to_string()),
+            );
+            return steps;
+        }
+    };
+
+    let (Some(address), Some(account_value), Some(nodes)) = (
+        fields.bytes("account_key"),
+        fields.bytes("account_value"),
+        fields.proof("account_proof"),
+    ) else {
+        step(
+            "account proof",
+            Err("the parameters lack account_key, account_value or account_proof".to_string()),
+        );
+        return steps;
+    };
+    let exists = fields.int("account_exists") != Some(0);
+    let outcome = match trie::verify_proof(&state_root, &trie::key_nibbles(address), &nodes) {
+        Ok(Some(leaf)) if exists && leaf == account_value => {
+            let account = Rlp::new(account_value);
+            let nonce: U256 = account.val_at(0).unwrap_or_default();
+            let balance: U256 = account.val_at(1).unwrap_or_default();
+            Ok(format!(
+                "{} nodes lead to {} (nonce {}, balance {})",
+                nodes.len(),
+                hex(address),
+                nonce,
+                balance
+            ))
+        }
+        Ok(Some(leaf)) => Err(format!(
+            "{} nodes lead to the account {}, not account_value {}",
+            nodes.len(),
+            hex(&leaf),
+            hex(account_value)
+        )),
+        Ok(None) if !exists => Ok(format!(
+            "{} nodes show {} is absent",
+            nodes.len(),
+            hex(address)
+        )),
+        Ok(None) => Err(format!(
+            "{} nodes show {} is absent, but account_exists is set",
+            nodes.len(),
+            hex(address)
+        )),
+        Err(e) => Err(format!("{:?}", e)),
+    };
+    if !step("account proof", outcome) {
+        return steps;
+    }
+
+    let Some(storage_key) = fields.get("storage_key") else {
+        return steps;
+    };
+    let from_account = Rlp::new(account_value).at(2).and_then(|item| item.data());
+    let storage_root = fields.bytes("storage_root").unwrap_or_default();
+    let outcome = match from_account {
+        Ok(root) if root == storage_root => Ok(hex(root)),
+        Ok(root) => Err(format!(
+            "the account holds {}, but storage_root = {}",
+            hex(root),
+            hex(storage_root)
+        )),
+        Err(e) => Err(format!("the account has no storage root: {:?}", e)),
+    };
+    if !step("storage root", outcome) {
+        return steps;
+    }
+
+    let encoding = numeric_encoding(params);
+    let (Some(key), Some(expected)) = (
+        encoding.decode(storage_key),
+        fields.get("storage_value").and_then(|value| {
+            encoding::value_form(params).decode(value, fields.int("storage_value_len"), encoding)
+        }),
+    ) else {
+        step(
+            "storage proof",
+            Err("storage_key or storage_value does not decode".to_string()),
+        );
+        return steps;
+    };
+    let mut key_bytes = [0u8; 32];
+    key.to_big_endian(&mut key_bytes);
+    let nodes = fields.proof("storage_proof").unwrap_or_default();
+    let value =
+        trie::verify_proof(storage_root, &trie::key_nibbles(&key_bytes), &nodes).and_then(|leaf| {
+            match leaf {
+                Some(leaf) => Ok(U256::from_big_endian(Rlp::new(&leaf).data()?)),
+                None => Ok(U256::zero()),
+            }
+        });
+    let outcome = match value {
+        Ok(value) if value == expected => Ok(format!(
+            "{} nodes lead from slot {} to {:#x}",
+            nodes.len(),
+            hex(&key_bytes),
+            value
+        )),
+        Ok(value) => Err(format!(
+            "{} nodes lead to {:#x}, not storage_value {:#x}",
+            nodes.len(),
+            value,
+            expected
+        )),
+        Err(e) => Err(format!("{:?}", e)),
+    };
+    step("storage proof", outcome);
+    steps
+}
+
+/// `steps` as a table, one line per step with its verdict.
+pub fn render(steps: &[Step]) -> String {
+    let mut out = String::from("Statement check:\n");
+    for step in steps {
+        let verdict = if step.passed { "PASS" } else { "FAIL" };
+        out.push_str(&format!(
+            "  {}  {:<14} {}\n",
+            verdict, step.name, step.detail
+        ));
+    }
+    out
+}
+
+/// Fails with [`Error::Circuit`] when one of `steps` does.
+pub fn check(steps: &[Step]) -> Result<()> {
+    match steps.iter().find(|step| !step.passed) {
+        Some(step) => Err(Error::Circuit(format!(
+            "the {} step of the statement fails: {}",
+            step.name, step.detail
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,216 @@
+//! Solidity storage layouts, as `solc --storage-layout` writes them (and
+//! Foundry and Hardhat keep in their build artifacts), read to find where
+//! a struct stored in a mapping keeps each of its members.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use web3::types::{H256, U256};
+
+use crate::error::{Error, Result};
+use crate::slots;
+
+#[derive(Debug, Clone, Deserialize)]
+struct Variable {
+    label: String,
+    offset: usize,
+    slot: String,
+    #[serde(rename = "type")]
+    type_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TypeInfo {
+    encoding: String,
+    label: String,
+    number_of_bytes: String,
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    members: Vec<Variable>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageLayout {
+    storage: Vec<Variable>,
+    #[serde(default)]
+    types: HashMap<String, TypeInfo>,
+}
+
+/// A member of a struct, placed relative to the struct's first slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub label: String,
+    /// The member's type, as Solidity spells it.
+    pub type_label: String,
+    pub slot: usize,
+    /// Bytes from the low-order end of the slot, as solc counts them.
+    pub offset: usize,
+    pub bytes: usize,
+}
+
+/// The slots of a struct stored in a mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructSlots {
+    /// The slot index of the mapping and the key of the entry.
+    pub mapping: U256,
+    pub key: Vec<u8>,
+    pub base: H256,
+    pub slots: usize,
+    /// The members, when a storage layout names them.
+    pub members: Vec<Member>,
+}
+
+fn parse_number(what: &str, text: &str) -> Result<usize> {
+    text.parse()
+        .map_err(|_| Error::Decode(format!("invalid {} {} in the storage layout", what, text)))
+}
+
+impl StorageLayout {
+    /// Reads a layout file: the `storageLayout` object itself, or a build
+    /// artifact holding it under that key.
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |e: serde_json::Error| {
+            Error::Config(format!(
+                "{} is not a solc storage layout: {}",
+                path.display(),
+                e
+            ))
+        };
+        let mut json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path)?).map_err(invalid)?;
+        if let Some(layout) = json.get_mut("storageLayout") {
+            json = layout.take();
+        }
+        serde_json::from_value(json).map_err(invalid)
+    }
+
+    fn type_info(&self, id: &str) -> Result<&TypeInfo> {
+        self.types
+            .get(id)
+            .ok_or_else(|| Error::Config(format!("the storage layout does not describe {}", id)))
+    }
+
+    /// The struct type a mapping declared at `index`, a variable name or
+    /// slot, holds as its value, with the mapping's slot.
+    fn mapping_struct(&self, index: &str) -> Result<(U256, &TypeInfo)> {
+        let variable = match slots::parse_slot_index(index) {
+            Ok(slot) => self.storage.iter().find(|variable| {
+                variable.offset == 0
+                    && U256::from_dec_str(&variable.slot).ok() == Some(slot)
+                    && self
+                        .types
+                        .get(&variable.type_id)
+                        .is_some_and(|info| info.encoding == "mapping")
+            }),
+            Err(_) => self.storage.iter().find(|variable| variable.label == index),
+        }
+        .ok_or_else(|| {
+            Error::Config(format!(
+                "the storage layout has no mapping declared at {}",
+                index
+            ))
+        })?;
+        let mapping = self.type_info(&variable.type_id)?;
+        let value = match (mapping.encoding.as_str(), &mapping.value) {
+            ("mapping", Some(value)) => self.type_info(value)?,
+            _ => {
+                return Err(Error::Config(format!(
+                    "{} is a {}, not a mapping",
+                    variable.label, mapping.label
+                )))
+            }
+        };
+        if value.members.is_empty() {
+            return Err(Error::Config(format!(
+                "{} maps to {}, not a struct",
+                variable.label, value.label
+            )));
+        }
+        let slot = U256::from_dec_str(&variable.slot).map_err(|_| {
+            Error::Decode(format!(
+                "invalid slot {} in the storage layout",
+                variable.slot
+            ))
+        })?;
+        Ok((slot, value))
+    }
+}
+
+impl StructSlots {
+    /// The slots of the struct stored under `key` in the mapping declared
+    /// at `index`, `--slot-struct INDEX KEY`. The layout names its members
+    /// and its size; without one, `slots` gives the size.
+    pub fn resolve(
+        index: &str,
+        key: &str,
+        slots: Option<usize>,
+        layout: Option<&Path>,
+    ) -> Result<Self> {
+        let key = slots::parse_mapping_key(key)?;
+        let Some(path) = layout else {
+            let slots = slots.ok_or_else(|| {
+                Error::Config(
+                    "--slot-struct needs --struct-slots or a --storage-layout giving the \
+                     struct's size"
+                        .to_string(),
+                )
+            })?;
+            let mapping = slots::parse_slot_index(index)?;
+            return Ok(StructSlots {
+                mapping,
+                base: slots::mapping_slot(mapping, &key),
+                key,
+                slots,
+                members: Vec::new(),
+            });
+        };
+        let layout = StorageLayout::load(path)?;
+        let (mapping_slot, info) = layout.mapping_struct(index)?;
+        let size = parse_number("size", &info.number_of_bytes)?.div_ceil(32);
+        if let Some(slots) = slots.filter(|&slots| slots != size) {
+            return Err(Error::Config(format!(
+                "--struct-slots {} disagrees with the storage layout, where {} takes {} slots",
+                slots, info.label, size
+            )));
+        }
+        let members = info
+            .members
+            .iter()
+            .map(|member| {
+                let member_type = layout.type_info(&member.type_id)?;
+                Ok(Member {
+                    label: member.label.clone(),
+                    type_label: member_type.label.clone(),
+                    slot: parse_number("slot", &member.slot)?,
+                    offset: member.offset,
+                    bytes: parse_number("size", &member_type.number_of_bytes)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(StructSlots {
+            mapping: mapping_slot,
+            base: slots::mapping_slot(mapping_slot, &key),
+            key,
+            slots: size,
+            members,
+        })
+    }
+
+    /// The struct's slots, from its first.
+    pub fn slots(&self) -> Vec<H256> {
+        let base = U256::from_big_endian(self.base.as_bytes());
+        (0..self.slots)
+            .map(|i| {
+                let mut slot = [0u8; 32];
+                base.overflowing_add(U256::from(i))
+                    .0
+                    .to_big_endian(&mut slot);
+                H256(slot)
+            })
+            .collect()
+    }
+}
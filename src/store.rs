@@ -0,0 +1,388 @@
+//! A local, content-addressed store of generated witnesses, shared by every
+//! project on the machine. Each witness is saved once under its witness
+//! hash, in `objects/<hash>.toml`, and found through a ref named by what it
+//! proves: `refs/<chain id>/<block>/<account>/<slot>/<variant>`, where the
+//! variant tells the prover parameters from the verifier ones and
+//! fingerprints the options that shape them. `gen_prove_params --store`
+//! reuses a stored witness instead of fetching it again.
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use web3::types::{H160, H256};
+
+#[cfg(feature = "cli")]
+use crate::cli::StoreFilterArgs;
+use crate::compression::{self, Compressor};
+use crate::error::{Error, Result};
+use crate::keccak::keccak256;
+use crate::migrate::WITNESS_FORMAT_VERSION;
+use crate::options::GenOptions;
+use crate::params::{Fields, Params};
+use crate::witness;
+
+/// What a stored witness proves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub chain_id: u64,
+    pub block: u64,
+    pub account: H160,
+    pub slot: Option<H256>,
+}
+
+/// One ref of the store.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub key: Key,
+    pub variant: String,
+    pub hash: String,
+    pub modified: SystemTime,
+    path: PathBuf,
+}
+
+impl Entry {
+    /// Whether the entry passes the filters of `filter`.
+    #[cfg(feature = "cli")]
+    pub fn matches(&self, filter: &StoreFilterArgs) -> bool {
+        filter.chain_id.is_none_or(|id| id == self.key.chain_id)
+            && filter.block.is_none_or(|block| block == self.key.block)
+            && filter
+                .account
+                .is_none_or(|account| account == self.key.account)
+            && filter.slot.is_none_or(|slot| Some(slot) == self.key.slot)
+            && (!filter.prover || self.variant.starts_with("prover-"))
+            && (!filter.verifier || self.variant.starts_with("verifier-"))
+    }
+}
+
+/// What [`Store::gc`] removed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcStats {
+    pub refs: usize,
+    pub objects: usize,
+    pub bytes: u64,
+}
+
+/// `$NOIR_MIP_STORE`, falling back to `~/.noir-mip/store`.
+pub fn default_dir() -> Result<PathBuf> {
+    match env::var_os("NOIR_MIP_STORE") {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+        _ => env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".noir-mip").join("store"))
+            .ok_or_else(|| {
+                Error::Unsupported(
+                    "cannot locate the witness store without HOME; set NOIR_MIP_STORE".to_string(),
+                )
+            }),
+    }
+}
+
+/// The variant of the witness `args` and `prove` generate: `prover` or
+/// `verifier`, then a fingerprint of every option that changes the emitted
+/// fields or the checks they passed. Renames, overrides, encodings and
+/// signatures are applied after the store, so they are left out.
+pub fn variant(args: &GenOptions, prove: bool) -> String {
+    let options = format!(
+        "{} {:?} {:?} {:?} {} {} {} {:?} {:?} {} {:?} {:?} {} {} {} {} {:?} {:?} {} {} {:?} {:?} {} \
+         {} {} {} {:?} {:?} {} {:?} {} {} {:?} {:?}",
+        WITNESS_FORMAT_VERSION,
+        args.root_mode,
+        args.state_root,
+        args.chain_spec,
+        args.keccak_blocks,
+        args.blob_gas_offsets,
+        args.header_parts,
+        args.header_head_bytes,
+        args.header_tail_bytes,
+        args.follow_delegation,
+        args.chain,
+        args.rollup,
+        args.rlp_hints,
+        args.keccak_preimages,
+        args.node_hashes,
+        args.split_deep_proofs,
+        args.numeric_encoding,
+        args.range,
+        args.range_limb_bits,
+        args.allow_empty,
+        args.beacon_api,
+        args.lc_checkpoint,
+        args.reorg_check,
+        args.confirmations,
+        args.wait_finalized,
+        args.quorum,
+        args.at_timestamp,
+        args.beacon_root,
+        args.only_checkpointed,
+        args.value_form,
+        args.account_fields,
+        args.empty_constants,
+        args.header_padding,
+        args.proof_order,
+    );
+    let kind = if prove { "prover" } else { "verifier" };
+    format!(
+        "{}-{}",
+        kind,
+        hex::encode(&keccak256(options.as_bytes())[..8])
+    )
+}
+
+fn slot_dir(slot: Option<H256>) -> String {
+    slot.map_or_else(|| "account".to_string(), hex::encode)
+}
+
+/// Writes `data` to `path` through a temporary file, so concurrent runs
+/// never read a partial entry.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension(format!("{}.partial", std::process::id()));
+    fs::write(&partial, data)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// The entries of directory `dir`, none when it does not exist.
+fn read_dir(dir: &Path) -> Result<Vec<fs::DirEntry>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Ok(entries.collect::<std::io::Result<_>>()?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// `fields`, read back from TOML in name order, in the order `text`
+/// writes them.
+fn in_file_order(fields: &Fields, text: &str) -> Fields {
+    let mut ordered = Fields::default();
+    for name in text
+        .lines()
+        .filter_map(|line| line.split_once(" = ").map(|(name, _)| name))
+    {
+        if let Some(value) = fields.get(name) {
+            ordered.push(name, value.clone());
+        }
+    }
+    ordered
+}
+
+fn file_name(entry: &fs::DirEntry) -> String {
+    entry.file_name().to_string_lossy().into_owned()
+}
+
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(dir: &Path) -> Self {
+        Store {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// The store at [`default_dir`].
+    pub fn open() -> Result<Self> {
+        Ok(Store::new(&default_dir()?))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.dir.join("objects").join(format!("{}.toml", hash))
+    }
+
+    fn ref_path(&self, key: &Key, variant: &str) -> PathBuf {
+        self.dir
+            .join("refs")
+            .join(key.chain_id.to_string())
+            .join(key.block.to_string())
+            .join(hex::encode(key.account))
+            .join(slot_dir(key.slot))
+            .join(variant)
+    }
+
+    /// The text of the object whose witness hash is or starts with `hash`.
+    pub fn object(&self, hash: &str) -> Result<String> {
+        let hash = hash.trim_start_matches("0x").to_lowercase();
+        let matches: Vec<String> = read_dir(&self.dir.join("objects"))?
+            .iter()
+            .map(file_name)
+            .filter_map(|name| name.strip_suffix(".toml").map(str::to_string))
+            .filter(|name| !hash.is_empty() && name.starts_with(&hash))
+            .collect();
+        match &matches[..] {
+            [name] => compression::read_to_string(&self.object_path(name)),
+            [] => Err(Error::Io(std::io::Error::new(
+                ErrorKind::NotFound,
+                format!("no stored witness has hash 0x{}", hash),
+            ))),
+            _ => Err(Error::Decode(format!(
+                "{} stored witnesses have hashes starting with 0x{}; give more digits",
+                matches.len(),
+                hash
+            ))),
+        }
+    }
+
+    /// The stored witness of `key` and `variant`, checked against its
+    /// witness hash.
+    pub fn get(&self, key: &Key, variant: &str) -> Result<Option<Params>> {
+        let hash = match fs::read_to_string(self.ref_path(key, variant)) {
+            Ok(hash) => hash.trim().to_string(),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let text = match compression::read_to_string(&self.object_path(&hash)) {
+            Ok(text) => text,
+            // A ref left behind by gc or an interrupted run.
+            Err(Error::Io(e)) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut params = witness::parse(&text)?;
+        let (fields, meta) = text.split_once("\n[meta]\n").unwrap_or((&text, ""));
+        params = Params {
+            fields: in_file_order(&params.fields, fields),
+            meta: in_file_order(&params.meta, meta),
+        };
+        if hex::encode(params.witness_hash()) != hash {
+            return Err(Error::Verification(format!(
+                "the stored witness {} does not match its hash",
+                self.object_path(&hash).display()
+            )));
+        }
+        Ok(Some(params))
+    }
+
+    /// Saves `params` as the witness of `key` and `variant`, its object
+    /// compressed with `compressor`, returning its hash.
+    pub fn put(
+        &self,
+        key: &Key,
+        variant: &str,
+        params: &Params,
+        compressor: Compressor,
+    ) -> Result<String> {
+        let hash = hex::encode(params.witness_hash());
+        let object = compressor.compress(params.to_toml().as_bytes())?;
+        write_atomic(&self.object_path(&hash), &object)?;
+        write_atomic(
+            &self.ref_path(key, variant),
+            format!("{}\n", hash).as_bytes(),
+        )?;
+        Ok(hash)
+    }
+
+    /// Every ref, ordered by chain, block, account and slot.
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for chain in read_dir(&self.dir.join("refs"))? {
+            let Ok(chain_id) = file_name(&chain).parse() else {
+                continue;
+            };
+            for block in read_dir(&chain.path())? {
+                let Ok(number) = file_name(&block).parse() else {
+                    continue;
+                };
+                for account in read_dir(&block.path())? {
+                    let Ok(address) = hex::decode(file_name(&account)) else {
+                        continue;
+                    };
+                    if address.len() != 20 {
+                        continue;
+                    }
+                    for slot in read_dir(&account.path())? {
+                        let slot_name = file_name(&slot);
+                        let slot_key = match slot_name.as_str() {
+                            "account" => None,
+                            name => match hex::decode(name) {
+                                Ok(bytes) if bytes.len() == 32 => Some(H256::from_slice(&bytes)),
+                                _ => continue,
+                            },
+                        };
+                        for variant in read_dir(&slot.path())? {
+                            let name = file_name(&variant);
+                            if name.contains(".partial") {
+                                continue;
+                            }
+                            let path = variant.path();
+                            entries.push(Entry {
+                                key: Key {
+                                    chain_id,
+                                    block: number,
+                                    account: H160::from_slice(&address),
+                                    slot: slot_key,
+                                },
+                                variant: name,
+                                hash: fs::read_to_string(&path)?.trim().to_string(),
+                                modified: variant.metadata()?.modified()?,
+                                path,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        entries.sort_by_key(|entry| {
+            (
+                entry.key.chain_id,
+                entry.key.block,
+                entry.key.account,
+                entry.key.slot,
+                entry.variant.clone(),
+            )
+        });
+        Ok(entries)
+    }
+
+    /// Removes refs older than `older_than` and refs to missing objects,
+    /// then every object no ref points to.
+    pub fn gc(&self, older_than: Option<Duration>) -> Result<GcStats> {
+        let mut stats = GcStats::default();
+        let now = SystemTime::now();
+        let mut live = std::collections::HashSet::new();
+        for entry in self.entries()? {
+            let expired = older_than.is_some_and(|age| {
+                now.duration_since(entry.modified)
+                    .is_ok_and(|elapsed| elapsed > age)
+            });
+            if expired || !self.object_path(&entry.hash).exists() {
+                fs::remove_file(&entry.path)?;
+                stats.refs += 1;
+            } else {
+                live.insert(entry.hash);
+            }
+        }
+        for object in read_dir(&self.dir.join("objects"))? {
+            let name = file_name(&object);
+            let hash = name.split('.').next().unwrap_or_default();
+            if !live.contains(hash) {
+                stats.bytes += object.metadata()?.len();
+                fs::remove_file(object.path())?;
+                stats.objects += 1;
+            }
+        }
+        // Directories left empty go too; removing a non-empty one fails.
+        let refs = self.dir.join("refs");
+        for chain in read_dir(&refs)? {
+            for block in read_dir(&chain.path())? {
+                for account in read_dir(&block.path())? {
+                    for slot in read_dir(&account.path())? {
+                        let _ = fs::remove_dir(slot.path());
+                    }
+                    let _ = fs::remove_dir(account.path());
+                }
+                let _ = fs::remove_dir(block.path());
+            }
+            let _ = fs::remove_dir(chain.path());
+        }
+        Ok(stats)
+    }
+}
@@ -0,0 +1,61 @@
+//! `--strict`: refuses a witness whose real bytes could be mistaken for
+//! padding by a circuit that finds where data ends by scanning for zeros.
+//! Proof nodes are zero-padded to `PROOF_BYTES_LEN` bytes without their
+//! lengths, so a node that fills its slot or ends in a zero byte is
+//! ambiguous unless `--rlp-hints` or `--keccak-preimages` also emits where
+//! each node ends. The node count is always given by the `*_depth` fields,
+//! and the header and the trimmed and RLP storage values by their lengths.
+
+use crate::error::{Error, Result};
+use crate::params::Params;
+use crate::PROOF_BYTES_LEN;
+
+/// The padded proofs of a witness.
+const PROOFS: &[&str] = &[
+    "account_proof",
+    "storage_proof",
+    "account_proof_tail",
+    "storage_proof_tail",
+];
+
+/// Checks the proofs of `params`, as generated and before any override,
+/// for nodes that cannot be told from their padding.
+pub fn check(params: &Params) -> Result<()> {
+    let fields = &params.fields;
+    for name in PROOFS {
+        let (Some(flat), Some(depth)) = (
+            fields.get_bytes(name),
+            fields.get_int(&format!("{}_depth", name)),
+        ) else {
+            continue;
+        };
+        let explicit = fields.get(&format!("{}_item_lengths", name)).is_some()
+            || fields.get("keccak_preimage_lengths").is_some();
+        if explicit {
+            continue;
+        }
+        for (i, slot) in flat
+            .chunks(PROOF_BYTES_LEN)
+            .take(depth as usize)
+            .enumerate()
+        {
+            let len = rlp::Rlp::new(slot)
+                .payload_info()
+                .map(|info| info.header_len + info.value_len)
+                .unwrap_or(slot.len());
+            let problem = if len >= PROOF_BYTES_LEN {
+                "fills its slot"
+            } else if slot[..len].last() == Some(&0) {
+                "ends in a zero byte"
+            } else {
+                continue;
+            };
+            return Err(Error::Unsupported(format!(
+                "--strict: node {} of {} {}, so its end cannot be told from the padding; \
+                 pass --rlp-hints or --keccak-preimages to emit the node lengths",
+                i, name, problem
+            )));
+        }
+    }
+    Ok(())
+}
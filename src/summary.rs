@@ -0,0 +1,89 @@
+//! The table a run prints to a terminal in place of the parameters: one
+//! line per field with its size, first bytes and a digest, so two runs can
+//! be compared at a glance without scrolling through their arrays.
+
+use std::fmt::Write;
+
+use openssl::sha::sha256;
+
+use crate::params::{Params, Value};
+
+/// Leading bytes of a field shown in the table.
+const HEAD_BYTES: usize = 8;
+
+/// Leading bytes of the SHA-256 of a field shown in the table.
+const DIGEST_BYTES: usize = 8;
+
+/// The bytes a value holds, its elements' concatenated for an array.
+fn content(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Bytes(bytes) | Value::Hex(bytes) => out.extend_from_slice(bytes),
+        Value::Int(value) => out.extend_from_slice(&value.to_be_bytes()),
+        Value::Str(value) => out.extend_from_slice(value.as_bytes()),
+        Value::Array(values) => {
+            for value in values {
+                content(value, out);
+            }
+        }
+    }
+}
+
+/// The first column of a field: its bytes in hex, or the value itself for
+/// integers and strings.
+fn head(value: &Value, bytes: &[u8]) -> String {
+    match value {
+        Value::Int(value) => value.to_string(),
+        Value::Str(value) => format!("{:?}", value),
+        _ => {
+            let more = if bytes.len() > HEAD_BYTES { ".." } else { "" };
+            format!(
+                "0x{}{}",
+                hex::encode(&bytes[..bytes.len().min(HEAD_BYTES)]),
+                more
+            )
+        }
+    }
+}
+
+/// The fields of `params`, one line each: name, size in bytes, first
+/// bytes and the leading bytes of the SHA-256 of the content, followed by
+/// the witness hash and the reading of the value when they are recorded.
+pub fn render(params: &Params) -> String {
+    let width = params
+        .fields
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or_default()
+        .max("field".len());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>6}  {:<22}  sha256",
+        "field", "bytes", "first bytes"
+    );
+    for (name, value) in params.fields.iter() {
+        let mut bytes = Vec::new();
+        content(value, &mut bytes);
+        // An integer is a field element whatever bytes it is held in.
+        let size = match value {
+            Value::Int(_) => "-".to_string(),
+            _ => bytes.len().to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>6}  {:<22}  {}",
+            name,
+            size,
+            head(value, &bytes),
+            hex::encode(&sha256(&bytes)[..DIGEST_BYTES])
+        );
+    }
+    if let Some(Value::Hex(hash)) = params.meta.get("witness_hash") {
+        let _ = writeln!(out, "witness_hash 0x{}", hex::encode(hash));
+    }
+    if let Some(Value::Str(reading)) = params.meta.get("value_readable") {
+        let _ = writeln!(out, "value        {}", reading);
+    }
+    out
+}
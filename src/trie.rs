@@ -0,0 +1,630 @@
+use std::collections::HashMap;
+
+use rlp::{DecoderError, Rlp, RlpStream};
+
+pub use crate::core::nodes::{
+    decode_compact_path, encode_compact_path, key_nibbles, nibbles, unpad_proof, BRANCH_NODE_ITEMS,
+    EMPTY_CODE_HASH, EMPTY_TRIE_ROOT,
+};
+pub use crate::core::proof::{verify_proof, walk_proof, Break, ProofBreak};
+use crate::keccak::keccak256;
+
+/// Structural hints for a single proof node, so the circuit can slice the
+/// node without parsing RLP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeLayout {
+    /// Number of items in the node list (17 for branches, 2 otherwise).
+    pub item_count: usize,
+    /// Offset of each item's payload within the node bytes.
+    pub item_offsets: Vec<usize>,
+    /// Length of each item's payload.
+    pub item_lengths: Vec<usize>,
+    /// Index of the item the traversal follows out of this node.
+    pub path_index: usize,
+}
+
+/// Builds the trie holding `entries` under their unhashed keys, as the
+/// transaction and receipt tries do, and returns its root with the proof of
+/// `key`: the nodes on its path, root first, leaving out nodes short enough
+/// to be embedded in their parent, as `eth_getProof` does.
+pub fn build_proof(entries: &[(Vec<u8>, Vec<u8>)], key: &[u8]) -> ([u8; 32], Vec<Vec<u8>>) {
+    if entries.is_empty() {
+        return (EMPTY_TRIE_ROOT, Vec::new());
+    }
+    let mut items: Vec<(Vec<u8>, &[u8])> = entries
+        .iter()
+        .map(|(key, value)| (nibbles(key), value.as_slice()))
+        .collect();
+    items.sort();
+    let target = nibbles(key);
+    let mut proof = Vec::new();
+    let root = encode_node(&items, 0, Some(&target), &mut proof);
+    if root.len() < 32 {
+        // The root is referenced by its hash however short it is.
+        proof.push(root.clone());
+    }
+    proof.reverse();
+    (keccak256(&root), proof)
+}
+
+/// Encodes the node over `items`, sorted by key, whose keys share their
+/// first `depth` nibbles. While `target` is set the node lies on its path,
+/// and the node and its descendants on the path are pushed onto `proof`,
+/// deepest first.
+fn encode_node(
+    items: &[(Vec<u8>, &[u8])],
+    depth: usize,
+    target: Option<&[u8]>,
+    proof: &mut Vec<Vec<u8>>,
+) -> Vec<u8> {
+    let mut stream = RlpStream::new();
+    if let [(key, value)] = items {
+        stream
+            .begin_list(2)
+            .append(&encode_compact_path(&key[depth..], true))
+            .append(value);
+    } else {
+        let first = &items[0].0;
+        let last = &items[items.len() - 1].0;
+        let shared = first[depth..]
+            .iter()
+            .zip(&last[depth..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if shared > 0 {
+            let end = depth + shared;
+            let target = target.filter(|target| target.get(depth..end) == Some(&first[depth..end]));
+            let child = encode_node(items, end, target, proof);
+            stream
+                .begin_list(2)
+                .append(&encode_compact_path(&first[depth..end], false));
+            append_reference(&mut stream, &child);
+        } else {
+            stream.begin_list(BRANCH_NODE_ITEMS);
+            let mut value: &[u8] = &[];
+            let mut rest = items;
+            if rest[0].0.len() == depth {
+                value = rest[0].1;
+                rest = &rest[1..];
+            }
+            for nibble in 0..16u8 {
+                let count = rest
+                    .iter()
+                    .take_while(|(key, _)| key[depth] == nibble)
+                    .count();
+                let (children, remaining) = rest.split_at(count);
+                rest = remaining;
+                if children.is_empty() {
+                    stream.append_empty_data();
+                    continue;
+                }
+                let target = target.filter(|target| target.get(depth) == Some(&nibble));
+                let child = encode_node(children, depth + 1, target, proof);
+                append_reference(&mut stream, &child);
+            }
+            stream.append(&value);
+        }
+    }
+    let encoded = stream.out().to_vec();
+    if target.is_some() && encoded.len() >= 32 {
+        proof.push(encoded.clone());
+    }
+    encoded
+}
+
+/// Appends the reference a parent holds to `child`: its hash, or the node
+/// itself when it is shorter than a hash.
+fn append_reference(stream: &mut RlpStream, child: &[u8]) {
+    if child.len() < 32 {
+        stream.append_raw(child, 1);
+    } else {
+        stream.append(&keccak256(child).as_slice());
+    }
+}
+
+/// Trie nodes by their hash, gathered from proofs and grown by [`update`].
+pub type NodeStore = HashMap<[u8; 32], Vec<u8>>;
+
+/// Adds the nodes of `proof` to `store`.
+pub fn store_proof(store: &mut NodeStore, proof: &[Vec<u8>]) {
+    for node in proof {
+        store.insert(keccak256(node), node.clone());
+    }
+}
+
+/// A decoded node being rewritten. Children are kept as the raw RLP item
+/// their parent holds: an empty string, a hash or an embedded node.
+enum Node {
+    Branch(Vec<Vec<u8>>, Vec<u8>),
+    Leaf(Vec<u8>, Vec<u8>),
+    Extension(Vec<u8>, Vec<u8>),
+}
+
+/// Reference a branch holds in place of a missing child.
+const EMPTY_REFERENCE: [u8; 1] = [0x80];
+
+const MISSING_NODE: &str = "the proofs do not cover a trie node the update touches";
+
+fn decode_node(node: &[u8]) -> Result<Node, DecoderError> {
+    let rlp = Rlp::new(node);
+    match rlp.item_count()? {
+        BRANCH_NODE_ITEMS => Ok(Node::Branch(
+            (0..16)
+                .map(|i| Ok(rlp.at(i)?.as_raw().to_vec()))
+                .collect::<Result<_, DecoderError>>()?,
+            rlp.at(16)?.data()?.to_vec(),
+        )),
+        2 => {
+            let (path, is_leaf) = decode_compact_path(rlp.at(0)?.data()?)?;
+            let item = rlp.at(1)?;
+            Ok(if is_leaf {
+                Node::Leaf(path, item.data()?.to_vec())
+            } else {
+                Node::Extension(path, item.as_raw().to_vec())
+            })
+        }
+        _ => Err(DecoderError::Custom("unexpected trie node item count")),
+    }
+}
+
+fn encode_rewritten(node: &Node) -> Vec<u8> {
+    let mut stream = RlpStream::new();
+    match node {
+        Node::Branch(children, value) => {
+            stream.begin_list(BRANCH_NODE_ITEMS);
+            for child in children {
+                stream.append_raw(child, 1);
+            }
+            stream.append(value);
+        }
+        Node::Leaf(path, value) => {
+            stream
+                .begin_list(2)
+                .append(&encode_compact_path(path, true))
+                .append(value);
+        }
+        Node::Extension(path, child) => {
+            stream
+                .begin_list(2)
+                .append(&encode_compact_path(path, false))
+                .append_raw(child, 1);
+        }
+    }
+    stream.out().to_vec()
+}
+
+/// The node `reference` points to, `None` for an empty child.
+fn resolve(reference: &[u8], store: &NodeStore) -> Result<Option<Node>, DecoderError> {
+    let rlp = Rlp::new(reference);
+    if rlp.is_list() {
+        return decode_node(reference).map(Some);
+    }
+    let hash = rlp.data()?;
+    if hash.is_empty() {
+        return Ok(None);
+    }
+    let node = <[u8; 32]>::try_from(hash)
+        .ok()
+        .and_then(|hash| store.get(&hash))
+        .ok_or(DecoderError::Custom(MISSING_NODE))?;
+    decode_node(node).map(Some)
+}
+
+/// Stores `node` and returns the reference its parent holds to it.
+fn reference(node: &Node, store: &mut NodeStore) -> Vec<u8> {
+    let encoded = encode_rewritten(node);
+    if encoded.len() < 32 {
+        return encoded;
+    }
+    let hash = keccak256(&encoded);
+    store.insert(hash, encoded);
+    rlp::encode(&hash.as_slice()).to_vec()
+}
+
+/// Places a leaf for `path` under a branch being built.
+fn branch_insert(
+    children: &mut [Vec<u8>],
+    branch_value: &mut Vec<u8>,
+    path: &[u8],
+    value: &[u8],
+    store: &mut NodeStore,
+) {
+    match path.split_first() {
+        None => *branch_value = value.to_vec(),
+        Some((&nibble, rest)) => {
+            children[nibble as usize] = reference(&Node::Leaf(rest.to_vec(), value.to_vec()), store)
+        }
+    }
+}
+
+/// Puts `branch` behind the `shared` nibbles its entries have in common.
+fn behind(shared: &[u8], branch: Node, store: &mut NodeStore) -> Node {
+    if shared.is_empty() {
+        branch
+    } else {
+        Node::Extension(shared.to_vec(), reference(&branch, store))
+    }
+}
+
+/// Prepends `prefix` to the path of `node`, which replaces a child that
+/// `prefix` led to.
+fn prefixed(mut prefix: Vec<u8>, node: Node, store: &mut NodeStore) -> Node {
+    match node {
+        Node::Leaf(path, value) => {
+            prefix.extend(path);
+            Node::Leaf(prefix, value)
+        }
+        Node::Extension(path, child) => {
+            prefix.extend(path);
+            Node::Extension(prefix, child)
+        }
+        branch => Node::Extension(prefix, reference(&branch, store)),
+    }
+}
+
+/// Rewrites the subtrie under `node` with `path` set to `value`, or removed
+/// when `value` is `None`.
+fn put(
+    node: Option<Node>,
+    path: &[u8],
+    value: Option<&[u8]>,
+    store: &mut NodeStore,
+) -> Result<Option<Node>, DecoderError> {
+    let Some(node) = node else {
+        return Ok(value.map(|value| Node::Leaf(path.to_vec(), value.to_vec())));
+    };
+    let shared = |other: &[u8]| other.iter().zip(path).take_while(|(a, b)| a == b).count();
+    match node {
+        Node::Leaf(leaf_path, leaf_value) => {
+            if leaf_path == path {
+                return Ok(value.map(|value| Node::Leaf(leaf_path, value.to_vec())));
+            }
+            let Some(value) = value else {
+                return Ok(Some(Node::Leaf(leaf_path, leaf_value)));
+            };
+            let shared = shared(&leaf_path);
+            let mut children = vec![EMPTY_REFERENCE.to_vec(); 16];
+            let mut branch_value = Vec::new();
+            branch_insert(
+                &mut children,
+                &mut branch_value,
+                &leaf_path[shared..],
+                &leaf_value,
+                store,
+            );
+            branch_insert(
+                &mut children,
+                &mut branch_value,
+                &path[shared..],
+                value,
+                store,
+            );
+            let branch = Node::Branch(children, branch_value);
+            Ok(Some(behind(&path[..shared], branch, store)))
+        }
+        Node::Extension(extension_path, child) => {
+            if path.starts_with(&extension_path) {
+                let child_node = resolve(&child, store)?;
+                let rest = &path[extension_path.len()..];
+                return Ok(put(child_node, rest, value, store)?
+                    .map(|node| prefixed(extension_path, node, store)));
+            }
+            let Some(value) = value else {
+                return Ok(Some(Node::Extension(extension_path, child)));
+            };
+            let shared = shared(&extension_path);
+            let mut children = vec![EMPTY_REFERENCE.to_vec(); 16];
+            let mut branch_value = Vec::new();
+            let rest = &extension_path[shared + 1..];
+            children[extension_path[shared] as usize] = if rest.is_empty() {
+                child
+            } else {
+                reference(&Node::Extension(rest.to_vec(), child), store)
+            };
+            branch_insert(
+                &mut children,
+                &mut branch_value,
+                &path[shared..],
+                value,
+                store,
+            );
+            let branch = Node::Branch(children, branch_value);
+            Ok(Some(behind(&path[..shared], branch, store)))
+        }
+        Node::Branch(mut children, mut branch_value) => {
+            match path.split_first() {
+                None => branch_value = value.map(<[u8]>::to_vec).unwrap_or_default(),
+                Some((&nibble, rest)) => {
+                    let child = resolve(&children[nibble as usize], store)?;
+                    children[nibble as usize] = match put(child, rest, value, store)? {
+                        Some(node) => reference(&node, store),
+                        None => EMPTY_REFERENCE.to_vec(),
+                    };
+                }
+            }
+            collapse(children, branch_value, store)
+        }
+    }
+}
+
+/// Folds a branch left with a single entry by a removal into its parent's
+/// shape: a leaf, or an extension to the remaining child.
+fn collapse(
+    children: Vec<Vec<u8>>,
+    branch_value: Vec<u8>,
+    store: &mut NodeStore,
+) -> Result<Option<Node>, DecoderError> {
+    let occupied: Vec<usize> = (0..16)
+        .filter(|&i| children[i] != EMPTY_REFERENCE)
+        .collect();
+    match (occupied.as_slice(), branch_value.is_empty()) {
+        ([], true) => Ok(None),
+        ([], false) => Ok(Some(Node::Leaf(Vec::new(), branch_value))),
+        (&[nibble], true) => {
+            let child =
+                resolve(&children[nibble], store)?.ok_or(DecoderError::Custom(MISSING_NODE))?;
+            Ok(Some(prefixed(vec![nibble as u8], child, store)))
+        }
+        _ => Ok(Some(Node::Branch(children, branch_value))),
+    }
+}
+
+/// Sets the value at `nibbles` in the trie under `root`, or removes it when
+/// `value` is `None`, and returns the new root. Every node the change
+/// touches must be in `store`: the proof of the key, plus the sibling a
+/// removal folds into its parent. The rewritten nodes are added to `store`.
+pub fn update(
+    root: [u8; 32],
+    nibbles: &[u8],
+    value: Option<&[u8]>,
+    store: &mut NodeStore,
+) -> Result<[u8; 32], DecoderError> {
+    let node = if root == EMPTY_TRIE_ROOT {
+        None
+    } else {
+        let node = store.get(&root).ok_or(DecoderError::Custom(MISSING_NODE))?;
+        Some(decode_node(node)?)
+    };
+    match put(node, nibbles, value, store)? {
+        None => Ok(EMPTY_TRIE_ROOT),
+        Some(node) => {
+            let encoded = encode_rewritten(&node);
+            let hash = keccak256(&encoded);
+            store.insert(hash, encoded);
+            Ok(hash)
+        }
+    }
+}
+
+/// Proof of `nibbles` in the trie under `root`, read from the nodes in
+/// `store`; it proves absence when the key is not in the trie.
+pub fn collect_proof(
+    root: [u8; 32],
+    nibbles: &[u8],
+    store: &NodeStore,
+) -> Result<Vec<Vec<u8>>, DecoderError> {
+    let mut proof = Vec::new();
+    if root == EMPTY_TRIE_ROOT {
+        return Ok(proof);
+    }
+    let mut reference = rlp::encode(&root.as_slice()).to_vec();
+    let mut position = 0;
+    loop {
+        let rlp = Rlp::new(&reference);
+        let node = if rlp.is_list() {
+            reference.clone()
+        } else {
+            let hash = rlp.data()?;
+            if hash.is_empty() {
+                return Ok(proof);
+            }
+            let node = <[u8; 32]>::try_from(hash)
+                .ok()
+                .and_then(|hash| store.get(&hash))
+                .ok_or(DecoderError::Custom(MISSING_NODE))?;
+            proof.push(node.clone());
+            node.clone()
+        };
+        reference = match decode_node(&node)? {
+            Node::Branch(children, _) => {
+                let Some(&nibble) = nibbles.get(position) else {
+                    return Ok(proof);
+                };
+                position += 1;
+                children[nibble as usize].clone()
+            }
+            Node::Extension(path, child) if nibbles[position..].starts_with(&path) => {
+                position += path.len();
+                child
+            }
+            _ => return Ok(proof),
+        };
+    }
+}
+
+/// Number of key nibbles the traversal consumes passing through `nodes`.
+pub fn consumed_nibbles(nodes: &[Vec<u8>]) -> Result<usize, DecoderError> {
+    nodes.iter().try_fold(0, |position, node| {
+        let rlp = Rlp::new(node);
+        match rlp.item_count()? {
+            BRANCH_NODE_ITEMS => Ok(position + 1),
+            2 => Ok(position + decode_compact_path(rlp.at(0)?.data()?)?.0.len()),
+            _ => Err(DecoderError::Custom("unexpected trie node item count")),
+        }
+    })
+}
+
+/// Computes the layout of every node in `proof`, following `nibbles` from
+/// the root node down.
+pub fn proof_layouts(proof: &[Vec<u8>], nibbles: &[u8]) -> Result<Vec<NodeLayout>, DecoderError> {
+    let mut layouts = Vec::with_capacity(proof.len());
+    let mut position = 0;
+
+    for node in proof {
+        let rlp = Rlp::new(node);
+        let item_count = rlp.item_count()?;
+        let mut item_offsets = Vec::with_capacity(item_count);
+        let mut item_lengths = Vec::with_capacity(item_count);
+
+        for i in 0..item_count {
+            let (item, offset) = rlp.at_with_offset(i)?;
+            let info = item.payload_info()?;
+            item_offsets.push(offset + info.header_len);
+            item_lengths.push(info.value_len);
+        }
+
+        let path_index = match item_count {
+            BRANCH_NODE_ITEMS => {
+                let index = nibbles.get(position).copied().unwrap_or(16) as usize;
+                position += 1;
+                index
+            }
+            2 => {
+                let (path, _) = decode_compact_path(rlp.at(0)?.data()?)?;
+                position += path.len();
+                1
+            }
+            _ => return Err(DecoderError::Custom("unexpected trie node item count")),
+        };
+
+        layouts.push(NodeLayout {
+            item_count,
+            item_offsets,
+            item_lengths,
+            path_index,
+        });
+    }
+
+    Ok(layouts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+
+    /// A store holding every node of the trie over `entries`.
+    fn full_store(entries: &BTreeMap<Vec<u8>, Vec<u8>>) -> ([u8; 32], NodeStore) {
+        let entries: Vec<_> = entries.clone().into_iter().collect();
+        let mut store = NodeStore::new();
+        let mut root = EMPTY_TRIE_ROOT;
+        for (key, _) in &entries {
+            let (built, proof) = build_proof(&entries, key);
+            store_proof(&mut store, &proof);
+            root = built;
+        }
+        (root, store)
+    }
+
+    fn rebuilt_root(entries: &BTreeMap<Vec<u8>, Vec<u8>>) -> [u8; 32] {
+        let entries: Vec<_> = entries.clone().into_iter().collect();
+        build_proof(&entries, &[]).0
+    }
+
+    /// Keys from a few bytes, so that they share prefixes and some are
+    /// prefixes of others, and values both shorter and longer than a hash.
+    fn key() -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(
+            prop::sample::select(vec![0x00u8, 0x01, 0x10, 0x11, 0xff]),
+            1..4,
+        )
+    }
+
+    fn value() -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(any::<u8>(), 1..40)
+    }
+
+    proptest! {
+        #[test]
+        fn update_matches_a_trie_built_from_scratch(
+            initial in prop::collection::btree_map(key(), value(), 0..12),
+            changes in prop::collection::vec((key(), prop::option::of(value())), 1..12),
+        ) {
+            let (mut root, mut store) = full_store(&initial);
+            let mut entries = initial;
+            for (key, value) in changes {
+                root = update(root, &nibbles(&key), value.as_deref(), &mut store).unwrap();
+                match value {
+                    Some(value) => entries.insert(key, value),
+                    None => entries.remove(&key),
+                };
+                prop_assert_eq!(root, rebuilt_root(&entries));
+            }
+            // Deleting what is left empties the trie.
+            for key in entries.keys() {
+                root = update(root, &nibbles(key), None, &mut store).unwrap();
+            }
+            prop_assert_eq!(root, EMPTY_TRIE_ROOT);
+        }
+
+        #[test]
+        fn collect_proof_reads_back_the_updated_trie(
+            entries in prop::collection::btree_map(key(), value(), 1..12),
+            probe in key(),
+        ) {
+            let mut store = NodeStore::new();
+            let mut root = EMPTY_TRIE_ROOT;
+            for (key, value) in &entries {
+                root = update(root, &nibbles(key), Some(value), &mut store).unwrap();
+            }
+            let proof = collect_proof(root, &nibbles(&probe), &store).unwrap();
+            let all: Vec<_> = entries.clone().into_iter().collect();
+            prop_assert_eq!(&proof, &build_proof(&all, &probe).1);
+        }
+    }
+
+    /// Keys 0x13 and 0x14 under one branch and 0x50 beside it, with values
+    /// long enough for every node to be referenced by its hash.
+    fn branch_of_branches() -> BTreeMap<Vec<u8>, Vec<u8>> {
+        [(0x13, 0xaa), (0x14, 0xbb), (0x50, 0xcc)]
+            .into_iter()
+            .map(|(key, byte)| (vec![key], vec![byte; 40]))
+            .collect()
+    }
+
+    #[test]
+    fn a_branch_left_with_one_child_folds_into_an_extension() {
+        let mut entries = branch_of_branches();
+        let (root, mut store) = full_store(&entries);
+        let root = update(root, &nibbles(&[0x50]), None, &mut store).unwrap();
+        entries.remove(&vec![0x50]);
+        assert_eq!(root, rebuilt_root(&entries));
+        // The root is now an extension over nibble 1 to the branch of 3
+        // and 4.
+        match decode_node(&store[&root]).unwrap() {
+            Node::Extension(path, _) => assert_eq!(path, vec![1]),
+            _ => panic!("the root is not an extension"),
+        }
+
+        // Removing 0x14 in turn leaves a single leaf holding the whole key.
+        let root = update(root, &nibbles(&[0x14]), None, &mut store).unwrap();
+        match decode_node(&store[&root]).unwrap() {
+            Node::Leaf(path, value) => {
+                assert_eq!(path, vec![1, 3]);
+                assert_eq!(value, vec![0xaa; 40]);
+            }
+            _ => panic!("the root is not a leaf"),
+        }
+    }
+
+    #[test]
+    fn a_removal_that_folds_an_unproven_sibling_fails() {
+        let entries: Vec<_> = branch_of_branches().into_iter().collect();
+        // The proof of 0x50 does not hold the branch under nibble 1, which
+        // removing 0x50 folds into the root.
+        let (root, proof) = build_proof(&entries, &[0x50]);
+        let mut store = NodeStore::new();
+        store_proof(&mut store, &proof);
+        assert_eq!(
+            update(root, &nibbles(&[0x50]), None, &mut store),
+            Err(DecoderError::Custom(MISSING_NODE))
+        );
+        // Setting it again needs no sibling.
+        assert!(update(root, &nibbles(&[0x50]), Some(&[1; 40]), &mut store).is_ok());
+        // Nor does a key that is not in the trie, whose path ends at the
+        // proof's last node.
+        assert!(update(root, &nibbles(&[0x57]), None, &mut store).is_ok());
+        assert!(update(root, &nibbles(&[0x13]), None, &mut store).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::fs;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use rlp::Rlp;
+use web3::types::H256;
+
+use noir_mip::cli::TuiArgs;
+use noir_mip::error::{Error, Result};
+use noir_mip::params::Params;
+use noir_mip::proof_order;
+use noir_mip::trie::{self, NodeLayout};
+use noir_mip::PROOF_BYTES_LEN;
+
+/// One proof as shown in the node list.
+struct Proof {
+    name: &'static str,
+    nodes: Vec<Vec<u8>>,
+    layouts: Vec<NodeLayout>,
+}
+
+enum Input {
+    Block(String),
+    Slot(String),
+}
+
+struct App {
+    args: TuiArgs,
+    params: Params,
+    proofs: Vec<Proof>,
+    proof: usize,
+    nodes: ListState,
+    input: Option<Input>,
+    status: String,
+}
+
+/// Splits a padded, flattened proof back into its nodes.
+fn proof_nodes(params: &Params, name: &str) -> Vec<Vec<u8>> {
+    let (Some(flat), Some(depth)) = (
+        params.fields.get_bytes(name),
+        params.fields.get_int(&format!("{}_depth", name)),
+    ) else {
+        return Vec::new();
+    };
+    proof_order::recorded(params).root_first(trie::unpad_proof(
+        flat,
+        PROOF_BYTES_LEN,
+        depth as usize,
+    ))
+}
+
+fn node_kind(node: &[u8]) -> &'static str {
+    let rlp = Rlp::new(node);
+    match rlp.item_count() {
+        Ok(trie::BRANCH_NODE_ITEMS) => "branch",
+        Ok(2) => match rlp
+            .at(0)
+            .and_then(|path| trie::decode_compact_path(path.data()?))
+        {
+            Ok((_, true)) => "leaf",
+            Ok((_, false)) => "extension",
+            Err(_) => "invalid",
+        },
+        _ => "invalid",
+    }
+}
+
+impl App {
+    async fn load(args: TuiArgs) -> Result<Self> {
+        let params = noir_mip::generate_with_quorum(&args.gen.options, true).await?;
+        let mut proofs = Vec::new();
+        let account_nodes = proof_nodes(&params, "account_proof");
+        proofs.push(Proof {
+            name: "account_proof",
+            layouts: trie::proof_layouts(
+                &account_nodes,
+                &trie::key_nibbles(args.gen.options.account.as_bytes()),
+            )?,
+            nodes: account_nodes,
+        });
+        if let Some(slot) = args.gen.options.slot {
+            let storage_nodes = proof_nodes(&params, "storage_proof");
+            proofs.push(Proof {
+                name: "storage_proof",
+                layouts: trie::proof_layouts(&storage_nodes, &trie::key_nibbles(slot.as_bytes()))?,
+                nodes: storage_nodes,
+            });
+        }
+        let mut nodes = ListState::default();
+        nodes.select(Some(0));
+        Ok(App {
+            args,
+            params,
+            proofs,
+            proof: 0,
+            nodes,
+            input: None,
+            status: String::new(),
+        })
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, main, bottom] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Length(32), Constraint::Min(0)]).areas(main);
+
+        let block_hash = self
+            .params
+            .fields
+            .get_bytes("block_hash")
+            .map(H256::from_slice)
+            .unwrap_or_default();
+        let slot = self
+            .args
+            .gen
+            .options
+            .slot
+            .map_or_else(|| "none".to_string(), |slot| format!("{:?}", slot));
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(format!(
+                    "block {} {:?}",
+                    self.args.gen.options.block, block_hash
+                )),
+                Line::from(format!(
+                    "account {:?}  slot {}",
+                    self.args.gen.options.account, slot
+                )),
+            ])
+            .block(Block::bordered().title("noir-mip")),
+            top,
+        );
+
+        let proof = &self.proofs[self.proof];
+        let items: Vec<ListItem> = proof
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| ListItem::new(format!("#{} {} {}B", i, node_kind(node), node.len())))
+            .collect();
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(proof.name))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            left,
+            &mut self.nodes,
+        );
+
+        let mut lines = Vec::new();
+        if let Some(i) = self.nodes.selected() {
+            if let (Some(node), Some(layout)) = (proof.nodes.get(i), proof.layouts.get(i)) {
+                lines.push(Line::from(format!(
+                    "keccak 0x{}",
+                    hex::encode(noir_mip::keccak::keccak256(node))
+                )));
+                for item in 0..layout.item_count {
+                    let offset = layout.item_offsets[item];
+                    let length = layout.item_lengths[item];
+                    let marker = if item == layout.path_index { ">" } else { " " };
+                    lines.push(Line::from(format!(
+                        "{} [{:>2}] @{:<4} {:>3}B 0x{}",
+                        marker,
+                        item,
+                        offset,
+                        length,
+                        hex::encode(&node[offset..offset + length])
+                    )));
+                }
+            }
+        }
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title("RLP items (> = followed)"))
+                .wrap(Wrap { trim: false }),
+            right,
+        );
+
+        let help = match &self.input {
+            Some(Input::Block(text)) => format!("block number: {}_", text),
+            Some(Input::Slot(text)) => format!("slot (hex): {}_", text),
+            None if !self.status.is_empty() => self.status.clone(),
+            None => "up/down node  tab proof  b block  s slot  e export  q quit".to_string(),
+        };
+        frame.render_widget(Paragraph::new(help), bottom);
+    }
+
+    /// Refetches with `args`, keeping the current view if that fails.
+    async fn reload(&mut self, args: TuiArgs) {
+        match App::load(args).await {
+            Ok(app) => *self = app,
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn export(&mut self) {
+        self.status = match fs::write(&self.args.export, self.params.to_toml()) {
+            Ok(()) => format!("wrote {}", self.args.export.display()),
+            Err(e) => format!("export failed: {}", e),
+        };
+    }
+
+    /// Handles a key press, returning false once the user quits.
+    async fn key(&mut self, code: KeyCode) -> bool {
+        if let Some(input) = &mut self.input {
+            let text = match input {
+                Input::Block(text) | Input::Slot(text) => text,
+            };
+            match code {
+                KeyCode::Char(c) if c.is_ascii_hexdigit() || c == 'x' => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Enter => {
+                    let mut args = self.args.clone();
+                    match self.input.take() {
+                        Some(Input::Block(text)) => match text.parse() {
+                            Ok(block) => args.gen.options.block = block,
+                            Err(e) => {
+                                self.status = format!("invalid block: {}", e);
+                                return true;
+                            }
+                        },
+                        Some(Input::Slot(text)) => {
+                            match hex::decode(format!("{:0>64}", text.trim_start_matches("0x"))) {
+                                Ok(bytes) if bytes.len() == 32 => {
+                                    args.gen.options.slot = Some(H256::from_slice(&bytes))
+                                }
+                                _ => {
+                                    self.status = format!("invalid slot: {}", text);
+                                    return true;
+                                }
+                            }
+                        }
+                        None => {}
+                    }
+                    self.reload(args).await;
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        self.status.clear();
+        let len = self.proofs[self.proof].nodes.len();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down if len > 0 => {
+                let next = self.nodes.selected().map_or(0, |i| (i + 1).min(len - 1));
+                self.nodes.select(Some(next));
+            }
+            KeyCode::Up => {
+                let previous = self.nodes.selected().map_or(0, |i| i.saturating_sub(1));
+                self.nodes.select(Some(previous));
+            }
+            KeyCode::Tab => {
+                self.proof = (self.proof + 1) % self.proofs.len();
+                self.nodes.select(Some(0));
+            }
+            KeyCode::Char('b') => self.input = Some(Input::Block(String::new())),
+            KeyCode::Char('s') => self.input = Some(Input::Slot(String::new())),
+            KeyCode::Char('e') => self.export(),
+            _ => {}
+        }
+        true
+    }
+}
+
+async fn event_loop(terminal: &mut DefaultTerminal, app: &mut App) -> Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.key(key.code).await {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Fetches the proofs for `args` and opens the browser on them.
+pub async fn run(args: TuiArgs) -> Result<()> {
+    let mut app = App::load(args).await?;
+    let mut terminal = ratatui::try_init().map_err(Error::Io)?;
+    let result = event_loop(&mut terminal, &mut app).await;
+    ratatui::restore();
+    result
+}
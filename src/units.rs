@@ -0,0 +1,98 @@
+//! A human-readable reading of the proven value for `--token-decimals` and
+//! `--as`, recorded in `[meta]` and shown in the run summaries so an
+//! operator can sanity-check its magnitude: a token balance of `1234567891`
+//! with 6 decimals reads `1234.567891`.
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+use rlp::Rlp;
+use web3::types::U256;
+
+use crate::encoding;
+use crate::options::GenOptions;
+use crate::params::{Params, Value};
+
+/// Units of ether `--as` reads a value in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum Unit {
+    Wei,
+    Gwei,
+    Eth,
+}
+
+impl Unit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Unit::Wei => "wei",
+            Unit::Gwei => "gwei",
+            Unit::Eth => "eth",
+        }
+    }
+
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Unit::Wei => 0,
+            Unit::Gwei => 9,
+            Unit::Eth => 18,
+        }
+    }
+}
+
+/// `value` with its last `decimals` digits after the point, without
+/// trailing zeros.
+pub fn format(value: U256, decimals: u32) -> String {
+    let digits = value.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let digits = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    match fraction.trim_end_matches('0') {
+        "" => whole.to_string(),
+        fraction => format!("{}.{}", whole, fraction),
+    }
+}
+
+/// The value a witness proves: the storage value, or the account's balance
+/// when it has no storage proof.
+pub fn proven_value(params: &Params) -> Option<U256> {
+    if params.fields.get("storage_value").is_some() {
+        return encoding::storage_value(params);
+    }
+    Rlp::new(params.fields.get_bytes("account_value")?)
+        .val_at(1)
+        .ok()
+}
+
+/// `value` read with the decimals and unit recorded in the metadata of
+/// `params`, if any.
+pub fn readable(params: &Params, value: U256) -> Option<String> {
+    let decimals = params.meta.get_int("value_decimals")?;
+    let number = format(value, decimals as u32);
+    Some(match params.meta.get("value_unit") {
+        Some(Value::Str(unit)) => format!("{} {}", number, unit),
+        _ => number,
+    })
+}
+
+/// Records the decimals, unit and reading of the proven value of `params`
+/// for `--token-decimals` or `--as`.
+pub fn record(args: &GenOptions, params: &mut Params) {
+    let (decimals, unit) = match (args.token_decimals, args.as_unit) {
+        (_, Some(unit)) => (unit.decimals(), Some(unit)),
+        (Some(decimals), None) => (decimals, None),
+        (None, None) => return,
+    };
+    let Some(value) = proven_value(params) else {
+        return;
+    };
+    params.meta.push("value_decimals", decimals as usize);
+    if let Some(unit) = unit {
+        params.meta.push("value_unit", unit.name());
+    }
+    if let Some(reading) = readable(params, value) {
+        params.meta.push("value_readable", reading);
+    }
+}
@@ -0,0 +1,539 @@
+#[cfg(feature = "formats-json")]
+use ethereum_types::U256;
+#[cfg(feature = "formats-json")]
+use rlp::Rlp;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "formats-json")]
+use serde_json::json;
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::container;
+use crate::encoding::NumericEncoding;
+use crate::error::{Error, Result};
+use crate::namespace;
+use crate::params::{hex_bytes, Fields, Params, Value};
+#[cfg(feature = "formats-json")]
+use crate::{proof_order, trie, PROOF_BYTES_LEN};
+
+/// How `gen_prove_params` and `gen_verify_params` write the parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum OutputFormat {
+    /// Prover.toml or Verifier.toml
+    #[default]
+    Toml,
+    /// A Noir `#[test]` function calling `main` with the parameters inlined
+    NoirTest,
+    /// The witness as JSON, split into header, account and storage sections
+    Json,
+    /// The normalized eth_getProof response the witness was built from
+    Eip1186,
+    /// The self-describing binary container, with the fields raw
+    #[cfg_attr(feature = "clap", value(alias = "bin"))]
+    Binary,
+    /// A Circom `input.json`: camelCased signals as decimal strings
+    CircomJson,
+    /// One flat JSON map of the fields under their own names, for Halo2 and
+    /// other frameworks
+    KvJson,
+}
+
+impl OutputFormat {
+    /// The extension of a file in this format written beside `--out`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Toml => "toml",
+            OutputFormat::NoirTest => "nr",
+            OutputFormat::Json => "json",
+            OutputFormat::Eip1186 => "eip1186.json",
+            OutputFormat::Binary => "bin",
+            OutputFormat::CircomJson => "circom.json",
+            OutputFormat::KvJson => "kv.json",
+        }
+    }
+}
+
+/// How [`render`] lays out a witness beside its format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// The block, which names the function of a Noir test.
+    pub block: u64,
+    /// Group the fields into tables, for the TOML and JSON formats.
+    pub namespaced: bool,
+    /// The numeric encoding the witness was generated with, which the
+    /// eip1186 format reads its keys and values back from.
+    pub numeric_encoding: NumericEncoding,
+}
+
+/// `params` in `format`. The JSON formats need the `formats-json`
+/// feature.
+pub fn render(params: &Params, format: OutputFormat, options: &RenderOptions) -> Result<Vec<u8>> {
+    let text = match (format, options.namespaced) {
+        (OutputFormat::Toml, false) => params.to_toml(),
+        (OutputFormat::Toml, true) => namespace::split(params)?.to_toml(),
+        (OutputFormat::NoirTest, _) => {
+            params.to_noir_test(&format!("test_block_{}", options.block))
+        }
+        (OutputFormat::Binary, _) => return Ok(container::write(params)),
+        #[cfg(feature = "formats-json")]
+        (OutputFormat::Json, false) => to_json(params)?,
+        #[cfg(feature = "formats-json")]
+        (OutputFormat::Json, true) => namespace::split(params)?.to_json()?,
+        #[cfg(feature = "formats-json")]
+        (OutputFormat::Eip1186, _) => to_eip1186(params, options.numeric_encoding)?,
+        #[cfg(feature = "formats-json")]
+        (OutputFormat::CircomJson, _) => crate::interop::to_circom_json(params)?,
+        #[cfg(feature = "formats-json")]
+        (OutputFormat::KvJson, _) => crate::interop::to_kv_json(params)?,
+        #[cfg(not(feature = "formats-json"))]
+        (format, _) => {
+            return Err(Error::Unsupported(format!(
+                "the {} format needs the formats-json feature",
+                format.extension()
+            )))
+        }
+    };
+    Ok(text.into_bytes())
+}
+
+/// Fields of a prover witness the sections model, in the order the
+/// generator emits them.
+pub(crate) const PROVER_LAYOUT: &[&str] = &[
+    "block_hash",
+    "state_root",
+    "account_key",
+    "account_value",
+    "storage_key",
+    "storage_value",
+    "block_header_rlp",
+    "block_header_rlp_head_len",
+    "block_header_rlp_tail_len",
+    "block_header_rlp_head",
+    "block_header_rlp_tail",
+    "storage_root",
+    "account_proof",
+    "storage_proof",
+    "account_proof_depth",
+    "account_exists",
+    "storage_proof_depth",
+    "is_zero_value",
+];
+
+/// The same for a verifier witness, which has no proofs or header.
+pub(crate) const VERIFIER_LAYOUT: &[&str] = &[
+    "account_key",
+    "account_value",
+    "block_hash",
+    "state_root",
+    "storage_key",
+    "storage_value",
+    "account_exists",
+    "is_zero_value",
+];
+
+/// What the proofs are anchored to: the block hash and its header, or a
+/// state root taken as a public input.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub block_hash: Option<Vec<u8>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub state_root: Option<Vec<u8>>,
+    /// The RLP encoded header, zero-padded to the circuit's length.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub rlp: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rlp_head_len: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rlp_tail_len: Option<u64>,
+    /// The padded header bytes before the state root, with `--header-parts`.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub rlp_head: Option<Vec<u8>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub rlp_tail: Option<Vec<u8>>,
+}
+
+/// The proven account and its proof from the state root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    #[serde(with = "hex_bytes")]
+    pub key: Vec<u8>,
+    /// The RLP encoded account: nonce, balance, storage root, code hash.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub value: Option<Vec<u8>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub storage_root: Option<Vec<u8>>,
+    /// The flattened proof, each node zero-padded to the same length.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub proof: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_depth: Option<u64>,
+    /// False for an exclusion proof of an account absent at the block.
+    #[serde(default = "default_exists")]
+    pub exists: bool,
+}
+
+fn default_exists() -> bool {
+    true
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Account {
+            key: Vec::new(),
+            value: None,
+            storage_root: None,
+            proof: None,
+            proof_depth: None,
+            exists: true,
+        }
+    }
+}
+
+/// The proven slot and its proof from the account's storage root. The key
+/// and value are in the witness's `numeric_encoding`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Storage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "hex_bytes::option"
+    )]
+    pub proof: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_depth: Option<u64>,
+    /// True for an exclusion proof of a slot holding zero.
+    #[serde(default)]
+    pub is_zero_value: bool,
+}
+
+/// A storage proof witness split into its header, account and storage
+/// sections. Fields no section models, such as RLP hints, keccak
+/// preimages, proof tails or rollup leaf indices, are kept in `extra`.
+/// [`StorageProofWitness::to_params`] turns it back into the fields the
+/// output formats render, and [`StorageProofWitness::from_params`] reads
+/// them, so a witness survives a round trip through either form.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageProofWitness {
+    #[serde(default)]
+    pub header: Header,
+    pub account: Account,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<Storage>,
+    #[serde(default, skip_serializing_if = "Fields::is_empty")]
+    pub extra: Fields,
+    /// Field names in the order they are emitted, which is the order of the
+    /// circuit's parameters. When it is empty the generator's order is used,
+    /// followed by `extra`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layout: Vec<String>,
+    #[serde(default, skip_serializing_if = "Fields::is_empty")]
+    pub meta: Fields,
+}
+
+fn bytes(name: &str, value: &Value) -> Result<Vec<u8>> {
+    match value {
+        Value::Bytes(bytes) | Value::Hex(bytes) => Ok(bytes.clone()),
+        _ => Err(Error::Decode(format!("{} is not a byte array", name))),
+    }
+}
+
+fn int(name: &str, value: &Value) -> Result<u64> {
+    match value {
+        Value::Int(value) => Ok(*value),
+        _ => Err(Error::Decode(format!("{} is not an integer", name))),
+    }
+}
+
+impl StorageProofWitness {
+    /// Splits the fields of a storage proof witness into sections. The
+    /// order of the fields is kept in `layout`.
+    pub fn from_params(params: &Params) -> Result<Self> {
+        let mut witness = StorageProofWitness {
+            meta: params.meta.clone(),
+            ..Default::default()
+        };
+        let mut account_key = None;
+        let mut storage = Storage::default();
+        let mut has_storage = false;
+        for (name, value) in params.fields.iter() {
+            let header = &mut witness.header;
+            let account = &mut witness.account;
+            match name.as_str() {
+                "block_hash" => header.block_hash = Some(bytes(name, value)?),
+                "state_root" => header.state_root = Some(bytes(name, value)?),
+                "block_header_rlp" => header.rlp = Some(bytes(name, value)?),
+                "block_header_rlp_head_len" => header.rlp_head_len = Some(int(name, value)?),
+                "block_header_rlp_tail_len" => header.rlp_tail_len = Some(int(name, value)?),
+                "block_header_rlp_head" => header.rlp_head = Some(bytes(name, value)?),
+                "block_header_rlp_tail" => header.rlp_tail = Some(bytes(name, value)?),
+                "account_key" => account_key = Some(bytes(name, value)?),
+                "account_value" => account.value = Some(bytes(name, value)?),
+                "storage_root" => account.storage_root = Some(bytes(name, value)?),
+                "account_proof" => account.proof = Some(bytes(name, value)?),
+                "account_proof_depth" => account.proof_depth = Some(int(name, value)?),
+                "account_exists" => account.exists = int(name, value)? != 0,
+                "storage_key" => storage.key = Some(value.clone()),
+                "storage_value" => storage.value = Some(value.clone()),
+                "storage_proof" => storage.proof = Some(bytes(name, value)?),
+                "storage_proof_depth" => storage.proof_depth = Some(int(name, value)?),
+                "is_zero_value" => storage.is_zero_value = int(name, value)? != 0,
+                _ => {
+                    witness.extra.push(name.clone(), value.clone());
+                    witness.layout.push(name.clone());
+                    continue;
+                }
+            }
+            has_storage |=
+                (name.starts_with("storage_") && name != "storage_root") || name == "is_zero_value";
+            witness.layout.push(name.clone());
+        }
+        witness.account.key = account_key.ok_or_else(|| {
+            Error::Decode("not a storage proof witness: it has no account_key".to_string())
+        })?;
+        witness.storage = has_storage.then_some(storage);
+        Ok(witness)
+    }
+
+    /// The value of the modelled field `name`. The two flags are only
+    /// present when they differ from their default, unless `explicit`.
+    fn field(&self, name: &str, explicit: bool) -> Option<Value> {
+        let header = &self.header;
+        let account = &self.account;
+        let storage = self.storage.as_ref();
+        let bytes = |bytes: &Option<Vec<u8>>| bytes.clone().map(Value::Bytes);
+        let int = |value: Option<u64>| value.map(Value::Int);
+        match name {
+            "block_hash" => bytes(&header.block_hash),
+            "state_root" => bytes(&header.state_root),
+            "block_header_rlp" => bytes(&header.rlp),
+            "block_header_rlp_head_len" => int(header.rlp_head_len),
+            "block_header_rlp_tail_len" => int(header.rlp_tail_len),
+            "block_header_rlp_head" => bytes(&header.rlp_head),
+            "block_header_rlp_tail" => bytes(&header.rlp_tail),
+            "account_key" => Some(Value::Bytes(account.key.clone())),
+            "account_value" => bytes(&account.value),
+            "storage_root" => bytes(&account.storage_root),
+            "account_proof" => bytes(&account.proof),
+            "account_proof_depth" => int(account.proof_depth),
+            "account_exists" => {
+                (explicit || !account.exists).then_some(Value::Int(account.exists as u64))
+            }
+            "storage_key" => storage?.key.clone(),
+            "storage_value" => storage?.value.clone(),
+            "storage_proof" => storage.and_then(|storage| bytes(&storage.proof)),
+            "storage_proof_depth" => int(storage?.proof_depth),
+            "is_zero_value" => storage
+                .filter(|storage| explicit || storage.is_zero_value)
+                .map(|storage| Value::Int(storage.is_zero_value as u64)),
+            _ => None,
+        }
+    }
+
+    /// The fields and metadata of the witness, in `layout` order. Fields
+    /// it leaves out come after it: the modelled ones in the generator's
+    /// order, then those of `extra`.
+    pub fn to_params(&self) -> Params {
+        let mut params = Params {
+            meta: self.meta.clone(),
+            ..Default::default()
+        };
+        for name in &self.layout {
+            let value = match PROVER_LAYOUT.contains(&name.as_str()) {
+                true => self.field(name, true),
+                false => self.extra.get(name).cloned(),
+            };
+            if let Some(value) = value {
+                params.push(name.clone(), value);
+            }
+        }
+        let proofs = self.account.proof.is_some()
+            || self.header.rlp.is_some()
+            || self
+                .storage
+                .as_ref()
+                .is_some_and(|storage| storage.proof.is_some());
+        let order = if proofs {
+            PROVER_LAYOUT
+        } else {
+            VERIFIER_LAYOUT
+        };
+        for &name in order.iter().chain(PROVER_LAYOUT) {
+            if params.fields.get(name).is_some() || self.layout.iter().any(|field| field == name) {
+                continue;
+            }
+            if let Some(value) = self.field(name, false) {
+                params.push(name, value);
+            }
+        }
+        for (name, value) in self.extra.iter() {
+            if !self.layout.contains(name) {
+                params.push(name.clone(), value.clone());
+            }
+        }
+        params
+    }
+}
+
+/// `params` as a pretty-printed [`StorageProofWitness`], the `json` output
+/// format.
+#[cfg(feature = "formats-json")]
+pub fn to_json(params: &Params) -> Result<String> {
+    let witness = StorageProofWitness::from_params(params)?;
+    serde_json::to_string_pretty(&witness)
+        .map(|json| json + "\n")
+        .map_err(|e| Error::Decode(e.to_string()))
+}
+
+/// The unpadded nodes of the proof `name`, with those of its tail when
+/// `--split-deep-proofs` split it.
+#[cfg(feature = "formats-json")]
+fn proof_nodes(params: &Params, name: &str) -> Result<Vec<serde_json::Value>> {
+    let mut nodes = Vec::new();
+    for part in [name.to_string(), format!("{}_tail", name)] {
+        let Some(flat) = params.fields.get_bytes(&part) else {
+            continue;
+        };
+        let depth = params
+            .fields
+            .get_int(&format!("{}_depth", part))
+            .ok_or_else(|| Error::Decode(format!("the witness has {} but no depth", part)))?;
+        nodes.extend(trie::unpad_proof(flat, PROOF_BYTES_LEN, depth as usize));
+    }
+    Ok(proof_order::recorded(params)
+        .root_first(nodes)
+        .iter()
+        .map(|node| format!("0x{}", hex::encode(node)).into())
+        .collect())
+}
+
+/// `params`, a prover witness, as the `eth_getProof` response it was built
+/// from, the `eip1186` output format. The response is normalized: keys are
+/// sorted, hex is lowercase, quantities carry no leading zeros and the
+/// storage key is 32 bytes.
+#[cfg(feature = "formats-json")]
+pub fn to_eip1186(params: &Params, encoding: NumericEncoding) -> Result<String> {
+    let missing = |name: &str| Error::Decode(format!("the witness has no {}", name));
+    let hex = |bytes: &[u8]| serde_json::Value::from(format!("0x{}", hex::encode(bytes)));
+    let quantity = |value: U256| serde_json::Value::from(format!("{:#x}", value));
+    let address = params
+        .fields
+        .get_bytes("account_key")
+        .ok_or_else(|| missing("account_key"))?;
+    let account = Rlp::new(
+        params
+            .fields
+            .get_bytes("account_value")
+            .ok_or_else(|| missing("account_value"))?,
+    );
+    let invalid = |e: rlp::DecoderError| Error::Decode(format!("invalid account_value: {:?}", e));
+    let mut response = json!({
+        "address": hex(address),
+        "nonce": quantity(account.val_at(0).map_err(invalid)?),
+        "balance": quantity(account.val_at(1).map_err(invalid)?),
+        "storageHash": hex(account.at(2).and_then(|item| item.data()).map_err(invalid)?),
+        "codeHash": hex(account.at(3).and_then(|item| item.data()).map_err(invalid)?),
+        "accountProof": proof_nodes(params, "account_proof")?,
+        "storageProof": [],
+    });
+    if let Some(key) = params.fields.get("storage_key") {
+        let decode = |name: &str, value: &Value| {
+            encoding
+                .decode(value)
+                .ok_or_else(|| Error::Decode(format!("invalid {}", name)))
+        };
+        let mut key_bytes = [0u8; 32];
+        decode("storage_key", key)?.to_big_endian(&mut key_bytes);
+        params
+            .fields
+            .get("storage_value")
+            .ok_or_else(|| missing("storage_value"))?;
+        let value = crate::encoding::storage_value(params)
+            .ok_or_else(|| Error::Decode("invalid storage_value".to_string()))?;
+        response["storageProof"] = json!([{
+            "key": hex(&key_bytes),
+            "value": quantity(value),
+            "proof": proof_nodes(params, "storage_proof")?,
+        }]);
+    }
+    serde_json::to_string_pretty(&response)
+        .map(|json| json + "\n")
+        .map_err(|e| Error::Decode(e.to_string()))
+}
+
+/// Parses a witness written as TOML or, when it is a JSON object, as a
+/// serialized [`StorageProofWitness`].
+pub fn parse(input: &str) -> Result<Params> {
+    if !input.trim_start().starts_with('{') {
+        #[cfg(feature = "formats-toml")]
+        return Params::from_toml(input);
+        #[cfg(not(feature = "formats-toml"))]
+        return Err(without("formats-toml"));
+    }
+    #[cfg(feature = "formats-json")]
+    {
+        let witness: StorageProofWitness = serde_json::from_str(input)
+            .map_err(|e| Error::Decode(format!("invalid JSON witness: {}", e)))?;
+        Ok(witness.to_params())
+    }
+    #[cfg(not(feature = "formats-json"))]
+    Err(without("formats-json"))
+}
+
+/// A witness this build was compiled without the `feature` to read.
+#[cfg(not(all(feature = "formats-toml", feature = "formats-json")))]
+fn without(feature: &str) -> Error {
+    Error::Unsupported(format!(
+        "this build cannot read the witness; rebuild with --features {}",
+        feature
+    ))
+}
+
+/// [`parse`] of a witness file's bytes, which may also hold a binary
+/// container.
+pub fn parse_bytes(data: &[u8]) -> Result<Params> {
+    if container::is_container(data) {
+        return container::read(data);
+    }
+    let text = std::str::from_utf8(data)
+        .map_err(|_| Error::Decode("the witness is neither text nor a container".to_string()))?;
+    parse(text)
+}
@@ -0,0 +1,143 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use web3::types::{H256, U256};
+use web3::Transport;
+
+use crate::error::{Error, Result};
+use crate::options::GenOptions;
+use crate::params::Params;
+use crate::{migrate, pad_nodes, rpc};
+
+/// Depth of the zkSync Era state tree, one level per bit of the tree key.
+pub const TREE_DEPTH: usize = 256;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockDetails {
+    l1_batch_number: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchDetails {
+    root_hash: Option<H256>,
+}
+
+#[derive(Deserialize)]
+struct StorageProof {
+    key: H256,
+    value: H256,
+    /// Enumeration index of the leaf, 0 for slots never written.
+    index: u64,
+    /// Sibling hashes from the root down, with the hashes of empty subtrees
+    /// at the bottom left out.
+    proof: Vec<H256>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Proof {
+    storage_proof: Vec<StorageProof>,
+}
+
+async fn request<T: DeserializeOwned>(
+    web3: &web3::Web3<rpc::Client>,
+    method: &str,
+    params: Vec<serde_json::Value>,
+) -> Result<T> {
+    let response = web3.transport().execute(method, params).await?;
+    serde_json::from_value(response)
+        .map_err(|e| Error::Decode(format!("invalid {} response: {}", method, e)))
+}
+
+/// Generates the parameters of `args` from the zkSync Era provider at
+/// `rpc_url`: the storage proof of `args.slot` against the state root of
+/// the L1 batch holding `args.block`. zkSync keeps no account trie, so only
+/// storage is proven; balances live in the storage of the base token
+/// contract.
+pub async fn generate(rpc_url: &str, args: &GenOptions, prove: bool) -> Result<Params> {
+    let web3 = web3::Web3::new(rpc::Client::new(rpc_url)?);
+    let slot = args
+        .slot
+        .ok_or_else(|| Error::Unsupported("--rollup zksync needs --slot".to_string()))?;
+
+    let details: Option<BlockDetails> = request(
+        &web3,
+        "zks_getBlockDetails",
+        vec![serde_json::json!(args.block)],
+    )
+    .await?;
+    let batch = details
+        .and_then(|details| details.l1_batch_number)
+        .ok_or_else(|| {
+            Error::UnminedBlock(format!(
+                "zkSync block {} is not in an L1 batch yet",
+                args.block
+            ))
+        })?;
+    let batch_details: Option<BatchDetails> = request(
+        &web3,
+        "zks_getL1BatchDetails",
+        vec![serde_json::json!(batch)],
+    )
+    .await?;
+    let state_root = batch_details
+        .and_then(|details| details.root_hash)
+        .ok_or_else(|| Error::UnminedBlock(format!("L1 batch {} has no state root yet", batch)))?;
+
+    let proof: Proof = request(
+        &web3,
+        "zks_getProof",
+        vec![
+            serde_json::json!(args.account),
+            serde_json::json!([slot]),
+            serde_json::json!(batch),
+        ],
+    )
+    .await?;
+    let storage = proof
+        .storage_proof
+        .into_iter()
+        .find(|storage| storage.key == slot)
+        .ok_or_else(|| Error::EmptyProof(format!("no storage proof of {:?}", slot)))?;
+    crate::check_expected(args, U256::from_big_endian(&storage.value.0))?;
+    let nodes: Vec<Vec<u8>> = storage
+        .proof
+        .iter()
+        .map(|hash| hash.as_bytes().to_vec())
+        .collect();
+    log::info!(
+        "Note: zkSync proofs hash with Blake2s, which this tool does not verify; the circuit \
+         must check the proof against state_root"
+    );
+
+    let encoding = args.numeric_encoding;
+    let mut params = Params::default();
+    params.push("state_root", state_root.as_bytes());
+    params.push("account_key", args.account.as_bytes());
+    params.push(
+        "storage_key",
+        encoding.encode(U256::from_big_endian(&slot.0)),
+    );
+    params.push(
+        "storage_value",
+        encoding.encode(U256::from_big_endian(&storage.value.0)),
+    );
+    if prove {
+        params.push("storage_leaf_index", storage.index as usize);
+        params.push(
+            "storage_proof",
+            pad_nodes("storage proof", &nodes, TREE_DEPTH, 32)?,
+        );
+        params.push("storage_proof_depth", nodes.len());
+    }
+    params.meta.push(
+        "witness_format_version",
+        migrate::WITNESS_FORMAT_VERSION as usize,
+    );
+    params.meta.push("numeric_encoding", encoding.name());
+    params.meta.push("rollup", "zksync");
+    params.meta.push("l1_batch", batch as usize);
+    params.record_witness_hash();
+    Ok(params)
+}
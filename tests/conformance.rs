@@ -0,0 +1,16 @@
+//! Runs the trie and RLP vectors of ethereum/tests through the
+//! `conformance` checks. A subset is vendored under
+//! `fixtures/ethereum-tests`, in the repository's layout; set
+//! `NOIR_MIP_ETHEREUM_TESTS` to a checkout to run all of them.
+
+#![cfg(feature = "conformance")]
+
+use std::path::{Path, PathBuf};
+
+#[test]
+fn the_ethereum_tests_vectors_pass() {
+    let dir = std::env::var_os("NOIR_MIP_ETHEREUM_TESTS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/ethereum-tests"));
+    noir_mip::conformance::run(&dir).unwrap();
+}
@@ -0,0 +1,147 @@
+//! Snapshots of every output format, rendered from the recorded demo block
+//! and compared byte for byte with the files under `fixtures/golden`.
+//! Circuits read these layouts, so a change to any of them has to be
+//! deliberate: when it is, rerun with `NOIR_MIP_BLESS=1` to rewrite the
+//! files and commit them with the change.
+
+#![cfg(all(feature = "rpc", feature = "formats-json", feature = "formats-toml"))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use noir_mip::demo;
+use noir_mip::encoding::ByteEncoding;
+use noir_mip::params::Params;
+use noir_mip::witness::{self, OutputFormat, RenderOptions};
+
+const FORMATS: &[OutputFormat] = &[
+    OutputFormat::Toml,
+    OutputFormat::NoirTest,
+    OutputFormat::Json,
+    OutputFormat::Eip1186,
+    OutputFormat::Binary,
+    OutputFormat::CircomJson,
+    OutputFormat::KvJson,
+];
+
+/// The golden file of the prover parameters in `format`. The match is
+/// exhaustive, so a new format cannot be added without its snapshot.
+fn file_name(format: OutputFormat, namespaced: bool) -> String {
+    let stem = match format {
+        OutputFormat::Toml => "Prover.toml",
+        OutputFormat::NoirTest => "prover_test.nr",
+        OutputFormat::Json => "Prover.json",
+        OutputFormat::Eip1186 => "Prover.eip1186.json",
+        OutputFormat::Binary => "Prover.bin",
+        OutputFormat::CircomJson => "Prover.circom.json",
+        OutputFormat::KvJson => "Prover.kv.json",
+    };
+    match namespaced {
+        true => stem.replacen("Prover.", "Prover.namespaced.", 1),
+        false => stem.to_string(),
+    }
+}
+
+fn generate(prove: bool) -> Params {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(noir_mip::generate_with_source(
+            &demo::Recorded,
+            &demo::args(),
+            prove,
+        ))
+        .unwrap()
+}
+
+/// Every golden output, named after its file.
+fn outputs() -> Vec<(String, Vec<u8>)> {
+    let prover = generate(true);
+    let verifier = generate(false);
+    let args = demo::args();
+    let mut outputs = Vec::new();
+    for &format in FORMATS {
+        for namespaced in [false, true] {
+            // Only the TOML and JSON layouts have a namespaced form.
+            if namespaced && !matches!(format, OutputFormat::Toml | OutputFormat::Json) {
+                continue;
+            }
+            let options = RenderOptions {
+                block: args.block,
+                namespaced,
+                numeric_encoding: args.numeric_encoding,
+            };
+            let rendered = witness::render(&prover, format, &options).unwrap();
+            outputs.push((file_name(format, namespaced), rendered));
+        }
+    }
+    outputs.push(("Verifier.toml".to_string(), verifier.to_toml().into_bytes()));
+    for (name, encoding) in [
+        ("Prover.hex.toml", ByteEncoding::Hex),
+        ("Prover.base64.toml", ByteEncoding::Base64),
+    ] {
+        let mut encoded = prover.clone();
+        encoded.meta.push("byte_encoding", encoding.name());
+        outputs.push((name.to_string(), encoded.to_toml().into_bytes()));
+    }
+    outputs
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/golden")
+}
+
+/// Where `output` first differs from `golden`: a line for text, a byte
+/// offset for the binary container.
+fn first_change(output: &[u8], golden: &[u8]) -> String {
+    match (std::str::from_utf8(output), std::str::from_utf8(golden)) {
+        (Ok(output), Ok(golden)) => {
+            let line = output
+                .lines()
+                .zip(golden.lines())
+                .position(|(a, b)| a != b)
+                .unwrap_or(output.lines().count().min(golden.lines().count()));
+            format!("from line {}", line + 1)
+        }
+        _ => {
+            let offset = output
+                .iter()
+                .zip(golden)
+                .position(|(a, b)| a != b)
+                .unwrap_or(output.len().min(golden.len()));
+            format!("from byte {}", offset)
+        }
+    }
+}
+
+#[test]
+fn every_output_format_matches_its_golden_file() {
+    let dir = golden_dir();
+    if std::env::var_os("NOIR_MIP_BLESS").is_some() {
+        fs::create_dir_all(&dir).unwrap();
+        for (name, output) in outputs() {
+            fs::write(dir.join(name), output).unwrap();
+        }
+        return;
+    }
+    let mut changed = Vec::new();
+    for (name, output) in outputs() {
+        match fs::read(dir.join(&name)) {
+            Ok(golden) if golden == output => {}
+            Ok(golden) => changed.push(format!("{} ({})", name, first_change(&output, &golden))),
+            Err(_) => changed.push(format!("{} (missing)", name)),
+        }
+    }
+    assert!(
+        changed.is_empty(),
+        "the output layout changed: {} no longer match fixtures/golden; if the change is \
+         intended, rerun with NOIR_MIP_BLESS=1 and commit the files",
+        changed.join(", ")
+    );
+}
+
+#[test]
+fn the_golden_witness_passes_the_native_circuit_checks() {
+    demo::verify(&generate(true)).unwrap();
+}
@@ -0,0 +1,74 @@
+//! Runs the reference circuit under `fixtures/reference` on the witness
+//! generated from the recorded demo block. The witness is committed as
+//! `fixtures/reference/Prover.toml`, checked here against what the
+//! generator produces, and handed to `nargo execute`, which rejects any
+//! break of the byte contract. Without `nargo` on the `PATH` the run is
+//! skipped; rerun with `NOIR_MIP_BLESS=1` to rewrite the witness.
+
+#![cfg(feature = "rpc")]
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use noir_mip::demo;
+
+const REFERENCE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/reference");
+
+fn witness() -> String {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(noir_mip::generate_with_source(
+            &demo::Recorded,
+            &demo::args(),
+            true,
+        ))
+        .unwrap()
+        .to_toml()
+}
+
+#[test]
+fn the_recorded_witness_is_current() {
+    let path = Path::new(REFERENCE_DIR).join("Prover.toml");
+    if std::env::var_os("NOIR_MIP_BLESS").is_some() {
+        fs::write(&path, witness()).unwrap();
+        return;
+    }
+    assert!(
+        fs::read_to_string(&path).unwrap() == witness(),
+        "{} no longer matches the generated witness; if the change is intended, rerun with \
+         NOIR_MIP_BLESS=1 and commit it",
+        path.display()
+    );
+}
+
+#[test]
+fn nargo_executes_the_reference_circuit() {
+    // A copy, so nargo's target directory stays out of the source tree.
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("reference-circuit");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    for file in ["Nargo.toml", "src/main.nr", "Prover.toml"] {
+        fs::copy(Path::new(REFERENCE_DIR).join(file), dir.join(file)).unwrap();
+    }
+    let status = match Command::new("nargo")
+        .arg("execute")
+        .current_dir(&dir)
+        .status()
+    {
+        Ok(status) => status,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("nargo is not installed; skipping the reference circuit");
+            return;
+        }
+        Err(e) => panic!("cannot run nargo: {}", e),
+    };
+    assert!(
+        status.success(),
+        "nargo execute rejected the recorded witness in {} ({})",
+        dir.display(),
+        status
+    );
+}
@@ -0,0 +1,64 @@
+//! Generation from a source that misreports what its proofs show, which the
+//! generator has to refuse rather than emit.
+
+#![cfg(feature = "rpc")]
+
+use futures::future::BoxFuture;
+use noir_mip::demo;
+use noir_mip::error::{Error, Result};
+use noir_mip::source::EthDataSource;
+use web3::types::{Bytes, Proof, TransactionReceipt, H160, U256};
+
+/// The recorded demo source, with the storage value it reports replaced.
+struct Misreported(U256);
+
+impl EthDataSource for Misreported {
+    fn chain_id(&self) -> BoxFuture<'_, Result<u64>> {
+        demo::Recorded.chain_id()
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<serde_json::Value>> {
+        demo::Recorded.get_block(number)
+    }
+
+    fn get_proof(
+        &self,
+        account: H160,
+        keys: Vec<U256>,
+        block: u64,
+    ) -> BoxFuture<'_, Result<Option<Proof>>> {
+        Box::pin(async move {
+            let mut proof = demo::Recorded.get_proof(account, keys, block).await?;
+            if let Some(proof) = &mut proof {
+                proof.storage_proof[0].value = self.0;
+            }
+            Ok(proof)
+        })
+    }
+
+    fn get_code(&self, account: H160, block: u64) -> BoxFuture<'_, Result<Bytes>> {
+        demo::Recorded.get_code(account, block)
+    }
+
+    fn get_receipts(&self, number: u64) -> BoxFuture<'_, Result<Vec<TransactionReceipt>>> {
+        demo::Recorded.get_receipts(number)
+    }
+}
+
+fn generate(source: &Misreported) -> Result<noir_mip::params::Params> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(noir_mip::generate_with_source(source, &demo::args(), true))
+}
+
+#[test]
+fn a_value_the_storage_proof_contradicts_is_refused() {
+    for value in [U256::from(0x2a), U256::zero()] {
+        match generate(&Misreported(value)) {
+            Err(Error::Verification(message)) => assert!(message.contains("its proof shows")),
+            other => panic!("{:#x} was not refused: {:?}", value, other.map(|_| ())),
+        }
+    }
+}